@@ -1,4 +1,4 @@
-use super::{BuiltinFunctionsMap, get_builtins};
+use super::{BuiltinFunctionsMap, CustomBuiltin, get_builtins};
 use crate::errors::{BurregoError, Result};
 
 use lazy_static::lazy_static;
@@ -17,23 +17,62 @@ pub(crate) struct BuiltinsHelper {
 }
 
 impl BuiltinsHelper {
+    /// Invokes the given builtin. The custom builtin, when provided, is
+    /// used instead of the builtins implemented by burrego
     pub(crate) fn invoke(
         &self,
         builtin_name: &str,
+        custom_builtin: Option<&CustomBuiltin>,
         args: &[serde_json::Value],
     ) -> Result<serde_json::Value> {
-        let builtin_fn = self
-            .builtins
-            .get(builtin_name)
-            .ok_or_else(|| BurregoError::BuiltinNotImplementedError(builtin_name.to_string()))?;
+        if custom_builtin.is_none() && !self.builtins.contains_key(builtin_name) {
+            return Err(BurregoError::BuiltinNotImplementedError(
+                builtin_name.to_string(),
+            ));
+        }
 
         debug!(
             builtin = builtin_name,
+            custom = custom_builtin.is_some(),
             args = serde_json::to_string(&args)
                 .expect("cannot convert builtins args to JSON")
                 .as_str(),
             "invoking builtin"
         );
-        builtin_fn(args)
+        match custom_builtin {
+            Some(custom_builtin) => custom_builtin(args),
+            None => self.builtins[builtin_name](args),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn invoke_custom_builtin() -> Result<()> {
+        let helper = BuiltinsHelper {
+            builtins: get_builtins(),
+        };
+        let custom_builtin: CustomBuiltin =
+            Arc::new(|args: &[serde_json::Value]| Ok(json!({"args": args})));
+
+        assert_eq!(
+            helper.invoke("example.echo", Some(&custom_builtin), &[json!("a")])?,
+            json!({"args": ["a"]})
+        );
+        assert!(matches!(
+            helper.invoke("example.echo", None, &[json!("a")]),
+            Err(BurregoError::BuiltinNotImplementedError(_))
+        ));
+        assert_eq!(
+            helper.invoke("sprintf", None, &[json!("%v"), json!([1])])?,
+            json!("1")
+        );
+
+        Ok(())
     }
 }
@@ -1,4 +1,7 @@
-use crate::errors::{BurregoError, Result};
+use crate::{
+    builtins::{PolicyMessage, record_message},
+    errors::{BurregoError, Result},
+};
 
 #[tracing::instrument(skip(args))]
 pub fn trace(args: &[serde_json::Value]) -> Result<serde_json::Value> {
@@ -15,6 +18,7 @@ pub fn trace(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     })?;
 
     tracing::debug!("{}", message_str);
+    record_message(PolicyMessage::Trace(message_str.to_string()));
 
     Ok(serde_json::Value::Null)
 }
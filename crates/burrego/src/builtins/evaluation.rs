@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::cell::{Cell, RefCell};
+
+/// The state shared by the builtins during a single evaluation. Like OPA,
+/// `time.now_ns` returns the same value during a whole evaluation, and the
+/// random builtins return the same value when invoked with the same
+/// arguments.
+#[derive(Clone, Copy)]
+struct Evaluation {
+    time: DateTime<Utc>,
+    seed: u64,
+}
+
+/// A message emitted by the policy during an evaluation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyMessage {
+    /// Message sent through the `opa_println` import
+    Println(String),
+    /// Message produced by the `trace` builtin
+    Trace(String),
+}
+
+thread_local! {
+    static EVALUATION: Cell<Option<Evaluation>> = const { Cell::new(None) };
+    static MESSAGES: RefCell<Vec<PolicyMessage>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Clears the evaluation state, even when the evaluation panics
+struct EvaluationGuard;
+
+impl Drop for EvaluationGuard {
+    fn drop(&mut self) {
+        EVALUATION.set(None);
+        MESSAGES.with_borrow_mut(Vec::clear);
+    }
+}
+
+/// Runs the given evaluation. The random builtins are seeded with the given
+/// value, or with a random one when it's not provided.
+pub(crate) fn with_evaluation<T>(seed: Option<u64>, evaluation: impl FnOnce() -> T) -> T {
+    EVALUATION.set(Some(Evaluation {
+        time: Utc::now(),
+        seed: seed.unwrap_or_else(random_seed),
+    }));
+    MESSAGES.with_borrow_mut(Vec::clear);
+    let _guard = EvaluationGuard;
+    evaluation()
+}
+
+/// Returns the time of the evaluation in progress, or the current time
+/// when invoked outside of an evaluation
+pub(crate) fn now() -> DateTime<Utc> {
+    EVALUATION
+        .get()
+        .map(|evaluation| evaluation.time)
+        .unwrap_or_else(Utc::now)
+}
+
+/// Returns random bytes for the given builtin invocation. During an
+/// evaluation the same bytes are returned for the same builtin and key.
+pub(crate) fn random_bytes(builtin: &str, key: &str) -> [u8; 32] {
+    let seed = EVALUATION
+        .get()
+        .map(|evaluation| evaluation.seed)
+        .unwrap_or_else(random_seed);
+
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+    hasher.update(builtin.as_bytes());
+    hasher.update([0]);
+    hasher.update(key.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Records a message emitted by the policy. Messages emitted outside of an
+/// evaluation are discarded
+pub(crate) fn record_message(message: PolicyMessage) {
+    if EVALUATION.get().is_some() {
+        MESSAGES.with_borrow_mut(|messages| messages.push(message));
+    }
+}
+
+/// Returns the messages emitted so far by the evaluation in progress
+pub(crate) fn take_messages() -> Vec<PolicyMessage> {
+    MESSAGES.take()
+}
+
+fn random_seed() -> u64 {
+    let bytes: [u8; 8] = ring::rand::generate(&ring::rand::SystemRandom::new())
+        .map(|random| random.expose())
+        // the system random generator is not expected to fail, the
+        // current time is still different between evaluations
+        .unwrap_or_else(|_| {
+            Utc::now()
+                .timestamp_nanos_opt()
+                .unwrap_or_default()
+                .to_be_bytes()
+        });
+    u64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn time_is_stable_during_an_evaluation() {
+        let (first, second) = with_evaluation(None, || {
+            let first = now();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            (first, now())
+        });
+        assert_eq!(first, second);
+        assert!(now() > first);
+    }
+
+    #[test]
+    fn random_bytes_are_stable_during_an_evaluation() {
+        let (first, second, other) = with_evaluation(None, || {
+            (
+                random_bytes("rand.intn", "a"),
+                random_bytes("rand.intn", "a"),
+                random_bytes("rand.intn", "b"),
+            )
+        });
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+
+        let seeded = with_evaluation(Some(42), || random_bytes("rand.intn", "a"));
+        assert_eq!(
+            seeded,
+            with_evaluation(Some(42), || random_bytes("rand.intn", "a"))
+        );
+        assert_ne!(
+            seeded,
+            with_evaluation(Some(43), || random_bytes("rand.intn", "a"))
+        );
+    }
+
+    #[test]
+    fn messages_are_collected_per_evaluation() {
+        record_message(PolicyMessage::Trace("outside".to_string()));
+
+        let messages = with_evaluation(None, || {
+            record_message(PolicyMessage::Println("hello".to_string()));
+            record_message(PolicyMessage::Trace("world".to_string()));
+            take_messages()
+        });
+        assert_eq!(
+            messages,
+            vec![
+                PolicyMessage::Println("hello".to_string()),
+                PolicyMessage::Trace("world".to_string()),
+            ]
+        );
+
+        let messages = with_evaluation(None, take_messages);
+        assert!(messages.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::errors::{BurregoError, Result};
+use regex::Regex;
 
 pub fn quote_meta(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     if args.len() != 1 {
@@ -19,6 +20,182 @@ pub fn quote_meta(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     })
 }
 
+/// Matches the value against the glob pattern. The wildcards `*` and `?`
+/// don't match the delimiters, which default to `["."]`. A `null` list of
+/// delimiters disables them.
+pub fn matches(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 3 {
+        return Err(BurregoError::BuiltinError {
+            name: "glob.match".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let pattern = args[0].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "glob.match".to_string(),
+        message: "1st parameter is not a string".to_string(),
+    })?;
+
+    let mut delimiters: Vec<char> = Vec::new();
+    match &args[1] {
+        serde_json::Value::Null => {}
+        serde_json::Value::Array(values) => {
+            for value in values {
+                let delimiter = value.as_str().ok_or_else(|| BurregoError::BuiltinError {
+                    name: "glob.match".to_string(),
+                    message: format!("delimiter {value} is not a string"),
+                })?;
+                delimiters.extend(delimiter.chars());
+            }
+            if delimiters.is_empty() {
+                delimiters.push('.');
+            }
+        }
+        _ => {
+            return Err(BurregoError::BuiltinError {
+                name: "glob.match".to_string(),
+                message: "2nd parameter is neither an array nor null".to_string(),
+            });
+        }
+    }
+
+    let value = args[2].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "glob.match".to_string(),
+        message: "3rd parameter is not a string".to_string(),
+    })?;
+
+    let regex = GlobCompiler::new(pattern, &delimiters)
+        .compile()
+        .map_err(|message| BurregoError::BuiltinError {
+            name: "glob.match".to_string(),
+            message: format!("invalid glob pattern {pattern}: {message}"),
+        })?;
+
+    Ok(serde_json::Value::Bool(regex.is_match(value)))
+}
+
+/// Translates a glob pattern into a regular expression. The syntax is the
+/// one of the Go library used by OPA: `*`, `**`, `?`, `[a-z]`, `[!a-z]`,
+/// `{a,b}` and `\` to escape the special characters.
+struct GlobCompiler {
+    pattern: Vec<char>,
+    position: usize,
+    /// matches any character but the delimiters
+    any_char: String,
+}
+
+impl GlobCompiler {
+    fn new(pattern: &str, delimiters: &[char]) -> Self {
+        let any_char = if delimiters.is_empty() {
+            ".".to_string()
+        } else {
+            let delimiters: String = delimiters
+                .iter()
+                .map(|delimiter| regex::escape(&delimiter.to_string()))
+                .collect();
+            format!("[^{delimiters}]")
+        };
+
+        GlobCompiler {
+            pattern: pattern.chars().collect(),
+            position: 0,
+            any_char,
+        }
+    }
+
+    fn compile(mut self) -> std::result::Result<Regex, String> {
+        let expression = self.sequence(false)?;
+        if self.position < self.pattern.len() {
+            return Err(format!("unexpected {:?}", self.pattern[self.position]));
+        }
+
+        Regex::new(&format!("(?s)^{expression}$")).map_err(|e| e.to_string())
+    }
+
+    /// Compiles the pattern until its end or, inside of `{}`, until the end
+    /// of the current alternative
+    fn sequence(&mut self, inside_alternatives: bool) -> std::result::Result<String, String> {
+        let mut expression = String::new();
+        while let Some(&c) = self.pattern.get(self.position) {
+            if inside_alternatives && (c == ',' || c == '}') {
+                break;
+            }
+            self.position += 1;
+            match c {
+                '*' if self.pattern.get(self.position) == Some(&'*') => {
+                    self.position += 1;
+                    expression.push_str(".*");
+                }
+                '*' => expression.push_str(&format!("{}*", self.any_char)),
+                '?' => expression.push_str(&self.any_char),
+                '[' => expression.push_str(&self.range()?),
+                '{' => expression.push_str(&self.alternatives()?),
+                '\\' => {
+                    let escaped = self
+                        .pattern
+                        .get(self.position)
+                        .ok_or("unterminated escape sequence")?;
+                    self.position += 1;
+                    expression.push_str(&regex::escape(&escaped.to_string()));
+                }
+                c => expression.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        Ok(expression)
+    }
+
+    fn range(&mut self) -> std::result::Result<String, String> {
+        let mut expression = String::from("[");
+        if self.pattern.get(self.position) == Some(&'!') {
+            self.position += 1;
+            expression.push('^');
+        }
+
+        let mut empty = true;
+        loop {
+            let c = *self
+                .pattern
+                .get(self.position)
+                .ok_or("unterminated range")?;
+            self.position += 1;
+            match c {
+                ']' if !empty => break,
+                '-' if !empty && self.pattern.get(self.position) != Some(&']') => {
+                    let to = *self
+                        .pattern
+                        .get(self.position)
+                        .ok_or("unterminated range")?;
+                    self.position += 1;
+                    expression.push('-');
+                    expression.push_str(&regex::escape(&to.to_string()));
+                }
+                c => expression.push_str(&regex::escape(&c.to_string())),
+            }
+            empty = false;
+        }
+        expression.push(']');
+
+        Ok(expression)
+    }
+
+    fn alternatives(&mut self) -> std::result::Result<String, String> {
+        let mut alternatives = Vec::new();
+        loop {
+            alternatives.push(self.sequence(true)?);
+            match self.pattern.get(self.position) {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    break;
+                }
+                _ => return Err("unterminated alternatives".to_string()),
+            }
+        }
+
+        Ok(format!("(?:{})", alternatives.join("|")))
+    }
+}
+
 fn escape(s: &str) -> String {
     let mut escaped = String::new();
     for c in s.chars() {
@@ -54,4 +231,50 @@ mod test {
             "some \\*\\?\\\\\\[\\]\\{\\} text"
         );
     }
+
+    #[test]
+    fn glob_match() -> crate::errors::Result<()> {
+        use serde_json::json;
+
+        for (pattern, delimiters, value, expected) in [
+            ("*.github.com", json!([]), "api.github.com", true),
+            ("*.github.com", json!([]), "api.cdn.github.com", false),
+            ("*.github.com", json!(null), "api.cdn.github.com", true),
+            ("**.github.com", json!([]), "api.cdn.github.com", true),
+            ("*:github:com", json!([":"]), "api:github:com", true),
+            ("*:github:com", json!([":"]), "api:cdn:github:com", false),
+            ("api.?ithub.com", json!([]), "api.github.com", true),
+            ("api.?ithub.com", json!([]), "api..ithub.com", false),
+            ("[abc]at", json!([]), "cat", true),
+            ("[!abc]at", json!([]), "cat", false),
+            ("[a-c]at", json!([]), "bat", true),
+            ("[!a-c]at", json!([]), "rat", true),
+            ("{cat,bat,[fr]at}", json!([]), "rat", true),
+            ("{cat,bat,[fr]at}", json!([]), "mat", false),
+            (
+                "{api,www}.{github,gitlab}.com",
+                json!([]),
+                "www.gitlab.com",
+                true,
+            ),
+            ("\\*.github.com", json!([]), "*.github.com", true),
+            ("\\*.github.com", json!([]), "api.github.com", false),
+            ("*", json!([]), "", true),
+        ] {
+            assert_eq!(
+                super::matches(&[json!(pattern), delimiters.clone(), json!(value)])?,
+                json!(expected),
+                "{pattern} {delimiters} {value}"
+            );
+        }
+
+        for pattern in ["[abc", "{a,b", "a\\"] {
+            assert!(
+                super::matches(&[json!(pattern), json!([]), json!("a")]).is_err(),
+                "{pattern}"
+            );
+        }
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use crate::errors::{BurregoError, Result};
+use std::collections::BTreeMap;
 
 pub fn patch(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     if args.len() != 2 {
@@ -39,6 +40,252 @@ pub fn patch(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     })
 }
 
+/// Keeps only the given paths of the object. Paths are either strings, like
+/// `a/b/0`, or arrays of keys, like `["a", "b", 0]`.
+pub fn filter(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    let (object, paths) = object_and_paths("json.filter", args)?;
+
+    Ok(paths
+        .filter(object)
+        .unwrap_or_else(|| serde_json::json!({})))
+}
+
+/// Removes the given paths from the object. Paths are either strings, like
+/// `a/b/0`, or arrays of keys, like `["a", "b", 0]`.
+pub fn remove(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    let (object, paths) = object_and_paths("json.remove", args)?;
+
+    Ok(paths.remove(object))
+}
+
+/// Checks whether the given value is a valid JSON schema. Returns
+/// `[true, null]` or `[false, error]`.
+pub fn verify_schema(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "json.verify_schema".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    Ok(match json_schema("json.verify_schema", &args[0]) {
+        Ok(_) => serde_json::json!([true, null]),
+        Err(BurregoError::BuiltinError { message, .. }) => serde_json::json!([false, message]),
+        Err(e) => return Err(e),
+    })
+}
+
+/// Validates the document against the JSON schema. Returns `[true, []]` or
+/// `[false, errors]`.
+pub fn match_schema(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "json.match_schema".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let document = match &args[0] {
+        serde_json::Value::String(document) => {
+            serde_json::from_str(document).map_err(|e| BurregoError::BuiltinError {
+                name: "json.match_schema".to_string(),
+                message: format!("1st parameter is not a valid JSON document: {e}"),
+            })?
+        }
+        document => document.clone(),
+    };
+    let validator = json_schema("json.match_schema", &args[1])?;
+
+    let errors: Vec<serde_json::Value> = validator
+        .iter_errors(&document)
+        .map(|error| {
+            let field = error
+                .instance_path()
+                .to_string()
+                .trim_start_matches('/')
+                .replace('/', ".");
+            let field = if field.is_empty() {
+                "(root)".to_string()
+            } else {
+                field
+            };
+            let schema_path = error.schema_path().to_string();
+            let keyword = schema_path.rsplit('/').next().unwrap_or_default();
+            let description = error.to_string();
+
+            serde_json::json!({
+                "error": format!("{field}: {description}"),
+                "type": keyword,
+                "field": field,
+                "desc": description,
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!([errors.is_empty(), errors]))
+}
+
+/// Builds a validator for the JSON schema, which is either a JSON document
+/// or a string containing it
+fn json_schema(name: &str, schema: &serde_json::Value) -> Result<jsonschema::Validator> {
+    let schema = match schema {
+        serde_json::Value::String(schema) => {
+            serde_json::from_str(schema).map_err(|e| BurregoError::BuiltinError {
+                name: name.to_string(),
+                message: format!("the schema is not a valid JSON document: {e}"),
+            })?
+        }
+        schema => schema.clone(),
+    };
+
+    jsonschema::meta::validate(&schema).map_err(|e| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: format!("invalid JSON schema: {e}"),
+    })?;
+    jsonschema::validator_for(&schema).map_err(|e| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: format!("invalid JSON schema: {e}"),
+    })
+}
+
+/// The paths given to `json.filter` and `json.remove`, stored as a tree
+#[derive(Default)]
+struct PathTree {
+    /// the path ends here: the whole value is selected
+    selected: bool,
+    children: BTreeMap<String, PathTree>,
+}
+
+impl PathTree {
+    fn insert(&mut self, path: Vec<String>) {
+        let mut node = self;
+        for key in path {
+            if node.selected {
+                // a parent of the path is already selected
+                return;
+            }
+            node = node.children.entry(key).or_default();
+        }
+        node.selected = true;
+        node.children.clear();
+    }
+
+    fn filter(&self, value: &serde_json::Value) -> Option<serde_json::Value> {
+        if self.selected {
+            return Some(value.clone());
+        }
+
+        match value {
+            serde_json::Value::Object(object) => Some(serde_json::Value::Object(
+                object
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let filtered = self.children.get(key)?.filter(value)?;
+                        Some((key.clone(), filtered))
+                    })
+                    .collect(),
+            )),
+            serde_json::Value::Array(array) => Some(serde_json::Value::Array(
+                array
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, value)| {
+                        self.children.get(&index.to_string())?.filter(value)
+                    })
+                    .collect(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn remove(&self, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(object) => serde_json::Value::Object(
+                object
+                    .iter()
+                    .filter_map(|(key, value)| match self.children.get(key) {
+                        Some(child) if child.selected => None,
+                        Some(child) => Some((key.clone(), child.remove(value))),
+                        None => Some((key.clone(), value.clone())),
+                    })
+                    .collect(),
+            ),
+            serde_json::Value::Array(array) => serde_json::Value::Array(
+                array
+                    .iter()
+                    .enumerate()
+                    .filter_map(
+                        |(index, value)| match self.children.get(&index.to_string()) {
+                            Some(child) if child.selected => None,
+                            Some(child) => Some(child.remove(value)),
+                            None => Some(value.clone()),
+                        },
+                    )
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+}
+
+fn object_and_paths<'a>(
+    name: &str,
+    args: &'a [serde_json::Value],
+) -> Result<(&'a serde_json::Value, PathTree)> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    if !args[0].is_object() {
+        return Err(BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "1st parameter is not an object".to_string(),
+        });
+    }
+
+    // sets are passed as arrays
+    let paths = args[1]
+        .as_array()
+        .ok_or_else(|| BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "2nd parameter is neither an array nor a set".to_string(),
+        })?;
+
+    let mut tree = PathTree::default();
+    for path in paths {
+        let path: Vec<String> = match path {
+            serde_json::Value::String(path) => path
+                .trim_matches('/')
+                .split('/')
+                .map(|key| key.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+            serde_json::Value::Array(keys) => keys
+                .iter()
+                .map(|key| match key {
+                    serde_json::Value::String(key) => Ok(key.clone()),
+                    serde_json::Value::Number(key) => Ok(key.to_string()),
+                    _ => Err(BurregoError::BuiltinError {
+                        name: name.to_string(),
+                        message: format!("invalid path key {key}"),
+                    }),
+                })
+                .collect::<Result<_>>()?,
+            _ => {
+                return Err(BurregoError::BuiltinError {
+                    name: name.to_string(),
+                    message: format!("invalid path {path}"),
+                });
+            }
+        };
+        tree.insert(path);
+    }
+
+    Ok((&args[0], tree))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -56,4 +303,110 @@ mod test {
         assert!(actual.is_ok());
         assert_json_eq!(json!({"a": {"foo": 1, "bar": 2}}), actual.unwrap());
     }
+
+    #[test]
+    fn filter_paths() -> Result<()> {
+        let object = json!({"a": {"b": 1, "c": [10, 20, 30]}, "d": "e", "f/g": true});
+        for (paths, expected) in [
+            (json!(["a/b"]), json!({"a": {"b": 1}})),
+            (json!(["/d", "a/c/2"]), json!({"a": {"c": [30]}, "d": "e"})),
+            (
+                json!([["a", "c", 0], "a/c/1"]),
+                json!({"a": {"c": [10, 20]}}),
+            ),
+            (
+                json!(["a", "a/b"]),
+                json!({"a": {"b": 1, "c": [10, 20, 30]}}),
+            ),
+            (json!(["f~1g"]), json!({"f/g": true})),
+            (json!(["d/e", "missing"]), json!({})),
+            (json!([]), json!({})),
+        ] {
+            assert_eq!(
+                filter(&[object.clone(), paths.clone()])?,
+                expected,
+                "{paths}"
+            );
+        }
+
+        assert!(filter(&[json!([1]), json!(["a"])]).is_err());
+        assert!(filter(&[object, json!([1])]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_paths() -> Result<()> {
+        let object = json!({"a": {"b": 1, "c": [10, 20, 30]}, "d": "e"});
+        for (paths, expected) in [
+            (json!(["a/b"]), json!({"a": {"c": [10, 20, 30]}, "d": "e"})),
+            (
+                json!(["d", ["a", "c", 1]]),
+                json!({"a": {"b": 1, "c": [10, 30]}}),
+            ),
+            (json!(["a"]), json!({"d": "e"})),
+            (json!(["d/e", "missing"]), object.clone()),
+            (json!([]), object.clone()),
+        ] {
+            assert_eq!(
+                remove(&[object.clone(), paths.clone()])?,
+                expected,
+                "{paths}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_json_schema() -> Result<()> {
+        let schema = json!({"type": "object", "required": ["a"]});
+        assert_eq!(
+            verify_schema(std::slice::from_ref(&schema))?,
+            json!([true, null])
+        );
+        assert_eq!(
+            verify_schema(&[json!(schema.to_string())])?,
+            json!([true, null])
+        );
+
+        for invalid in [json!({"type": 12}), json!("{\"type\": ")] {
+            let res = verify_schema(&[invalid])?;
+            assert_eq!(res[0], json!(false));
+            assert!(res[1].is_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn match_json_schema() -> Result<()> {
+        let schema = json!({
+            "type": "object",
+            "properties": {"a": {"type": "integer"}},
+            "required": ["a"],
+        });
+
+        assert_eq!(
+            match_schema(&[json!({"a": 1}), schema.clone()])?,
+            json!([true, []])
+        );
+        assert_eq!(
+            match_schema(&[json!("{\"a\": 1}"), json!(schema.to_string())])?,
+            json!([true, []])
+        );
+
+        let res = match_schema(&[json!({"a": "b"}), schema.clone()])?;
+        assert_eq!(res[0], json!(false));
+        assert_eq!(res[1][0]["field"], json!("a"));
+        assert_eq!(res[1][0]["type"], json!("type"));
+
+        let res = match_schema(&[json!({}), schema.clone()])?;
+        assert_eq!(res[1][0]["field"], json!("(root)"));
+        assert_eq!(res[1][0]["type"], json!("required"));
+
+        assert!(match_schema(&[json!({}), json!({"type": 12})]).is_err());
+
+        Ok(())
+    }
 }
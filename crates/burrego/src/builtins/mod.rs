@@ -1,24 +1,107 @@
 use crate::errors::Result;
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
 
 pub(crate) mod builtins_helper;
+mod crypto;
 mod debugging;
 mod encoding;
+mod evaluation;
 mod glob;
 mod json;
+mod jwt;
+mod net;
+mod rand;
 mod regex;
 mod semver;
 mod strings;
 mod time;
+mod units;
+mod uuid;
 
 pub(crate) use builtins_helper::BUILTINS_HELPER;
+pub use evaluation::PolicyMessage;
+pub(crate) use evaluation::{record_message, take_messages, with_evaluation};
 
 pub(crate) type BuiltinFunctionsMap =
     HashMap<&'static str, fn(&[serde_json::Value]) -> Result<serde_json::Value>>;
 
+/// A builtin provided by the embedder of burrego. Unlike the builtins
+/// implemented by burrego, it can capture state, like a channel used to
+/// reach the host
+pub type CustomBuiltin =
+    Arc<dyn Fn(&[serde_json::Value]) -> Result<serde_json::Value> + Send + Sync>;
+
+/// The custom builtins, indexed by their fully qualified name, e.g.
+/// `kubewarden.oci.manifest_digest`
+pub type CustomBuiltins = HashMap<String, CustomBuiltin>;
+
+/// The builtins a Rego policy needs from the host, split between the ones
+/// implemented by burrego and the unsupported ones
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BuiltinsReport {
+    pub supported: BTreeSet<String>,
+    pub unsupported: BTreeSet<String>,
+}
+
+impl BuiltinsReport {
+    pub fn new(used_builtins: impl IntoIterator<Item = String>) -> Self {
+        Self::with_custom_builtins(used_builtins, &CustomBuiltins::new())
+    }
+
+    /// Like `new`, but the given custom builtins are reported as supported
+    pub fn with_custom_builtins(
+        used_builtins: impl IntoIterator<Item = String>,
+        custom_builtins: &CustomBuiltins,
+    ) -> Self {
+        let builtins = get_builtins();
+        let (supported, unsupported) = used_builtins.into_iter().partition(|name| {
+            builtins.contains_key(name.as_str()) || custom_builtins.contains_key(name)
+        });
+
+        BuiltinsReport {
+            supported,
+            unsupported,
+        }
+    }
+
+    /// Returns true when the policy can be evaluated by burrego
+    pub fn is_compatible(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+}
+
 pub fn get_builtins() -> BuiltinFunctionsMap {
     let mut functions: BuiltinFunctionsMap = HashMap::new();
 
+    // crypto
+    functions.insert("crypto.md5", crypto::md5);
+    functions.insert("crypto.sha1", crypto::sha1);
+    functions.insert("crypto.sha256", crypto::sha256);
+    functions.insert("crypto.hmac.md5", crypto::hmac::md5);
+    functions.insert("crypto.hmac.sha1", crypto::hmac::sha1);
+    functions.insert("crypto.hmac.sha256", crypto::hmac::sha256);
+    functions.insert("crypto.hmac.sha512", crypto::hmac::sha512);
+    functions.insert("crypto.hmac.equal", crypto::hmac::equal);
+    functions.insert(
+        "crypto.x509.parse_certificates",
+        crypto::x509::parse_certificates,
+    );
+    functions.insert(
+        "crypto.x509.parse_and_verify_certificates",
+        crypto::x509::parse_and_verify_certificates,
+    );
+    functions.insert(
+        "crypto.x509.parse_certificate_request",
+        crypto::x509::parse_certificate_request,
+    );
+    functions.insert(
+        "crypto.x509.parse_rsa_private_key",
+        crypto::x509::parse_rsa_private_key,
+    );
+
     // debugging
     functions.insert("trace", debugging::trace);
 
@@ -40,9 +123,39 @@ pub fn get_builtins() -> BuiltinFunctionsMap {
 
     // glob
     functions.insert("glob.quote_meta", glob::quote_meta);
+    functions.insert("glob.match", glob::matches);
+
+    // jwt
+    functions.insert("io.jwt.decode", jwt::decode);
+    functions.insert("io.jwt.decode_verify", jwt::decode_verify);
+    functions.insert("io.jwt.verify_rs256", jwt::verify_rs256);
+    functions.insert("io.jwt.verify_rs384", jwt::verify_rs384);
+    functions.insert("io.jwt.verify_rs512", jwt::verify_rs512);
+    functions.insert("io.jwt.verify_ps256", jwt::verify_ps256);
+    functions.insert("io.jwt.verify_ps384", jwt::verify_ps384);
+    functions.insert("io.jwt.verify_ps512", jwt::verify_ps512);
+    functions.insert("io.jwt.verify_es256", jwt::verify_es256);
+    functions.insert("io.jwt.verify_es384", jwt::verify_es384);
+    functions.insert("io.jwt.verify_hs256", jwt::verify_hs256);
+    functions.insert("io.jwt.verify_hs384", jwt::verify_hs384);
+    functions.insert("io.jwt.verify_hs512", jwt::verify_hs512);
+
+    // net
+    functions.insert("net.cidr_contains", net::cidr_contains);
+    functions.insert("net.cidr_intersects", net::cidr_intersects);
+    functions.insert("net.cidr_merge", net::cidr_merge);
+    functions.insert("net.cidr_expand", net::cidr_expand);
+    functions.insert("net.cidr_is_valid", net::cidr_is_valid);
 
     // objects
     functions.insert("json.patch", json::patch);
+    functions.insert("json.filter", json::filter);
+    functions.insert("json.remove", json::remove);
+    functions.insert("json.verify_schema", json::verify_schema);
+    functions.insert("json.match_schema", json::match_schema);
+
+    // rand
+    functions.insert("rand.intn", rand::intn);
 
     // regex
     functions.insert("regex.split", regex::split);
@@ -55,11 +168,29 @@ pub fn get_builtins() -> BuiltinFunctionsMap {
 
     // strings
     functions.insert("sprintf", strings::sprintf);
+    functions.insert("strings.replace_n", strings::replace_n);
+    functions.insert("strings.render_template", strings::render_template);
 
     // time
     functions.insert("time.now_ns", time::now_ns);
     functions.insert("parse_rfc3339_ns", time::parse_rfc3339_ns);
+    functions.insert("time.parse_rfc3339_ns", time::parse_rfc3339_ns);
+    functions.insert("time.parse_ns", time::parse_ns);
+    functions.insert("time.parse_duration_ns", time::parse_duration_ns);
     functions.insert("date", time::date);
+    functions.insert("time.date", time::date);
+    functions.insert("time.clock", time::clock);
+    functions.insert("time.weekday", time::weekday);
+    functions.insert("time.format", time::format);
+    functions.insert("time.add_date", time::add_date);
+    functions.insert("time.diff", time::diff);
+
+    // units
+    functions.insert("units.parse", units::parse);
+    functions.insert("units.parse_bytes", units::parse_bytes);
+
+    // uuid
+    functions.insert("uuid.rfc4122", uuid::rfc4122);
 
     functions
 }
@@ -0,0 +1,460 @@
+use crate::errors::{BurregoError, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// An IPv4 or IPv6 network, the addresses are stored as u128 to share
+/// the arithmetic between the two families
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Cidr {
+    ipv6: bool,
+    network: u128,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parses a CIDR the same way Go's `net.ParseCIDR` does: the host bits
+    /// of the address are allowed to be set
+    fn parse(cidr: &str) -> Option<Self> {
+        let (ip, prefix) = cidr.split_once('/')?;
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let prefix: u8 = prefix.parse().ok()?;
+        let (ipv6, address) = address_to_u128(ip.parse().ok()?);
+        if prefix > bits(ipv6) {
+            return None;
+        }
+
+        let mut cidr = Cidr {
+            ipv6,
+            network: 0,
+            prefix,
+        };
+        cidr.network = address & !cidr.host_mask();
+        Some(cidr)
+    }
+
+    /// Parses either a CIDR or a single IP address, which is turned into
+    /// a network containing only itself
+    fn parse_cidr_or_ip(value: &str) -> Option<Self> {
+        if value.contains('/') {
+            return Cidr::parse(value);
+        }
+        let (ipv6, network) = address_to_u128(value.parse().ok()?);
+        Some(Cidr {
+            ipv6,
+            network,
+            prefix: bits(ipv6),
+        })
+    }
+
+    fn host_mask(&self) -> u128 {
+        match bits(self.ipv6) - self.prefix {
+            0 => 0,
+            128 => u128::MAX,
+            host_bits => (1 << host_bits) - 1,
+        }
+    }
+
+    fn last(&self) -> u128 {
+        self.network | self.host_mask()
+    }
+
+    fn contains(&self, other: &Cidr) -> bool {
+        self.ipv6 == other.ipv6
+            && other.prefix >= self.prefix
+            && other.network & !self.host_mask() == self.network
+    }
+
+    fn intersects(&self, other: &Cidr) -> bool {
+        self.contains(other) || other.contains(self)
+    }
+
+    /// Returns the network containing exactly `self` and `other` when they
+    /// are the two halves of it
+    fn merge_sibling(&self, other: &Cidr) -> Option<Cidr> {
+        if self.ipv6 != other.ipv6 || self.prefix != other.prefix || self.prefix == 0 {
+            return None;
+        }
+        let parent = Cidr {
+            ipv6: self.ipv6,
+            network: self.network,
+            prefix: self.prefix - 1,
+        };
+        (parent.network & parent.host_mask() == 0 && parent.last() == other.last())
+            .then_some(parent)
+    }
+}
+
+impl std::fmt::Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}",
+            address_to_string(self.ipv6, self.network),
+            self.prefix
+        )
+    }
+}
+
+/// Largest number of addresses `net.cidr_expand` returns. The networks can
+/// come from the objects being evaluated, expanding a `/8` or an IPv6
+/// network would exhaust the memory of the host
+const CIDR_EXPAND_MAX_ADDRESSES: u128 = 1 << 16;
+
+fn bits(ipv6: bool) -> u8 {
+    if ipv6 { 128 } else { 32 }
+}
+
+fn address_to_u128(address: IpAddr) -> (bool, u128) {
+    match address {
+        IpAddr::V4(address) => (false, u128::from(u32::from(address))),
+        IpAddr::V6(address) => (true, u128::from(address)),
+    }
+}
+
+fn address_to_string(ipv6: bool, address: u128) -> String {
+    if ipv6 {
+        Ipv6Addr::from(address).to_string()
+    } else {
+        Ipv4Addr::from(address as u32).to_string()
+    }
+}
+
+fn string_arg<'a>(name: &str, args: &'a [serde_json::Value], index: usize) -> Result<&'a str> {
+    args[index]
+        .as_str()
+        .ok_or_else(|| BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: format!("parameter {} is not a string", index + 1),
+        })
+}
+
+fn parse_cidr(name: &str, cidr: &str) -> Result<Cidr> {
+    Cidr::parse(cidr).ok_or_else(|| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: format!("invalid CIDR address: {cidr}"),
+    })
+}
+
+/// OPA returns sets, which are sorted
+fn to_sorted_json(name: &str, values: impl Iterator<Item = String>) -> Result<serde_json::Value> {
+    let mut values: Vec<String> = values.collect();
+    values.sort();
+    values.dedup();
+
+    serde_json::to_value(values).map_err(|e| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+pub fn cidr_contains(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "net.cidr_contains".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let cidr = parse_cidr(
+        "net.cidr_contains",
+        string_arg("net.cidr_contains", args, 0)?,
+    )?;
+    let cidr_or_ip = string_arg("net.cidr_contains", args, 1)?;
+    let other = Cidr::parse_cidr_or_ip(cidr_or_ip).ok_or_else(|| BurregoError::BuiltinError {
+        name: "net.cidr_contains".to_string(),
+        message: format!("invalid IP or CIDR address: {cidr_or_ip}"),
+    })?;
+
+    serde_json::to_value(cidr.contains(&other)).map_err(|e| BurregoError::BuiltinError {
+        name: "net.cidr_contains".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+pub fn cidr_intersects(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "net.cidr_intersects".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let cidr1 = parse_cidr(
+        "net.cidr_intersects",
+        string_arg("net.cidr_intersects", args, 0)?,
+    )?;
+    let cidr2 = parse_cidr(
+        "net.cidr_intersects",
+        string_arg("net.cidr_intersects", args, 1)?,
+    )?;
+
+    serde_json::to_value(cidr1.intersects(&cidr2)).map_err(|e| BurregoError::BuiltinError {
+        name: "net.cidr_intersects".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+pub fn cidr_is_valid(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "net.cidr_is_valid".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let cidr = string_arg("net.cidr_is_valid", args, 0)?;
+
+    serde_json::to_value(Cidr::parse(cidr).is_some()).map_err(|e| BurregoError::BuiltinError {
+        name: "net.cidr_is_valid".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+/// Returns all the addresses of the network, including the network and
+/// broadcast ones
+pub fn cidr_expand(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "net.cidr_expand".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let cidr = parse_cidr("net.cidr_expand", string_arg("net.cidr_expand", args, 0)?)?;
+    if cidr.host_mask() >= CIDR_EXPAND_MAX_ADDRESSES {
+        return Err(BurregoError::BuiltinError {
+            name: "net.cidr_expand".to_string(),
+            message: format!("{cidr} contains more than {CIDR_EXPAND_MAX_ADDRESSES} addresses"),
+        });
+    }
+
+    to_sorted_json(
+        "net.cidr_expand",
+        (cidr.network..=cidr.last()).map(|address| address_to_string(cidr.ipv6, address)),
+    )
+}
+
+/// Merges the given IP addresses and networks into the smallest possible
+/// list of networks
+pub fn cidr_merge(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "net.cidr_merge".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let addresses = args[0]
+        .as_array()
+        .ok_or_else(|| BurregoError::BuiltinError {
+            name: "net.cidr_merge".to_string(),
+            message: "1st parameter is not an array".to_string(),
+        })?;
+
+    let cidrs = addresses
+        .iter()
+        .map(|address| {
+            address
+                .as_str()
+                .and_then(Cidr::parse_cidr_or_ip)
+                .ok_or_else(|| BurregoError::BuiltinError {
+                    name: "net.cidr_merge".to_string(),
+                    message: format!("{address} is not a valid IP or CIDR address"),
+                })
+        })
+        .collect::<Result<Vec<Cidr>>>()?;
+
+    to_sorted_json(
+        "net.cidr_merge",
+        merge(cidrs).iter().map(|cidr| cidr.to_string()),
+    )
+}
+
+fn merge(mut cidrs: Vec<Cidr>) -> Vec<Cidr> {
+    loop {
+        // once sorted, a network is preceded by the ones containing it
+        cidrs.sort();
+        let mut kept: Vec<Cidr> = Vec::with_capacity(cidrs.len());
+        for cidr in cidrs {
+            if kept.last().is_some_and(|last| last.contains(&cidr)) {
+                continue;
+            }
+            kept.push(cidr);
+        }
+
+        let mut merged = Vec::with_capacity(kept.len());
+        let mut changed = false;
+        let mut index = 0;
+        while index < kept.len() {
+            let parent = kept
+                .get(index + 1)
+                .and_then(|next| kept[index].merge_sibling(next));
+            match parent {
+                Some(parent) => {
+                    merged.push(parent);
+                    changed = true;
+                    index += 2;
+                }
+                None => {
+                    merged.push(kept[index]);
+                    index += 1;
+                }
+            }
+        }
+
+        cidrs = merged;
+        if !changed {
+            return cidrs;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn contains() -> Result<()> {
+        for (cidr, cidr_or_ip, expected) in [
+            ("10.0.0.0/8", "10.1.2.3", true),
+            ("10.0.0.0/8", "192.168.0.1", false),
+            ("10.0.0.0/8", "10.1.0.0/16", true),
+            ("10.0.0.0/16", "10.0.0.0/8", false),
+            ("10.0.0.0/8", "10.0.0.0/8", true),
+            ("10.1.2.3/8", "10.200.0.0/16", true),
+            ("0.0.0.0/0", "203.0.113.7", true),
+            ("2001:db8::/32", "2001:db8:1::1", true),
+            ("2001:db8::/32", "2001:db9::1", false),
+            ("2001:db8::/32", "10.0.0.1", false),
+            ("::/0", "2001:db8::/48", true),
+        ] {
+            assert_eq!(
+                cidr_contains(&[json!(cidr), json!(cidr_or_ip)])?,
+                expected,
+                "{cidr} {cidr_or_ip}"
+            );
+        }
+
+        assert!(cidr_contains(&[json!("10.0.0.0/33"), json!("10.0.0.1")]).is_err());
+        assert!(cidr_contains(&[json!("10.0.0.1"), json!("10.0.0.1")]).is_err());
+        assert!(cidr_contains(&[json!("10.0.0.0/8"), json!("foo")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn intersects() -> Result<()> {
+        for (cidr1, cidr2, expected) in [
+            ("192.168.0.0/16", "192.168.1.0/24", true),
+            ("192.168.1.0/24", "192.168.0.0/16", true),
+            ("10.0.0.0/8", "192.168.0.0/16", false),
+            (
+                "fd1e:5bfe:8af3:9ddc::/64",
+                "fd1e:5bfe:8af3:9ddc:1111::/72",
+                true,
+            ),
+            ("fd1e:5bfe:8af3:9ddc::/64", "10.0.0.0/8", false),
+        ] {
+            assert_eq!(
+                cidr_intersects(&[json!(cidr1), json!(cidr2)])?,
+                expected,
+                "{cidr1} {cidr2}"
+            );
+        }
+
+        assert!(cidr_intersects(&[json!("10.0.0.0/8"), json!("10.0.0.1")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_valid() -> Result<()> {
+        for (cidr, expected) in [
+            ("192.168.0.0/16", true),
+            ("192.168.0.1/16", true),
+            ("2001:db8::/32", true),
+            ("192.168.0.0/33", false),
+            ("192.168.0.0/+1", false),
+            ("192.168.0.0/", false),
+            ("192.168.0.1", false),
+            ("foo/24", false),
+        ] {
+            assert_eq!(cidr_is_valid(&[json!(cidr)])?, expected, "{cidr}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn expand() -> Result<()> {
+        assert_eq!(
+            cidr_expand(&[json!("192.168.1.1/30")])?,
+            json!(["192.168.1.0", "192.168.1.1", "192.168.1.2", "192.168.1.3"])
+        );
+        assert_eq!(cidr_expand(&[json!("10.0.0.1/32")])?, json!(["10.0.0.1"]));
+        assert_eq!(
+            cidr_expand(&[json!("2001:db8::/127")])?,
+            json!(["2001:db8::", "2001:db8::1"])
+        );
+        assert!(cidr_expand(&[json!("10.0.0.1")]).is_err());
+        assert_eq!(
+            cidr_expand(&[json!("10.1.0.0/16")])?
+                .as_array()
+                .unwrap()
+                .len(),
+            65536
+        );
+        assert!(cidr_expand(&[json!("10.0.0.0/8")]).is_err());
+        assert!(cidr_expand(&[json!("::/0")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge() -> Result<()> {
+        for (addresses, expected) in [
+            (
+                json!(["192.0.128.0/24", "192.0.129.0/24"]),
+                json!(["192.0.128.0/23"]),
+            ),
+            (json!(["10.0.0.0/8", "10.1.0.0/16"]), json!(["10.0.0.0/8"])),
+            (
+                json!(["192.168.1.1", "192.168.1.0"]),
+                json!(["192.168.1.0/31"]),
+            ),
+            (
+                json!([
+                    "192.168.1.0/26",
+                    "192.168.1.64/26",
+                    "192.168.1.128/26",
+                    "192.168.1.192/26"
+                ]),
+                json!(["192.168.1.0/24"]),
+            ),
+            // not siblings: 192.0.129.0/24 and 192.0.130.0/24 belong to different /23
+            (
+                json!(["192.0.129.0/24", "192.0.130.0/24"]),
+                json!(["192.0.129.0/24", "192.0.130.0/24"]),
+            ),
+            (
+                json!(["2001:db8::/33", "2001:db8:8000::/33", "10.0.0.0/8"]),
+                json!(["10.0.0.0/8", "2001:db8::/32"]),
+            ),
+            (json!(["0.0.0.0/1", "128.0.0.0/1"]), json!(["0.0.0.0/0"])),
+            (json!([]), json!([])),
+        ] {
+            assert_eq!(
+                cidr_merge(std::slice::from_ref(&addresses))?,
+                expected,
+                "{addresses}"
+            );
+        }
+
+        assert!(cidr_merge(&[json!(["foo"])]).is_err());
+        assert!(cidr_merge(&[json!([1])]).is_err());
+
+        Ok(())
+    }
+}
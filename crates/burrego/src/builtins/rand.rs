@@ -0,0 +1,74 @@
+use crate::builtins::evaluation::random_bytes;
+use crate::errors::{BurregoError, Result};
+
+/// Returns a random integer in `[0, n)`. During an evaluation the same
+/// number is returned when the function is invoked with the same arguments.
+pub fn intn(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "rand.intn".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let key = args[0].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "rand.intn".to_string(),
+        message: "1st parameter is not a string".to_string(),
+    })?;
+
+    let n = args[1].as_i64().ok_or_else(|| BurregoError::BuiltinError {
+        name: "rand.intn".to_string(),
+        message: "2nd parameter is not an integer".to_string(),
+    })?;
+
+    // like OPA, negative values are turned into positive ones
+    let n = n.unsigned_abs();
+    if n == 0 {
+        return Ok(serde_json::json!(0));
+    }
+
+    let bytes = random_bytes("rand.intn", &serde_json::json!([key, n]).to_string());
+    let random = u64::from_be_bytes(bytes[..8].try_into().expect("slice of 8 bytes"));
+
+    serde_json::to_value(random % n).map_err(|e| BurregoError::BuiltinError {
+        name: "rand.intn".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::builtins::evaluation::with_evaluation;
+    use serde_json::json;
+
+    #[test]
+    fn intn_is_in_range() -> Result<()> {
+        for n in [1i64, 2, 10, -10, 1_000_000] {
+            for key in ["a", "b", "c"] {
+                let value = intn(&[json!(key), json!(n)])?.as_u64().unwrap();
+                assert!(value < n.unsigned_abs(), "{value} not in [0, {n})");
+            }
+        }
+        assert_eq!(intn(&[json!("a"), json!(0)])?, json!(0));
+
+        assert!(intn(&[json!("a"), json!(1.5)]).is_err());
+        assert!(intn(&[json!(1), json!(10)]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn intn_is_stable_during_an_evaluation() -> Result<()> {
+        let args = [json!("key"), json!(i64::MAX)];
+        let (first, second) = with_evaluation(None, || (intn(&args), intn(&args)));
+        assert_eq!(first?, second?);
+
+        assert_eq!(
+            with_evaluation(Some(1), || intn(&args))?,
+            with_evaluation(Some(1), || intn(&args))?
+        );
+
+        Ok(())
+    }
+}
@@ -29,7 +29,7 @@ pub fn split(args: &[serde_json::Value]) -> Result<serde_json::Value> {
                 ),
             })?
             .split(string_str)
-            .collect::<String>(),
+            .collect::<Vec<&str>>(),
     )
     .map_err(|e| BurregoError::BuiltinError {
         name: "regex.split".to_string(),
@@ -8,8 +8,11 @@ impl From<serde_json::Value> for GoTmplValue {
         match value {
             serde_json::Value::String(s) => GoTmplValue(gtmpl::Value::String(s)),
             serde_json::Value::Number(n) => {
-                let n: i64 = n.as_i64().unwrap();
-                let number: gtmpl_value::Number = n.into();
+                let number: gtmpl_value::Number = match (n.as_i64(), n.as_u64()) {
+                    (Some(n), _) => n.into(),
+                    (None, Some(n)) => n.into(),
+                    (None, None) => n.as_f64().unwrap_or_default().into(),
+                };
                 GoTmplValue(gtmpl::Value::Number(number))
             }
             serde_json::Value::Bool(b) => GoTmplValue(gtmpl::Value::Bool(b)),
@@ -84,6 +87,95 @@ pub fn sprintf(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     })
 }
 
+/// Replaces all the occurrences of the keys of the given object with their
+/// values. Like Go's `strings.Replacer`, the replacements don't overlap and,
+/// when multiple keys match at the same position, the first one in
+/// lexicographical order wins.
+pub fn replace_n(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "strings.replace_n".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let patterns = args[0]
+        .as_object()
+        .ok_or_else(|| BurregoError::BuiltinError {
+            name: "strings.replace_n".to_string(),
+            message: "1st parameter is not an object".to_string(),
+        })?;
+    let mut replacements: Vec<(&str, &str)> = Vec::with_capacity(patterns.len());
+    for (old, new) in patterns {
+        let new = new.as_str().ok_or_else(|| BurregoError::BuiltinError {
+            name: "strings.replace_n".to_string(),
+            message: format!("the value of key {old} is not a string"),
+        })?;
+        if !old.is_empty() {
+            replacements.push((old.as_str(), new));
+        }
+    }
+    replacements.sort();
+
+    let value = args[1].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "strings.replace_n".to_string(),
+        message: "2nd parameter is not a string".to_string(),
+    })?;
+
+    let mut res = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(c) = rest.chars().next() {
+        match replacements.iter().find(|(old, _)| rest.starts_with(old)) {
+            Some((old, new)) => {
+                res.push_str(new);
+                rest = &rest[old.len()..];
+            }
+            None => {
+                res.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    serde_json::to_value(res).map_err(|e| BurregoError::BuiltinError {
+        name: "strings.replace_n".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+/// Renders a Go template with the given variables
+pub fn render_template(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "strings.render_template".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let template_str = args[0].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "strings.render_template".to_string(),
+        message: "1st parameter is not a string".to_string(),
+    })?;
+
+    if !args[1].is_object() {
+        return Err(BurregoError::BuiltinError {
+            name: "strings.render_template".to_string(),
+            message: "2nd parameter is not an object".to_string(),
+        });
+    }
+    let vars: GoTmplValue = args[1].clone().into();
+
+    let res = gtmpl::template(template_str, vars.0).map_err(|e| BurregoError::BuiltinError {
+        name: "strings.render_template".to_string(),
+        message: format!("cannot render go template '{template_str}': {e:?}"),
+    })?;
+
+    serde_json::to_value(res).map_err(|e| BurregoError::BuiltinError {
+        name: "strings.render_template".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -100,4 +192,45 @@ mod test {
         assert!(actual.is_ok());
         assert_eq!(json!("hello world 42 [this is a list]"), actual.unwrap());
     }
+
+    #[test]
+    fn replace_n_replaces_all_the_patterns() -> Result<()> {
+        for (patterns, value, expected) in [
+            (json!({"a": "b", "c": "d"}), "abcabc", "bbdbbd"),
+            (json!({"%s": "world"}), "hello %s %s", "hello world world"),
+            // the first key in lexicographical order wins
+            (json!({"aa": "b", "a": "c"}), "aaa", "ccc"),
+            // replacements are not applied to the output of other replacements
+            (json!({"a": "b", "b": "c"}), "ab", "bc"),
+            (json!({"ü": "u"}), "über", "uber"),
+            (json!({}), "unchanged", "unchanged"),
+        ] {
+            assert_eq!(
+                replace_n(&[patterns.clone(), json!(value)])?,
+                json!(expected),
+                "{patterns} {value}"
+            );
+        }
+
+        assert!(replace_n(&[json!({"a": 1}), json!("a")]).is_err());
+        assert!(replace_n(&[json!(["a"]), json!("a")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_template_with_variables() -> Result<()> {
+        assert_eq!(
+            render_template(&[
+                json!("{{.name}} has {{range .items}}[{{.}}]{{end}} and {{.count}} items"),
+                json!({"name": "list", "items": ["a", "b"], "count": 2}),
+            ])?,
+            json!("list has [a][b] and 2 items")
+        );
+
+        assert!(render_template(&[json!("{{.name"), json!({})]).is_err());
+        assert!(render_template(&[json!("{{.name}}"), json!("name")]).is_err());
+
+        Ok(())
+    }
 }
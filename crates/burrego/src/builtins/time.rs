@@ -1,6 +1,10 @@
+use crate::builtins::evaluation::now;
 use crate::errors::{BurregoError, Result};
-use chrono::{self, DateTime, Datelike, Duration, Local};
-use std::str::FromStr;
+use chrono::{
+    self, DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, TimeZone, Timelike, Utc,
+    format::{Fixed, Item, Numeric, Pad, Parsed},
+};
+use std::{fmt::Display, str::FromStr};
 
 pub fn now_ns(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     if !args.is_empty() {
@@ -9,8 +13,7 @@ pub fn now_ns(args: &[serde_json::Value]) -> Result<serde_json::Value> {
             message: "wrong number of arguments given".to_string(),
         });
     }
-    let now = Local::now();
-    serde_json::to_value(now.timestamp_nanos_opt()).map_err(|e| BurregoError::BuiltinError {
+    serde_json::to_value(now().timestamp_nanos_opt()).map_err(|e| BurregoError::BuiltinError {
         name: "time.now_ns".to_string(),
         message: format!("cannot convert value into JSON: {e:?}"),
     })
@@ -40,6 +43,199 @@ pub fn parse_rfc3339_ns(args: &[serde_json::Value]) -> Result<serde_json::Value>
     })
 }
 
+/// Parses the value using a Go reference layout, like `2006-01-02`
+pub fn parse_ns(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "time.parse_ns".to_string(),
+            message: "wrong number of arguments given".to_string(),
+        });
+    }
+
+    let layout = args[0].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "time.parse_ns".to_string(),
+        message: "1st parameter is not a string".to_string(),
+    })?;
+
+    let value = args[1].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "time.parse_ns".to_string(),
+        message: "2nd parameter is not a string".to_string(),
+    })?;
+
+    let layout = GoLayout::new(layout);
+    let mut parsed = Parsed::new();
+    chrono::format::parse(&mut parsed, value, layout.parse_items().iter()).map_err(|e| {
+        BurregoError::BuiltinError {
+            name: "time.parse_ns".to_string(),
+            message: format!("cannot parse {value}: {e}"),
+        }
+    })?;
+
+    // like Go, the missing elements default to the beginning of year 0, in UTC.
+    // Setting a value that has already been parsed is a no-op.
+    if !layout.has_year {
+        let _ = parsed.set_year(0);
+    }
+    if !layout.has_year_day {
+        let _ = parsed.set_month(1);
+        let _ = parsed.set_day(1);
+    }
+    let _ = parsed.set_hour(0);
+    let _ = parsed.set_minute(0);
+    let _ = parsed.set_second(0);
+    let _ = parsed.set_offset(0);
+
+    let nanoseconds = parsed
+        .to_datetime()
+        .map_err(|e| BurregoError::BuiltinError {
+            name: "time.parse_ns".to_string(),
+            message: format!("cannot parse {value}: {e}"),
+        })?
+        .timestamp_nanos_opt()
+        .ok_or_else(|| BurregoError::BuiltinError {
+            name: "time.parse_ns".to_string(),
+            message: "time outside of valid range".to_string(),
+        })?;
+
+    serde_json::to_value(nanoseconds).map_err(|e| BurregoError::BuiltinError {
+        name: "time.parse_ns".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+/// Parses a Go duration string, like `1h30m` or `-1.5s`
+pub fn parse_duration_ns(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "time.parse_duration_ns".to_string(),
+            message: "wrong number of arguments given".to_string(),
+        });
+    }
+
+    let value = args[0].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "time.parse_duration_ns".to_string(),
+        message: "1st parameter is not a string".to_string(),
+    })?;
+
+    let duration = go_duration_ns(value).ok_or_else(|| BurregoError::BuiltinError {
+        name: "time.parse_duration_ns".to_string(),
+        message: format!("invalid duration {value:?}"),
+    })?;
+
+    serde_json::to_value(duration).map_err(|e| BurregoError::BuiltinError {
+        name: "time.parse_duration_ns".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+fn go_duration_ns(value: &str) -> Option<i64> {
+    let (negative, mut rest) = match value.as_bytes().first() {
+        Some(b'-') => (true, &value[1..]),
+        Some(b'+') => (false, &value[1..]),
+        _ => (false, value),
+    };
+    if rest == "0" {
+        return Some(0);
+    }
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total: u64 = 0;
+    while !rest.is_empty() {
+        let integer_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (integer, after_integer) = rest.split_at(integer_end);
+        let (fraction, after_fraction) = match after_integer.strip_prefix('.') {
+            Some(after_dot) => {
+                let fraction_end = after_dot
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(after_dot.len());
+                after_dot.split_at(fraction_end)
+            }
+            None => ("", after_integer),
+        };
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let unit_end = after_fraction
+            .find(|c: char| c == '.' || c.is_ascii_digit())
+            .unwrap_or(after_fraction.len());
+        let (unit, after_unit) = after_fraction.split_at(unit_end);
+        let unit: u64 = match unit {
+            "ns" => 1,
+            "us" | "\u{00b5}s" | "\u{03bc}s" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60 * 1_000_000_000,
+            "h" => 60 * 60 * 1_000_000_000,
+            _ => return None,
+        };
+
+        let integer: u64 = if integer.is_empty() {
+            0
+        } else {
+            integer.parse().ok()?
+        };
+        let mut amount = integer.checked_mul(unit)?;
+        // the digits beyond the nanosecond precision are ignored
+        let fraction = &fraction[..fraction.len().min(18)];
+        if !fraction.is_empty() {
+            let scale = 10u128.pow(fraction.len() as u32);
+            let fraction: u128 = fraction.parse().ok()?;
+            amount = amount.checked_add((fraction * u128::from(unit) / scale) as u64)?;
+        }
+        total = total.checked_add(amount)?;
+        rest = after_unit;
+    }
+
+    let total = i64::try_from(total).ok()?;
+    Some(if negative { -total } else { total })
+}
+
+/// Formats the time using the given Go reference layout or one of the
+/// names of the Go layout constants, `RFC3339Nano` is used by default.
+/// The input is either a number of nanoseconds, `[ns, tz]` or
+/// `[ns, tz, layout]`.
+pub fn format(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "time.format".to_string(),
+            message: "wrong number of arguments given".to_string(),
+        });
+    }
+
+    let (nanoseconds, timezone, layout) = match &args[0] {
+        serde_json::Value::Array(val) if val.len() == 3 => {
+            let layout = val[2].as_str().ok_or_else(|| BurregoError::BuiltinError {
+                name: "time.format".to_string(),
+                message: "3rd array item is not a string".to_string(),
+            })?;
+            let (nanoseconds, timezone) =
+                nanoseconds_and_timezone("time.format", &serde_json::json!([val[0], val[1]]))?;
+            (nanoseconds, timezone, layout)
+        }
+        value => {
+            let (nanoseconds, timezone) = nanoseconds_and_timezone("time.format", value)?;
+            (nanoseconds, timezone, "RFC3339Nano")
+        }
+    };
+
+    let layout = GoLayout::new(named_layout(layout).unwrap_or(layout));
+    let dt = from_nanoseconds("time.format", nanoseconds)?;
+    let res = match timezone.as_str() {
+        "Local" => layout.format(&dt.with_timezone(&Local)),
+        name => layout.format(&dt.with_timezone(&parse_timezone("time.format", name)?)),
+    };
+
+    serde_json::to_value(res).map_err(|e| BurregoError::BuiltinError {
+        name: "time.format".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
 pub fn date(args: &[serde_json::Value]) -> Result<serde_json::Value> {
     if args.len() != 1 {
         return Err(BurregoError::BuiltinError {
@@ -48,71 +244,397 @@ pub fn date(args: &[serde_json::Value]) -> Result<serde_json::Value> {
         });
     }
 
-    let nanoseconds: i64;
-    let mut timezone: chrono_tz::Tz = chrono_tz::UTC;
+    let dt = datetime_in_timezone("time.date", &args[0])?;
+
+    Ok(serde_json::json!([dt.year(), dt.month(), dt.day(),]))
+}
+
+pub fn clock(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "time.clock".to_string(),
+            message: "wrong number of arguments given".to_string(),
+        });
+    }
+
+    let dt = datetime_in_timezone("time.clock", &args[0])?;
+
+    Ok(serde_json::json!([dt.hour(), dt.minute(), dt.second()]))
+}
+
+pub fn weekday(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "time.weekday".to_string(),
+            message: "wrong number of arguments given".to_string(),
+        });
+    }
+
+    let dt = datetime_in_timezone("time.weekday", &args[0])?;
+
+    Ok(serde_json::json!(dt.format("%A").to_string()))
+}
+
+/// Adds the given number of years, months and days to the time. Like Go,
+/// the overflowing dates are normalized: October 31 plus one month is
+/// December 1.
+pub fn add_date(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 4 {
+        return Err(BurregoError::BuiltinError {
+            name: "time.add_date".to_string(),
+            message: "wrong number of arguments given".to_string(),
+        });
+    }
+
+    let mut numbers = [0i64; 4];
+    for (index, number) in numbers.iter_mut().enumerate() {
+        *number = args[index]
+            .as_i64()
+            .ok_or_else(|| BurregoError::BuiltinError {
+                name: "time.add_date".to_string(),
+                message: format!("parameter {} is not an integer", index + 1),
+            })?;
+    }
+    let [nanoseconds, years, months, days] = numbers;
+
+    let overflow = || BurregoError::BuiltinError {
+        name: "time.add_date".to_string(),
+        message: "time outside of valid range".to_string(),
+    };
+    let dt = from_nanoseconds("time.add_date", nanoseconds)?;
+    let total_months = (i64::from(dt.year()) + years) * 12 + i64::from(dt.month0()) + months;
+    let year = i32::try_from(total_months.div_euclid(12)).map_err(|_| overflow())?;
+    let month = total_months.rem_euclid(12) as u32 + 1;
+    let res = NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.checked_add_signed(Duration::days(i64::from(dt.day()) - 1 + days)))
+        .map(|date| date.and_time(dt.time()).and_utc())
+        .and_then(|dt| dt.timestamp_nanos_opt())
+        .ok_or_else(overflow)?;
+
+    serde_json::to_value(res).map_err(|e| BurregoError::BuiltinError {
+        name: "time.add_date".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+/// Returns the difference between two times as
+/// `[years, months, days, hours, minutes, seconds]`, regardless of their order
+pub fn diff(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 2 {
+        return Err(BurregoError::BuiltinError {
+            name: "time.diff".to_string(),
+            message: "wrong number of arguments given".to_string(),
+        });
+    }
 
-    match args[0].clone() {
+    let mut a = datetime_in_timezone("time.diff", &args[0])?;
+    let mut b = datetime_in_timezone("time.diff", &args[1])?.with_timezone(&a.timezone());
+    if a > b {
+        std::mem::swap(&mut a, &mut b);
+    }
+
+    let mut year = b.year() - a.year();
+    let mut month = b.month() as i32 - a.month() as i32;
+    let mut day = b.day() as i32 - a.day() as i32;
+    let mut hour = b.hour() as i32 - a.hour() as i32;
+    let mut minute = b.minute() as i32 - a.minute() as i32;
+    let mut second = b.second() as i32 - a.second() as i32;
+
+    if second < 0 {
+        second += 60;
+        minute -= 1;
+    }
+    if minute < 0 {
+        minute += 60;
+        hour -= 1;
+    }
+    if hour < 0 {
+        hour += 24;
+        day -= 1;
+    }
+    if day < 0 {
+        day += days_in_month(a.year(), a.month());
+        month -= 1;
+    }
+    if month < 0 {
+        month += 12;
+        year -= 1;
+    }
+
+    Ok(serde_json::json!([year, month, day, hour, minute, second]))
+}
+
+fn days_in_month(year: i32, month: u32) -> i32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|next| next.pred_opt())
+        .map(|last| last.day() as i32)
+        .unwrap_or(31)
+}
+
+fn from_nanoseconds(name: &str, nanoseconds: i64) -> Result<DateTime<Utc>> {
+    DateTime::UNIX_EPOCH
+        .checked_add_signed(Duration::nanoseconds(nanoseconds))
+        .ok_or_else(|| BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "overflow when building date".to_string(),
+        })
+}
+
+fn parse_timezone(name: &str, timezone: &str) -> Result<chrono_tz::Tz> {
+    if timezone.is_empty() {
+        return Ok(chrono_tz::UTC);
+    }
+    chrono_tz::Tz::from_str(timezone).map_err(|e| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: format!("cannot handle given timezone {timezone}: {e:?}"),
+    })
+}
+
+/// Parses the time parameter of the builtins, which is either a number of
+/// nanoseconds or `[ns, tz]`
+fn nanoseconds_and_timezone(name: &str, value: &serde_json::Value) -> Result<(i64, String)> {
+    match value {
         serde_json::Value::Number(val) => {
-            nanoseconds = val.as_i64().ok_or_else(|| BurregoError::BuiltinError {
-                name: "time.date".to_string(),
+            let nanoseconds = val.as_i64().ok_or_else(|| BurregoError::BuiltinError {
+                name: name.to_string(),
                 message: "1st parameter is not a number".to_string(),
             })?;
+            Ok((nanoseconds, "UTC".to_string()))
         }
         serde_json::Value::Array(val) => {
             if val.len() != 2 {
                 return Err(BurregoError::BuiltinError {
-                    name: "time.date".to_string(),
+                    name: name.to_string(),
                     message: "wrong number of items inside of input array".to_string(),
                 });
             }
-            nanoseconds = val[0].as_i64().ok_or_else(|| BurregoError::BuiltinError {
-                name: "time.date".to_string(),
+            let nanoseconds = val[0].as_i64().ok_or_else(|| BurregoError::BuiltinError {
+                name: name.to_string(),
                 message: "1st array item is not a number".to_string(),
             })?;
-            let tz_name = val[1].as_str().ok_or_else(|| BurregoError::BuiltinError {
-                name: "time.date".to_string(),
+            let timezone = val[1].as_str().ok_or_else(|| BurregoError::BuiltinError {
+                name: name.to_string(),
                 message: "2nd array item is not a string".to_string(),
             })?;
-            if tz_name == "Local" {
-                return date_local(nanoseconds);
+            Ok((nanoseconds, timezone.to_string()))
+        }
+        _ => Err(BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "the 1st parameter is neither a number nor an array".to_string(),
+        }),
+    }
+}
+
+/// Returns the time expressed by the parameter, in its timezone
+fn datetime_in_timezone(name: &str, value: &serde_json::Value) -> Result<DateTime<FixedOffset>> {
+    let (nanoseconds, timezone) = nanoseconds_and_timezone(name, value)?;
+    let dt = from_nanoseconds(name, nanoseconds)?;
+    Ok(match timezone.as_str() {
+        "Local" => dt.with_timezone(&Local).fixed_offset(),
+        timezone => dt
+            .with_timezone(&parse_timezone(name, timezone)?)
+            .fixed_offset(),
+    })
+}
+
+/// Returns the Go layout defined by the given constant of the Go time package
+fn named_layout(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "ANSIC" => "Mon Jan _2 15:04:05 2006",
+        "UnixDate" => "Mon Jan _2 15:04:05 MST 2006",
+        "RubyDate" => "Mon Jan 02 15:04:05 -0700 2006",
+        "RFC822" => "02 Jan 06 15:04 MST",
+        "RFC822Z" => "02 Jan 06 15:04 -0700",
+        "RFC850" => "Monday, 02-Jan-06 15:04:05 MST",
+        "RFC1123" => "Mon, 02 Jan 2006 15:04:05 MST",
+        "RFC1123Z" => "Mon, 02 Jan 2006 15:04:05 -0700",
+        "RFC3339" => "2006-01-02T15:04:05Z07:00",
+        "RFC3339Nano" => "2006-01-02T15:04:05.999999999Z07:00",
+        "Kitchen" => "3:04PM",
+        "Stamp" => "Jan _2 15:04:05",
+        "StampMilli" => "Jan _2 15:04:05.000",
+        "StampMicro" => "Jan _2 15:04:05.000000",
+        "StampNano" => "Jan _2 15:04:05.000000000",
+        "DateTime" => "2006-01-02 15:04:05",
+        "DateOnly" => "2006-01-02",
+        "TimeOnly" => "15:04:05",
+        _ => return None,
+    })
+}
+
+/// A Go reference layout, like `2006-01-02T15:04:05Z07:00`, translated into
+/// chrono format items
+struct GoLayout<'a> {
+    elements: Vec<LayoutElement<'a>>,
+    has_year: bool,
+    has_year_day: bool,
+}
+
+enum LayoutElement<'a> {
+    Item(Item<'a>),
+    /// Fractional seconds: `.000` always prints the given number of digits,
+    /// `.999` omits the trailing zeros
+    Fraction {
+        separator: char,
+        digits: usize,
+        trim: bool,
+    },
+}
+
+impl<'a> GoLayout<'a> {
+    fn new(layout: &'a str) -> Self {
+        let mut res = GoLayout {
+            elements: Vec::new(),
+            has_year: false,
+            has_year_day: false,
+        };
+
+        let mut literal_start = 0;
+        let mut index = 0;
+        while index < layout.len() {
+            let rest = &layout[index..];
+            // `_2006` is a literal underscore followed by the year
+            let element = if rest.starts_with("_2006") {
+                None
             } else {
-                timezone =
-                    chrono_tz::Tz::from_str(tz_name).map_err(|e| BurregoError::BuiltinError {
-                        name: "time.date".to_string(),
-                        message: format!("cannot handle given timezone {tz_name}: {e:?}"),
-                    })?;
+                Self::element(rest)
+            };
+            match element {
+                Some((len, element)) => {
+                    if literal_start < index {
+                        res.elements.push(LayoutElement::Item(Item::Literal(
+                            &layout[literal_start..index],
+                        )));
+                    }
+                    match element {
+                        LayoutElement::Item(Item::Numeric(
+                            Numeric::Year | Numeric::YearMod100,
+                            _,
+                        )) => res.has_year = true,
+                        LayoutElement::Item(Item::Numeric(Numeric::Ordinal, _)) => {
+                            res.has_year_day = true
+                        }
+                        _ => {}
+                    }
+                    res.elements.push(element);
+                    index += len;
+                    literal_start = index;
+                }
+                None => {
+                    index += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+                }
             }
         }
-        _ => {
-            return Err(BurregoError::BuiltinError {
-                name: "time.date".to_string(),
-                message: "the 1st parameter is neither a number nor an array".to_string(),
-            });
+        if literal_start < layout.len() {
+            res.elements
+                .push(LayoutElement::Item(Item::Literal(&layout[literal_start..])));
         }
-    };
 
-    let dt = DateTime::UNIX_EPOCH
-        .checked_add_signed(Duration::nanoseconds(nanoseconds))
-        .ok_or_else(|| BurregoError::BuiltinError {
-            name: "time.date".to_string(),
-            message: "overflow when building date".to_string(),
-        })?
-        .with_timezone(&timezone);
+        res
+    }
 
-    Ok(serde_json::json!([dt.year(), dt.month(), dt.day(),]))
-}
+    /// Returns the layout element at the beginning of the given string,
+    /// with its length
+    fn element(rest: &str) -> Option<(usize, LayoutElement<'static>)> {
+        let elements = [
+            ("January", Item::Fixed(Fixed::LongMonthName)),
+            ("Jan", Item::Fixed(Fixed::ShortMonthName)),
+            ("Monday", Item::Fixed(Fixed::LongWeekdayName)),
+            ("Mon", Item::Fixed(Fixed::ShortWeekdayName)),
+            ("MST", Item::Fixed(Fixed::TimezoneName)),
+            ("2006", Item::Numeric(Numeric::Year, Pad::Zero)),
+            ("002", Item::Numeric(Numeric::Ordinal, Pad::Zero)),
+            ("01", Item::Numeric(Numeric::Month, Pad::Zero)),
+            ("02", Item::Numeric(Numeric::Day, Pad::Zero)),
+            ("03", Item::Numeric(Numeric::Hour12, Pad::Zero)),
+            ("04", Item::Numeric(Numeric::Minute, Pad::Zero)),
+            ("05", Item::Numeric(Numeric::Second, Pad::Zero)),
+            ("06", Item::Numeric(Numeric::YearMod100, Pad::Zero)),
+            ("_2", Item::Numeric(Numeric::Day, Pad::Space)),
+            ("15", Item::Numeric(Numeric::Hour, Pad::Zero)),
+            ("1", Item::Numeric(Numeric::Month, Pad::None)),
+            ("2", Item::Numeric(Numeric::Day, Pad::None)),
+            ("3", Item::Numeric(Numeric::Hour12, Pad::None)),
+            ("4", Item::Numeric(Numeric::Minute, Pad::None)),
+            ("5", Item::Numeric(Numeric::Second, Pad::None)),
+            ("PM", Item::Fixed(Fixed::UpperAmPm)),
+            ("pm", Item::Fixed(Fixed::LowerAmPm)),
+            ("Z07:00", Item::Fixed(Fixed::TimezoneOffsetColonZ)),
+            ("Z0700", Item::Fixed(Fixed::TimezoneOffsetZ)),
+            ("-07:00", Item::Fixed(Fixed::TimezoneOffsetColon)),
+            ("-0700", Item::Fixed(Fixed::TimezoneOffset)),
+        ];
+        for (chunk, item) in elements {
+            if rest.starts_with(chunk) {
+                return Some((chunk.len(), LayoutElement::Item(item)));
+            }
+        }
 
-pub fn date_local(ns: i64) -> Result<serde_json::Value> {
-    let dt = DateTime::UNIX_EPOCH
-        .checked_add_signed(Duration::nanoseconds(ns))
-        .ok_or_else(|| BurregoError::BuiltinError {
-            name: "time.date".to_string(),
-            message: "overflow when building date".to_string(),
-        })?
-        .with_timezone(&chrono::Local);
+        let mut chars = rest.chars();
+        let separator = chars.next().filter(|c| *c == '.' || *c == ',')?;
+        let digit = chars.next().filter(|c| *c == '0' || *c == '9')?;
+        let digits = (1 + chars.take_while(|c| *c == digit).count()).min(9);
+        if rest[1 + digits..].starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        Some((
+            1 + digits,
+            LayoutElement::Fraction {
+                separator,
+                digits,
+                trim: digit == '9',
+            },
+        ))
+    }
 
-    Ok(serde_json::json!([dt.year(), dt.month(), dt.day(),]))
+    /// The chrono items used to parse a value, which accept any number of
+    /// fractional digits
+    fn parse_items(&self) -> Vec<Item<'a>> {
+        self.elements
+            .iter()
+            .map(|element| match element {
+                LayoutElement::Item(item) => item.clone(),
+                LayoutElement::Fraction { .. } => Item::Fixed(Fixed::Nanosecond),
+            })
+            .collect()
+    }
+
+    fn format<Tz: TimeZone>(&self, dt: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: Display,
+    {
+        let mut res = String::new();
+        for element in &self.elements {
+            match element {
+                LayoutElement::Item(item) => {
+                    res.push_str(&dt.format_with_items(std::iter::once(item)).to_string())
+                }
+                LayoutElement::Fraction {
+                    separator,
+                    digits,
+                    trim,
+                } => {
+                    let nanoseconds = format!("{:09}", dt.nanosecond() % 1_000_000_000);
+                    let mut fraction = &nanoseconds[..*digits];
+                    if *trim {
+                        fraction = fraction.trim_end_matches('0');
+                    }
+                    if !fraction.is_empty() {
+                        res.push(*separator);
+                        res.push_str(fraction);
+                    }
+                }
+            }
+        }
+        res
+    }
 }
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -164,10 +686,9 @@ mod test {
 
     #[test]
     fn date_with_local_tz() {
-        let input_dt = Local::now().naive_utc();
+        let input_dt = Local::now();
 
-        let args: Vec<serde_json::Value> =
-            vec![json!([input_dt.and_utc().timestamp_nanos_opt(), "Local"])];
+        let args: Vec<serde_json::Value> = vec![json!([input_dt.timestamp_nanos_opt(), "Local"])];
 
         let actual = date(&args);
         assert!(actual.is_ok());
@@ -176,4 +697,153 @@ mod test {
             actual.unwrap()
         );
     }
+
+    // 2022-07-31T10:22:40Z
+    const NS: i64 = 1659262960000000000;
+
+    #[test]
+    fn now_ns_is_stable_during_an_evaluation() {
+        let (first, second) = crate::builtins::evaluation::with_evaluation(None, || {
+            let first = now_ns(&[]).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            (first, now_ns(&[]).unwrap())
+        });
+        assert_eq!(first, second);
+
+        let after = now_ns(&[]).unwrap();
+        assert!(after.as_i64().unwrap() > first.as_i64().unwrap());
+    }
+
+    #[test]
+    fn parse_with_layout() -> Result<()> {
+        for (layout, value, expected) in [
+            ("2006-01-02", "2022-07-31", 1659225600000000000),
+            ("2006-01-02T15:04:05Z07:00", "2022-07-31T10:22:40Z", NS),
+            ("2006-01-02T15:04:05Z07:00", "2022-07-31T12:22:40+02:00", NS),
+            ("02 Jan 06 15:04:05 -0700", "31 Jul 22 10:22:40 +0000", NS),
+            ("Jan _2 2006 3:04:05PM", "Jul 31 2022 10:22:40AM", NS),
+            (
+                "January 2, 2006 15:04:05.000",
+                "July 31, 2022 10:22:40.000",
+                NS,
+            ),
+            ("2006-002", "2022-212", 1659225600000000000),
+        ] {
+            assert_eq!(
+                parse_ns(&[json!(layout), json!(value)])?,
+                json!(expected),
+                "{layout} {value}"
+            );
+        }
+
+        // the date defaults to year 0, which doesn't fit into the nanoseconds
+        assert!(parse_ns(&[json!("15:04"), json!("00:01")]).is_err());
+        assert!(parse_ns(&[json!("2006-01-02"), json!("31/07/2022")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_duration() -> Result<()> {
+        for (value, expected) in [
+            ("0", 0i64),
+            ("1h30m", 5_400_000_000_000),
+            ("1.5h", 5_400_000_000_000),
+            ("-2m3.4s", -123_400_000_000),
+            ("+300ms", 300_000_000),
+            ("1µs", 1_000),
+            ("1us", 1_000),
+            ("10ns", 10),
+            (".5s", 500_000_000),
+        ] {
+            assert_eq!(
+                parse_duration_ns(&[json!(value)])?,
+                json!(expected),
+                "{value}"
+            );
+        }
+
+        for value in ["", "1", "1x", ".s", "h", "1h-1m", "9999999999999999h"] {
+            assert!(parse_duration_ns(&[json!(value)]).is_err(), "{value}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_time() -> Result<()> {
+        for (input, expected) in [
+            (json!(NS), "2022-07-31T10:22:40Z"),
+            (json!(NS + 500_000_000), "2022-07-31T10:22:40.5Z"),
+            (json!([NS, "Europe/Rome"]), "2022-07-31T12:22:40+02:00"),
+            (
+                json!([NS, "Europe/Rome", "Mon Jan _2 15:04:05 MST 2006"]),
+                "Sun Jul 31 12:22:40 CEST 2022",
+            ),
+            (json!([NS, "UTC", "Kitchen"]), "10:22AM"),
+            (json!([NS, "", "02/01/2006"]), "31/07/2022"),
+            (json!([NS, "UTC", "Monday, 002"]), "Sunday, 212"),
+        ] {
+            assert_eq!(
+                format(std::slice::from_ref(&input))?,
+                json!(expected),
+                "{input}"
+            );
+        }
+
+        assert!(format(&[json!([NS, "Mars/Olympus_Mons"])]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn clock_and_weekday() -> Result<()> {
+        assert_eq!(clock(&[json!(NS)])?, json!([10, 22, 40]));
+        assert_eq!(clock(&[json!([NS, "Asia/Tokyo"])])?, json!([19, 22, 40]));
+        assert_eq!(weekday(&[json!(NS)])?, json!("Sunday"));
+        assert_eq!(
+            weekday(&[json!([NS, "Pacific/Kiritimati"])])?,
+            json!("Monday")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_to_date() -> Result<()> {
+        for (input, years, months, days, expected) in [
+            (NS, 0, 0, 0, NS),
+            // 2022-01-31 plus one month is normalized to 2022-03-03
+            (1643624560000000000i64, 0, 1, 0, 1646302960000000000i64),
+            (1643624560000000000, 0, 0, -31, 1640946160000000000),
+            // 2024-02-29 plus one year is normalized to 2025-03-01
+            (1709200800000000000, 1, 0, 0, 1740823200000000000),
+        ] {
+            assert_eq!(
+                add_date(&[json!(input), json!(years), json!(months), json!(days)])?,
+                json!(expected)
+            );
+        }
+
+        assert!(add_date(&[json!(NS), json!(1000), json!(0), json!(0)]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_times() -> Result<()> {
+        // 1978-11-22T14:30:00Z
+        let earlier = 280593000000000000i64;
+        assert_eq!(
+            diff(&[json!(NS), json!(earlier)])?,
+            json!([43, 8, 8, 19, 52, 40])
+        );
+        assert_eq!(
+            diff(&[json!(earlier), json!(NS)])?,
+            json!([43, 8, 8, 19, 52, 40])
+        );
+        assert_eq!(diff(&[json!(NS), json!(NS)])?, json!([0, 0, 0, 0, 0, 0]));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,185 @@
+use crate::errors::{BurregoError, Result};
+
+/// Parses strings like `10G`, `5K`, `4Mi` or `1500m` into a number. Like OPA,
+/// `m` and `M` are case-sensitive, to distinguish milli from mega, all the
+/// other units are case-insensitive.
+pub fn parse(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    let (amount, unit) = amount_and_unit("units.parse", args)?;
+
+    if unit == "m" {
+        return to_json("units.parse", amount / 1e3);
+    }
+
+    let multiplier = match unit.to_lowercase().as_str() {
+        "" => 1.0,
+        "k" => 1e3,
+        "ki" => 1024_f64,
+        "m" => 1e6,
+        "mi" => 1024_f64.powi(2),
+        "g" => 1e9,
+        "gi" => 1024_f64.powi(3),
+        "t" => 1e12,
+        "ti" => 1024_f64.powi(4),
+        "p" => 1e15,
+        "pi" => 1024_f64.powi(5),
+        "e" => 1e18,
+        "ei" => 1024_f64.powi(6),
+        _ => return Err(unknown_unit("units.parse", &unit)),
+    };
+
+    to_json("units.parse", amount * multiplier)
+}
+
+/// Parses strings like `10GB`, `5K` or `4mib` into an integer number of
+/// bytes. The units are case-insensitive and the trailing `b` is optional.
+pub fn parse_bytes(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    let (amount, unit) = amount_and_unit("units.parse_bytes", args)?;
+
+    let lowercase_unit = unit.to_lowercase();
+    let multiplier = match lowercase_unit
+        .strip_suffix('b')
+        .unwrap_or(lowercase_unit.as_str())
+    {
+        "" => 1.0,
+        "k" => 1e3,
+        "ki" => 1024_f64,
+        "m" => 1e6,
+        "mi" => 1024_f64.powi(2),
+        "g" => 1e9,
+        "gi" => 1024_f64.powi(3),
+        "t" => 1e12,
+        "ti" => 1024_f64.powi(4),
+        "p" => 1e15,
+        "pi" => 1024_f64.powi(5),
+        "e" => 1e18,
+        "ei" => 1024_f64.powi(6),
+        _ => return Err(unknown_unit("units.parse_bytes", &unit)),
+    };
+
+    to_json("units.parse_bytes", (amount * multiplier).trunc())
+}
+
+/// Splits the input into the amount and its unit
+fn amount_and_unit(name: &str, args: &[serde_json::Value]) -> Result<(f64, String)> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let input = args[0].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: "1st parameter is not a string".to_string(),
+    })?;
+
+    // OPA ignores the quotes, to cope with values like "\"10Mi\""
+    let input = input.replace('"', "");
+    if input.contains(' ') {
+        return Err(BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "spaces not allowed in resource strings".to_string(),
+        });
+    }
+
+    let unit_start = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(unit_start);
+    if amount.is_empty() {
+        return Err(BurregoError::BuiltinError {
+            name: name.to_string(),
+            message: "no amount provided".to_string(),
+        });
+    }
+
+    let amount: f64 = amount.parse().map_err(|_| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: "could not parse amount to a number".to_string(),
+    })?;
+
+    Ok((amount, unit.to_string()))
+}
+
+fn unknown_unit(name: &str, unit: &str) -> BurregoError {
+    BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: format!("unknown unit: {unit}"),
+    }
+}
+
+/// Integral values are rendered as JSON integers
+fn to_json(name: &str, value: f64) -> Result<serde_json::Value> {
+    if value.fract() == 0.0 && value.abs() < u64::MAX as f64 {
+        return Ok(serde_json::Value::from(value as u64));
+    }
+
+    serde_json::to_value(value).map_err(|e| BurregoError::BuiltinError {
+        name: name.to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_units() -> Result<()> {
+        for (input, expected) in [
+            ("0", json!(0)),
+            ("10", json!(10)),
+            ("1K", json!(1000)),
+            ("1k", json!(1000)),
+            ("1Ki", json!(1024)),
+            ("1KI", json!(1024)),
+            ("1.5M", json!(1500000)),
+            ("1Mi", json!(1048576)),
+            ("200m", json!(0.2)),
+            ("1500m", json!(1.5)),
+            ("12G", json!(12000000000u64)),
+            ("2Gi", json!(2147483648u64)),
+            ("1T", json!(1000000000000u64)),
+            ("1Ei", json!(1152921504606846976u64)),
+            ("\"10K\"", json!(10000)),
+        ] {
+            assert_eq!(parse(&[json!(input)])?, expected, "{input}");
+        }
+
+        for input in ["", "K", "1 K", "1KX", "1.2.3K", "-1K"] {
+            assert!(parse(&[json!(input)]).is_err(), "{input}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_units_bytes() -> Result<()> {
+        for (input, expected) in [
+            ("10", 10u64),
+            ("1b", 1),
+            ("1KB", 1000),
+            ("1kb", 1000),
+            ("1K", 1000),
+            ("1KiB", 1024),
+            ("1kib", 1024),
+            ("1Ki", 1024),
+            ("2mb", 2000000),
+            ("512Mi", 536870912),
+            ("1.5KB", 1500),
+            ("1.5KiB", 1536),
+            ("1Gi", 1073741824),
+            ("1GiB", 1073741824),
+            ("0.1b", 0),
+        ] {
+            assert_eq!(parse_bytes(&[json!(input)])?, json!(expected), "{input}");
+        }
+
+        for input in ["foo", "1 KB", "1XB", "KB", "1bb"] {
+            assert!(parse_bytes(&[json!(input)]).is_err(), "{input}");
+        }
+
+        Ok(())
+    }
+}
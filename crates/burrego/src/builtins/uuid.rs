@@ -0,0 +1,77 @@
+use crate::builtins::evaluation::random_bytes;
+use crate::errors::{BurregoError, Result};
+
+/// Returns a version 4 UUID. During an evaluation the same UUID is returned
+/// for the same key.
+pub fn rfc4122(args: &[serde_json::Value]) -> Result<serde_json::Value> {
+    if args.len() != 1 {
+        return Err(BurregoError::BuiltinError {
+            name: "uuid.rfc4122".to_string(),
+            message: "wrong number of arguments".to_string(),
+        });
+    }
+
+    let key = args[0].as_str().ok_or_else(|| BurregoError::BuiltinError {
+        name: "uuid.rfc4122".to_string(),
+        message: "1st parameter is not a string".to_string(),
+    })?;
+
+    let mut bytes: [u8; 16] = random_bytes("uuid.rfc4122", key)[..16]
+        .try_into()
+        .expect("slice of 16 bytes");
+    // version 4, variant RFC 4122
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    let uuid = format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    );
+
+    serde_json::to_value(uuid).map_err(|e| BurregoError::BuiltinError {
+        name: "uuid.rfc4122".to_string(),
+        message: format!("cannot convert value into JSON: {e:?}"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::builtins::evaluation::with_evaluation;
+    use serde_json::json;
+
+    #[test]
+    fn rfc4122_format() -> Result<()> {
+        let uuid = rfc4122(&[json!("key")])?;
+        let re = regex::Regex::new(
+            "^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$",
+        )
+        .unwrap();
+        assert!(re.is_match(uuid.as_str().unwrap()), "{uuid}");
+
+        assert!(rfc4122(&[json!(1)]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rfc4122_is_stable_during_an_evaluation() -> Result<()> {
+        let (first, second, other) = with_evaluation(None, || {
+            (
+                rfc4122(&[json!("a")]),
+                rfc4122(&[json!("a")]),
+                rfc4122(&[json!("b")]),
+            )
+        });
+        let first = first?;
+        assert_eq!(first, second?);
+        assert_ne!(first, other?);
+
+        Ok(())
+    }
+}
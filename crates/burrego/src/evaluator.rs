@@ -5,7 +5,7 @@ use tracing::debug;
 use wasmtime::{Engine, Instance, Linker, Memory, MemoryType, Module, Store};
 
 use crate::{
-    builtins,
+    builtins::{self, BuiltinsReport, CustomBuiltins, PolicyMessage},
     errors::{BurregoError, Result},
     host_callbacks::HostCallbacks,
     opa_host_functions,
@@ -29,6 +29,14 @@ struct EvaluatorStack {
     policy: Policy,
 }
 
+/// The result of an evaluation, together with the messages emitted by the
+/// policy while being evaluated
+#[derive(Debug)]
+pub struct EvaluationOutput {
+    pub result: Result<serde_json::Value>,
+    pub messages: Vec<PolicyMessage>,
+}
+
 pub struct Evaluator {
     engine: Engine,
     module: Module,
@@ -37,12 +45,16 @@ pub struct Evaluator {
     memory: Memory,
     policy: Policy,
     host_callbacks: HostCallbacks,
+    custom_builtins: CustomBuiltins,
     /// used to tune the [epoch
     /// interruption](https://docs.rs/wasmtime/latest/wasmtime/struct.Config.html#method.epoch_interruption)
     /// feature of wasmtime
     epoch_deadline: Option<u64>,
     entrypoints: HashMap<String, i32>,
     used_builtins: HashSet<String>,
+    /// seed of the random builtins, like `rand.intn`. When not set, a random
+    /// seed is used by each evaluation
+    random_seed: Option<u64>,
 }
 
 impl Evaluator {
@@ -50,12 +62,14 @@ impl Evaluator {
         engine: Engine,
         module: Module,
         host_callbacks: HostCallbacks,
+        custom_builtins: CustomBuiltins,
         epoch_deadline: Option<u64>,
     ) -> Result<Evaluator> {
         let stack = Self::setup(
             engine.clone(),
             module.clone(),
             host_callbacks.clone(),
+            custom_builtins.clone(),
             epoch_deadline,
         )?;
         let mut store = stack.store;
@@ -63,14 +77,7 @@ impl Evaluator {
         let memory = stack.memory;
         let policy = stack.policy;
 
-        let used_builtins: HashSet<String> =
-            set_epoch_deadline_and_call_guest!(epoch_deadline, store, {
-                policy
-                    .builtins(&mut store, &memory)?
-                    .keys()
-                    .cloned()
-                    .collect()
-            });
+        let used_builtins = Self::used_builtins(&mut store, &memory, &policy, epoch_deadline)?;
 
         let entrypoints = set_epoch_deadline_and_call_guest!(epoch_deadline, store, {
             policy.entrypoints(&mut store, &memory)
@@ -89,9 +96,11 @@ impl Evaluator {
             memory,
             policy,
             host_callbacks,
+            custom_builtins,
             epoch_deadline,
             entrypoints,
             used_builtins,
+            random_seed: None,
         };
 
         let not_implemented_builtins = evaluator.not_implemented_builtins()?;
@@ -104,10 +113,52 @@ impl Evaluator {
         Ok(evaluator)
     }
 
+    /// Reports the builtins used by the policy. Unlike
+    /// `from_engine_and_module`, this doesn't fail when some of them are not
+    /// implemented
+    pub(crate) fn builtins_report_from_engine_and_module(
+        engine: Engine,
+        module: Module,
+        host_callbacks: HostCallbacks,
+        custom_builtins: CustomBuiltins,
+        epoch_deadline: Option<u64>,
+    ) -> Result<BuiltinsReport> {
+        let mut stack = Self::setup(
+            engine,
+            module,
+            host_callbacks,
+            custom_builtins.clone(),
+            epoch_deadline,
+        )?;
+        let used_builtins = Self::used_builtins(
+            &mut stack.store,
+            &stack.memory,
+            &stack.policy,
+            epoch_deadline,
+        )?;
+
+        Ok(BuiltinsReport::with_custom_builtins(
+            used_builtins,
+            &custom_builtins,
+        ))
+    }
+
+    fn used_builtins(
+        store: &mut Store<Option<StackHelper>>,
+        memory: &Memory,
+        policy: &Policy,
+        epoch_deadline: Option<u64>,
+    ) -> Result<HashSet<String>> {
+        Ok(set_epoch_deadline_and_call_guest!(epoch_deadline, store, {
+            policy.builtins(&mut *store, memory)?.into_keys().collect()
+        }))
+    }
+
     fn setup(
         engine: Engine,
         module: Module,
         host_callbacks: HostCallbacks,
+        custom_builtins: CustomBuiltins,
         epoch_deadline: Option<u64>,
     ) -> Result<EvaluatorStack> {
         let mut linker = Linker::<Option<StackHelper>>::new(&engine);
@@ -144,6 +195,7 @@ impl Evaluator {
             &mut store,
             host_callbacks.opa_abort,
             host_callbacks.opa_println,
+            custom_builtins,
         )?;
         let policy = Policy::new(&instance, &mut store, &memory)?;
         _ = store.data_mut().insert(stack_helper);
@@ -161,6 +213,7 @@ impl Evaluator {
             self.engine.clone(),
             self.module.clone(),
             self.host_callbacks.clone(),
+            self.custom_builtins.clone(),
             self.epoch_deadline,
         )?;
         self.store = stack.store;
@@ -201,6 +254,7 @@ impl Evaluator {
         let supported_builtins: HashSet<String> = builtins::get_builtins()
             .keys()
             .map(|v| String::from(*v))
+            .chain(self.custom_builtins.keys().cloned())
             .collect();
         Ok(self
             .used_builtins
@@ -209,6 +263,17 @@ impl Evaluator {
             .collect())
     }
 
+    /// Reports the builtins used by the policy, the custom ones included
+    pub fn builtins_report(&self) -> BuiltinsReport {
+        BuiltinsReport::with_custom_builtins(self.used_builtins.clone(), &self.custom_builtins)
+    }
+
+    /// Makes the random builtins, like `rand.intn` and `uuid.rfc4122`,
+    /// deterministic: all the evaluations are seeded with the given value
+    pub fn set_random_seed(&mut self, seed: Option<u64>) {
+        self.random_seed = seed;
+    }
+
     pub fn entrypoint_id(&mut self, entrypoint: &str) -> Result<i32> {
         self.entrypoints
             .iter()
@@ -235,6 +300,34 @@ impl Evaluator {
         entrypoint_id: i32,
         input: &serde_json::Value,
         data: &[u8],
+    ) -> Result<serde_json::Value> {
+        self.evaluate_with_messages(entrypoint_id, input, data)
+            .result
+    }
+
+    /// Like `evaluate`, but the messages emitted by the policy through
+    /// `opa_println` and the `trace` builtin are returned too. The messages
+    /// emitted before a failure are returned together with the error.
+    pub fn evaluate_with_messages(
+        &mut self,
+        entrypoint_id: i32,
+        input: &serde_json::Value,
+        data: &[u8],
+    ) -> EvaluationOutput {
+        builtins::with_evaluation(self.random_seed, || {
+            let result = self.evaluate_entrypoint(entrypoint_id, input, data);
+            EvaluationOutput {
+                result,
+                messages: builtins::take_messages(),
+            }
+        })
+    }
+
+    fn evaluate_entrypoint(
+        &mut self,
+        entrypoint_id: i32,
+        input: &serde_json::Value,
+        data: &[u8],
     ) -> Result<serde_json::Value> {
         set_epoch_deadline_and_call_guest!(self.epoch_deadline, self.store, {
             if !self.has_entrypoint(entrypoint_id) {
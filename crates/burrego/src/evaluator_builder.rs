@@ -2,7 +2,10 @@ use crate::errors::{BurregoError, Result};
 use std::path::{Path, PathBuf};
 use wasmtime::{Engine, Module};
 
-use crate::{Evaluator, host_callbacks::HostCallbacks};
+use crate::{
+    BuiltinsReport, CustomBuiltin, CustomBuiltins, Evaluator, builtins::get_builtins,
+    host_callbacks::HostCallbacks,
+};
 
 #[derive(Default)]
 pub struct EvaluatorBuilder {
@@ -11,6 +14,8 @@ pub struct EvaluatorBuilder {
     engine: Option<Engine>,
     epoch_deadline: Option<u64>,
     host_callbacks: Option<HostCallbacks>,
+    custom_builtins: CustomBuiltins,
+    random_seed: Option<u64>,
 }
 
 impl EvaluatorBuilder {
@@ -44,6 +49,30 @@ impl EvaluatorBuilder {
         self
     }
 
+    /// Registers a builtin that is not part of the OPA standard library.
+    /// The policy must have been built with an OPA capabilities file that
+    /// declares it
+    #[must_use]
+    pub fn custom_builtin(mut self, name: &str, builtin: CustomBuiltin) -> Self {
+        self.custom_builtins.insert(name.to_string(), builtin);
+        self
+    }
+
+    /// Registers the given builtins, see `custom_builtin`
+    #[must_use]
+    pub fn custom_builtins(mut self, builtins: CustomBuiltins) -> Self {
+        self.custom_builtins.extend(builtins);
+        self
+    }
+
+    /// Seeds the random builtins, like `rand.intn` and `uuid.rfc4122`, to
+    /// get reproducible results
+    #[must_use]
+    pub fn random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+
     fn validate(&self) -> Result<()> {
         if self.policy_path.is_some() && self.module.is_some() {
             return Err(BurregoError::EvaluatorBuilderError(
@@ -62,10 +91,49 @@ impl EvaluatorBuilder {
             ));
         }
 
+        let builtins = get_builtins();
+        if let Some(name) = self
+            .custom_builtins
+            .keys()
+            .find(|name| builtins.contains_key(name.as_str()))
+        {
+            return Err(BurregoError::EvaluatorBuilderError(format!(
+                "custom builtin {name} cannot replace the one provided by burrego"
+            )));
+        }
+
         Ok(())
     }
 
     pub fn build(&self) -> Result<Evaluator> {
+        let (engine, module, host_callbacks) = self.engine_module_and_callbacks()?;
+        let mut evaluator = Evaluator::from_engine_and_module(
+            engine,
+            module,
+            host_callbacks,
+            self.custom_builtins.clone(),
+            self.epoch_deadline,
+        )?;
+        evaluator.set_random_seed(self.random_seed);
+
+        Ok(evaluator)
+    }
+
+    /// Reports the builtins used by the policy, and the ones that are not
+    /// implemented by burrego. Unlike `build`, this doesn't fail when the
+    /// policy uses unsupported builtins.
+    pub fn builtins_report(&self) -> Result<BuiltinsReport> {
+        let (engine, module, host_callbacks) = self.engine_module_and_callbacks()?;
+        Evaluator::builtins_report_from_engine_and_module(
+            engine,
+            module,
+            host_callbacks,
+            self.custom_builtins.clone(),
+            self.epoch_deadline,
+        )
+    }
+
+    fn engine_module_and_callbacks(&self) -> Result<(Engine, Module, HostCallbacks)> {
         self.validate()?;
 
         let engine = match &self.engine {
@@ -97,6 +165,45 @@ impl EvaluatorBuilder {
             .clone()
             .expect("host callbacks should be set");
 
-        Evaluator::from_engine_and_module(engine, module, host_callbacks, self.epoch_deadline)
+        Ok((engine, module, host_callbacks))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn custom_builtins_cannot_replace_burrego_ones() {
+        let builder = EvaluatorBuilder::default()
+            .policy_path(Path::new("policy.wasm"))
+            .host_callbacks(HostCallbacks::default())
+            .custom_builtin(
+                "sprintf",
+                Arc::new(|_: &[serde_json::Value]| Ok(serde_json::Value::Null)),
+            );
+
+        assert!(matches!(
+            builder.validate(),
+            Err(BurregoError::EvaluatorBuilderError(_))
+        ));
+    }
+
+    #[test]
+    fn custom_builtins_are_reported_as_supported() {
+        let custom_builtins = CustomBuiltins::from([(
+            "example.echo".to_string(),
+            Arc::new(|_: &[serde_json::Value]| Ok(serde_json::Value::Null)) as CustomBuiltin,
+        )]);
+        let used_builtins = ["example.echo", "sprintf", "example.missing"].map(String::from);
+
+        let report = BuiltinsReport::with_custom_builtins(used_builtins, &custom_builtins);
+
+        assert_eq!(
+            report.supported,
+            ["example.echo", "sprintf"].map(String::from).into()
+        );
+        assert_eq!(report.unsupported, ["example.missing".to_string()].into());
     }
 }
@@ -7,7 +7,7 @@ mod opa_host_functions;
 mod policy;
 mod stack_helper;
 
-pub use builtins::get_builtins;
-pub use evaluator::Evaluator;
+pub use builtins::{BuiltinsReport, CustomBuiltin, CustomBuiltins, PolicyMessage, get_builtins};
+pub use evaluator::{EvaluationOutput, Evaluator};
 pub use evaluator_builder::EvaluatorBuilder;
 pub use host_callbacks::HostCallbacks;
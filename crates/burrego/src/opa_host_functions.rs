@@ -2,7 +2,7 @@ use crate::errors::{BurregoError, Result};
 use tracing::{debug, error};
 use wasmtime::{AsContextMut, Caller, Linker};
 
-use crate::builtins::BUILTINS_HELPER;
+use crate::builtins::{self, BUILTINS_HELPER};
 use crate::stack_helper::StackHelper;
 
 /// Add OPA host callbacks to the linker.
@@ -67,6 +67,7 @@ fn register_opa_println_func(
                     |data| String::from_utf8(data).unwrap_or_else(|e| format!("cannot decode opa_println message: didn't read a valid string from memory - {e:?}")),
                 );
             opa_println_host_callback(&msg);
+            builtins::record_message(builtins::PolicyMessage::Println(msg));
 
             Ok(())
         },
@@ -98,6 +99,7 @@ fn register_opa_builtin0_func(
                     error!(builtin_id, builtins =? stack_helper.builtins, "opa_builtin0: cannot find builtin");
                     BurregoError::BuiltinNotImplementedError(format!("opa_builtin0: cannot find builtin {builtin_id}"))
                 })?.clone();
+            let custom_builtin = stack_helper.custom_builtins.get(&builtin_name).cloned();
             let args = vec![];
 
             let memory_export = caller.get_export("memory").ok_or_else(|| BurregoError::RegoWasmError("cannot find 'memory' export".to_string()))?;
@@ -108,7 +110,7 @@ fn register_opa_builtin0_func(
                 .map_err(|e| BurregoError::RegoWasmError(format!("Cannot access global builtin helper: {e:?}")))?;
 
             let builtin_result = builtin_helper
-                .invoke(&builtin_name, &args)?;
+                .invoke(&builtin_name, custom_builtin.as_ref(), &args)?;
 
             let addr = StackHelper::push_json(
                 caller.as_context_mut(),
@@ -151,6 +153,7 @@ fn register_opa_builtin1_func(
                     BurregoError::BuiltinNotImplementedError(
                     format!("opa_bunltin1: cannot find builtin {builtin_id}"))
                 })?.clone();
+            let custom_builtin = stack_helper.custom_builtins.get(&builtin_name).cloned();
 
             let memory_export = caller.get_export("memory").ok_or_else(|| BurregoError::RegoWasmError("cannot find 'memory' export".to_string()))?;
             let memory = memory_export.into_memory().ok_or_else(|| BurregoError::RegoWasmError("'memory' export cannot be converted into a memory object".to_string()))?;
@@ -165,7 +168,7 @@ fn register_opa_builtin1_func(
                 .map_err(|e| BurregoError::RegoWasmError(format!("Cannot access global builtin helper: {e:?}")))?;
 
             let builtin_result = builtin_helper
-                .invoke(&builtin_name, &args)?;
+                .invoke(&builtin_name, custom_builtin.as_ref(), &args)?;
 
             let addr = StackHelper::push_json(
                 caller.as_context_mut(),
@@ -209,6 +212,7 @@ fn register_opa_builtin2_func(
                     error!(builtin_id, builtins =? stack_helper.builtins, "opa_builtin0: cannot find builtin");
                     BurregoError::BuiltinNotImplementedError(format!("opa_builtin2: cannot find builtin {builtin_id}"))
                 })?.clone();
+            let custom_builtin = stack_helper.custom_builtins.get(&builtin_name).cloned();
 
             let memory_export = caller.get_export("memory").ok_or_else(|| BurregoError::RegoWasmError("cannot find 'memory' export".to_string()))?;
             let memory = memory_export.into_memory().ok_or_else(|| BurregoError::RegoWasmError("'memory' export cannot be converted into a memory object".to_string()))?;
@@ -224,7 +228,7 @@ fn register_opa_builtin2_func(
                 .read()
                 .map_err(|e| BurregoError::RegoWasmError(format!("Cannot access global builtin helper: {e:?}")))?;
 
-            let builtin_result = builtin_helper.invoke(&builtin_name, &args)?;
+            let builtin_result = builtin_helper.invoke(&builtin_name, custom_builtin.as_ref(), &args)?;
 
             let addr = StackHelper::push_json(
                 caller.as_context_mut(),
@@ -269,6 +273,7 @@ fn register_opa_builtin3_func(
                     error!(builtin_id, builtins =? stack_helper.builtins, "opa_builtin0: cannot find builtin");
                     BurregoError::BuiltinNotImplementedError(format!("opa_builtin3: cannot find builtin {builtin_id}"))
                 })?.clone();
+            let custom_builtin = stack_helper.custom_builtins.get(&builtin_name).cloned();
 
             let memory_export = caller.get_export("memory").ok_or_else(|| BurregoError::RegoWasmError("cannot find 'memory' export".to_string()))?;
             let memory = memory_export.into_memory().ok_or_else(|| BurregoError::RegoWasmError("'memory' export cannot be converted into a memory object".to_string()))?;
@@ -286,7 +291,7 @@ fn register_opa_builtin3_func(
                 .read()
                 .map_err(|e| BurregoError::RegoWasmError(format!("Cannot access global builtin helper: {e:?}")))?;
 
-            let builtin_result = builtin_helper.invoke(&builtin_name, &args)?;
+            let builtin_result = builtin_helper.invoke(&builtin_name, custom_builtin.as_ref(), &args)?;
 
             let addr = StackHelper::push_json(
                 caller.as_context_mut(),
@@ -332,6 +337,7 @@ fn register_opa_builtin4_func(
                     error!(builtin_id, builtins =? stack_helper.builtins, "opa_builtin0: cannot find builtin");
                     BurregoError::BuiltinNotImplementedError(format!("opa_builtin4: cannot find builtin {builtin_id}"))
                 })?.clone();
+            let custom_builtin = stack_helper.custom_builtins.get(&builtin_name).cloned();
 
             let memory_export = caller.get_export("memory").ok_or_else(|| BurregoError::RegoWasmError("cannot find 'memory' export".to_string()))?;
             let memory = memory_export.into_memory().ok_or_else(|| BurregoError::RegoWasmError("'memory' export cannot be converted into a memory object".to_string()))?;
@@ -351,7 +357,7 @@ fn register_opa_builtin4_func(
                 .read()
                 .map_err(|e| BurregoError::RegoWasmError(format!("Cannot access global builtin helper: {e:?}")))?;
 
-            let builtin_result = builtin_helper.invoke(&builtin_name, &args)?;
+            let builtin_result = builtin_helper.invoke(&builtin_name, custom_builtin.as_ref(), &args)?;
 
             let addr = StackHelper::push_json(
                 caller.as_context_mut(),
@@ -1,3 +1,4 @@
+use crate::builtins::CustomBuiltins;
 use crate::errors::{BurregoError, Result};
 use crate::host_callbacks;
 
@@ -17,6 +18,7 @@ pub(crate) struct StackHelper {
     pub(crate) opa_println_host_callback: host_callbacks::HostCallback,
 
     pub(crate) builtins: HashMap<i32, String>,
+    pub(crate) custom_builtins: CustomBuiltins,
 }
 
 impl StackHelper {
@@ -26,6 +28,7 @@ impl StackHelper {
         mut store: impl AsContextMut,
         opa_abort_host_callback: host_callbacks::HostCallback,
         opa_println_host_callback: host_callbacks::HostCallback,
+        custom_builtins: CustomBuiltins,
     ) -> Result<StackHelper> {
         let opa_json_dump_fn = instance
             .get_typed_func::<i32, i32>(store.as_context_mut(), "opa_json_dump")
@@ -73,6 +76,7 @@ impl StackHelper {
             opa_malloc_fn,
             opa_json_parse_fn,
             builtins,
+            custom_builtins,
             opa_abort_host_callback,
             opa_println_host_callback,
         })
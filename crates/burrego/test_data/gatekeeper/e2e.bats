@@ -1,17 +1,21 @@
 #!/usr/bin/env bats
 
+kwctl() {
+  cargo run --quiet --package kwctl -- "$@"
+}
+
 @test "[accept in namespace]: valid namespace" {
-  run cargo run --example  cli -- -v eval policy.wasm --input-path request-valid.json
+  run kwctl rego eval policy.wasm --input-path request-valid.json --output json
   # this prints the output when one the checks below fails
   echo "output = ${output}"
 
   # request accepted
   [ "$status" -eq 0 ]
-  [ $(expr "$output" : '.*"result":.*\[\]') -ne 0 ]
+  [ $(expr "$output" : '.*"result": \[\]') -ne 0 ]
 }
 
 @test "[accept in namespace]: not valid namespace" {
-  run cargo run --example  cli -- -v eval policy.wasm --input-path request-not-valid.json
+  run kwctl rego eval policy.wasm --input-path request-not-valid.json --output json
   # this prints the output when one the checks below fails
   echo "output = ${output}"
 
@@ -0,0 +1,393 @@
+//! Runs the test cases of the OPA builtins, stored inside of
+//! `tests/builtins`, against the builtins provided by burrego. The cases
+//! vendored from OPA, inside of `tests/builtins/opa`, are run too.
+//! See `tests/builtins/README.md` for the format of the cases.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use burrego::get_builtins;
+use regex::Regex;
+use serde_json::Value;
+
+const CASES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/builtins");
+const NOT_IMPLEMENTED_FILE: &str = "not_implemented.yaml";
+const OPA_CASES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/builtins/opa/testdata");
+const OPA_SKIPPED_FILE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/builtins/opa/skipped.yaml"
+);
+
+fn read_yaml(path: &Path) -> Value {
+    let contents =
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {}: {e}", path.display()));
+    serde_yaml::from_str(&contents)
+        .unwrap_or_else(|e| panic!("cannot parse {}: {e}", path.display()))
+}
+
+fn not_implemented_builtins() -> BTreeSet<String> {
+    read_yaml(&Path::new(CASES_DIR).join(NOT_IMPLEMENTED_FILE))["not_implemented"]
+        .as_array()
+        .expect("not_implemented should be a list")
+        .iter()
+        .map(|builtin| {
+            builtin
+                .as_str()
+                .expect("builtin names should be strings")
+                .to_string()
+        })
+        .collect()
+}
+
+/// The outcome expected from the invocation of a builtin
+enum Expectation {
+    Result(Value),
+    Match(Regex),
+    Error,
+}
+
+impl Expectation {
+    /// Returns whether the builtin behaved as expected, together with a
+    /// description of its outcome
+    fn check<E: std::fmt::Display>(&self, result: Result<Value, E>) -> (bool, String) {
+        match result {
+            Ok(result) => {
+                // strings are matched against their value, the other
+                // results against their JSON encoding
+                let outcome = result
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| result.to_string());
+                let passed = match self {
+                    Expectation::Result(want_result) => want_result == &result,
+                    Expectation::Match(want_match) => want_match.is_match(&outcome),
+                    Expectation::Error => false,
+                };
+                (passed, outcome)
+            }
+            Err(e) => (matches!(self, Expectation::Error), format!("error: {e}")),
+        }
+    }
+}
+
+fn case_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(CASES_DIR)
+        .expect("cannot read the directory of the cases")
+        .map(|entry| entry.expect("cannot read directory entry").path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "yaml")
+                && path
+                    .file_name()
+                    .is_some_and(|name| name != NOT_IMPLEMENTED_FILE)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn builtin_cases() {
+    let builtins = get_builtins();
+    let not_implemented = not_implemented_builtins();
+
+    let mut executed = 0;
+    let mut failures = Vec::new();
+    for path in case_files() {
+        let file = path.file_name().unwrap().to_string_lossy().to_string();
+        let cases = read_yaml(&path);
+        let cases = cases["cases"]
+            .as_array()
+            .unwrap_or_else(|| panic!("{file}: cases should be a list"));
+
+        for case in cases {
+            let note = case["note"].as_str().unwrap_or("unnamed case");
+            let builtin = case["builtin"]
+                .as_str()
+                .unwrap_or_else(|| panic!("{file}: {note}: builtin should be a string"));
+            if not_implemented.contains(builtin) {
+                continue;
+            }
+            let Some(function) = builtins.get(builtin) else {
+                failures.push(format!(
+                    "{file}: {note}: {builtin} is neither implemented nor listed inside of {NOT_IMPLEMENTED_FILE}"
+                ));
+                continue;
+            };
+
+            let args = case["args"].as_array().cloned().unwrap_or_default();
+            let expectation = if case["want_error"].as_bool().unwrap_or(false) {
+                Expectation::Error
+            } else if let Some(pattern) = case["want_match"].as_str() {
+                Expectation::Match(
+                    Regex::new(pattern)
+                        .unwrap_or_else(|e| panic!("{file}: {note}: invalid want_match: {e}")),
+                )
+            } else {
+                Expectation::Result(case.get("want_result").cloned().unwrap_or_else(|| {
+                    panic!("{file}: {note}: want_result, want_match or want_error should be set")
+                }))
+            };
+            let (passed, outcome) = expectation.check(function(&args));
+            executed += 1;
+
+            match (passed, case["known_issue"].as_str()) {
+                (false, None) => failures.push(format!("{file}: {note}: got {outcome}")),
+                (true, Some(known_issue)) => failures.push(format!(
+                    "{file}: {note}: the case passes, remove its known issue: {known_issue}"
+                )),
+                _ => {}
+            }
+        }
+    }
+
+    assert!(executed > 0, "no case has been executed");
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// An OPA case that can be run as the invocation of a single builtin
+struct OpaCall {
+    builtin: String,
+    args: Vec<Value>,
+    expectation: Expectation,
+}
+
+static OPA_QUERY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^data\.([A-Za-z0-9_.]+)\.([A-Za-z_][A-Za-z0-9_]*)\s*=\s*([A-Za-z_][A-Za-z0-9_]*)$")
+        .unwrap()
+});
+
+/// `p := builtin(args)`
+static OPA_CALL_RULE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)\s*:?=\s*([A-Za-z_][A-Za-z0-9_.]*)\((.*)\)$").unwrap()
+});
+
+/// `p := x if { x := builtin(args) }`
+static OPA_CALL_BODY_RULE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^([A-Za-z_][A-Za-z0-9_]*)\s*:?=\s*([A-Za-z_][A-Za-z0-9_]*)\s*(?:if\s*)?\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*:?=\s*([A-Za-z_][A-Za-z0-9_.]*)\((.*)\)\s*\}$",
+    )
+    .unwrap()
+});
+
+/// Turns an OPA case into the invocation of a single builtin. That's
+/// possible when its only module defines a rule assigned the result of a
+/// builtin invoked with JSON literals, and its query reads that rule.
+/// Otherwise, the reason why the case cannot be run is returned.
+fn opa_single_builtin_call(case: &Value) -> Result<OpaCall, String> {
+    let module = match case["modules"].as_array().map(Vec::as_slice) {
+        Some([module]) => module.as_str().ok_or("the module is not a string")?,
+        _ => return Err("the case doesn't have a single module".to_string()),
+    };
+
+    let mut package = None;
+    let mut rule_lines = Vec::new();
+    for line in module.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with("import ") {
+            continue;
+        }
+        match line.strip_prefix("package ") {
+            Some(name) => package = Some(name.trim()),
+            None => rule_lines.push(line),
+        }
+    }
+    let package = package.ok_or("the module has no package")?;
+    let rule = rule_lines.join(" ");
+
+    let (rule_name, builtin, args) = if let Some(captures) = OPA_CALL_RULE.captures(&rule) {
+        (
+            captures[1].to_string(),
+            captures[2].to_string(),
+            captures[3].to_string(),
+        )
+    } else if let Some(captures) = OPA_CALL_BODY_RULE.captures(&rule)
+        && captures[2] == captures[3]
+    {
+        (
+            captures[1].to_string(),
+            captures[4].to_string(),
+            captures[5].to_string(),
+        )
+    } else {
+        return Err("the module is not a single rule invoking a builtin".to_string());
+    };
+    let args: Vec<Value> = serde_json::from_str(&format!("[{args}]"))
+        .map_err(|_| "the arguments of the builtin are not JSON literals".to_string())?;
+
+    let query = case["query"].as_str().ok_or("the case has no query")?;
+    let captures = OPA_QUERY
+        .captures(query.trim())
+        .filter(|captures| captures[1] == *package && captures[2] == rule_name)
+        .ok_or("the query doesn't read the rule invoking the builtin")?;
+    let var = &captures[3];
+
+    let expectation = if case.get("want_error").is_some() || case.get("want_error_code").is_some() {
+        Expectation::Error
+    } else {
+        match case["want_result"].as_array().map(Vec::as_slice) {
+            // the rule is undefined when the builtin fails
+            Some([]) => Expectation::Error,
+            Some([bindings]) => Expectation::Result(
+                bindings
+                    .get(var)
+                    .cloned()
+                    .ok_or("the result doesn't bind the variable of the query")?,
+            ),
+            _ => return Err("the case doesn't expect a single result".to_string()),
+        }
+    };
+
+    Ok(OpaCall {
+        builtin,
+        args,
+        expectation,
+    })
+}
+
+/// Returns whether the modules of the OPA case invoke one of the given
+/// builtins
+fn opa_case_invokes<'a>(case: &Value, mut builtins: impl Iterator<Item = &'a str>) -> bool {
+    let modules: Vec<&str> = case["modules"]
+        .as_array()
+        .map(|modules| modules.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    builtins.any(|builtin| {
+        let invocation = format!("{builtin}(");
+        modules.iter().any(|module| {
+            module.match_indices(&invocation).any(|(start, _)| {
+                !module[..start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+            })
+        })
+    })
+}
+
+/// The OPA cases that are skipped, indexed by the path of their file and
+/// by their note, with the reason why they are skipped
+fn opa_skipped_cases() -> BTreeMap<(String, String), String> {
+    read_yaml(Path::new(OPA_SKIPPED_FILE))["skipped"]
+        .as_array()
+        .expect("skipped should be a list")
+        .iter()
+        .map(|entry| {
+            let field = |name: &str| {
+                entry[name]
+                    .as_str()
+                    .unwrap_or_else(|| panic!("{OPA_SKIPPED_FILE}: {name} should be a string"))
+                    .to_string()
+            };
+            ((field("file"), field("note")), field("reason"))
+        })
+        .collect()
+}
+
+fn opa_case_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).expect("cannot read the directory of the OPA cases") {
+        let path = entry.expect("cannot read directory entry").path();
+        if path.is_dir() {
+            opa_case_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "yaml") {
+            files.push(path);
+        }
+    }
+}
+
+// The OPA cases have not been vendored yet: once `tests/builtins/opa/vendor.sh`
+// has been run and `opa/skipped.yaml` updated, this test must be enabled.
+#[test]
+#[ignore]
+fn opa_builtin_cases() {
+    let opa_cases_dir = Path::new(OPA_CASES_DIR);
+    assert!(
+        opa_cases_dir.is_dir(),
+        "the OPA cases are not vendored, run tests/builtins/opa/vendor.sh to vendor them"
+    );
+
+    let builtins = get_builtins();
+    let mut skipped = opa_skipped_cases();
+    let mut files = Vec::new();
+    opa_case_files(opa_cases_dir, &mut files);
+    files.sort();
+
+    let mut executed = 0;
+    let mut failures = Vec::new();
+    for path in files {
+        let file = path
+            .strip_prefix(opa_cases_dir)
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let cases = read_yaml(&path);
+        let Some(cases) = cases["cases"].as_array() else {
+            continue;
+        };
+
+        for case in cases {
+            let note = case["note"].as_str().unwrap_or("unnamed case");
+            let skip_reason = skipped.remove(&(file.clone(), note.to_string()));
+
+            let call = match opa_single_builtin_call(case) {
+                Ok(call) => call,
+                Err(reason) => {
+                    // the cases invoking only builtins compiled into the
+                    // Wasm module, or not implemented yet, don't matter
+                    if skip_reason.is_none() && opa_case_invokes(case, builtins.keys().copied()) {
+                        failures.push(format!(
+                            "{file}: {note}: {reason}, list the case inside of {OPA_SKIPPED_FILE}"
+                        ));
+                    }
+                    continue;
+                }
+            };
+            let Some(function) = builtins.get(call.builtin.as_str()) else {
+                if skip_reason.is_some() {
+                    failures.push(format!(
+                        "{file}: {note}: {} is not provided by burrego, remove the case from {OPA_SKIPPED_FILE}",
+                        call.builtin
+                    ));
+                }
+                continue;
+            };
+
+            let (passed, outcome) = call.expectation.check(function(&call.args));
+            executed += 1;
+
+            match (passed, skip_reason) {
+                (false, None) => failures.push(format!("{file}: {note}: got {outcome}")),
+                (true, Some(reason)) => failures.push(format!(
+                    "{file}: {note}: the case passes, remove it from {OPA_SKIPPED_FILE}: {reason}"
+                )),
+                _ => {}
+            }
+        }
+    }
+
+    failures.extend(skipped.into_keys().map(|(file, note)| {
+        format!("{file}: {note}: the case doesn't exist, remove it from {OPA_SKIPPED_FILE}")
+    }));
+
+    assert!(executed > 0, "no OPA case has been executed");
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn not_implemented_builtins_are_up_to_date() {
+    let builtins = get_builtins();
+
+    let implemented: Vec<String> = not_implemented_builtins()
+        .into_iter()
+        .filter(|builtin| builtins.contains_key(builtin.as_str()))
+        .collect();
+
+    assert!(
+        implemented.is_empty(),
+        "these builtins are implemented, remove them from {NOT_IMPLEMENTED_FILE}: {}",
+        implemented.join(", ")
+    );
+}
@@ -0,0 +1,56 @@
+#!/bin/bash
+# Vendors the OPA test cases of the builtins provided by burrego.
+#
+# Downloads the OPA sources at OPA_VERSION, and copies inside of
+# `testdata` the YAML files of `v1/test/cases/testdata/v1` that invoke at
+# least one of the builtins registered inside of `src/builtins/mod.rs`,
+# keeping their relative path. The OPA license is copied next to them.
+#
+# The previously vendored files are removed, hence the script must be
+# re-run after bumping OPA_VERSION or after registering a new builtin.
+# The cases that cannot be run by `tests/builtins.rs` must then be listed
+# inside of `skipped.yaml`.
+#
+# Usage:
+#   ./vendor.sh
+
+set -euo pipefail
+
+OPA_VERSION="v1.0.0"
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+BUILTINS_SRC="${SCRIPT_DIR}/../../../src/builtins/mod.rs"
+TESTDATA_DIR="${SCRIPT_DIR}/testdata"
+
+WORK_DIR="$(mktemp -d)"
+trap 'rm -rf "${WORK_DIR}"' EXIT
+
+curl --fail --silent --show-error --location \
+  "https://github.com/open-policy-agent/opa/archive/refs/tags/${OPA_VERSION}.tar.gz" |
+  tar --extract --gzip --directory "${WORK_DIR}" --strip-components 1
+
+# The builtin names are the string literals of `src/builtins/mod.rs`,
+# turned into a regular expression matching their invocations
+BUILTINS_PATTERN="$(
+  grep --only-matching --extended-regexp '"[a-z0-9_.]+"' "${BUILTINS_SRC}" |
+    tr --delete '"' |
+    sort --unique |
+    sed 's/\./\\./g' |
+    paste --serial --delimiters '|'
+)"
+
+rm -rf "${TESTDATA_DIR}"
+mkdir -p "${TESTDATA_DIR}"
+cp "${WORK_DIR}/LICENSE" "${SCRIPT_DIR}/LICENSE"
+
+cd "${WORK_DIR}/v1/test/cases/testdata/v1"
+find . -name '*.yaml' -print0 |
+  sort --zero-terminated |
+  while IFS= read -r -d '' file; do
+    if grep --quiet --extended-regexp "(^|[^a-z0-9_.])(${BUILTINS_PATTERN})\(" "${file}"; then
+      mkdir -p "${TESTDATA_DIR}/$(dirname "${file}")"
+      cp "${file}" "${TESTDATA_DIR}/${file}"
+    fi
+  done
+
+echo "vendored the OPA ${OPA_VERSION} test cases inside of ${TESTDATA_DIR}"
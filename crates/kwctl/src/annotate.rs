@@ -6,12 +6,13 @@ use std::{
 
 use anyhow::{Result, anyhow};
 use policy_evaluator::{
-    ProtocolVersion, constants::*, policy_metadata::Metadata, validator::Validate,
+    ProtocolVersion, burrego::BuiltinsReport, constants::*, policy_evaluator::PolicyExecutionMode,
+    policy_metadata::Metadata, validator::Validate,
 };
 use tracing::warn;
 
 use crate::{
-    backend::{Backend, BackendDetector},
+    backend::{Backend, BackendDetector, rego_builtins_report},
     wasm_scanner,
 };
 
@@ -37,7 +38,20 @@ pub(crate) fn write_annotation(
         wasm_scanner::scan(&module).map_err(|e| anyhow!("Error scanning wasm module: {}", e))?;
 
     let backend_detector = BackendDetector::default();
-    let metadata = prepare_metadata(wasm_path, metadata_path, backend_detector, usage.as_deref())?;
+    let metadata = prepare_metadata(
+        wasm_path.clone(),
+        metadata_path,
+        backend_detector,
+        usage.as_deref(),
+    )?;
+
+    if matches!(
+        metadata.execution_mode,
+        PolicyExecutionMode::Opa | PolicyExecutionMode::OpaGatekeeper
+    ) {
+        warn_on_unsupported_builtins(&rego_builtins_report(&wasm_path)?);
+    }
+
     write_annotated_wasm_file(&mut module, destination, metadata, &detected_capabilities)
 }
 
@@ -55,7 +69,7 @@ fn prepare_metadata(
     let backend = backend_detector.detect(wasm_path, &metadata)?;
 
     match backend {
-        Backend::Opa | Backend::OpaGatekeeper | Backend::Wasi => {
+        Backend::Opa | Backend::OpaGatekeeper | Backend::Wasi | Backend::GatekeeperMutation => {
             metadata.protocol_version = Some(ProtocolVersion::Unknown)
         }
         Backend::KubewardenWapc(protocol_version) => {
@@ -115,6 +129,15 @@ fn warn_on_capabilities_mismatch(
     }
 }
 
+fn warn_on_unsupported_builtins(builtins_report: &BuiltinsReport) {
+    if !builtins_report.is_compatible() {
+        warn!(
+            builtins = ?builtins_report.unsupported,
+            "the Rego policy uses builtins that are not supported by Kubewarden, its evaluation will fail"
+        );
+    }
+}
+
 fn write_annotated_wasm_file(
     module: &mut walrus::Module,
     output_path: PathBuf,
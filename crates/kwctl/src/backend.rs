@@ -1,11 +1,20 @@
 use anyhow::{Result, anyhow};
 use lazy_static::lazy_static;
 use policy_evaluator::{
-    ProtocolVersion, evaluation_context::EvaluationContext, policy_evaluator::PolicyExecutionMode,
-    policy_evaluator_builder::PolicyEvaluatorBuilder, policy_metadata::Metadata, wasmparser,
+    ProtocolVersion,
+    burrego::{BuiltinsReport, EvaluatorBuilder, HostCallbacks},
+    evaluation_context::EvaluationContext,
+    policy_evaluator::PolicyExecutionMode,
+    policy_evaluator_builder::PolicyEvaluatorBuilder,
+    policy_metadata::Metadata,
+    runtimes::kubewarden_rego_builtins,
+    wasmparser,
 };
 use semver::{BuildMetadata, Prerelease, Version};
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 lazy_static! {
     static ref KUBEWARDEN_VERSION: Version = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
@@ -15,6 +24,7 @@ pub(crate) enum Backend {
     Opa,
     OpaGatekeeper,
     Wasi,
+    GatekeeperMutation,
     KubewardenWapc(ProtocolVersion),
 }
 
@@ -96,6 +106,16 @@ impl BackendDetector {
         let is_rego_policy = self.is_rego_policy(&wasm_path)?;
         match metadata.execution_mode {
             PolicyExecutionMode::Wasi => Ok(Backend::Wasi),
+            PolicyExecutionMode::GatekeeperMutation => {
+                // the mutators are applied natively, the module is never evaluated
+                if is_rego_policy {
+                    Err(anyhow!(
+                        "Wrong value inside of policy's metadata for 'executionMode'. This policy has been created using Rego"
+                    ))
+                } else {
+                    Ok(Backend::GatekeeperMutation)
+                }
+            }
             PolicyExecutionMode::Opa => {
                 if is_rego_policy {
                     Ok(Backend::Opa)
@@ -131,6 +151,21 @@ impl BackendDetector {
     }
 }
 
+/// Reports the builtins required by a Rego policy, flagging the ones that are
+/// not implemented by the Kubewarden Rego runtime. This allows to find out
+/// whether a policy can be evaluated before deploying it.
+/// The Kubewarden builtins are reported as supported.
+pub(crate) fn rego_builtins_report(wasm_path: &Path) -> Result<BuiltinsReport> {
+    EvaluatorBuilder::default()
+        .policy_path(wasm_path)
+        .host_callbacks(HostCallbacks::default())
+        .custom_builtins(kubewarden_rego_builtins(Arc::new(
+            EvaluationContext::default(),
+        )))
+        .builtins_report()
+        .map_err(|e| anyhow!("cannot compute the builtins used by the Rego policy: {}", e))
+}
+
 /// Check if policy server version is compatible with  minimum kubewarden
 /// version required by the policy
 /// TODO: this should not take an Option
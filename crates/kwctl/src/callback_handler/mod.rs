@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
-use policy_evaluator::{callback_requests::CallbackRequest, kube};
+use policy_evaluator::{
+    callback_requests::CallbackRequest, host_capability_provider::HostCapabilityProviders, kube,
+};
 use tokio::sync::{mpsc, oneshot};
 
 mod proxy;
@@ -49,6 +51,15 @@ impl CallbackHandler {
         }
     }
 
+    /// The host capability providers registered on the handler. The proxy
+    /// does not register any provider.
+    pub fn host_capability_providers(&self) -> HostCapabilityProviders {
+        match self {
+            CallbackHandler::Direct(handler) => handler.host_capability_providers(),
+            CallbackHandler::Proxy(_) => HostCapabilityProviders::default(),
+        }
+    }
+
     pub async fn loop_eval(self) {
         match self {
             CallbackHandler::Direct(mut handler) => handler.loop_eval().await,
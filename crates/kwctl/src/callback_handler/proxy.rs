@@ -441,4 +441,33 @@ mod tests {
         let err = response.unwrap_err();
         assert_eq!(err.to_string(), expected_err_msg);
     }
+
+    #[test]
+    fn record_response_replay_host_capability_provider_response() {
+        let request = CallbackRequestType::HostCapabilityProvider {
+            namespace: "cmdb".to_string(),
+            operation: "v1/team_owner".to_string(),
+            payload: serde_json::json!({"namespace": "default", "replicas": 3}),
+        };
+        let expected_payload = r#"{"owner":"team-a"}"#.to_string();
+        let exchange = Exchange {
+            request: serde_yaml::to_string(&request).expect("cannot serialize request"),
+            response: Response::Success {
+                payload: expected_payload.clone(),
+            },
+        };
+
+        let mut exchanges: VecDeque<Exchange> = VecDeque::new();
+        exchanges.push_front(exchange);
+
+        let (response_tx, _) = oneshot::channel::<Result<CallbackResponse>>();
+        let request = CallbackRequest {
+            request,
+            response_channel: response_tx,
+        };
+
+        let response = CallbackHandlerProxy::produce_recorded_response(&request, &mut exchanges)
+            .expect("should not be an error");
+        assert_eq!(response.payload, expected_payload.into_bytes());
+    }
 }
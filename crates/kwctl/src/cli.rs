@@ -5,6 +5,7 @@ use clap::{
     crate_description, crate_name, crate_version, value_parser,
 };
 use lazy_static::lazy_static;
+use policy_evaluator::gatekeeper_mutation;
 
 pub(crate) mod bench;
 pub(crate) mod run;
@@ -112,11 +113,19 @@ fn pull_shared_flags() -> Vec<Arg> {
 
 fn subcommand_pull() -> Command {
     let mut args = pull_shared_flags();
-    args.extend_from_slice(&[Arg::new("output-path")
-        .short('o')
-        .long("output-path")
-        .value_name("PATH")
-        .help("Output file. If not provided will be downloaded to the Kubewarden store")]);
+    args.extend_from_slice(&[
+        Arg::new("output-path")
+            .short('o')
+            .long("output-path")
+            .value_name("PATH")
+            .help("Output file. If not provided will be downloaded to the Kubewarden store"),
+        Arg::new("fetch-mode")
+            .long("fetch-mode")
+            .value_name("FETCH_MODE")
+            .default_value("IfDigestChanged")
+            .value_parser(PossibleValuesParser::new(["IfNotPresent", "Always", "IfDigestChanged"]))
+            .help("When a policy that has already been pulled is pulled again. IfDigestChanged pulls the policy when its tag points to a different manifest inside of the registry"),
+    ]);
     args.sort_by(|a, b| a.get_id().cmp(b.get_id()));
     args.push(
         Arg::new("uri")
@@ -133,6 +142,11 @@ fn subcommand_pull() -> Command {
 
 fn subcommand_verify() -> Command {
     let mut args = vec![
+        Arg::new("bundle")
+            .long("bundle")
+            .value_parser(value_parser!(PathBuf))
+            .value_name("PATH")
+            .help("Sigstore bundle holding the signature of the policy. The policy is verified offline, it must be a local file or be inside of the store. A bundle named after a local policy, with the '.sigstore.json' or '.bundle' extension, is used automatically"),
         Arg::new("docker-config-json-path")
             .long("docker-config-json-path")
             .value_name("PATH")
@@ -190,7 +204,7 @@ fn subcommand_verify() -> Command {
         Arg::new("uri")
             .required(true)
             .index(1)
-            .help("Policy URI. Supported schemes: registry://"),
+            .help("Policy URI. Supported schemes: registry://, file://. Can also be the path to a local file or the SHA prefix of a policy in the store when a bundle is used"),
     );
 
     Command::new("verify")
@@ -317,7 +331,7 @@ fn run_args() -> Vec<Arg> {
            .long("execution-mode")
            .short('e')
            .value_name("MODE")
-           .value_parser(PossibleValuesParser::new(["opa","gatekeeper", "kubewarden", "wasi"]))
+           .value_parser(PossibleValuesParser::new(["opa","gatekeeper", "kubewarden", "wasi", "gatekeeper-mutation"]))
            .help("The runtime to use to execute this policy"),
        Arg::new("raw")
                .long("raw")
@@ -355,6 +369,12 @@ interactions with OCI registries, DNS, Kubernetes are performed."#),
             .num_args(0..)
             .default_values(["*"])
             .help("Host capabilities the policy is allowed to use. Use '*' to allow all. Can be repeated multiple times. Examples: 'oci/*', 'net/v1/dns_lookup_host'"),
+        Arg::new("allowed-http-endpoints")
+            .long("allowed-http-endpoints")
+            .action(ArgAction::Append)
+            .number_of_values(1)
+            .value_name("[METHODS=]URL_PREFIX")
+            .help("HTTP endpoint the policy is allowed to reach with the 'net/v1/http_request' host capability. Only GET requests are allowed unless a comma separated list of methods is given. Can be repeated multiple times. Examples: 'https://svc.corp/api', 'GET,POST=https://svc.corp/api'"),
      ]
 }
 
@@ -365,7 +385,7 @@ fn subcommand_run() -> Command {
         Arg::new("uri_or_sha_prefix_or_yaml_file")
             .required(true)
             .index(1)
-            .help("Policy URI, SHA prefix or YAML file containing Kubewarden policy resources. Supported schemes: registry://, https://, file://. If schema is omitted, file:// is assumed, rooted on the current directory.")
+            .help("Policy URI, SHA prefix or YAML file containing Kubewarden policy resources. Supported schemes: registry://, https://, file://. If schema is omitted, file:// is assumed, rooted on the current directory. Use builtin://gatekeeper-mutation to apply the Gatekeeper mutators defined inside of the settings.")
     );
 
     Command::new("run")
@@ -527,17 +547,39 @@ fn subcommand_scaffold() -> Command {
             .long("policy")
             .short('p')
             .required(true)
-            .value_name("VALIDATING-ADMISSION-POLICY.yaml")
-            .help("The file containing the ValidatingAdmissionPolicy definition"),
+            .value_name("ADMISSION-POLICY.yaml")
+            .help("The file containing the ValidatingAdmissionPolicy or MutatingAdmissionPolicy definition"),
         Arg::new("binding")
             .long("binding")
             .short('b')
             .required(true)
-            .value_name("VALIDATING-ADMISSION-POLICY-BINDING.yaml")
-            .help("The file containing the ValidatingAdmissionPolicyBinding definition"),
+            .value_name("ADMISSION-POLICY-BINDING.yaml")
+            .help("The file containing the ValidatingAdmissionPolicyBinding or MutatingAdmissionPolicyBinding definition"),
     ];
     vap_args.sort_by(|a, b| a.get_id().cmp(b.get_id()));
 
+    let mut gatekeeper_mutation_args = vec![
+        Arg::new("module")
+            .long("module")
+            .short('m')
+            .value_name("URI")
+            .default_value(gatekeeper_mutation::BUILTIN_MODULE)
+            .help("The policy module to use. The mutators are applied natively by the builtin module, other modules must be annotated with the 'gatekeeper-mutation' execution mode"),
+        Arg::new("name")
+            .long("name")
+            .value_name("NAME")
+            .default_value("gatekeeper-mutators")
+            .help("The name of the generated ClusterAdmissionPolicy"),
+    ];
+    gatekeeper_mutation_args.sort_by(|a, b| a.get_id().cmp(b.get_id()));
+    gatekeeper_mutation_args.push(
+        Arg::new("mutators")
+            .required(true)
+            .num_args(1..)
+            .value_name("MUTATORS.yaml")
+            .help("Files containing the Gatekeeper Assign, AssignMetadata, ModifySet or AssignImage definitions"),
+    );
+
     let mut admission_request_args = vec![
         Arg::new("operation")
             .long("operation")
@@ -567,8 +609,11 @@ fn subcommand_scaffold() -> Command {
             .about("Output a Kubernetes resource manifest")
             .args(manifest_args),
         Command::new("vap")
-            .about("Convert a Kubernetes `ValidatingAdmissionPolicy` into a Kubewarden `ClusterAdmissionPolicy`")
+            .about("Convert a Kubernetes `ValidatingAdmissionPolicy` or `MutatingAdmissionPolicy` into a Kubewarden `ClusterAdmissionPolicy`")
             .args(vap_args),
+        Command::new("gatekeeper-mutation")
+            .about("Convert Gatekeeper mutators into a mutating Kubewarden `ClusterAdmissionPolicy`")
+            .args(gatekeeper_mutation_args),
         Command::new("admission-request")
             .about("Scaffold an AdmissionRequest object")
             .args(admission_request_args),
@@ -634,7 +679,7 @@ fn subcommand_bench() -> Command {
         Arg::new("uri_or_sha_prefix_or_yaml_file")
             .required(true)
             .index(1)
-            .help("Policy URI, SHA prefix or YAML file containing Kubewarden policy resources. Supported schemes: registry://, https://, file://. If schema is omitted, file:// is assumed, rooted on the current directory.")
+            .help("Policy URI, SHA prefix or YAML file containing Kubewarden policy resources. Supported schemes: registry://, https://, file://. If schema is omitted, file:// is assumed, rooted on the current directory. Use builtin://gatekeeper-mutation to apply the Gatekeeper mutators defined inside of the settings.")
     );
 
     Command::new("bench")
@@ -657,14 +702,34 @@ fn subcommand_bench() -> Command {
 
 fn subcommand_save() -> Command {
     Command::new("save")
-        .about("save policies to a tar.gz file")
+        .about("save policies to a tar.gz file or to an OCI image layout")
         .arg(
             Arg::new("output")
                 .long("output")
                 .short('o')
                 .required(true)
                 .value_name("FILE")
-                .help("path where the file will be stored"),
+                .help("path where the file will be stored. When saving an OCI image layout, a tarball is created if the path ends with .tar, .tar.gz or .tgz, otherwise a directory"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value("tar.gz")
+                .value_parser(PossibleValuesParser::new(["tar.gz", "oci-layout"]))
+                .help("tar.gz saves the policies found inside of the store. oci-layout fetches the policies, together with their manifests, signatures and attestations, from their registry"),
+        )
+        .arg(
+            Arg::new("docker-config-json-path")
+                .long("docker-config-json-path")
+                .value_name("DOCKER_CONFIG")
+                .help("Path to a directory containing the Docker 'config.json' file. Can be used to indicate registry authentication details"),
+        )
+        .arg(
+            Arg::new("sources-path")
+                .long("sources-path")
+                .value_name("PATH")
+                .help("YAML file holding source information (https, registry insecure hosts, custom CA's...)"),
         )
         .arg(
             Arg::new("policies")
@@ -674,6 +739,72 @@ fn subcommand_save() -> Command {
         )
 }
 
+fn subcommand_rego() -> Command {
+    let mut args = vec![
+        Arg::new("input")
+            .long("input")
+            .short('i')
+            .value_name("JSON")
+            .conflicts_with("input-path")
+            .help("JSON string with the input of the policy"),
+        Arg::new("input-path")
+            .long("input-path")
+            .value_name("PATH")
+            .help("File containing the input of the policy in JSON format"),
+        Arg::new("data")
+            .long("data")
+            .short('d')
+            .value_name("JSON")
+            .conflicts_with("data-path")
+            .help("JSON string with the data of the policy"),
+        Arg::new("data-path")
+            .long("data-path")
+            .value_name("PATH")
+            .help("File containing the data of the policy in JSON format"),
+        Arg::new("entrypoint")
+            .long("entrypoint")
+            .short('e')
+            .action(ArgAction::Append)
+            .number_of_values(1)
+            .value_name("NAME_OR_ID")
+            .help("Entrypoint to evaluate, can be repeated. All the entrypoints are evaluated when not provided"),
+        Arg::new("list-entrypoints")
+            .long("list-entrypoints")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("entrypoint")
+            .help("List the entrypoints of the policy, without evaluating it"),
+        Arg::new("output")
+            .long("output")
+            .short('o')
+            .value_name("FORMAT")
+            .value_parser(PossibleValuesParser::new(["json"]))
+            .help("Output format"),
+    ];
+    args.sort_by(|a, b| a.get_id().cmp(b.get_id()));
+    args.push(
+        Arg::new("uri_or_sha_prefix")
+            .required(true)
+            .index(1)
+            .help("Policy URI or SHA prefix. Supported schemes: registry://, https://, file://. If schema is omitted, file:// is assumed, rooted on the current directory."),
+    );
+
+    Command::new("rego")
+        .about("Debug Rego policies")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("eval")
+                .about("Evaluate the entrypoints of a Rego policy, showing the messages printed by the policy")
+                .long_about(
+                    r#"Evaluate the entrypoints of a Rego policy compiled to WebAssembly, without a Kubernetes admission request.
+
+The OPA Wasm ABI version and the entrypoints of the policy are reported. The output of the `print` statements and of the `trace` builtin is shown next to the result of each evaluation.
+
+The evaluation is refused when the policy uses builtins not implemented by Kubewarden. The Kubewarden builtins reaching the host, like `kubewarden.oci.manifest_digest`, fail when invoked."#,
+                )
+                .args(args),
+        )
+}
+
 fn subcommand_docs() -> Command {
     Command::new("docs")
         .about("Generates the markdown documentation for kwctl commands")
@@ -689,7 +820,37 @@ fn subcommand_docs() -> Command {
 
 pub fn build_cli() -> Command {
     let mut subcommands = vec![
-        Command::new("policies").about("Lists all downloaded policies"),
+        Command::new("policies")
+            .about("Lists all downloaded policies")
+            .arg(
+                Arg::new("prune")
+                    .long("prune")
+                    .num_args(0)
+                    .help("Remove the modules not referenced by any policy before listing the policies"),
+            )
+            .arg(
+                Arg::new("outdated")
+                    .long("outdated")
+                    .num_args(0)
+                    .help("List only the policies pulled from a registry that have a newer digest or newer semver tags"),
+            )
+            .arg(
+                Arg::new("docker-config-json-path")
+                    .long("docker-config-json-path")
+                    .value_name("DOCKER_CONFIG")
+                    .requires("outdated")
+                    .help("Path to a directory containing the Docker 'config.json' file. Can be used to indicate registry authentication details"),
+            )
+            .arg(
+                Arg::new("sources-path")
+                    .long("sources-path")
+                    .value_name("PATH")
+                    .requires("outdated")
+                    .help("YAML file holding source information (https, registry insecure hosts, custom CA's...)"),
+            ),
+        Command::new("gc").about(
+            "Removes the modules not referenced by any policy from the store, together with the corrupted ones",
+        ),
         Command::new("info").about("Display system information"),
         Command::new("rm")
             .about("Removes a Kubewarden policy from the store")
@@ -717,12 +878,32 @@ pub fn build_cli() -> Command {
                     .help("Shell type"),
             ),
         Command::new("load")
-            .about("load policies from a tar.gz file")
+            .about("load policies from a tar.gz file or from an OCI image layout")
             .arg(
                 Arg::new("input")
                     .long("input")
                     .required(true)
-                    .help("load policies from tarball"),
+                    .help("load policies from tarball, or from an OCI image layout directory or tarball. The signatures and attestations of the OCI image layout are kept inside of the store, to verify the policies offline"),
+            )
+            .arg(
+                Arg::new("push-to-registry")
+                    .long("push-to-registry")
+                    .value_name("REGISTRY")
+                    .help("Push the artifacts of the OCI image layout, including signatures and attestations, to the given registry. Their repository and tag are kept"),
+            )
+            .arg(
+                Arg::new("docker-config-json-path")
+                    .long("docker-config-json-path")
+                    .value_name("DOCKER_CONFIG")
+                    .requires("push-to-registry")
+                    .help("Path to a directory containing the Docker 'config.json' file. Can be used to indicate registry authentication details"),
+            )
+            .arg(
+                Arg::new("sources-path")
+                    .long("sources-path")
+                    .value_name("PATH")
+                    .requires("push-to-registry")
+                    .help("YAML file holding source information (https, registry insecure hosts, custom CA's...)"),
             ),
         subcommand_pull(),
         subcommand_verify(),
@@ -734,6 +915,7 @@ pub fn build_cli() -> Command {
         subcommand_digest(),
         subcommand_bench(),
         subcommand_save(),
+        subcommand_rego(),
         subcommand_docs(),
     ];
     subcommands.sort_by(|a, b| a.get_name().cmp(b.get_name()));
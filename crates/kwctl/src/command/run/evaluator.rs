@@ -5,10 +5,11 @@ use policy_evaluator::{
     admission_request::AdmissionRequest,
     admission_response::AdmissionResponse,
     evaluation_context::EvaluationContext,
+    gatekeeper_mutation,
     host_capabilities::HostCapabilities,
     kube,
     kubewarden_policy_sdk::settings::SettingsValidationResponse,
-    policy_evaluator::{PolicyEvaluator, PolicySettings, ValidateRequest},
+    policy_evaluator::{PolicyEvaluator, PolicyExecutionMode, PolicySettings, ValidateRequest},
     policy_evaluator_builder::PolicyEvaluatorBuilder,
     policy_group_evaluator::evaluator::PolicyGroupEvaluator,
     policy_metadata::{ContextAwareResource, Metadata, PolicyType},
@@ -85,22 +86,12 @@ impl Evaluator {
                 settings,
                 ctx_aware_cfg,
                 allowed_host_capabilities,
+                allowed_http_endpoints,
                 ..
             } => {
                 let metadata = local_data.metadata(uri);
-
-                let execution_mode = match user_execution_cfg {
-                    PolicyExecutionConfiguration::UserDefined(mode) => mode.to_owned(),
-                    PolicyExecutionConfiguration::PolicyDefined => {
-                        let wasm_path = local_data.local_path(uri)?;
-                        determine_execution_mode(
-                            metadata,
-                            None,
-                            BackendDetector::default(),
-                            wasm_path,
-                        )?
-                    }
-                };
+                let policy_evaluator_builder =
+                    build_policy_evaluator_builder(uri, user_execution_cfg, local_data, cfg)?;
 
                 let context_aware_allowed_resources =
                     build_context_aware_allowed_resources(metadata, ctx_aware_cfg);
@@ -115,19 +106,17 @@ impl Evaluator {
                 )
                 .await?;
 
-                let mut policy_evaluator_builder = PolicyEvaluatorBuilder::new()
-                    .policy_file(local_data.local_path(uri)?)?
-                    .execution_mode(execution_mode);
-                if cfg.enable_wasmtime_cache {
-                    policy_evaluator_builder = policy_evaluator_builder.enable_wasmtime_cache();
-                }
                 let eval_ctx = EvaluationContext {
                     policy_id: uri.to_owned(),
                     callback_channel: Some(callback_handler.sender_channel()),
                     ctx_aware_resources_allow_list: context_aware_allowed_resources.clone(),
                     epoch_deadline: None,
-                    host_capabilities: HostCapabilities::new(allowed_host_capabilities)
-                        .map_err(|e| anyhow::anyhow!("Invalid host capabilities pattern: {e}"))?,
+                    host_capabilities: HostCapabilities::new_with_providers(
+                        allowed_host_capabilities,
+                        &callback_handler.host_capability_providers(),
+                    )
+                    .map_err(|e| anyhow::anyhow!("Invalid host capabilities pattern: {e}"))?,
+                    http_endpoints_allow_list: allowed_http_endpoints.clone(),
                 };
                 let policy_evaluator =
                     policy_evaluator_builder.build_pre()?.rehydrate(&eval_ctx)?;
@@ -147,6 +136,7 @@ impl Evaluator {
                 policy_members,
                 expression,
                 message,
+                allowed_host_capabilities,
                 ..
             } => {
                 let is_context_aware = policy_members
@@ -156,6 +146,12 @@ impl Evaluator {
                 let callback_handler =
                     build_callback_handler(is_context_aware, cfg, shutdown_channel_rx).await?;
 
+                let host_capabilities = HostCapabilities::new_with_providers(
+                    allowed_host_capabilities,
+                    &callback_handler.host_capability_providers(),
+                )
+                .map_err(|e| anyhow::anyhow!("Invalid host capabilities pattern: {e}"))?;
+
                 // group policies cannot be raw right now
                 let request = build_validate_request(&cfg.request, false)?;
 
@@ -167,32 +163,21 @@ impl Evaluator {
                 );
 
                 for (member_id, member) in policy_members {
-                    let metadata = local_data.metadata(&member.uri);
-
-                    let execution_mode = {
-                        let wasm_path = local_data.local_path(&member.uri)?;
-                        determine_execution_mode(
-                            metadata,
-                            None,
-                            BackendDetector::default(),
-                            wasm_path,
-                        )?
-                    };
-
-                    let mut policy_evaluator_builder = PolicyEvaluatorBuilder::new()
-                        .policy_file(local_data.local_path(&member.uri)?)?
-                        .execution_mode(execution_mode);
-
-                    if cfg.enable_wasmtime_cache {
-                        policy_evaluator_builder = policy_evaluator_builder.enable_wasmtime_cache();
-                    }
-
+                    let policy_evaluator_builder = build_policy_evaluator_builder(
+                        &member.uri,
+                        &PolicyExecutionConfiguration::PolicyDefined,
+                        local_data,
+                        cfg,
+                    )?;
                     let policy_evaluator_pre = Arc::new(policy_evaluator_builder.build_pre()?);
 
+                    let mut settings = member.settings.clone();
+                    settings.host_capabilities = host_capabilities.clone();
+
                     policy_group_evaluator.add_policy_member(
                         member_id,
                         policy_evaluator_pre,
-                        member.settings.clone(),
+                        settings,
                     );
                 }
 
@@ -244,6 +229,55 @@ impl Evaluator {
     }
 }
 
+/// Creates the `PolicyEvaluatorBuilder` of the given module. The Gatekeeper
+/// mutators are applied natively, there's no module to load for them.
+fn build_policy_evaluator_builder(
+    uri: &str,
+    execution_cfg: &PolicyExecutionConfiguration,
+    local_data: &LocalData,
+    cfg: &PullAndRunSettings,
+) -> Result<PolicyEvaluatorBuilder> {
+    if uri == gatekeeper_mutation::BUILTIN_MODULE {
+        if let PolicyExecutionConfiguration::UserDefined(mode) = execution_cfg
+            && *mode != PolicyExecutionMode::GatekeeperMutation
+        {
+            return Err(anyhow!(
+                "{uri} can only be run with the {} execution mode",
+                PolicyExecutionMode::GatekeeperMutation
+            ));
+        }
+        return Ok(
+            PolicyEvaluatorBuilder::new().execution_mode(PolicyExecutionMode::GatekeeperMutation)
+        );
+    }
+
+    let wasm_path = local_data.local_path(uri)?;
+    let execution_mode = match execution_cfg {
+        PolicyExecutionConfiguration::UserDefined(mode) => mode.to_owned(),
+        PolicyExecutionConfiguration::PolicyDefined => determine_execution_mode(
+            local_data.metadata(uri),
+            None,
+            BackendDetector::default(),
+            wasm_path,
+        )?,
+    };
+
+    let mut policy_evaluator_builder = PolicyEvaluatorBuilder::new()
+        .policy_file(wasm_path)?
+        .execution_mode(execution_mode);
+    if local_data
+        .metadata(uri)
+        .is_some_and(|metadata| !metadata.kubernetes_inventory())
+    {
+        policy_evaluator_builder = policy_evaluator_builder.skip_kubernetes_inventory();
+    }
+    if cfg.enable_wasmtime_cache {
+        policy_evaluator_builder = policy_evaluator_builder.enable_wasmtime_cache();
+    }
+
+    Ok(policy_evaluator_builder)
+}
+
 fn build_validate_request(
     request: &serde_json::Value,
     raw_request: bool,
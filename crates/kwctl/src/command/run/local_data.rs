@@ -1,7 +1,11 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use anyhow::{Result, anyhow};
-use policy_evaluator::{policy_fetcher::PullDestination, policy_metadata::Metadata};
+use policy_evaluator::{
+    gatekeeper_mutation,
+    policy_fetcher::{FetchMode, PullDestination},
+    policy_metadata::Metadata,
+};
 
 use crate::{
     backend::has_minimum_kubewarden_version,
@@ -22,7 +26,11 @@ impl LocalData {
         cfg: &PullAndRunSettings,
     ) -> Result<Self> {
         let local_paths = pull_all(policy_definitions, cfg).await?;
-        let modules_metadata = build_metadata(&local_paths)?;
+        let mut modules_metadata = build_metadata(&local_paths)?;
+        modules_metadata.insert(
+            gatekeeper_mutation::BUILTIN_MODULE.to_owned(),
+            gatekeeper_mutation::builtin_module_metadata(),
+        );
 
         Ok(Self {
             local_paths,
@@ -52,23 +60,33 @@ async fn pull_all(
 
     for policy_definition in policy_definitions {
         for uri in policy_definition.uris() {
-            if local_paths.contains_key(&uri) {
+            // the Gatekeeper mutators are applied natively, there's nothing to pull
+            if local_paths.contains_key(&uri) || uri == gatekeeper_mutation::BUILTIN_MODULE {
                 continue;
             }
-            let policy = pull::pull(&uri, sources, PullDestination::MainStore).await?;
+            let policy = pull::pull(
+                &uri,
+                sources,
+                PullDestination::MainStore,
+                FetchMode::default(),
+            )
+            .await?;
 
             if let Some(digests) = cfg.verified_manifest_digests.as_ref() {
                 let digest = digests
                     .get(&uri)
                     .ok_or_else(|| anyhow!("No digest found for {}", uri))?;
 
-                verify::verify_local_checksum(
-                    &policy,
-                    sources,
-                    digest,
-                    cfg.sigstore_trust_root.clone(),
-                )
-                .await?
+                // policies allowed to be unsigned have no verified digest
+                if let Some(digest) = digest {
+                    verify::verify_local_checksum(
+                        &policy,
+                        sources,
+                        digest,
+                        cfg.sigstore_trust_root.clone(),
+                    )
+                    .await?
+                }
             }
 
             local_paths.insert(uri, policy.local_path);
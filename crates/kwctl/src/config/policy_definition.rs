@@ -9,7 +9,7 @@ use clap::ArgMatches;
 use k8s_openapi::api::core::v1::ObjectReference;
 use policy_evaluator::{
     admission_response_handler::{policy_id::PolicyID, policy_mode::PolicyMode},
-    host_capabilities::HostCapabilities,
+    http_endpoint::HttpEndpoint,
     kubewarden_policy_sdk::crd::policies::{
         AdmissionPolicy, AdmissionPolicyGroup, ClusterAdmissionPolicy, ClusterAdmissionPolicyGroup,
     },
@@ -49,6 +49,9 @@ pub(crate) enum PolicyDefinition {
         // The list of host capabilities the policy is allowed to use.
         // An empty list means no host capabilities are allowed.
         allowed_host_capabilities: Vec<String>,
+        // The list of HTTP endpoints the policy is allowed to reach.
+        // An empty list means no HTTP request can be made.
+        allowed_http_endpoints: BTreeSet<HttpEndpoint>,
     },
     /// This is a group of policies. This can be defined only by providing a Kubewarden CRD
     /// file.
@@ -59,6 +62,9 @@ pub(crate) enum PolicyDefinition {
         policy_members: HashMap<String, PolicyMember>,
         expression: String,
         message: String,
+        // The list of host capabilities all the members of the group are
+        // allowed to use. An empty list means no host capabilities are allowed.
+        allowed_host_capabilities: Vec<String>,
     },
 }
 
@@ -169,6 +175,7 @@ impl TryFrom<AdmissionPolicy> for PolicyDefinition {
             settings,
             ctx_aware_cfg: ContextAwareConfiguration::NoAccess,
             allowed_host_capabilities: vec![],
+            allowed_http_endpoints: BTreeSet::new(),
         })
     }
 }
@@ -210,6 +217,7 @@ impl TryFrom<ClusterAdmissionPolicy> for PolicyDefinition {
             settings,
             ctx_aware_cfg: ContextAwareConfiguration::AllowList(ctx_aware_allow_list),
             allowed_host_capabilities: vec![],
+            allowed_http_endpoints: BTreeSet::new(),
         })
     }
 }
@@ -243,6 +251,7 @@ impl TryFrom<ClusterAdmissionPolicyGroup> for PolicyDefinition {
             policy_mode,
             expression: spec.expression.clone(),
             message: spec.message.clone(),
+            allowed_host_capabilities: vec![],
         })
     }
 }
@@ -276,6 +285,7 @@ impl TryFrom<AdmissionPolicyGroup> for PolicyDefinition {
             policy_mode,
             expression: spec.expression.clone(),
             message: spec.message.clone(),
+            allowed_host_capabilities: vec![],
         })
     }
 }
@@ -325,6 +335,7 @@ impl PolicyDefinition {
     pub fn from_yaml_file(
         yaml_path: &str,
         allowed_host_capabilities: &[String],
+        allowed_http_endpoints: &BTreeSet<HttpEndpoint>,
     ) -> Result<Vec<PolicyDefinition>> {
         let deserializer = serde_yaml::Deserializer::from_reader(
             std::fs::File::open(yaml_path)
@@ -338,22 +349,29 @@ impl PolicyDefinition {
                 .map_err(|e| anyhow!("Cannot parse YAML file {:?}: {}", yaml_path, e))?;
 
             let mut policy = PolicyDefinition::new(value_yaml)?;
-            // Overwrite the host capabilities with the value provided by the caller.
-            // For individual policies this sets the field directly. For group policies
-            // the same list is applied uniformly to all members, because host capabilities
-            // are not part of the Kubewarden CRD spec and must therefore come from the CLI.
-            let hc_allow_list = HostCapabilities::new(allowed_host_capabilities)
-                .map_err(|e| anyhow!("Invalid host capabilities pattern: {e}"))?;
+            // Overwrite the host capabilities and the HTTP endpoints with the values
+            // provided by the caller. For individual policies this sets the fields directly.
+            // For group policies the same lists are applied uniformly to all members, because
+            // they are not part of the Kubewarden CRD spec and must therefore come from the CLI.
+            // The host capabilities are validated when the policy is evaluated, against the
+            // host capability providers of the callback handler.
             match &mut policy {
                 PolicyDefinition::Policy {
                     allowed_host_capabilities: caps,
+                    allowed_http_endpoints: endpoints,
                     ..
                 } => {
                     *caps = allowed_host_capabilities.to_vec();
+                    *endpoints = allowed_http_endpoints.clone();
                 }
-                PolicyDefinition::PolicyGroup { policy_members, .. } => {
+                PolicyDefinition::PolicyGroup {
+                    policy_members,
+                    allowed_host_capabilities: caps,
+                    ..
+                } => {
+                    *caps = allowed_host_capabilities.to_vec();
                     for member in policy_members.values_mut() {
-                        member.settings.host_capabilities = hc_allow_list.clone();
+                        member.settings.http_endpoints_allow_list = allowed_http_endpoints.clone();
                     }
                 }
             }
@@ -428,6 +446,8 @@ impl PolicyDefinition {
             .cloned()
             .collect();
 
+        let allowed_http_endpoints = allowed_http_endpoints_from_cli(matches)?;
+
         Ok(PolicyDefinition::Policy {
             id: "policy-from-cli".to_string(),
             policy_mode,
@@ -439,6 +459,7 @@ impl PolicyDefinition {
             settings,
             ctx_aware_cfg,
             allowed_host_capabilities,
+            allowed_http_endpoints,
         })
     }
 
@@ -452,11 +473,58 @@ impl PolicyDefinition {
     }
 }
 
+/// Reads the HTTP endpoints granted to the policy via the
+/// `--allowed-http-endpoints` flag
+pub(crate) fn allowed_http_endpoints_from_cli(
+    matches: &ArgMatches,
+) -> Result<BTreeSet<HttpEndpoint>> {
+    matches
+        .get_many::<String>("allowed-http-endpoints")
+        .unwrap_or_default()
+        .map(|value| parse_http_endpoint(value))
+        .collect()
+}
+
+/// Parses an HTTP endpoint expressed as `[METHOD[,METHOD...]=]URL_PREFIX`.
+/// Only `GET` requests are allowed when no method is given
+fn parse_http_endpoint(value: &str) -> Result<HttpEndpoint> {
+    let (methods, url_prefix) = match value.split_once('=') {
+        Some((methods, url_prefix))
+            if !methods.is_empty()
+                && methods.chars().all(|c| c.is_ascii_alphabetic() || c == ',') =>
+        {
+            (
+                methods
+                    .split(',')
+                    .filter(|method| !method.is_empty())
+                    .map(str::to_uppercase)
+                    .collect(),
+                url_prefix,
+            )
+        }
+        _ => (BTreeSet::from(["GET".to_string()]), value),
+    };
+
+    let url_prefix =
+        url::Url::parse(url_prefix).map_err(|e| anyhow!("invalid HTTP endpoint {value:?}: {e}"))?;
+    if !matches!(url_prefix.scheme(), "http" | "https") {
+        return Err(anyhow!(
+            "invalid HTTP endpoint {value:?}: only http and https URLs are supported"
+        ));
+    }
+
+    Ok(HttpEndpoint {
+        url_prefix,
+        methods,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use k8s_openapi::apimachinery::pkg::runtime::RawExtension;
+    use policy_evaluator::host_capabilities::HostCapabilities;
     use policy_evaluator::kubewarden_policy_sdk::crd::policies::common::ContextAwareResource as ContextAwareResourceSdk;
     use policy_evaluator::kubewarden_policy_sdk::crd::policies::common::PolicyMode as PolicyModeSdk;
     use serde_json::json;
@@ -507,6 +575,7 @@ mod tests {
                 allowed_to_mutate,
                 custom_rejection_message,
                 allowed_host_capabilities,
+                allowed_http_endpoints,
             } => {
                 assert_eq!(id, name);
                 assert_eq!(uri, module_uri);
@@ -520,6 +589,7 @@ mod tests {
                 assert_eq!(custom_rejection_message, Some("foo".to_string()));
                 assert_eq!(settings, expected_settings);
                 assert!(matches!(ctx_aware_cfg, ContextAwareConfiguration::NoAccess));
+                assert!(allowed_http_endpoints.is_empty());
             }
             _ => panic!("Expected Individual PolicyDefinition"),
         }
@@ -594,6 +664,7 @@ mod tests {
                 allowed_to_mutate,
                 custom_rejection_message,
                 allowed_host_capabilities,
+                allowed_http_endpoints,
             } => {
                 assert_eq!(id, name);
                 assert_eq!(uri, module_uri);
@@ -610,6 +681,7 @@ mod tests {
                     ctx_aware_cfg,
                     ContextAwareConfiguration::AllowList(expected_context_aware_resources)
                 );
+                assert!(allowed_http_endpoints.is_empty());
             }
             _ => panic!("Expected Individual PolicyDefinition"),
         }
@@ -707,6 +779,7 @@ mod tests {
                             ctx_aware_resources_allow_list: pgm_1_expected_context_aware_resources,
                             epoch_deadline: None,
                             host_capabilities: HostCapabilities::DenyAll,
+                            http_endpoints_allow_list: Default::default(),
                         },
                     },
                 ),
@@ -720,6 +793,7 @@ mod tests {
                             ctx_aware_resources_allow_list: BTreeSet::new(),
                             epoch_deadline: None,
                             host_capabilities: HostCapabilities::DenyAll,
+                            http_endpoints_allow_list: Default::default(),
                         },
                     },
                 ),
@@ -727,6 +801,7 @@ mod tests {
             policy_mode: PolicyMode::Protect,
             expression,
             message,
+            allowed_host_capabilities: vec![],
         };
 
         assert_eq!(policy_definition, expected_policy_definition);
@@ -795,11 +870,13 @@ mod tests {
                 expression,
                 policy_mode,
                 message,
+                allowed_host_capabilities,
             } => {
                 assert_eq!(id, name);
                 assert_eq!(expression, expression);
                 assert_eq!(message, message);
                 assert_eq!(policy_mode, PolicyMode::Protect);
+                assert!(allowed_host_capabilities.is_empty());
 
                 assert_eq!(policy_members.len(), 2);
 
@@ -827,4 +904,34 @@ mod tests {
             _ => panic!("Expected Group PolicyDefinition"),
         }
     }
+
+    #[rstest::rstest]
+    #[case::only_url("https://svc.corp/api", "https://svc.corp/api", &["GET"])]
+    #[case::with_methods("get,POST=https://svc.corp/api", "https://svc.corp/api", &["GET", "POST"])]
+    #[case::url_with_query(
+        "https://svc.corp/api?ns=default",
+        "https://svc.corp/api?ns=default",
+        &["GET"]
+    )]
+    fn parse_allowed_http_endpoint(
+        #[case] value: &str,
+        #[case] expected_url_prefix: &str,
+        #[case] expected_methods: &[&str],
+    ) {
+        let endpoint = parse_http_endpoint(value).expect("cannot parse endpoint");
+
+        assert_eq!(endpoint.url_prefix.as_str(), expected_url_prefix);
+        assert_eq!(
+            endpoint.methods,
+            expected_methods.iter().map(|m| m.to_string()).collect()
+        );
+    }
+
+    #[rstest::rstest]
+    #[case::not_a_url("svc.corp/api")]
+    #[case::not_http("ftp://svc.corp/api")]
+    #[case::bad_url_after_methods("GET=svc.corp")]
+    fn parse_invalid_allowed_http_endpoint(#[case] value: &str) {
+        assert!(parse_http_endpoint(value).is_err());
+    }
 }
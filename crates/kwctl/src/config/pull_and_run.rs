@@ -9,9 +9,12 @@ use std::{
 
 use anyhow::{Result, anyhow};
 use clap::ArgMatches;
-use policy_evaluator::policy_fetcher::{
-    sigstore::trust::sigstore::SigstoreTrustRoot, sources::Sources,
-    verify::config::LatestVerificationConfig,
+use policy_evaluator::{
+    gatekeeper_mutation,
+    policy_fetcher::{
+        sigstore::trust::sigstore::SigstoreTrustRoot, sources::Sources,
+        verify::config::LatestVerificationConfig,
+    },
 };
 use tracing::{info, warn};
 
@@ -19,7 +22,7 @@ use crate::{
     callback_handler,
     config::{
         HostCapabilitiesMode,
-        policy_definition::PolicyDefinition,
+        policy_definition::{PolicyDefinition, allowed_http_endpoints_from_cli},
         sources::remote_server_options,
         verification::{build_sigstore_trust_root, build_verification_options},
     },
@@ -32,8 +35,9 @@ pub(crate) struct PullAndRunSettings {
     pub request: serde_json::Value,
     /// When verification is enabled, the map is populated with:
     /// - key: the policy URI
-    /// - value: the digest of the verified manifest
-    pub verified_manifest_digests: Option<HashMap<String, String>>,
+    /// - value: the digest of the verified manifest, `None` for the policies
+    ///   that are allowed to be unsigned
+    pub verified_manifest_digests: Option<HashMap<String, Option<String>>>,
     pub sigstore_trust_root: Option<Arc<SigstoreTrustRoot>>,
     pub enable_wasmtime_cache: bool,
     pub host_capabilities_mode: HostCapabilitiesMode,
@@ -64,7 +68,12 @@ pub(crate) fn parse_policy_definitions(matches: &ArgMatches) -> Result<Vec<Polic
             .cloned()
             .collect();
         // If the URI is a YAML file, parse it as a policy definition
-        return PolicyDefinition::from_yaml_file(uri, &allowed_host_capabilities);
+        let allowed_http_endpoints = allowed_http_endpoints_from_cli(matches)?;
+        return PolicyDefinition::from_yaml_file(
+            uri,
+            &allowed_host_capabilities,
+            &allowed_http_endpoints,
+        );
     }
 
     Ok(vec![PolicyDefinition::from_cli(matches)?])
@@ -180,7 +189,7 @@ async fn build_verified_manifest_digests(
     verification_options: &LatestVerificationConfig,
     sources: &Option<Sources>,
     sigstore_trust_root: Option<Arc<SigstoreTrustRoot>>,
-) -> Result<HashMap<String, String>> {
+) -> Result<HashMap<String, Option<String>>> {
     let mut uris: HashSet<String> = HashSet::new();
     for policy_definition in policy_definitions {
         uris = uris.union(&policy_definition.uris()).cloned().collect();
@@ -188,6 +197,9 @@ async fn build_verified_manifest_digests(
 
     let mut verified_manifest_digests = HashMap::new();
 
+    // the Gatekeeper mutators are applied natively, there's nothing to verify
+    uris.remove(gatekeeper_mutation::BUILTIN_MODULE);
+
     for uri in &uris {
         // verify policy prior to pulling if keys listed, and keep the
         // verified manifest digest:
@@ -198,7 +210,8 @@ async fn build_verified_manifest_digests(
             sigstore_trust_root.clone(),
         )
         .await
-        .map_err(|e| anyhow!("Policy {} cannot be validated\n{:?}", uri, e))?;
+        .map_err(|e| anyhow!("Policy {} cannot be validated\n{:?}", uri, e))?
+        .map(|report| report.manifest_digest);
         verified_manifest_digests.insert(uri.clone(), verified_manifest_digest);
     }
 
@@ -10,7 +10,9 @@ use clap::ArgMatches;
 use policy_evaluator::policy_fetcher::{
     sigstore::{self, trust::sigstore::SigstoreTrustRoot},
     store::DEFAULT_ROOT,
-    verify::config::{LatestVerificationConfig, Signature, Subject, read_verification_file},
+    verify::config::{
+        LatestVerificationConfig, Signature, SignatureRequirements, Subject, read_verification_file,
+    },
 };
 use sigstore_protobuf_specs::dev::sigstore::trustroot::v1::ClientTrustConfig;
 use tracing::{debug, info};
@@ -46,8 +48,8 @@ pub(crate) fn build_verification_options(
     }
 }
 
-/// Takes clap flags and builds a Some(LatestVerificationConfig) containing all
-/// passed pub keys and annotations in LatestVerificationConfig.AllOf.
+/// Takes clap flags and builds a Some(LatestVerificationConfig) requiring all
+/// passed pub keys and annotations to every policy, using its default AllOf.
 /// If no verification flags where used, it returns a None.
 fn build_verification_options_from_flags(
     matches: &ArgMatches,
@@ -159,8 +161,12 @@ fn build_verification_options_from_flags(
         Some(signatures)
     };
     let verification_config = LatestVerificationConfig {
-        all_of: signatures_all_of,
-        any_of: None,
+        default: Some(SignatureRequirements {
+            all_of: signatures_all_of,
+            any_of: None,
+            attestations: None,
+        }),
+        ..Default::default()
     };
     Ok(Some(verification_config))
 }
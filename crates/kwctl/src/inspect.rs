@@ -8,14 +8,16 @@ use std::{
 use anyhow::{Result, anyhow};
 use is_terminal::IsTerminal;
 use policy_evaluator::{
+    burrego::BuiltinsReport,
     constants::*,
     policy_evaluator::PolicyExecutionMode,
     policy_fetcher::{
         oci_client::{
+            Reference,
             manifest::{OciImageManifest, OciManifest},
             secrets::RegistryAuth,
         },
-        registry::Registry,
+        registry::{Registry, credentials::RegistryAction},
         sigstore::{
             cosign::{ClientBuilder, CosignCapabilities},
             registry::{Auth, ClientConfig, oci_reference::OciReference},
@@ -27,6 +29,8 @@ use policy_evaluator::{
 use prettytable::{Table, format::FormatBuilder, row};
 use termimad::{FmtText, MadSkin, terminal_size};
 
+use crate::backend::rego_builtins_report;
+
 pub(crate) async fn inspect(
     uri_or_sha_prefix: &str,
     output: OutputType,
@@ -41,8 +45,8 @@ pub(crate) async fn inspect(
     let metadata = Metadata::from_path(&wasm_path)
         .map_err(|e| anyhow!("Error parsing policy metadata: {}", e))?;
 
-    match metadata {
-        Some(metadata) => metadata_printer.print(&metadata, no_color)?,
+    let metadata = match metadata {
+        Some(metadata) => metadata,
         None => {
             return Err(anyhow!(
                 "No Kubewarden metadata found inside of '{}'.\nPolicies can be annotated with the `kwctl annotate` command.",
@@ -50,6 +54,16 @@ pub(crate) async fn inspect(
             ));
         }
     };
+    // the YAML output is the metadata document, the builtins are reported
+    // only by the pretty output
+    let builtins_report = match (&metadata_printer, &metadata.execution_mode) {
+        (
+            MetadataPrinter::Pretty,
+            PolicyExecutionMode::Opa | PolicyExecutionMode::OpaGatekeeper,
+        ) => Some(rego_builtins_report(&wasm_path)?),
+        _ => None,
+    };
+    metadata_printer.print(&metadata, builtins_report.as_ref(), no_color)?;
 
     if no_signatures {
         return Ok(());
@@ -114,7 +128,12 @@ impl From<&OutputType> for MetadataPrinter {
 }
 
 impl MetadataPrinter {
-    fn print(&self, metadata: &Metadata, no_color: bool) -> Result<()> {
+    fn print(
+        &self,
+        metadata: &Metadata,
+        builtins_report: Option<&BuiltinsReport>,
+        no_color: bool,
+    ) -> Result<()> {
         match self {
             MetadataPrinter::Yaml => {
                 let metadata_yaml = serde_yaml::to_string(metadata)?;
@@ -130,6 +149,10 @@ impl MetadataPrinter {
                     self.print_metadata_context_aware_resources(metadata, no_color)?;
                     println!();
                 }
+                if let Some(builtins_report) = builtins_report {
+                    self.print_rego_builtins(builtins_report);
+                    println!();
+                }
                 self.print_metadata_usage(metadata, no_color);
                 Ok(())
             }
@@ -232,6 +255,28 @@ impl MetadataPrinter {
         Ok(())
     }
 
+    fn print_rego_builtins(&self, builtins_report: &BuiltinsReport) {
+        let mut table = Table::new();
+        table.set_format(FormatBuilder::new().padding(0, 1).build());
+        table.add_row(row![Fmbl -> "Rego Builtins"]);
+        for builtin in &builtins_report.supported {
+            table.add_row(row![Fgbl -> builtin, d -> "supported"]);
+        }
+        for builtin in &builtins_report.unsupported {
+            table.add_row(row![Frbl -> builtin, d -> "unsupported"]);
+        }
+        table.printstd();
+
+        if builtins_report.supported.is_empty() && builtins_report.unsupported.is_empty() {
+            println!("The policy doesn't require any builtin from the host.");
+        }
+        if !builtins_report.is_compatible() {
+            println!(
+                "The policy uses builtins that are not supported by Kubewarden, its evaluation will fail."
+            );
+        }
+    }
+
     fn print_metadata_usage(&self, metadata: &Metadata, no_color: bool) {
         let usage = match metadata.annotations.clone() {
             None => None,
@@ -337,7 +382,14 @@ async fn fetch_signatures_manifest(
         .strip_prefix("registry://")
         .ok_or_else(|| anyhow!("invalid uri"))?;
     let image_ref = OciReference::from_str(image_name)?;
-    let auth = match Registry::auth(image_name) {
+    let reference = Reference::from_str(image_name)?;
+    let auth = match Registry::auth(
+        &reference,
+        RegistryAction::Pull,
+        &sources.clone().unwrap_or_default(),
+    )
+    .await
+    {
         RegistryAuth::Anonymous => Auth::Anonymous,
         RegistryAuth::Basic(username, password) => Auth::Basic(username, password),
         RegistryAuth::Bearer(token) => Auth::Bearer(token),
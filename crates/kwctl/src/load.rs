@@ -1,20 +1,150 @@
 use anyhow::{Result, anyhow};
 use flate2::read::GzDecoder;
-use policy_evaluator::policy_fetcher::store::Store;
-use std::fs::File;
+use policy_evaluator::policy_fetcher::{
+    oci_client::Reference, oci_layout::OciLayout, registry::Registry, sources::Sources,
+    store::Store,
+};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    str::FromStr,
+};
 use tar::Archive;
 
-// load policies inside the tarball provided by source_path into the default store
-pub(crate) fn load(source_path: &str) -> Result<()> {
+const GZIP_MAGIC_NUMBER: [u8; 2] = [0x1f, 0x8b];
+
+// load policies into the default store. The source can be the tarball created
+// by `kwctl save`, or an OCI image layout: either a directory or a tarball.
+//
+// The signatures and the attestations found inside of an OCI image layout are
+// saved inside of the store too, hence the policies can be verified without
+// contacting their registry.
+//
+// The artifacts of an OCI image layout can also be pushed to `push_registry`,
+// keeping their repository and tag. Their digests don't change, hence their
+// signatures can still be verified.
+pub(crate) async fn load(
+    source_path: &str,
+    push_registry: Option<&str>,
+    sources: Option<&Sources>,
+) -> Result<()> {
+    if Path::new(source_path).is_dir() {
+        return load_oci_layout(Path::new(source_path), push_registry, sources).await;
+    }
+
     let default_store = Store::default();
-    let destination_path = default_store.root;
-    let tar_gz =
+    let staging_dir = default_store.staging_dir()?;
+    let result = unpack(source_path, &staging_dir);
+    let result = match result {
+        Ok(()) if OciLayout::is_oci_layout(&staging_dir) => {
+            load_oci_layout(&staging_dir, push_registry, sources).await
+        }
+        Ok(()) if push_registry.is_some() => Err(anyhow!(
+            "cannot push the policies of {}: only OCI image layouts can be pushed",
+            source_path
+        )),
+        Ok(()) => default_store
+            .import_legacy_layout(&staging_dir)
+            .map(|_| ())
+            .map_err(|e| anyhow!("cannot import policies from {}: {}", source_path, e)),
+        Err(e) => Err(e),
+    };
+    // the staging directory would be removed by the garbage collection anyway
+    let _ = std::fs::remove_dir_all(&staging_dir);
+
+    result
+}
+
+// unpack the tarball, compressed or not, into the given directory
+fn unpack(source_path: &str, destination: &Path) -> Result<()> {
+    let file =
         File::open(source_path).map_err(|e| anyhow!("cannot open file {}: {}", source_path, e))?;
-    let tar = GzDecoder::new(tar_gz);
-    let mut archive = Archive::new(tar);
-    archive
-        .unpack(destination_path)
-        .map_err(|e| anyhow!("cannot unpack file {}: {}", source_path, e))?;
+    let mut reader = BufReader::new(file);
+    let compressed = reader
+        .fill_buf()
+        .map_err(|e| anyhow!("cannot read file {}: {}", source_path, e))?
+        .starts_with(&GZIP_MAGIC_NUMBER);
+    let reader: Box<dyn Read> = if compressed {
+        Box::new(GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    };
+    Archive::new(reader)
+        .unpack(destination)
+        .map_err(|e| anyhow!("cannot unpack file {}: {}", source_path, e))
+}
+
+async fn load_oci_layout(
+    layout_dir: &Path,
+    push_registry: Option<&str>,
+    sources: Option<&Sources>,
+) -> Result<()> {
+    let layout = OciLayout::open(layout_dir)?;
+    let store = Store::default();
+    let registry = Registry::new();
+
+    for (ref_name, artifact) in layout.artifacts()? {
+        // the signatures and the attestations are not policies
+        match artifact.wasm_module()? {
+            Some(module) => {
+                let uri = format!("registry://{ref_name}");
+                store
+                    .add_registry_policy(&uri, module, &artifact.digest())
+                    .map_err(|e| anyhow!("cannot import policy {}: {}", uri, e))?;
+            }
+            None => store
+                .add_registry_artifact(&ref_name, &artifact)
+                .map_err(|e| anyhow!("cannot import {}: {}", ref_name, e))?,
+        }
+
+        if let Some(push_registry) = push_registry {
+            let destination = push_destination(&ref_name, push_registry)?;
+            registry
+                .push_artifact(&artifact, &destination, sources)
+                .await
+                .map_err(|e| anyhow!("cannot push {} to {}: {}", ref_name, destination, e))?;
+            eprintln!("Pushed {ref_name} to {destination}");
+        }
+    }
 
     Ok(())
 }
+
+/// The location the artifact is pushed to: the registry is replaced,
+/// while the repository and the tag, or the digest, are kept
+fn push_destination(ref_name: &str, push_registry: &str) -> Result<String> {
+    let reference = Reference::from_str(ref_name)?;
+    let destination = format!("registry://{}/{}", push_registry, reference.repository());
+    Ok(match (reference.tag(), reference.digest()) {
+        (Some(tag), _) => format!("{destination}:{tag}"),
+        (None, Some(digest)) => format!("{destination}@{digest}"),
+        (None, None) => format!("{destination}:latest"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::tag(
+        "ghcr.io/kubewarden/policies/psp:v1.0.0",
+        "registry://registry.local:5000/kubewarden/policies/psp:v1.0.0"
+    )]
+    #[case::signature(
+        "ghcr.io/kubewarden/policies/psp:sha256-61ef63621fa5be8e422881d96d05edfef810992fbf9468e35d1fa5ae815bd97c.sig",
+        "registry://registry.local:5000/kubewarden/policies/psp:sha256-61ef63621fa5be8e422881d96d05edfef810992fbf9468e35d1fa5ae815bd97c.sig"
+    )]
+    #[case::digest(
+        "ghcr.io/kubewarden/policies/psp@sha256:61ef63621fa5be8e422881d96d05edfef810992fbf9468e35d1fa5ae815bd97c",
+        "registry://registry.local:5000/kubewarden/policies/psp@sha256:61ef63621fa5be8e422881d96d05edfef810992fbf9468e35d1fa5ae815bd97c"
+    )]
+    fn compute_push_destination(#[case] ref_name: &str, #[case] expected: &str) {
+        assert_eq!(
+            push_destination(ref_name, "registry.local:5000").unwrap(),
+            expected
+        );
+    }
+}
@@ -6,7 +6,15 @@ use anyhow::{Result, anyhow};
 use clap::ArgMatches;
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use policy_evaluator::policy_fetcher::{PullDestination, registry::Registry, store::DEFAULT_ROOT};
+use policy_evaluator::policy_fetcher::{
+    FetchMode, PullDestination,
+    registry::Registry,
+    store::{DEFAULT_ROOT, Store},
+    verify::{
+        bundle::{find_bundle, find_stored_bundle},
+        has_stored_signatures,
+    },
+};
 use rustls::crypto::aws_lc_rs::default_provider;
 use tracing::{debug, info};
 use tracing_subscriber::{
@@ -38,6 +46,7 @@ mod load;
 mod policies;
 mod pull;
 mod push;
+mod rego;
 mod rm;
 mod save;
 mod scaffold;
@@ -116,7 +125,19 @@ async fn main() -> Result<()> {
     }
 
     match matches.subcommand_name() {
-        Some("policies") => policies::list(),
+        Some("policies") => {
+            if let Some(matches) = matches.subcommand_matches("policies") {
+                if matches.contains_id("prune") {
+                    policies::gc()?;
+                }
+                if matches.contains_id("outdated") {
+                    let sources = remote_server_options(matches)?;
+                    return policies::outdated(sources.as_ref()).await;
+                }
+            }
+            policies::list()
+        }
+        Some("gc") => policies::gc(),
         Some("info") => info::info(),
         Some("pull") => {
             if let Some(matches) = matches.subcommand_matches("pull") {
@@ -129,7 +150,11 @@ async fn main() -> Result<()> {
                     Some(destination) => PullDestination::LocalFile(destination),
                     None => PullDestination::MainStore,
                 };
-                pull_command(uri, destination, matches).await?
+                let fetch_mode = matches
+                    .get_one::<String>("fetch-mode")
+                    .expect("clap should have set a default value")
+                    .parse::<FetchMode>()?;
+                pull_command(uri, destination, fetch_mode, matches).await?
             };
             Ok(())
         }
@@ -140,9 +165,60 @@ async fn main() -> Result<()> {
                 let sources = remote_server_options(matches)?;
                 let verification_options = build_verification_options(matches)?
                     .ok_or_else(|| anyhow!("could not retrieve sigstore options"))?;
-                let sigstore_trust_root =
-                    build_sigstore_trust_root(matches.get_one::<PathBuf>("sigstore-trust-config"))
-                        .await?;
+                let sigstore_trust_config = matches.get_one::<PathBuf>("sigstore-trust-config");
+
+                // local policies can be verified offline, using the bundle next to them,
+                // like the policies served over HTTP(S), using the signature published
+                // next to them
+                let bundle = matches
+                    .get_one::<PathBuf>("bundle")
+                    .cloned()
+                    .or_else(|| {
+                        crate::utils::map_path_to_uri(uri)
+                            .ok()
+                            .and_then(|uri| find_bundle(&uri))
+                    })
+                    .or_else(|| find_stored_bundle(&Store::default(), uri));
+                // the policies loaded from OCI image layouts can be verified
+                // offline too, using the signatures saved inside of the store
+                let offline = bundle.is_some() || has_stored_signatures(&Store::default(), uri);
+                if offline {
+                    let sigstore_trust_root = match build_sigstore_trust_root(sigstore_trust_config)
+                        .await
+                    {
+                        Ok(trust_root) => trust_root,
+                        // signatures made with a key can still be verified
+                        Err(e) if sigstore_trust_config.is_none() => {
+                            tracing::warn!(error = %e, "cannot fetch Sigstore data, keyless signatures cannot be verified");
+                            None
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    match bundle {
+                        Some(bundle) => {
+                            verify::verify_bundle(
+                                uri,
+                                &bundle,
+                                &verification_options,
+                                sigstore_trust_root,
+                            )
+                            .await
+                        }
+                        None => {
+                            verify::verify_stored(
+                                uri,
+                                &Store::default(),
+                                &verification_options,
+                                sigstore_trust_root,
+                            )
+                            .await
+                        }
+                    }
+                    .map_err(|e| anyhow!("Policy {} cannot be validated\n{:?}", uri, e))?;
+                    return Ok(());
+                }
+
+                let sigstore_trust_root = build_sigstore_trust_root(sigstore_trust_config).await?;
                 verify::verify(
                     uri,
                     sources.as_ref(),
@@ -312,6 +388,19 @@ async fn main() -> Result<()> {
                     vap_binding_file.as_path(),
                 )?;
             };
+            if let Some(matches) = matches.subcommand_matches("scaffold")
+                && let Some(matches) = matches.subcommand_matches("gatekeeper-mutation")
+            {
+                let module = matches.get_one::<String>("module").unwrap();
+                let name = matches.get_one::<String>("name").unwrap();
+                let mutator_files: Vec<PathBuf> = matches
+                    .get_many::<String>("mutators")
+                    .unwrap()
+                    .map(PathBuf::from)
+                    .collect();
+
+                scaffold::gatekeeper_mutation(module, name, &mutator_files)?;
+            };
             if let Some(matches) = matches.subcommand_matches("scaffold")
                 && let Some(matches) = matches.subcommand_matches("admission-request")
             {
@@ -356,15 +445,31 @@ async fn main() -> Result<()> {
             if let Some(matches) = matches.subcommand_matches("save") {
                 let policies = matches.get_many::<String>("policies").unwrap();
                 let output = matches.get_one::<String>("output").unwrap();
+                let format = matches.get_one::<String>("format").unwrap().parse()?;
+                let sources = remote_server_options(matches)?;
 
-                save(policies.collect_vec(), output)?;
+                save(policies.collect_vec(), output, format, sources.as_ref()).await?;
             }
             Ok(())
         }
         Some("load") => {
             if let Some(matches) = matches.subcommand_matches("load") {
                 let input = matches.get_one::<String>("input").unwrap();
-                load(input)?;
+                let push_registry = matches.get_one::<String>("push-to-registry");
+                let sources = remote_server_options(matches)?;
+                load(input, push_registry.map(String::as_str), sources.as_ref()).await?;
+            }
+            Ok(())
+        }
+        Some("rego") => {
+            if let Some(matches) = matches.subcommand_matches("rego")
+                && let Some(eval_matches) = matches.subcommand_matches("eval")
+            {
+                let uri_or_sha_prefix =
+                    eval_matches.get_one::<String>("uri_or_sha_prefix").unwrap();
+                let wasm_path = crate::utils::get_wasm_path(uri_or_sha_prefix)?;
+                let options = rego::EvalOptions::try_from(eval_matches)?;
+                rego::eval(&wasm_path, options)?;
             }
             Ok(())
         }
@@ -396,7 +501,13 @@ async fn pull_if_needed(uri_or_sha_prefix: &str, matches: &ArgMatches) -> Result
                 "cannot find policy with uri: {}, trying to pull it from remote registry",
                 uri
             );
-            pull_command(&uri, PullDestination::MainStore, matches).await
+            pull_command(
+                &uri,
+                PullDestination::MainStore,
+                FetchMode::default(),
+                matches,
+            )
+            .await
         }
         Err(e) => Err(anyhow!("{}", e)),
         Ok(_path) => Ok(()),
@@ -407,38 +518,69 @@ async fn pull_if_needed(uri_or_sha_prefix: &str, matches: &ArgMatches) -> Result
 async fn pull_command(
     uri: &String,
     destination: PullDestination,
+    fetch_mode: FetchMode,
     matches: &ArgMatches,
 ) -> Result<()> {
     let sources = remote_server_options(matches)?;
 
     let verification_options = build_verification_options(matches)?;
+    // the policies served over HTTP(S) are verified after pulling them, using
+    // the signature published next to them
+    let served_over_http = uri.starts_with("http://") || uri.starts_with("https://");
     let mut verified_manifest_digest: Option<String> = None;
-    if let Some(ref verification_config) = verification_options {
+    if let Some(ref verification_config) = verification_options
+        && !served_over_http
+    {
         let sigstore_trust_root =
             build_sigstore_trust_root(matches.get_one::<PathBuf>("sigstore-trust-config")).await?;
         // verify policy prior to pulling if keys listed, and keep the
         // verified manifest digest:
-        verified_manifest_digest = Some(
-            verify::verify(
-                uri,
-                sources.as_ref(),
-                verification_config,
-                sigstore_trust_root.clone(),
-            )
-            .await
-            .map_err(|e| anyhow!("Policy {} cannot be validated\n{:?}", uri, e))?,
-        );
+        verified_manifest_digest = verify::verify(
+            uri,
+            sources.as_ref(),
+            verification_config,
+            sigstore_trust_root.clone(),
+        )
+        .await
+        .map_err(|e| anyhow!("Policy {} cannot be validated\n{:?}", uri, e))?
+        .map(|report| report.manifest_digest);
     }
 
-    let policy = pull::pull(uri, sources.as_ref(), destination).await?;
+    let policy = pull::pull(uri, sources.as_ref(), destination, fetch_mode).await?;
+
+    if let Some(ref verification_config) = verification_options
+        && served_over_http
+    {
+        let local_uri = crate::utils::map_path_to_uri(&policy.local_path.to_string_lossy())?;
+        let (policy_uri, bundle) = match find_bundle(&local_uri) {
+            Some(bundle) => (local_uri.as_str(), Some(bundle)),
+            None => (uri.as_str(), find_stored_bundle(&Store::default(), uri)),
+        };
+        let bundle = bundle.ok_or_else(|| {
+            anyhow!(
+                "Policy {} cannot be validated: no signature published next to it",
+                uri
+            )
+        })?;
+        let sigstore_trust_root =
+            build_sigstore_trust_root(matches.get_one::<PathBuf>("sigstore-trust-config")).await?;
+        return verify::verify_bundle(
+            policy_uri,
+            &bundle,
+            verification_config,
+            sigstore_trust_root,
+        )
+        .await
+        .map_err(|e| anyhow!("Policy {} cannot be validated\n{:?}", uri, e));
+    }
 
-    if verification_options.is_some() {
+    if let Some(verified_manifest_digest) = verified_manifest_digest {
         let sigstore_trust_root =
             build_sigstore_trust_root(matches.get_one::<PathBuf>("sigstore-trust-config")).await?;
         return verify::verify_local_checksum(
             &policy,
             sources.as_ref(),
-            &verified_manifest_digest.unwrap(),
+            &verified_manifest_digest,
             sigstore_trust_root.clone(),
         )
         .await;
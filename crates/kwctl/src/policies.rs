@@ -1,9 +1,14 @@
+use std::str::FromStr;
+
 use anyhow::{Result, anyhow};
 use policy_evaluator::{
-    policy_fetcher::{policy::Policy, store::Store},
+    policy_fetcher::{
+        oci_client::Reference, policy::Policy, registry::Registry, sources::Sources, store::Store,
+    },
     policy_metadata::Metadata as PolicyMetadata,
 };
 use prettytable::{Table, format, row};
+use semver::Version;
 
 pub(crate) fn list() -> Result<()> {
     if policy_list()?.is_empty() {
@@ -57,6 +62,149 @@ pub(crate) fn list() -> Result<()> {
     Ok(())
 }
 
+/// Removes the unreferenced and the corrupted modules from the store
+pub(crate) fn gc() -> Result<()> {
+    let report = Store::default().gc()?;
+    for uri in &report.removed_entries {
+        eprintln!("Removed corrupted policy: {uri}");
+    }
+    eprintln!(
+        "Removed {} modules, reclaimed {}",
+        report.removed_blobs.len(),
+        humansize::format_size(report.reclaimed_bytes, humansize::DECIMAL)
+    );
+    Ok(())
+}
+
+/// Lists the policies pulled from a registry whose tag now points to a
+/// different manifest, or that have newer semver tags inside of the registry
+pub(crate) async fn outdated(sources: Option<&Sources>) -> Result<()> {
+    let store = Store::default();
+    let registry = Registry::new();
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["Policy", "Digest", "Newer tags"]);
+    for policy in policy_list()? {
+        let Some(image) = policy.uri.strip_prefix("registry://") else {
+            continue;
+        };
+        let reference = Reference::from_str(image)?;
+        if reference.digest().is_some() {
+            // policies referenced by digest never change
+            continue;
+        }
+
+        let recorded_digest = store
+            .index_entry(&policy.uri)?
+            .and_then(|entry| entry.manifest_digest);
+        let digest_status = match registry.manifest_digest(&policy.uri, sources).await {
+            Ok(digest) => DigestStatus::compute(recorded_digest.as_deref(), &digest),
+            Err(e) => {
+                eprintln!("Cannot fetch the manifest digest of {policy}: {e}");
+                DigestStatus::Unknown
+            }
+        };
+        let newer_tags = match registry.tags(&policy.uri, sources).await {
+            Ok(tags) => newer_tags(reference.tag().unwrap_or("latest"), &tags),
+            Err(e) => {
+                eprintln!("Cannot list the tags of {policy}: {e}");
+                vec![]
+            }
+        };
+
+        if digest_status != DigestStatus::Unchanged || !newer_tags.is_empty() {
+            table.add_row(row![
+                format!("{policy}"),
+                digest_status.as_str(),
+                newer_tags.join(", "),
+            ]);
+        }
+    }
+    if !table.is_empty() {
+        table.printstd();
+    }
+    Ok(())
+}
+
+/// How the manifest digest of a policy inside of the registry compares with
+/// the one recorded when the policy was pulled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestStatus {
+    Unchanged,
+    Changed,
+    /// No digest was recorded, like for the policies pulled by older
+    /// versions of kwctl, or the registry could not be reached, hence the
+    /// policy could be outdated
+    Unknown,
+}
+
+impl DigestStatus {
+    fn compute(recorded: Option<&str>, current: &str) -> Self {
+        match recorded {
+            Some(recorded) if recorded == current => DigestStatus::Unchanged,
+            Some(_) => DigestStatus::Changed,
+            None => DigestStatus::Unknown,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            DigestStatus::Unchanged => "",
+            DigestStatus::Changed => "changed",
+            DigestStatus::Unknown => "unknown",
+        }
+    }
+}
+
+/// Returns the semver tags newer than the given one, sorted by version. The
+/// leading `v` of the tags is ignored, pre-releases are taken into account only
+/// when the current tag is a pre-release too
+fn newer_tags(current: &str, tags: &[String]) -> Vec<String> {
+    let Some(current) = parse_semver_tag(current) else {
+        return vec![];
+    };
+    let mut newer: Vec<(Version, &String)> = tags
+        .iter()
+        .filter_map(|tag| parse_semver_tag(tag).map(|version| (version, tag)))
+        .filter(|(version, _)| {
+            version > &current && (version.pre.is_empty() || !current.pre.is_empty())
+        })
+        .collect();
+    newer.sort();
+    newer.into_iter().map(|(_, tag)| tag.to_owned()).collect()
+}
+
+fn parse_semver_tag(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
 fn policy_list() -> Result<Vec<Policy>> {
     Store::default().list().map_err(anyhow::Error::new)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::unchanged(Some("sha256:1234"), DigestStatus::Unchanged)]
+    #[case::changed(Some("sha256:5678"), DigestStatus::Changed)]
+    #[case::not_recorded(None, DigestStatus::Unknown)]
+    fn compute_digest_status(#[case] recorded: Option<&str>, #[case] expected: DigestStatus) {
+        assert_eq!(DigestStatus::compute(recorded, "sha256:1234"), expected);
+    }
+
+    #[rstest]
+    #[case::newer_versions("v1.0.0", &["v0.9.0", "v1.0.0", "v1.2.0", "v1.1.0"], &["v1.1.0", "v1.2.0"])]
+    #[case::without_prefix("1.0.0", &["1.0.1", "v2.0.0"], &["1.0.1", "v2.0.0"])]
+    #[case::skip_pre_releases("v1.0.0", &["v1.1.0-rc1", "v1.1.0"], &["v1.1.0"])]
+    #[case::pre_release("v1.1.0-rc1", &["v1.1.0-rc2", "v1.1.0"], &["v1.1.0-rc2", "v1.1.0"])]
+    #[case::not_semver_tags("v1.0.0", &["latest", "main"], &[])]
+    #[case::current_not_semver("latest", &["v1.0.0"], &[])]
+    fn compute_newer_tags(#[case] current: &str, #[case] tags: &[&str], #[case] expected: &[&str]) {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        assert_eq!(newer_tags(current, &tags), expected);
+    }
+}
@@ -3,13 +3,14 @@ use std::time::Duration;
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
 use policy_evaluator::policy_fetcher::{
-    PullDestination, fetch_policy, policy::Policy, sources::Sources,
+    FetchMode, PullDestination, fetch_policy_with_mode, policy::Policy, sources::Sources,
 };
 
 pub(crate) async fn pull(
     uri: &str,
     sources: Option<&Sources>,
     destination: PullDestination,
+    fetch_mode: FetchMode,
 ) -> Result<Policy> {
     let pb = ProgressBar::new_spinner();
     pb.set_style(
@@ -20,7 +21,7 @@ pub(crate) async fn pull(
     pb.set_message(format!("Pulling policy from {}", uri));
     pb.enable_steady_tick(Duration::from_millis(100));
 
-    let result = fetch_policy(uri, destination, sources)
+    let result = fetch_policy_with_mode(uri, destination, sources, fetch_mode)
         .await
         .map_err(anyhow::Error::new);
 
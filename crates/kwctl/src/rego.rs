@@ -0,0 +1,306 @@
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use clap::ArgMatches;
+use itertools::Itertools;
+use policy_evaluator::{
+    burrego::{EvaluatorBuilder, HostCallbacks, PolicyMessage},
+    evaluation_context::EvaluationContext,
+    runtimes::kubewarden_rego_builtins,
+};
+use serde::Serialize;
+
+use crate::backend::rego_builtins_report;
+
+/// What `kwctl rego eval` has to do with the policy
+pub(crate) struct EvalOptions {
+    pub input: serde_json::Value,
+    pub data: serde_json::Value,
+    /// Names or ids of the entrypoints to evaluate. All the entrypoints are
+    /// evaluated when empty
+    pub entrypoints: Vec<String>,
+    /// Only list the entrypoints, without evaluating the policy
+    pub list_entrypoints: bool,
+    pub output: OutputType,
+}
+
+impl TryFrom<&ArgMatches> for EvalOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(matches: &ArgMatches) -> Result<Self, Self::Error> {
+        Ok(EvalOptions {
+            input: json_arg(matches, "input", "input-path")?,
+            data: json_arg(matches, "data", "data-path")?,
+            entrypoints: matches
+                .get_many::<String>("entrypoint")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
+            list_entrypoints: matches.get_flag("list-entrypoints"),
+            output: OutputType::try_from(matches.get_one::<String>("output").map(|s| s.as_str()))?,
+        })
+    }
+}
+
+/// Reads a JSON document provided either inline or through a file. An empty
+/// object is returned when none of them is provided
+fn json_arg(matches: &ArgMatches, inline: &str, path: &str) -> Result<serde_json::Value> {
+    if let Some(json) = matches.get_one::<String>(inline) {
+        return serde_json::from_str(json)
+            .map_err(|e| anyhow!("Cannot parse '{}' as JSON: {}", inline, e));
+    }
+    if let Some(path) = matches.get_one::<String>(path) {
+        let file = File::open(path).map_err(|e| anyhow!("Cannot open {}: {}", path, e))?;
+        return serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| anyhow!("Cannot parse {} as JSON: {}", path, e));
+    }
+    Ok(serde_json::json!({}))
+}
+
+pub(crate) enum OutputType {
+    Json,
+    Pretty,
+}
+
+impl TryFrom<Option<&str>> for OutputType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Option<&str>) -> Result<Self, Self::Error> {
+        match value {
+            Some("json") => Ok(Self::Json),
+            None => Ok(Self::Pretty),
+            Some(unknown) => Err(anyhow!("Invalid output format '{}'", unknown)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    opa_abi_version: String,
+    entrypoints: Vec<Entrypoint>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    evaluations: Vec<Evaluation>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Entrypoint {
+    id: i32,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Evaluation {
+    entrypoint: Entrypoint,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Message {
+    source: &'static str,
+    message: String,
+}
+
+impl From<PolicyMessage> for Message {
+    fn from(message: PolicyMessage) -> Self {
+        match message {
+            PolicyMessage::Println(message) => Message {
+                source: "println",
+                message,
+            },
+            PolicyMessage::Trace(message) => Message {
+                source: "trace",
+                message,
+            },
+        }
+    }
+}
+
+/// The messages of the policy are collected by burrego and shown together
+/// with the evaluation result, they must not be printed while evaluating
+fn opa_println(_msg: &str) {}
+
+fn opa_abort(msg: &str) {
+    eprintln!("OPA abort with message: {msg:?}");
+}
+
+/// Evaluates the entrypoints of a Rego policy with the given input and data,
+/// showing the messages printed by the policy. The Kubewarden builtins are
+/// available, but the host capabilities cannot be reached: the builtins
+/// fetching data from the host fail.
+pub(crate) fn eval(wasm_path: &Path, options: EvalOptions) -> Result<()> {
+    let builtins_report = rego_builtins_report(wasm_path)?;
+    if !builtins_report.is_compatible() {
+        return Err(anyhow!(
+            "Cannot evaluate policy, these builtins are not yet implemented: {}",
+            builtins_report.unsupported.iter().join(", ")
+        ));
+    }
+
+    let mut evaluator = EvaluatorBuilder::default()
+        .policy_path(wasm_path)
+        .host_callbacks(HostCallbacks {
+            opa_abort,
+            opa_println,
+        })
+        .custom_builtins(kubewarden_rego_builtins(Arc::new(
+            EvaluationContext::default(),
+        )))
+        .build()?;
+
+    let (major, minor) = evaluator.opa_abi_version()?;
+    let entrypoints: Vec<Entrypoint> = evaluator
+        .entrypoints()
+        .into_iter()
+        .map(|(name, id)| Entrypoint { id, name })
+        .sorted_by_key(|entrypoint| entrypoint.id)
+        .collect();
+
+    let mut report = Report {
+        opa_abi_version: format!("{major}.{minor}"),
+        entrypoints,
+        evaluations: Vec::new(),
+    };
+
+    if !options.list_entrypoints {
+        let selected = select_entrypoints(&report.entrypoints, &options.entrypoints)?;
+        let data = serde_json::to_vec(&options.data)?;
+
+        for entrypoint in selected {
+            let output = evaluator.evaluate_with_messages(entrypoint.id, &options.input, &data);
+            let messages = output.messages.into_iter().map(Message::from).collect();
+            let evaluation = match output.result {
+                Ok(result) => Evaluation {
+                    entrypoint,
+                    messages,
+                    result: Some(result),
+                    error: None,
+                },
+                Err(e) => {
+                    // the Wasm instance might be left in a broken state
+                    evaluator.reset()?;
+                    Evaluation {
+                        entrypoint,
+                        messages,
+                        result: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            report.evaluations.push(evaluation);
+        }
+    }
+
+    match options.output {
+        OutputType::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputType::Pretty => print_report(&report)?,
+    }
+
+    let failures = report
+        .evaluations
+        .iter()
+        .filter(|evaluation| evaluation.error.is_some())
+        .count();
+    if failures > 0 {
+        return Err(anyhow!("{failures} entrypoint(s) cannot be evaluated"));
+    }
+
+    Ok(())
+}
+
+/// Finds the entrypoints to evaluate, referenced either by name or by id
+fn select_entrypoints(entrypoints: &[Entrypoint], selection: &[String]) -> Result<Vec<Entrypoint>> {
+    if selection.is_empty() {
+        return Ok(entrypoints.to_vec());
+    }
+
+    selection
+        .iter()
+        .map(|wanted| {
+            entrypoints
+                .iter()
+                .find(|entrypoint| {
+                    entrypoint.name == *wanted || entrypoint.id.to_string() == *wanted
+                })
+                .cloned()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Cannot find entrypoint '{}', the policy provides: {}",
+                        wanted,
+                        entrypoints
+                            .iter()
+                            .map(|entrypoint| entrypoint.name.as_str())
+                            .join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+fn print_report(report: &Report) -> Result<()> {
+    println!("OPA Wasm ABI: {}", report.opa_abi_version);
+    println!("Entrypoints:");
+    for entrypoint in &report.entrypoints {
+        println!("  {}: {}", entrypoint.id, entrypoint.name);
+    }
+
+    for evaluation in &report.evaluations {
+        println!();
+        println!(
+            "Evaluation of {} ({}):",
+            evaluation.entrypoint.name, evaluation.entrypoint.id
+        );
+        for message in &evaluation.messages {
+            println!("  [{}] {}", message.source, message.message);
+        }
+        if let Some(result) = &evaluation.result {
+            println!("{}", serde_json::to_string_pretty(result)?);
+        }
+        if let Some(error) = &evaluation.error {
+            println!("  error: {error}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entrypoints() -> Vec<Entrypoint> {
+        vec![
+            Entrypoint {
+                id: 0,
+                name: "policy/main".to_string(),
+            },
+            Entrypoint {
+                id: 1,
+                name: "policy/violation".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn select_entrypoints_by_name_or_id() {
+        let selected = select_entrypoints(
+            &entrypoints(),
+            &["policy/violation".to_string(), "0".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            selected.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![1, 0]
+        );
+
+        let all = select_entrypoints(&entrypoints(), &[]).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let err = select_entrypoints(&entrypoints(), &["policy/deny".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("policy/main, policy/violation"));
+    }
+}
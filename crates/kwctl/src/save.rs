@@ -1,12 +1,57 @@
 use anyhow::{Result, anyhow};
 use flate2::Compression;
 use flate2::write::GzEncoder;
-use policy_evaluator::policy_fetcher::store::{PolicyPath, Store};
-use std::fs::File;
+use policy_evaluator::policy_fetcher::{
+    oci_client::Reference,
+    oci_layout::OciLayout,
+    registry::Registry,
+    sources::Sources,
+    store::{PolicyPath, Store},
+    verify::{ATTESTATIONS_SUFFIX, SIGNATURES_SUFFIX, cosign_ref_name},
+};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::debug;
+
+/// The formats policies can be saved into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SaveFormat {
+    /// A tarball of the policies found inside of the store
+    TarGz,
+    /// An OCI image layout, holding the policies together with their manifests and signatures
+    OciLayout,
+}
+
+impl FromStr for SaveFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "tar.gz" => Ok(SaveFormat::TarGz),
+            "oci-layout" => Ok(SaveFormat::OciLayout),
+            _ => Err(anyhow!("unknown save format: {}", s)),
+        }
+    }
+}
+
+pub(crate) async fn save(
+    policies: Vec<&String>,
+    output: &str,
+    format: SaveFormat,
+    sources: Option<&Sources>,
+) -> Result<()> {
+    match format {
+        SaveFormat::TarGz => save_tar_gz(policies, output),
+        SaveFormat::OciLayout => save_oci_layout(policies, output, sources).await,
+    }
+}
 
 // saves all policies in a tarball with the name provided as output.
 // policies must be inside the default store.
-pub(crate) fn save(policies: Vec<&String>, output: &str) -> Result<()> {
+fn save_tar_gz(policies: Vec<&String>, output: &str) -> Result<()> {
     let tar_gz =
         File::create(output).map_err(|e| anyhow!("cannot create file {}: {}", output, e))?;
     let enc = GzEncoder::new(tar_gz, Compression::default());
@@ -28,3 +73,144 @@ pub(crate) fn save(policies: Vec<&String>, output: &str) -> Result<()> {
 
     Ok(())
 }
+
+// saves the policies, together with their Sigstore signatures and attestations,
+// as an OCI image layout. The layout is written inside of the `output` directory,
+// unless `output` is a `.tar`, `.tar.gz` or `.tgz` file.
+//
+// The artifacts are fetched from their registry: the policies pulled into the
+// store are saved using the digest recorded at pull time.
+async fn save_oci_layout(
+    policies: Vec<&String>,
+    output: &str,
+    sources: Option<&Sources>,
+) -> Result<()> {
+    let store = Store::default();
+    let layout_output = LayoutOutput::from(output);
+    let layout_dir = match layout_output {
+        LayoutOutput::Directory => PathBuf::from(output),
+        LayoutOutput::Tar | LayoutOutput::TarGz => store.staging_dir()?,
+    };
+    let result = write_oci_layout(policies, &layout_dir, &store, sources)
+        .await
+        .and_then(|_| archive_oci_layout(&layout_dir, output, &layout_output));
+    if layout_output != LayoutOutput::Directory {
+        // the staging directory would be removed by the garbage collection anyway
+        let _ = std::fs::remove_dir_all(&layout_dir);
+    }
+
+    result
+}
+
+async fn write_oci_layout(
+    policies: Vec<&String>,
+    layout_dir: &Path,
+    store: &Store,
+    sources: Option<&Sources>,
+) -> Result<()> {
+    let layout = OciLayout::create(layout_dir)
+        .map_err(|e| anyhow!("cannot create OCI image layout {:?}: {}", layout_dir, e))?;
+    let registry = Registry::new();
+
+    for policy in policies {
+        let uri = crate::utils::map_path_to_uri(policy.as_str())?;
+        let image = uri.strip_prefix("registry://").ok_or_else(|| {
+            anyhow!(
+                "cannot save policy {}: only policies hosted on OCI registries can be saved as OCI image layout",
+                policy
+            )
+        })?;
+        let reference = Reference::from_str(image)?;
+        let repository = format!("{}/{}", reference.registry(), reference.repository());
+
+        let source = match store
+            .index_entry(&uri)?
+            .and_then(|entry| entry.manifest_digest)
+        {
+            Some(digest) => format!("registry://{repository}@{digest}"),
+            None => uri.clone(),
+        };
+        let artifact = registry
+            .pull_artifact(&source, sources)
+            .await
+            .map_err(|e| anyhow!("cannot fetch policy {}: {}", policy, e))?;
+        layout
+            .add_artifact(&artifact, &reference.whole())
+            .map_err(|e| anyhow!("cannot save policy {}: {}", policy, e))?;
+
+        // cosign stores the signatures and the attestations of an artifact
+        // inside of its repository, using tags derived from its digest
+        for suffix in [SIGNATURES_SUFFIX, ATTESTATIONS_SUFFIX] {
+            let ref_name = cosign_ref_name(&reference, &artifact.digest(), suffix);
+            match registry
+                .pull_artifact(&format!("registry://{ref_name}"), sources)
+                .await
+            {
+                Ok(signature) => layout
+                    .add_artifact(&signature, &ref_name)
+                    .map_err(|e| anyhow!("cannot save {}: {}", ref_name, e))?,
+                Err(e) => debug!(policy, ref_name, error = %e, "no signature artifact found"),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn archive_oci_layout(layout_dir: &Path, output: &str, layout_output: &LayoutOutput) -> Result<()> {
+    match layout_output {
+        LayoutOutput::Directory => {}
+        LayoutOutput::Tar => {
+            let mut tar = tar::Builder::new(create_file(output)?);
+            tar.append_dir_all(".", layout_dir)?;
+            tar.finish()?;
+        }
+        LayoutOutput::TarGz => {
+            let enc = GzEncoder::new(create_file(output)?, Compression::default());
+            let mut tar = tar::Builder::new(enc);
+            tar.append_dir_all(".", layout_dir)?;
+            tar.into_inner()?.finish()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn create_file(path: &str) -> Result<File> {
+    File::create(path).map_err(|e| anyhow!("cannot create file {}: {}", path, e))
+}
+
+/// Where the OCI image layout is written, chosen from the extension of the output
+#[derive(Debug, PartialEq, Eq)]
+enum LayoutOutput {
+    Directory,
+    Tar,
+    TarGz,
+}
+
+impl From<&str> for LayoutOutput {
+    fn from(output: &str) -> Self {
+        if output.ends_with(".tar") {
+            LayoutOutput::Tar
+        } else if output.ends_with(".tar.gz") || output.ends_with(".tgz") {
+            LayoutOutput::TarGz
+        } else {
+            LayoutOutput::Directory
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::directory("policies", LayoutOutput::Directory)]
+    #[case::tar("policies.tar", LayoutOutput::Tar)]
+    #[case::tar_gz("policies.tar.gz", LayoutOutput::TarGz)]
+    #[case::tgz("policies.tgz", LayoutOutput::TarGz)]
+    fn oci_layout_output(#[case] output: &str, #[case] expected: LayoutOutput) {
+        assert_eq!(LayoutOutput::from(output), expected);
+    }
+}
@@ -6,6 +6,9 @@ pub(crate) use manifest::manifest;
 mod vap;
 pub(crate) use vap::vap;
 
+mod gatekeeper_mutation;
+pub(crate) use gatekeeper_mutation::gatekeeper_mutation;
+
 mod verification_config;
 pub(crate) use verification_config::verification_config;
 
@@ -0,0 +1,265 @@
+use anyhow::{Result, anyhow};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use policy_evaluator::{
+    gatekeeper_mutation::{ApplyTo, Mutator},
+    policy_metadata::{Operation, Rule},
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+use crate::scaffold::kubewarden_crds::{ClusterAdmissionPolicy, ClusterAdmissionPolicySpec};
+
+pub(crate) fn gatekeeper_mutation(
+    module: &str,
+    name: &str,
+    mutator_paths: &[PathBuf],
+) -> Result<()> {
+    let mut mutators = Vec::new();
+    for path in mutator_paths {
+        mutators.extend(read_mutators(path)?);
+    }
+
+    let cluster_admission_policy =
+        convert_mutators_to_cluster_admission_policy(module, name, mutators)?;
+
+    serde_yaml::to_writer(std::io::stdout(), &cluster_admission_policy)?;
+
+    Ok(())
+}
+
+/// Reads all the mutators defined inside of the given YAML file
+fn read_mutators(path: &Path) -> Result<Vec<Mutator>> {
+    let file =
+        File::open(path).map_err(|e| anyhow!("cannot open {}: #{e}", path.to_str().unwrap()))?;
+
+    let mut mutators = Vec::new();
+    for document in serde_yaml::Deserializer::from_reader(file) {
+        let value = serde_yaml::Value::deserialize(document)
+            .map_err(|e| anyhow!("cannot parse {}: #{e}", path.to_str().unwrap()))?;
+        if value.is_null() {
+            continue;
+        }
+        let mutator: Mutator = serde_yaml::from_value(value).map_err(|e| {
+            anyhow!(
+                "cannot convert data inside of {} into a Gatekeeper mutator: #{e}",
+                path.to_str().unwrap()
+            )
+        })?;
+        mutators.push(mutator);
+    }
+
+    Ok(mutators)
+}
+
+fn convert_mutators_to_cluster_admission_policy(
+    module: &str,
+    name: &str,
+    mutators: Vec<Mutator>,
+) -> Result<ClusterAdmissionPolicy> {
+    if mutators.is_empty() {
+        return Err(anyhow!("at least one Gatekeeper mutator must be provided"));
+    }
+
+    let mut rules: Vec<Rule> = Vec::new();
+    for mutator in &mutators {
+        mutator.validate()?;
+
+        if !mutator.match_criteria().namespaces.is_empty()
+            || !mutator.match_criteria().excluded_namespaces.is_empty()
+        {
+            warn!(
+                "{} {:?}: namespaces and excludedNamespaces are evaluated by the policy, it will receive the requests of all the namespaces",
+                mutator.kind(),
+                mutator.name()
+            );
+        }
+
+        let mutator_rules = match mutator {
+            // AssignMetadata mutators apply to all the resources
+            Mutator::AssignMetadata(_) => vec![Rule {
+                api_groups: vec!["*".to_string()],
+                api_versions: vec!["*".to_string()],
+                resources: vec!["*".to_string()],
+                operations: vec![Operation::Create, Operation::Update],
+            }],
+            _ => mutator.apply_to().iter().map(rule_from_apply_to).collect(),
+        };
+        for rule in mutator_rules {
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+    }
+
+    let mut settings = serde_yaml::Mapping::new();
+    settings.insert("mutators".into(), serde_yaml::to_value(&mutators)?);
+
+    Ok(ClusterAdmissionPolicy {
+        api_version: "policies.kubewarden.io/v1".to_string(),
+        kind: "ClusterAdmissionPolicy".to_string(),
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            ..Default::default()
+        },
+        spec: ClusterAdmissionPolicySpec {
+            module: module.to_string(),
+            settings,
+            rules,
+            mutating: true,
+            // mutators never reject a request, there's nothing to audit
+            background_audit: false,
+            context_aware_resources: BTreeSet::new(),
+            failure_policy: None,
+            mode: None,
+            match_policy: None,
+            namespace_selector: None,
+            object_selector: None,
+        },
+    })
+}
+
+fn rule_from_apply_to(apply_to: &ApplyTo) -> Rule {
+    Rule {
+        api_groups: with_wildcard(apply_to.groups.clone()),
+        api_versions: with_wildcard(apply_to.versions.clone()),
+        resources: with_wildcard(
+            apply_to
+                .kinds
+                .iter()
+                .map(String::as_str)
+                .map(guess_resource)
+                .collect(),
+        ),
+        operations: vec![Operation::Create, Operation::Update],
+    }
+}
+
+/// A `*` cannot be used together with other values inside of a rule
+fn with_wildcard(values: Vec<String>) -> Vec<String> {
+    if values.iter().any(|value| value == "*") {
+        vec!["*".to_string()]
+    } else {
+        values
+    }
+}
+
+/// Gatekeeper mutators reference kinds, while the rules of the policy
+/// reference resources. Guess the name of the resource the same way
+/// Kubernetes does when the discovery information is not available
+fn guess_resource(kind: &str) -> String {
+    if kind == "*" {
+        return kind.to_string();
+    }
+
+    let resource = kind.to_lowercase();
+    if resource.ends_with('s') || resource.ends_with("ch") || resource.ends_with("sh") {
+        format!("{resource}es")
+    } else if let Some(stem) = resource.strip_suffix('y')
+        && !stem.ends_with(['a', 'e', 'i', 'o', 'u'])
+    {
+        format!("{stem}ies")
+    } else {
+        format!("{resource}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use policy_evaluator::gatekeeper_mutation::BUILTIN_MODULE;
+    use rstest::*;
+
+    fn test_data(path: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("data")
+            .join(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn from_mutators_to_cluster_admission_policy() {
+        let mutators =
+            read_mutators(Path::new(&test_data("gatekeeper-mutation/mutators.yml"))).unwrap();
+        assert_eq!(mutators.len(), 4);
+
+        let cluster_admission_policy = convert_mutators_to_cluster_admission_policy(
+            BUILTIN_MODULE,
+            "mutators",
+            mutators.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            cluster_admission_policy.metadata.name.as_deref(),
+            Some("mutators")
+        );
+        assert_eq!(BUILTIN_MODULE, cluster_admission_policy.spec.module);
+        assert!(cluster_admission_policy.spec.mutating);
+        assert!(!cluster_admission_policy.spec.background_audit);
+        assert_eq!(
+            cluster_admission_policy.spec.rules,
+            vec![
+                Rule {
+                    api_groups: vec!["".to_string()],
+                    api_versions: vec!["v1".to_string()],
+                    resources: vec!["pods".to_string()],
+                    operations: vec![Operation::Create, Operation::Update],
+                },
+                Rule {
+                    api_groups: vec!["*".to_string()],
+                    api_versions: vec!["*".to_string()],
+                    resources: vec!["*".to_string()],
+                    operations: vec![Operation::Create, Operation::Update],
+                },
+                Rule {
+                    api_groups: vec!["apps".to_string()],
+                    api_versions: vec!["v1".to_string()],
+                    resources: vec!["deployments".to_string()],
+                    operations: vec![Operation::Create, Operation::Update],
+                },
+            ]
+        );
+        assert_eq!(
+            serde_yaml::to_value(&mutators).unwrap(),
+            cluster_admission_policy.spec.settings["mutators"]
+        );
+    }
+
+    #[test]
+    fn invalid_mutator_is_rejected() {
+        let mutators = read_mutators(Path::new(&test_data(
+            "gatekeeper-mutation/invalid-mutator.yml",
+        )))
+        .unwrap();
+
+        let result =
+            convert_mutators_to_cluster_admission_policy(BUILTIN_MODULE, "mutators", mutators);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn no_mutators() {
+        let result =
+            convert_mutators_to_cluster_admission_policy(BUILTIN_MODULE, "mutators", vec![]);
+
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    #[case("Pod", "pods")]
+    #[case("Ingress", "ingresses")]
+    #[case("NetworkPolicy", "networkpolicies")]
+    #[case("Gateway", "gateways")]
+    #[case("*", "*")]
+    fn guess_resource_from_kind(#[case] kind: &str, #[case] resource: &str) {
+        assert_eq!(guess_resource(kind), resource);
+    }
+}
@@ -1,8 +1,10 @@
 use anyhow::{Result, anyhow};
-use k8s_openapi::api::admissionregistration::v1::{
-    ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding,
+use k8s_openapi::api::admissionregistration::{
+    v1::{ValidatingAdmissionPolicy, ValidatingAdmissionPolicyBinding},
+    v1alpha1::{MutatingAdmissionPolicy, MutatingAdmissionPolicyBinding},
 };
 use policy_evaluator::{policy_fetcher::oci_client::Reference, policy_metadata::Rule};
+use serde::Serialize;
 use std::{collections::BTreeSet, convert::TryFrom, fs::File, path::Path};
 use tracing::warn;
 
@@ -14,12 +16,10 @@ pub(crate) fn vap(cel_policy_module: &str, vap_path: &Path, binding_path: &Path)
     let binding_file = File::open(binding_path)
         .map_err(|e| anyhow!("cannot open {}: #{e}", binding_path.to_str().unwrap()))?;
 
-    let vap: ValidatingAdmissionPolicy = serde_yaml::from_reader(vap_file)
-        .map_err(|e| anyhow!("cannot convert given data into a ValidatingAdmissionPolicy: #{e}"))?;
-    let vap_binding: ValidatingAdmissionPolicyBinding = serde_yaml::from_reader(binding_file)
-        .map_err(|e| {
-            anyhow!("cannot convert given data into a ValidatingAdmissionPolicyBinding: #{e}")
-        })?;
+    let policy: serde_yaml::Value = serde_yaml::from_reader(vap_file)
+        .map_err(|e| anyhow!("cannot parse {}: #{e}", vap_path.to_str().unwrap()))?;
+    let binding: serde_yaml::Value = serde_yaml::from_reader(binding_file)
+        .map_err(|e| anyhow!("cannot parse {}: #{e}", binding_path.to_str().unwrap()))?;
 
     match cel_policy_module.parse::<Reference>() {
         Ok(cel_policy_ref) => match cel_policy_ref.tag() {
@@ -35,56 +35,73 @@ pub(crate) fn vap(cel_policy_module: &str, vap_path: &Path, binding_path: &Path)
         }
     }
 
-    let cluster_admission_policy =
-        convert_vap_to_cluster_admission_policy(cel_policy_module, vap, vap_binding)?;
+    let cluster_admission_policy = match policy.get("kind").and_then(|kind| kind.as_str()) {
+        Some("MutatingAdmissionPolicy") => {
+            let map: MutatingAdmissionPolicy = serde_yaml::from_value(policy).map_err(|e| {
+                anyhow!("cannot convert given data into a MutatingAdmissionPolicy: #{e}")
+            })?;
+            let map_binding: MutatingAdmissionPolicyBinding = serde_yaml::from_value(binding)
+                .map_err(|e| {
+                    anyhow!("cannot convert given data into a MutatingAdmissionPolicyBinding: #{e}")
+                })?;
+            convert_map_to_cluster_admission_policy(cel_policy_module, map, map_binding)?
+        }
+        _ => {
+            let vap: ValidatingAdmissionPolicy = serde_yaml::from_value(policy).map_err(|e| {
+                anyhow!("cannot convert given data into a ValidatingAdmissionPolicy: #{e}")
+            })?;
+            let vap_binding: ValidatingAdmissionPolicyBinding = serde_yaml::from_value(binding)
+                .map_err(|e| {
+                    anyhow!(
+                        "cannot convert given data into a ValidatingAdmissionPolicyBinding: #{e}"
+                    )
+                })?;
+            convert_vap_to_cluster_admission_policy(cel_policy_module, vap, vap_binding)?
+        }
+    };
 
     serde_yaml::to_writer(std::io::stdout(), &cluster_admission_policy)?;
 
     Ok(())
 }
 
-fn convert_vap_to_cluster_admission_policy(
-    cel_policy_module: &str,
-    vap: ValidatingAdmissionPolicy,
-    vap_binding: ValidatingAdmissionPolicyBinding,
-) -> anyhow::Result<ClusterAdmissionPolicy> {
-    let vap_spec = vap.spec.unwrap_or_default();
-    let vap_binding_spec = vap_binding.spec.unwrap_or_default();
-    if vap_spec.audit_annotations.is_some() {
-        warn!(
-            "auditAnnotations are not supported by Kubewarden's CEL policy yet. They will be ignored."
-        );
-    }
-    if vap_spec.match_conditions.is_some() {
-        warn!(
-            "matchConditions are not supported by Kubewarden's CEL policy yet. They will be ignored."
-        );
-    }
-
+/// Migrate the settings shared by ValidatingAdmissionPolicy and MutatingAdmissionPolicy
+/// into the settings of Kubewarden's CEL policy
+fn migrate_common_settings<V, K, R>(
+    failure_policy: Option<String>,
+    variables: Option<Vec<V>>,
+    param_kind: Option<K>,
+    param_ref: Option<R>,
+) -> Result<serde_yaml::Mapping>
+where
+    V: Serialize,
+    K: Serialize,
+    R: Serialize,
+{
     let mut settings = serde_yaml::Mapping::new();
 
-    if let Some(vap_failure_policy) = vap_spec.failure_policy {
+    if let Some(failure_policy) = failure_policy {
         // CEL settings.failurePolicy, not to confuse with spec.failurePolicy
         settings.insert(
             "failurePolicy".into(),
-            serde_yaml::to_value(vap_failure_policy)?,
+            serde_yaml::to_value(failure_policy)?,
         );
     }
 
     // migrate CEL variables
-    if let Some(vap_variables) = vap_spec.variables {
-        let vap_variables: Vec<serde_yaml::Value> = vap_variables
+    if let Some(variables) = variables {
+        let variables: Vec<serde_yaml::Value> = variables
             .iter()
-            .map(|v| serde_yaml::to_value(v).expect("cannot convert VAP variable to YAML"))
+            .map(|v| serde_yaml::to_value(v).expect("cannot convert variable to YAML"))
             .collect();
-        settings.insert("variables".into(), vap_variables.into());
+        settings.insert("variables".into(), variables.into());
     }
 
     // migrate CEL params
-    match (vap_spec.param_kind, vap_binding_spec.param_ref) {
-        (Some(vap_param_kind), Some(vap_param_ref)) => {
-            settings.insert("paramKind".into(), serde_yaml::to_value(vap_param_kind)?);
-            settings.insert("paramRef".into(), serde_yaml::to_value(vap_param_ref)?);
+    match (param_kind, param_ref) {
+        (Some(param_kind), Some(param_ref)) => {
+            settings.insert("paramKind".into(), serde_yaml::to_value(param_kind)?);
+            settings.insert("paramRef".into(), serde_yaml::to_value(param_ref)?);
         }
         (None, None) => {}
         _ => {
@@ -94,6 +111,34 @@ fn convert_vap_to_cluster_admission_policy(
         }
     }
 
+    Ok(settings)
+}
+
+fn convert_vap_to_cluster_admission_policy(
+    cel_policy_module: &str,
+    vap: ValidatingAdmissionPolicy,
+    vap_binding: ValidatingAdmissionPolicyBinding,
+) -> anyhow::Result<ClusterAdmissionPolicy> {
+    let vap_spec = vap.spec.unwrap_or_default();
+    let vap_binding_spec = vap_binding.spec.unwrap_or_default();
+    if vap_spec.audit_annotations.is_some() {
+        warn!(
+            "auditAnnotations are not supported by Kubewarden's CEL policy yet. They will be ignored."
+        );
+    }
+    if vap_spec.match_conditions.is_some() {
+        warn!(
+            "matchConditions are not supported by Kubewarden's CEL policy yet. They will be ignored."
+        );
+    }
+
+    let mut settings = migrate_common_settings(
+        vap_spec.failure_policy,
+        vap_spec.variables,
+        vap_spec.param_kind,
+        vap_binding_spec.param_ref,
+    )?;
+
     // migrate CEL validations
     if let Some(vap_validations) = vap_spec.validations {
         let kw_cel_validations: Vec<serde_yaml::Value> = vap_validations
@@ -143,6 +188,84 @@ fn convert_vap_to_cluster_admission_policy(
     Ok(cluster_admission_policy)
 }
 
+fn convert_map_to_cluster_admission_policy(
+    cel_policy_module: &str,
+    map: MutatingAdmissionPolicy,
+    map_binding: MutatingAdmissionPolicyBinding,
+) -> anyhow::Result<ClusterAdmissionPolicy> {
+    let map_spec = map.spec.unwrap_or_default();
+    let map_binding_spec = map_binding.spec.unwrap_or_default();
+    if map_spec.match_conditions.is_some() {
+        warn!(
+            "matchConditions are not supported by Kubewarden's CEL policy yet. They will be ignored."
+        );
+    }
+    if map_spec.reinvocation_policy.is_some() {
+        warn!(
+            "reinvocationPolicy is not supported by Kubewarden, mutating policies are never reinvoked. It will be ignored."
+        );
+    }
+
+    let mut settings = migrate_common_settings(
+        map_spec.failure_policy,
+        map_spec.variables,
+        map_spec.param_kind,
+        map_binding_spec.param_ref,
+    )?;
+
+    // migrate CEL mutations, both ApplyConfiguration and JSONPatch ones
+    let map_mutations = map_spec.mutations.unwrap_or_default();
+    if map_mutations.is_empty() {
+        return Err(anyhow!(
+            "MutatingAdmissionPolicy must define at least one mutation"
+        ));
+    }
+    let kw_cel_mutations: Vec<serde_yaml::Value> = map_mutations
+        .iter()
+        .map(|m| serde_yaml::to_value(m).expect("cannot convert MAP mutation to YAML"))
+        .collect();
+    settings.insert("mutations".into(), kw_cel_mutations.into());
+
+    // MAP specifies the namespace selector inside of the binding
+    let namespace_selector = map_binding_spec
+        .match_resources
+        .unwrap_or_default()
+        .namespace_selector;
+
+    // MAP rules are specified inside of the MAP object
+    let map_match_constraints = map_spec.match_constraints.unwrap_or_default();
+    let match_policy = map_match_constraints.match_policy;
+    let rules = map_match_constraints
+        .resource_rules
+        .unwrap_or_default()
+        .iter()
+        .map(Rule::try_from)
+        .collect::<Result<Vec<Rule>, &'static str>>()
+        .map_err(|e| anyhow!("error converting MAP matchConstraints into rules: {e}"))?;
+
+    // migrate MAP
+    let cluster_admission_policy = ClusterAdmissionPolicy {
+        api_version: "policies.kubewarden.io/v1".to_string(),
+        kind: "ClusterAdmissionPolicy".to_string(),
+        metadata: map_binding.metadata,
+        spec: ClusterAdmissionPolicySpec {
+            module: cel_policy_module.to_string(),
+            namespace_selector,
+            match_policy,
+            rules,
+            object_selector: map_match_constraints.object_selector,
+            mutating: true,
+            background_audit: true,
+            context_aware_resources: BTreeSet::new(),
+            failure_policy: None,
+            mode: None,
+            settings,
+        },
+    };
+
+    Ok(cluster_admission_policy)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -305,4 +428,99 @@ mod tests {
             );
         }
     }
+
+    #[rstest]
+    #[case::map_without_params("vap/map.yml", "vap/map-binding.yml", true, false)]
+    #[case::map_with_params("vap/map-with-params.yml", "vap/map-binding-params.yml", false, true)]
+    fn from_map_to_cluster_admission_policy(
+        #[case] map_yaml_path: &str,
+        #[case] map_binding_yaml_path: &str,
+        #[case] has_variables: bool,
+        #[case] has_params: bool,
+    ) {
+        let yaml_file = File::open(test_data(map_yaml_path)).unwrap();
+        let map: MutatingAdmissionPolicy = serde_yaml::from_reader(yaml_file).unwrap();
+        let yaml_file = File::open(test_data(map_binding_yaml_path)).unwrap();
+        let map_binding: MutatingAdmissionPolicyBinding =
+            serde_yaml::from_reader(yaml_file).unwrap();
+
+        let map_spec = map.clone().spec.unwrap();
+        let expected_mutations = serde_yaml::to_value(map_spec.mutations.unwrap()).unwrap();
+        let expected_rules = map_spec
+            .match_constraints
+            .unwrap()
+            .resource_rules
+            .unwrap()
+            .iter()
+            .map(Rule::try_from)
+            .collect::<Result<Vec<Rule>, &str>>()
+            .unwrap();
+        let expected_failure_policy = serde_yaml::to_value(map_spec.failure_policy).unwrap();
+
+        let cluster_admission_policy =
+            convert_map_to_cluster_admission_policy(CEL_POLICY_MODULE, map, map_binding.clone())
+                .unwrap();
+
+        assert_eq!(CEL_POLICY_MODULE, cluster_admission_policy.spec.module);
+        assert!(cluster_admission_policy.spec.mutating);
+        assert_eq!(cluster_admission_policy.spec.rules, expected_rules);
+        assert_eq!(map_binding.metadata, cluster_admission_policy.metadata);
+        assert_eq!(
+            map_binding
+                .spec
+                .unwrap()
+                .match_resources
+                .unwrap()
+                .namespace_selector,
+            cluster_admission_policy.spec.namespace_selector
+        );
+        assert_eq!(
+            expected_failure_policy,
+            cluster_admission_policy.spec.settings["failurePolicy"]
+        );
+        assert_eq!(
+            expected_mutations,
+            cluster_admission_policy.spec.settings["mutations"]
+        );
+        assert!(
+            !cluster_admission_policy
+                .spec
+                .settings
+                .contains_key("validations")
+        );
+        assert_eq!(
+            has_variables,
+            cluster_admission_policy
+                .spec
+                .settings
+                .contains_key("variables")
+        );
+        assert_eq!(
+            has_params,
+            cluster_admission_policy
+                .spec
+                .settings
+                .contains_key("paramKind")
+        );
+        assert_eq!(
+            has_params,
+            cluster_admission_policy
+                .spec
+                .settings
+                .contains_key("paramRef")
+        );
+    }
+
+    #[test]
+    fn map_without_mutations_is_rejected() {
+        let yaml_file = File::open(test_data("vap/map-without-mutations.yml")).unwrap();
+        let map: MutatingAdmissionPolicy = serde_yaml::from_reader(yaml_file).unwrap();
+        let yaml_file = File::open(test_data("vap/map-binding.yml")).unwrap();
+        let map_binding: MutatingAdmissionPolicyBinding =
+            serde_yaml::from_reader(yaml_file).unwrap();
+
+        let result = convert_map_to_cluster_admission_policy(CEL_POLICY_MODULE, map, map_binding);
+
+        assert!(result.is_err());
+    }
 }
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use policy_evaluator::policy_fetcher::verify::config::{
-    LatestVerificationConfig, Signature, VersionedVerificationConfig,
+    LatestVerificationConfig, Signature, SignatureRequirements, VersionedVerificationConfig,
 };
 
 pub(crate) fn verification_config() -> Result<String> {
@@ -27,13 +27,17 @@ pub(crate) fn verification_config() -> Result<String> {
     );
 
     let kubewarden_verification_config =
-        VersionedVerificationConfig::V1(LatestVerificationConfig {
-            all_of: Some(vec![Signature::GithubAction {
-                owner: "kubewarden".to_string(),
-                repo: None,
-                annotations: None,
-            }]),
-            any_of: None,
+        VersionedVerificationConfig::V2(LatestVerificationConfig {
+            default: Some(SignatureRequirements {
+                all_of: Some(vec![Signature::GithubAction {
+                    owner: "kubewarden".to_string(),
+                    repo: None,
+                    annotations: None,
+                }]),
+                any_of: None,
+                attestations: None,
+            }),
+            ..Default::default()
         });
 
     Ok(format!(
@@ -96,11 +96,12 @@ pub(crate) fn new_policy_execution_mode_from_str(name: &str) -> Result<PolicyExe
     let execution_mode: PolicyExecutionMode =
         serde_json::from_value(json!(name)).map_err(|_| {
             anyhow!(
-                "Unknown policy execution mode \"{}\". Valid values are {}, {}, {}",
+                "Unknown policy execution mode \"{}\". Valid values are {}, {}, {}, {}",
                 name,
                 serde_json::to_string(&PolicyExecutionMode::KubewardenWapc).unwrap(),
                 serde_json::to_string(&PolicyExecutionMode::Opa).unwrap(),
                 serde_json::to_string(&PolicyExecutionMode::OpaGatekeeper).unwrap(),
+                serde_json::to_string(&PolicyExecutionMode::GatekeeperMutation).unwrap(),
             )
         })?;
     Ok(execution_mode)
@@ -172,6 +173,10 @@ mod tests {
             String::from("kubewarden-wapc"),
             PolicyExecutionMode::KubewardenWapc,
         );
+        data.insert(
+            String::from("gatekeeper-mutation"),
+            PolicyExecutionMode::GatekeeperMutation,
+        );
 
         for (name, mode) in data {
             let actual = new_policy_execution_mode_from_str(name.as_str());
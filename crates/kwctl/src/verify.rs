@@ -1,33 +1,140 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use policy_evaluator::policy_fetcher::{
     policy::Policy,
     sigstore::trust::sigstore::SigstoreTrustRoot,
     sources::Sources,
-    verify::{Verifier, config::LatestVerificationConfig},
+    store::Store,
+    verify::{
+        VerificationReport, Verifier,
+        config::{LatestVerificationConfig, PolicyVerification},
+    },
 };
 use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 use tracing::{debug, info};
 
 pub(crate) type VerificationAnnotations = BTreeMap<String, String>;
 
+/// Verifies the policy, returning the digest of the verified manifest and its
+/// verified attestations. No report is returned when the policy is allowed to
+/// be unsigned.
 pub(crate) async fn verify(
     url: &str,
     sources: Option<&Sources>,
     verification_config: &LatestVerificationConfig,
     sigstore_trust_root: Option<Arc<SigstoreTrustRoot>>,
-) -> Result<String> {
+) -> Result<Option<VerificationReport>> {
     debug!(
         policy = url,
         ?sources,
         ?verification_config,
         "Verifying policy"
     );
+    let signature_requirements = match verification_config.policy_verification(url)? {
+        PolicyVerification::Signatures(signature_requirements) => signature_requirements,
+        PolicyVerification::Unsigned => {
+            info!(
+                policy = url,
+                "Policy allowed to be unsigned, skipping verification"
+            );
+            return Ok(None);
+        }
+    };
     let mut verifier = Verifier::new(sources.cloned(), sigstore_trust_root).await?;
-    let verified_manifest_digest = verifier.verify(url, verification_config).await?;
+    let report = verifier
+        .verify_with_report(url, signature_requirements)
+        .await?;
+    log_attestations(&report);
+
+    info!("Policy successfully verified");
+    Ok(Some(report))
+}
+
+/// Verifies a policy loaded from an OCI image layout, using the signatures and
+/// the attestations saved inside of the store. No registry is contacted.
+pub(crate) async fn verify_stored(
+    url: &str,
+    store: &Store,
+    verification_config: &LatestVerificationConfig,
+    sigstore_trust_root: Option<Arc<SigstoreTrustRoot>>,
+) -> Result<()> {
+    debug!(
+        policy = url,
+        ?verification_config,
+        "Verifying policy using the signatures inside of the store"
+    );
+    let signature_requirements = match verification_config.policy_verification(url)? {
+        PolicyVerification::Signatures(signature_requirements) => signature_requirements,
+        PolicyVerification::Unsigned => {
+            info!(
+                policy = url,
+                "Policy allowed to be unsigned, skipping verification"
+            );
+            return Ok(());
+        }
+    };
+    let verifier = Verifier::new(None, sigstore_trust_root).await?;
+    let report = verifier
+        .verify_stored(store, url, signature_requirements)?
+        .ok_or_else(|| {
+            anyhow!(
+                "the signatures of policy {} are not inside of the store",
+                url
+            )
+        })?;
+    log_attestations(&report);
 
     info!("Policy successfully verified");
-    Ok(verified_manifest_digest)
+    Ok(())
+}
+
+fn log_attestations(report: &VerificationReport) {
+    for attestation in &report.attestations {
+        info!(
+            predicate_type = attestation.predicate_type,
+            builder_id = attestation.builder_id,
+            source_repository = attestation.source_repository,
+            source_ref = attestation.source_ref,
+            "Attestation successfully verified"
+        );
+    }
+}
+
+/// Verifies a local policy, or one inside of the store, using a Sigstore bundle.
+/// No registry is contacted.
+pub(crate) async fn verify_bundle(
+    uri_or_sha_prefix: &str,
+    bundle_path: &Path,
+    verification_config: &LatestVerificationConfig,
+    sigstore_trust_root: Option<Arc<SigstoreTrustRoot>>,
+) -> Result<()> {
+    let uri = crate::utils::map_path_to_uri(uri_or_sha_prefix)?;
+    let policy = Policy {
+        local_path: crate::utils::wasm_path(&uri)?,
+        uri,
+    };
+    debug!(
+        policy = policy.uri.as_str(),
+        bundle = ?bundle_path,
+        ?verification_config,
+        "Verifying policy using Sigstore bundle"
+    );
+    let signature_requirements = match verification_config.policy_verification(&policy.uri)? {
+        PolicyVerification::Signatures(signature_requirements) => signature_requirements,
+        PolicyVerification::Unsigned => {
+            info!(
+                policy = policy.uri.as_str(),
+                "Policy allowed to be unsigned, skipping verification"
+            );
+            return Ok(());
+        }
+    };
+    let verifier = Verifier::new(None, sigstore_trust_root).await?;
+    verifier.verify_bundle(&policy, bundle_path, signature_requirements)?;
+
+    info!("Policy successfully verified");
+    Ok(())
 }
 
 pub(crate) async fn verify_local_checksum(
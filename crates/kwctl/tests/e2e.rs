@@ -105,6 +105,23 @@ fn test_policies() {
         .stdout(contains("v0.1.13"));
 }
 
+#[test]
+fn test_gc_keeps_referenced_policies() {
+    let tempdir = tempdir().unwrap();
+    pull_policies(tempdir.path(), POLICIES);
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("gc");
+    cmd.assert().success().stderr(contains("Removed 0 modules"));
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("policies").arg("--prune");
+    cmd.assert()
+        .success()
+        .stdout(contains("pod-privileged"))
+        .stdout(contains("safe-labels"));
+}
+
 #[rstest]
 #[case::https(
     "https://github.com/kubewarden/pod-privileged-policy/releases/download/v0.2.5/policy.wasm"
@@ -983,6 +1000,14 @@ fn test_scaffold_manifest(#[case] pull_policies_before: bool) {
     contains("module: ghcr.io/kubewarden/tests/cel-policy:1.0.0"),
     is_empty()
 )]
+#[case::mutating_admission_policy(
+    Some("vap/map.yml"),
+    Some("vap/map-binding.yml"),
+    Some("ghcr.io/kubewarden/tests/cel-policy:1.0.0"),
+    true,
+    contains("mutating: true"),
+    contains("reinvocationPolicy is not supported")
+)]
 #[case::missing_policy(
     None,
     Some("vap/vap-binding.yml"),
@@ -1032,6 +1057,73 @@ fn test_scaffold_from_vap(
     cmd.assert().stderr(stderr_predicate);
 }
 
+#[rstest]
+#[case::mutators(
+    vec!["gatekeeper-mutation/mutators.yml"],
+    true,
+    contains("mutating: true").and(contains("kind: AssignImage")),
+    contains("are evaluated by the policy")
+)]
+#[case::invalid_mutator(
+    vec!["gatekeeper-mutation/invalid-mutator.yml"],
+    false,
+    is_empty(),
+    contains("metadata can be mutated only by AssignMetadata")
+)]
+#[case::missing_mutators(
+    vec![],
+    false,
+    is_empty(),
+    contains("the following required arguments were not provided")
+)]
+fn test_scaffold_from_gatekeeper_mutators(
+    #[case] mutators: Vec<&str>,
+    #[case] success: bool,
+    #[case] stdout_predicate: impl predicates::str::PredicateStrExt,
+    #[case] stderr_predicate: impl predicates::str::PredicateStrExt,
+) {
+    let tempdir = tempdir().unwrap();
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("scaffold").arg("gatekeeper-mutation");
+    for mutator in mutators {
+        cmd.arg(test_data(mutator));
+    }
+
+    if success {
+        cmd.assert().success();
+    } else {
+        cmd.assert().failure();
+    }
+
+    cmd.assert().stdout(stdout_predicate);
+    cmd.assert().stderr(stderr_predicate);
+}
+
+#[test]
+fn test_run_scaffolded_gatekeeper_mutators() {
+    let tempdir = tempdir().unwrap();
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("scaffold")
+        .arg("gatekeeper-mutation")
+        .arg(test_data("gatekeeper-mutation/mutators.yml"));
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let policy_file = write_tmp_yaml_file(&output.stdout);
+
+    // the builtin module is used, nothing is pulled
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("run")
+        .arg("--request-path")
+        .arg(test_data("unprivileged-pod.json"))
+        .arg(policy_file.path());
+
+    cmd.assert().success();
+    cmd.assert().stdout(contains("\"allowed\":true"));
+    cmd.assert().stdout(contains("\"patchType\":\"JSONPatch\""));
+}
+
 #[rstest]
 #[case::matching_capabilities("context-aware-annotate/metadata-correct.yml", false)]
 #[case::mismatched_capabilities("context-aware-annotate/metadata-wrong.yml", true)]
@@ -1116,6 +1208,114 @@ fn test_annotate_rego(
     }
 }
 
+#[test]
+fn test_inspect_rego_policy_reports_builtins() {
+    let tempdir = tempdir().unwrap();
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("annotate")
+        .arg("-m")
+        .arg(test_data("rego-annotate/metadata-correct.yml"))
+        .arg(test_data("rego-annotate/no-default-namespace-rego.wasm"))
+        .arg("-o")
+        .arg("annotated-policy.wasm");
+    cmd.assert().success();
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("inspect").arg("annotated-policy.wasm");
+
+    cmd.assert()
+        .success()
+        .stdout(contains("Rego Builtins"))
+        .stdout(contains("unsupported").not());
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("inspect")
+        .arg("-o")
+        .arg("yaml")
+        .arg("annotated-policy.wasm");
+
+    cmd.assert().success();
+    let metadata: serde_yaml::Mapping = serde_yaml::from_slice(&cmd.assert().get_output().stdout)
+        .expect("a valid yaml document was expected");
+    assert!(
+        !metadata.contains_key("regoBuiltins"),
+        "the yaml output should contain only the metadata"
+    );
+}
+
+#[test]
+fn test_rego_eval_lists_entrypoints() {
+    let tempdir = tempdir().unwrap();
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("rego")
+        .arg("eval")
+        .arg("--list-entrypoints")
+        .arg(test_data("rego-annotate/no-default-namespace-rego.wasm"));
+
+    cmd.assert()
+        .success()
+        .stdout(contains("OPA Wasm ABI"))
+        .stdout(contains("0: policy/main"))
+        .stdout(contains("Evaluation of").not());
+}
+
+#[rstest]
+#[case::allowed("kube-system", true)]
+#[case::rejected("default", false)]
+fn test_rego_eval(#[case] namespace: &str, #[case] allowed: bool) {
+    let tempdir = tempdir().unwrap();
+    let input = serde_json::json!({
+        "request": {
+            "uid": "1299d386-525b-4032-98ae-1949f69f9cfc",
+            "object": {
+                "metadata": {
+                    "namespace": namespace,
+                }
+            }
+        }
+    });
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("rego")
+        .arg("eval")
+        .arg("-o")
+        .arg("json")
+        .arg("--input")
+        .arg(input.to_string())
+        .arg("--entrypoint")
+        .arg("policy/main")
+        .arg(test_data("rego-annotate/no-default-namespace-rego.wasm"));
+
+    cmd.assert().success();
+    let report: serde_json::Value =
+        serde_json::from_slice(&cmd.assert().get_output().stdout).unwrap();
+    let evaluations = report["evaluations"].as_array().unwrap();
+    assert_eq!(evaluations.len(), 1);
+    assert_eq!(evaluations[0]["entrypoint"]["name"], "policy/main");
+    assert_eq!(
+        evaluations[0]["result"][0]["result"]["response"]["allowed"],
+        allowed
+    );
+}
+
+#[test]
+fn test_rego_eval_unknown_entrypoint() {
+    let tempdir = tempdir().unwrap();
+
+    let mut cmd = setup_command(tempdir.path());
+    cmd.arg("rego")
+        .arg("eval")
+        .arg("--entrypoint")
+        .arg("policy/violation")
+        .arg(test_data("rego-annotate/no-default-namespace-rego.wasm"));
+
+    cmd.assert()
+        .failure()
+        .stderr(contains("Cannot find entrypoint 'policy/violation'"));
+}
+
 #[rstest]
 #[case::show_signatures(true)]
 #[case::hide_signatures(false)]
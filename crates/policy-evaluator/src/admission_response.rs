@@ -1,4 +1,5 @@
 use crate::errors::ResponseError;
+use crate::mutating_admission_policy::{Mutation, apply_mutations};
 
 use base64::{Engine as _, engine::general_purpose};
 use kubewarden_policy_sdk::response::ValidationResponse as PolicyValidationResponse;
@@ -43,6 +44,30 @@ pub struct AdmissionResponse {
     pub warnings: Option<Vec<String>>,
 }
 
+/// The validation response returned by a waPC or WASI policy.
+///
+/// This extends the `ValidationResponse` of the Kubewarden policy SDK with an
+/// optional list of causes. A policy can use them to report all the violations
+/// it found, each one pointing to the field of the object that caused it.
+/// Policies that do not know about this extension keep working, because the
+/// `causes` attribute is optional.
+#[derive(Deserialize, Debug)]
+pub struct ExtendedPolicyValidationResponse {
+    #[serde(flatten)]
+    pub response: PolicyValidationResponse,
+
+    /// Field level violations found by the policy. They are propagated to
+    /// the `status.details.causes` of the AdmissionResponse.
+    #[serde(default)]
+    pub causes: Vec<StatusCause>,
+
+    /// The evaluated mutations of a `MutatingAdmissionPolicy`. Instead of
+    /// returning the mutated object, a policy can return them: they are
+    /// applied to the request object to build the JSONPatch of the response.
+    #[serde(default)]
+    pub mutations: Vec<Mutation>,
+}
+
 /// PatchType is the type of patch being used to represent the mutated object
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
 pub enum PatchType {
@@ -172,6 +197,75 @@ impl AdmissionResponse {
             status,
         })
     }
+
+    pub fn from_extended_policy_validation_response(
+        uid: String,
+        req_obj: Option<&serde_json::Value>,
+        pol_val_resp: &ExtendedPolicyValidationResponse,
+    ) -> Result<AdmissionResponse, ResponseError> {
+        if pol_val_resp.mutations.is_empty() || !pol_val_resp.response.accepted {
+            return AdmissionResponse::from_policy_validation_response(
+                uid,
+                req_obj,
+                &pol_val_resp.response,
+            )
+            .map(|response| response.with_status_causes(pol_val_resp.causes.clone()));
+        }
+
+        if pol_val_resp.response.mutated_object.is_some() {
+            return Err(ResponseError::MutationsAndMutatedObject);
+        }
+        // DELETE requests have no object, the mutation is rejected later on
+        let mutated_object = match req_obj {
+            Some(req_obj) => apply_mutations(req_obj, &pol_val_resp.mutations)
+                .map_err(ResponseError::Mutation)?,
+            None => serde_json::Value::Null,
+        };
+        let response = PolicyValidationResponse {
+            accepted: pol_val_resp.response.accepted,
+            message: pol_val_resp.response.message.clone(),
+            code: pol_val_resp.response.code,
+            mutated_object: Some(mutated_object),
+            audit_annotations: pol_val_resp.response.audit_annotations.clone(),
+            warnings: pol_val_resp.response.warnings.clone(),
+        };
+
+        AdmissionResponse::from_policy_validation_response(uid, req_obj, &response)
+    }
+
+    /// Add the given causes to the `status.details` of a rejected response.
+    ///
+    /// When the response does not have a message, one is built by joining
+    /// the messages of the causes, so that it's still shown by `kubectl`.
+    /// The status of an accepted response is discarded by Kubernetes, hence
+    /// accepted responses are returned unchanged.
+    pub fn with_status_causes(self, causes: Vec<StatusCause>) -> AdmissionResponse {
+        if self.allowed || causes.is_empty() {
+            return self;
+        }
+
+        let status = self.status.unwrap_or_default();
+        let message = status.message.or_else(|| {
+            Some(
+                causes
+                    .iter()
+                    .filter_map(|cause| cause.message.clone())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )
+        });
+        let mut details = status.details.unwrap_or_default();
+        details.causes.extend(causes);
+
+        AdmissionResponse {
+            status: Some(AdmissionResponseStatus {
+                message,
+                details: Some(details),
+                ..status
+            }),
+            ..self
+        }
+    }
 }
 
 /// StatusReason is an enumeration of possible failure causes.
@@ -319,6 +413,8 @@ pub struct StatusDetails {
 pub struct StatusCause {
     // A machine-readable description of the cause of the error. If this value is
     // empty there is no information available.
+    // Unknown values are ignored, the rest of the cause is kept.
+    #[serde(default, deserialize_with = "deserialize_cause_type")]
     pub reason: Option<CauseType>,
 
     // A human-readable description of the cause of the error.  This field may be
@@ -336,6 +432,16 @@ pub struct StatusCause {
     pub field: Option<String>,
 }
 
+/// Policies are free to return any reason, only the ones known by Kubernetes
+/// are kept. Failing here would discard the whole response of the policy.
+fn deserialize_cause_type<'de, D>(deserializer: D) -> Result<Option<CauseType>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let reason = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(reason.and_then(|reason| serde_json::from_value(reason).ok()))
+}
+
 /// CauseType is a machine readable value providing more detail about what occurred in a
 /// status response.
 /// An operation may have multiple causes for a status (whether Failure or Success).
@@ -563,4 +669,222 @@ mod tests {
             serde_json::from_slice(patch_decoded_str.as_slice()).unwrap();
         assert_eq!(patch, expected_diff);
     }
+
+    #[test]
+    fn create_from_extended_policy_validation_response_with_causes() {
+        let uid = String::from("UID");
+        let req_obj = Some(json!({"hello": "world"}));
+
+        let pol_val_resp: ExtendedPolicyValidationResponse = serde_json::from_value(json!({
+            "accepted": false,
+            "causes": [
+                {
+                    "reason": "FieldValueInvalid",
+                    "message": "image tag 'latest' is not allowed",
+                    "field": "spec.containers[0].image"
+                },
+                {
+                    "reason": "FieldValueRequired",
+                    "message": "resource limits are required",
+                    "field": "spec.containers[1].resources.limits"
+                }
+            ]
+        }))
+        .unwrap();
+
+        let response = AdmissionResponse::from_extended_policy_validation_response(
+            uid.clone(),
+            req_obj.as_ref(),
+            &pol_val_resp,
+        )
+        .unwrap();
+
+        assert_eq!(response.uid, uid);
+        assert!(!response.allowed);
+
+        let status = response.status.unwrap();
+        assert_eq!(
+            status.message,
+            Some("image tag 'latest' is not allowed, resource limits are required".to_string())
+        );
+        assert_eq!(
+            status.details.unwrap().causes,
+            vec![
+                StatusCause {
+                    reason: Some(CauseType::FieldValueInvalid),
+                    message: Some("image tag 'latest' is not allowed".to_string()),
+                    field: Some("spec.containers[0].image".to_string()),
+                },
+                StatusCause {
+                    reason: Some(CauseType::FieldValueRequired),
+                    message: Some("resource limits are required".to_string()),
+                    field: Some("spec.containers[1].resources.limits".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn extended_policy_validation_response_with_unknown_cause_reason() {
+        let pol_val_resp: ExtendedPolicyValidationResponse = serde_json::from_value(json!({
+            "accepted": false,
+            "causes": [
+                {
+                    "reason": "ImageNotSigned",
+                    "message": "the image is not signed",
+                    "field": "spec.containers[0].image"
+                },
+                {
+                    "message": "resource limits are required"
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            pol_val_resp.causes,
+            vec![
+                StatusCause {
+                    reason: None,
+                    message: Some("the image is not signed".to_string()),
+                    field: Some("spec.containers[0].image".to_string()),
+                },
+                StatusCause {
+                    reason: None,
+                    message: Some("resource limits are required".to_string()),
+                    field: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn create_from_extended_policy_validation_response_keeps_policy_message() {
+        let pol_val_resp: ExtendedPolicyValidationResponse = serde_json::from_value(json!({
+            "accepted": false,
+            "message": "the pod is not compliant",
+            "code": 400,
+            "causes": [
+                {
+                    "message": "privileged containers are not allowed",
+                    "field": "spec.containers[0].securityContext.privileged"
+                }
+            ]
+        }))
+        .unwrap();
+
+        let response = AdmissionResponse::from_extended_policy_validation_response(
+            "UID".to_string(),
+            None,
+            &pol_val_resp,
+        )
+        .unwrap();
+
+        let status = response.status.unwrap();
+        assert_eq!(status.message, Some("the pod is not compliant".to_string()));
+        assert_eq!(status.code, Some(400));
+        assert_eq!(status.details.unwrap().causes.len(), 1);
+    }
+
+    #[test]
+    fn create_from_extended_policy_validation_response_without_causes() {
+        let pol_val_resp: ExtendedPolicyValidationResponse =
+            serde_json::from_value(json!({"accepted": true})).unwrap();
+
+        let response = AdmissionResponse::from_extended_policy_validation_response(
+            "UID".to_string(),
+            None,
+            &pol_val_resp,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        assert!(response.status.is_none());
+    }
+
+    #[test]
+    fn create_from_extended_policy_validation_response_with_mutations() {
+        let req_obj = json!({"metadata": {"name": "nginx", "labels": {"app": "nginx"}}});
+        let pol_val_resp: ExtendedPolicyValidationResponse = serde_json::from_value(json!({
+            "accepted": true,
+            "mutations": [
+                {
+                    "patchType": "ApplyConfiguration",
+                    "applyConfiguration": {"metadata": {"labels": {"owner": "team-a"}}}
+                },
+                {
+                    "patchType": "JSONPatch",
+                    "jsonPatch": [{"op": "remove", "path": "/metadata/labels/app"}]
+                }
+            ]
+        }))
+        .unwrap();
+
+        let response = AdmissionResponse::from_extended_policy_validation_response(
+            "UID".to_string(),
+            Some(&req_obj),
+            &pol_val_resp,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        assert_eq!(response.patch_type, Some(PatchType::JSONPatch));
+        let patch = general_purpose::STANDARD
+            .decode(response.patch.unwrap())
+            .unwrap();
+        let patch: serde_json::Value = serde_json::from_slice(&patch).unwrap();
+        assert_eq!(
+            patch,
+            json!([
+                {"op": "add", "path": "/metadata/labels/owner", "value": "team-a"},
+                {"op": "remove", "path": "/metadata/labels/app"}
+            ])
+        );
+    }
+
+    #[test]
+    fn create_from_extended_policy_validation_response_with_invalid_mutations() {
+        let req_obj = json!({"metadata": {"name": "nginx"}});
+        let pol_val_resp: ExtendedPolicyValidationResponse = serde_json::from_value(json!({
+            "accepted": true,
+            "mutations": [{
+                "patchType": "JSONPatch",
+                "jsonPatch": [{"op": "remove", "path": "/spec"}]
+            }]
+        }))
+        .unwrap();
+
+        let response = AdmissionResponse::from_extended_policy_validation_response(
+            "UID".to_string(),
+            Some(&req_obj),
+            &pol_val_resp,
+        );
+
+        assert!(matches!(response, Err(ResponseError::Mutation(_))));
+    }
+
+    #[test]
+    fn create_from_extended_policy_validation_response_with_mutations_and_mutated_object() {
+        let req_obj = json!({"metadata": {"name": "nginx"}});
+        let pol_val_resp: ExtendedPolicyValidationResponse = serde_json::from_value(json!({
+            "accepted": true,
+            "mutated_object": {"metadata": {"name": "other"}},
+            "mutations": [{
+                "patchType": "ApplyConfiguration",
+                "applyConfiguration": {"metadata": {"name": "other"}}
+            }]
+        }))
+        .unwrap();
+
+        let response = AdmissionResponse::from_extended_policy_validation_response(
+            "UID".to_string(),
+            Some(&req_obj),
+            &pol_val_resp,
+        );
+
+        assert!(matches!(
+            response,
+            Err(ResponseError::MutationsAndMutatedObject)
+        ));
+    }
 }
@@ -7,9 +7,11 @@ use tracing::{debug, warn};
 
 use crate::callback_handler::kubernetes::field_mask;
 use crate::callback_requests::{CallbackRequest, CallbackRequestType, CallbackResponse};
+use crate::host_capability_provider::HostCapabilityProviders;
 
 mod builder;
 mod crypto;
+mod http;
 mod kubernetes;
 mod oci;
 mod sigstore_verification;
@@ -29,6 +31,8 @@ pub struct CallbackHandler {
     oci_client: Arc<oci::Client>,
     sigstore_client: sigstore_verification::Client,
     kubernetes_client: Option<kubernetes::Client>,
+    http_client: Arc<http::Client>,
+    host_capability_providers: HostCapabilityProviders,
     rx: mpsc::Receiver<CallbackRequest>,
     tx: mpsc::Sender<CallbackRequest>,
     shutdown_channel: oneshot::Receiver<()>,
@@ -66,6 +70,13 @@ impl CallbackHandler {
         self.tx.clone()
     }
 
+    /// Returns the host capability providers registered on the builder. They
+    /// are needed to validate the host capabilities allow list of the policies,
+    /// see [`crate::host_capabilities::HostCapabilities::new_with_providers`]
+    pub fn host_capability_providers(&self) -> HostCapabilityProviders {
+        self.host_capability_providers.clone()
+    }
+
     /// Enter an endless loop that:
     ///    1. Waits for requests to be evaluated
     ///    2. Evaluate the request
@@ -94,6 +105,8 @@ impl CallbackHandler {
         let oci_client = self.oci_client.clone();
         let mut sigstore_client = self.sigstore_client.clone();
         let mut kubernetes_client = self.kubernetes_client.clone();
+        let http_client = self.http_client.clone();
+        let host_capability_providers = self.host_capability_providers.clone();
 
         tokio::spawn(async move {
             match req.request {
@@ -204,6 +217,18 @@ impl CallbackHandler {
                         warn!("callback handler: cannot send response back: {:?}", e);
                     }
                 }
+                CallbackRequestType::HttpRequest { request } => {
+                    let request_description = format!("{} {}", request.method, request.url);
+                    if request.disable_cache || !http::is_cacheable(&request.method) {
+                        handle_callback!(req, request_description, "HTTP request done", {
+                            http::request(&http_client, &request)
+                        })
+                    } else {
+                        handle_callback!(req, request_description, "HTTP request done", {
+                            http::request_cached(&http_client, &request)
+                        })
+                    }
+                }
                 CallbackRequestType::KubernetesListResourceNamespace {
                     api_version,
                     kind,
@@ -330,9 +355,9 @@ impl CallbackHandler {
                 CallbackRequestType::HasKubernetesListResourceAllResultChangedSinceInstant {
                     api_version,
                     kind,
-                    label_selector,
+                    label_selector: _,
                     field_selector,
-                    field_masks,
+                    field_masks: _,
                     since,
                 } => {
                     handle_callback!(
@@ -344,9 +369,7 @@ impl CallbackHandler {
                                 kubernetes_client.as_mut(),
                                 &api_version,
                                 &kind,
-                                label_selector,
                                 field_selector,
-                                field_masks,
                                 since,
                             )
                         }
@@ -378,6 +401,19 @@ impl CallbackHandler {
                         async { crypto::verify_certificate(request) }
                     })
                 }
+                CallbackRequestType::HostCapabilityProvider {
+                    namespace,
+                    operation,
+                    payload,
+                } => {
+                    let capability_path = format!("{namespace}/{operation}");
+                    handle_callback!(
+                        req,
+                        capability_path,
+                        "Host capability provider evaluation done",
+                        { host_capability_providers.call_cached(&namespace, &operation, payload) }
+                    )
+                }
             }
         });
     }
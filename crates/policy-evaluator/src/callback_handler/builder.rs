@@ -2,11 +2,13 @@ use anyhow::Result;
 use policy_fetcher::sigstore::trust::sigstore::SigstoreTrustRoot;
 use policy_fetcher::sources::Sources;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot};
 
 use super::CallbackHandler;
-use super::{oci, sigstore_verification};
+use super::{http, oci, sigstore_verification};
 use crate::callback_requests::CallbackRequest;
+use crate::host_capability_provider::{HostCapabilityProvider, HostCapabilityProviders};
 
 const DEFAULT_CHANNEL_BUFF_SIZE: usize = 100;
 
@@ -17,6 +19,9 @@ pub struct CallbackHandlerBuilder {
     shutdown_channel: oneshot::Receiver<()>,
     trust_root: Option<Arc<SigstoreTrustRoot>>,
     kube_client: Option<kube::Client>,
+    http_request_timeout: Duration,
+    http_response_max_size: usize,
+    host_capability_providers: Vec<Arc<dyn HostCapabilityProvider>>,
 }
 
 impl CallbackHandlerBuilder {
@@ -27,6 +32,9 @@ impl CallbackHandlerBuilder {
             channel_buffer_size: DEFAULT_CHANNEL_BUFF_SIZE,
             trust_root: None,
             kube_client: None,
+            http_request_timeout: http::DEFAULT_TIMEOUT,
+            http_response_max_size: http::DEFAULT_MAX_RESPONSE_SIZE,
+            host_capability_providers: Vec::new(),
         }
     }
 
@@ -56,6 +64,28 @@ impl CallbackHandlerBuilder {
         self
     }
 
+    /// Set the maximum amount of time an HTTP request made by a policy can take.
+    /// Optional, defaults to 5 seconds
+    pub fn http_request_timeout(mut self, timeout: Duration) -> Self {
+        self.http_request_timeout = timeout;
+        self
+    }
+
+    /// Set the maximum size, in bytes, of the body of the HTTP responses given
+    /// back to the policies. Optional, defaults to 1 MiB
+    pub fn http_response_max_size(mut self, size: usize) -> Self {
+        self.http_response_max_size = size;
+        self
+    }
+
+    /// Register a provider of host capabilities, see
+    /// [`crate::host_capability_provider`]. Can be invoked multiple times,
+    /// the namespaces of the providers must be unique. Optional
+    pub fn host_capability_provider(mut self, provider: Arc<dyn HostCapabilityProvider>) -> Self {
+        self.host_capability_providers.push(provider);
+        self
+    }
+
     /// Create a CallbackHandler object
     pub async fn build(self) -> Result<CallbackHandler> {
         let (tx, rx) = mpsc::channel::<CallbackRequest>(self.channel_buffer_size);
@@ -67,10 +97,23 @@ impl CallbackHandlerBuilder {
 
         let kubernetes_client = self.kube_client.map(super::kubernetes::Client::new);
 
+        let http_client = Arc::new(http::Client::new(
+            self.oci_sources.as_ref(),
+            self.http_request_timeout,
+            self.http_response_max_size,
+        )?);
+
+        let mut host_capability_providers = HostCapabilityProviders::default();
+        for provider in self.host_capability_providers {
+            host_capability_providers.register(provider)?;
+        }
+
         Ok(CallbackHandler {
             oci_client,
             sigstore_client,
             kubernetes_client,
+            http_client,
+            host_capability_providers,
             tx,
             rx,
             shutdown_channel: self.shutdown_channel,
@@ -0,0 +1,440 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{Result, anyhow};
+use cached::proc_macro::cached;
+use policy_fetcher::sources::Sources;
+use url::Url;
+
+use crate::callback_requests::{HttpRequest, HttpResponse};
+
+/// Default maximum amount of time an HTTP request can take
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default maximum size of the body of an HTTP response
+pub(crate) const DEFAULT_MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+
+/// Headers a policy is not allowed to set: `Host` would make the request
+/// reach a different virtual host than the allowed one, the hop-by-hop
+/// headers are managed by the HTTP client
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Helper struct used to perform the HTTP requests made by the policies
+pub(crate) struct Client {
+    /// Client used against all the hosts that do not have a dedicated one
+    default_client: reqwest::Client,
+    /// Clients used against the hosts that have custom certificate authorities
+    /// or that are insecure. The key has the `host[:port]` format used by the
+    /// sources file
+    host_clients: HashMap<String, reqwest::Client>,
+    max_response_size: usize,
+}
+
+impl Client {
+    /// Create a new client using the proxies, the insecure sources and the
+    /// certificate authorities defined inside of the given sources.
+    ///
+    /// Redirects are never followed: the allow list of a policy is checked
+    /// against the requested URL, a redirect could lead somewhere else.
+    pub fn new(
+        sources: Option<&Sources>,
+        timeout: Duration,
+        max_response_size: usize,
+    ) -> Result<Self> {
+        let sources_default = Sources::default();
+        let sources = sources.unwrap_or(&sources_default);
+
+        let default_client = client_builder(sources, timeout)?.build()?;
+
+        let mut host_clients = HashMap::new();
+        for (host, certificates) in &sources.source_authorities.0 {
+            let certificates = certificates
+                .iter()
+                .map(reqwest::Certificate::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut builder = client_builder(sources, timeout)?.tls_certs_merge(certificates);
+            if sources.is_insecure_source(host) {
+                builder = builder.danger_accept_invalid_certs(true);
+            }
+            host_clients.insert(host.to_owned(), builder.build()?);
+        }
+        for host in &sources.insecure_sources {
+            if !host_clients.contains_key(host) {
+                let client = client_builder(sources, timeout)?
+                    .danger_accept_invalid_certs(true)
+                    .build()?;
+                host_clients.insert(host.to_owned(), client);
+            }
+        }
+
+        Ok(Client {
+            default_client,
+            host_clients,
+            max_response_size,
+        })
+    }
+
+    /// Perform the given HTTP request
+    pub async fn request(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let url = Url::parse(&request.url)?;
+        let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+            .map_err(|_| anyhow!("invalid HTTP method: {}", request.method))?;
+
+        let host_and_port = format!(
+            "{}{}",
+            url.host_str().unwrap_or_default(),
+            url.port()
+                .map(|port| format!(":{port}"))
+                .unwrap_or_default()
+        );
+        let client = self
+            .host_clients
+            .get(&host_and_port)
+            .unwrap_or(&self.default_client);
+
+        let mut builder = client.request(method, url);
+        for (name, value) in &request.headers {
+            if FORBIDDEN_HEADERS
+                .iter()
+                .any(|forbidden| name.eq_ignore_ascii_case(forbidden))
+            {
+                return Err(anyhow!("the {name} header cannot be set by the policy"));
+            }
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.to_owned());
+        }
+
+        let mut response = builder.send().await?;
+
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_response_size as u64)
+        {
+            return Err(anyhow!(
+                "HTTP response exceeds the maximum size of {} bytes",
+                self.max_response_size
+            ));
+        }
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.to_string(), value.to_owned()))
+            })
+            .collect();
+
+        // The Content-Length header is not always set, hence the size of the
+        // body is checked also while reading it
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > self.max_response_size {
+                return Err(anyhow!(
+                    "HTTP response exceeds the maximum size of {} bytes",
+                    self.max_response_size
+                ));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let body = String::from_utf8(body)
+            .map_err(|_| anyhow!("the body of the HTTP response is not valid UTF-8"))?;
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// Create a `reqwest::ClientBuilder` that honours the proxy configuration
+/// of the sources
+fn client_builder(sources: &Sources, timeout: Duration) -> Result<reqwest::ClientBuilder> {
+    let proxy_sources = sources.proxies();
+    let no_proxy = || {
+        proxy_sources
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string)
+    };
+
+    // Disable reqwest's automatic env-based proxy detection, the proxies are
+    // taken from the sources
+    let mut builder = reqwest::Client::builder()
+        .no_proxy()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(timeout);
+    if let Some(proxy_url) = &proxy_sources.https_proxy {
+        builder = builder.proxy(reqwest::Proxy::https(proxy_url)?.no_proxy(no_proxy()));
+    }
+    if let Some(proxy_url) = &proxy_sources.http_proxy {
+        builder = builder.proxy(reqwest::Proxy::http(proxy_url)?.no_proxy(no_proxy()));
+    }
+
+    Ok(builder)
+}
+
+/// Only the responses of the requests made with these methods are cached
+pub(crate) fn is_cacheable(method: &str) -> bool {
+    method.eq_ignore_ascii_case("GET") || method.eq_ignore_ascii_case("HEAD")
+}
+
+pub(crate) async fn request(
+    client: &Client,
+    request: &HttpRequest,
+) -> Result<cached::Return<HttpResponse>> {
+    client.request(request).await.map(cached::Return::new)
+}
+
+// Policies can be evaluated many times per second, doing the same HTTP request
+// each time would slow down the evaluations and hammer the remote service.
+//
+// Details about this cache:
+//   * the method, the URL, the headers and the body of the request are used as
+//     key. http::Client is not hashable, plus the client is always the same
+//   * the cache is time bound: cached values are purged after 60 seconds
+//   * only successful results are cached
+#[cached(
+    time = 60,
+    result = true,
+    sync_writes = "default",
+    key = "String",
+    convert = r#"{ format!("{} {} {:?} {:?}", request.method.to_uppercase(), request.url, request.headers, request.body) }"#,
+    with_cached_flag = true
+)]
+pub(crate) async fn request_cached(
+    client: &Client,
+    request: &HttpRequest,
+) -> Result<cached::Return<HttpResponse>> {
+    client.request(request).await.map(cached::Return::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::{collections::BTreeMap, net::SocketAddr};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Start a local stand-in for an HTTP service. Each request is answered
+    /// with the raw HTTP response produced by `handler`, which receives the
+    /// raw request
+    async fn serve<F>(handler: F) -> SocketAddr
+    where
+        F: Fn(&str) -> String + Copy + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buf = vec![0; 8192];
+                    let read = stream.read(&mut buf).await.unwrap();
+                    let request = String::from_utf8_lossy(&buf[..read]).to_string();
+                    let response = handler(&request);
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        addr
+    }
+
+    /// Answer with the request line and the body of the request
+    fn echo(request: &str) -> String {
+        let request_line = request.lines().next().unwrap_or_default();
+        let body = request.split("\r\n\r\n").nth(1).unwrap_or_default();
+        let response_body = format!("{request_line}|{body}");
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{response_body}",
+            response_body.len()
+        )
+    }
+
+    fn http_request(method: &str, addr: SocketAddr, body: Option<&str>) -> HttpRequest {
+        HttpRequest {
+            method: method.to_owned(),
+            url: format!("http://{addr}/api/quota"),
+            headers: BTreeMap::new(),
+            body: body.map(|b| b.to_owned()),
+            disable_cache: false,
+        }
+    }
+
+    #[rstest]
+    #[case::get("GET", None, "GET /api/quota HTTP/1.1|")]
+    #[case::post_with_body(
+        "POST",
+        Some(r#"{"ns":"default"}"#),
+        r#"POST /api/quota HTTP/1.1|{"ns":"default"}"#
+    )]
+    #[tokio::test]
+    async fn perform_request(
+        #[case] method: &str,
+        #[case] body: Option<&str>,
+        #[case] expected_body: &str,
+    ) {
+        let addr = serve(echo).await;
+        let client = Client::new(None, DEFAULT_TIMEOUT, DEFAULT_MAX_RESPONSE_SIZE).unwrap();
+
+        let response = client
+            .request(&http_request(method, addr, body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.headers.get("content-type").map(String::as_str),
+            Some("text/plain")
+        );
+        assert_eq!(response.body, expected_body);
+    }
+
+    #[tokio::test]
+    async fn error_status_is_given_back_to_the_policy() {
+        let addr = serve(|_| {
+            "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_string()
+        })
+        .await;
+        let client = Client::new(None, DEFAULT_TIMEOUT, DEFAULT_MAX_RESPONSE_SIZE).unwrap();
+
+        let response = client
+            .request(&http_request("GET", addr, None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 404);
+        assert!(response.body.is_empty());
+    }
+
+    #[rstest]
+    #[case::with_content_length(
+        "HTTP/1.1 200 OK\r\ncontent-length: 32\r\nconnection: close\r\n\r\n0123456789abcdef0123456789abcdef"
+    )]
+    #[case::chunked(
+        "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n20\r\n0123456789abcdef0123456789abcdef\r\n0\r\n\r\n"
+    )]
+    #[tokio::test]
+    async fn response_too_big(#[case] response: &'static str) {
+        let addr = serve(move |_| response.to_string()).await;
+        let client = Client::new(None, DEFAULT_TIMEOUT, 16).unwrap();
+
+        let result = client.request(&http_request("GET", addr, None)).await;
+
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds the maximum size")
+        );
+    }
+
+    #[rstest]
+    #[case::host("Host")]
+    #[case::connection("connection")]
+    #[case::transfer_encoding("Transfer-Encoding")]
+    #[case::upgrade("upgrade")]
+    #[tokio::test]
+    async fn forbidden_headers_are_rejected(#[case] header: &str) {
+        let addr = serve(echo).await;
+        let client = Client::new(None, DEFAULT_TIMEOUT, DEFAULT_MAX_RESPONSE_SIZE).unwrap();
+        let mut request = http_request("GET", addr, None);
+        request
+            .headers
+            .insert(header.to_owned(), "example.com".to_owned());
+
+        let result = client.request(&request).await;
+
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("cannot be set by the policy")
+        );
+    }
+
+    #[tokio::test]
+    async fn request_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // accept the connection, but never answer
+        tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+        let client =
+            Client::new(None, Duration::from_millis(100), DEFAULT_MAX_RESPONSE_SIZE).unwrap();
+
+        let result = client.request(&http_request("GET", addr, None)).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn redirects_are_not_followed() {
+        let addr = serve(|_| {
+            "HTTP/1.1 302 Found\r\nlocation: http://example.com/\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                .to_string()
+        })
+        .await;
+        let client = Client::new(None, DEFAULT_TIMEOUT, DEFAULT_MAX_RESPONSE_SIZE).unwrap();
+
+        let response = client
+            .request(&http_request("GET", addr, None))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 302);
+        assert_eq!(
+            response.headers.get("location").map(String::as_str),
+            Some("http://example.com/")
+        );
+    }
+
+    #[tokio::test]
+    async fn responses_are_cached() {
+        let addr = serve(echo).await;
+        let client = Client::new(None, DEFAULT_TIMEOUT, DEFAULT_MAX_RESPONSE_SIZE).unwrap();
+        let request = http_request("GET", addr, None);
+
+        let first = request_cached(&client, &request).await.unwrap();
+        let second = request_cached(&client, &request).await.unwrap();
+
+        assert!(!first.was_cached);
+        assert!(second.was_cached);
+        assert_eq!(first.value, second.value);
+    }
+
+    #[rstest]
+    #[case::get("GET", true)]
+    #[case::head("head", true)]
+    #[case::post("POST", false)]
+    #[case::delete("DELETE", false)]
+    fn cacheable_methods(#[case] method: &str, #[case] expected: bool) {
+        assert_eq!(is_cacheable(method), expected);
+    }
+}
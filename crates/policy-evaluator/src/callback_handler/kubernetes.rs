@@ -2,7 +2,9 @@ use std::collections::BTreeSet;
 
 mod client;
 pub(crate) mod field_mask;
+mod label_index;
 mod reflector;
+mod selector;
 
 use anyhow::{Result, anyhow};
 use cached::proc_macro::cached;
@@ -138,14 +140,14 @@ pub(crate) async fn get_resource_plural_name(
 }
 
 /// Check if the results of the "list all resources" query have changed since the provided instant
-/// This is done by querying the reflector that keeps track of this query
+/// This is done by querying the reflector that keeps track of this query. The reflector is shared
+/// by all the label selectors, field selectors and field masks, hence they don't take part in
+/// the check.
 pub(crate) async fn has_list_resources_all_result_changed_since_instant(
     client: Option<&mut Client>,
     api_version: &str,
     kind: &str,
-    label_selector: Option<String>,
     field_selector: Option<String>,
-    field_masks: Option<BTreeSet<String>>,
     since: tokio::time::Instant,
 ) -> Result<cached::Return<bool>> {
     if client.is_none() {
@@ -157,9 +159,7 @@ pub(crate) async fn has_list_resources_all_result_changed_since_instant(
         .has_list_resources_all_result_changed_since_instant(
             api_version,
             kind,
-            label_selector,
             field_selector,
-            field_masks,
             since,
         )
         .await
@@ -5,15 +5,16 @@ use std::{
 
 use anyhow::{Result, anyhow};
 use k8s_openapi::api::authorization::v1::{SubjectAccessReview, SubjectAccessReviewStatus};
-use kube::{
-    Api,
-    api::PostParams,
-    core::{DynamicObject, ObjectList},
-};
+use kube::{Api, api::PostParams, core::ObjectList};
 use kubewarden_policy_sdk::host_capabilities::kubernetes::SubjectAccessReview as KWSubjectAccessReview;
 use tokio::{sync::RwLock, time::Instant};
 
-use crate::callback_handler::kubernetes::{ApiVersionKind, KubeResource, reflector::Reflector};
+use crate::callback_handler::kubernetes::{
+    ApiVersionKind, KubeResource,
+    field_mask::FieldMaskNode,
+    reflector::Reflector,
+    selector::{FieldSelector, LabelSelector},
+};
 
 #[derive(Clone)]
 pub(crate) struct Client {
@@ -96,42 +97,52 @@ impl Client {
         Ok(kube_resource)
     }
 
-    async fn get_reflector_reader(
+    /// Get the reflector watching the given resource and retaining the given fields, `None`
+    /// meaning the whole objects. Create it if it doesn't exist yet.
+    ///
+    /// A single reflector serves all the queries made against the resource, regardless of their
+    /// selectors and field masks. The reflector retains only the union of the fields needed by
+    /// these queries: when a query needs more fields, the reflector is replaced by a new one
+    /// retaining them too.
+    async fn get_reflector(
         &mut self,
-        reflector_id: &str,
         resource: KubeResource,
         namespace: Option<String>,
-        label_selector: Option<String>,
-        field_selector: Option<String>,
-        field_masks: Option<BTreeSet<String>>,
-    ) -> Result<kube::runtime::reflector::Store<kube::core::DynamicObject>> {
-        let reader = {
+        fields: Option<BTreeSet<String>>,
+    ) -> Result<Reflector> {
+        let reflector = {
             let reflectors = self.reflectors.read().await;
-            reflectors
-                .get(reflector_id)
-                .map(|reflector| reflector.reader.clone())
+            find_reflector(&reflectors, &resource, namespace.as_deref()).cloned()
+        };
+        let (namespace, retained_fields) = match reflector {
+            Some(reflector) if reflector.retains(fields.as_ref()) => return Ok(reflector),
+            Some(reflector) => (
+                reflector.namespace().map(str::to_owned),
+                union_of_fields(reflector.retained_fields(), fields.as_ref()),
+            ),
+            None => (namespace, fields),
         };
-        if let Some(reader) = reader {
-            return Ok(reader);
-        }
 
+        let reflector_id = Reflector::compute_id(&resource, namespace.as_deref());
         let reflector = Reflector::create_and_run(
             self.kube_client.clone(),
             resource,
             namespace,
-            label_selector,
-            field_selector,
-            field_masks,
+            retained_fields.clone(),
         )
         .await?;
-        let reader = reflector.reader.clone();
 
+        let mut reflectors = self.reflectors.write().await;
+        // keep the reflector created in the meantime by another query, when it retains
+        // the fields too. The watch of the discarded reflector is stopped once it's dropped
+        if let Some(current) = reflectors.get(&reflector_id)
+            && current.retains(retained_fields.as_ref())
         {
-            let mut reflectors = self.reflectors.write().await;
-            reflectors.insert(reflector_id.to_string(), reflector);
+            return Ok(current.clone());
         }
+        reflectors.insert(reflector_id, reflector.clone());
 
-        Ok(reader)
+        Ok(reflector)
     }
 
     pub async fn list_resources_by_namespace(
@@ -184,22 +195,13 @@ impl Client {
         &mut self,
         api_version: &str,
         kind: &str,
-        label_selector: Option<String>,
         field_selector: Option<String>,
-        field_masks: Option<BTreeSet<String>>,
         since: Instant,
     ) -> Result<bool> {
         let resource = self.build_kube_resource(api_version, kind).await?;
 
         Ok(self
-            .have_reflector_resources_changed_since(
-                &resource,
-                None,
-                label_selector,
-                field_selector,
-                field_masks,
-                since,
-            )
+            .have_reflector_resources_changed_since(&resource, None, field_selector, since)
             .await)
     }
 
@@ -214,60 +216,62 @@ impl Client {
         let api_version = resource.resource.api_version.clone();
         let kind = resource.resource.kind.clone();
 
-        let reflector_id = Reflector::compute_id(
-            &resource,
+        let label_selector = LabelSelector::parse(label_selector.as_deref().unwrap_or_default())?;
+        let field_selector = FieldSelector::parse(field_selector.as_deref().unwrap_or_default())?;
+        field_selector.ensure_supported_by(&resource.resource)?;
+        let field_masker = field_masks.as_ref().map(FieldMaskNode::new);
+        // the field selector is evaluated against the stored objects
+        let fields = field_masks.map(|field_masks| {
+            field_masks
+                .into_iter()
+                .chain(field_selector.data_fields().map(str::to_owned))
+                .collect()
+        });
+
+        let reflector = self
+            .get_reflector(resource, namespace.clone(), fields)
+            .await?;
+        let items = reflector.list(
             namespace.as_deref(),
-            label_selector.as_deref(),
-            field_selector.as_deref(),
-            field_masks.as_ref(),
+            &label_selector,
+            &field_selector,
+            field_masker.as_ref(),
         );
 
-        let reader = self
-            .get_reflector_reader(
-                &reflector_id,
-                resource,
-                namespace,
-                label_selector,
-                field_selector,
-                field_masks,
-            )
-            .await?;
-
         Ok(ObjectList {
             types: kube::core::TypeMeta {
                 api_version,
                 kind: format!("{kind}List"),
             },
             metadata: Default::default(),
-            items: reader
-                .state()
-                .iter()
-                .map(|v| DynamicObject::clone(v))
-                .collect(),
+            items,
         })
     }
 
-    /// Check if the resources cached by the reflector have changed since the provided instant
+    /// Check if the resources cached by the reflector have changed since the provided instant.
+    ///
+    /// The reflector is resolved the same way the query did, hence a namespaced query can be
+    /// served by the cluster wide reflector of the resource. The reflector is shared by all the
+    /// queries made against the resource, hence a change to an object not selected by the query
+    /// is reported as a change too.
     async fn have_reflector_resources_changed_since(
         &mut self,
         resource: &KubeResource,
         namespace: Option<String>,
-        label_selector: Option<String>,
         field_selector: Option<String>,
-        field_masks: Option<BTreeSet<String>>,
         since: Instant,
     ) -> bool {
-        let reflector_id = Reflector::compute_id(
-            resource,
-            namespace.as_deref(),
-            label_selector.as_deref(),
-            field_selector.as_deref(),
-            field_masks.as_ref(),
-        );
+        // report invalid selectors as a change, the query will then return the error
+        let supported_field_selector =
+            FieldSelector::parse(field_selector.as_deref().unwrap_or_default())
+                .and_then(|field_selector| field_selector.ensure_supported_by(&resource.resource));
+        if supported_field_selector.is_err() {
+            return true;
+        }
 
         let last_change_seen_at = {
             let reflectors = self.reflectors.read().await;
-            match reflectors.get(&reflector_id) {
+            match find_reflector(&reflectors, resource, namespace.as_deref()) {
                 Some(reflector) => reflector.last_change_seen_at().await,
                 None => return true,
             }
@@ -338,3 +342,179 @@ impl Client {
         })
     }
 }
+
+/// The fields retained by a reflector serving the queries that need either `a` or `b`.
+/// `None` stands for the whole objects
+fn union_of_fields(
+    a: Option<&BTreeSet<String>>,
+    b: Option<&BTreeSet<String>>,
+) -> Option<BTreeSet<String>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(b).cloned().collect()),
+        _ => None,
+    }
+}
+
+/// Find the reflector serving the queries made against the given resource, inside of the given
+/// namespace when provided.
+///
+/// Namespaced queries are served by the cluster wide reflector of the resource, when there's no
+/// reflector dedicated to the namespace. This avoids watching the same objects twice.
+fn find_reflector<'a>(
+    reflectors: &'a HashMap<String, Reflector>,
+    resource: &KubeResource,
+    namespace: Option<&str>,
+) -> Option<&'a Reflector> {
+    reflectors
+        .get(&Reflector::compute_id(resource, namespace))
+        .or_else(|| reflectors.get(&Reflector::compute_id(resource, None)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::{Request, Response};
+    use kube::client::Body;
+    use rstest::rstest;
+
+    fn pods() -> KubeResource {
+        KubeResource {
+            resource: kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&()),
+            namespaced: true,
+        }
+    }
+
+    fn client() -> Client {
+        let (mocksvc, _handle) = tower_test::mock::pair::<Request<Body>, Response<Body>>();
+        Client::new(kube::Client::new(mocksvc, "default"))
+    }
+
+    #[tokio::test]
+    async fn namespaced_query_is_served_by_the_cluster_wide_reflector() {
+        let mut client = client();
+        let last_change_seen_at = Instant::now();
+        client.reflectors.write().await.insert(
+            Reflector::compute_id(&pods(), None),
+            Reflector::from_objects(pods().resource, None, None, Vec::new(), last_change_seen_at),
+        );
+
+        let before_last_change = last_change_seen_at - std::time::Duration::from_secs(1);
+        for namespace in [None, Some("default".to_owned())] {
+            assert!(
+                client
+                    .have_reflector_resources_changed_since(
+                        &pods(),
+                        namespace.clone(),
+                        None,
+                        before_last_change
+                    )
+                    .await
+            );
+            assert!(
+                !client
+                    .have_reflector_resources_changed_since(
+                        &pods(),
+                        namespace,
+                        Some("status.phase=Running".to_owned()),
+                        last_change_seen_at
+                    )
+                    .await
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn reflector_retaining_the_fields_serves_the_query() {
+        let mut client = client();
+        client.reflectors.write().await.insert(
+            Reflector::compute_id(&pods(), None),
+            Reflector::from_objects(
+                pods().resource,
+                None,
+                Some(BTreeSet::from(["spec".to_owned()])),
+                Vec::new(),
+                Instant::now(),
+            ),
+        );
+
+        let reflector = client
+            .get_reflector(
+                pods(),
+                Some("default".to_owned()),
+                Some(BTreeSet::from(["spec.containers".to_owned()])),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            reflector.retained_fields(),
+            Some(&BTreeSet::from(["spec".to_owned()]))
+        );
+    }
+
+    #[rstest]
+    #[case::both_masked(Some(&["spec"][..]), Some(&["status.phase"][..]), Some(&["spec", "status.phase"][..]))]
+    #[case::whole_objects(Some(&["spec"][..]), None, None)]
+    fn union_of_retained_fields(
+        #[case] a: Option<&[&str]>,
+        #[case] b: Option<&[&str]>,
+        #[case] expected: Option<&[&str]>,
+    ) {
+        let to_set = |fields: &[&str]| -> BTreeSet<String> {
+            fields.iter().map(|field| field.to_string()).collect()
+        };
+
+        assert_eq!(
+            union_of_fields(a.map(to_set).as_ref(), b.map(to_set).as_ref()),
+            expected.map(to_set)
+        );
+    }
+
+    #[tokio::test]
+    async fn changes_are_reported_when_no_reflector_serves_the_query() {
+        let mut client = client();
+        let last_change_seen_at = Instant::now();
+        client.reflectors.write().await.insert(
+            Reflector::compute_id(&pods(), Some("kube-system")),
+            Reflector::from_objects(
+                pods().resource,
+                Some("kube-system"),
+                None,
+                Vec::new(),
+                last_change_seen_at,
+            ),
+        );
+
+        assert!(
+            client
+                .have_reflector_resources_changed_since(
+                    &pods(),
+                    Some("default".to_owned()),
+                    None,
+                    last_change_seen_at
+                )
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn changes_are_reported_for_unsupported_field_selectors() {
+        let mut client = client();
+        let last_change_seen_at = Instant::now();
+        client.reflectors.write().await.insert(
+            Reflector::compute_id(&pods(), None),
+            Reflector::from_objects(pods().resource, None, None, Vec::new(), last_change_seen_at),
+        );
+
+        assert!(
+            client
+                .have_reflector_resources_changed_since(
+                    &pods(),
+                    None,
+                    Some("spec.unschedulable=true".to_owned()),
+                    last_change_seen_at
+                )
+                .await
+        );
+    }
+}
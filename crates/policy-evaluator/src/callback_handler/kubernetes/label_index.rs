@@ -0,0 +1,231 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use kube::{ResourceExt, core::DynamicObject, runtime::watcher};
+
+use crate::callback_handler::kubernetes::selector::LabelSelector;
+
+/// The label keys that are indexed. These are the ones commonly used by
+/// policies to select objects, the selectors using other keys are resolved
+/// by scanning all the objects cached by the reflector.
+const INDEXED_LABEL_KEYS: &[&str] = &[
+    "app",
+    "app.kubernetes.io/component",
+    "app.kubernetes.io/instance",
+    "app.kubernetes.io/managed-by",
+    "app.kubernetes.io/name",
+    "app.kubernetes.io/part-of",
+    "app.kubernetes.io/version",
+    "kubernetes.io/metadata.name",
+];
+
+/// Identifies an object cached by a reflector: namespace and name
+pub(crate) type ObjectKey = (Option<String>, String);
+
+#[derive(Default)]
+struct Entries {
+    by_label: HashMap<(String, String), HashSet<ObjectKey>>,
+    by_object: HashMap<ObjectKey, Vec<(String, String)>>,
+}
+
+impl Entries {
+    fn insert(&mut self, object_key: ObjectKey, labels: &BTreeMap<String, String>) {
+        self.remove(&object_key);
+
+        let indexed: Vec<(String, String)> = INDEXED_LABEL_KEYS
+            .iter()
+            .filter_map(|key| {
+                labels
+                    .get(*key)
+                    .map(|value| (key.to_string(), value.to_owned()))
+            })
+            .collect();
+        if indexed.is_empty() {
+            return;
+        }
+
+        for label in &indexed {
+            self.by_label
+                .entry(label.clone())
+                .or_default()
+                .insert(object_key.clone());
+        }
+        self.by_object.insert(object_key, indexed);
+    }
+
+    fn remove(&mut self, object_key: &ObjectKey) {
+        let Some(indexed) = self.by_object.remove(object_key) else {
+            return;
+        };
+        for label in indexed {
+            if let Some(objects) = self.by_label.get_mut(&label) {
+                objects.remove(object_key);
+                if objects.is_empty() {
+                    self.by_label.remove(&label);
+                }
+            }
+        }
+    }
+}
+
+/// An index of the objects cached by a reflector, built using the values of
+/// well-known labels. It is kept updated by applying the same watcher events
+/// that are applied to the reflector store.
+#[derive(Default)]
+pub(crate) struct LabelIndex {
+    entries: Entries,
+    /// The entries being built while the watcher performs a (re)list of the
+    /// objects. They replace the current ones once the list is completed,
+    /// like it's done by the reflector store.
+    init_buffer: Option<Entries>,
+}
+
+fn object_key(object: &DynamicObject) -> ObjectKey {
+    (object.namespace(), object.name_any())
+}
+
+impl LabelIndex {
+    pub fn apply_watcher_event(&mut self, event: &watcher::Event<DynamicObject>) {
+        match event {
+            watcher::Event::Apply(obj) => self.entries.insert(object_key(obj), obj.labels()),
+            watcher::Event::Delete(obj) => self.entries.remove(&object_key(obj)),
+            watcher::Event::Init => self.init_buffer = Some(Entries::default()),
+            watcher::Event::InitApply(obj) => {
+                if let Some(buffer) = self.init_buffer.as_mut() {
+                    buffer.insert(object_key(obj), obj.labels());
+                }
+            }
+            watcher::Event::InitDone => {
+                if let Some(buffer) = self.init_buffer.take() {
+                    self.entries = buffer;
+                }
+            }
+        }
+    }
+
+    /// The objects that could satisfy the selector. `None` is returned when
+    /// the selector cannot be resolved using the index: all the objects have
+    /// to be evaluated in that case.
+    ///
+    /// The candidates must still be evaluated against the whole selector.
+    pub fn candidates(&self, selector: &LabelSelector) -> Option<HashSet<ObjectKey>> {
+        let mut candidates: Option<HashSet<ObjectKey>> = None;
+
+        for (key, values) in selector.required_values() {
+            if !INDEXED_LABEL_KEYS.contains(&key) {
+                continue;
+            }
+            let objects: HashSet<ObjectKey> = values
+                .into_iter()
+                .filter_map(|value| {
+                    self.entries
+                        .by_label
+                        .get(&(key.to_owned(), value.to_owned()))
+                })
+                .flatten()
+                .cloned()
+                .collect();
+
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&objects).cloned().collect(),
+                None => objects,
+            });
+        }
+
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn pod(name: &str, labels: &[(&str, &str)]) -> DynamicObject {
+        let mut pod = DynamicObject::new(
+            name,
+            &kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&()),
+        )
+        .within("default");
+        pod.metadata.labels = Some(
+            labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        pod
+    }
+
+    fn key(name: &str) -> ObjectKey {
+        (Some("default".to_string()), name.to_string())
+    }
+
+    fn index() -> LabelIndex {
+        let mut index = LabelIndex::default();
+        for event in [
+            watcher::Event::Init,
+            watcher::Event::InitApply(pod("web", &[("app", "shop"), ("tier", "frontend")])),
+            watcher::Event::InitApply(pod(
+                "api",
+                &[("app", "shop"), ("app.kubernetes.io/component", "api")],
+            )),
+            watcher::Event::InitApply(pod("db", &[("app", "postgres")])),
+            watcher::Event::InitDone,
+        ] {
+            index.apply_watcher_event(&event);
+        }
+        index
+    }
+
+    #[rstest]
+    #[case::equals("app=shop", Some(vec!["web", "api"]))]
+    #[case::in_set("app in (shop, postgres)", Some(vec!["web", "api", "db"]))]
+    #[case::intersection("app=shop,app.kubernetes.io/component=api", Some(vec!["api"]))]
+    #[case::no_match("app=billing", Some(vec![]))]
+    #[case::not_indexed_key_is_ignored("app=shop,tier=frontend", Some(vec!["web", "api"]))]
+    #[case::only_not_indexed_keys("tier=frontend", None)]
+    #[case::not_equals("app!=shop", None)]
+    fn candidates(#[case] selector: &str, #[case] expected: Option<Vec<&str>>) {
+        let selector = LabelSelector::parse(selector).unwrap();
+
+        let candidates = index().candidates(&selector);
+
+        assert_eq!(
+            candidates,
+            expected.map(|names| names.into_iter().map(key).collect())
+        );
+    }
+
+    #[test]
+    fn index_is_updated_by_watcher_events() {
+        let mut index = index();
+        let selector = LabelSelector::parse("app=shop").unwrap();
+
+        index.apply_watcher_event(&watcher::Event::Apply(pod("web", &[("app", "blog")])));
+        index.apply_watcher_event(&watcher::Event::Delete(pod("api", &[])));
+
+        assert_eq!(index.candidates(&selector), Some(HashSet::new()));
+        assert_eq!(
+            index.candidates(&LabelSelector::parse("app=blog").unwrap()),
+            Some(HashSet::from([key("web")]))
+        );
+    }
+
+    #[test]
+    fn relist_replaces_the_index_once_completed() {
+        let mut index = index();
+        let selector = LabelSelector::parse("app=shop").unwrap();
+
+        index.apply_watcher_event(&watcher::Event::Init);
+        index.apply_watcher_event(&watcher::Event::InitApply(pod("web", &[("app", "shop")])));
+        assert_eq!(
+            index.candidates(&selector),
+            Some(HashSet::from([key("web"), key("api")]))
+        );
+
+        index.apply_watcher_event(&watcher::Event::InitDone);
+        assert_eq!(
+            index.candidates(&selector),
+            Some(HashSet::from([key("web")]))
+        );
+    }
+}
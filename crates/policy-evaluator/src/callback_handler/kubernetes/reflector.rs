@@ -1,49 +1,68 @@
-use std::{collections::BTreeSet, hash::Hash};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
 
 use anyhow::Result;
 use futures::{Stream, StreamExt, TryStreamExt, future::ready};
 use kube::{
-    Resource, ResourceExt,
+    ResourceExt,
+    core::DynamicObject,
     runtime::{
         WatchStreamExt,
-        reflector::store::{self, Writer},
+        reflector::{
+            ObjectRef,
+            store::{self, Writer},
+        },
         watcher,
     },
 };
-use tokio::{sync::watch, time::Instant};
+use tokio::{sync::watch, task::AbortHandle, time::Instant};
 use tracing::{debug, info, warn};
 
-use crate::callback_handler::kubernetes::{KubeResource, field_mask};
+use crate::callback_handler::kubernetes::{
+    KubeResource, field_mask,
+    label_index::LabelIndex,
+    selector::{FieldSelector, LabelSelector},
+};
 
 /// Like `kube::runtime::reflector::reflector`, but also sends the time of the last change to a
-/// watch channel
-pub fn reflector_tracking_changes_instant<K, W>(
-    mut writer: store::Writer<K>,
+/// watch channel and keeps the label index updated
+pub fn reflector_tracking_changes_instant<W>(
+    mut writer: store::Writer<DynamicObject>,
     stream: W,
     last_change_seen_at: watch::Sender<Instant>,
+    label_index: Arc<RwLock<LabelIndex>>,
 ) -> impl Stream<Item = W::Item>
 where
-    K: Resource + Clone,
-    K::DynamicType: Eq + Hash + Clone,
-    W: Stream<Item = watcher::Result<watcher::Event<K>>>,
+    W: Stream<Item = watcher::Result<watcher::Event<DynamicObject>>>,
 {
     stream.inspect_ok(move |event| {
         if let Err(err) = last_change_seen_at.send(Instant::now()) {
             warn!(error = ?err, "failed to set last_change_seen_at");
         }
-        writer.apply_watcher_event(event)
+        writer.apply_watcher_event(event);
+        label_index
+            .write()
+            .expect("cannot acquire label index write lock")
+            .apply_watcher_event(event);
     })
 }
 
-/// A reflector fetches Kubernetes objects based on filtering criteria.
-/// When created, the list is populated slowly, to prevent hammering the Kubernetes API server.
+/// A reflector fetches all the Kubernetes objects of a given kind, either cluster wide or
+/// inside of a namespace. When created, the list is populated slowly, to prevent hammering the Kubernetes API server.
 /// The items are stored in-memory. The `managedFields` attribute is stripped from all the objects
-/// to reduce memory consumption. All the other fields are retained unless field masks
-/// are provided by the caller. When that happens, only the fields specified by the field masks are retained in the objects, all the
-/// other fields are pruned. The field masks are applied in-place, so the objects stored in the Reflector
-/// only contain the fields specified by the field masks.
+/// to reduce memory consumption. When the reflector is created with a set of retained fields, only
+/// these fields are kept inside of the stored objects, all the other ones are pruned. The metadata
+/// of the objects is always retained.
 /// A Kubernetes Watch is then created to keep the contents of the list updated.
 ///
+/// Label selectors, field selectors and field masks are not sent to the Kubernetes API server:
+/// they are applied when reading the cached objects. This allows a single reflector, hence a
+/// single watch, to serve all the queries made against the same kind of resource, as long as it
+/// retains the fields they need. The values of well-known labels are indexed to avoid scanning
+/// all the cached objects when resolving a label selector.
+///
 /// This is code relies heavily on the `kube::runtime::reflector` module.
 ///
 /// ## Stale date
@@ -55,37 +74,47 @@ where
 ///
 /// Finally, when started, the Reflector takes some time to make the loaded data available to
 /// consumers.
+#[derive(Clone)]
 pub(crate) struct Reflector {
+    resource: kube::api::ApiResource,
+    namespace: Option<String>,
+    /// The fields kept inside of the stored objects, `None` when they are stored in full
+    retained_fields: Option<BTreeSet<String>>,
     /// Read-only access to the data cached by the Reflector
-    pub reader: kube::runtime::reflector::Store<kube::core::DynamicObject>,
+    reader: kube::runtime::reflector::Store<DynamicObject>,
+    label_index: Arc<RwLock<LabelIndex>>,
     last_change_seen_at: watch::Receiver<Instant>,
+    /// Stops the watch once the last copy of the Reflector is dropped, like when it's replaced
+    /// by a Reflector retaining more fields
+    _watch_task: Option<Arc<WatchTask>>,
+}
+
+struct WatchTask(AbortHandle);
+
+impl Drop for WatchTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 impl Reflector {
     /// Compute a unique identifier for the Reflector. This is used to prevent the creation of two
     /// Reflectors watching the same set of resources.
-    pub fn compute_id(
-        resource: &KubeResource,
-        namespace: Option<&str>,
-        label_selector: Option<&str>,
-        field_selector: Option<&str>,
-        field_masks: Option<&BTreeSet<String>>,
-    ) -> String {
+    pub fn compute_id(resource: &KubeResource, namespace: Option<&str>) -> String {
         format!(
-            "{}|{}|{namespace:?}|{label_selector:?}|{field_selector:?}|{field_masks:?}",
+            "{}|{}|{namespace:?}",
             resource.resource.api_version, resource.resource.kind
         )
     }
 
     /// Create the reflector and start a tokio task in the background that keeps
-    /// the contents of the Reflector updated
+    /// the contents of the Reflector updated. When provided, only the `retained_fields`
+    /// are kept inside of the stored objects
     pub async fn create_and_run(
         kube_client: kube::Client,
         resource: KubeResource,
         namespace: Option<String>,
-        label_selector: Option<String>,
-        field_selector: Option<String>,
-        field_masks: Option<BTreeSet<String>>,
+        retained_fields: Option<BTreeSet<String>>,
     ) -> Result<Self> {
         let group = resource.resource.group.clone();
         let version = resource.resource.version.clone();
@@ -96,34 +125,27 @@ impl Reflector {
             version,
             kind,
             ?namespace,
-            ?label_selector,
-            ?field_selector,
+            ?retained_fields,
             "creating new reflector"
         );
 
         let api = match namespace {
-            Some(ref ns) => kube::api::Api::<kube::core::DynamicObject>::namespaced_with(
+            Some(ref ns) => kube::api::Api::<DynamicObject>::namespaced_with(
                 kube_client,
                 ns,
                 &resource.resource,
             ),
-            None => kube::api::Api::<kube::core::DynamicObject>::all_with(
-                kube_client,
-                &resource.resource,
-            ),
+            None => kube::api::Api::<DynamicObject>::all_with(kube_client, &resource.resource),
         };
 
-        let writer = Writer::new(resource.resource);
+        let writer = Writer::new(resource.resource.clone());
         let reader = writer.as_reader();
+        let label_index = Arc::new(RwLock::new(LabelIndex::default()));
 
-        let filter = watcher::Config {
-            label_selector: label_selector.clone(),
-            field_selector: field_selector.clone(),
-            ..Default::default()
-        };
+        let filter = watcher::Config::default();
 
         let field_masker: Option<field_mask::FieldMaskNode> =
-            field_masks.map(field_mask::FieldMaskNode::new);
+            retained_fields.as_ref().map(field_mask::FieldMaskNode::new);
 
         let stream = watcher(api, filter).map_ok(move |ev| {
             ev.modify(|obj| {
@@ -134,9 +156,16 @@ impl Reflector {
         // this is a watch channel that tracks the last time the reflector saw a change
         let (updated_at_watch_tx, updated_at_watch_rx) = watch::channel(Instant::now());
 
-        let rf = reflector_tracking_changes_instant(writer, stream, updated_at_watch_tx);
+        let rf = reflector_tracking_changes_instant(
+            writer,
+            stream,
+            updated_at_watch_tx,
+            label_index.clone(),
+        );
 
-        tokio::spawn(async move {
+        let task_namespace = namespace.clone();
+        let watch_task = tokio::spawn(async move {
+            let namespace = task_namespace;
             let infinite_watch = rf.default_backoff().touched_objects().for_each(|obj| {
                 match obj {
                     Ok(o) => debug!(
@@ -144,8 +173,6 @@ impl Reflector {
                         version,
                         kind,
                         ?namespace,
-                        ?label_selector,
-                        ?field_selector,
                         object=?o,
                         "watcher saw object"
                     ),
@@ -154,8 +181,6 @@ impl Reflector {
                         version,
                         kind,
                         ?namespace,
-                        ?label_selector,
-                        ?field_selector,
                         error=?e,
                         "watcher error"
                     ),
@@ -164,31 +189,137 @@ impl Reflector {
             });
             infinite_watch.await
         });
+        let watch_task = Arc::new(WatchTask(watch_task.abort_handle()));
 
         reader.wait_until_ready().await?;
 
         Ok(Reflector {
+            resource: resource.resource,
+            namespace,
+            retained_fields,
             reader,
+            label_index,
             last_change_seen_at: updated_at_watch_rx,
+            _watch_task: Some(watch_task),
         })
     }
 
+    /// The namespace watched by the reflector, `None` when it's cluster wide
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// The fields kept inside of the stored objects, `None` when they are stored in full
+    pub fn retained_fields(&self) -> Option<&BTreeSet<String>> {
+        self.retained_fields.as_ref()
+    }
+
+    /// Returns true when the stored objects hold all the given fields. `None` stands for
+    /// the whole objects
+    pub fn retains(&self, fields: Option<&BTreeSet<String>>) -> bool {
+        match (&self.retained_fields, fields) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(retained), Some(fields)) => fields.iter().all(|field| {
+                retained.iter().any(|retained| {
+                    field == retained
+                        || field
+                            .strip_prefix(retained.as_str())
+                            .is_some_and(|rest| rest.starts_with('.'))
+                })
+            }),
+        }
+    }
+
+    /// List the cached objects that live inside of the given namespace, when provided, and that
+    /// satisfy both the label and the field selectors. When provided, the field masks are
+    /// applied to the returned objects, the cached ones are left untouched. The field masks
+    /// and the field selector must refer only to fields retained by the reflector
+    pub fn list(
+        &self,
+        namespace: Option<&str>,
+        label_selector: &LabelSelector,
+        field_selector: &FieldSelector,
+        field_masker: Option<&field_mask::FieldMaskNode>,
+    ) -> Vec<DynamicObject> {
+        let candidates = self
+            .label_index
+            .read()
+            .expect("cannot acquire label index read lock")
+            .candidates(label_selector);
+
+        let objects = match candidates {
+            Some(object_keys) => object_keys
+                .into_iter()
+                .filter_map(|(object_namespace, name)| {
+                    let mut object_ref = ObjectRef::new_with(&name, self.resource.clone());
+                    if let Some(object_namespace) = object_namespace {
+                        object_ref = object_ref.within(&object_namespace);
+                    }
+                    self.reader.get(&object_ref)
+                })
+                .collect(),
+            None => self.reader.state(),
+        };
+
+        objects
+            .into_iter()
+            .filter(|obj| namespace.is_none() || obj.namespace().as_deref() == namespace)
+            .filter(|obj| label_selector.matches(obj.labels()) && field_selector.matches(obj))
+            .map(|obj| {
+                let mut obj = DynamicObject::clone(&obj);
+                if let Some(field_masker) = field_masker {
+                    field_mask::prune_in_place(&mut obj.data, field_masker);
+                }
+                obj
+            })
+            .collect()
+    }
+
+    /// Create a reflector holding the given objects, without watching the cluster
+    #[cfg(test)]
+    pub fn from_objects(
+        resource: kube::api::ApiResource,
+        namespace: Option<&str>,
+        retained_fields: Option<BTreeSet<String>>,
+        objects: Vec<DynamicObject>,
+        last_change_seen_at: Instant,
+    ) -> Self {
+        let mut writer = Writer::new(resource.clone());
+        let mut label_index = LabelIndex::default();
+        let field_masker = retained_fields.as_ref().map(field_mask::FieldMaskNode::new);
+        for mut obj in objects {
+            modify_object(&mut obj, field_masker.as_ref());
+            let event = watcher::Event::Apply(obj);
+            writer.apply_watcher_event(&event);
+            label_index.apply_watcher_event(&event);
+        }
+        let (_, last_change_seen_at) = watch::channel(last_change_seen_at);
+
+        Reflector {
+            resource,
+            namespace: namespace.map(str::to_owned),
+            retained_fields,
+            reader: writer.as_reader(),
+            label_index: Arc::new(RwLock::new(label_index)),
+            last_change_seen_at,
+            _watch_task: None,
+        }
+    }
+
     /// Get the last time a change was seen by the reflector
     pub async fn last_change_seen_at(&self) -> Instant {
         *self.last_change_seen_at.borrow()
     }
 }
 
-fn modify_object(
-    obj: &mut kube::core::DynamicObject,
-    field_masker: Option<&field_mask::FieldMaskNode>,
-) {
+fn modify_object(obj: &mut DynamicObject, field_masker: Option<&field_mask::FieldMaskNode>) {
     // clear managed fields to reduce memory usage
     obj.managed_fields_mut().clear();
     // clear last-applied-configuration to reduce memory usage
     obj.annotations_mut()
         .remove("kubectl.kubernetes.io/last-applied-configuration");
-    // apply field masks, if any
+    // keep only the retained fields, if any
     if let Some(mask) = field_masker {
         field_mask::prune_in_place(&mut obj.data, mask);
     }
@@ -199,6 +330,7 @@ mod tests {
     use super::*;
     use k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry;
     use kube::core::{DynamicObject, ObjectMeta};
+    use rstest::rstest;
     use serde_json::json;
     use std::collections::BTreeMap;
 
@@ -244,7 +376,7 @@ mod tests {
     }
 
     #[test]
-    fn test_modify_object_applies_field_masks() {
+    fn test_list_applies_field_masks() {
         let mut obj = DynamicObject::new(
             "Pod",
             &kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&()),
@@ -267,8 +399,20 @@ mod tests {
         let masks = ["spec.containers.image"];
 
         let field_masker = field_mask::FieldMaskNode::new(masks);
+        let reflector = Reflector::from_objects(
+            kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&()),
+            None,
+            Some(BTreeSet::from(["spec.containers".to_owned()])),
+            vec![obj],
+            Instant::now(),
+        );
 
-        modify_object(&mut obj, Some(&field_masker));
+        let objects = reflector.list(
+            None,
+            &LabelSelector::default(),
+            &FieldSelector::default(),
+            Some(&field_masker),
+        );
 
         let expected_data = json!({
             "spec": {
@@ -280,11 +424,26 @@ mod tests {
             }
         });
 
-        assert_eq!(obj.data, expected_data);
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].data, expected_data);
+        // the cached object holds only the retained fields, and is left untouched
+        assert_eq!(
+            reflector.reader.state()[0].data,
+            json!({
+                "spec": {
+                    "containers": [
+                        {
+                            "name": "nginx",
+                            "image": "nginx:latest"
+                        }
+                    ]
+                }
+            })
+        );
     }
 
     #[test]
-    fn test_modify_object_applies_field_masks_with_sbomscanner_vulnerability_report() {
+    fn test_stored_objects_hold_only_the_retained_fields_with_sbomscanner_vulnerability_report() {
         let sbom_data = std::fs::read_to_string("tests/data/sbomscanner_vulnerability_report.json")
             .expect("Failed to read sbomscanner_vulnerability_report.json");
         let vulnerability_report_json: serde_json::Value =
@@ -304,12 +463,17 @@ mod tests {
             "report.results.vulnerabilities.severity",
             "report.results.vulnerabilities.suppressed",
         ];
-        let field_masker = field_mask::FieldMaskNode::new(field_masks);
-
-        modify_object(&mut obj, Some(&field_masker));
+        let reflector = Reflector::from_objects(
+            kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&()),
+            None,
+            Some(field_masks.iter().map(|mask| mask.to_string()).collect()),
+            vec![obj],
+            Instant::now(),
+        );
 
         // Validation
-        let report = obj.data.get("report").expect("report field missing");
+        let objects = reflector.reader.state();
+        let report = objects[0].data.get("report").expect("report field missing");
 
         assert!(
             report.get("imageMetadata").is_none(),
@@ -343,4 +507,31 @@ mod tests {
             }
         }
     }
+
+    #[rstest]
+    #[case::everything_retained(None, Some(&["spec.containers"][..]), true)]
+    #[case::whole_objects_required(Some(&["spec"][..]), None, false)]
+    #[case::same_fields(Some(&["spec.containers", "status.phase"][..]), Some(&["status.phase"][..]), true)]
+    #[case::nested_field(Some(&["spec"][..]), Some(&["spec.containers.image"][..]), true)]
+    #[case::parent_field(Some(&["spec.containers"][..]), Some(&["spec"][..]), false)]
+    #[case::field_with_common_prefix(Some(&["spec.node"][..]), Some(&["spec.nodeName"][..]), false)]
+    #[case::missing_field(Some(&["spec"][..]), Some(&["spec", "status.phase"][..]), false)]
+    fn test_retains(
+        #[case] retained_fields: Option<&[&str]>,
+        #[case] fields: Option<&[&str]>,
+        #[case] expected: bool,
+    ) {
+        let to_set = |fields: &[&str]| -> BTreeSet<String> {
+            fields.iter().map(|field| field.to_string()).collect()
+        };
+        let reflector = Reflector::from_objects(
+            kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&()),
+            None,
+            retained_fields.map(to_set),
+            Vec::new(),
+            Instant::now(),
+        );
+
+        assert_eq!(reflector.retains(fields.map(to_set).as_ref()), expected);
+    }
 }
@@ -0,0 +1,530 @@
+//! In-memory evaluation of Kubernetes label and field selectors.
+//!
+//! The reflectors watch all the objects of a given kind, the selectors
+//! provided by the policies are then evaluated against the cached objects.
+//! The syntax follows the one accepted by the Kubernetes API server,
+//! see https://kubernetes.io/docs/concepts/overview/working-with-objects/labels/#label-selectors
+//! and https://kubernetes.io/docs/concepts/overview/working-with-objects/field-selectors/
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Result, anyhow};
+use kube::{ResourceExt, core::DynamicObject};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LabelRequirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, BTreeSet<String>),
+    NotIn(String, BTreeSet<String>),
+    Exists(String),
+    DoesNotExist(String),
+    GreaterThan(String, i64),
+    LessThan(String, i64),
+}
+
+impl LabelRequirement {
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            LabelRequirement::Equals(key, value) => labels.get(key) == Some(value),
+            LabelRequirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            LabelRequirement::In(key, values) => {
+                labels.get(key).is_some_and(|v| values.contains(v))
+            }
+            LabelRequirement::NotIn(key, values) => {
+                !labels.get(key).is_some_and(|v| values.contains(v))
+            }
+            LabelRequirement::Exists(key) => labels.contains_key(key),
+            LabelRequirement::DoesNotExist(key) => !labels.contains_key(key),
+            LabelRequirement::GreaterThan(key, bound) => labels
+                .get(key)
+                .and_then(|v| v.parse::<i64>().ok())
+                .is_some_and(|v| v > *bound),
+            LabelRequirement::LessThan(key, bound) => labels
+                .get(key)
+                .and_then(|v| v.parse::<i64>().ok())
+                .is_some_and(|v| v < *bound),
+        }
+    }
+}
+
+/// A parsed label selector, e.g. `app=nginx,tier in (frontend, backend),!canary`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LabelSelector {
+    requirements: Vec<LabelRequirement>,
+}
+
+impl LabelSelector {
+    /// Parse the given selector. An empty selector matches all the objects.
+    pub fn parse(selector: &str) -> Result<Self> {
+        let requirements = split_requirements(selector)?
+            .into_iter()
+            .map(parse_label_requirement)
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| anyhow!("invalid label selector '{selector}': {e}"))?;
+
+        Ok(LabelSelector { requirements })
+    }
+
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.requirements.iter().all(|r| r.matches(labels))
+    }
+
+    /// The label values an object must have to satisfy the selector, grouped
+    /// by label key. Only the `=`, `==` and `in` requirements are returned,
+    /// these are the ones that can be resolved by looking up an index.
+    pub fn required_values(&self) -> impl Iterator<Item = (&str, Vec<&str>)> {
+        self.requirements.iter().filter_map(|r| match r {
+            LabelRequirement::Equals(key, value) => Some((key.as_str(), vec![value.as_str()])),
+            LabelRequirement::In(key, values) => {
+                Some((key.as_str(), values.iter().map(String::as_str).collect()))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Split a selector into its requirements. Commas found inside of the
+/// parentheses of a set based requirement do not start a new requirement.
+fn split_requirements(selector: &str) -> Result<Vec<&str>> {
+    let mut requirements = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(anyhow!("unbalanced parentheses in '{selector}'")),
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                requirements.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(anyhow!("unbalanced parentheses in '{selector}'"));
+    }
+    requirements.push(&selector[start..]);
+
+    if requirements.len() == 1 && requirements[0].trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(requirements)
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/')
+}
+
+fn parse_key(key: &str) -> Result<String> {
+    if key.is_empty() || !key.chars().all(is_key_char) {
+        return Err(anyhow!("invalid key '{key}'"));
+    }
+    Ok(key.to_owned())
+}
+
+fn parse_value(value: &str) -> Result<String> {
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(anyhow!("invalid value '{value}'"));
+    }
+    Ok(value.to_owned())
+}
+
+fn parse_values(values: &str) -> Result<BTreeSet<String>> {
+    let inner = values
+        .strip_prefix('(')
+        .and_then(|v| v.strip_suffix(')'))
+        .ok_or_else(|| anyhow!("set of values must be enclosed in parentheses"))?;
+
+    inner.split(',').map(|v| parse_value(v.trim())).collect()
+}
+
+fn parse_label_requirement(requirement: &str) -> Result<LabelRequirement> {
+    let requirement = requirement.trim();
+    if requirement.is_empty() {
+        return Err(anyhow!("empty requirement"));
+    }
+
+    if let Some(key) = requirement.strip_prefix('!') {
+        return Ok(LabelRequirement::DoesNotExist(parse_key(key.trim())?));
+    }
+
+    let key_end = requirement
+        .find(|c: char| !is_key_char(c))
+        .unwrap_or(requirement.len());
+    let key = parse_key(&requirement[..key_end])?;
+    let rest = requirement[key_end..].trim_start();
+
+    if rest.is_empty() {
+        return Ok(LabelRequirement::Exists(key));
+    }
+    if let Some(value) = rest.strip_prefix("==").or_else(|| rest.strip_prefix('=')) {
+        return Ok(LabelRequirement::Equals(key, parse_value(value.trim())?));
+    }
+    if let Some(value) = rest.strip_prefix("!=") {
+        return Ok(LabelRequirement::NotEquals(key, parse_value(value.trim())?));
+    }
+    if let Some(value) = rest.strip_prefix('>') {
+        let bound = value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("'{}' is not an integer", value.trim()))?;
+        return Ok(LabelRequirement::GreaterThan(key, bound));
+    }
+    if let Some(value) = rest.strip_prefix('<') {
+        let bound = value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("'{}' is not an integer", value.trim()))?;
+        return Ok(LabelRequirement::LessThan(key, bound));
+    }
+    if let Some(values) = rest.strip_prefix("notin") {
+        return Ok(LabelRequirement::NotIn(key, parse_values(values.trim())?));
+    }
+    if let Some(values) = rest.strip_prefix("in") {
+        return Ok(LabelRequirement::In(key, parse_values(values.trim())?));
+    }
+
+    Err(anyhow!("unknown operator in '{requirement}'"))
+}
+
+/// The fields all the resources can be selected by
+const METADATA_FIELDS: &[&str] = &["metadata.name", "metadata.namespace"];
+
+/// The API groups of the built-in Kubernetes resources
+const BUILTIN_API_GROUPS: &[&str] = &[
+    "",
+    "admissionregistration.k8s.io",
+    "apiextensions.k8s.io",
+    "apiregistration.k8s.io",
+    "apps",
+    "authentication.k8s.io",
+    "authorization.k8s.io",
+    "autoscaling",
+    "batch",
+    "certificates.k8s.io",
+    "coordination.k8s.io",
+    "discovery.k8s.io",
+    "events.k8s.io",
+    "flowcontrol.apiserver.k8s.io",
+    "internal.apiserver.k8s.io",
+    "networking.k8s.io",
+    "node.k8s.io",
+    "policy",
+    "rbac.authorization.k8s.io",
+    "resource.k8s.io",
+    "scheduling.k8s.io",
+    "storage.k8s.io",
+    "storagemigration.k8s.io",
+];
+
+/// The fields, other than the metadata ones, the built-in resources can be selected by.
+/// See https://kubernetes.io/docs/concepts/overview/working-with-objects/field-selectors/#list-of-supported-fields
+const SELECTABLE_FIELDS: &[(&str, &str, &[&str])] = &[
+    (
+        "",
+        "Pod",
+        &[
+            "spec.nodeName",
+            "spec.restartPolicy",
+            "spec.schedulerName",
+            "spec.serviceAccountName",
+            "spec.hostNetwork",
+            "status.phase",
+            "status.podIP",
+            "status.nominatedNodeName",
+        ],
+    ),
+    (
+        "",
+        "Event",
+        &[
+            "involvedObject.kind",
+            "involvedObject.namespace",
+            "involvedObject.name",
+            "involvedObject.uid",
+            "involvedObject.apiVersion",
+            "involvedObject.resourceVersion",
+            "involvedObject.fieldPath",
+            "reason",
+            "reportingComponent",
+            "source",
+            "type",
+        ],
+    ),
+    ("", "Secret", &["type"]),
+    ("", "Namespace", &["status.phase"]),
+    ("apps", "ReplicaSet", &["status.replicas"]),
+    ("", "ReplicationController", &["status.replicas"]),
+    ("batch", "Job", &["status.successful"]),
+    ("", "Node", &["spec.unschedulable"]),
+    (
+        "certificates.k8s.io",
+        "CertificateSigningRequest",
+        &["spec.signerName"],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FieldRequirement {
+    Equals(String, String),
+    NotEquals(String, String),
+}
+
+/// A parsed field selector, e.g. `metadata.name=nginx,status.phase!=Running`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FieldSelector {
+    requirements: Vec<FieldRequirement>,
+}
+
+impl FieldSelector {
+    /// Parse the given selector. An empty selector matches all the objects.
+    pub fn parse(selector: &str) -> Result<Self> {
+        let requirements = split_requirements(selector)?
+            .into_iter()
+            .map(|requirement| {
+                let requirement = requirement.trim();
+                if let Some((field, value)) = requirement.split_once("!=") {
+                    Ok(FieldRequirement::NotEquals(
+                        parse_key(field.trim())?,
+                        value.trim().to_owned(),
+                    ))
+                } else if let Some((field, value)) = requirement
+                    .split_once("==")
+                    .or_else(|| requirement.split_once('='))
+                {
+                    Ok(FieldRequirement::Equals(
+                        parse_key(field.trim())?,
+                        value.trim().to_owned(),
+                    ))
+                } else {
+                    Err(anyhow!("unknown operator in '{requirement}'"))
+                }
+            })
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| anyhow!("invalid field selector '{selector}': {e}"))?;
+
+        Ok(FieldSelector { requirements })
+    }
+
+    pub fn matches(&self, object: &DynamicObject) -> bool {
+        self.requirements.iter().all(|r| match r {
+            FieldRequirement::Equals(field, value) => field_value(object, field) == *value,
+            FieldRequirement::NotEquals(field, value) => field_value(object, field) != *value,
+        })
+    }
+
+    /// Ensure the Kubernetes API server supports all the fields referenced by
+    /// the selector for the given resource. All the resources can be selected by
+    /// `metadata.name` and `metadata.namespace`, only a few built-in kinds
+    /// support other fields. Custom resources can declare their own selectable
+    /// fields, hence their fields outside of the metadata are not checked.
+    pub fn ensure_supported_by(&self, resource: &kube::api::ApiResource) -> Result<()> {
+        let builtin = BUILTIN_API_GROUPS.contains(&resource.group.as_str());
+        let selectable_fields = SELECTABLE_FIELDS
+            .iter()
+            .find(|(group, kind, _)| *group == resource.group && *kind == resource.kind)
+            .map(|(_, _, fields)| *fields)
+            .unwrap_or_default();
+
+        for field in self.fields() {
+            let supported = METADATA_FIELDS.contains(&field)
+                || selectable_fields.contains(&field)
+                || (!builtin && !field.starts_with("metadata."));
+            if !supported {
+                return Err(anyhow!(
+                    "field selector '{field}' is not supported for {}/{}",
+                    resource.api_version,
+                    resource.kind
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The fields referenced by the selector that live outside of the metadata
+    /// of the objects, hence that must be retained by the reflectors
+    pub fn data_fields(&self) -> impl Iterator<Item = &str> {
+        self.fields()
+            .filter(|field| !field.starts_with("metadata."))
+    }
+
+    /// The fields referenced by the selector
+    fn fields(&self) -> impl Iterator<Item = &str> {
+        self.requirements.iter().map(|r| match r {
+            FieldRequirement::Equals(field, _) | FieldRequirement::NotEquals(field, _) => {
+                field.as_str()
+            }
+        })
+    }
+}
+
+/// The value of the field, as a string. Like the Kubernetes API server does,
+/// a missing field is considered to have an empty value.
+fn field_value(object: &DynamicObject, field: &str) -> String {
+    match field {
+        "metadata.name" => object.name_any(),
+        "metadata.namespace" => object.namespace().unwrap_or_default(),
+        // the source of an Event is selected by its component
+        "source" => field_value(object, "source.component"),
+        _ => {
+            let value = field
+                .split('.')
+                .try_fold(&object.data, |value, segment| value.get(segment));
+            match value {
+                Some(Value::String(s)) => s.to_owned(),
+                Some(Value::Bool(b)) => b.to_string(),
+                Some(Value::Number(n)) => n.to_string(),
+                _ => String::new(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn labels(labels: &[(&str, &str)]) -> BTreeMap<String, String> {
+        labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[rstest]
+    #[case::empty("", true)]
+    #[case::equals("app=nginx", true)]
+    #[case::double_equals("app == nginx", true)]
+    #[case::equals_no_match("app=apache", false)]
+    #[case::not_equals("app!=apache", true)]
+    #[case::not_equals_missing_key("team!=a", true)]
+    #[case::in_set("tier in (frontend, backend)", true)]
+    #[case::in_set_no_match("tier in (backend)", false)]
+    #[case::not_in_set("tier notin (backend)", true)]
+    #[case::not_in_missing_key("team notin (a)", true)]
+    #[case::exists("app.kubernetes.io/part-of", true)]
+    #[case::does_not_exist("!canary", true)]
+    #[case::does_not_exist_no_match("!app", false)]
+    #[case::greater_than("replicas>2", true)]
+    #[case::less_than("replicas < 2", false)]
+    #[case::multiple_requirements("app=nginx,tier in (frontend,backend),!canary", true)]
+    #[case::multiple_requirements_no_match("app=nginx,tier notin (frontend,backend)", false)]
+    fn label_selector_matches(#[case] selector: &str, #[case] expected: bool) {
+        let object_labels = labels(&[
+            ("app", "nginx"),
+            ("tier", "frontend"),
+            ("replicas", "3"),
+            ("app.kubernetes.io/part-of", "shop"),
+        ]);
+
+        let selector = LabelSelector::parse(selector).unwrap();
+
+        assert_eq!(selector.matches(&object_labels), expected);
+    }
+
+    #[rstest]
+    #[case::missing_key("=nginx")]
+    #[case::unknown_operator("app ~ nginx")]
+    #[case::set_without_parentheses("tier in frontend")]
+    #[case::unbalanced_parentheses("tier in (frontend")]
+    #[case::empty_requirement("app=nginx,")]
+    #[case::not_an_integer("replicas>two")]
+    #[case::invalid_value("app=ng inx")]
+    fn invalid_label_selector(#[case] selector: &str) {
+        assert!(LabelSelector::parse(selector).is_err(), "{selector}");
+    }
+
+    #[test]
+    fn label_selector_required_values() {
+        let selector = LabelSelector::parse("app=nginx,tier in (a,b),!canary,team!=x").unwrap();
+
+        let required: Vec<(&str, Vec<&str>)> = selector.required_values().collect();
+
+        assert_eq!(
+            required,
+            vec![("app", vec!["nginx"]), ("tier", vec!["a", "b"])]
+        );
+    }
+
+    fn pod() -> DynamicObject {
+        let mut pod = DynamicObject::new(
+            "nginx",
+            &kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&()),
+        )
+        .within("default");
+        pod.data = json!({
+            "spec": {"nodeName": "node-1", "hostNetwork": false},
+            "status": {"phase": "Running"}
+        });
+        pod
+    }
+
+    #[rstest]
+    #[case::empty("", true)]
+    #[case::name("metadata.name=nginx", true)]
+    #[case::namespace("metadata.namespace==kube-system", false)]
+    #[case::data_field("status.phase=Running,spec.nodeName!=node-2", true)]
+    #[case::boolean_field("spec.hostNetwork=false", true)]
+    #[case::missing_field_is_empty("spec.serviceAccountName=", true)]
+    #[case::missing_field_not_equals("spec.serviceAccountName!=default", true)]
+    fn field_selector_matches(#[case] selector: &str, #[case] expected: bool) {
+        let selector = FieldSelector::parse(selector).unwrap();
+
+        assert_eq!(selector.matches(&pod()), expected);
+    }
+
+    #[rstest]
+    #[case::metadata_fields("v1", "Service", "metadata.name=a,metadata.namespace!=b", true)]
+    #[case::pod_field("v1", "Pod", "status.phase=Running,spec.nodeName=node-1", true)]
+    #[case::field_of_another_kind("v1", "Service", "status.phase=Running", false)]
+    #[case::kind_of_another_group("apps/v1", "Pod", "status.phase=Running", false)]
+    #[case::unsupported_metadata_field("v1", "Pod", "metadata.uid=1234", false)]
+    #[case::custom_resource_field("example.com/v1", "Foo", "spec.bar=baz", true)]
+    #[case::custom_resource_metadata_field("example.com/v1", "Foo", "metadata.uid=1234", false)]
+    fn field_selector_supported_by(
+        #[case] api_version: &str,
+        #[case] kind: &str,
+        #[case] selector: &str,
+        #[case] expected: bool,
+    ) {
+        let (group, version) = api_version.split_once('/').unwrap_or(("", api_version));
+        let resource = kube::api::ApiResource {
+            group: group.to_owned(),
+            version: version.to_owned(),
+            api_version: api_version.to_owned(),
+            kind: kind.to_owned(),
+            plural: format!("{}s", kind.to_lowercase()),
+        };
+        let selector = FieldSelector::parse(selector).unwrap();
+
+        assert_eq!(selector.ensure_supported_by(&resource).is_ok(), expected);
+    }
+
+    #[test]
+    fn event_source_is_selected_by_its_component() {
+        let mut event = DynamicObject::new(
+            "nginx.1",
+            &kube::api::ApiResource::erase::<k8s_openapi::api::core::v1::Event>(&()),
+        );
+        event.data = json!({"source": {"component": "kubelet", "host": "node-1"}});
+
+        let selector = FieldSelector::parse("source=kubelet").unwrap();
+
+        assert!(selector.matches(&event));
+    }
+
+    #[test]
+    fn invalid_field_selector() {
+        assert!(FieldSelector::parse("status.phase").is_err());
+    }
+}
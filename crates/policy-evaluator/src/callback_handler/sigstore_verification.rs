@@ -11,7 +11,7 @@ use policy_fetcher::{
     sources::Sources,
     verify::{
         Verifier,
-        config::{LatestVerificationConfig, Signature, Subject},
+        config::{Signature, SignatureRequirements, Subject},
         fetch_sigstore_remote_data,
     },
 };
@@ -29,6 +29,7 @@ use tracing::warn;
 pub(crate) struct Client {
     cosign_client: Arc<Mutex<sigstore::cosign::Client>>,
     verifier: Verifier,
+    sources: Option<Arc<Sources>>,
 }
 
 impl Client {
@@ -39,11 +40,12 @@ impl Client {
         let cosign_client = Arc::new(Mutex::new(
             Self::build_cosign_client(sources.clone(), trust_root).await?,
         ));
-        let verifier = Verifier::new_from_cosign_client(cosign_client.clone(), sources);
+        let verifier = Verifier::new_from_cosign_client(cosign_client.clone(), sources.clone());
 
         Ok(Client {
             cosign_client,
             verifier,
+            sources: sources.map(Arc::new),
         })
     }
 
@@ -95,9 +97,10 @@ impl Client {
             };
             signatures_all_of.push(signature);
         }
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: Some(signatures_all_of),
             any_of: None,
+            attestations: None,
         };
 
         let result = self.verifier.verify(&image, &verification_config).await;
@@ -130,9 +133,10 @@ impl Client {
             };
             signatures_all_of.push(signature);
         }
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: Some(signatures_all_of),
             any_of: None,
+            attestations: None,
         };
 
         let result = self.verifier.verify(&image, &verification_config).await;
@@ -166,9 +170,10 @@ impl Client {
             };
             signatures_all_of.push(signature);
         }
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: Some(signatures_all_of),
             any_of: None,
+            attestations: None,
         };
 
         let result = self.verifier.verify(&image, &verification_config).await;
@@ -200,9 +205,10 @@ impl Client {
             annotations: annotations.clone(),
         };
         signatures_all_of.push(signature);
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: Some(signatures_all_of),
             any_of: None,
+            attestations: None,
         };
 
         let result = self.verifier.verify(&image, &verification_config).await;
@@ -224,7 +230,7 @@ impl Client {
         annotations: Option<BTreeMap<String, String>>,
     ) -> Result<VerificationResponse> {
         let (source_image_digest, trusted_layers) =
-            fetch_sigstore_remote_data(&self.cosign_client, image).await?;
+            fetch_sigstore_remote_data(&self.cosign_client, image, self.sources.as_deref()).await?;
         let chain: Option<Vec<Certificate>> = certificate_chain.map(|certs| {
             certs
                 .iter()
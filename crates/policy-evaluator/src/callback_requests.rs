@@ -27,6 +27,38 @@ pub struct CallbackRequest {
     pub response_channel: oneshot::Sender<Result<CallbackResponse>>,
 }
 
+/// An HTTP request made by a policy via the `net/v1/http_request` host capability
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    /// The HTTP method, e.g. `GET`
+    pub method: String,
+    /// The URL to be requested
+    pub url: String,
+    /// Optional - The headers to be sent
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Optional - The body of the request
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Disable caching of the response. By default the responses of `GET`
+    /// and `HEAD` requests are cached for 60 seconds, that might cause stale
+    /// data to be returned
+    #[serde(default)]
+    pub disable_cache: bool,
+}
+
+/// The response to an HTTP request made via the `net/v1/http_request`
+/// host capability
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    /// The HTTP status code
+    pub status: u16,
+    /// The headers of the response
+    pub headers: BTreeMap<String, String>,
+    /// The body of the response
+    pub body: String,
+}
+
 /// Describes the different kinds of request a waPC guest can make to
 /// our host.
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -125,6 +157,13 @@ pub enum CallbackRequestType {
     /// Lookup the addresses for a given hostname via DNS
     DNSLookupHost { host: String },
 
+    /// Perform an HTTP request against one of the endpoints the policy
+    /// has been granted access to
+    HttpRequest {
+        /// The request to be performed
+        request: HttpRequest,
+    },
+
     /// Get all the Kubernetes resources defined inside of the given
     /// namespace
     /// Note: cannot be used with cluster-wide resources
@@ -317,6 +356,17 @@ pub enum CallbackRequestType {
         /// a chain to validate it with.
         request: CertificateVerificationRequest,
     },
+
+    /// Evaluate a host capability served by a provider registered at runtime.
+    /// See `crate::host_capability_provider`
+    HostCapabilityProvider {
+        /// Namespace of the capability (e.g. `cmdb`)
+        namespace: String,
+        /// Operation of the capability (e.g. `v1/team_owner`)
+        operation: String,
+        /// The JSON payload sent by the policy
+        payload: serde_json::Value,
+    },
 }
 mod tokio_instant_serializer {
     use serde::de::Error;
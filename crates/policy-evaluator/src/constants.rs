@@ -21,6 +21,9 @@ pub const KUBEWARDEN_ANNOTATION_ARTIFACTHUB_HIDDENUI: &str = "io.kubewarden.hidd
 
 pub const KUBEWARDEN_ANNOTATION_KWCTL_VERSION: &str = "io.kubewarden.kwctl";
 
+pub const KUBEWARDEN_ANNOTATION_REGO_KUBERNETES_INVENTORY: &str =
+    "io.kubewarden.rego.kubernetes-inventory";
+
 pub const ARTIFACTHUB_ANNOTATION_KUBEWARDEN_MUTATION: &str = "kubewarden/mutation";
 pub const ARTIFACTHUB_ANNOTATION_KUBEWARDEN_CONTEXTAWARE_RESOURCES: &str =
     "kubewarden/contextAwareResources";
@@ -104,6 +104,78 @@ pub enum MetadataError {
 pub enum ResponseError {
     #[error("cannot deserialize JSONPatch: {0}")]
     Deserialize(#[source] serde_json::Error),
+
+    #[error("{0}")]
+    Mutation(#[source] MutationError),
+
+    #[error("the policy returned both a mutated object and a list of mutations")]
+    MutationsAndMutatedObject,
+}
+
+#[derive(Error, Debug)]
+pub enum MutationError {
+    #[error("cannot apply JSONPatch mutation: {0}")]
+    JSONPatch(#[source] json_patch::PatchError),
+
+    #[error("the apply configuration of a mutation must be an object")]
+    InvalidApplyConfiguration,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum GatekeeperMutationError {
+    #[error("invalid location {location:?}: {message}")]
+    InvalidLocation { location: String, message: String },
+
+    #[error("{kind} {name:?}: {message}")]
+    InvalidMutator {
+        kind: String,
+        name: String,
+        message: String,
+    },
+
+    #[error("{kind} {name:?}: cannot mutate {location:?}: {message}")]
+    Mutation {
+        kind: String,
+        name: String,
+        location: String,
+        message: String,
+    },
+
+    #[error("mutators did not converge after {0} iterations")]
+    NotConverged(usize),
+
+    #[error("cannot mutate raw requests, an AdmissionRequest is required")]
+    RawRequest,
+}
+
+#[derive(Error, Debug)]
+pub enum HostCapabilityProviderError {
+    #[error("invalid host capability namespace {0:?}")]
+    InvalidNamespace(String),
+
+    #[error("host capability namespace {0:?} is reserved to the built-in capabilities")]
+    ReservedNamespace(String),
+
+    #[error("a host capability provider is already registered for namespace {0:?}")]
+    AlreadyRegistered(String),
+
+    #[error("host capability provider {0:?} does not declare any operation")]
+    NoOperations(String),
+
+    #[error("invalid operation {operation:?} declared by host capability provider {namespace:?}")]
+    InvalidOperation {
+        namespace: String,
+        operation: String,
+    },
+
+    #[error("no host capability provider registered for namespace {0:?}")]
+    UnknownNamespace(String),
+
+    #[error("host capability provider {namespace:?} does not handle operation {operation:?}")]
+    UnknownOperation {
+        namespace: String,
+        operation: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
@@ -2,9 +2,11 @@ use std::{collections::BTreeSet, fmt};
 
 use tokio::sync::mpsc;
 
+use url::Url;
+
 use crate::{
     callback_requests::CallbackRequest, host_capabilities::HostCapabilities,
-    policy_metadata::ContextAwareResource,
+    http_endpoint::HttpEndpoint, policy_metadata::ContextAwareResource,
 };
 
 /// A struct that holds metadata and other data that are needed when a policy
@@ -36,6 +38,10 @@ pub struct EvaluationContext {
     /// An empty list means no host capabilities are allowed (deny by default).
     /// A list containing `*` means all capabilities are allowed.
     pub host_capabilities: HostCapabilities,
+
+    /// List of HTTP endpoints the policy can reach via the `net/v1/http_request`
+    /// host capability
+    pub http_endpoints_allow_list: BTreeSet<HttpEndpoint>,
 }
 
 impl EvaluationContext {
@@ -59,6 +65,15 @@ impl EvaluationContext {
     pub(crate) fn can_access_host_capability(&self, capability_path: &str) -> bool {
         self.host_capabilities.is_allowed(capability_path)
     }
+
+    /// Checks if a policy can make an HTTP request with the given method
+    /// against the given URL, based on the HTTP endpoints that have been
+    /// granted by the user
+    pub(crate) fn can_access_http_endpoint(&self, method: &str, url: &Url) -> bool {
+        self.http_endpoints_allow_list
+            .iter()
+            .any(|endpoint| endpoint.allows(method, url))
+    }
 }
 
 impl fmt::Debug for EvaluationContext {
@@ -70,11 +85,12 @@ impl fmt::Debug for EvaluationContext {
 
         write!(
             f,
-            r#"EvaluationContext {{ policy_id: "{}", callback_channel: {}, allowed_kubernetes_resources: {:?}, host_capabilities: {} }}"#,
+            r#"EvaluationContext {{ policy_id: "{}", callback_channel: {}, allowed_kubernetes_resources: {:?}, host_capabilities: {}, allowed_http_endpoints: {:?} }}"#,
             self.policy_id,
             callback_channel,
             self.ctx_aware_resources_allow_list,
             self.host_capabilities,
+            self.http_endpoints_allow_list,
         )
     }
 }
@@ -119,6 +135,7 @@ mod tests {
             ctx_aware_resources_allow_list: allowed_resources,
             epoch_deadline: None,
             host_capabilities: HostCapabilities::AllowAll,
+            http_endpoints_allow_list: BTreeSet::new(),
         };
 
         let requested_resource = ContextAwareResource {
@@ -152,7 +169,52 @@ mod tests {
             ctx_aware_resources_allow_list: BTreeSet::new(),
             epoch_deadline: None,
             host_capabilities: HostCapabilities::new(patterns).expect("valid patterns"),
+            http_endpoints_allow_list: BTreeSet::new(),
         };
         assert_eq!(ctx.can_access_host_capability(capability), allowed);
     }
+
+    #[rstest]
+    #[case::nothing_allowed(vec![], "GET", "https://svc.corp/api", false)]
+    #[case::allowed(
+        vec![("https://svc.corp/api", "GET")],
+        "GET",
+        "https://svc.corp/api/quota",
+        true
+    )]
+    #[case::second_endpoint_allowed(
+        vec![("https://svc.corp/api", "GET"), ("https://exceptions.corp", "POST")],
+        "POST",
+        "https://exceptions.corp/check",
+        true
+    )]
+    #[case::method_denied(
+        vec![("https://svc.corp/api", "GET")],
+        "DELETE",
+        "https://svc.corp/api/quota",
+        false
+    )]
+    fn can_access_http_endpoint(
+        #[case] endpoints: Vec<(&str, &str)>,
+        #[case] method: &str,
+        #[case] url: &str,
+        #[case] allowed: bool,
+    ) {
+        let ctx = EvaluationContext {
+            policy_id: "a-policy-using-http".to_string(),
+            http_endpoints_allow_list: endpoints
+                .into_iter()
+                .map(|(url_prefix, method)| HttpEndpoint {
+                    url_prefix: Url::parse(url_prefix).unwrap(),
+                    methods: BTreeSet::from([method.to_string()]),
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ctx.can_access_http_endpoint(method, &Url::parse(url).unwrap()),
+            allowed
+        );
+    }
 }
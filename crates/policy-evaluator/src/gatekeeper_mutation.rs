@@ -0,0 +1,1411 @@
+//! Native implementation of the Gatekeeper mutators: `Assign`,
+//! `AssignMetadata`, `ModifySet` and `AssignImage`.
+//!
+//! The mutators are read from the settings of the policies using the
+//! `gatekeeper-mutation` execution mode. The ones matching the admission
+//! request are applied to its object, the changes are returned as a JSONPatch.
+//! See https://open-policy-agent.github.io/gatekeeper/website/docs/mutation
+//!
+//! No Wasm module is needed: policies select this execution mode by using
+//! [`BUILTIN_MODULE`] as their module.
+
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kubewarden_policy_sdk::response::ValidationResponse as PolicyValidationResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    admission_request::{AdmissionRequest, GroupVersionKind},
+    admission_response::AdmissionResponse,
+    errors::GatekeeperMutationError,
+    policy_evaluator::{PolicyExecutionMode, PolicySettings, ValidateRequest},
+    policy_metadata::Metadata,
+};
+
+/// The module of the policies applying Gatekeeper mutators. It's not
+/// downloaded: the mutators are applied natively
+pub const BUILTIN_MODULE: &str = "builtin://gatekeeper-mutation";
+
+/// Like Gatekeeper, the mutators are applied until the object doesn't change
+/// anymore, this allows a mutator to act on the changes made by another one
+const MAX_ITERATIONS: usize = 3;
+
+/// The settings of a policy using the `gatekeeper-mutation` execution mode
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GatekeeperMutationSettings {
+    pub mutators: Vec<Mutator>,
+}
+
+impl TryFrom<&PolicySettings> for GatekeeperMutationSettings {
+    type Error = serde_json::Error;
+
+    fn try_from(settings: &PolicySettings) -> Result<Self, Self::Error> {
+        serde_json::from_value(Value::Object(settings.0.clone()))
+    }
+}
+
+/// The metadata of [`BUILTIN_MODULE`]
+pub fn builtin_module_metadata() -> Metadata {
+    Metadata {
+        execution_mode: PolicyExecutionMode::GatekeeperMutation,
+        mutating: true,
+        // mutators never reject a request, there's nothing to audit
+        background_audit: false,
+        ..Default::default()
+    }
+}
+
+impl GatekeeperMutationSettings {
+    /// Ensure all the mutators can be applied
+    pub fn validate(&self) -> Result<(), GatekeeperMutationError> {
+        self.mutators.iter().try_for_each(Mutator::validate)
+    }
+}
+
+/// A Gatekeeper mutator, as defined by its Custom Resource
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum Mutator {
+    Assign(MutatorResource<AssignParameters>),
+    AssignMetadata(MutatorResource<AssignMetadataParameters>),
+    ModifySet(MutatorResource<ModifySetParameters>),
+    AssignImage(MutatorResource<AssignImageParameters>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(
+    rename_all = "camelCase",
+    bound(deserialize = "P: Deserialize<'de> + Default")
+)]
+pub struct MutatorResource<P> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    #[serde(default)]
+    pub metadata: MutatorMetadata,
+    pub spec: MutatorSpec<P>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MutatorMetadata {
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(
+    rename_all = "camelCase",
+    bound(deserialize = "P: Deserialize<'de> + Default")
+)]
+pub struct MutatorSpec<P> {
+    /// The resources the mutator applies to. Not used by `AssignMetadata`,
+    /// which applies to all the resources
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apply_to: Vec<ApplyTo>,
+    #[serde(default, rename = "match")]
+    pub match_: Match,
+    /// Path of the field to mutate, e.g. `spec.containers[name: *].image`
+    pub location: String,
+    #[serde(default)]
+    pub parameters: P,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ApplyTo {
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub kinds: Vec<String>,
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Match {
+    /// One of `*`, `Cluster` or `Namespaced`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<MatchKinds>,
+    /// Namespaces matched, a `*` can be used as prefix or suffix
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_namespaces: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_selector: Option<LabelSelector>,
+    /// Not supported, the namespace of the object is not available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_selector: Option<LabelSelector>,
+    /// Name of the object, a `*` can be used as prefix or suffix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchKinds {
+    #[serde(default)]
+    pub api_groups: Vec<String>,
+    #[serde(default)]
+    pub kinds: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PathTest {
+    pub sub_path: String,
+    pub condition: PathCondition,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathCondition {
+    MustExist,
+    MustNotExist,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignParameters {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_tests: Vec<PathTest>,
+    #[serde(default)]
+    pub assign: AssignValue,
+    /// Assign the value only when the current one satisfies these conditions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assign_if: Option<AssignIf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignValue {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_metadata: Option<FromMetadata>,
+    /// Not supported, external data providers cannot be reached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_data: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FromMetadata {
+    pub field: MetadataField,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataField {
+    Namespace,
+    Name,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignIf {
+    #[serde(default, rename = "in", skip_serializing_if = "Vec::is_empty")]
+    pub in_: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub not_in: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AssignMetadataParameters {
+    #[serde(default)]
+    pub assign: AssignValue,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifySetParameters {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_tests: Vec<PathTest>,
+    #[serde(default)]
+    pub operation: SetOperation,
+    #[serde(default)]
+    pub values: SetValues,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SetOperation {
+    #[default]
+    Merge,
+    Prune,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SetValues {
+    #[serde(default)]
+    pub from_list: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignImageParameters {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_tests: Vec<PathTest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assign_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assign_path: Option<String>,
+    /// The tag, starting with `:`, or the digest, starting with `@`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assign_tag: Option<String>,
+}
+
+impl Mutator {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Mutator::Assign(_) => "Assign",
+            Mutator::AssignMetadata(_) => "AssignMetadata",
+            Mutator::ModifySet(_) => "ModifySet",
+            Mutator::AssignImage(_) => "AssignImage",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Mutator::Assign(m) => &m.metadata.name,
+            Mutator::AssignMetadata(m) => &m.metadata.name,
+            Mutator::ModifySet(m) => &m.metadata.name,
+            Mutator::AssignImage(m) => &m.metadata.name,
+        }
+    }
+
+    pub fn location(&self) -> &str {
+        match self {
+            Mutator::Assign(m) => &m.spec.location,
+            Mutator::AssignMetadata(m) => &m.spec.location,
+            Mutator::ModifySet(m) => &m.spec.location,
+            Mutator::AssignImage(m) => &m.spec.location,
+        }
+    }
+
+    pub fn apply_to(&self) -> &[ApplyTo] {
+        match self {
+            Mutator::Assign(m) => &m.spec.apply_to,
+            Mutator::AssignMetadata(m) => &m.spec.apply_to,
+            Mutator::ModifySet(m) => &m.spec.apply_to,
+            Mutator::AssignImage(m) => &m.spec.apply_to,
+        }
+    }
+
+    pub fn match_criteria(&self) -> &Match {
+        match self {
+            Mutator::Assign(m) => &m.spec.match_,
+            Mutator::AssignMetadata(m) => &m.spec.match_,
+            Mutator::ModifySet(m) => &m.spec.match_,
+            Mutator::AssignImage(m) => &m.spec.match_,
+        }
+    }
+
+    fn path_tests(&self) -> &[PathTest] {
+        match self {
+            Mutator::Assign(m) => &m.spec.parameters.path_tests,
+            Mutator::AssignMetadata(_) => &[],
+            Mutator::ModifySet(m) => &m.spec.parameters.path_tests,
+            Mutator::AssignImage(m) => &m.spec.parameters.path_tests,
+        }
+    }
+
+    fn invalid(&self, message: impl Into<String>) -> GatekeeperMutationError {
+        GatekeeperMutationError::InvalidMutator {
+            kind: self.kind().to_string(),
+            name: self.name().to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Ensure the mutator can be applied. The same constraints enforced by
+    /// the Gatekeeper webhook are checked, plus the features that cannot be
+    /// implemented outside of the cluster are rejected.
+    pub fn validate(&self) -> Result<(), GatekeeperMutationError> {
+        let location = Location::parse(self.location())?;
+        for path_test in self.path_tests() {
+            let sub_path = Location::parse(&path_test.sub_path)?;
+            if !location.0.starts_with(&sub_path.0) {
+                return Err(self.invalid(format!(
+                    "pathTests subPath {:?} is not a prefix of the location",
+                    path_test.sub_path
+                )));
+            }
+        }
+        if self.match_criteria().namespace_selector.is_some() {
+            return Err(self.invalid("match.namespaceSelector is not supported"));
+        }
+        if !matches!(self, Mutator::AssignMetadata(_)) {
+            if self.apply_to().is_empty() {
+                return Err(self.invalid("applyTo must be set"));
+            }
+            if location.is_metadata() {
+                return Err(self.invalid("metadata can be mutated only by AssignMetadata"));
+            }
+        }
+
+        match self {
+            Mutator::Assign(m) => self.validate_assign_value(&m.spec.parameters.assign),
+            Mutator::AssignMetadata(m) => {
+                if !location.is_metadata_label_or_annotation() {
+                    return Err(self.invalid(
+                        "location must be metadata.labels.<key> or metadata.annotations.<key>",
+                    ));
+                }
+                self.validate_assign_value(&m.spec.parameters.assign)?;
+                match &m.spec.parameters.assign.value {
+                    Some(value) if !value.is_string() => {
+                        Err(self.invalid("labels and annotations must be strings"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Mutator::ModifySet(_) => {
+                if location.ends_with_keyed_list() {
+                    return Err(self.invalid("location cannot end with a keyed list"));
+                }
+                Ok(())
+            }
+            Mutator::AssignImage(m) => {
+                if location.ends_with_keyed_list() {
+                    return Err(self.invalid("location cannot end with a keyed list"));
+                }
+                validate_assign_image(&m.spec.parameters).map_err(|message| self.invalid(message))
+            }
+        }
+    }
+
+    fn validate_assign_value(&self, assign: &AssignValue) -> Result<(), GatekeeperMutationError> {
+        if assign.external_data.is_some() {
+            return Err(self.invalid("assign.externalData is not supported"));
+        }
+        match (&assign.value, &assign.from_metadata) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => {
+                Err(self.invalid("exactly one of assign.value and assign.fromMetadata must be set"))
+            }
+        }
+    }
+
+    /// Returns true when the mutator applies to the object of the request
+    fn matches(&self, request: &AdmissionRequest, object: &Value) -> bool {
+        let applies = matches!(self, Mutator::AssignMetadata(_))
+            || self
+                .apply_to()
+                .iter()
+                .any(|apply_to| apply_to.matches(&request.kind));
+
+        applies && self.match_criteria().matches(request, object)
+    }
+
+    fn mutate(
+        &self,
+        request: &AdmissionRequest,
+        object: &mut Value,
+    ) -> Result<(), GatekeeperMutationError> {
+        let location = Location::parse(self.location())?;
+        for path_test in self.path_tests() {
+            let exists = Location::parse(&path_test.sub_path)?.exists(object);
+            match path_test.condition {
+                PathCondition::MustExist if !exists => return Ok(()),
+                PathCondition::MustNotExist if exists => return Ok(()),
+                _ => {}
+            }
+        }
+
+        let result = match self {
+            Mutator::Assign(m) => {
+                let value = assigned_value(&m.spec.parameters.assign, request, object);
+                let assign_if = m.spec.parameters.assign_if.as_ref();
+                location.visit(object, true, &mut |current| {
+                    if assign_if.is_some_and(|assign_if| !assign_if.allows(current)) {
+                        return Ok(None);
+                    }
+                    Ok(Some(value.clone()))
+                })
+            }
+            Mutator::AssignMetadata(m) => {
+                let value = assigned_value(&m.spec.parameters.assign, request, object);
+                // labels and annotations set by the user are never changed
+                location.visit(object, true, &mut |current| {
+                    Ok(current.is_none().then(|| value.clone()))
+                })
+            }
+            Mutator::ModifySet(m) => {
+                let values = &m.spec.parameters.values.from_list;
+                let operation = m.spec.parameters.operation;
+                location.visit(object, operation == SetOperation::Merge, &mut |current| {
+                    modify_set(current, operation, values)
+                })
+            }
+            Mutator::AssignImage(m) => {
+                location.visit(object, false, &mut |current| match current {
+                    Some(Value::String(image)) => {
+                        Ok(Some(Value::String(assign_image(image, &m.spec.parameters))))
+                    }
+                    Some(_) => Err("the image is not a string".to_string()),
+                    None => Ok(None),
+                })
+            }
+        };
+
+        result.map_err(|message| GatekeeperMutationError::Mutation {
+            kind: self.kind().to_string(),
+            name: self.name().to_string(),
+            location: self.location().to_string(),
+            message,
+        })
+    }
+}
+
+impl ApplyTo {
+    fn matches(&self, gvk: &GroupVersionKind) -> bool {
+        contains(&self.groups, &gvk.group)
+            && contains(&self.kinds, &gvk.kind)
+            && contains(&self.versions, &gvk.version)
+    }
+}
+
+impl Match {
+    fn matches(&self, request: &AdmissionRequest, object: &Value) -> bool {
+        let metadata = object.get("metadata");
+        let name = metadata
+            .and_then(|metadata| metadata.get("name"))
+            .and_then(Value::as_str)
+            .or(request.name.as_deref())
+            .unwrap_or_default();
+        // Namespace objects are cluster scoped, the API server sets the
+        // namespace of their requests to their name. That name is used to
+        // match `namespaces` and `excludedNamespaces`
+        let (namespace, matched_namespace) =
+            if request.kind.group.is_empty() && request.kind.kind == "Namespace" {
+                let namespace = metadata
+                    .and_then(|metadata| metadata.get("namespace"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                (namespace, name)
+            } else {
+                let namespace = request.namespace.as_deref().unwrap_or_default();
+                (namespace, namespace)
+            };
+
+        let scope = match self.scope.as_deref() {
+            Some("Cluster") => namespace.is_empty(),
+            Some("Namespaced") => !namespace.is_empty(),
+            _ => true,
+        };
+        let kinds = self.kinds.is_empty()
+            || self.kinds.iter().any(|kinds| {
+                (kinds.api_groups.is_empty() || contains(&kinds.api_groups, &request.kind.group))
+                    && (kinds.kinds.is_empty() || contains(&kinds.kinds, &request.kind.kind))
+            });
+        let namespaces = self.namespaces.is_empty()
+            || self
+                .namespaces
+                .iter()
+                .any(|pattern| glob_matches(pattern, matched_namespace));
+        let excluded_namespaces = self
+            .excluded_namespaces
+            .iter()
+            .any(|pattern| glob_matches(pattern, matched_namespace));
+        let object_name = self
+            .name
+            .as_ref()
+            .is_none_or(|pattern| glob_matches(pattern, name));
+        let labels = self.label_selector.as_ref().is_none_or(|selector| {
+            let labels = metadata
+                .and_then(|metadata| metadata.get("labels"))
+                .and_then(Value::as_object);
+            label_selector_matches(selector, labels)
+        });
+
+        scope && kinds && namespaces && !excluded_namespaces && object_name && labels
+    }
+}
+
+impl AssignIf {
+    fn allows(&self, current: Option<&Value>) -> bool {
+        let current = current.unwrap_or(&Value::Null);
+        (self.in_.is_empty() || self.in_.contains(current)) && !self.not_in.contains(current)
+    }
+}
+
+/// A list of values that matches the given one, `*` matches everything
+fn contains(list: &[String], value: &str) -> bool {
+    list.iter().any(|item| item == "*" || item == value)
+}
+
+/// Gatekeeper allows a `*` only at the beginning or at the end of a pattern
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        value.starts_with(prefix)
+    } else if let Some(suffix) = pattern.strip_prefix('*') {
+        value.ends_with(suffix)
+    } else {
+        pattern == value
+    }
+}
+
+fn label_selector_matches(selector: &LabelSelector, labels: Option<&Map<String, Value>>) -> bool {
+    let label = |key: &str| {
+        labels
+            .and_then(|labels| labels.get(key))
+            .and_then(Value::as_str)
+    };
+
+    let match_labels = selector
+        .match_labels
+        .as_ref()
+        .unwrap_or(&BTreeMap::new())
+        .iter()
+        .all(|(key, value)| label(key) == Some(value.as_str()));
+    let match_expressions = selector
+        .match_expressions
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .all(|requirement| {
+            let values = requirement.values.as_deref().unwrap_or_default();
+            let current = label(&requirement.key);
+            match requirement.operator.as_str() {
+                "In" => current.is_some_and(|current| values.iter().any(|v| v == current)),
+                "NotIn" => current.is_none_or(|current| !values.iter().any(|v| v == current)),
+                "Exists" => current.is_some(),
+                "DoesNotExist" => current.is_none(),
+                _ => false,
+            }
+        });
+
+    match_labels && match_expressions
+}
+
+fn assigned_value(assign: &AssignValue, request: &AdmissionRequest, object: &Value) -> Value {
+    match (&assign.value, &assign.from_metadata) {
+        (Some(value), _) => value.clone(),
+        (None, Some(from_metadata)) => {
+            let value = match from_metadata.field {
+                MetadataField::Namespace => request.namespace.clone(),
+                MetadataField::Name => object
+                    .pointer("/metadata/name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .or_else(|| request.name.clone()),
+            };
+            Value::String(value.unwrap_or_default())
+        }
+        (None, None) => Value::Null,
+    }
+}
+
+fn modify_set(
+    current: Option<&Value>,
+    operation: SetOperation,
+    values: &[Value],
+) -> Result<Option<Value>, String> {
+    match (current, operation) {
+        (None, SetOperation::Merge) => Ok(Some(Value::Array(values.to_vec()))),
+        (None, SetOperation::Prune) => Ok(None),
+        (Some(Value::Array(items)), SetOperation::Merge) => {
+            let mut items = items.clone();
+            for value in values {
+                if !items.contains(value) {
+                    items.push(value.clone());
+                }
+            }
+            Ok(Some(Value::Array(items)))
+        }
+        (Some(Value::Array(items)), SetOperation::Prune) => Ok(Some(Value::Array(
+            items
+                .iter()
+                .filter(|item| !values.contains(item))
+                .cloned()
+                .collect(),
+        ))),
+        (Some(_), _) => Err("the location is not a list".to_string()),
+    }
+}
+
+fn validate_assign_image(parameters: &AssignImageParameters) -> Result<(), String> {
+    if parameters.assign_domain.is_none()
+        && parameters.assign_path.is_none()
+        && parameters.assign_tag.is_none()
+    {
+        return Err("one of assignDomain, assignPath and assignTag must be set".to_string());
+    }
+    if let Some(domain) = &parameters.assign_domain
+        && (domain.is_empty() || domain.contains('/'))
+    {
+        return Err(format!("invalid assignDomain {domain:?}"));
+    }
+    if let Some(path) = &parameters.assign_path
+        && (path.is_empty() || path.starts_with('/') || path.ends_with('/'))
+    {
+        return Err(format!("invalid assignPath {path:?}"));
+    }
+    if let Some(tag) = &parameters.assign_tag
+        && !(tag.len() > 1 && (tag.starts_with(':') || tag.starts_with('@')))
+    {
+        return Err(format!(
+            "invalid assignTag {tag:?}, it must start with ':' or '@'"
+        ));
+    }
+    Ok(())
+}
+
+/// Split an image reference into its domain, path and tag or digest. The
+/// tag keeps its leading `:` or `@`
+fn split_image(image: &str) -> (Option<&str>, &str, &str) {
+    let tag_start = image.find('@').or_else(|| {
+        image
+            .rfind(':')
+            .filter(|&colon| !image[colon..].contains('/'))
+    });
+    let (name, tag) = image.split_at(tag_start.unwrap_or(image.len()));
+
+    match name.split_once('/') {
+        Some((domain, path))
+            if domain.contains('.') || domain.contains(':') || domain == "localhost" =>
+        {
+            (Some(domain), path, tag)
+        }
+        _ => (None, name, tag),
+    }
+}
+
+fn assign_image(image: &str, parameters: &AssignImageParameters) -> String {
+    let (domain, path, tag) = split_image(image);
+    let domain = parameters.assign_domain.as_deref().or(domain);
+    let path = parameters.assign_path.as_deref().unwrap_or(path);
+    let tag = parameters.assign_tag.as_deref().unwrap_or(tag);
+
+    match domain {
+        Some(domain) => format!("{domain}/{path}{tag}"),
+        None => format!("{path}{tag}"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Field(String),
+    /// An element of a list of objects, identified by the value of one of
+    /// their keys. A `None` value is the `*` glob, matching all the elements
+    Keyed {
+        field: String,
+        key: String,
+        value: Option<String>,
+    },
+}
+
+/// The parsed location of a mutator
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Location(Vec<Segment>);
+
+type Leaf<'a> = dyn FnMut(Option<&Value>) -> Result<Option<Value>, String> + 'a;
+
+impl Location {
+    fn parse(location: &str) -> Result<Location, GatekeeperMutationError> {
+        LocationParser {
+            location,
+            chars: location.chars().peekable(),
+        }
+        .parse()
+    }
+
+    fn is_metadata(&self) -> bool {
+        self.0.first() == Some(&Segment::Field("metadata".to_string()))
+    }
+
+    fn is_metadata_label_or_annotation(&self) -> bool {
+        matches!(
+            self.0.as_slice(),
+            [Segment::Field(metadata), Segment::Field(field), Segment::Field(_)]
+                if metadata == "metadata" && (field == "labels" || field == "annotations")
+        )
+    }
+
+    fn ends_with_keyed_list(&self) -> bool {
+        matches!(self.0.last(), Some(Segment::Keyed { .. }))
+    }
+
+    fn exists(&self, object: &Value) -> bool {
+        exists(object, &self.0)
+    }
+
+    /// Invoke `leaf` with the current value of each field the location points
+    /// to, replacing it with the returned value. When `create` is set, the
+    /// missing parents are created
+    fn visit(&self, object: &mut Value, create: bool, leaf: &mut Leaf<'_>) -> Result<(), String> {
+        visit(object, &self.0, create, leaf)
+    }
+}
+
+fn item_matches(item: &Value, key: &str, value: Option<&String>) -> bool {
+    item.get(key)
+        .is_some_and(|current| value.is_none_or(|value| current.as_str() == Some(value.as_str())))
+}
+
+fn exists(node: &Value, segments: &[Segment]) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return true;
+    };
+    match segment {
+        Segment::Field(field) => node.get(field).is_some_and(|child| exists(child, rest)),
+        Segment::Keyed { field, key, value } => node
+            .get(field)
+            .and_then(Value::as_array)
+            .is_some_and(|items| {
+                items
+                    .iter()
+                    .any(|item| item_matches(item, key, value.as_ref()) && exists(item, rest))
+            }),
+    }
+}
+
+fn visit(
+    node: &mut Value,
+    segments: &[Segment],
+    create: bool,
+    leaf: &mut Leaf<'_>,
+) -> Result<(), String> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return Ok(());
+    };
+    let Value::Object(object) = node else {
+        return Err("the location goes through a value that is not an object".to_string());
+    };
+
+    match segment {
+        Segment::Field(field) if rest.is_empty() => {
+            if let Some(value) = leaf(object.get(field))? {
+                object.insert(field.clone(), value);
+            }
+        }
+        Segment::Field(field) => match object.get_mut(field) {
+            Some(child) => visit(child, rest, create, leaf)?,
+            None if create => {
+                let mut child = Value::Object(Map::new());
+                visit(&mut child, rest, create, leaf)?;
+                if child != Value::Object(Map::new()) {
+                    object.insert(field.clone(), child);
+                }
+            }
+            None => {}
+        },
+        Segment::Keyed { field, key, value } => {
+            // the list is created only when a new element can be added to it
+            let created = !object.contains_key(field);
+            if created {
+                if !create || value.is_none() {
+                    return Ok(());
+                }
+                object.insert(field.clone(), Value::Array(Vec::new()));
+            }
+            let Some(Value::Array(items)) = object.get_mut(field) else {
+                return Err(format!("{field} is not a list"));
+            };
+
+            let mut found = false;
+            for item in items.iter_mut() {
+                if !item_matches(item, key, value.as_ref()) {
+                    continue;
+                }
+                found = true;
+                if rest.is_empty() {
+                    if let Some(new_item) = leaf(Some(&*item))? {
+                        *item = keyed_item(new_item, key, value.as_ref())?;
+                    }
+                } else {
+                    visit(item, rest, create, leaf)?;
+                }
+            }
+
+            if !found
+                && create
+                && let Some(value) = value
+            {
+                let new_item = serde_json::json!({ key: value });
+                let new_item = if rest.is_empty() {
+                    leaf(None)?
+                        .map(|new_item| keyed_item(new_item, key, Some(value)))
+                        .transpose()?
+                } else {
+                    let mut item = new_item.clone();
+                    visit(&mut item, rest, create, leaf)?;
+                    (item != new_item).then_some(item)
+                };
+                if let Some(new_item) = new_item {
+                    items.push(new_item);
+                }
+            }
+            if created && items.is_empty() {
+                object.remove(field);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ensure the element of a keyed list keeps its key
+fn keyed_item(item: Value, key: &str, value: Option<&String>) -> Result<Value, String> {
+    let Value::Object(mut item) = item else {
+        return Err("the elements of a keyed list must be objects".to_string());
+    };
+    if let Some(value) = value {
+        item.insert(key.to_string(), Value::String(value.clone()));
+    }
+    Ok(Value::Object(item))
+}
+
+struct LocationParser<'a> {
+    location: &'a str,
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl LocationParser<'_> {
+    fn error(&self, message: impl Into<String>) -> GatekeeperMutationError {
+        GatekeeperMutationError::InvalidLocation {
+            location: self.location.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Location, GatekeeperMutationError> {
+        let mut segments = Vec::new();
+        loop {
+            let (field, _) = self.token(&['.', '['])?;
+            if field.is_empty() {
+                return Err(self.error("empty field name"));
+            }
+
+            if self.chars.peek() == Some(&'[') {
+                self.chars.next();
+                let (key, _) = self.token(&[':'])?;
+                self.expect(':')?;
+                let (value, quoted) = self.token(&[']'])?;
+                self.expect(']')?;
+                if key.is_empty() || value.is_empty() {
+                    return Err(self.error("keyed lists must be written as [key: value]"));
+                }
+                segments.push(Segment::Keyed {
+                    field,
+                    key,
+                    value: (quoted || value != "*").then_some(value),
+                });
+            } else {
+                segments.push(Segment::Field(field));
+            }
+
+            match self.chars.next() {
+                None => return Ok(Location(segments)),
+                Some('.') => {}
+                Some(c) => return Err(self.error(format!("unexpected character {c:?}"))),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), GatekeeperMutationError> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(format!("expected {expected:?}"))),
+        }
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    /// Read a name, either quoted or ending with one of the terminators.
+    /// Returns whether it was quoted
+    fn token(&mut self, terminators: &[char]) -> Result<(String, bool), GatekeeperMutationError> {
+        self.skip_whitespaces();
+        let mut token = String::new();
+
+        if self.chars.next_if_eq(&'"').is_some() {
+            loop {
+                match self.chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match self.chars.next() {
+                        Some(c) => token.push(c),
+                        None => return Err(self.error("unterminated quoted name")),
+                    },
+                    Some(c) => token.push(c),
+                    None => return Err(self.error("unterminated quoted name")),
+                }
+            }
+            self.skip_whitespaces();
+            return Ok((token, true));
+        }
+
+        while let Some(c) = self
+            .chars
+            .next_if(|c| !terminators.contains(c) && !c.is_whitespace())
+        {
+            token.push(c);
+        }
+        self.skip_whitespaces();
+        Ok((token, false))
+    }
+}
+
+/// Apply the mutators matching the request to its object, returning the
+/// mutated object. Like Gatekeeper, the mutators are sorted by kind and name.
+pub fn apply_mutators(
+    request: &AdmissionRequest,
+    object: &Value,
+    mutators: &[Mutator],
+) -> Result<Value, GatekeeperMutationError> {
+    let mut mutators: Vec<&Mutator> = mutators
+        .iter()
+        .filter(|mutator| mutator.matches(request, object))
+        .collect();
+    mutators.sort_by(|a, b| (a.kind(), a.name()).cmp(&(b.kind(), b.name())));
+
+    let mut mutated = object.clone();
+    for _ in 0..MAX_ITERATIONS {
+        let previous = mutated.clone();
+        for mutator in &mutators {
+            mutator.mutate(request, &mut mutated)?;
+        }
+        if mutated == previous {
+            return Ok(mutated);
+        }
+    }
+
+    Err(GatekeeperMutationError::NotConverged(MAX_ITERATIONS))
+}
+
+/// Apply the mutators to the request, the changes are returned as a JSONPatch.
+/// The request is always accepted, unless the mutators cannot be applied.
+///
+/// Like the responses of the other policies, the response must be processed
+/// by the [`AdmissionResponseHandler`](crate::admission_response_handler::AdmissionResponseHandler),
+/// which rejects it when the policy is not allowed to mutate.
+pub fn mutate(request: &ValidateRequest, mutators: &[Mutator]) -> AdmissionResponse {
+    let uid = request.uid().to_string();
+    let ValidateRequest::AdmissionRequest(admission_request) = request else {
+        return AdmissionResponse::reject_internal_server_error(
+            uid,
+            GatekeeperMutationError::RawRequest.to_string(),
+        );
+    };
+    // DELETE requests do not have an object to mutate
+    let Some(object) = admission_request.object.as_ref().map(|object| &object.0) else {
+        return AdmissionResponse {
+            uid,
+            allowed: true,
+            ..Default::default()
+        };
+    };
+
+    let mutated_object = match apply_mutators(admission_request, object, mutators) {
+        Ok(mutated_object) => mutated_object,
+        Err(e) => return AdmissionResponse::reject_internal_server_error(uid, e.to_string()),
+    };
+    let validation_response = PolicyValidationResponse {
+        accepted: true,
+        message: None,
+        code: None,
+        mutated_object: Some(mutated_object),
+        audit_annotations: None,
+        warnings: None,
+    };
+
+    AdmissionResponse::from_policy_validation_response(
+        uid.clone(),
+        Some(object),
+        &validation_response,
+    )
+    .unwrap_or_else(|e| AdmissionResponse::reject_internal_server_error(uid, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{Engine as _, engine::general_purpose};
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::admission_response_handler::{
+        AdmissionResponseHandler, policy_id::PolicyID, policy_mode::PolicyMode,
+    };
+
+    fn pod() -> Value {
+        json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "name": "nginx",
+                "namespace": "default",
+                "labels": {
+                    "app": "nginx"
+                }
+            },
+            "spec": {
+                "containers": [
+                    {
+                        "name": "nginx",
+                        "image": "nginx:1.27"
+                    },
+                    {
+                        "name": "sidecar",
+                        "image": "ghcr.io/example/sidecar@sha256:1234"
+                    }
+                ]
+            }
+        })
+    }
+
+    fn admission_request(object: Option<Value>) -> AdmissionRequest {
+        serde_json::from_value(json!({
+            "uid": "1299d386-525b-4032-98ae-1949f69f9cfc",
+            "kind": {"group": "", "version": "v1", "kind": "Pod"},
+            "resource": {"group": "", "version": "v1", "resource": "pods"},
+            "name": "nginx",
+            "namespace": "default",
+            "operation": if object.is_some() { "CREATE" } else { "DELETE" },
+            "userInfo": {},
+            "object": object,
+        }))
+        .unwrap()
+    }
+
+    fn mutator(kind: &str, location: &str, parameters: Value) -> Mutator {
+        serde_json::from_value(json!({
+            "apiVersion": "mutations.gatekeeper.sh/v1",
+            "kind": kind,
+            "metadata": {"name": format!("{}-mutator", kind.to_lowercase())},
+            "spec": {
+                "applyTo": [{"groups": [""], "kinds": ["Pod"], "versions": ["v1"]}],
+                "location": location,
+                "parameters": parameters,
+            }
+        }))
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case::field("spec.dnsPolicy", vec![
+        Segment::Field("spec".to_string()),
+        Segment::Field("dnsPolicy".to_string()),
+    ])]
+    #[case::keyed_list("spec.containers[name: nginx].image", vec![
+        Segment::Field("spec".to_string()),
+        Segment::Keyed {field: "containers".to_string(), key: "name".to_string(), value: Some("nginx".to_string())},
+        Segment::Field("image".to_string()),
+    ])]
+    #[case::glob("spec.containers[name:*]", vec![
+        Segment::Field("spec".to_string()),
+        Segment::Keyed {field: "containers".to_string(), key: "name".to_string(), value: None},
+    ])]
+    #[case::quoted(r#"metadata.labels."app.kubernetes.io/name""#, vec![
+        Segment::Field("metadata".to_string()),
+        Segment::Field("labels".to_string()),
+        Segment::Field("app.kubernetes.io/name".to_string()),
+    ])]
+    #[case::quoted_glob(r#"spec.containers[name: "*"]"#, vec![
+        Segment::Field("spec".to_string()),
+        Segment::Keyed {field: "containers".to_string(), key: "name".to_string(), value: Some("*".to_string())},
+    ])]
+    fn parse_location(#[case] location: &str, #[case] expected: Vec<Segment>) {
+        assert_eq!(Location::parse(location).unwrap(), Location(expected));
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::empty_field("spec..image")]
+    #[case::unterminated_list("spec.containers[name: nginx")]
+    #[case::missing_value("spec.containers[name]")]
+    #[case::unterminated_quote(r#"metadata.labels."app"#)]
+    fn parse_invalid_location(#[case] location: &str) {
+        assert!(matches!(
+            Location::parse(location),
+            Err(GatekeeperMutationError::InvalidLocation { .. })
+        ));
+    }
+
+    #[rstest]
+    #[case::assign_creates_parents(
+        mutator("Assign", "spec.securityContext.runAsNonRoot", json!({"assign": {"value": true}})),
+        json!([{"op": "add", "path": "/spec/securityContext", "value": {"runAsNonRoot": true}}])
+    )]
+    #[case::assign_to_all_containers(
+        mutator("Assign", "spec.containers[name: *].imagePullPolicy", json!({"assign": {"value": "Always"}})),
+        json!([
+            {"op": "add", "path": "/spec/containers/0/imagePullPolicy", "value": "Always"},
+            {"op": "add", "path": "/spec/containers/1/imagePullPolicy", "value": "Always"}
+        ])
+    )]
+    #[case::assign_adds_missing_list_element(
+        mutator("Assign", "spec.containers[name: logger].image", json!({"assign": {"value": "busybox"}})),
+        json!([{"op": "add", "path": "/spec/containers/2", "value": {"name": "logger", "image": "busybox"}}])
+    )]
+    #[case::assign_path_test_must_not_exist(
+        mutator("Assign", "spec.containers[name: nginx].image", json!({
+            "assign": {"value": "nginx:latest"},
+            "pathTests": [{"subPath": "spec.containers[name: nginx].image", "condition": "MustNotExist"}]
+        })),
+        json!([])
+    )]
+    #[case::assign_path_test_must_exist(
+        mutator("Assign", "spec.containers[name: logger].image", json!({
+            "assign": {"value": "busybox"},
+            "pathTests": [{"subPath": "spec.containers[name: logger]", "condition": "MustExist"}]
+        })),
+        json!([])
+    )]
+    #[case::assign_if(
+        mutator("Assign", "spec.containers[name: *].image", json!({
+            "assign": {"value": "nginx:1.28"},
+            "assignIf": {"in": ["nginx:1.27"]}
+        })),
+        json!([{"op": "replace", "path": "/spec/containers/0/image", "value": "nginx:1.28"}])
+    )]
+    #[case::assign_from_metadata(
+        mutator("Assign", "spec.hostname", json!({"assign": {"fromMetadata": {"field": "name"}}})),
+        json!([{"op": "add", "path": "/spec/hostname", "value": "nginx"}])
+    )]
+    #[case::assign_metadata_adds_label(
+        mutator("AssignMetadata", "metadata.labels.owner", json!({"assign": {"value": "team-a"}})),
+        json!([{"op": "add", "path": "/metadata/labels/owner", "value": "team-a"}])
+    )]
+    #[case::assign_metadata_keeps_existing_label(
+        mutator("AssignMetadata", "metadata.labels.app", json!({"assign": {"value": "other"}})),
+        json!([])
+    )]
+    #[case::assign_metadata_creates_annotations(
+        mutator("AssignMetadata", "metadata.annotations.namespace", json!({"assign": {"fromMetadata": {"field": "namespace"}}})),
+        json!([{"op": "add", "path": "/metadata/annotations", "value": {"namespace": "default"}}])
+    )]
+    #[case::modify_set_merge(
+        mutator("ModifySet", "spec.containers[name: nginx].args", json!({"values": {"fromList": ["--verbose"]}})),
+        json!([{"op": "add", "path": "/spec/containers/0/args", "value": ["--verbose"]}])
+    )]
+    #[case::modify_set_prune_missing_list(
+        mutator("ModifySet", "spec.containers[name: nginx].args", json!({"operation": "prune", "values": {"fromList": ["--verbose"]}})),
+        json!([])
+    )]
+    #[case::assign_image_domain(
+        mutator("AssignImage", "spec.containers[name: *].image", json!({"assignDomain": "registry.example.com"})),
+        json!([
+            {"op": "replace", "path": "/spec/containers/0/image", "value": "registry.example.com/nginx:1.27"},
+            {"op": "replace", "path": "/spec/containers/1/image", "value": "registry.example.com/example/sidecar@sha256:1234"}
+        ])
+    )]
+    #[case::assign_image_tag(
+        mutator("AssignImage", "spec.containers[name: nginx].image", json!({"assignTag": ":1.28"})),
+        json!([{"op": "replace", "path": "/spec/containers/0/image", "value": "nginx:1.28"}])
+    )]
+    fn apply_mutator(#[case] mutator: Mutator, #[case] expected_patch: Value) {
+        mutator.validate().unwrap();
+        let request = admission_request(Some(pod()));
+        let expected_patch: json_patch::Patch = serde_json::from_value(expected_patch).unwrap();
+
+        let mutated = apply_mutators(&request, &pod(), &[mutator]).unwrap();
+
+        assert_eq!(json_patch::diff(&pod(), &mutated), expected_patch);
+    }
+
+    #[test]
+    fn modify_set_prune() {
+        let mut object = pod();
+        object["spec"]["containers"][0]["args"] = json!(["--verbose", "--debug"]);
+        let mutator = mutator(
+            "ModifySet",
+            "spec.containers[name: nginx].args",
+            json!({"operation": "prune", "values": {"fromList": ["--debug"]}}),
+        );
+
+        let mutated = apply_mutators(
+            &admission_request(Some(object.clone())),
+            &object,
+            &[mutator],
+        )
+        .unwrap();
+
+        assert_eq!(
+            mutated["spec"]["containers"][0]["args"],
+            json!(["--verbose"])
+        );
+    }
+
+    #[rstest]
+    #[case::matching(json!({"kinds": [{"apiGroups": [""], "kinds": ["Pod"]}], "namespaces": ["def*"]}), true)]
+    #[case::other_kind(json!({"kinds": [{"apiGroups": ["apps"], "kinds": ["Deployment"]}]}), false)]
+    #[case::excluded_namespace(json!({"excludedNamespaces": ["default"]}), false)]
+    #[case::cluster_scope(json!({"scope": "Cluster"}), false)]
+    #[case::name(json!({"name": "ngi*"}), true)]
+    #[case::label_selector(json!({"labelSelector": {"matchLabels": {"app": "nginx"}}}), true)]
+    #[case::label_selector_expression(
+        json!({"labelSelector": {"matchExpressions": [{"key": "app", "operator": "NotIn", "values": ["nginx"]}]}}),
+        false
+    )]
+    fn match_criteria(#[case] criteria: Value, #[case] matches: bool) {
+        let mut mutator = mutator(
+            "AssignMetadata",
+            "metadata.labels.owner",
+            json!({"assign": {"value": "team-a"}}),
+        );
+        if let Mutator::AssignMetadata(m) = &mut mutator {
+            m.spec.match_ = serde_json::from_value(criteria).unwrap();
+        }
+
+        assert_eq!(
+            mutator.matches(&admission_request(Some(pod())), &pod()),
+            matches
+        );
+    }
+
+    #[rstest]
+    #[case::cluster_scope(json!({"scope": "Cluster"}), true)]
+    #[case::namespaced_scope(json!({"scope": "Namespaced"}), false)]
+    #[case::namespace_name(json!({"namespaces": ["team-*"]}), true)]
+    #[case::excluded_namespace_name(json!({"excludedNamespaces": ["team-a"]}), false)]
+    fn match_criteria_of_namespace(#[case] criteria: Value, #[case] matches: bool) {
+        let namespace = json!({
+            "apiVersion": "v1",
+            "kind": "Namespace",
+            "metadata": {"name": "team-a"},
+        });
+        let mut request = admission_request(Some(namespace.clone()));
+        request.kind.kind = "Namespace".to_string();
+        request.name = Some("team-a".to_string());
+        request.namespace = Some("team-a".to_string());
+
+        let mut mutator = mutator(
+            "AssignMetadata",
+            "metadata.labels.owner",
+            json!({"assign": {"value": "team-a"}}),
+        );
+        if let Mutator::AssignMetadata(m) = &mut mutator {
+            m.spec.match_ = serde_json::from_value(criteria).unwrap();
+        }
+
+        assert_eq!(mutator.matches(&request, &namespace), matches);
+    }
+
+    #[test]
+    fn apply_to_must_match_the_request() {
+        let mut mutator = mutator(
+            "Assign",
+            "spec.dnsPolicy",
+            json!({"assign": {"value": "None"}}),
+        );
+        if let Mutator::Assign(m) = &mut mutator {
+            m.spec.apply_to[0].kinds = vec!["Deployment".to_string()];
+        }
+
+        let mutated = apply_mutators(&admission_request(Some(pod())), &pod(), &[mutator]).unwrap();
+
+        assert_eq!(mutated, pod());
+    }
+
+    #[rstest]
+    #[case::assign_metadata_with_assign(mutator("Assign", "metadata.labels.owner", json!({"assign": {"value": "team-a"}})))]
+    #[case::assign_metadata_location(mutator("AssignMetadata", "spec.dnsPolicy", json!({"assign": {"value": "None"}})))]
+    #[case::assign_metadata_not_string(mutator("AssignMetadata", "metadata.labels.owner", json!({"assign": {"value": 1}})))]
+    #[case::assign_without_value(mutator("Assign", "spec.dnsPolicy", json!({"assign": {}})))]
+    #[case::external_data(mutator("Assign", "spec.dnsPolicy", json!({"assign": {"externalData": {"provider": "p"}}})))]
+    #[case::path_test_not_prefix(mutator("Assign", "spec.dnsPolicy", json!({
+        "assign": {"value": "None"},
+        "pathTests": [{"subPath": "spec.containers", "condition": "MustExist"}]
+    })))]
+    #[case::modify_set_keyed_list(mutator("ModifySet", "spec.containers[name: nginx]", json!({"values": {"fromList": ["a"]}})))]
+    #[case::assign_image_without_changes(mutator("AssignImage", "spec.containers[name: *].image", json!({})))]
+    #[case::assign_image_invalid_tag(mutator("AssignImage", "spec.containers[name: *].image", json!({"assignTag": "latest"})))]
+    fn invalid_mutator(#[case] mutator: Mutator) {
+        assert!(matches!(
+            mutator.validate(),
+            Err(GatekeeperMutationError::InvalidMutator { .. })
+        ));
+    }
+
+    #[rstest]
+    #[case::image_with_tag("nginx:1.27", (None, "nginx", ":1.27"))]
+    #[case::image_without_tag("library/nginx", (None, "library/nginx", ""))]
+    #[case::registry_with_port("localhost:5000/nginx:1.27", (Some("localhost:5000"), "nginx", ":1.27"))]
+    #[case::digest("ghcr.io/example/app@sha256:1234", (Some("ghcr.io"), "example/app", "@sha256:1234"))]
+    fn split_image_reference(#[case] image: &str, #[case] expected: (Option<&str>, &str, &str)) {
+        assert_eq!(split_image(image), expected);
+    }
+
+    #[test]
+    fn mutate_returns_a_json_patch() {
+        let request = ValidateRequest::AdmissionRequest(Box::new(admission_request(Some(pod()))));
+        let mutators = vec![mutator(
+            "AssignMetadata",
+            "metadata.labels.owner",
+            json!({"assign": {"value": "team-a"}}),
+        )];
+
+        let response = mutate(&request, &mutators);
+
+        assert!(response.allowed);
+        assert_eq!(
+            response.patch_type,
+            Some(crate::admission_response::PatchType::JSONPatch)
+        );
+        let patch = general_purpose::STANDARD
+            .decode(response.patch.clone().unwrap())
+            .unwrap();
+        let patch: Value = serde_json::from_slice(&patch).unwrap();
+        assert_eq!(
+            patch,
+            json!([{"op": "add", "path": "/metadata/labels/owner", "value": "team-a"}])
+        );
+
+        // the response is rejected when the policy is not allowed to mutate
+        let policy_id = PolicyID::Policy("gatekeeper-mutation".to_string());
+        let handler = AdmissionResponseHandler::new(&policy_id, &PolicyMode::Protect, false, None);
+        let response = handler.process_response(response);
+        assert!(!response.allowed);
+        assert!(response.patch.is_none());
+    }
+
+    #[test]
+    fn mutate_delete_request() {
+        let request = ValidateRequest::AdmissionRequest(Box::new(admission_request(None)));
+        let mutators = vec![mutator(
+            "AssignMetadata",
+            "metadata.labels.owner",
+            json!({"assign": {"value": "team-a"}}),
+        )];
+
+        let response = mutate(&request, &mutators);
+
+        assert!(response.allowed);
+        assert!(response.patch.is_none());
+    }
+
+    #[test]
+    fn mutate_raw_request() {
+        let request = ValidateRequest::Raw(pod());
+
+        let response = mutate(&request, &[]);
+
+        assert!(!response.allowed);
+    }
+
+    #[test]
+    fn settings_from_policy_settings() {
+        let settings: PolicySettings = serde_json::from_value(json!({
+            "mutators": [{
+                "kind": "AssignMetadata",
+                "metadata": {"name": "owner"},
+                "spec": {
+                    "location": "metadata.labels.owner",
+                    "parameters": {"assign": {"value": "team-a"}}
+                }
+            }]
+        }))
+        .unwrap();
+
+        let settings = GatekeeperMutationSettings::try_from(&settings).unwrap();
+
+        assert_eq!(settings.mutators.len(), 1);
+        assert_eq!(settings.mutators[0].name(), "owner");
+        assert!(settings.validate().is_ok());
+    }
+}
@@ -1,12 +1,14 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt,
     sync::LazyLock,
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::errors::HostCapabilitiesPatternError;
+use crate::{
+    errors::HostCapabilitiesPatternError, host_capability_provider::HostCapabilityProviders,
+};
 
 /// A node in the host-capability path tree.
 /// Leaf nodes (complete, addressable operations) have a `None` value.
@@ -47,7 +49,10 @@ static CAPABILITY_TREE: LazyLock<CapabilityNode> = LazyLock::new(|| {
             "net",
             CapabilityNode::node(HashMap::from([(
                 "v1",
-                CapabilityNode::node(HashMap::from([("dns_lookup_host", CapabilityNode::leaf())])),
+                CapabilityNode::node(HashMap::from([
+                    ("dns_lookup_host", CapabilityNode::leaf()),
+                    ("http_request", CapabilityNode::leaf()),
+                ])),
             )])),
         ),
         (
@@ -83,10 +88,22 @@ static CAPABILITY_TREE: LazyLock<CapabilityNode> = LazyLock::new(|| {
 ///
 /// For exact paths every segment must lead to a known node, and the final
 /// segment must be a leaf.
-fn validate_against_tree(pattern: &str) -> Result<(), HostCapabilitiesPatternError> {
+///
+/// Namespaces that are not part of the tree are looked up among the
+/// registered host capability providers.
+fn validate_against_tree(
+    pattern: &str,
+    providers: &HostCapabilityProviders,
+) -> Result<(), HostCapabilitiesPatternError> {
     let parts: Vec<&str> = pattern.split('/').collect();
     let mut node: &CapabilityNode = &CAPABILITY_TREE;
 
+    if !CAPABILITY_TREE.0.contains_key(parts[0])
+        && let Some(operations) = providers.operations(parts[0])
+    {
+        return validate_against_provider(pattern, &parts[1..], &operations);
+    }
+
     for (i, &part) in parts.iter().enumerate() {
         if part == "*" {
             // Already guaranteed to be the last segment by the wildcard syntax
@@ -97,7 +114,10 @@ fn validate_against_tree(pattern: &str) -> Result<(), HostCapabilitiesPatternErr
 
         match node.0.get(part) {
             None => {
-                let mut valid: Vec<&str> = node.0.keys().copied().collect();
+                let mut valid: Vec<String> = node.0.keys().map(|key| key.to_string()).collect();
+                if i == 0 {
+                    valid.extend(providers.namespaces());
+                }
                 valid.sort_unstable();
                 return Err(HostCapabilitiesPatternError::UnknownSegment {
                     pattern: pattern.to_string(),
@@ -134,6 +154,44 @@ fn validate_against_tree(pattern: &str) -> Result<(), HostCapabilitiesPatternErr
     Ok(())
 }
 
+/// Validates the segments following a custom namespace against the
+/// operations declared by its host capability provider.
+fn validate_against_provider(
+    pattern: &str,
+    parts: &[&str],
+    operations: &BTreeSet<String>,
+) -> Result<(), HostCapabilitiesPatternError> {
+    let path = parts.join("/");
+
+    if let Some(prefix) = path.strip_suffix('*') {
+        // The wildcard must cover at least one of the declared operations
+        if operations.iter().any(|op| op.starts_with(prefix)) {
+            return Ok(());
+        }
+    } else if operations.contains(&path) {
+        return Ok(());
+    } else if path.is_empty()
+        || operations
+            .iter()
+            .any(|op| op.starts_with(&format!("{path}/")))
+    {
+        return Err(HostCapabilitiesPatternError::IncompleteCapabilityPath {
+            pattern: pattern.to_string(),
+            suggestion: format!("{pattern}/*"),
+        });
+    }
+
+    Err(HostCapabilitiesPatternError::UnknownSegment {
+        pattern: pattern.to_string(),
+        segment: path,
+        valid_options: operations
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join(", "),
+    })
+}
+
 /// Represents the set of host capabilities a policy is allowed to use.
 ///
 /// Host capability paths follow the format `{namespace}/{operation}`, e.g.
@@ -145,6 +203,9 @@ fn validate_against_tree(pattern: &str) -> Result<(), HostCapabilitiesPatternErr
 /// - `oci/v2/*`: allow all OCI v2 capabilities
 /// - `oci/v1/verify`: allow only the exact capability
 ///
+/// Capabilities served by the registered host capability providers (see
+/// `crate::host_capability_provider`) are accepted as well.
+///
 /// Invalid patterns (rejected at parse time):
 /// - `oci*`: wildcard must follow a `/`
 /// - `oci/v1/oci_*`: wildcard must be the entire last segment
@@ -173,6 +234,14 @@ impl HostCapabilities {
     /// The list is derived by recursively walking `CAPABILITY_TREE` and collecting
     /// every leaf path.
     pub fn enumerate_operations() -> Vec<(String, String)> {
+        Self::enumerate_operations_with_providers(&HostCapabilityProviders::default())
+    }
+
+    /// Like [`HostCapabilities::enumerate_operations`], including the operations
+    /// of the given host capability providers.
+    pub fn enumerate_operations_with_providers(
+        providers: &HostCapabilityProviders,
+    ) -> Vec<(String, String)> {
         fn walk(
             node: &CapabilityNode,
             path: &mut Vec<&'static str>,
@@ -195,6 +264,11 @@ impl HostCapabilities {
 
         let mut out = Vec::new();
         walk(&CAPABILITY_TREE, &mut vec![], &mut out);
+        for namespace in providers.namespaces() {
+            for operation in providers.operations(&namespace).unwrap_or_default() {
+                out.push((namespace.clone(), operation));
+            }
+        }
         out.sort();
         out
     }
@@ -205,6 +279,15 @@ impl HostCapabilities {
     /// an unknown capability namespace/operation.
     pub fn new(
         patterns: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Self, HostCapabilitiesPatternError> {
+        Self::new_with_providers(patterns, &HostCapabilityProviders::default())
+    }
+
+    /// Like [`HostCapabilities::new`], the patterns can refer to the
+    /// capabilities of the given host capability providers too.
+    pub fn new_with_providers(
+        patterns: impl IntoIterator<Item = impl AsRef<str>>,
+        providers: &HostCapabilityProviders,
     ) -> Result<Self, HostCapabilitiesPatternError> {
         let mut prefixes = HashSet::new();
         let mut exact = HashSet::new();
@@ -226,7 +309,7 @@ impl HostCapabilities {
                 });
             }
 
-            validate_against_tree(trimmed)?;
+            validate_against_tree(trimmed, providers)?;
 
             if let Some(prefix) = trimmed.strip_suffix("*") {
                 prefixes.insert(prefix.to_string());
@@ -435,6 +518,7 @@ mod tests {
             "oci/v1/oci_manifest",
             "oci/v1/oci_manifest_config",
             "net/v1/dns_lookup_host",
+            "net/v1/http_request",
             "net/*",
             "crypto/v1/is_certificate_trusted",
             "crypto/*",
@@ -489,6 +573,7 @@ mod tests {
             ("kubernetes", "list_resources_all"),
             ("kubernetes", "list_resources_by_namespace"),
             ("net", "v1/dns_lookup_host"),
+            ("net", "v1/http_request"),
             ("oci", "v1/manifest_digest"),
             ("oci", "v1/oci_manifest"),
             ("oci", "v1/oci_manifest_config"),
@@ -503,6 +588,79 @@ mod tests {
         assert_eq!(ops, expected);
     }
 
+    struct CmdbProvider;
+
+    impl crate::host_capability_provider::HostCapabilityProvider for CmdbProvider {
+        fn namespace(&self) -> String {
+            "cmdb".to_string()
+        }
+
+        fn operations(&self) -> BTreeSet<String> {
+            BTreeSet::from(["v1/team_owner".to_string(), "v1/cost_center".to_string()])
+        }
+
+        fn call(
+            &self,
+            _operation: &str,
+            _payload: serde_json::Value,
+        ) -> futures::future::BoxFuture<'static, anyhow::Result<serde_json::Value>> {
+            Box::pin(async { Ok(serde_json::Value::Null) })
+        }
+    }
+
+    fn cmdb_providers() -> HostCapabilityProviders {
+        let mut providers = HostCapabilityProviders::default();
+        providers
+            .register(std::sync::Arc::new(CmdbProvider))
+            .expect("cannot register provider");
+        providers
+    }
+
+    #[rstest]
+    #[case::exact("cmdb/v1/team_owner", "cmdb/v1/team_owner", true)]
+    #[case::exact_no_match("cmdb/v1/team_owner", "cmdb/v1/cost_center", false)]
+    #[case::namespace_wildcard("cmdb/*", "cmdb/v1/cost_center", true)]
+    #[case::version_wildcard("cmdb/v1/*", "cmdb/v1/team_owner", true)]
+    #[case::builtin_not_allowed("cmdb/*", "oci/v1/verify", false)]
+    fn provider_capability_allowed(
+        #[case] pattern: &str,
+        #[case] capability: &str,
+        #[case] expected: bool,
+    ) {
+        let allow_list = HostCapabilities::new_with_providers([pattern], &cmdb_providers())
+            .expect("pattern should be valid");
+        assert_eq!(allow_list.is_allowed(capability), expected);
+    }
+
+    #[rstest]
+    #[case::unknown_operation("cmdb/v1/nonexistent")]
+    #[case::unknown_version_wildcard("cmdb/v2/*")]
+    #[case::incomplete_path("cmdb")]
+    #[case::incomplete_path_version("cmdb/v1")]
+    #[case::unregistered_namespace("quota/v1/usage")]
+    fn invalid_provider_patterns(#[case] pattern: &str) {
+        let result = HostCapabilities::new_with_providers([pattern], &cmdb_providers());
+        assert!(result.is_err(), "pattern {pattern:?} should be invalid");
+    }
+
+    #[test]
+    fn unknown_namespace_error_lists_provider_namespaces() {
+        let err = HostCapabilities::new_with_providers(["quota/v1/usage"], &cmdb_providers())
+            .unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("cmdb"), "error should mention cmdb: {msg}");
+        assert!(msg.contains("oci"), "error should mention oci: {msg}");
+    }
+
+    #[test]
+    fn enumerate_operations_includes_provider_operations() {
+        let ops = HostCapabilities::enumerate_operations_with_providers(&cmdb_providers());
+
+        assert!(ops.contains(&("cmdb".to_string(), "v1/cost_center".to_string())));
+        assert!(ops.contains(&("cmdb".to_string(), "v1/team_owner".to_string())));
+        assert!(ops.contains(&("oci".to_string(), "v1/verify".to_string())));
+    }
+
     #[rstest]
     #[case::allow_all(HostCapabilities::AllowAll, "[*]")]
     #[case::deny_all(HostCapabilities::DenyAll, "[]")]
@@ -0,0 +1,328 @@
+//! Host capabilities provided by the embedder of policy-evaluator.
+//!
+//! The built-in host capabilities (`oci`, `net`, `crypto`, `kubernetes`) are
+//! implemented by policy-evaluator itself. Programs embedding policy-evaluator
+//! can expose additional, organisation specific, capabilities to policies by
+//! implementing the [`HostCapabilityProvider`] trait and registering it via
+//! [`CallbackHandlerBuilder::host_capability_provider`].
+//!
+//! Registered capabilities are handled like the built-in ones:
+//! * they can be referenced inside of the `HostCapabilities` allow list of a policy
+//! * policies invoke them via the waPC `host_call` function, using the
+//!   `kubewarden` binding, the namespace and the operation of the capability
+//! * the requests are evaluated by the `CallbackHandler`, hence they are
+//!   recorded and replayed by kwctl like any other host capability
+//!
+//! The providers registered on a `CallbackHandler` are returned by
+//! [`CallbackHandler::host_capability_providers`]. They must be given to
+//! [`HostCapabilities::new_with_providers`], otherwise their capabilities are
+//! rejected when the allow list of the policies is validated.
+//!
+//! [`CallbackHandlerBuilder::host_capability_provider`]: crate::callback_handler::CallbackHandlerBuilder::host_capability_provider
+//! [`CallbackHandler::host_capability_providers`]: crate::callback_handler::CallbackHandler::host_capability_providers
+//! [`HostCapabilities::new_with_providers`]: crate::host_capabilities::HostCapabilities::new_with_providers
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use serde_json::Value;
+
+use crate::errors::HostCapabilityProviderError;
+
+/// Namespaces that are reserved to the host capabilities implemented by
+/// policy-evaluator
+const RESERVED_NAMESPACES: &[&str] = &["crypto", "kubernetes", "net", "oci", "tracing"];
+
+/// A provider of host capabilities living under the same namespace
+pub trait HostCapabilityProvider: Send + Sync {
+    /// The namespace of the capabilities, e.g. `cmdb`
+    fn namespace(&self) -> String;
+
+    /// The operations handled by this provider, e.g. `v1/team_owner`.
+    /// The full path of a capability is `{namespace}/{operation}`.
+    fn operations(&self) -> BTreeSet<String>;
+
+    /// Evaluate the given operation. Both the payload sent by the policy
+    /// and the response given back to it are JSON documents.
+    fn call(&self, operation: &str, payload: Value) -> BoxFuture<'static, Result<Value>>;
+
+    /// How long a successful response is cached, using the operation and
+    /// the payload as key. No caching is done by default.
+    fn cache_ttl(&self) -> Option<Duration> {
+        None
+    }
+}
+
+struct RegisteredProvider {
+    provider: Arc<dyn HostCapabilityProvider>,
+    operations: BTreeSet<String>,
+    cache: Mutex<HashMap<(String, String), (Instant, Value)>>,
+}
+
+/// A set of registered host capability providers, indexed by namespace.
+/// Cloning it is cheap, the clones share the providers and their caches.
+#[derive(Clone, Default)]
+pub struct HostCapabilityProviders(Arc<HashMap<String, Arc<RegisteredProvider>>>);
+
+impl HostCapabilityProviders {
+    /// Register a new provider of host capabilities.
+    ///
+    /// An error is returned when the namespace of the provider is reserved to the
+    /// built-in capabilities, is already used by another provider, or when the
+    /// provider declares invalid operations.
+    pub(crate) fn register(
+        &mut self,
+        provider: Arc<dyn HostCapabilityProvider>,
+    ) -> Result<(), HostCapabilityProviderError> {
+        let namespace = provider.namespace();
+        if namespace.is_empty() || namespace.contains('/') || namespace.contains('*') {
+            return Err(HostCapabilityProviderError::InvalidNamespace(namespace));
+        }
+        if RESERVED_NAMESPACES.contains(&namespace.as_str()) {
+            return Err(HostCapabilityProviderError::ReservedNamespace(namespace));
+        }
+
+        let operations = provider.operations();
+        if operations.is_empty() {
+            return Err(HostCapabilityProviderError::NoOperations(namespace));
+        }
+        if let Some(operation) = operations.iter().find(|operation| {
+            operation.is_empty()
+                || operation.contains('*')
+                || operation.split('/').any(|segment| segment.is_empty())
+        }) {
+            return Err(HostCapabilityProviderError::InvalidOperation {
+                namespace,
+                operation: operation.to_owned(),
+            });
+        }
+
+        let providers = Arc::make_mut(&mut self.0);
+        if providers.contains_key(&namespace) {
+            return Err(HostCapabilityProviderError::AlreadyRegistered(namespace));
+        }
+        providers.insert(
+            namespace,
+            Arc::new(RegisteredProvider {
+                provider,
+                operations,
+                cache: Mutex::new(HashMap::new()),
+            }),
+        );
+
+        Ok(())
+    }
+
+    /// Returns the operations registered under the given namespace, `None` if
+    /// no provider has been registered for the namespace
+    pub(crate) fn operations(&self, namespace: &str) -> Option<BTreeSet<String>> {
+        self.0
+            .get(namespace)
+            .map(|registered| registered.operations.clone())
+    }
+
+    /// Returns the namespaces of all the registered providers
+    pub(crate) fn namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = self.0.keys().cloned().collect();
+        namespaces.sort();
+        namespaces
+    }
+
+    /// Evaluate the given operation using the provider registered for the
+    /// namespace. Successful responses are cached when the provider asks
+    /// for that.
+    pub(crate) async fn call_cached(
+        &self,
+        namespace: &str,
+        operation: &str,
+        payload: Value,
+    ) -> Result<cached::Return<Value>> {
+        let registered =
+            self.0.get(namespace).cloned().ok_or_else(|| {
+                HostCapabilityProviderError::UnknownNamespace(namespace.to_owned())
+            })?;
+        if !registered.operations.contains(operation) {
+            return Err(HostCapabilityProviderError::UnknownOperation {
+                namespace: namespace.to_owned(),
+                operation: operation.to_owned(),
+            }
+            .into());
+        }
+
+        let ttl = registered.provider.cache_ttl();
+        let key = (operation.to_owned(), payload.to_string());
+        if let Some(ttl) = ttl {
+            let mut cache = registered.cache.lock().expect("cannot acquire cache lock");
+            cache.retain(|_, (inserted_at, _)| inserted_at.elapsed() < ttl);
+            if let Some((_, value)) = cache.get(&key) {
+                return Ok(cached::Return {
+                    was_cached: true,
+                    value: value.clone(),
+                });
+            }
+        }
+
+        let value = registered.provider.call(operation, payload).await?;
+
+        if ttl.is_some() {
+            registered
+                .cache
+                .lock()
+                .expect("cannot acquire cache lock")
+                .insert(key, (Instant::now(), value.clone()));
+        }
+
+        Ok(cached::Return::new(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct TeamOwnerProvider {
+        namespace: String,
+        operations: BTreeSet<String>,
+        cache_ttl: Option<Duration>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl TeamOwnerProvider {
+        fn new(namespace: &str, operations: &[&str]) -> Self {
+            TeamOwnerProvider {
+                namespace: namespace.to_owned(),
+                operations: operations.iter().map(|op| op.to_string()).collect(),
+                cache_ttl: None,
+                calls: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+    }
+
+    impl HostCapabilityProvider for TeamOwnerProvider {
+        fn namespace(&self) -> String {
+            self.namespace.clone()
+        }
+
+        fn operations(&self) -> BTreeSet<String> {
+            self.operations.clone()
+        }
+
+        fn call(&self, _operation: &str, payload: Value) -> BoxFuture<'static, Result<Value>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { Ok(json!({"owner": "team-a", "request": payload})) })
+        }
+
+        fn cache_ttl(&self) -> Option<Duration> {
+            self.cache_ttl
+        }
+    }
+
+    #[rstest]
+    #[case::reserved_namespace("oci", &["v1/lookup"])]
+    #[case::empty_namespace("", &["v1/lookup"])]
+    #[case::namespace_with_slash("cmdb/v1", &["lookup"])]
+    #[case::no_operations("cmdb", &[])]
+    #[case::operation_with_wildcard("cmdb", &["v1/*"])]
+    #[case::operation_with_empty_segment("cmdb", &["v1//lookup"])]
+    fn invalid_provider_is_rejected(#[case] namespace: &str, #[case] operations: &[&str]) {
+        let mut providers = HostCapabilityProviders::default();
+
+        let result = providers.register(Arc::new(TeamOwnerProvider::new(namespace, operations)));
+
+        assert!(
+            result.is_err(),
+            "{namespace} {operations:?} should be rejected"
+        );
+        assert!(providers.namespaces().is_empty());
+    }
+
+    #[test]
+    fn namespace_cannot_be_registered_twice() {
+        let mut providers = HostCapabilityProviders::default();
+        providers
+            .register(Arc::new(TeamOwnerProvider::new("cmdb", &["v1/team_owner"])))
+            .unwrap();
+
+        let result =
+            providers.register(Arc::new(TeamOwnerProvider::new("cmdb", &["v2/team_owner"])));
+
+        assert!(matches!(
+            result,
+            Err(HostCapabilityProviderError::AlreadyRegistered(_))
+        ));
+        assert_eq!(
+            providers.operations("cmdb"),
+            Some(BTreeSet::from(["v1/team_owner".to_string()]))
+        );
+    }
+
+    #[tokio::test]
+    async fn call_registered_operation() {
+        let mut providers = HostCapabilityProviders::default();
+        providers
+            .register(Arc::new(TeamOwnerProvider::new("cmdb", &["v1/team_owner"])))
+            .unwrap();
+
+        let response = providers
+            .call_cached("cmdb", "v1/team_owner", json!({"namespace": "default"}))
+            .await
+            .unwrap();
+
+        assert!(!response.was_cached);
+        assert_eq!(
+            response.value,
+            json!({"owner": "team-a", "request": {"namespace": "default"}})
+        );
+    }
+
+    #[rstest]
+    #[case::unknown_namespace("quota", "v1/team_owner")]
+    #[case::unknown_operation("cmdb", "v1/cost_center")]
+    #[tokio::test]
+    async fn call_unknown_capability(#[case] namespace: &str, #[case] operation: &str) {
+        let mut providers = HostCapabilityProviders::default();
+        providers
+            .register(Arc::new(TeamOwnerProvider::new("cmdb", &["v1/team_owner"])))
+            .unwrap();
+
+        let result = providers.call_cached(namespace, operation, json!({})).await;
+
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    #[case::caching_enabled(Some(Duration::from_secs(60)), 1)]
+    #[case::caching_disabled(None, 2)]
+    #[tokio::test]
+    async fn responses_are_cached_only_when_requested(
+        #[case] cache_ttl: Option<Duration>,
+        #[case] expected_calls: usize,
+    ) {
+        let mut providers = HostCapabilityProviders::default();
+        let mut provider = TeamOwnerProvider::new("cmdb", &["v1/team_owner"]);
+        provider.cache_ttl = cache_ttl;
+        let calls = provider.calls.clone();
+        providers.register(Arc::new(provider)).unwrap();
+
+        let first = providers
+            .call_cached("cmdb", "v1/team_owner", json!({"namespace": "default"}))
+            .await
+            .unwrap();
+        let second = providers
+            .call_cached("cmdb", "v1/team_owner", json!({"namespace": "default"}))
+            .await
+            .unwrap();
+
+        assert_eq!(first.value, second.value);
+        assert_eq!(second.was_cached, cache_ttl.is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), expected_calls);
+    }
+}
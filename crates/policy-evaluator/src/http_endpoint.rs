@@ -0,0 +1,119 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+fn default_methods() -> BTreeSet<String> {
+    BTreeSet::from(["GET".to_string()])
+}
+
+/// An HTTP endpoint a policy is allowed to reach via the `net/v1/http_request`
+/// host capability
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct HttpEndpoint {
+    /// Only the URLs starting with this prefix can be requested.
+    ///
+    /// The scheme, the host and the port of the requested URL must match the
+    /// ones of the prefix. The path of the prefix is matched on a path segment
+    /// boundary: `https://svc.corp/api` allows `https://svc.corp/api/quota`,
+    /// but not `https://svc.corp/api-admin`.
+    pub url_prefix: Url,
+    /// The HTTP methods that can be used against the endpoint, `GET` when
+    /// not specified
+    #[serde(default = "default_methods")]
+    pub methods: BTreeSet<String>,
+}
+
+impl HttpEndpoint {
+    /// Returns `true` when a request with the given method and URL can be
+    /// made against this endpoint
+    pub fn allows(&self, method: &str, url: &Url) -> bool {
+        if !self
+            .methods
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(method))
+        {
+            return false;
+        }
+
+        if self.url_prefix.scheme() != url.scheme()
+            || self.url_prefix.host_str() != url.host_str()
+            || self.url_prefix.port_or_known_default() != url.port_or_known_default()
+        {
+            return false;
+        }
+
+        let prefix = self.url_prefix.path();
+        let path = url.path();
+        match path.strip_prefix(prefix) {
+            Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn endpoint(url_prefix: &str, methods: &[&str]) -> HttpEndpoint {
+        HttpEndpoint {
+            url_prefix: Url::parse(url_prefix).unwrap(),
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    #[rstest]
+    #[case::exact_url("https://svc.corp/api", "GET", "https://svc.corp/api", true)]
+    #[case::sub_path("https://svc.corp/api", "GET", "https://svc.corp/api/quota", true)]
+    #[case::query_string(
+        "https://svc.corp/api/",
+        "GET",
+        "https://svc.corp/api/q?ns=default",
+        true
+    )]
+    #[case::method_case_insensitive("https://svc.corp/api", "get", "https://svc.corp/api", true)]
+    #[case::root_prefix("https://svc.corp", "GET", "https://svc.corp/anything", true)]
+    #[case::explicit_default_port("https://svc.corp/api", "GET", "https://svc.corp:443/api", true)]
+    #[case::method_not_allowed("https://svc.corp/api", "POST", "https://svc.corp/api", false)]
+    #[case::path_not_on_segment_boundary(
+        "https://svc.corp/api",
+        "GET",
+        "https://svc.corp/api-admin",
+        false
+    )]
+    #[case::path_traversal("https://svc.corp/api/", "GET", "https://svc.corp/api/../admin", false)]
+    #[case::different_scheme("https://svc.corp/api", "GET", "http://svc.corp/api", false)]
+    #[case::different_port("https://svc.corp/api", "GET", "https://svc.corp:8443/api", false)]
+    #[case::host_suffix("https://svc.corp/api", "GET", "https://svc.corp.evil.com/api", false)]
+    #[case::userinfo_trick("https://svc.corp/api", "GET", "https://svc.corp@evil.com/api", false)]
+    fn endpoint_allows(
+        #[case] url_prefix: &str,
+        #[case] method: &str,
+        #[case] url: &str,
+        #[case] expected: bool,
+    ) {
+        let endpoint = endpoint(url_prefix, &["GET"]);
+        let url = Url::parse(url).unwrap();
+
+        assert_eq!(endpoint.allows(method, &url), expected, "{method} {url}");
+    }
+
+    #[test]
+    fn deserialize_with_default_methods() {
+        let deserialized: HttpEndpoint =
+            serde_json::from_str(r#"{"urlPrefix": "https://svc.corp/api"}"#).unwrap();
+
+        assert_eq!(deserialized, endpoint("https://svc.corp/api", &["GET"]));
+    }
+
+    #[test]
+    fn deserialize_invalid_url_prefix() {
+        let result: Result<HttpEndpoint, _> =
+            serde_json::from_str(r#"{"urlPrefix": "not a url", "methods": ["GET"]}"#);
+
+        assert!(result.is_err());
+    }
+}
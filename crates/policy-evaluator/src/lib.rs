@@ -8,7 +8,11 @@ pub mod callback_requests;
 pub mod constants;
 pub mod errors;
 pub mod evaluation_context;
+pub mod gatekeeper_mutation;
 pub mod host_capabilities;
+pub mod host_capability_provider;
+pub mod http_endpoint;
+pub mod mutating_admission_policy;
 pub mod policy_artifacthub;
 pub mod policy_evaluator;
 pub mod policy_group_evaluator;
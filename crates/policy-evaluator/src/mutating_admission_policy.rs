@@ -0,0 +1,243 @@
+//! Helpers to turn the mutations of a Kubernetes `MutatingAdmissionPolicy`
+//! into a JSONPatch that can be returned inside of an `AdmissionResponse`.
+//!
+//! The CEL expressions of the mutations are evaluated by the policy. What
+//! is handled here is the evaluated output of each mutation: either an
+//! `ApplyConfiguration` object, or the list of JSONPatch operations.
+//! The policy returns them inside of the `mutations` of its validation
+//! response, see [`ExtendedPolicyValidationResponse`](crate::admission_response::ExtendedPolicyValidationResponse).
+//! See https://kubernetes.io/docs/reference/access-authn-authz/mutating-admission-policy/
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::errors::MutationError;
+
+/// The evaluated output of a `MutatingAdmissionPolicy` mutation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "patchType")]
+pub enum Mutation {
+    /// An object that is merged into the request object, following the
+    /// semantics of Server Side Apply
+    #[serde(rename = "ApplyConfiguration")]
+    ApplyConfiguration {
+        #[serde(rename = "applyConfiguration")]
+        apply_configuration: Value,
+    },
+    /// A list of RFC 6902 operations applied to the request object
+    #[serde(rename = "JSONPatch")]
+    JSONPatch {
+        #[serde(rename = "jsonPatch")]
+        json_patch: json_patch::Patch,
+    },
+}
+
+/// Apply the given mutations, in order, to the object. Each mutation sees the
+/// result of the previous one, like the Kubernetes API server does.
+pub fn apply_mutations(object: &Value, mutations: &[Mutation]) -> Result<Value, MutationError> {
+    let mut mutated = object.clone();
+
+    for mutation in mutations {
+        match mutation {
+            Mutation::ApplyConfiguration {
+                apply_configuration,
+            } => {
+                if !apply_configuration.is_object() {
+                    return Err(MutationError::InvalidApplyConfiguration);
+                }
+                merge_apply_configuration(&mut mutated, apply_configuration);
+            }
+            Mutation::JSONPatch { json_patch } => {
+                json_patch::patch(&mut mutated, json_patch).map_err(MutationError::JSONPatch)?;
+            }
+        }
+    }
+
+    Ok(mutated)
+}
+
+/// Merge an apply configuration into the target.
+///
+/// Maps are merged recursively, a `null` value removes the field from the
+/// target like Server Side Apply does. Lists made only of objects that have a `name`
+/// are merged using `name` as key, which is how the most common Kubernetes
+/// list-type=map lists (containers, volumes, env, ports,...) are declared.
+/// Every other list is considered atomic and is replaced.
+fn merge_apply_configuration(target: &mut Value, apply_configuration: &Value) {
+    match (target, apply_configuration) {
+        (Value::Object(target), Value::Object(apply_configuration)) => {
+            for (key, value) in apply_configuration {
+                if value.is_null() {
+                    target.remove(key);
+                    continue;
+                }
+                match target.get_mut(key) {
+                    Some(current) => merge_apply_configuration(current, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(apply_configuration))
+            if is_named_list(target) && is_named_list(apply_configuration) =>
+        {
+            for item in apply_configuration {
+                let name = item.get("name");
+                match target
+                    .iter_mut()
+                    .find(|current| current.get("name") == name)
+                {
+                    Some(current) => merge_apply_configuration(current, item),
+                    None => target.push(item.clone()),
+                }
+            }
+        }
+        (target, apply_configuration) => *target = apply_configuration.clone(),
+    }
+}
+
+fn is_named_list(list: &[Value]) -> bool {
+    list.iter()
+        .all(|item| item.as_object().is_some_and(has_name))
+}
+
+fn has_name(item: &Map<String, Value>) -> bool {
+    item.get("name").is_some_and(Value::is_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn pod() -> Value {
+        json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "name": "nginx",
+                "labels": {
+                    "app": "nginx"
+                }
+            },
+            "spec": {
+                "containers": [
+                    {
+                        "name": "nginx",
+                        "image": "nginx:latest"
+                    }
+                ]
+            }
+        })
+    }
+
+    #[rstest]
+    #[case::apply_configuration_adds_label(
+        json!([{
+            "patchType": "ApplyConfiguration",
+            "applyConfiguration": {
+                "metadata": {"labels": {"environment": "test"}}
+            }
+        }]),
+        json!([{"op": "add", "path": "/metadata/labels/environment", "value": "test"}])
+    )]
+    #[case::apply_configuration_merges_named_lists(
+        json!([{
+            "patchType": "ApplyConfiguration",
+            "applyConfiguration": {
+                "spec": {
+                    "containers": [
+                        {"name": "nginx", "imagePullPolicy": "Always"},
+                        {"name": "sidecar", "image": "busybox"}
+                    ]
+                }
+            }
+        }]),
+        json!([
+            {"op": "add", "path": "/spec/containers/0/imagePullPolicy", "value": "Always"},
+            {"op": "add", "path": "/spec/containers/1", "value": {"name": "sidecar", "image": "busybox"}}
+        ])
+    )]
+    #[case::json_patch(
+        json!([{
+            "patchType": "JSONPatch",
+            "jsonPatch": [
+                {"op": "replace", "path": "/spec/containers/0/image", "value": "nginx:1.27"}
+            ]
+        }]),
+        json!([{"op": "replace", "path": "/spec/containers/0/image", "value": "nginx:1.27"}])
+    )]
+    #[case::mutations_are_applied_in_order(
+        json!([
+            {
+                "patchType": "JSONPatch",
+                "jsonPatch": [
+                    {"op": "add", "path": "/metadata/annotations", "value": {"owner": "team-a"}}
+                ]
+            },
+            {
+                "patchType": "ApplyConfiguration",
+                "applyConfiguration": {
+                    "metadata": {"annotations": {"owner": "team-b"}}
+                }
+            }
+        ]),
+        json!([{"op": "add", "path": "/metadata/annotations", "value": {"owner": "team-b"}}])
+    )]
+    #[case::no_changes(
+        json!([{
+            "patchType": "ApplyConfiguration",
+            "applyConfiguration": {
+                "metadata": {"labels": {"app": "nginx"}}
+            }
+        }]),
+        json!([])
+    )]
+    #[case::apply_configuration_null_removes_field(
+        json!([{
+            "patchType": "ApplyConfiguration",
+            "applyConfiguration": {
+                "metadata": {"labels": {"app": null, "owner": null}}
+            }
+        }]),
+        json!([{"op": "remove", "path": "/metadata/labels/app"}])
+    )]
+    fn produce_patch_from_mutations(#[case] mutations: Value, #[case] expected_patch: Value) {
+        let mutations: Vec<Mutation> = serde_json::from_value(mutations).unwrap();
+        let expected_patch: json_patch::Patch = serde_json::from_value(expected_patch).unwrap();
+
+        let mutated = apply_mutations(&pod(), &mutations).unwrap();
+
+        assert_eq!(expected_patch, json_patch::diff(&pod(), &mutated));
+    }
+
+    #[test]
+    fn invalid_json_patch_mutation() {
+        let mutations = vec![Mutation::JSONPatch {
+            json_patch: serde_json::from_value(json!([
+                {"op": "remove", "path": "/spec/volumes"}
+            ]))
+            .unwrap(),
+        }];
+
+        let result = apply_mutations(&pod(), &mutations);
+
+        assert!(matches!(result, Err(MutationError::JSONPatch(_))));
+    }
+
+    #[test]
+    fn apply_configuration_must_be_an_object() {
+        let mutations = vec![Mutation::ApplyConfiguration {
+            apply_configuration: json!(["not", "an", "object"]),
+        }];
+
+        let result = apply_mutations(&pod(), &mutations);
+
+        assert!(matches!(
+            result,
+            Err(MutationError::InvalidApplyConfiguration)
+        ));
+    }
+}
@@ -26,6 +26,9 @@ pub enum PolicyExecutionMode {
     OpaGatekeeper,
     #[serde(rename = "wasi")]
     Wasi,
+    /// Gatekeeper mutators, applied natively. See [`crate::gatekeeper_mutation`]
+    #[serde(rename = "gatekeeper-mutation")]
+    GatekeeperMutation,
 }
 
 impl fmt::Display for PolicyExecutionMode {
@@ -73,7 +76,9 @@ impl TryFrom<PolicyExecutionMode> for RegoPolicyExecutionMode {
         match execution_mode {
             PolicyExecutionMode::Opa => Ok(RegoPolicyExecutionMode::Opa),
             PolicyExecutionMode::OpaGatekeeper => Ok(RegoPolicyExecutionMode::Gatekeeper),
-            PolicyExecutionMode::KubewardenWapc | PolicyExecutionMode::Wasi => {
+            PolicyExecutionMode::KubewardenWapc
+            | PolicyExecutionMode::Wasi
+            | PolicyExecutionMode::GatekeeperMutation => {
                 Err(PolicyEvaluatorBuilderError::ExecutionModeNotRegoCompatible)
             }
         }
@@ -128,6 +133,10 @@ mod tests {
             serde_json::to_string(&json!("gatekeeper")).unwrap(),
             PolicyExecutionMode::OpaGatekeeper,
         );
+        test_data.insert(
+            serde_json::to_string(&json!("gatekeeper-mutation")).unwrap(),
+            PolicyExecutionMode::GatekeeperMutation,
+        );
 
         for (expected, mode) in &test_data {
             let actual = serde_json::to_string(&mode);
@@ -151,6 +160,10 @@ mod tests {
             serde_json::to_string(&json!("gatekeeper")).unwrap(),
             PolicyExecutionMode::OpaGatekeeper,
         );
+        test_data.insert(
+            serde_json::to_string(&json!("gatekeeper-mutation")).unwrap(),
+            PolicyExecutionMode::GatekeeperMutation,
+        );
 
         for (mode_str, expected) in &test_data {
             let actual: std::result::Result<PolicyExecutionMode, serde_json::Error> =
@@ -46,6 +46,7 @@ impl PolicyEvaluator {
                 }
             }
             Runtime::Cli(ref mut cli_stack) => WasiRuntime(cli_stack).validate(settings, &request),
+            Runtime::GatekeeperMutation(ref runtime) => runtime.validate(settings, &request),
         }
     }
 
@@ -71,6 +72,7 @@ impl PolicyEvaluator {
             Runtime::Cli(ref mut cli_stack) => {
                 WasiRuntime(cli_stack).validate_settings(settings_str)
             }
+            Runtime::GatekeeperMutation(ref runtime) => runtime.validate_settings(settings_str),
         }
     }
 
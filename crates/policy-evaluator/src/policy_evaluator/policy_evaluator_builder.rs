@@ -34,6 +34,7 @@ pub struct PolicyEvaluatorBuilder {
     execution_mode: Option<PolicyExecutionMode>,
     wasmtime_cache: bool,
     epoch_deadlines: Option<EpochDeadlines>,
+    skip_kubernetes_inventory: bool,
 }
 
 impl PolicyEvaluatorBuilder {
@@ -99,6 +100,16 @@ impl PolicyEvaluatorBuilder {
         self
     }
 
+    /// Do not provide the inventory of the context aware resources to Rego policies.
+    /// Meant for the policies fetching the Kubernetes resources they need via the
+    /// `kubewarden.kubernetes.*` builtins, see
+    /// [`Metadata::kubernetes_inventory`](crate::policy_metadata::Metadata::kubernetes_inventory)
+    #[must_use]
+    pub fn skip_kubernetes_inventory(mut self) -> PolicyEvaluatorBuilder {
+        self.skip_kubernetes_inventory = true;
+        self
+    }
+
     /// Enable Wasmtime [epoch-based interruptions](wasmtime::Config::epoch_interruption) and set
     /// the deadlines to be enforced
     ///
@@ -143,9 +154,11 @@ impl PolicyEvaluatorBuilder {
             return Err(InvalidUserInputError::ContentsAndModule);
         }
 
+        // the Gatekeeper mutators are applied natively, they don't need a module
         if self.policy_file.is_none()
             && self.policy_contents.is_none()
             && self.policy_module.is_none()
+            && self.execution_mode != Some(PolicyExecutionMode::GatekeeperMutation)
         {
             return Err(InvalidUserInputError::OneOfFileContentsModule);
         }
@@ -162,36 +175,47 @@ impl PolicyEvaluatorBuilder {
         self.validate_user_input()
             .map_err(PolicyEvaluatorBuilderError::InvalidUserInput)?;
 
-        let engine = self.build_engine()?;
-        let module = self.build_module(&engine)?;
-
         let execution_mode = self.execution_mode.unwrap_or_default();
 
         let stack_pre = match execution_mode {
             PolicyExecutionMode::KubewardenWapc => {
+                let (engine, module) = self.build_engine_and_module()?;
                 let wapc_stack_pre = wapc::StackPre::new(engine, module)
                     .map_err(PolicyEvaluatorBuilderError::NewWapcStackPre)?;
                 StackPre::from(wapc_stack_pre)
             }
             PolicyExecutionMode::Wasi => {
+                let (engine, module) = self.build_engine_and_module()?;
                 let wasi_stack_pre = wasi_cli::StackPre::new(engine, module)
                     .map_err(PolicyEvaluatorBuilderError::NewWasiStackPre)?;
                 StackPre::from(wasi_stack_pre)
             }
             PolicyExecutionMode::Opa | PolicyExecutionMode::OpaGatekeeper => {
+                let (engine, module) = self.build_engine_and_module()?;
                 let rego_stack_pre = rego::StackPre::new(
                     engine,
                     module,
                     0, // currently the entrypoint is hard coded to this value
                     execution_mode.try_into()?,
+                    !self.skip_kubernetes_inventory,
                 );
                 StackPre::from(rego_stack_pre)
             }
+            // the mutators are read from the settings, the Wasm module is not used
+            PolicyExecutionMode::GatekeeperMutation => StackPre::GatekeeperMutation,
         };
 
         Ok(PolicyEvaluatorPre::new(stack_pre))
     }
 
+    fn build_engine_and_module(
+        &self,
+    ) -> Result<(wasmtime::Engine, wasmtime::Module), PolicyEvaluatorBuilderError> {
+        let engine = self.build_engine()?;
+        let module = self.build_module(&engine)?;
+        Ok((engine, module))
+    }
+
     fn build_engine(&self) -> Result<wasmtime::Engine, PolicyEvaluatorBuilderError> {
         self.engine
             .as_ref()
@@ -237,6 +261,7 @@ impl PolicyEvaluatorBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{evaluation_context::EvaluationContext, policy_evaluator::PolicySettings};
 
     #[test]
     fn build_policy_evaluator_pre() {
@@ -253,4 +278,20 @@ mod tests {
 
         _ = policy_evaluator_builder.build_pre().unwrap();
     }
+
+    #[test]
+    fn build_gatekeeper_mutation_policy_evaluator() {
+        // the mutators are applied natively, no module is needed
+        let policy_evaluator_pre = PolicyEvaluatorBuilder::new()
+            .execution_mode(PolicyExecutionMode::GatekeeperMutation)
+            .build_pre()
+            .unwrap();
+
+        let mut policy_evaluator = policy_evaluator_pre
+            .rehydrate(&EvaluationContext::default())
+            .unwrap();
+        let settings: PolicySettings =
+            serde_json::from_value(serde_json::json!({"mutators": []})).unwrap();
+        assert!(policy_evaluator.validate_settings(&settings).valid);
+    }
 }
@@ -3,7 +3,7 @@ use std::result::Result;
 use crate::errors::PolicyEvaluatorPreError;
 use crate::evaluation_context::EvaluationContext;
 use crate::policy_evaluator::{PolicyEvaluator, stack_pre::StackPre};
-use crate::runtimes::{Runtime, rego, wapc, wasi_cli};
+use crate::runtimes::{Runtime, gatekeeper_mutation, rego, wapc, wasi_cli};
 
 /// This struct provides a way to quickly allocate a `PolicyEvaluator`
 /// object.
@@ -45,6 +45,9 @@ impl PolicyEvaluatorPre {
                     .map_err(PolicyEvaluatorPreError::RehydrateRego)?;
                 Runtime::Rego(Box::new(rego_stack))
             }
+            StackPre::GatekeeperMutation => {
+                Runtime::GatekeeperMutation(gatekeeper_mutation::Runtime)
+            }
         };
 
         Ok(PolicyEvaluator::new(runtime, eval_ctx))
@@ -13,6 +13,8 @@ pub(crate) enum StackPre {
     Wapc(Box<crate::runtimes::wapc::StackPre>),
     Wasi(crate::runtimes::wasi_cli::StackPre),
     Rego(crate::runtimes::rego::StackPre),
+    /// Gatekeeper mutators are applied natively, there's nothing to pre-initialize
+    GatekeeperMutation,
 }
 
 impl From<wapc::StackPre> for StackPre {
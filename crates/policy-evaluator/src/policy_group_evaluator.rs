@@ -9,8 +9,11 @@ pub mod errors;
 pub mod evaluator;
 
 use crate::{
-    admission_response::AdmissionResponse, host_capabilities::HostCapabilities,
-    policy_evaluator::PolicySettings, policy_metadata::ContextAwareResource,
+    admission_response::{AdmissionResponse, StatusCause},
+    host_capabilities::HostCapabilities,
+    http_endpoint::HttpEndpoint,
+    policy_evaluator::PolicySettings,
+    policy_metadata::ContextAwareResource,
 };
 
 /// The settings of a policy group member
@@ -24,6 +27,8 @@ pub struct PolicyGroupMemberSettings {
     pub epoch_deadline: Option<u64>,
     /// The list of host capabilities granted to this policy member
     pub host_capabilities: HostCapabilities,
+    /// The list of HTTP endpoints the policy member is allowed to reach
+    pub http_endpoints_allow_list: BTreeSet<HttpEndpoint>,
 }
 
 /// This holds the a summary of the evaluation results of a policy group member
@@ -32,13 +37,27 @@ struct PolicyGroupMemberEvaluationResult {
     allowed: bool,
     /// the optional message included inside of the evaluation result of the policy
     message: Option<String>,
+    /// The causes reported by the policy, if any
+    causes: Vec<StatusCause>,
 }
 
 impl From<AdmissionResponse> for PolicyGroupMemberEvaluationResult {
     fn from(response: AdmissionResponse) -> Self {
+        let (message, causes) = match response.status {
+            Some(status) => (
+                status.message,
+                status
+                    .details
+                    .map(|details| details.causes)
+                    .unwrap_or_default(),
+            ),
+            None => (None, Vec::new()),
+        };
+
         Self {
             allowed: response.allowed,
-            message: response.status.and_then(|status| status.message),
+            message,
+            causes,
         }
     }
 }
@@ -77,6 +96,7 @@ impl TryFrom<&PolicyGroupMemberWithContext> for PolicyGroupMemberSettings {
                 .as_ref()
                 .map(|t| Into::<i32>::into(t) as u64),
             host_capabilities: HostCapabilities::DenyAll,
+            http_endpoints_allow_list: BTreeSet::new(),
         })
     }
 }
@@ -95,6 +115,7 @@ impl TryFrom<&PolicyGroupMember> for PolicyGroupMemberSettings {
                 .as_ref()
                 .map(|t| Into::<i32>::into(t) as u64),
             host_capabilities: HostCapabilities::DenyAll,
+            http_endpoints_allow_list: BTreeSet::new(),
         })
     }
 }
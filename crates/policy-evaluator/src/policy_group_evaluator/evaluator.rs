@@ -156,6 +156,7 @@ impl PolicyGroupEvaluator {
                                 message: Some(
                                     "mutation is not allowed inside of policy group".to_string(),
                                 ),
+                                causes: Vec::new(),
                             },
                         );
                         return Ok(false);
@@ -195,12 +196,30 @@ impl PolicyGroupEvaluator {
             if let Some(result) = evaluation_results.get(policy_id)
                 && !result.allowed
             {
+                let policy_field = format!("spec.policies.{}", policy_id);
                 let cause = admission_response::StatusCause {
-                    field: Some(format!("spec.policies.{}", policy_id)),
+                    field: Some(policy_field.clone()),
                     message: result.message.clone(),
                     ..Default::default()
                 };
                 status_causes.push(cause);
+
+                // Keep the details reported by the policy as well, scoped to
+                // the policy. Skip the ones repeating an existing cause, like
+                // a cause holding just the message of the policy.
+                for cause in &result.causes {
+                    let field = match &cause.field {
+                        Some(field) => format!("{policy_field}.{field}"),
+                        None => policy_field.clone(),
+                    };
+                    let cause = admission_response::StatusCause {
+                        field: Some(field),
+                        ..cause.clone()
+                    };
+                    if !status_causes.contains(&cause) {
+                        status_causes.push(cause);
+                    }
+                }
             }
         }
         debug!(
@@ -263,6 +282,7 @@ impl PolicyGroupEvaluator {
             ctx_aware_resources_allow_list: settings.ctx_aware_resources_allow_list.clone(),
             epoch_deadline: settings.epoch_deadline,
             host_capabilities: settings.host_capabilities.clone(),
+            http_endpoints_allow_list: settings.http_endpoints_allow_list.clone(),
         };
         let mut evaluator = evaluator_pre.rehydrate(&eval_ctx).map_err(|e| {
             EvaluationError::CannotRehydratePolicyGroupMember(policy_id.to_owned(), e)
@@ -338,6 +358,7 @@ impl PolicyGroupEvaluator {
             ctx_aware_resources_allow_list: settings.ctx_aware_resources_allow_list.clone(),
             epoch_deadline: settings.epoch_deadline,
             host_capabilities: settings.host_capabilities.clone(),
+            http_endpoints_allow_list: settings.http_endpoints_allow_list.clone(),
         };
         let mut evaluator = evaluator_pre.rehydrate(&eval_ctx).map_err(|e| {
             EvaluationError::CannotRehydratePolicyGroupMember(policy_id.to_owned(), e)
@@ -478,6 +499,7 @@ mod tests {
                     ctx_aware_resources_allow_list: Default::default(),
                     epoch_deadline: None,
                     host_capabilities: HostCapabilities::AllowAll,
+                    http_endpoints_allow_list: Default::default(),
                 },
             );
         }
@@ -557,6 +579,7 @@ mod tests {
                     ctx_aware_resources_allow_list: Default::default(),
                     epoch_deadline: None,
                     host_capabilities: HostCapabilities::AllowAll,
+                    http_endpoints_allow_list: Default::default(),
                 },
             );
         }
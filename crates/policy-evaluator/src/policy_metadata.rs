@@ -4,7 +4,10 @@ use std::{
     path::Path,
 };
 
-use k8s_openapi::api::admissionregistration::v1::NamedRuleWithOperations;
+use k8s_openapi::api::admissionregistration::{
+    v1::NamedRuleWithOperations,
+    v1alpha1::NamedRuleWithOperations as NamedRuleWithOperationsV1Alpha1,
+};
 use kubewarden_policy_sdk::metadata::ProtocolVersion;
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -142,19 +145,49 @@ impl TryFrom<&NamedRuleWithOperations> for Rule {
     type Error = &'static str;
 
     fn try_from(rule: &NamedRuleWithOperations) -> Result<Self, Self::Error> {
-        let operations = match &rule.operations {
-            Some(operations) => operations
-                .iter()
-                .map(|op| Operation::try_from(op.as_str()))
-                .collect::<Result<Vec<Operation>, Self::Error>>()?,
-            None => Vec::new(),
-        };
+        Rule::from_named_rule_parts(
+            rule.operations.as_deref(),
+            rule.api_groups.as_deref(),
+            rule.api_versions.as_deref(),
+            rule.resources.as_deref(),
+        )
+    }
+}
+
+/// `MutatingAdmissionPolicy` objects are still served under
+/// `admissionregistration.k8s.io/v1alpha1`, which has its own copy of
+/// `NamedRuleWithOperations`.
+impl TryFrom<&NamedRuleWithOperationsV1Alpha1> for Rule {
+    type Error = &'static str;
+
+    fn try_from(rule: &NamedRuleWithOperationsV1Alpha1) -> Result<Self, Self::Error> {
+        Rule::from_named_rule_parts(
+            rule.operations.as_deref(),
+            rule.api_groups.as_deref(),
+            rule.api_versions.as_deref(),
+            rule.resources.as_deref(),
+        )
+    }
+}
+
+impl Rule {
+    fn from_named_rule_parts(
+        operations: Option<&[String]>,
+        api_groups: Option<&[String]>,
+        api_versions: Option<&[String]>,
+        resources: Option<&[String]>,
+    ) -> Result<Self, &'static str> {
+        let operations = operations
+            .unwrap_or_default()
+            .iter()
+            .map(|op| Operation::try_from(op.as_str()))
+            .collect::<Result<Vec<Operation>, &'static str>>()?;
 
         Ok(Rule {
             operations,
-            api_groups: rule.api_groups.clone().unwrap_or_default(),
-            api_versions: rule.api_versions.clone().unwrap_or_default(),
-            resources: rule.resources.clone().unwrap_or_default(),
+            api_groups: api_groups.unwrap_or_default().to_vec(),
+            api_versions: api_versions.unwrap_or_default().to_vec(),
+            resources: resources.unwrap_or_default().to_vec(),
         })
     }
 }
@@ -243,6 +276,19 @@ impl Default for Metadata {
 }
 
 impl Metadata {
+    /// Whether the Rego policy gets the inventory of its context aware resources
+    /// inside of its data. Policies fetching the Kubernetes resources they need via
+    /// the `kubewarden.kubernetes.*` builtins can skip the inventory by setting the
+    /// `io.kubewarden.rego.kubernetes-inventory` annotation to `false`.
+    pub fn kubernetes_inventory(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|annotations| {
+                annotations.get(crate::constants::KUBEWARDEN_ANNOTATION_REGO_KUBERNETES_INVENTORY)
+            })
+            .is_none_or(|value| value != "false")
+    }
+
     pub fn from_path(path: &Path) -> std::result::Result<Option<Metadata>, MetadataError> {
         Metadata::from_contents(&std::fs::read(path).map_err(MetadataError::Path)?)
     }
@@ -716,4 +762,19 @@ mod tests {
 
         assert!(metadata.validate().is_err());
     }
+
+    #[test]
+    fn kubernetes_inventory_can_be_skipped() {
+        let mut metadata = Metadata::default();
+        assert!(metadata.kubernetes_inventory());
+
+        metadata.annotations = None;
+        assert!(metadata.kubernetes_inventory());
+
+        metadata.annotations = Some(BTreeMap::from([(
+            String::from("io.kubewarden.rego.kubernetes-inventory"),
+            String::from("false"),
+        )]));
+        assert!(!metadata.kubernetes_inventory());
+    }
 }
@@ -3,16 +3,20 @@ use std::fmt::Display;
 use crate::policy_evaluator::RegoPolicyExecutionMode;
 
 pub(crate) mod callback;
+pub(crate) mod gatekeeper_mutation;
 pub(crate) mod rego;
 pub(crate) mod wapc;
 pub(crate) mod wasi_cli;
 
+pub use rego::builtins::kubewarden_builtins as kubewarden_rego_builtins;
+
 pub(crate) enum Runtime {
     // This enum uses the `Box` type to avoid the need for a large enum size causing memory layout
     // problems. https://rust-lang.github.io/rust-clippy/master/index.html#large_enum_variant
     Wapc(Box<wapc::WapcStack>),
     Rego(Box<rego::Stack>),
     Cli(wasi_cli::Stack),
+    GatekeeperMutation(gatekeeper_mutation::Runtime),
 }
 
 impl Display for Runtime {
@@ -20,6 +24,7 @@ impl Display for Runtime {
         match self {
             Runtime::Cli(_) => write!(f, "wasi"),
             Runtime::Wapc(_) => write!(f, "wapc"),
+            Runtime::GatekeeperMutation(_) => write!(f, "Gatekeeper mutation"),
             Runtime::Rego(stack) => match stack.policy_execution_mode {
                 RegoPolicyExecutionMode::Opa => {
                     write!(f, "OPA")
@@ -10,9 +10,10 @@ use kubewarden_policy_sdk::host_capabilities::{
 };
 use tokio::sync::{mpsc, oneshot, oneshot::Receiver};
 use tracing::{debug, error};
+use url::Url;
 
 use crate::{
-    callback_requests::{CallbackRequest, CallbackRequestType, CallbackResponse},
+    callback_requests::{CallbackRequest, CallbackRequestType, CallbackResponse, HttpRequest},
     evaluation_context::EvaluationContext,
 };
 
@@ -24,11 +25,6 @@ fn unknown_operation(
     Err(format!("unknown operation: {}", operation).into())
 }
 
-fn unknown_namespace(namespace: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-    error!(namespace, "unknown namespace");
-    Err(format!("unknown namespace: {}", namespace).into())
-}
-
 fn host_capability_denied(
     policy_id: &str,
     capability_path: &str,
@@ -202,6 +198,45 @@ pub(crate) fn host_callback(
                     eval_ctx,
                 )
             }
+            "v1/http_request" => {
+                let request: HttpRequest = serde_json::from_slice(payload)?;
+                let url = Url::parse(&request.url)?;
+
+                if !eval_ctx.can_access_http_endpoint(&request.method, &url) {
+                    error!(
+                        policy = eval_ctx.policy_id,
+                        method = request.method,
+                        url = request.url,
+                        endpoints_allowed = ?eval_ctx.http_endpoints_allow_list,
+                        "Policy tried to access an HTTP endpoint it doesn't have access to");
+                    return Err(format!(
+                            "Policy has not been granted access to the HTTP endpoint {} {}. The violation has been reported.",
+                            request.method,
+                            request.url).into());
+                }
+
+                debug!(
+                    eval_ctx.policy_id,
+                    binding,
+                    operation,
+                    method = request.method,
+                    url = request.url,
+                    "Sending request via callback channel"
+                );
+                let (tx, rx) = oneshot::channel::<Result<CallbackResponse>>();
+                let req = CallbackRequest {
+                    request: CallbackRequestType::HttpRequest { request },
+                    response_channel: tx,
+                };
+                send_request_and_wait_for_response(
+                    &eval_ctx.policy_id,
+                    binding,
+                    operation,
+                    req,
+                    rx,
+                    eval_ctx,
+                )
+            }
             _ => unknown_operation(namespace, operation),
         },
         "crypto" => match operation {
@@ -365,7 +400,33 @@ pub(crate) fn host_callback(
             }
             _ => unknown_operation(namespace, operation),
         },
-        _ => unknown_namespace(namespace),
+        // The other namespaces are served by the host capability providers
+        // registered on the CallbackHandler, which rejects the unknown ones
+        _ => {
+            let payload: serde_json::Value = serde_json::from_slice(payload)?;
+
+            debug!(
+                eval_ctx.policy_id,
+                binding, namespace, operation, "Sending request via callback channel"
+            );
+            let (tx, rx) = oneshot::channel::<Result<CallbackResponse>>();
+            let req = CallbackRequest {
+                request: CallbackRequestType::HostCapabilityProvider {
+                    namespace: namespace.to_owned(),
+                    operation: operation.to_owned(),
+                    payload,
+                },
+                response_channel: tx,
+            };
+            send_request_and_wait_for_response(
+                &eval_ctx.policy_id,
+                binding,
+                operation,
+                req,
+                rx,
+                eval_ctx,
+            )
+        }
     }
 }
 
@@ -433,6 +494,7 @@ mod tests {
 
     use crate::evaluation_context::EvaluationContext;
     use crate::host_capabilities::HostCapabilities;
+    use crate::http_endpoint::HttpEndpoint;
 
     use super::host_callback;
 
@@ -443,6 +505,7 @@ mod tests {
             ctx_aware_resources_allow_list: BTreeSet::new(),
             epoch_deadline: None,
             host_capabilities: HostCapabilities::DenyAll,
+            http_endpoints_allow_list: Default::default(),
         })
     }
 
@@ -453,6 +516,7 @@ mod tests {
             ctx_aware_resources_allow_list: BTreeSet::new(),
             epoch_deadline: None,
             host_capabilities: HostCapabilities::AllowAll,
+            http_endpoints_allow_list: Default::default(),
         })
     }
 
@@ -463,6 +527,7 @@ mod tests {
     #[case("oci", "v1/oci_manifest")]
     #[case("oci", "v1/oci_manifest_config")]
     #[case("net", "v1/dns_lookup_host")]
+    #[case("net", "v1/http_request")]
     #[case("crypto", "v1/is_certificate_trusted")]
     #[case("kubernetes", "list_resources_by_namespace")]
     #[case("kubernetes", "list_resources_all")]
@@ -504,6 +569,13 @@ mod tests {
     #[case("oci", "v1/oci_manifest_config", br#""ghcr.io/example/image:latest""#.as_slice())]
     // net: payload is a JSON-encoded hostname string
     #[case("net", "v1/dns_lookup_host", br#""example.com""#.as_slice())]
+    // net: http_request also has an allowed endpoints check after the capability gate;
+    // with an empty allow-list the function returns an endpoint denial.
+    #[case(
+        "net",
+        "v1/http_request",
+        br#"{"method":"GET","url":"https://svc.corp/api"}"#.as_slice()
+    )]
     // crypto: minimal CertificateVerificationRequest
     #[case(
         "crypto",
@@ -553,4 +625,41 @@ mod tests {
             "namespace={namespace}, operation={operation}: should not be a host-capability denial, got: {msg}"
         );
     }
+
+    #[rstest]
+    #[case::allowed_endpoint("GET", "https://svc.corp/api/quota", false)]
+    #[case::method_not_allowed("POST", "https://svc.corp/api/quota", true)]
+    #[case::url_not_allowed("GET", "https://svc.corp/admin", true)]
+    fn http_request_checks_allowed_endpoints(
+        #[case] method: &str,
+        #[case] url: &str,
+        #[case] denied: bool,
+    ) {
+        let ctx = Arc::new(EvaluationContext {
+            policy_id: "test-policy".to_owned(),
+            host_capabilities: HostCapabilities::AllowAll,
+            http_endpoints_allow_list: BTreeSet::from([HttpEndpoint {
+                url_prefix: "https://svc.corp/api".parse().unwrap(),
+                methods: BTreeSet::from(["GET".to_owned()]),
+            }]),
+            ..Default::default()
+        });
+        let payload = serde_json::json!({"method": method, "url": url}).to_string();
+
+        let result = host_callback(
+            "kubewarden",
+            "net",
+            "v1/http_request",
+            payload.as_bytes(),
+            &ctx,
+        );
+
+        // Allowed requests fail later on, because the callback channel is None
+        let err = result.expect_err("expected Err");
+        assert_eq!(
+            err.to_string().contains("has not been granted access"),
+            denied,
+            "{method} {url}: unexpected error: {err}"
+        );
+    }
 }
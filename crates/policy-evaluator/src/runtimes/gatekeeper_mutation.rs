@@ -0,0 +1,89 @@
+use kubewarden_policy_sdk::settings::SettingsValidationResponse;
+use tracing::error;
+
+use crate::admission_response::AdmissionResponse;
+use crate::gatekeeper_mutation::{self, GatekeeperMutationSettings};
+use crate::policy_evaluator::{PolicySettings, ValidateRequest};
+
+/// Applies the Gatekeeper mutators found inside of the policy settings.
+///
+/// The mutators are implemented natively, no Wasm module is involved.
+pub(crate) struct Runtime;
+
+impl Runtime {
+    pub fn validate(
+        &self,
+        settings: &PolicySettings,
+        request: &ValidateRequest,
+    ) -> AdmissionResponse {
+        match GatekeeperMutationSettings::try_from(settings) {
+            Ok(settings) => gatekeeper_mutation::mutate(request, &settings.mutators),
+            Err(e) => {
+                error!(error = e.to_string().as_str(), "cannot parse the mutators");
+                AdmissionResponse::reject_internal_server_error(
+                    request.uid().to_string(),
+                    format!("cannot parse the mutators: {e}"),
+                )
+            }
+        }
+    }
+
+    pub fn validate_settings(&self, settings: String) -> SettingsValidationResponse {
+        let validation = serde_json::from_str::<GatekeeperMutationSettings>(&settings)
+            .map_err(|e| format!("cannot parse the mutators: {e}"))
+            .and_then(|settings| settings.validate().map_err(|e| e.to_string()));
+
+        match validation {
+            Ok(()) => SettingsValidationResponse {
+                valid: true,
+                message: None,
+            },
+            Err(message) => SettingsValidationResponse {
+                valid: false,
+                message: Some(message),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn validate_settings() {
+        let valid = json!({
+            "mutators": [{
+                "kind": "AssignMetadata",
+                "metadata": {"name": "owner"},
+                "spec": {
+                    "location": "metadata.labels.owner",
+                    "parameters": {"assign": {"value": "team-a"}}
+                }
+            }]
+        });
+        let response = Runtime.validate_settings(valid.to_string());
+        assert!(response.valid);
+
+        let invalid = json!({
+            "mutators": [{
+                "kind": "Assign",
+                "metadata": {"name": "dns"},
+                "spec": {
+                    "location": "spec.dnsPolicy",
+                    "parameters": {"assign": {"value": "None"}}
+                }
+            }]
+        });
+        let response = Runtime.validate_settings(invalid.to_string());
+        assert!(!response.valid);
+        assert_eq!(
+            response.message.unwrap(),
+            r#"Assign "dns": applyTo must be set"#
+        );
+
+        let response = Runtime.validate_settings(json!({"mutators": "foo"}).to_string());
+        assert!(!response.valid);
+    }
+}
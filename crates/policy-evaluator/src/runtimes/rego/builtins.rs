@@ -0,0 +1,382 @@
+//! Kubewarden builtins provided to Rego policies.
+//!
+//! They give Rego policies access to the same host capabilities waPC policies
+//! reach via `host_call`. Each builtin is routed through the callback channel
+//! and is subject to the `HostCapabilities` allow list of the policy.
+//!
+//! The `kubewarden.kubernetes.*` builtins fetch Kubernetes resources on
+//! demand, from the cache of the host. Policies using them still get the
+//! inventory of their `contextAwareResources` inside of their data, unless
+//! their metadata sets the `io.kubewarden.rego.kubernetes-inventory`
+//! annotation to `false`.
+//!
+//! OPA does not know about these builtins: policies using them must be built
+//! with a capabilities file declaring them, see `opa build --capabilities`.
+
+use std::sync::Arc;
+
+use burrego::{CustomBuiltin, CustomBuiltins, errors::BurregoError};
+use serde_json::{Value, json};
+
+use crate::{evaluation_context::EvaluationContext, runtimes::callback::host_callback};
+
+/// A Kubewarden builtin, backed by a host capability
+struct KubewardenBuiltin {
+    /// name of the builtin, as used by the Rego policy
+    name: &'static str,
+    /// namespace of the host capability
+    namespace: &'static str,
+    /// operation of the host capability
+    operation: &'static str,
+    /// builds the payload of the host capability from the builtin arguments
+    payload: fn(&[Value]) -> Result<Value, String>,
+}
+
+const KUBEWARDEN_BUILTINS: &[KubewardenBuiltin] = &[
+    KubewardenBuiltin {
+        name: "kubewarden.oci.manifest_digest",
+        namespace: "oci",
+        operation: "v1/manifest_digest",
+        payload: string_payload,
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.oci.manifest",
+        namespace: "oci",
+        operation: "v1/oci_manifest",
+        payload: string_payload,
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.oci.manifest_config",
+        namespace: "oci",
+        operation: "v1/oci_manifest_config",
+        payload: string_payload,
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.sigstore.verify_pub_key",
+        namespace: "oci",
+        operation: "v2/verify",
+        payload: |args| sigstore_payload("SigstorePubKeyVerify", &["pub_keys"], args),
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.sigstore.verify_keyless_exact_match",
+        namespace: "oci",
+        operation: "v2/verify",
+        payload: |args| sigstore_payload("SigstoreKeylessVerify", &["keyless"], args),
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.sigstore.verify_keyless_prefix_match",
+        namespace: "oci",
+        operation: "v2/verify",
+        payload: |args| sigstore_payload("SigstoreKeylessPrefixVerify", &["keyless_prefix"], args),
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.sigstore.verify_github_actions",
+        namespace: "oci",
+        operation: "v2/verify",
+        payload: |args| sigstore_payload("SigstoreGithubActionsVerify", &["owner", "repo"], args),
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.net.lookup_host",
+        namespace: "net",
+        operation: "v1/dns_lookup_host",
+        payload: string_payload,
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.crypto.is_certificate_trusted",
+        namespace: "crypto",
+        operation: "v1/is_certificate_trusted",
+        payload: object_payload,
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.kubernetes.list_resources_by_namespace",
+        namespace: "kubernetes",
+        operation: "list_resources_by_namespace",
+        payload: |args| {
+            kubernetes_payload(
+                args,
+                &["api_version", "kind", "namespace"],
+                json!({"label_selector": null, "field_selector": null, "field_masks": null}),
+            )
+        },
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.kubernetes.list_resources_all",
+        namespace: "kubernetes",
+        operation: "list_resources_all",
+        payload: |args| {
+            kubernetes_payload(
+                args,
+                &["api_version", "kind"],
+                json!({"label_selector": null, "field_selector": null, "field_masks": null}),
+            )
+        },
+    },
+    KubewardenBuiltin {
+        name: "kubewarden.kubernetes.get_resource",
+        namespace: "kubernetes",
+        operation: "get_resource",
+        payload: |args| {
+            kubernetes_payload(
+                args,
+                &["api_version", "kind", "name"],
+                json!({"namespace": null, "disable_cache": false}),
+            )
+        },
+    },
+];
+
+/// Creates the Kubewarden builtins to be registered inside of a burrego
+/// evaluator. The host capabilities are invoked on behalf of the policy
+/// described by the given evaluation context.
+pub fn kubewarden_builtins(eval_ctx: Arc<EvaluationContext>) -> CustomBuiltins {
+    KUBEWARDEN_BUILTINS
+        .iter()
+        .map(|builtin| {
+            let eval_ctx = eval_ctx.clone();
+            let custom_builtin: CustomBuiltin =
+                Arc::new(move |args: &[Value]| builtin.invoke(args, &eval_ctx));
+            (builtin.name.to_string(), custom_builtin)
+        })
+        .collect()
+}
+
+impl KubewardenBuiltin {
+    fn invoke(
+        &self,
+        args: &[Value],
+        eval_ctx: &Arc<EvaluationContext>,
+    ) -> burrego::errors::Result<Value> {
+        let payload = (self.payload)(args)
+            .and_then(|payload| serde_json::to_vec(&payload).map_err(|e| e.to_string()))
+            .map_err(|message| self.error(message))?;
+
+        let response = host_callback(
+            "kubewarden",
+            self.namespace,
+            self.operation,
+            &payload,
+            eval_ctx,
+        )
+        .map_err(|e| self.error(e.to_string()))?;
+
+        serde_json::from_slice(&response)
+            .map_err(|e| self.error(format!("cannot decode host capability response: {e}")))
+    }
+
+    fn error(&self, message: String) -> BurregoError {
+        BurregoError::BuiltinError {
+            name: self.name.to_string(),
+            message,
+        }
+    }
+}
+
+/// The payload is the only argument of the builtin, which must be a string
+fn string_payload(args: &[Value]) -> Result<Value, String> {
+    match args {
+        [value @ Value::String(_)] => Ok(value.clone()),
+        [_] => Err("1st parameter is not a string".to_string()),
+        _ => Err("wrong number of arguments".to_string()),
+    }
+}
+
+/// The payload is the only argument of the builtin, which must be an object
+fn object_payload(args: &[Value]) -> Result<Value, String> {
+    match args {
+        [value @ Value::Object(_)] => Ok(value.clone()),
+        [_] => Err("1st parameter is not an object".to_string()),
+        _ => Err("wrong number of arguments".to_string()),
+    }
+}
+
+/// The only argument of the builtin is an object describing the Kubernetes
+/// request. The given fields must be strings, the optional fields not set
+/// by the policy are taken from `defaults`
+fn kubernetes_payload(args: &[Value], required: &[&str], defaults: Value) -> Result<Value, String> {
+    let mut payload = defaults;
+    for (key, value) in object_payload(args)?
+        .as_object()
+        .expect("the payload is an object")
+    {
+        payload[key] = value.clone();
+    }
+
+    if let Some(field) = required
+        .iter()
+        .find(|field| !payload.get(**field).is_some_and(Value::is_string))
+    {
+        return Err(format!("the {field} field is not a string"));
+    }
+
+    Ok(payload)
+}
+
+/// Builds a `SigstoreVerificationInputV2` of the given type. The arguments
+/// are the image, the given fields and the annotations, in this order
+fn sigstore_payload(
+    verification_type: &str,
+    fields: &[&str],
+    args: &[Value],
+) -> Result<Value, String> {
+    if args.len() != fields.len() + 2 {
+        return Err("wrong number of arguments".to_string());
+    }
+
+    let image = &args[0];
+    if !image.is_string() {
+        return Err("1st parameter is not a string".to_string());
+    }
+    let annotations = &args[args.len() - 1];
+    if !annotations.is_null() && !annotations.is_object() {
+        return Err("the annotations are neither an object nor null".to_string());
+    }
+
+    let mut payload = json!({
+        "type": verification_type,
+        "image": image,
+        "annotations": annotations,
+    });
+    for (field, value) in fields.iter().zip(&args[1..]) {
+        payload[*field] = value.clone();
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    use crate::host_capabilities::HostCapabilities;
+
+    fn eval_ctx(host_capabilities: HostCapabilities) -> Arc<EvaluationContext> {
+        Arc::new(EvaluationContext {
+            policy_id: "rego-policy".to_owned(),
+            host_capabilities,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn sigstore_payload_is_a_verification_input() {
+        let payload = sigstore_payload(
+            "SigstoreGithubActionsVerify",
+            &["owner", "repo"],
+            &[
+                json!("ghcr.io/kubewarden/policy:v1"),
+                json!("kubewarden"),
+                Value::Null,
+                json!({"env": "prod"}),
+            ],
+        )
+        .expect("valid payload");
+
+        assert_eq!(
+            payload,
+            json!({
+                "type": "SigstoreGithubActionsVerify",
+                "image": "ghcr.io/kubewarden/policy:v1",
+                "owner": "kubewarden",
+                "repo": null,
+                "annotations": {"env": "prod"},
+            })
+        );
+
+        assert!(
+            sigstore_payload("SigstorePubKeyVerify", &["pub_keys"], &[json!("image")]).is_err()
+        );
+        assert!(
+            sigstore_payload(
+                "SigstorePubKeyVerify",
+                &["pub_keys"],
+                &[json!("image"), json!([]), json!("annotations")]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn kubernetes_payload_has_defaults() {
+        let payload = kubernetes_payload(
+            &[json!({"api_version": "v1", "kind": "Pod", "label_selector": "app=nginx"})],
+            &["api_version", "kind"],
+            json!({"label_selector": null, "field_selector": null}),
+        )
+        .expect("valid payload");
+
+        assert_eq!(
+            payload,
+            json!({
+                "api_version": "v1",
+                "kind": "Pod",
+                "label_selector": "app=nginx",
+                "field_selector": null,
+            })
+        );
+
+        let err = kubernetes_payload(
+            &[json!({"api_version": "v1"})],
+            &["api_version", "kind"],
+            json!({}),
+        )
+        .expect_err("kind is missing");
+        assert_eq!(err, "the kind field is not a string");
+    }
+
+    #[rstest]
+    #[case::oci("kubewarden.oci.manifest_digest", vec![json!("ghcr.io/kubewarden/policy:v1")])]
+    #[case::sigstore(
+        "kubewarden.sigstore.verify_pub_key",
+        vec![json!("ghcr.io/kubewarden/policy:v1"), json!(["key"]), Value::Null]
+    )]
+    #[case::net("kubewarden.net.lookup_host", vec![json!("kubewarden.io")])]
+    fn builtins_respect_host_capabilities(#[case] name: &str, #[case] args: Vec<Value>) {
+        let builtins = kubewarden_builtins(eval_ctx(HostCapabilities::DenyAll));
+        let err = builtins[name](&args).expect_err("the host capability is denied");
+        assert!(
+            err.to_string().contains("has not been granted access"),
+            "{name}: unexpected error: {err}"
+        );
+
+        // the capability is granted, the invocation fails later on because
+        // the callback channel is not set
+        let builtins = kubewarden_builtins(eval_ctx(HostCapabilities::AllowAll));
+        let err = builtins[name](&args).expect_err("the callback channel is not set");
+        assert!(
+            !err.to_string().contains("has not been granted access"),
+            "{name}: unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn kubernetes_builtins_respect_context_aware_resources() {
+        let builtins = kubewarden_builtins(eval_ctx(HostCapabilities::AllowAll));
+        let err = builtins["kubewarden.kubernetes.get_resource"](&[json!({
+            "api_version": "v1",
+            "kind": "Pod",
+            "name": "nginx",
+            "namespace": "default",
+        })])
+        .expect_err("the policy cannot access Pods");
+        assert!(
+            err.to_string()
+                .contains("has not been granted access to Kubernetes v1/Pod resources"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn builtins_validate_their_arguments() {
+        let builtins = kubewarden_builtins(eval_ctx(HostCapabilities::AllowAll));
+
+        let err = builtins["kubewarden.oci.manifest_digest"](&[json!(1)])
+            .expect_err("the image is not a string");
+        assert!(err.to_string().contains("not a string"), "{err}");
+
+        let err = builtins["kubewarden.crypto.is_certificate_trusted"](&[json!("cert")])
+            .expect_err("the request is not an object");
+        assert!(err.to_string().contains("not an object"), "{err}");
+    }
+}
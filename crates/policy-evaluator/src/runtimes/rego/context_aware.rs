@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 
 use kube::api::ObjectList;
 use tokio::sync::{mpsc, oneshot};
@@ -8,39 +8,24 @@ use crate::{
     policy_metadata::ContextAwareResource,
     runtimes::rego::{
         errors::{RegoRuntimeError, Result},
-        opa_inventory::OpaInventory,
+        inventory_cache::CachedInventory,
     },
 };
 
-#[derive(serde::Serialize)]
-#[serde(untagged)]
+/// The serialized inventories are shared with the other evaluations
+/// accessing the same set of resources
 pub(crate) enum KubernetesContext {
     Empty,
-    Opa(OpaInventory),
-    Gatekeeper(Vec<u8>),
+    Opa(Arc<CachedInventory>),
+    Gatekeeper(Arc<CachedInventory>),
 }
 
-/// Uses the callback channel to get all the Kubernetes resources defined inside of
-/// the cluster whose type is mentioned inside of `allowed_resources`.
+/// Uses the callback channel to get all the Kubernetes resources of the given type
+/// defined inside of the cluster.
 ///
 /// The resources are returned based on the actual RBAC privileges of the client
 /// used by the runtime.
-pub(crate) fn get_allowed_resources(
-    callback_channel: &mpsc::Sender<CallbackRequest>,
-    allowed_resources: &BTreeSet<ContextAwareResource>,
-) -> Result<BTreeMap<ContextAwareResource, ObjectList<kube::core::DynamicObject>>> {
-    let mut kube_resources: BTreeMap<ContextAwareResource, ObjectList<kube::core::DynamicObject>> =
-        BTreeMap::new();
-
-    for resource in allowed_resources {
-        let resource_list = get_all_resources_by_type(callback_channel, resource)?;
-        kube_resources.insert(resource.to_owned(), resource_list);
-    }
-
-    Ok(kube_resources)
-}
-
-fn get_all_resources_by_type(
+pub(crate) fn get_all_resources_by_type(
     callback_channel: &mpsc::Sender<CallbackRequest>,
     resource_type: &ContextAwareResource,
 ) -> Result<ObjectList<kube::core::DynamicObject>> {
@@ -57,25 +42,10 @@ fn get_all_resources_by_type(
         .map_err(RegoRuntimeError::CallbackConvertList)
 }
 
-/// For each allowed resource, check if the "list all resources" result changed since the given instant
-pub(crate) fn have_allowed_resources_changed_since_instant(
-    callback_channel: &mpsc::Sender<CallbackRequest>,
-    allowed_resources: &BTreeSet<ContextAwareResource>,
-    since: tokio::time::Instant,
-) -> Result<bool> {
-    for resource in allowed_resources {
-        if has_resource_changed_since(callback_channel, resource, since)? {
-            return Ok(true);
-        }
-    }
-
-    Ok(false)
-}
-
 /// Check if the "list all resources" result changed since the given instant
 /// Note: this function doesn't take label_selector, field_selector and field_masks into account because
-/// it's used only by gatekeeper policies, which don't use these selectors.
-fn has_resource_changed_since(
+/// it's used only by the inventories of the Rego policies, which don't use these selectors.
+pub(crate) fn has_resource_changed_since(
     callback_channel: &mpsc::Sender<CallbackRequest>,
     resource_type: &ContextAwareResource,
     since: tokio::time::Instant,
@@ -93,29 +63,21 @@ fn has_resource_changed_since(
     serde_json::from_slice::<bool>(&response.payload).map_err(RegoRuntimeError::CallbackConvertBool)
 }
 
-/// Creates a map that has ContextAwareResource as key, and its plural name as value.
-/// For example, the key for {`apps/v1`, `Deployment`} will have `deployments` as value.
-/// The map is built by making request via the given callback channel.
-pub(crate) fn get_plural_names(
+/// Returns the plural name of the given resource.
+/// For example, {`apps/v1`, `Deployment`} has `deployments` as plural name.
+/// The name is obtained by making a request via the given callback channel.
+pub(crate) fn get_plural_name(
     callback_channel: &mpsc::Sender<CallbackRequest>,
-    allowed_resources: &BTreeSet<ContextAwareResource>,
-) -> Result<BTreeMap<ContextAwareResource, String>> {
-    let mut plural_names_by_resource: BTreeMap<ContextAwareResource, String> = BTreeMap::new();
-
-    for resource in allowed_resources {
-        let req_type = CallbackRequestType::KubernetesGetResourcePluralName {
-            api_version: resource.api_version.to_owned(),
-            kind: resource.kind.to_owned(),
-        };
-
-        let response = make_request_via_callback_channel(req_type, callback_channel)?;
-        let plural_name = serde_json::from_slice::<String>(&response.payload)
-            .map_err(RegoRuntimeError::CallbackGetPluralName)?;
-
-        plural_names_by_resource.insert(resource.to_owned(), plural_name);
-    }
+    resource: &ContextAwareResource,
+) -> Result<String> {
+    let req_type = CallbackRequestType::KubernetesGetResourcePluralName {
+        api_version: resource.api_version.to_owned(),
+        kind: resource.kind.to_owned(),
+    };
 
-    Ok(plural_names_by_resource)
+    let response = make_request_via_callback_channel(req_type, callback_channel)?;
+    serde_json::from_slice::<String>(&response.payload)
+        .map_err(RegoRuntimeError::CallbackGetPluralName)
 }
 
 /// Internal helper function that sends a request over the callback channel and returns the
@@ -145,7 +107,6 @@ pub(crate) mod tests {
     use anyhow::{Result, anyhow};
     use assert_json_diff::assert_json_eq;
     use rstest::rstest;
-    use std::collections::HashMap;
     use std::path::Path;
 
     pub fn dynamic_object_from_fixture(
@@ -237,13 +198,6 @@ pub(crate) mod tests {
             kind: "Service".to_string(),
         };
         let plural_name = "services";
-
-        let mut resources: BTreeSet<ContextAwareResource> = BTreeSet::new();
-        resources.insert(resource.clone());
-
-        let mut expected_names: BTreeMap<ContextAwareResource, String> = BTreeMap::new();
-        expected_names.insert(resource.clone(), plural_name.to_string());
-
         let expected_resource = resource.clone();
 
         tokio::spawn(async move {
@@ -269,51 +223,32 @@ pub(crate) mod tests {
         });
 
         tokio::task::spawn_blocking(move || {
-            let actual = get_plural_names(&callback_tx, &resources).unwrap();
-            assert_eq!(actual, expected_names);
+            let actual = get_plural_name(&callback_tx, &resource).unwrap();
+            assert_eq!(actual, plural_name);
         })
         .await
         .unwrap();
     }
+
     #[rstest]
-    #[case(
-        HashMap::<ContextAwareResource, bool>::from([(ContextAwareResource{api_version: "v1".to_string(), kind: "Service".to_string()}, true)]),
-        true,
-    )]
-    #[case(
-        HashMap::<ContextAwareResource, bool>::from([(ContextAwareResource{api_version: "v1".to_string(), kind: "Service".to_string()}, false)]),
-        false,
-    )]
-    #[case(
-        HashMap::<ContextAwareResource, bool>::from([
-            (ContextAwareResource{api_version: "v1".to_string(), kind: "Service".to_string()}, true),
-            (ContextAwareResource{api_version: "v1".to_string(), kind: "Pod".to_string()}, false),
-        ]),
-        true,
-    )]
-    #[case(
-        HashMap::<ContextAwareResource, bool>::from([
-            (ContextAwareResource{api_version: "v1".to_string(), kind: "Service".to_string()}, false),
-            (ContextAwareResource{api_version: "v1".to_string(), kind: "Pod".to_string()}, false),
-        ]),
-        false,
-    )]
+    #[case(true)]
+    #[case(false)]
     #[tokio::test(flavor = "multi_thread")]
-    async fn have_resources_changed_since_when_reflector_does_not_exist_yet(
-        #[case] resources_with_change_status: HashMap<ContextAwareResource, bool>,
-        #[case] expected: bool,
-    ) {
+    async fn has_resource_changed_since_instant(#[case] changed: bool) {
         let (callback_tx, mut callback_rx) = mpsc::channel::<CallbackRequest>(10);
+        let resource = ContextAwareResource {
+            api_version: "v1".to_string(),
+            kind: "Service".to_string(),
+        };
         let since = tokio::time::Instant::now();
-        let expected_resources_with_change_status = resources_with_change_status.clone();
+        let expected_resource = resource.clone();
 
         tokio::spawn(async move {
-            loop {
-                let req = match callback_rx.recv().await {
-                    Some(r) => r,
-                    None => return,
-                };
-                let changed = match req.request {
+            let req = match callback_rx.recv().await {
+                Some(r) => r,
+                None => return,
+            };
+            match req.request {
                 CallbackRequestType::HasKubernetesListResourceAllResultChangedSinceInstant {
                     api_version,
                     kind,
@@ -322,36 +257,26 @@ pub(crate) mod tests {
                     since: _,
                     field_masks: _,
                 } => {
-                    let resource = ContextAwareResource {
-                        api_version: api_version.clone(),
-                        kind: kind.clone(),
-                    };
+                    assert_eq!(api_version, expected_resource.api_version);
+                    assert_eq!(kind, expected_resource.kind);
                     assert!(label_selector.is_none());
                     assert!(field_selector.is_none());
-
-                    expected_resources_with_change_status
-                        .get(&resource)
-                        .expect("cannot find resource")
                 }
                 _ => {
                     panic!("not the expected request type");
                 }
             };
 
-                let callback_response = CallbackResponse {
-                    payload: serde_json::to_vec(&changed).unwrap(),
-                };
+            let callback_response = CallbackResponse {
+                payload: serde_json::to_vec(&changed).unwrap(),
+            };
 
-                req.response_channel.send(Ok(callback_response)).unwrap();
-            }
+            req.response_channel.send(Ok(callback_response)).unwrap();
         });
 
         tokio::task::spawn_blocking(move || {
-            let resources = resources_with_change_status.keys().cloned().collect();
-            let actual =
-                have_allowed_resources_changed_since_instant(&callback_tx, &resources, since)
-                    .unwrap();
-            assert_json_eq!(expected, actual);
+            let actual = has_resource_changed_since(&callback_tx, &resource, since).unwrap();
+            assert_eq!(changed, actual);
         })
         .await
         .unwrap();
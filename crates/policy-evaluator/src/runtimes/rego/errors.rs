@@ -49,6 +49,9 @@ pub enum RegoRuntimeError {
     #[error("cannot find plural name for resource {0}")]
     OpaInventoryMissingPluralName(String),
 
+    #[error("cannot convert OPA inventory to JSON: {0}")]
+    OpaInventorySerializationError(#[source] serde_json::Error),
+
     #[error("invalid response from policy")]
     InvalidResponse,
 
@@ -149,8 +149,13 @@ pub(crate) struct GatekeeperInventory {
 impl GatekeeperInventory {
     /// Creates a GatekeeperInventory by querying a Kubernetes cluster
     /// for all the resources specified
-    pub(crate) fn new(
-        kube_resources: &BTreeMap<ContextAwareResource, ObjectList<kube::core::DynamicObject>>,
+    pub(crate) fn new<'a>(
+        kube_resources: impl IntoIterator<
+            Item = (
+                &'a ContextAwareResource,
+                &'a ObjectList<kube::core::DynamicObject>,
+            ),
+        >,
     ) -> Result<Self> {
         let mut inventory = GatekeeperInventory::default();
 
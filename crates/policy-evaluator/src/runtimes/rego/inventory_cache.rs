@@ -0,0 +1,550 @@
+use kube::api::ObjectList;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, RwLock},
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    callback_requests::CallbackRequest,
+    policy_metadata::ContextAwareResource,
+    runtimes::rego::{
+        context_aware,
+        errors::{RegoRuntimeError, Result},
+        gatekeeper_inventory::GatekeeperInventory,
+        opa_inventory::OpaInventory,
+    },
+};
+
+lazy_static! {
+    /// Global cache for the inventories of the Rego policies
+    pub(crate) static ref INVENTORY_CACHE: InventoryCache = InventoryCache::new();
+}
+
+/// A serialized inventory. Building and serializing the inventory can
+/// be quite expensive when many Kubernetes resources are involved. This cache
+/// is used to avoid recomputing the inventory on every request.
+#[derive(Clone)]
+pub(crate) struct CachedInventory {
+    /// The serialized inventory
+    pub data: Vec<u8>,
+    /// The instant when the newest resources of the inventory were fetched.
+    /// This is used to invalidate the cache
+    pub cache_time: Instant,
+}
+
+/// All the Kubernetes resources of a given type
+struct CachedResources {
+    /// The resources, as returned by the Kubernetes API server
+    objects: ObjectList<kube::core::DynamicObject>,
+    /// The instant when the resources were fetched. This is used to invalidate the cache
+    cache_time: Instant,
+}
+
+/// This defines how Gatekeeper policy expects the `input` attribute to be structured.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct GatekeeperInput {
+    /// The actual inventory
+    inventory: GatekeeperInventory,
+}
+
+/// The resources an inventory is built from, shared with the other inventories
+type SharedResources<'a> = BTreeMap<&'a ContextAwareResource, Arc<CachedResources>>;
+
+/// Serialized inventories, by the set of resources they give access to
+type Inventories = RwLock<HashMap<BTreeSet<ContextAwareResource>, Arc<CachedInventory>>>;
+
+/// Hold all the inventories for the Rego runtime
+///
+/// The Kubernetes resources are fetched once per type and are shared by all the
+/// inventories giving access to them. Two policies accessing an overlapping set of
+/// resources fetch the resources they have in common only once.
+///
+/// The serialized inventories are stored inside of dictionaries that have the list of
+/// resources the inventory is allowed to access as key. Two different policies that access
+/// the same set of resources share the same serialized inventory, which is built again
+/// only when some of its resources changed.
+pub(crate) struct InventoryCache {
+    // Note: the Arc is used to make some `clone` invocation faster. The `clone` operations
+    // are required because the dictionaries are located inside of a RwLock
+    resources: RwLock<HashMap<ContextAwareResource, Arc<CachedResources>>>,
+    plural_names: RwLock<HashMap<ContextAwareResource, String>>,
+    gatekeeper_inventories: Inventories,
+    opa_inventories: Inventories,
+}
+
+impl InventoryCache {
+    pub fn new() -> Self {
+        Self {
+            resources: RwLock::new(HashMap::new()),
+            plural_names: RwLock::new(HashMap::new()),
+            gatekeeper_inventories: RwLock::new(HashMap::new()),
+            opa_inventories: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// This function returns the serialized Gatekeeper inventory for the given set of resources.
+    /// The inventory is shared with the cache, hence it's not copied on every request.
+    pub fn get_gatekeeper_inventory(
+        &self,
+        callback_channel: &mpsc::Sender<CallbackRequest>,
+        ctx_aware_resources: &BTreeSet<ContextAwareResource>,
+    ) -> Result<Arc<CachedInventory>> {
+        self.get_inventory(
+            &self.gatekeeper_inventories,
+            callback_channel,
+            ctx_aware_resources,
+            |resources| {
+                let input = GatekeeperInput {
+                    inventory: GatekeeperInventory::new(
+                        resources.iter().map(|(r, cached)| (*r, &cached.objects)),
+                    )?,
+                };
+                serde_json::to_vec(&input)
+                    .map_err(RegoRuntimeError::GatekeeperInventorySerializationError)
+            },
+        )
+    }
+
+    /// This function returns the serialized OPA inventory for the given set of resources.
+    /// The inventory is shared with the cache, hence it's not copied on every request.
+    pub fn get_opa_inventory(
+        &self,
+        callback_channel: &mpsc::Sender<CallbackRequest>,
+        ctx_aware_resources: &BTreeSet<ContextAwareResource>,
+    ) -> Result<Arc<CachedInventory>> {
+        let plural_names = ctx_aware_resources
+            .iter()
+            .map(|resource| {
+                let plural_name = self.get_plural_name(callback_channel, resource)?;
+                Ok((resource.to_owned(), plural_name))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        self.get_inventory(
+            &self.opa_inventories,
+            callback_channel,
+            ctx_aware_resources,
+            |resources| {
+                let inventory = OpaInventory::new(
+                    resources.iter().map(|(r, cached)| (*r, &cached.objects)),
+                    &plural_names,
+                )?;
+                serde_json::to_vec(&inventory)
+                    .map_err(RegoRuntimeError::OpaInventorySerializationError)
+            },
+        )
+    }
+
+    /// Return the inventory for the given set of resources. The inventory is built
+    /// and serialized by `build` only if it's not already present inside of `inventories`,
+    /// or if some of its resources changed since the time the inventory was built.
+    fn get_inventory<'a>(
+        &self,
+        inventories: &Inventories,
+        callback_channel: &mpsc::Sender<CallbackRequest>,
+        ctx_aware_resources: &'a BTreeSet<ContextAwareResource>,
+        build: impl FnOnce(&SharedResources<'a>) -> Result<Vec<u8>>,
+    ) -> Result<Arc<CachedInventory>> {
+        let resources = ctx_aware_resources
+            .iter()
+            .map(|resource| Ok((resource, self.get_resources(callback_channel, resource)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let cache_time = resources
+            .values()
+            .map(|cached| cached.cache_time)
+            .max()
+            .unwrap_or_else(Instant::now);
+
+        let cached_inventory = inventories
+            .read()
+            .unwrap()
+            .get(ctx_aware_resources)
+            .cloned();
+        if let Some(cached_inventory) = cached_inventory
+            && cached_inventory.cache_time >= cache_time
+        {
+            return Ok(cached_inventory);
+        }
+
+        let cached_inventory = Arc::new(CachedInventory {
+            data: build(&resources)?,
+            cache_time,
+        });
+        inventories
+            .write()
+            .unwrap()
+            .insert(ctx_aware_resources.to_owned(), cached_inventory.clone());
+        Ok(cached_inventory)
+    }
+
+    /// Return all the resources of the given type. The resources are fetched again
+    /// if they changed since the last time they were fetched.
+    fn get_resources(
+        &self,
+        callback_channel: &mpsc::Sender<CallbackRequest>,
+        resource: &ContextAwareResource,
+    ) -> Result<Arc<CachedResources>> {
+        let cached_resources = self.resources.read().unwrap().get(resource).cloned();
+        if let Some(cached_resources) = cached_resources
+            && !context_aware::has_resource_changed_since(
+                callback_channel,
+                resource,
+                cached_resources.cache_time,
+            )?
+        {
+            return Ok(cached_resources);
+        }
+
+        let cache_time = Instant::now();
+        let cached_resources = Arc::new(CachedResources {
+            objects: context_aware::get_all_resources_by_type(callback_channel, resource)?,
+            cache_time,
+        });
+        self.resources
+            .write()
+            .unwrap()
+            .insert(resource.to_owned(), cached_resources.clone());
+        Ok(cached_resources)
+    }
+
+    /// Return the plural name of the given resource, which never changes
+    fn get_plural_name(
+        &self,
+        callback_channel: &mpsc::Sender<CallbackRequest>,
+        resource: &ContextAwareResource,
+    ) -> Result<String> {
+        if let Some(plural_name) = self.plural_names.read().unwrap().get(resource) {
+            return Ok(plural_name.to_owned());
+        }
+
+        let plural_name = context_aware::get_plural_name(callback_channel, resource)?;
+        self.plural_names
+            .write()
+            .unwrap()
+            .insert(resource.to_owned(), plural_name.clone());
+        Ok(plural_name)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::callback_requests::{CallbackRequestType, CallbackResponse};
+    use serial_test::serial;
+    use std::sync::Mutex;
+
+    use crate::runtimes::rego::context_aware::tests::{
+        dynamic_object_from_fixture, object_list_from_dynamic_objects,
+    };
+
+    fn service() -> ContextAwareResource {
+        ContextAwareResource {
+            api_version: "v1".to_string(),
+            kind: "Service".to_string(),
+        }
+    }
+
+    fn deployment() -> ContextAwareResource {
+        ContextAwareResource {
+            api_version: "apps/v1".to_string(),
+            kind: "Deployment".to_string(),
+        }
+    }
+
+    fn namespace() -> ContextAwareResource {
+        ContextAwareResource {
+            api_version: "v1".to_string(),
+            kind: "Namespace".to_string(),
+        }
+    }
+
+    /// All the objects of the given resource, read from the fixtures
+    fn object_list(resource: &ContextAwareResource) -> ObjectList<kube::core::DynamicObject> {
+        let objects = match resource.kind.as_str() {
+            "Service" => vec![
+                dynamic_object_from_fixture("services", Some("kube-system"), "kube-dns").unwrap(),
+                dynamic_object_from_fixture("services", Some("kube-system"), "metrics-server")
+                    .unwrap(),
+            ],
+            "Deployment" => vec![
+                dynamic_object_from_fixture("deployments", Some("ingress"), "ingress-nginx")
+                    .unwrap(),
+                dynamic_object_from_fixture("deployments", Some("kube-system"), "coredns").unwrap(),
+            ],
+            "Namespace" => vec![
+                dynamic_object_from_fixture("namespaces", None, "cert-manager").unwrap(),
+                dynamic_object_from_fixture("namespaces", None, "kube-system").unwrap(),
+            ],
+            kind => panic!("no fixture for {kind}"),
+        };
+        object_list_from_dynamic_objects(&objects).unwrap()
+    }
+
+    /// Answer the requests sent over the callback channel using the fixtures.
+    /// The "has changed" requests are answered with `changed`.
+    /// Returns the list of the requests received, as `<request> <kind>`
+    fn serve_callback_requests(
+        mut callback_rx: mpsc::Receiver<CallbackRequest>,
+        changed: bool,
+    ) -> Arc<Mutex<Vec<String>>> {
+        let received_requests = Arc::new(Mutex::new(Vec::new()));
+        let requests = received_requests.clone();
+
+        tokio::spawn(async move {
+            while let Some(req) = callback_rx.recv().await {
+                let (request, payload) = match req.request {
+                    CallbackRequestType::KubernetesListResourceAll {
+                        api_version,
+                        kind,
+                        label_selector,
+                        field_selector,
+                        field_masks: _,
+                    } => {
+                        assert!(label_selector.is_none());
+                        assert!(field_selector.is_none());
+                        let list = object_list(&ContextAwareResource {
+                            api_version,
+                            kind: kind.clone(),
+                        });
+                        (format!("list {kind}"), serde_json::to_vec(&list).unwrap())
+                    }
+                    CallbackRequestType::HasKubernetesListResourceAllResultChangedSinceInstant {
+                        kind,
+                        label_selector,
+                        field_selector,
+                        ..
+                    } => {
+                        assert!(label_selector.is_none());
+                        assert!(field_selector.is_none());
+                        (format!("changed {kind}"), serde_json::to_vec(&changed).unwrap())
+                    }
+                    CallbackRequestType::KubernetesGetResourcePluralName { kind, .. } => (
+                        format!("plural {kind}"),
+                        serde_json::to_vec(&format!("{}s", kind.to_lowercase())).unwrap(),
+                    ),
+                    _ => {
+                        panic!("not the expected request type");
+                    }
+                };
+                requests.lock().unwrap().push(request);
+
+                req.response_channel
+                    .send(Ok(CallbackResponse { payload }))
+                    .unwrap();
+            }
+        });
+
+        received_requests
+    }
+
+    fn clear_cache() {
+        INVENTORY_CACHE.resources.write().unwrap().clear();
+        INVENTORY_CACHE.plural_names.write().unwrap().clear();
+        INVENTORY_CACHE
+            .gatekeeper_inventories
+            .write()
+            .unwrap()
+            .clear();
+        INVENTORY_CACHE.opa_inventories.write().unwrap().clear();
+    }
+
+    /// Put inside of the cache the resources of the given type, fetched one minute ago,
+    /// and a Gatekeeper inventory built from them
+    fn populate_cache(resource: &ContextAwareResource, inventory_data: &[u8]) -> Instant {
+        let cache_time = Instant::now()
+            .checked_sub(tokio::time::Duration::from_secs(60))
+            .unwrap();
+        INVENTORY_CACHE.resources.write().unwrap().insert(
+            resource.clone(),
+            Arc::new(CachedResources {
+                objects: object_list(resource),
+                cache_time,
+            }),
+        );
+        INVENTORY_CACHE
+            .gatekeeper_inventories
+            .write()
+            .unwrap()
+            .insert(
+                BTreeSet::from([resource.clone()]),
+                Arc::new(CachedInventory {
+                    data: inventory_data.to_vec(),
+                    cache_time,
+                }),
+            );
+        cache_time
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn test_create_entry_because_cache_does_not_exist() {
+        let (callback_tx, callback_rx) = mpsc::channel::<CallbackRequest>(10);
+        let received_requests = serve_callback_requests(callback_rx, false);
+        let resources = BTreeSet::from([service()]);
+        let expected_inventory =
+            GatekeeperInventory::new(&BTreeMap::from([(service(), object_list(&service()))]))
+                .unwrap();
+
+        tokio::task::spawn_blocking(move || {
+            clear_cache();
+
+            let cached_inventory = INVENTORY_CACHE
+                .get_gatekeeper_inventory(&callback_tx, &resources)
+                .unwrap();
+            let actual_inventory =
+                serde_json::from_slice::<GatekeeperInput>(&cached_inventory.data)
+                    .unwrap()
+                    .inventory;
+            assert_eq!(expected_inventory, actual_inventory);
+
+            let inventories = INVENTORY_CACHE.gatekeeper_inventories.read().unwrap();
+            assert!(Arc::ptr_eq(
+                inventories.get(&resources).unwrap(),
+                &cached_inventory
+            ));
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(*received_requests.lock().unwrap(), vec!["list Service"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn test_cached_entry_is_still_valid() {
+        let (callback_tx, callback_rx) = mpsc::channel::<CallbackRequest>(10);
+        let received_requests = serve_callback_requests(callback_rx, false);
+        let resources = BTreeSet::from([service()]);
+
+        tokio::task::spawn_blocking(move || {
+            clear_cache();
+            populate_cache(&service(), b"cached_inventory");
+
+            let actual = INVENTORY_CACHE
+                .get_gatekeeper_inventory(&callback_tx, &resources)
+                .unwrap();
+            assert_eq!(b"cached_inventory".to_vec(), actual.data);
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(*received_requests.lock().unwrap(), vec!["changed Service"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn test_cached_entry_is_no_longer_valid() {
+        let (callback_tx, callback_rx) = mpsc::channel::<CallbackRequest>(10);
+        let received_requests = serve_callback_requests(callback_rx, true);
+        let resources = BTreeSet::from([service()]);
+        let expected_inventory =
+            GatekeeperInventory::new(&BTreeMap::from([(service(), object_list(&service()))]))
+                .unwrap();
+
+        tokio::task::spawn_blocking(move || {
+            clear_cache();
+            let stale_cache_time = populate_cache(&service(), b"cached_inventory_stale");
+
+            let actual = INVENTORY_CACHE
+                .get_gatekeeper_inventory(&callback_tx, &resources)
+                .unwrap();
+            let actual_inventory = serde_json::from_slice::<GatekeeperInput>(&actual.data)
+                .unwrap()
+                .inventory;
+            assert_eq!(expected_inventory, actual_inventory);
+            assert!(actual.cache_time > stale_cache_time);
+
+            let inventories = INVENTORY_CACHE.gatekeeper_inventories.read().unwrap();
+            let cached_inventory = inventories.get(&resources).unwrap();
+            assert!(cached_inventory.cache_time > stale_cache_time);
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            *received_requests.lock().unwrap(),
+            vec!["changed Service", "list Service"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn test_overlapping_sets_of_resources_share_the_resources() {
+        let (callback_tx, callback_rx) = mpsc::channel::<CallbackRequest>(10);
+        let received_requests = serve_callback_requests(callback_rx, false);
+        let expected_inventory = GatekeeperInventory::new(&BTreeMap::from([
+            (service(), object_list(&service())),
+            (namespace(), object_list(&namespace())),
+        ]))
+        .unwrap();
+
+        tokio::task::spawn_blocking(move || {
+            clear_cache();
+
+            INVENTORY_CACHE
+                .get_gatekeeper_inventory(&callback_tx, &BTreeSet::from([service(), deployment()]))
+                .unwrap();
+            let actual = INVENTORY_CACHE
+                .get_gatekeeper_inventory(&callback_tx, &BTreeSet::from([service(), namespace()]))
+                .unwrap();
+            let actual_inventory = serde_json::from_slice::<GatekeeperInput>(&actual.data)
+                .unwrap()
+                .inventory;
+            assert_eq!(expected_inventory, actual_inventory);
+        })
+        .await
+        .unwrap();
+
+        // the services are fetched only once, by the first inventory
+        assert_eq!(
+            *received_requests.lock().unwrap(),
+            vec![
+                "list Deployment",
+                "list Service",
+                "list Namespace",
+                "changed Service",
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn test_opa_inventory_is_built_only_once() {
+        let (callback_tx, callback_rx) = mpsc::channel::<CallbackRequest>(10);
+        let received_requests = serve_callback_requests(callback_rx, false);
+        let resources = BTreeSet::from([service()]);
+        let expected_inventory = serde_json::to_vec(
+            &OpaInventory::new(
+                &BTreeMap::from([(service(), object_list(&service()))]),
+                &BTreeMap::from([(service(), "services".to_string())]),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        tokio::task::spawn_blocking(move || {
+            clear_cache();
+
+            let first = INVENTORY_CACHE
+                .get_opa_inventory(&callback_tx, &resources)
+                .unwrap();
+            assert_eq!(expected_inventory, first.data);
+
+            let second = INVENTORY_CACHE
+                .get_opa_inventory(&callback_tx, &resources)
+                .unwrap();
+            assert!(Arc::ptr_eq(&first, &second));
+        })
+        .await
+        .unwrap();
+
+        // the second evaluation only checks whether the services changed
+        assert_eq!(
+            *received_requests.lock().unwrap(),
+            vec!["plural Service", "list Service", "changed Service"]
+        );
+    }
+}
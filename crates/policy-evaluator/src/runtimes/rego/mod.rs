@@ -1,7 +1,8 @@
+pub(crate) mod builtins;
 mod context_aware;
 pub mod errors;
 mod gatekeeper_inventory;
-mod gatekeeper_inventory_cache;
+mod inventory_cache;
 mod opa_inventory;
 mod runtime;
 mod stack;
@@ -166,8 +166,13 @@ pub(crate) struct OpaInventory(ResourcesByPluralName);
 impl OpaInventory {
     /// Creates a GatekeeperInventory by querying a Kubernetes cluster
     /// for all the resources specified
-    pub(crate) fn new(
-        kube_resources: &BTreeMap<ContextAwareResource, ObjectList<kube::core::DynamicObject>>,
+    pub(crate) fn new<'a>(
+        kube_resources: impl IntoIterator<
+            Item = (
+                &'a ContextAwareResource,
+                &'a ObjectList<kube::core::DynamicObject>,
+            ),
+        >,
         plural_names: &BTreeMap<ContextAwareResource, String>,
     ) -> Result<Self> {
         let mut inventory = OpaInventory::default();
@@ -1,12 +1,12 @@
 use burrego::errors::BurregoError;
 use kubewarden_policy_sdk::settings::SettingsValidationResponse;
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{Value, json};
 use tracing::{error, warn};
 
 use crate::{
     admission_request,
-    admission_response::{AdmissionResponse, AdmissionResponseStatus},
+    admission_response::{AdmissionResponse, AdmissionResponseStatus, CauseType, StatusCause},
     policy_evaluator::{PolicySettings, RegoPolicyExecutionMode, ValidateRequest},
     runtimes::rego::{
         Stack,
@@ -76,55 +76,7 @@ impl Runtime<'_> {
                         }
                     }
                     RegoPolicyExecutionMode::Gatekeeper => {
-                        // Gatekeeper entrypoint is usually a
-                        // `violations` rule that might evaluate to a
-                        // list of violations, each violation with a
-                        // `msg` string explaining the violation
-                        // reason. If no violations are reported, the
-                        // request is accepted. Otherwise it is
-                        // rejected.
-                        #[derive(Debug, Deserialize)]
-                        struct Violation {
-                            msg: Option<String>,
-                        }
-                        #[derive(Debug, Default, Deserialize)]
-                        struct Violations {
-                            result: Vec<Violation>,
-                        }
-
-                        let violations: Violations = evaluation_result
-                            .get(0)
-                            .ok_or_else(|| RegoRuntimeError::InvalidResponse)
-                            .and_then(|response| {
-                                serde_json::from_value(response.clone())
-                                    .map_err(RegoRuntimeError::InvalidResponseWithError)
-                            })
-                            .unwrap_or_default();
-
-                        if violations.result.is_empty() {
-                            AdmissionResponse {
-                                uid: uid.to_string(),
-                                allowed: true,
-                                ..Default::default()
-                            }
-                        } else {
-                            AdmissionResponse {
-                                uid: uid.to_string(),
-                                allowed: false,
-                                status: Some(AdmissionResponseStatus {
-                                    message: Some(
-                                        violations
-                                            .result
-                                            .iter()
-                                            .filter_map(|violation| violation.msg.clone())
-                                            .collect::<Vec<String>>()
-                                            .join(", "),
-                                    ),
-                                    ..Default::default()
-                                }),
-                                ..Default::default()
-                            }
-                        }
+                        gatekeeper_admission_response(uid, &evaluation_result)
                     }
                 }
             }
@@ -158,30 +110,11 @@ impl Runtime<'_> {
             "request": &request,
         });
 
-        // OPA data seems to be free-form, except for the
-        // Kubernetes context aware data that must be under the
-        // `kubernetes` key
-        // We don't know the data that is provided by the users via
-        // their settings, hence set the context aware data, to
-        // ensure we overwrite what a user might have set.
-        let data = match ctx_data {
-            KubernetesContext::Opa(ctx) => {
-                let mut data = settings.clone();
-                if data
-                    .0
-                    .insert("kubernetes".to_string(), json!(ctx))
-                    .is_some()
-                {
-                    warn!(
-                        "OPA policy had user provided setting with key `kubernetes`. This value has been overwritten with the actual kubernetes context data"
-                    );
-                }
-                json!(data)
-            }
-            _ => json!(settings),
-        };
-
-        let data_raw = serde_json::to_vec(&data).map_err(|e| BurregoError::JSONError {
+        let data_raw = match ctx_data {
+            KubernetesContext::Opa(ctx) => opa_data(settings, &ctx.data),
+            _ => serde_json::to_vec(settings),
+        }
+        .map_err(|e| BurregoError::JSONError {
             msg: "cannot convert OPA data to JSON".to_string(),
             source: e,
         })?;
@@ -208,7 +141,7 @@ impl Runtime<'_> {
         });
 
         let data_raw = match ctx_data {
-            KubernetesContext::Gatekeeper(ctx) => ctx,
+            KubernetesContext::Gatekeeper(ctx) => &ctx.data,
             KubernetesContext::Empty => "{}".as_bytes(),
             KubernetesContext::Opa(_) => unreachable!(),
         };
@@ -228,3 +161,189 @@ impl Runtime<'_> {
         }
     }
 }
+
+/// OPA data seems to be free-form, except for the Kubernetes context aware
+/// data that must be under the `kubernetes` key.
+/// We don't know the data that is provided by the users via their settings,
+/// hence set the context aware data, to ensure we overwrite what a user might
+/// have set.
+///
+/// The inventory is already serialized and shared with the other evaluations:
+/// it's appended to the serialized settings instead of being converted back
+/// into a `Value`.
+fn opa_data(settings: &PolicySettings, inventory: &[u8]) -> serde_json::Result<Vec<u8>> {
+    let mut settings = settings.0.clone();
+    if settings.remove("kubernetes").is_some() {
+        warn!(
+            "OPA policy had user provided setting with key `kubernetes`. This value has been overwritten with the actual kubernetes context data"
+        );
+    }
+
+    // replace the closing brace of the settings object with the inventory
+    let mut data = serde_json::to_vec(&settings)?;
+    data.pop();
+    if !settings.is_empty() {
+        data.push(b',');
+    }
+    data.extend_from_slice(br#""kubernetes":"#);
+    data.extend_from_slice(inventory);
+    data.push(b'}');
+
+    Ok(data)
+}
+
+/// Gatekeeper entrypoint is usually a `violations` rule that might evaluate
+/// to a list of violations, each violation with a `msg` string explaining the
+/// violation reason. If no violations are reported, the request is accepted.
+/// Otherwise it is rejected.
+///
+/// Each violation is reported as a `StatusCause` of the response. The free-form
+/// `details` object of a violation can point to the offending field with the
+/// `field` key, and can classify the violation with the `reason` key, using
+/// one of the Kubernetes `CauseType` values.
+fn gatekeeper_admission_response(uid: &str, evaluation_result: &Value) -> AdmissionResponse {
+    #[derive(Debug, Deserialize)]
+    struct Violation {
+        msg: Option<String>,
+        details: Option<Value>,
+    }
+    #[derive(Debug, Default, Deserialize)]
+    struct Violations {
+        result: Vec<Violation>,
+    }
+
+    let violations: Violations = evaluation_result
+        .get(0)
+        .ok_or_else(|| RegoRuntimeError::InvalidResponse)
+        .and_then(|response| {
+            serde_json::from_value(response.clone())
+                .map_err(RegoRuntimeError::InvalidResponseWithError)
+        })
+        .unwrap_or_default();
+
+    if violations.result.is_empty() {
+        return AdmissionResponse {
+            uid: uid.to_string(),
+            allowed: true,
+            ..Default::default()
+        };
+    }
+
+    let causes = violations
+        .result
+        .iter()
+        .map(|violation| {
+            let details = violation.details.as_ref();
+            StatusCause {
+                reason: details
+                    .and_then(|details| details.get("reason"))
+                    .and_then(|reason| serde_json::from_value::<CauseType>(reason.clone()).ok()),
+                message: violation.msg.clone(),
+                field: details
+                    .and_then(|details| details.get("field"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            }
+        })
+        .collect();
+
+    AdmissionResponse {
+        uid: uid.to_string(),
+        allowed: false,
+        status: Some(AdmissionResponseStatus {
+            message: Some(
+                violations
+                    .result
+                    .iter()
+                    .filter_map(|violation| violation.msg.clone())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+    .with_status_causes(causes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::no_settings(json!({}), json!({"kubernetes": {"services": {}}}))]
+    #[case::settings(
+        json!({"replicas": 3}),
+        json!({"replicas": 3, "kubernetes": {"services": {}}})
+    )]
+    #[case::settings_overwritten(
+        json!({"kubernetes": "user value"}),
+        json!({"kubernetes": {"services": {}}})
+    )]
+    fn opa_data_includes_the_inventory(#[case] settings: Value, #[case] expected: Value) {
+        let settings: PolicySettings = serde_json::from_value(settings).unwrap();
+
+        let data = opa_data(&settings, br#"{"services":{}}"#).unwrap();
+
+        assert_eq!(expected, serde_json::from_slice::<Value>(&data).unwrap());
+    }
+
+    #[test]
+    fn gatekeeper_without_violations() {
+        let evaluation_result = json!([{"result": []}]);
+
+        let response = gatekeeper_admission_response("UID", &evaluation_result);
+
+        assert!(response.allowed);
+        assert!(response.status.is_none());
+    }
+
+    #[test]
+    fn gatekeeper_violations_are_reported_as_causes() {
+        let evaluation_result = json!([{
+            "result": [
+                {
+                    "msg": "container <nginx> has no resource limits",
+                    "details": {
+                        "field": "spec.containers[0].resources.limits",
+                        "reason": "FieldValueRequired"
+                    }
+                },
+                {
+                    "msg": "you must provide labels: {\"owner\"}",
+                    "details": {
+                        "missing_labels": ["owner"]
+                    }
+                }
+            ]
+        }]);
+
+        let response = gatekeeper_admission_response("UID", &evaluation_result);
+
+        assert!(!response.allowed);
+        let status = response.status.unwrap();
+        assert_eq!(
+            status.message,
+            Some(
+                "container <nginx> has no resource limits, you must provide labels: {\"owner\"}"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            status.details.unwrap().causes,
+            vec![
+                StatusCause {
+                    reason: Some(CauseType::FieldValueRequired),
+                    message: Some("container <nginx> has no resource limits".to_string()),
+                    field: Some("spec.containers[0].resources.limits".to_string()),
+                },
+                StatusCause {
+                    reason: None,
+                    message: Some("you must provide labels: {\"owner\"}".to_string()),
+                    field: None,
+                },
+            ]
+        );
+    }
+}
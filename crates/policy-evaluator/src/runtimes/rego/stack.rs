@@ -10,8 +10,7 @@ use crate::{
     runtimes::rego::{
         context_aware,
         errors::{RegoRuntimeError, Result},
-        gatekeeper_inventory_cache::GATEKEEPER_INVENTORY_CACHE,
-        opa_inventory::OpaInventory,
+        inventory_cache::INVENTORY_CACHE,
         stack_pre::StackPre,
     },
 };
@@ -20,18 +19,20 @@ pub(crate) struct Stack {
     pub evaluator: burrego::Evaluator,
     pub entrypoint_id: i32,
     pub policy_execution_mode: RegoPolicyExecutionMode,
+    pub kubernetes_inventory: bool,
 }
 
 impl Stack {
     /// Create a new `Stack` using a `StackPre` object
     pub fn new_from_pre(stack_pre: &StackPre, eval_ctx: &EvaluationContext) -> Result<Self> {
         let evaluator = stack_pre
-            .rehydrate(eval_ctx.epoch_deadline)
+            .rehydrate(eval_ctx)
             .map_err(|e| RegoRuntimeError::EvaluatorError(e.to_string()))?;
         Ok(Self {
             evaluator,
             entrypoint_id: stack_pre.entrypoint_id,
             policy_execution_mode: stack_pre.policy_execution_mode.clone(),
+            kubernetes_inventory: stack_pre.kubernetes_inventory,
         })
     }
 
@@ -40,7 +41,7 @@ impl Stack {
         callback_channel: Option<&mpsc::Sender<CallbackRequest>>,
         ctx_aware_resources_allow_list: &BTreeSet<ContextAwareResource>,
     ) -> Result<context_aware::KubernetesContext> {
-        if ctx_aware_resources_allow_list.is_empty() {
+        if ctx_aware_resources_allow_list.is_empty() || !self.kubernetes_inventory {
             return Ok(context_aware::KubernetesContext::Empty);
         }
 
@@ -48,17 +49,13 @@ impl Stack {
             None => Err(RegoRuntimeError::CallbackChannelNotSet),
             Some(chan) => match self.policy_execution_mode {
                 RegoPolicyExecutionMode::Opa => {
-                    let cluster_resources =
-                        context_aware::get_allowed_resources(chan, ctx_aware_resources_allow_list)?;
-                    let plural_names_by_resource =
-                        context_aware::get_plural_names(chan, ctx_aware_resources_allow_list)?;
-                    let inventory =
-                        OpaInventory::new(&cluster_resources, &plural_names_by_resource)?;
-                    Ok(context_aware::KubernetesContext::Opa(inventory))
+                    let cached_inventory =
+                        INVENTORY_CACHE.get_opa_inventory(chan, ctx_aware_resources_allow_list)?;
+                    Ok(context_aware::KubernetesContext::Opa(cached_inventory))
                 }
                 RegoPolicyExecutionMode::Gatekeeper => {
-                    let cached_inventory = GATEKEEPER_INVENTORY_CACHE
-                        .get_inventory(chan, ctx_aware_resources_allow_list)?;
+                    let cached_inventory = INVENTORY_CACHE
+                        .get_gatekeeper_inventory(chan, ctx_aware_resources_allow_list)?;
                     Ok(context_aware::KubernetesContext::Gatekeeper(
                         cached_inventory,
                     ))
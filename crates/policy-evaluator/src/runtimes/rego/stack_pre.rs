@@ -1,6 +1,12 @@
+use std::sync::Arc;
+
 use crate::{
+    evaluation_context::EvaluationContext,
     policy_evaluator::RegoPolicyExecutionMode,
-    runtimes::rego::errors::{RegoRuntimeError, Result},
+    runtimes::rego::{
+        builtins::kubewarden_builtins,
+        errors::{RegoRuntimeError, Result},
+    },
 };
 
 /// This struct allows to follow the `StackPre -> Stack`
@@ -20,6 +26,8 @@ pub(crate) struct StackPre {
     module: wasmtime::Module,
     pub entrypoint_id: i32,
     pub policy_execution_mode: RegoPolicyExecutionMode,
+    /// Provide the inventory of the context aware resources to the policy
+    pub kubernetes_inventory: bool,
 }
 
 impl StackPre {
@@ -28,23 +36,27 @@ impl StackPre {
         module: wasmtime::Module,
         entrypoint_id: i32,
         policy_execution_mode: RegoPolicyExecutionMode,
+        kubernetes_inventory: bool,
     ) -> Self {
         Self {
             engine,
             module,
             entrypoint_id,
             policy_execution_mode,
+            kubernetes_inventory,
         }
     }
 
-    /// Create a fresh `burrego::Evaluator`
-    pub(crate) fn rehydrate(&self, epoch_deadline: Option<u64>) -> Result<burrego::Evaluator> {
+    /// Create a fresh `burrego::Evaluator`. The Kubewarden builtins of the
+    /// evaluator act on behalf of the policy described by `eval_ctx`
+    pub(crate) fn rehydrate(&self, eval_ctx: &EvaluationContext) -> Result<burrego::Evaluator> {
         let mut builder = burrego::EvaluatorBuilder::default()
             .engine(&self.engine)
             .module(self.module.clone())
-            .host_callbacks(crate::runtimes::rego::new_host_callbacks());
+            .host_callbacks(crate::runtimes::rego::new_host_callbacks())
+            .custom_builtins(kubewarden_builtins(Arc::new(eval_ctx.to_owned())));
 
-        if let Some(deadline) = epoch_deadline {
+        if let Some(deadline) = eval_ctx.epoch_deadline {
             builder = builder.enable_epoch_interruptions(deadline);
         }
         let evaluator = builder
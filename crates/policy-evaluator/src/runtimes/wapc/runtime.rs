@@ -1,14 +1,11 @@
 use std::convert::TryFrom;
 
-use kubewarden_policy_sdk::{
-    metadata::ProtocolVersion, response::ValidationResponse as PolicyValidationResponse,
-    settings::SettingsValidationResponse,
-};
+use kubewarden_policy_sdk::{metadata::ProtocolVersion, settings::SettingsValidationResponse};
 use serde_json::json;
 use tracing::{error, info};
 
 use crate::{
-    admission_response::AdmissionResponse,
+    admission_response::{AdmissionResponse, ExtendedPolicyValidationResponse},
     policy_evaluator::{PolicySettings, ValidateRequest},
     runtimes::wapc::{
         WapcStack,
@@ -60,11 +57,12 @@ impl Runtime<'_> {
 
         match self.0.call("validate", validate_str.as_bytes()) {
             Ok(res) => {
-                let pol_val_resp: Result<PolicyValidationResponse> = serde_json::from_slice(&res)
-                    .map_err(WapcRuntimeError::InvalidResponseWithError);
+                let pol_val_resp: Result<ExtendedPolicyValidationResponse> =
+                    serde_json::from_slice(&res)
+                        .map_err(WapcRuntimeError::InvalidResponseWithError);
                 pol_val_resp
                     .and_then(|pol_val_resp| {
-                        AdmissionResponse::from_policy_validation_response(
+                        AdmissionResponse::from_extended_policy_validation_response(
                             uid.to_string(),
                             req_obj,
                             &pol_val_resp,
@@ -209,6 +207,7 @@ mod tests {
             ctx_aware_resources_allow_list: Default::default(),
             epoch_deadline: Some(epoch_deadline),
             host_capabilities: HostCapabilities::AllowAll,
+            http_endpoints_allow_list: Default::default(),
         };
 
         let eval_ctx = Arc::new(eval_ctx);
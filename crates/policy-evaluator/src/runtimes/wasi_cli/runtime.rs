@@ -1,9 +1,8 @@
-use kubewarden_policy_sdk::response::ValidationResponse as PolicyValidationResponse;
 use kubewarden_policy_sdk::settings::SettingsValidationResponse;
 use serde_json::json;
 use tracing::{error, warn};
 
-use crate::admission_response::AdmissionResponse;
+use crate::admission_response::{AdmissionResponse, ExtendedPolicyValidationResponse};
 use crate::policy_evaluator::{PolicySettings, ValidateRequest};
 use crate::runtimes::wasi_cli::stack::{RunResult, Stack};
 
@@ -45,7 +44,8 @@ impl Runtime<'_> {
                         stderr
                     )
                 }
-                match serde_json::from_slice::<PolicyValidationResponse>(stdout.as_bytes()) {
+                match serde_json::from_slice::<ExtendedPolicyValidationResponse>(stdout.as_bytes())
+                {
                     Ok(pvr) => {
                         let req_json_value = serde_json::to_value(request)
                             .expect("cannot convert request to json value");
@@ -54,7 +54,7 @@ impl Runtime<'_> {
                             ValidateRequest::AdmissionRequest(_) => req_json_value.get("object"),
                         };
 
-                        AdmissionResponse::from_policy_validation_response(
+                        AdmissionResponse::from_extended_policy_validation_response(
                             request.uid().to_string(),
                             req_obj,
                             &pvr,
@@ -178,6 +178,7 @@ async fn test_policy_evaluator(
         ctx_aware_resources_allow_list: Default::default(),
         epoch_deadline: None,
         host_capabilities: HostCapabilities::AllowAll,
+        http_endpoints_allow_list: Default::default(),
     };
 
     let mut policy_evaluator = build_policy_evaluator(execution_mode, &policy, &eval_ctx);
@@ -292,6 +293,7 @@ async fn test_runtime_context_aware<F, Fut>(
         ]),
         epoch_deadline: Some(2),
         host_capabilities: HostCapabilities::AllowAll,
+        http_endpoints_allow_list: Default::default(),
     };
 
     let request_data = load_request_data(request_file_path);
@@ -373,6 +375,7 @@ async fn test_host_capabilities<F, Fut>(
         ]),
         epoch_deadline: Some(2),
         host_capabilities,
+        http_endpoints_allow_list: Default::default(),
     };
 
     let request_data = load_request_data(request_file_path);
@@ -438,6 +441,7 @@ async fn test_oci_manifest_capability(
         ctx_aware_resources_allow_list: Default::default(),
         epoch_deadline: None,
         host_capabilities: HostCapabilities::AllowAll,
+        http_endpoints_allow_list: Default::default(),
     };
 
     let cb_channel: mpsc::Sender<CallbackRequest> = eval_ctx
@@ -532,6 +536,7 @@ async fn test_oci_manifest_and_config_capability(
         ctx_aware_resources_allow_list: Default::default(),
         epoch_deadline: None,
         host_capabilities: HostCapabilities::AllowAll,
+        http_endpoints_allow_list: Default::default(),
     };
 
     let cb_channel: mpsc::Sender<CallbackRequest> = eval_ctx
@@ -601,6 +606,7 @@ async fn test_oci_digest_capability() {
         ctx_aware_resources_allow_list: Default::default(),
         epoch_deadline: None,
         host_capabilities: HostCapabilities::AllowAll,
+        http_endpoints_allow_list: Default::default(),
     };
 
     let cb_channel: mpsc::Sender<CallbackRequest> = eval_ctx
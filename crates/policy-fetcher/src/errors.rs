@@ -6,8 +6,20 @@ pub type FetcherResult<T> = std::result::Result<T, FetcherError>;
 pub enum FetcherError {
     #[error("cannot retrieve path from uri: {0}")]
     InvalidFilePathError(String),
+    #[error("unknown fetch mode: {0}. Valid values are IfNotPresent, Always and IfDigestChanged")]
+    UnknownFetchModeError(String),
     #[error("invalid wasm file")]
     InvalidWasmFileError,
+    #[error("invalid checksum file published next to {0}")]
+    InvalidChecksumFileError(String),
+    #[error(
+        "the checksum of {url} doesn't match with the published one: expected {expected}, got {actual}"
+    )]
+    ChecksumMismatchError {
+        url: String,
+        expected: String,
+        actual: String,
+    },
     #[error("wasm module cannot be save to {0:?}: {1}")]
     CannotWriteWasmModuleFile(String, #[source] std::io::Error),
     #[error(transparent)]
@@ -1,17 +1,26 @@
 #![allow(clippy::upper_case_acronyms)]
 
 use async_trait::async_trait;
+use sha2::{Digest, Sha256};
 use std::{
     boxed::Box,
     convert::{TryFrom, TryInto},
 };
+use tracing::debug;
 use url::Url;
 
 use crate::Sources;
+use crate::errors::{FetcherError, FetcherResult};
 use crate::fetcher::{ClientProtocol, PolicyFetcher, TlsVerificationMode};
 use crate::sources::Certificate;
 use crate::sources::SourceError;
 use crate::sources::SourceResult;
+use crate::verify::bundle::{BUNDLE_EXTENSIONS, LocalBundle};
+use crate::with_protocols;
+
+/// The extension of the checksum published next to a policy, in the format
+/// used by `sha256sum`
+const CHECKSUM_EXTENSION: &str = "sha256";
 
 // Struct used to reference a WASM module that is hosted on a HTTP(s) server
 #[derive(Default)]
@@ -40,18 +49,22 @@ impl TryFrom<&Certificate> for reqwest::Certificate {
     }
 }
 
-#[async_trait]
-impl PolicyFetcher for Https {
-    async fn fetch(
-        &self,
-        url: &Url,
-        client_protocol: ClientProtocol,
+/// The response to a request conditioned by the ETag of the content that
+/// has already been fetched
+pub(crate) enum ConditionalResponse {
+    Modified { body: Vec<u8>, etag: Option<String> },
+    NotModified,
+}
+
+impl Https {
+    fn client(
+        client_protocol: &ClientProtocol,
         sources: &Sources,
-    ) -> SourceResult<Vec<u8>> {
+    ) -> SourceResult<reqwest::Client> {
         let mut client_builder = reqwest::Client::builder();
         match client_protocol {
             ClientProtocol::Http => {}
-            ClientProtocol::Https(ref tls_fetch_mode) => {
+            ClientProtocol::Https(tls_fetch_mode) => {
                 client_builder = client_builder.https_only(true);
                 match tls_fetch_mode {
                     TlsVerificationMode::SystemCa => {}
@@ -95,13 +108,218 @@ impl PolicyFetcher for Https {
             client_builder = client_builder.proxy(proxy);
         }
 
-        let client = client_builder.build()?;
-        Ok(client
+        Ok(client_builder.build()?)
+    }
+
+    /// Fetch the content of `url`, unless its ETag is still `etag`
+    pub(crate) async fn fetch_if_none_match(
+        &self,
+        url: &Url,
+        client_protocol: ClientProtocol,
+        sources: &Sources,
+        etag: Option<&str>,
+    ) -> SourceResult<ConditionalResponse> {
+        let request_error = |err| SourceError::HttpRequestError(url.to_string(), err);
+
+        let mut request = Https::client(&client_protocol, sources)?.get(url.as_ref());
+        if let Some(etag) = etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await.map_err(request_error)?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(ConditionalResponse::NotModified);
+        }
+        let response = response.error_for_status().map_err(request_error)?;
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.to_owned());
+        let body = response.bytes().await.map_err(request_error)?.to_vec();
+
+        Ok(ConditionalResponse::Modified { body, etag })
+    }
+
+    /// Fetch a file that may not exist, like the ones published next to a
+    /// policy. Returns `None` when the server doesn't have it.
+    pub(crate) async fn fetch_optional(
+        &self,
+        url: &Url,
+        client_protocol: ClientProtocol,
+        sources: &Sources,
+    ) -> SourceResult<Option<Vec<u8>>> {
+        let request_error = |err| SourceError::HttpRequestError(url.to_string(), err);
+
+        let response = Https::client(&client_protocol, sources)?
             .get(url.as_ref())
             .send()
+            .await
+            .map_err(request_error)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(request_error)?;
+        Ok(Some(
+            response.bytes().await.map_err(request_error)?.to_vec(),
+        ))
+    }
+}
+
+#[async_trait]
+impl PolicyFetcher for Https {
+    async fn fetch(
+        &self,
+        url: &Url,
+        client_protocol: ClientProtocol,
+        sources: &Sources,
+    ) -> SourceResult<Vec<u8>> {
+        match self
+            .fetch_if_none_match(url, client_protocol, sources, None)
             .await?
-            .bytes()
-            .await?
-            .to_vec())
+        {
+            ConditionalResponse::Modified { body, .. } => Ok(body),
+            ConditionalResponse::NotModified => {
+                unreachable!("unconditional request answered with 304")
+            }
+        }
+    }
+}
+
+/// A policy served over HTTP(S), together with the files published next to it
+#[derive(Default)]
+pub(crate) struct HttpsPolicy {
+    pub(crate) module: Vec<u8>,
+    pub(crate) etag: Option<String>,
+    /// The first signature found next to the policy, with its extension,
+    /// see [`BUNDLE_EXTENSIONS`]
+    pub(crate) signature: Option<(&'static str, Vec<u8>)>,
+}
+
+/// Fetch the policy served at `url`, unless its ETag is still `etag`, in
+/// which case `None` is returned.
+///
+/// The checksum published next to the policy, when present, must match with
+/// the one of the module. The signature published next to it is checked to be
+/// a valid Sigstore bundle or detached signature, its verification against
+/// the verification config happens later, see [`crate::verify::Verifier::verify_bundle`].
+pub(crate) async fn fetch_https_policy(
+    url: &Url,
+    sources: &Sources,
+    etag: Option<&str>,
+) -> FetcherResult<Option<HttpsPolicy>> {
+    let https = Https::default();
+    let response = with_protocols(url, sources, |client_protocol| {
+        https.fetch_if_none_match(url, client_protocol, sources, etag)
+    })
+    .await?;
+    let (module, etag) = match response {
+        ConditionalResponse::NotModified => return Ok(None),
+        ConditionalResponse::Modified { body, etag } => (body, etag),
+    };
+
+    let checksum_url = companion_url(url, CHECKSUM_EXTENSION);
+    if let Some(checksum) = with_protocols(&checksum_url, sources, |client_protocol| {
+        https.fetch_optional(&checksum_url, client_protocol, sources)
+    })
+    .await?
+    {
+        verify_checksum(url, &module, &checksum)?;
+        debug!(?url, "policy checksum verified");
+    }
+
+    let mut signature = None;
+    for extension in BUNDLE_EXTENSIONS {
+        let signature_url = companion_url(url, extension);
+        if let Some(data) = with_protocols(&signature_url, sources, |client_protocol| {
+            https.fetch_optional(&signature_url, client_protocol, sources)
+        })
+        .await?
+        {
+            LocalBundle::from_bytes(&data)?;
+            signature = Some((extension, data));
+            break;
+        }
+    }
+
+    Ok(Some(HttpsPolicy {
+        module,
+        etag,
+        signature,
+    }))
+}
+
+/// The URL of a file published next to the policy, like `policy.wasm.sha256`
+fn companion_url(url: &Url, extension: &str) -> Url {
+    let mut companion = url.clone();
+    companion.set_path(&format!("{}.{extension}", url.path()));
+    companion
+}
+
+fn verify_checksum(url: &Url, module: &[u8], checksum_file: &[u8]) -> FetcherResult<()> {
+    // the checksum can be followed by the name of the file
+    let expected = std::str::from_utf8(checksum_file)
+        .ok()
+        .and_then(|checksum_file| checksum_file.split_whitespace().next())
+        .filter(|checksum| checksum.len() == 64 && hex::decode(checksum).is_ok())
+        .map(|checksum| checksum.to_lowercase())
+        .ok_or_else(|| FetcherError::InvalidChecksumFileError(url.to_string()))?;
+    let actual = hex::encode(Sha256::digest(module));
+    if expected != actual {
+        return Err(FetcherError::ChecksumMismatchError {
+            url: url.to_string(),
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    // sha256 of "module"
+    const CHECKSUM: &str = "120970d812836f19888625587a4606a5ad23cef31c8684e601771552548fc6b9";
+
+    #[rstest]
+    #[case::sha256sum_format(&format!("{CHECKSUM}  policy.wasm\n"), true)]
+    #[case::checksum_only(CHECKSUM, true)]
+    #[case::uppercase(&CHECKSUM.to_uppercase(), true)]
+    #[case::other_module(
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        false
+    )]
+    fn checksum_verification(#[case] checksum_file: &str, #[case] valid: bool) {
+        let url = Url::parse("https://example.com/policy.wasm").unwrap();
+        let result = verify_checksum(&url, b"module", checksum_file.as_bytes());
+        assert_eq!(result.is_ok(), valid, "{result:?}");
+        if !valid {
+            assert!(matches!(
+                result,
+                Err(FetcherError::ChecksumMismatchError { .. })
+            ));
+        }
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::not_hex("not a checksum")]
+    #[case::too_short("120970d8")]
+    fn invalid_checksum_file(#[case] checksum_file: &str) {
+        let url = Url::parse("https://example.com/policy.wasm").unwrap();
+        assert!(matches!(
+            verify_checksum(&url, b"module", checksum_file.as_bytes()),
+            Err(FetcherError::InvalidChecksumFileError(_))
+        ));
+    }
+
+    #[test]
+    fn companion_urls() {
+        let url = Url::parse("https://example.com/policies/policy.wasm?version=1").unwrap();
+        assert_eq!(
+            companion_url(&url, "sha256").as_str(),
+            "https://example.com/policies/policy.wasm.sha256?version=1"
+        );
     }
 }
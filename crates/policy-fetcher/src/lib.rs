@@ -12,6 +12,7 @@ use url::Url;
 pub mod errors;
 pub mod fetcher;
 mod https;
+pub mod oci_layout;
 pub mod policy;
 pub mod proxy;
 pub mod registry;
@@ -19,23 +20,25 @@ pub mod sources;
 pub mod store;
 pub mod verify;
 
-use crate::errors::{CannotCreateStoragePathError, FetcherError};
+use crate::errors::FetcherError;
 use crate::fetcher::{ClientProtocol, PolicyFetcher, TlsVerificationMode};
 use crate::https::Https;
 use crate::policy::Policy;
 use crate::registry::Registry;
 use crate::registry::build_fully_resolved_reference;
 use crate::sources::Sources;
-use crate::store::Store;
+use crate::store::{HttpsPolicyMetadata, Store};
 
 #[macro_use]
 extern crate lazy_static;
 
 use std::{
     collections::HashSet,
+    fmt,
     path::{Path, PathBuf},
+    str::FromStr,
 };
-use tracing::debug;
+use tracing::{debug, info, warn};
 use url::ParseError;
 
 // re-export for usage by kwctl, policy-server, policy-evaluator,...
@@ -76,10 +79,64 @@ fn parse_url(url: &str) -> std::result::Result<reqwest::Url, url::ParseError> {
     }
 }
 
+/// Defines when a policy that has already been pulled is pulled again
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FetchMode {
+    /// Pull the policy only when it has not been pulled yet
+    IfNotPresent,
+    /// Always pull the policy
+    Always,
+    /// Pull the policy again when its tag points to a different manifest
+    /// inside of the registry. The policy that has already been pulled is
+    /// used when the registry cannot be reached.
+    ///
+    /// The policies served over HTTP(S) are pulled again when their ETag
+    /// changed. The policies referenced by digest, the local ones and the
+    /// ones served without an ETag are pulled only when they have not been
+    /// pulled yet.
+    #[default]
+    IfDigestChanged,
+}
+
+impl FromStr for FetchMode {
+    type Err = FetcherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "IfNotPresent" => Ok(FetchMode::IfNotPresent),
+            "Always" => Ok(FetchMode::Always),
+            "IfDigestChanged" => Ok(FetchMode::IfDigestChanged),
+            _ => Err(FetcherError::UnknownFetchModeError(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for FetchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchMode::IfNotPresent => write!(f, "IfNotPresent"),
+            FetchMode::Always => write!(f, "Always"),
+            FetchMode::IfDigestChanged => write!(f, "IfDigestChanged"),
+        }
+    }
+}
+
+/// Fetch the policy using the default [`FetchMode`]
 pub async fn fetch_policy(
     url: &str,
     destination: PullDestination,
     sources: Option<&Sources>,
+) -> FetcherResult<Policy> {
+    fetch_policy_with_mode(url, destination, sources, FetchMode::default()).await
+}
+
+/// Fetch the policy, the [`FetchMode`] defines whether a policy that has
+/// already been pulled is pulled again
+pub async fn fetch_policy_with_mode(
+    url: &str,
+    destination: PullDestination,
+    sources: Option<&Sources>,
+    fetch_mode: FetchMode,
 ) -> FetcherResult<Policy> {
     let url = parse_url(url)?;
     match url.scheme() {
@@ -95,72 +152,295 @@ pub async fn fetch_policy(
         "registry" | "http" | "https" => Ok(()),
         _ => Err(StoreError::UnknownSchemeError(url.scheme().to_owned())),
     }?;
-    let (store, mut destination) = pull_destination(&url, &destination)?;
-    if let Some(store) = store {
-        store
-            .ensure(&store.policy_full_path(url.as_str(), store::PolicyPath::PrefixOnly)?)
-            .map_err(CannotCreateStoragePathError)?;
-    }
-    match url.scheme() {
-        "registry" => {
-            // On a registry, the `latest` tag always pulls the latest version
-            let reference = build_fully_resolved_reference(url.as_str())?;
-            if reference.tag() != Some("latest") && Path::exists(&destination) {
+    let sources_default = Sources::default();
+    let sources = sources.unwrap_or(&sources_default);
+
+    let destination = pull_destination(&url, &destination);
+    // The digest of the manifest the policy is pulled from, when known
+    let mut manifest_digest = None;
+    // The policy served over HTTP(S), when it has already been fetched
+    let mut https_policy = None;
+    match &destination {
+        Destination::Store(store) => {
+            match lookup_stored_policy(store, &url, fetch_mode, sources).await? {
+                StoredPolicyLookup::Use(policy) => return Ok(policy),
+                StoredPolicyLookup::Pull {
+                    manifest_digest: digest,
+                } => manifest_digest = digest,
+                StoredPolicyLookup::Revalidate { stored, etag } => {
+                    match https::fetch_https_policy(&url, sources, Some(&etag)).await {
+                        Ok(None) => {
+                            debug!(?url, etag, "policy not modified");
+                            return Ok(stored);
+                        }
+                        Ok(Some(fetched)) => {
+                            info!(?url, "policy changed on the server");
+                            https_policy = Some(fetched);
+                        }
+                        Err(err) => {
+                            warn!(%err, ?url, "cannot check if the policy changed, using the stored one");
+                            return Ok(stored);
+                        }
+                    }
+                }
+            }
+        }
+        Destination::LocalFile(path) => {
+            let use_existing = match fetch_mode {
+                FetchMode::Always => false,
+                FetchMode::IfNotPresent => true,
+                FetchMode::IfDigestChanged => is_cacheable(&url)?,
+            };
+            if use_existing && Path::exists(path) {
                 return Ok(Policy {
                     uri: url.to_string(),
-                    local_path: destination,
+                    local_path: path.to_owned(),
                 });
             }
-            // If the reference tag is `latest` and the URL does not contain `:latest`
-            // we need to add it to the destination
-            if Some("latest") == reference.tag() && !str::ends_with(url.as_str(), ":latest") {
-                destination = PathBuf::from(destination.to_string_lossy().to_string() + ":latest");
+        }
+    }
+    debug!(?url, %fetch_mode, "pulling policy");
+    let policy_fetcher = url_fetcher(url.scheme())?;
+
+    let bytes = match url.scheme() {
+        "registry" => {
+            let reference = build_fully_resolved_reference(url.as_str())?;
+            if let Some(digest) = reference.digest() {
+                manifest_digest = Some(digest.to_owned());
+            } else if manifest_digest.is_none() && matches!(destination, Destination::Store(_)) {
+                // Resolve the tag, then pull by digest: this ensures the recorded
+                // digest matches the pulled module, even if the tag is moved meanwhile
+                manifest_digest = Registry::new()
+                    .manifest_digest(url.as_str(), Some(sources))
+                    .await
+                    .inspect_err(|err| debug!(%err, ?url, "cannot resolve the manifest digest"))
+                    .ok();
+            }
+
+            // The policy can be pulled from the mirrors of its registry, but
+            // it is always stored using its original reference
+            let mut last_error = None;
+            let mut bytes = None;
+            for location in sources.mirrored_references(&reference) {
+                let location_url = match &manifest_digest {
+                    Some(digest) => parse_url(&format!(
+                        "registry://{}/{}@{digest}",
+                        location.registry(),
+                        location.repository()
+                    ))?,
+                    None => parse_url(&format!("registry://{location}"))?,
+                };
+                match fetch_with_protocols(policy_fetcher.as_ref(), &location_url, sources).await {
+                    Ok(data) => {
+                        bytes = Some(data);
+                        break;
+                    }
+                    Err(err) => {
+                        debug!(%err, ?url, location = %location_url, "cannot pull policy");
+                        last_error = Some(err);
+                    }
+                }
+            }
+            match bytes {
+                Some(bytes) => bytes,
+                None => return Err(last_error.expect("at least one location is always tried")),
             }
         }
         "http" | "https" => {
-            if Path::exists(&destination) {
-                return Ok(Policy {
-                    uri: url.to_string(),
-                    local_path: destination,
-                });
+            let mut fetched = match https_policy.take() {
+                Some(fetched) => fetched,
+                // unconditional requests are always answered with the policy
+                None => https::fetch_https_policy(&url, sources, None)
+                    .await?
+                    .unwrap_or_default(),
+            };
+            let module = std::mem::take(&mut fetched.module);
+            https_policy = Some(fetched);
+            module
+        }
+        _ => fetch_with_protocols(policy_fetcher.as_ref(), &url, sources).await?,
+    };
+
+    match destination {
+        Destination::Store(store) => {
+            validate_wasm_file(&bytes)?;
+            let policy = match (&manifest_digest, https_policy) {
+                (Some(manifest_digest), _) => {
+                    store.add_registry_policy(url.as_str(), &bytes, manifest_digest)?
+                }
+                (None, Some(https_policy)) => store.add_https_policy(
+                    url.as_str(),
+                    &bytes,
+                    &HttpsPolicyMetadata {
+                        etag: https_policy.etag,
+                        signature: https_policy.signature.map(|(_, signature)| signature),
+                    },
+                )?,
+                (None, None) => store.add_policy(url.as_str(), &bytes)?,
+            };
+            Ok(Policy {
+                uri: url.to_string(),
+                local_path: policy.local_path,
+            })
+        }
+        Destination::LocalFile(path) => {
+            let policy = create_file_if_valid(&bytes, &path, url.to_string())?;
+            // the signature is saved next to the policy, where it's looked up
+            // when verifying local policies
+            if let Some((extension, signature)) =
+                https_policy.and_then(|https_policy| https_policy.signature)
+            {
+                let mut signature_path = path.into_os_string();
+                signature_path.push(format!(".{extension}"));
+                fs::write(&signature_path, signature).map_err(|e| {
+                    FetcherError::CannotWriteWasmModuleFile(
+                        signature_path.to_string_lossy().to_string(),
+                        e,
+                    )
+                })?;
             }
+            Ok(policy)
         }
-        _ => unreachable!(),
     }
-    debug!(?url, "pulling policy");
-    let policy_fetcher = url_fetcher(url.scheme())?;
-    let sources_default = Sources::default();
-    let sources = sources.unwrap_or(&sources_default);
+}
+
+enum StoredPolicyLookup {
+    /// The stored policy can be used
+    Use(Policy),
+    /// The policy must be pulled, from the given manifest when known
+    Pull { manifest_digest: Option<String> },
+    /// The policy served over HTTP(S) must be pulled again, unless its ETag
+    /// is still the given one
+    Revalidate { stored: Policy, etag: String },
+}
+
+/// Decide whether the policy stored inside of the store can be used,
+/// according to the fetch mode
+async fn lookup_stored_policy(
+    store: &Store,
+    url: &Url,
+    fetch_mode: FetchMode,
+    sources: &Sources,
+) -> FetcherResult<StoredPolicyLookup> {
+    let pull = StoredPolicyLookup::Pull {
+        manifest_digest: None,
+    };
+    if fetch_mode == FetchMode::Always {
+        return Ok(pull);
+    }
+
+    let stored = match store.get_policy_by_uri(url.as_str()) {
+        Ok(Some(stored)) => Policy {
+            uri: url.to_string(),
+            local_path: stored.local_path,
+        },
+        Ok(None) => return Ok(pull),
+        Err(err) => {
+            warn!(%err, ?url, "cannot use the stored policy, pulling it again");
+            return Ok(pull);
+        }
+    };
+    if fetch_mode == FetchMode::IfNotPresent {
+        return Ok(StoredPolicyLookup::Use(stored));
+    }
+    if matches!(url.scheme(), "http" | "https") {
+        let etag = store
+            .index_entry(url.as_str())?
+            .and_then(|entry| entry.etag);
+        return Ok(match etag {
+            Some(etag) => StoredPolicyLookup::Revalidate { stored, etag },
+            None => StoredPolicyLookup::Use(stored),
+        });
+    }
+    if url.scheme() != "registry" {
+        return Ok(StoredPolicyLookup::Use(stored));
+    }
 
-    match policy_fetcher
-        .fetch(&url, client_protocol(&url, sources)?, sources)
+    // The policies referenced by digest never change
+    if build_fully_resolved_reference(url.as_str())?
+        .digest()
+        .is_some()
+    {
+        return Ok(StoredPolicyLookup::Use(stored));
+    }
+
+    let recorded_digest = store
+        .index_entry(url.as_str())?
+        .and_then(|entry| entry.manifest_digest);
+    match Registry::new()
+        .manifest_digest(url.as_str(), Some(sources))
         .await
     {
+        Ok(digest) if Some(&digest) == recorded_digest.as_ref() => {
+            Ok(StoredPolicyLookup::Use(stored))
+        }
+        Ok(digest) => {
+            info!(?url, ?recorded_digest, %digest, "policy changed inside of the registry");
+            Ok(StoredPolicyLookup::Pull {
+                manifest_digest: Some(digest),
+            })
+        }
         Err(err) => {
-            if !sources.is_insecure_source(&host_and_port(&url)?) {
+            warn!(%err, ?url, "cannot check if the policy changed, using the stored one");
+            Ok(StoredPolicyLookup::Use(stored))
+        }
+    }
+}
+
+/// Whether a policy that has already been pulled into a local file can be
+/// used without pulling it again, when the fetch mode is `IfDigestChanged`.
+/// There's no record of the digest of these files: the `latest` tag of a
+/// registry always pulls the latest version.
+fn is_cacheable(url: &Url) -> FetcherResult<bool> {
+    Ok(match url.scheme() {
+        "registry" => build_fully_resolved_reference(url.as_str())?.tag() != Some("latest"),
+        _ => true,
+    })
+}
+
+/// Fetch the policy, falling back to less secure protocols when the
+/// source is marked as insecure
+async fn fetch_with_protocols(
+    policy_fetcher: &(dyn PolicyFetcher + Send + Sync),
+    url: &Url,
+    sources: &Sources,
+) -> FetcherResult<Vec<u8>> {
+    with_protocols(url, sources, |client_protocol| {
+        policy_fetcher.fetch(url, client_protocol, sources)
+    })
+    .await
+}
+
+/// Run `fetch` using the client protocol of `url`, falling back to less
+/// secure protocols when the source is marked as insecure
+pub(crate) async fn with_protocols<T, F, Fut>(
+    url: &Url,
+    sources: &Sources,
+    fetch: F,
+) -> FetcherResult<T>
+where
+    F: Fn(ClientProtocol) -> Fut,
+    Fut: std::future::Future<Output = sources::SourceResult<T>>,
+{
+    match fetch(client_protocol(url, sources)?).await {
+        Err(err) => {
+            if !sources.is_insecure_source(&host_and_port(url)?) {
                 return Err(FetcherError::SourceError(err));
             }
         }
-        Ok(bytes) => return create_file_if_valid(&bytes, &destination, url.to_string()),
+        Ok(value) => return Ok(value),
     }
-    if let Ok(bytes) = policy_fetcher
-        .fetch(
-            &url,
-            ClientProtocol::Https(TlsVerificationMode::NoTlsVerification),
-            sources,
-        )
-        .await
+    if let Ok(value) = fetch(ClientProtocol::Https(
+        TlsVerificationMode::NoTlsVerification,
+    ))
+    .await
     {
-        return create_file_if_valid(&bytes, &destination, url.to_string());
+        return Ok(value);
     }
 
-    match policy_fetcher
-        .fetch(&url, ClientProtocol::Http, sources)
+    fetch(ClientProtocol::Http)
         .await
-    {
-        Ok(bytes) => create_file_if_valid(&bytes, &destination, url.to_string()),
-        Err(e) => Err(FetcherError::SourceError(e)),
-    }
+        .map_err(FetcherError::SourceError)
 }
 
 fn client_protocol(
@@ -175,38 +455,42 @@ fn client_protocol(
     Ok(ClientProtocol::Https(TlsVerificationMode::SystemCa))
 }
 
-fn pull_destination(
-    url: &Url,
-    destination: &PullDestination,
-) -> FetcherResult<(Option<Store>, PathBuf)> {
-    Ok(match destination {
-        PullDestination::MainStore => {
-            let store = Store::default();
-            let policy_path =
-                store.policy_full_path(url.as_str(), store::PolicyPath::PrefixAndFilename)?;
-            (Some(store), policy_path)
-        }
-        PullDestination::Store(root) => {
-            let store = Store::new(root);
-            let policy_path =
-                store.policy_full_path(url.as_str(), store::PolicyPath::PrefixAndFilename)?;
-            (Some(store), policy_path)
-        }
+/// Where a pulled policy is saved
+#[derive(Debug, PartialEq, Eq)]
+enum Destination {
+    Store(Store),
+    LocalFile(PathBuf),
+}
+
+fn pull_destination(url: &Url, destination: &PullDestination) -> Destination {
+    match destination {
+        PullDestination::MainStore => Destination::Store(Store::default()),
+        PullDestination::Store(root) => Destination::Store(Store::new(root)),
         PullDestination::LocalFile(destination) => {
-            if Path::is_dir(destination) {
+            let mut path = if Path::is_dir(destination) {
                 let filename = url.path().split('/').next_back().unwrap();
-                (None, destination.join(filename))
+                destination.join(filename)
             } else {
-                (None, PathBuf::from(destination))
+                PathBuf::from(destination)
+            };
+            // If the reference tag is `latest` and the URL does not contain `:latest`
+            // we need to add it to the destination
+            if url.scheme() == "registry"
+                && build_fully_resolved_reference(url.as_str())
+                    .is_ok_and(|reference| reference.tag() == Some("latest"))
+                && !url.as_str().ends_with(":latest")
+            {
+                path = PathBuf::from(path.to_string_lossy().to_string() + ":latest");
             }
+            Destination::LocalFile(path)
         }
-    })
+    }
 }
 
 // Helper function, takes the URL of the policy and allocates the
 // right struct to interact with it
 #[allow(clippy::box_default)]
-fn url_fetcher(scheme: &str) -> StoreResult<Box<dyn PolicyFetcher + Send>> {
+fn url_fetcher(scheme: &str) -> StoreResult<Box<dyn PolicyFetcher + Send + Sync>> {
     match scheme {
         "http" | "https" => Ok(Box::new(Https::default())),
         "registry" => Ok(Box::new(Registry::new())),
@@ -232,10 +516,15 @@ pub(crate) fn host_and_port(url: &Url) -> std::result::Result<String, errors::In
 // https://webassembly.github.io/spec/core/bikeshed/#binary-magic
 const WASM_MAGIC_NUMBER: [u8; 4] = [0x00, 0x61, 0x73, 0x6D];
 
-fn create_file_if_valid(bytes: &[u8], destination: &Path, url: String) -> FetcherResult<Policy> {
+fn validate_wasm_file(bytes: &[u8]) -> FetcherResult<()> {
     if !bytes.starts_with(&WASM_MAGIC_NUMBER) {
         return Err(FetcherError::InvalidWasmFileError);
     };
+    Ok(())
+}
+
+fn create_file_if_valid(bytes: &[u8], destination: &Path, url: String) -> FetcherResult<Policy> {
+    validate_wasm_file(bytes)?;
     fs::write(destination, bytes).map_err(|e| {
         FetcherError::CannotWriteWasmModuleFile(destination.to_string_lossy().to_string(), e)
     })?;
@@ -268,10 +557,6 @@ mod tests {
         })
     }
 
-    fn store_path(path: &str) -> PathBuf {
-        Store::default().root.join(store::path::encode_path(path))
-    }
-
     struct UrlParseDetails {
         scheme: String,
         host: Option<String>,
@@ -408,9 +693,8 @@ mod tests {
             pull_destination(
                 &Url::parse("https://host.example.com:1234/path/to/policy.wasm").unwrap(),
                 &PullDestination::LocalFile(std::env::current_dir().unwrap()),
-            )
-            .expect("pull_destination failed"),
-            (None, std::env::current_dir().unwrap().join("policy.wasm"),),
+            ),
+            Destination::LocalFile(std::env::current_dir().unwrap().join("policy.wasm")),
         );
     }
 
@@ -422,114 +706,91 @@ mod tests {
                 &PullDestination::LocalFile(
                     std::env::current_dir().unwrap().join("named-policy.wasm")
                 ),
-            )
-            .expect("pull_destination failed"),
-            (
-                None,
-                std::env::current_dir().unwrap().join("named-policy.wasm"),
             ),
+            Destination::LocalFile(std::env::current_dir().unwrap().join("named-policy.wasm")),
         );
     }
 
     #[test]
-    fn store_pull_destination_from_http_with_port() {
+    fn local_file_pull_destination_from_registry_without_tag() {
         assert_eq!(
             pull_destination(
-                &Url::parse("http://host.example.com:1234/path/to/policy.wasm").unwrap(),
-                &PullDestination::MainStore,
-            )
-            .expect("pull_destination failed"),
-            (
-                Some(Store::default()),
-                store_path("http/host.example.com:1234/path/to/policy.wasm"),
+                &Url::parse("registry://host.example.com/path/to/policy").unwrap(),
+                &PullDestination::LocalFile(std::env::current_dir().unwrap()),
             ),
+            Destination::LocalFile(std::env::current_dir().unwrap().join("policy:latest")),
         );
     }
 
-    #[test]
-    fn store_pull_destination_from_http() {
+    #[rstest]
+    #[case::main_store(PullDestination::MainStore, Store::default())]
+    #[case::store(
+        PullDestination::Store(PathBuf::from("/tmp/store")),
+        Store::new(Path::new("/tmp/store"))
+    )]
+    fn store_pull_destination(#[case] destination: PullDestination, #[case] expected: Store) {
         assert_eq!(
             pull_destination(
-                &Url::parse("http://host.example.com/path/to/policy.wasm").unwrap(),
-                &PullDestination::MainStore,
-            )
-            .expect("pull_destination failed"),
-            (
-                Some(Store::default()),
-                store_path("http/host.example.com/path/to/policy.wasm"),
+                &Url::parse("registry://host.example.com:1234/path/to/policy:tag").unwrap(),
+                &destination,
             ),
+            Destination::Store(expected),
         );
     }
 
-    #[test]
-    fn store_pull_destination_from_https() {
-        assert_eq!(
-            pull_destination(
-                &Url::parse("https://host.example.com/path/to/policy.wasm").unwrap(),
-                &PullDestination::MainStore,
-            )
-            .expect("pull_destination failed"),
-            (
-                Some(Store::default()),
-                store_path("https/host.example.com/path/to/policy.wasm"),
-            ),
-        );
+    #[rstest]
+    #[case::registry_with_tag("registry://ghcr.io/kubewarden/policies/psp:v0.1.0", true)]
+    #[case::registry_latest("registry://ghcr.io/kubewarden/policies/psp:latest", false)]
+    #[case::registry_without_tag("registry://ghcr.io/kubewarden/policies/psp", false)]
+    #[case::https("https://example.com/policy.wasm", true)]
+    fn cacheable_policies(#[case] url: &str, #[case] expected: bool) {
+        assert_eq!(is_cacheable(&Url::parse(url).unwrap()).unwrap(), expected);
     }
 
-    #[test]
-    fn store_pull_destination_from_https_with_port() {
-        assert_eq!(
-            pull_destination(
-                &Url::parse("https://host.example.com:1234/path/to/policy.wasm").unwrap(),
-                &PullDestination::MainStore,
-            )
-            .expect("pull_destination failed"),
-            (
-                Some(Store::default()),
-                store_path("https/host.example.com:1234/path/to/policy.wasm"),
-            ),
-        );
+    #[rstest]
+    #[case::if_not_present("IfNotPresent", FetchMode::IfNotPresent)]
+    #[case::always("Always", FetchMode::Always)]
+    #[case::if_digest_changed("IfDigestChanged", FetchMode::IfDigestChanged)]
+    fn parse_fetch_mode(#[case] input: &str, #[case] expected: FetchMode) {
+        let fetch_mode: FetchMode = input.parse().unwrap();
+        assert_eq!(fetch_mode, expected);
+        assert_eq!(fetch_mode.to_string(), input);
     }
 
     #[test]
-    fn store_pull_destination_from_registry() {
-        assert_eq!(
-            pull_destination(
-                &Url::parse("registry://host.example.com/path/to/policy:tag").unwrap(),
-                &PullDestination::MainStore,
-            )
-            .expect("pull_destination failed"),
-            (
-                Some(Store::default()),
-                store_path("registry/host.example.com/path/to/policy:tag"),
-            ),
-        );
-        assert_eq!(
-            pull_destination(
-                &Url::parse("registry://host.example.com/policy:tag").unwrap(),
-                &PullDestination::MainStore,
-            )
-            .expect("pull_destination failed"),
-            (
-                Some(Store::default()),
-                store_path("registry/host.example.com/policy:tag"),
-            ),
-        );
+    fn parse_unknown_fetch_mode() {
+        assert!(matches!(
+            "Never".parse::<FetchMode>(),
+            Err(FetcherError::UnknownFetchModeError(_))
+        ));
     }
 
-    #[test]
-    fn store_pull_destination_from_registry_with_port() {
-        assert_eq!(
-            pull_destination(
-                &Url::parse("registry://host.example.com:1234/path/to/policy:tag").unwrap(),
-                &PullDestination::MainStore,
-            )
-            .expect("pull_destination failed"),
-            (
-                Some(Store::default()),
-                store_path("registry/host.example.com:1234/path/to/policy:tag"),
-            ),
-        );
+    #[rstest]
+    #[case::if_not_present("registry://ghcr.io/kubewarden/policy:v1", FetchMode::IfNotPresent)]
+    #[case::pinned_by_digest(
+        "registry://ghcr.io/kubewarden/policy@sha256:61ef63621fa5be8e422881d96d05edfef810992fbf9468e35d1fa5ae815bd97c",
+        FetchMode::IfDigestChanged
+    )]
+    #[case::https("https://example.com/policy.wasm", FetchMode::IfDigestChanged)]
+    #[tokio::test]
+    async fn fetch_stored_policy(#[case] url: &str, #[case] fetch_mode: FetchMode) {
+        let store_root = tempfile::tempdir().unwrap();
+        let store = Store::new(store_root.path());
+        let stored = store
+            .add_policy(url, &read_fixture(Path::new("simple.wasm")))
+            .unwrap();
+
+        // the stored policy is used without reaching the remote source
+        let policy = fetch_policy_with_mode(
+            url,
+            PullDestination::Store(store_root.path().to_path_buf()),
+            None,
+            fetch_mode,
+        )
+        .await
+        .expect("cannot fetch policy");
+
+        assert_eq!(policy.local_path, stored.local_path);
     }
 
     #[test]
@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+pub type OciLayoutResult<T> = std::result::Result<T, OciLayoutError>;
+
+#[derive(Error, Debug)]
+pub enum OciLayoutError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("{0:?} is not an OCI image layout")]
+    NotAnOciLayoutError(std::path::PathBuf),
+    #[error("unsupported OCI image layout version: {0}")]
+    UnsupportedLayoutVersionError(String),
+    #[error("cannot parse {0}: {1}")]
+    InvalidJsonError(String, #[source] serde_json::Error),
+    #[error("invalid digest: {0}")]
+    InvalidDigestError(String),
+    #[error("integrity check of blob {expected} failed, got {actual}")]
+    IntegrityError { expected: String, actual: String },
+    #[error("blob {0} is referenced by the manifest, but it is missing")]
+    MissingBlobError(String),
+    #[error("unsupported manifest media type: {0}")]
+    UnsupportedManifestError(String),
+}
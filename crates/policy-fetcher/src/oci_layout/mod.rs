@@ -0,0 +1,525 @@
+//! Read and write [OCI image layouts](https://github.com/opencontainers/image-spec/blob/main/image-layout.md).
+//!
+//! An image layout holds OCI artifacts as they are stored inside of a
+//! registry: the manifests are kept byte by byte, hence their digests, and
+//! the Sigstore signatures bound to them, are preserved. This allows policies
+//! to be moved into air-gapped environments without losing their verifiability.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+use crate::oci_layout::errors::{OciLayoutError, OciLayoutResult};
+
+pub mod errors;
+
+/// The file that marks a directory as an OCI image layout
+pub const OCI_LAYOUT_FILE: &str = "oci-layout";
+const INDEX_FILE: &str = "index.json";
+const BLOBS_DIR: &str = "blobs";
+const IMAGE_LAYOUT_VERSION: &str = "1.0.0";
+
+/// The annotation holding the reference of the artifacts listed by the index
+pub const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+pub const IMAGE_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+pub const IMAGE_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+pub const DOCKER_MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+const WASM_LAYER_MEDIA_TYPE: &str = "application/vnd.wasm.content.layer.v1+wasm";
+
+/// The media types of the manifests that can be saved inside of an image layout
+pub const SUPPORTED_MANIFEST_MEDIA_TYPES: [&str; 2] =
+    [IMAGE_MANIFEST_MEDIA_TYPE, DOCKER_MANIFEST_MEDIA_TYPE];
+
+/// Describes the content referenced by an index or a manifest
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct LayoutFile {
+    #[serde(rename = "imageLayoutVersion")]
+    image_layout_version: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageIndex {
+    schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media_type: Option<String>,
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+impl Default for ImageIndex {
+    fn default() -> Self {
+        ImageIndex {
+            schema_version: 2,
+            media_type: Some(IMAGE_INDEX_MEDIA_TYPE.to_string()),
+            manifests: Vec::new(),
+        }
+    }
+}
+
+/// The fields of an image manifest that are needed to find its blobs. All
+/// the other fields are preserved by keeping the original manifest.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageManifest {
+    #[serde(default)]
+    media_type: Option<String>,
+    config: Descriptor,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+impl ImageManifest {
+    fn parse(manifest: &[u8]) -> OciLayoutResult<Self> {
+        let image_manifest: ImageManifest = serde_json::from_slice(manifest)
+            .map_err(|e| OciLayoutError::InvalidJsonError("manifest".to_string(), e))?;
+        match image_manifest.media_type.as_deref() {
+            None => Ok(image_manifest),
+            Some(media_type) if SUPPORTED_MANIFEST_MEDIA_TYPES.contains(&media_type) => {
+                Ok(image_manifest)
+            }
+            Some(media_type) => Err(OciLayoutError::UnsupportedManifestError(
+                media_type.to_string(),
+            )),
+        }
+    }
+
+    fn blobs(&self) -> impl Iterator<Item = &Descriptor> {
+        std::iter::once(&self.config).chain(self.layers.iter())
+    }
+}
+
+/// An OCI artifact: its manifest, together with the blobs referenced by it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Artifact {
+    pub media_type: String,
+    /// The original manifest, its digest is the one of the artifact
+    pub manifest: Vec<u8>,
+    /// The config and the layers, indexed by digest
+    pub blobs: BTreeMap<String, Vec<u8>>,
+}
+
+impl Artifact {
+    /// The digest of the artifact
+    pub fn digest(&self) -> String {
+        sha256_digest(&self.manifest)
+    }
+
+    /// The WebAssembly module of the artifact, when it's a policy
+    pub fn wasm_module(&self) -> OciLayoutResult<Option<&[u8]>> {
+        let image_manifest = ImageManifest::parse(&self.manifest)?;
+        image_manifest
+            .layers
+            .iter()
+            .find(|layer| layer.media_type == WASM_LAYER_MEDIA_TYPE)
+            .map(|layer| {
+                self.blobs
+                    .get(&layer.digest)
+                    .map(Vec::as_slice)
+                    .ok_or_else(|| OciLayoutError::MissingBlobError(layer.digest.clone()))
+            })
+            .transpose()
+    }
+}
+
+/// The descriptors of the blobs referenced by the given manifest: its config and its layers
+pub fn referenced_blobs(manifest: &[u8]) -> OciLayoutResult<Vec<Descriptor>> {
+    Ok(ImageManifest::parse(manifest)?.blobs().cloned().collect())
+}
+
+/// The media type of the given manifest
+pub fn manifest_media_type(manifest: &[u8]) -> OciLayoutResult<String> {
+    Ok(ImageManifest::parse(manifest)?
+        .media_type
+        .unwrap_or_else(|| IMAGE_MANIFEST_MEDIA_TYPE.to_string()))
+}
+
+/// An OCI image layout stored inside of a directory
+#[derive(Debug)]
+pub struct OciLayout {
+    root: PathBuf,
+}
+
+impl OciLayout {
+    /// Creates an empty image layout inside of the given directory, or opens
+    /// the one that is already there
+    pub fn create(root: &Path) -> OciLayoutResult<Self> {
+        std::fs::create_dir_all(root.join(BLOBS_DIR))?;
+        if !root.join(OCI_LAYOUT_FILE).exists() {
+            write_json(
+                &root.join(OCI_LAYOUT_FILE),
+                &LayoutFile {
+                    image_layout_version: IMAGE_LAYOUT_VERSION.to_string(),
+                },
+            )?;
+        }
+        let layout = OciLayout::open(root)?;
+        if !root.join(INDEX_FILE).exists() {
+            layout.save_index(&ImageIndex::default())?;
+        }
+        Ok(layout)
+    }
+
+    /// Opens the image layout stored inside of the given directory
+    pub fn open(root: &Path) -> OciLayoutResult<Self> {
+        let layout_file: LayoutFile = read_json(&root.join(OCI_LAYOUT_FILE))
+            .map_err(|_| OciLayoutError::NotAnOciLayoutError(root.to_path_buf()))?;
+        if layout_file.image_layout_version != IMAGE_LAYOUT_VERSION {
+            return Err(OciLayoutError::UnsupportedLayoutVersionError(
+                layout_file.image_layout_version,
+            ));
+        }
+        Ok(OciLayout {
+            root: root.to_path_buf(),
+        })
+    }
+
+    /// Whether the given directory holds an image layout
+    pub fn is_oci_layout(root: &Path) -> bool {
+        root.join(OCI_LAYOUT_FILE).is_file()
+    }
+
+    /// Adds the artifact to the layout, the index refers to it using the given
+    /// reference. An artifact already referenced by the same name is replaced.
+    pub fn add_artifact(&self, artifact: &Artifact, ref_name: &str) -> OciLayoutResult<()> {
+        for descriptor in referenced_blobs(&artifact.manifest)? {
+            let blob = artifact
+                .blobs
+                .get(&descriptor.digest)
+                .ok_or_else(|| OciLayoutError::MissingBlobError(descriptor.digest.clone()))?;
+            self.write_blob(&descriptor.digest, blob)?;
+        }
+        let digest = artifact.digest();
+        self.write_blob(&digest, &artifact.manifest)?;
+
+        let mut index = self.load_index()?;
+        index.manifests.retain(|descriptor| {
+            descriptor
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(REF_NAME_ANNOTATION))
+                .is_none_or(|name| name != ref_name)
+        });
+        index.manifests.push(Descriptor {
+            media_type: artifact.media_type.clone(),
+            digest,
+            size: artifact.manifest.len() as u64,
+            annotations: Some(BTreeMap::from([(
+                REF_NAME_ANNOTATION.to_string(),
+                ref_name.to_string(),
+            )])),
+        });
+        self.save_index(&index)
+    }
+
+    /// The artifacts listed by the index, together with their reference. The
+    /// integrity of the manifests and of their blobs is checked while reading them.
+    pub fn artifacts(&self) -> OciLayoutResult<Vec<(String, Artifact)>> {
+        let mut artifacts = Vec::new();
+        for descriptor in self.load_index()?.manifests {
+            let Some(ref_name) = descriptor
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(REF_NAME_ANNOTATION))
+            else {
+                warn!(
+                    digest = descriptor.digest,
+                    "skipping artifact without a reference"
+                );
+                continue;
+            };
+            if !SUPPORTED_MANIFEST_MEDIA_TYPES.contains(&descriptor.media_type.as_str()) {
+                warn!(
+                    ref_name,
+                    media_type = descriptor.media_type,
+                    "skipping artifact with unsupported media type"
+                );
+                continue;
+            }
+
+            artifacts.push((ref_name.to_owned(), self.read_artifact(&descriptor)?));
+        }
+        Ok(artifacts)
+    }
+
+    /// The artifact referenced by the given name, if any. Its integrity is
+    /// checked like it's done by [`OciLayout::artifacts`].
+    pub fn artifact(&self, ref_name: &str) -> OciLayoutResult<Option<Artifact>> {
+        self.load_index()?
+            .manifests
+            .into_iter()
+            .find(|descriptor| {
+                descriptor
+                    .annotations
+                    .as_ref()
+                    .and_then(|annotations| annotations.get(REF_NAME_ANNOTATION))
+                    .is_some_and(|name| name == ref_name)
+            })
+            .map(|descriptor| {
+                if !SUPPORTED_MANIFEST_MEDIA_TYPES.contains(&descriptor.media_type.as_str()) {
+                    return Err(OciLayoutError::UnsupportedManifestError(
+                        descriptor.media_type,
+                    ));
+                }
+                self.read_artifact(&descriptor)
+            })
+            .transpose()
+    }
+
+    fn read_artifact(&self, descriptor: &Descriptor) -> OciLayoutResult<Artifact> {
+        let manifest = self.read_blob(&descriptor.digest)?;
+        let blobs = referenced_blobs(&manifest)?
+            .into_iter()
+            .map(|blob| Ok((blob.digest.clone(), self.read_blob(&blob.digest)?)))
+            .collect::<OciLayoutResult<BTreeMap<_, _>>>()?;
+        Ok(Artifact {
+            media_type: descriptor.media_type.clone(),
+            manifest,
+            blobs,
+        })
+    }
+
+    fn blob_path(&self, digest: &str) -> OciLayoutResult<PathBuf> {
+        // the digest comes from files that could have been crafted, it must
+        // not be able to point outside of the layout
+        match digest.split_once(':') {
+            Some(("sha256", hex))
+                if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()) =>
+            {
+                Ok(self.root.join(BLOBS_DIR).join("sha256").join(hex))
+            }
+            _ => Err(OciLayoutError::InvalidDigestError(digest.to_string())),
+        }
+    }
+
+    fn write_blob(&self, digest: &str, contents: &[u8]) -> OciLayoutResult<()> {
+        let actual = sha256_digest(contents);
+        if actual != digest {
+            return Err(OciLayoutError::IntegrityError {
+                expected: digest.to_string(),
+                actual,
+            });
+        }
+        let path = self.blob_path(digest)?;
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    fn read_blob(&self, digest: &str) -> OciLayoutResult<Vec<u8>> {
+        let path = self.blob_path(digest)?;
+        if !path.exists() {
+            return Err(OciLayoutError::MissingBlobError(digest.to_string()));
+        }
+        let contents = std::fs::read(path)?;
+        let actual = sha256_digest(&contents);
+        if actual != digest {
+            return Err(OciLayoutError::IntegrityError {
+                expected: digest.to_string(),
+                actual,
+            });
+        }
+        Ok(contents)
+    }
+
+    fn load_index(&self) -> OciLayoutResult<ImageIndex> {
+        read_json(&self.root.join(INDEX_FILE))
+    }
+
+    fn save_index(&self, index: &ImageIndex) -> OciLayoutResult<()> {
+        write_json(&self.root.join(INDEX_FILE), index)
+    }
+}
+
+fn sha256_digest(contents: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(contents)))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> OciLayoutResult<T> {
+    serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|e| OciLayoutError::InvalidJsonError(path.to_string_lossy().to_string(), e))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> OciLayoutResult<()> {
+    let contents = serde_json::to_vec_pretty(value)
+        .map_err(|e| OciLayoutError::InvalidJsonError(path.to_string_lossy().to_string(), e))?;
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    fn artifact(module: &[u8]) -> Artifact {
+        let config = b"{}".to_vec();
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": IMAGE_MANIFEST_MEDIA_TYPE,
+            "config": {
+                "mediaType": "application/vnd.wasm.config.v1+json",
+                "digest": sha256_digest(&config),
+                "size": config.len(),
+            },
+            "layers": [{
+                "mediaType": WASM_LAYER_MEDIA_TYPE,
+                "digest": sha256_digest(module),
+                "size": module.len(),
+            }],
+            "annotations": {"io.kubewarden.policy.title": "test"},
+        });
+        Artifact {
+            media_type: IMAGE_MANIFEST_MEDIA_TYPE.to_string(),
+            manifest: serde_json::to_vec(&manifest).unwrap(),
+            blobs: BTreeMap::from([
+                (sha256_digest(&config), config),
+                (sha256_digest(module), module.to_vec()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn add_and_read_artifacts() {
+        let root = tempdir().unwrap();
+        let layout = OciLayout::create(root.path()).unwrap();
+        let policy = artifact(b"\0asm-v1");
+        let other_policy = artifact(b"\0asm-v2");
+
+        layout
+            .add_artifact(&policy, "ghcr.io/kubewarden/policy:v1")
+            .unwrap();
+        layout
+            .add_artifact(&policy, "ghcr.io/kubewarden/policy:latest")
+            .unwrap();
+        // replaces the artifact referenced by the same name
+        layout
+            .add_artifact(&other_policy, "ghcr.io/kubewarden/policy:latest")
+            .unwrap();
+
+        let layout = OciLayout::open(root.path()).unwrap();
+        let artifacts = layout.artifacts().unwrap();
+        assert_eq!(
+            artifacts,
+            vec![
+                ("ghcr.io/kubewarden/policy:v1".to_string(), policy.clone()),
+                (
+                    "ghcr.io/kubewarden/policy:latest".to_string(),
+                    other_policy.clone()
+                ),
+            ]
+        );
+        assert_eq!(
+            layout.artifact("ghcr.io/kubewarden/policy:latest").unwrap(),
+            Some(other_policy)
+        );
+        assert_eq!(
+            layout.artifact("ghcr.io/kubewarden/policy:v2").unwrap(),
+            None
+        );
+        assert_eq!(
+            artifacts[0].1.wasm_module().unwrap(),
+            Some(&b"\0asm-v1"[..])
+        );
+    }
+
+    #[test]
+    fn open_directory_without_layout() {
+        let root = tempdir().unwrap();
+
+        assert!(!OciLayout::is_oci_layout(root.path()));
+        assert!(matches!(
+            OciLayout::open(root.path()),
+            Err(OciLayoutError::NotAnOciLayoutError(_))
+        ));
+    }
+
+    #[test]
+    fn tampered_blob() {
+        let root = tempdir().unwrap();
+        let layout = OciLayout::create(root.path()).unwrap();
+        let policy = artifact(b"\0asm-v1");
+        layout
+            .add_artifact(&policy, "ghcr.io/kubewarden/policy:v1")
+            .unwrap();
+        std::fs::write(
+            layout.blob_path(&sha256_digest(b"\0asm-v1")).unwrap(),
+            b"tampered",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            layout.artifacts(),
+            Err(OciLayoutError::IntegrityError { .. })
+        ));
+    }
+
+    #[test]
+    fn artifact_without_wasm_module() {
+        let mut signature = artifact(b"signature");
+        signature.manifest = String::from_utf8(signature.manifest)
+            .unwrap()
+            .replace(
+                WASM_LAYER_MEDIA_TYPE,
+                "application/vnd.dev.cosign.simplesigning.v1+json",
+            )
+            .into_bytes();
+
+        assert_eq!(signature.wasm_module().unwrap(), None);
+    }
+
+    #[rstest]
+    #[case::valid(
+        "sha256:93a44bbb96c751218e4c00d479e4c14358122a389acca16205b1e4d0dc5f9476",
+        true
+    )]
+    #[case::path_traversal("sha256:../../../etc/passwd", false)]
+    #[case::unknown_algorithm("md5:d41d8cd98f00b204e9800998ecf8427e", false)]
+    #[case::missing_algorithm(
+        "93a44bbb96c751218e4c00d479e4c14358122a389acca16205b1e4d0dc5f9476",
+        false
+    )]
+    fn blob_path(#[case] digest: &str, #[case] valid: bool) {
+        let layout = OciLayout {
+            root: PathBuf::from("/layout"),
+        };
+
+        assert_eq!(layout.blob_path(digest).is_ok(), valid);
+    }
+
+    #[test]
+    fn unsupported_manifest() {
+        let index = serde_json::to_vec(&ImageIndex::default()).unwrap();
+
+        assert!(matches!(
+            referenced_blobs(&index),
+            Err(OciLayoutError::InvalidJsonError(..))
+        ));
+        let index_with_config =
+            br#"{"mediaType": "application/vnd.oci.image.index.v1+json", "config": {"mediaType": "a", "digest": "b", "size": 1}}"#;
+        assert!(matches!(
+            referenced_blobs(index_with_config),
+            Err(OciLayoutError::UnsupportedManifestError(_))
+        ));
+    }
+}
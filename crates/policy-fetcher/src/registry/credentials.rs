@@ -0,0 +1,686 @@
+//! Providers of the credentials used to interact with OCI registries.
+//!
+//! The credentials of a registry are looked up using these providers, in order:
+//! * the [`CredentialProvider`]s added to [`Sources::credential_providers`]
+//! * the `registry_credentials` declared inside of the sources file
+//! * the docker configuration file, including its `credHelpers` and `credsStore`
+//!
+//! Anonymous access is used when none of them has credentials for the registry.
+//!
+//! Identity tokens are OAuth2 refresh tokens: they are exchanged for an access
+//! token scoped to the repository being accessed, using the token server
+//! advertised by the registry.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    process::Stdio,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use docker_credential::DockerCredential;
+use oci_client::{Reference, secrets::RegistryAuth};
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::{
+    registry::errors::{CredentialsError, CredentialsResult},
+    sources::{RegistryCredentials, SourceResult, Sources},
+};
+
+/// The client id sent to the token server when exchanging identity tokens
+const OAUTH2_CLIENT_ID: &str = "kubewarden";
+
+/// How long an access token is considered valid when the token server
+/// doesn't tell that
+const DEFAULT_ACCESS_TOKEN_TTL: Duration = Duration::from_secs(60);
+
+/// How long a credential helper can take to return the credentials. This
+/// includes the credential helpers referenced by the docker configuration
+const CREDENTIAL_HELPER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The credentials of a registry, as returned by a [`CredentialProvider`]
+#[derive(Clone, PartialEq, Eq)]
+pub enum Credential {
+    /// Username and password
+    Basic(String, String),
+    /// A bearer token, sent as it is to the registry
+    Bearer(String),
+    /// An OAuth2 refresh token, exchanged for an access token before being used
+    IdentityToken(String),
+}
+
+impl fmt::Debug for Credential {
+    // Do not leak secrets into the logs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credential::Basic(username, _) => write!(f, "Basic({username}, <redacted>)"),
+            Credential::Bearer(_) => write!(f, "Bearer(<redacted>)"),
+            Credential::IdentityToken(_) => write!(f, "IdentityToken(<redacted>)"),
+        }
+    }
+}
+
+/// The operation the credentials are going to be used for. This defines the
+/// scope of the access tokens obtained by exchanging an identity token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegistryAction {
+    Pull,
+    Push,
+}
+
+impl RegistryAction {
+    fn scope_actions(&self) -> &'static str {
+        match self {
+            RegistryAction::Pull => "pull",
+            RegistryAction::Push => "pull,push",
+        }
+    }
+}
+
+/// A provider of registry credentials
+#[async_trait]
+pub trait CredentialProvider: fmt::Debug + Send + Sync {
+    /// Returns the credentials to be used against the given registry, `None`
+    /// when the provider doesn't have credentials for it.
+    ///
+    /// `registry` is the host of the registry, followed by the port when this
+    /// is not the default one. For example: `ghcr.io`, `localhost:5000`.
+    async fn credentials(&self, registry: &str) -> CredentialsResult<Option<Credential>>;
+}
+
+/// Provides the credentials declared inside of the sources file
+#[derive(Debug)]
+struct SourcesCredentialProvider<'a>(&'a HashMap<String, RegistryCredentials>);
+
+#[async_trait]
+impl CredentialProvider for SourcesCredentialProvider<'_> {
+    async fn credentials(&self, registry: &str) -> CredentialsResult<Option<Credential>> {
+        let Some(registry_credentials) = self.0.get(registry) else {
+            return Ok(None);
+        };
+
+        match registry_credentials {
+            RegistryCredentials::Basic { username, password } => Ok(Some(Credential::Basic(
+                username.to_owned(),
+                password.to_owned(),
+            ))),
+            RegistryCredentials::BearerTokenFile { bearer_token_file } => {
+                let token = tokio::fs::read_to_string(bearer_token_file)
+                    .await
+                    .map_err(|e| {
+                        CredentialsError::CannotReadTokenFileError(bearer_token_file.to_owned(), e)
+                    })?;
+                let token = token.trim();
+                if token.is_empty() {
+                    return Err(CredentialsError::EmptyTokenFileError(
+                        bearer_token_file.to_owned(),
+                    ));
+                }
+                Ok(Some(Credential::Bearer(token.to_owned())))
+            }
+            RegistryCredentials::IdentityToken { identity_token } => {
+                Ok(Some(Credential::IdentityToken(identity_token.to_owned())))
+            }
+            RegistryCredentials::CredentialHelper { credential_helper } => {
+                CredentialHelper::new(credential_helper)
+                    .credentials(registry)
+                    .await
+            }
+        }
+    }
+}
+
+/// Provides the credentials stored inside of the docker configuration file,
+/// which can point to credential helpers via `credHelpers` and `credsStore`
+#[derive(Debug, Default)]
+pub struct DockerConfigCredentialProvider {}
+
+#[async_trait]
+impl CredentialProvider for DockerConfigCredentialProvider {
+    async fn credentials(&self, registry: &str) -> CredentialsResult<Option<Credential>> {
+        // The lookup reads the docker configuration file and can run a
+        // credential helper, both are blocking operations
+        let lookup = tokio::task::spawn_blocking({
+            let registry = registry.to_owned();
+            move || docker_credential::get_credential(&registry)
+        });
+        let result = tokio::time::timeout(CREDENTIAL_HELPER_TIMEOUT, lookup)
+            .await
+            .map_err(|_| {
+                CredentialsError::DockerConfigError(format!(
+                    "timed out after {CREDENTIAL_HELPER_TIMEOUT:?}"
+                ))
+            })?
+            .map_err(|e| CredentialsError::DockerConfigError(e.to_string()))?;
+
+        match result {
+            Ok(DockerCredential::UsernamePassword(username, password)) => {
+                Ok(Some(Credential::Basic(username, password)))
+            }
+            Ok(DockerCredential::IdentityToken(token)) => {
+                Ok(Some(Credential::IdentityToken(token)))
+            }
+            Err(error) => {
+                debug!(?error, %registry, "no credentials found inside of the docker configuration");
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Provides the credentials returned by a docker credential helper, the
+/// `docker-credential-<name>` executable
+#[derive(Debug)]
+pub struct CredentialHelper {
+    name: String,
+}
+
+/// The output of the `get` command of a docker credential helper
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CredentialHelperOutput {
+    username: String,
+    secret: String,
+}
+
+impl CredentialHelper {
+    pub fn new(name: &str) -> Self {
+        CredentialHelper {
+            name: name.to_owned(),
+        }
+    }
+
+    fn executable(&self) -> String {
+        format!("docker-credential-{}", self.name)
+    }
+}
+
+/// Parse the output of a credential helper. By convention, identity tokens
+/// are returned with `<token>` as username.
+fn parse_credential_helper_output(output: &[u8]) -> serde_json::Result<Credential> {
+    let output: CredentialHelperOutput = serde_json::from_slice(output)?;
+    Ok(if output.username == "<token>" {
+        Credential::IdentityToken(output.secret)
+    } else {
+        Credential::Basic(output.username, output.secret)
+    })
+}
+
+#[async_trait]
+impl CredentialProvider for CredentialHelper {
+    async fn credentials(&self, registry: &str) -> CredentialsResult<Option<Credential>> {
+        let executable = self.executable();
+        let helper_error =
+            |msg: String| CredentialsError::CredentialHelperError(executable.clone(), msg);
+
+        let mut child = Command::new(&executable)
+            .arg("get")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // the helper is killed when it times out
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| helper_error(e.to_string()))?;
+        let run = async {
+            // stdin is closed once the registry is written, helpers wait for it
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(registry.as_bytes())
+                .await?;
+            child.wait_with_output().await
+        };
+        let output = tokio::time::timeout(CREDENTIAL_HELPER_TIMEOUT, run)
+            .await
+            .map_err(|_| helper_error(format!("timed out after {CREDENTIAL_HELPER_TIMEOUT:?}")))?
+            .map_err(|e| helper_error(e.to_string()))?;
+
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // helpers report missing credentials on stdout
+            if stdout.contains("credentials not found") {
+                return Ok(None);
+            }
+            return Err(helper_error(format!(
+                "{}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        parse_credential_helper_output(&output.stdout)
+            .map(Some)
+            .map_err(|e| helper_error(format!("cannot parse output: {e}")))
+    }
+}
+
+/// Find the credentials of the given registry, by querying the providers
+/// in order
+async fn find_credentials(
+    registry: &str,
+    sources: &Sources,
+) -> CredentialsResult<Option<Credential>> {
+    let sources_provider = SourcesCredentialProvider(&sources.registry_credentials);
+    let docker_config_provider = DockerConfigCredentialProvider::default();
+
+    let providers = sources
+        .credential_providers
+        .iter()
+        .map(|provider| provider.as_ref())
+        .chain([
+            &sources_provider as &dyn CredentialProvider,
+            &docker_config_provider as &dyn CredentialProvider,
+        ]);
+
+    for provider in providers {
+        if let Some(credential) = provider.credentials(registry).await? {
+            debug!(%registry, ?provider, "registry credentials found");
+            return Ok(Some(credential));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the authentication to be used to perform the given action against
+/// the referenced repository. Anonymous access is used when no credentials are
+/// found, or when the credentials cannot be obtained.
+pub(crate) async fn registry_auth(
+    reference: &Reference,
+    action: RegistryAction,
+    sources: &Sources,
+) -> RegistryAuth {
+    let registry = reference.registry();
+    let credential = match find_credentials(registry, sources).await {
+        Ok(Some(credential)) => credential,
+        Ok(None) => {
+            debug!(%registry, "no credentials found. Using anonymous instead");
+            return RegistryAuth::Anonymous;
+        }
+        Err(error) => {
+            warn!(%error, %registry, "cannot obtain credentials. Using anonymous instead");
+            return RegistryAuth::Anonymous;
+        }
+    };
+
+    match credential {
+        Credential::Basic(username, password) => RegistryAuth::Basic(username, password),
+        Credential::Bearer(token) => RegistryAuth::Bearer(token),
+        Credential::IdentityToken(refresh_token) => {
+            match access_token(reference, action, &refresh_token, sources).await {
+                Ok(token) => RegistryAuth::Bearer(token),
+                Err(error) => {
+                    warn!(%error, %registry, "cannot exchange identity token. Using anonymous instead");
+                    RegistryAuth::Anonymous
+                }
+            }
+        }
+    }
+}
+
+type AccessTokenCacheKey = (String, String, RegistryAction, String);
+
+/// The access tokens obtained by exchanging identity tokens, with their expiration time
+static ACCESS_TOKENS: LazyLock<Mutex<HashMap<AccessTokenCacheKey, (Instant, String)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The `Bearer` challenge returned by a registry that uses token authentication
+#[derive(Debug, PartialEq, Eq)]
+struct BearerChallenge {
+    realm: String,
+    service: Option<String>,
+}
+
+/// Parse a `WWW-Authenticate` header like:
+/// `Bearer realm="https://auth.docker.io/token",service="registry.docker.io"`
+fn parse_bearer_challenge(header: &str) -> Option<BearerChallenge> {
+    let (scheme, params) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut realm = None;
+    let mut service = None;
+    for param in params.split(',') {
+        let Some((key, value)) = param.trim().split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_owned();
+        match key.trim() {
+            "realm" => realm = Some(value),
+            "service" => service = Some(value),
+            _ => {}
+        }
+    }
+
+    Some(BearerChallenge {
+        realm: realm?,
+        service,
+    })
+}
+
+/// The response of the token server. Some servers use `token` instead of `access_token`.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+impl TokenResponse {
+    fn into_token(self) -> Option<(String, Duration)> {
+        let ttl = self
+            .expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_ACCESS_TOKEN_TTL);
+        self.access_token.or(self.token).map(|token| (token, ttl))
+    }
+}
+
+/// The client used to talk with the given host, either the registry or its
+/// token server. Only the certificate authorities of the host are trusted,
+/// on top of the system ones
+fn http_client(host: &str, sources: &Sources) -> CredentialsResult<reqwest::Client> {
+    let mut client_builder = reqwest::Client::builder()
+        .no_proxy()
+        .danger_accept_invalid_certs(sources.is_insecure_source(host));
+
+    if let Some(certificates) = sources.source_authority(host) {
+        let certificates = certificates
+            .iter()
+            .map(|cert| cert.try_into())
+            .collect::<SourceResult<Vec<reqwest::Certificate>>>()?;
+        client_builder = client_builder.tls_certs_merge(certificates);
+    }
+
+    let proxies = sources.proxies();
+    let no_proxy = proxies
+        .no_proxy
+        .as_deref()
+        .and_then(reqwest::NoProxy::from_string);
+    if let Some(proxy_url) = &proxies.https_proxy {
+        client_builder =
+            client_builder.proxy(reqwest::Proxy::https(proxy_url)?.no_proxy(no_proxy.clone()));
+    }
+    if let Some(proxy_url) = &proxies.http_proxy {
+        client_builder = client_builder.proxy(reqwest::Proxy::http(proxy_url)?.no_proxy(no_proxy));
+    }
+
+    Ok(client_builder.build()?)
+}
+
+/// Exchange the identity token for an access token scoped to the repository,
+/// using the OAuth2 refresh token grant.
+/// See https://distribution.github.io/distribution/spec/auth/oauth/
+async fn access_token(
+    reference: &Reference,
+    action: RegistryAction,
+    refresh_token: &str,
+    sources: &Sources,
+) -> CredentialsResult<String> {
+    let registry = reference.registry();
+    let cache_key = (
+        registry.to_owned(),
+        reference.repository().to_owned(),
+        action,
+        refresh_token.to_owned(),
+    );
+    if let Some((expires_at, token)) = ACCESS_TOKENS
+        .lock()
+        .expect("cannot acquire access tokens lock")
+        .get(&cache_key)
+        && *expires_at > Instant::now()
+    {
+        return Ok(token.to_owned());
+    }
+
+    let exchange_error =
+        |msg: String| CredentialsError::TokenExchangeError(registry.to_owned(), msg);
+    // Ping the registry to find its token server
+    let response = http_client(registry, sources)?
+        .get(format!("https://{}/v2/", reference.resolve_registry()))
+        .send()
+        .await?;
+    let challenge = response
+        .headers()
+        .get(reqwest::header::WWW_AUTHENTICATE)
+        .and_then(|header| header.to_str().ok())
+        .and_then(parse_bearer_challenge)
+        .ok_or_else(|| {
+            exchange_error("the registry doesn't support token authentication".to_owned())
+        })?;
+
+    let scope = format!(
+        "repository:{}:{}",
+        reference.repository(),
+        action.scope_actions()
+    );
+    let mut form = vec![
+        ("grant_type", "refresh_token"),
+        ("client_id", OAUTH2_CLIENT_ID),
+        ("refresh_token", refresh_token),
+        ("scope", scope.as_str()),
+    ];
+    if let Some(service) = challenge.service.as_deref() {
+        form.push(("service", service));
+    }
+
+    // The token server is usually hosted somewhere else
+    let realm = Url::parse(&challenge.realm)
+        .map_err(|e| exchange_error(format!("invalid token server {}: {e}", challenge.realm)))?;
+    let realm_host = match (realm.host_str(), realm.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => {
+            return Err(exchange_error(format!(
+                "invalid token server {}",
+                challenge.realm
+            )));
+        }
+    };
+    let response = http_client(&realm_host, sources)?
+        .post(realm)
+        .form(&form)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(exchange_error(format!(
+            "the token server replied with {}",
+            response.status()
+        )));
+    }
+    let (token, ttl) = response
+        .json::<TokenResponse>()
+        .await?
+        .into_token()
+        .ok_or_else(|| {
+            exchange_error("the token server didn't return an access token".to_owned())
+        })?;
+
+    ACCESS_TOKENS
+        .lock()
+        .expect("cannot acquire access tokens lock")
+        .insert(cache_key, (Instant::now() + ttl, token.clone()));
+
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::{io::Write, path::PathBuf, sync::Arc};
+    use tempfile::NamedTempFile;
+
+    #[derive(Debug)]
+    struct StaticProvider(Option<Credential>);
+
+    #[async_trait]
+    impl CredentialProvider for StaticProvider {
+        async fn credentials(&self, _registry: &str) -> CredentialsResult<Option<Credential>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn sources_with_credentials(
+        registry: &str,
+        registry_credentials: RegistryCredentials,
+    ) -> Sources {
+        Sources {
+            registry_credentials: HashMap::from([(registry.to_owned(), registry_credentials)]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn sources_basic_credentials() {
+        let sources = sources_with_credentials(
+            "registry.corp",
+            RegistryCredentials::Basic {
+                username: "robot".to_owned(),
+                password: "s3cr3t".to_owned(),
+            },
+        );
+        let reference = Reference::try_from("registry.corp/policies/psp:v1").unwrap();
+
+        let auth = registry_auth(&reference, RegistryAction::Pull, &sources).await;
+
+        assert_eq!(
+            auth,
+            RegistryAuth::Basic("robot".to_owned(), "s3cr3t".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn bearer_token_file_is_read_every_time() {
+        let mut token_file = NamedTempFile::new().unwrap();
+        writeln!(token_file, "token-1").unwrap();
+        let sources = sources_with_credentials(
+            "registry.corp",
+            RegistryCredentials::BearerTokenFile {
+                bearer_token_file: token_file.path().to_path_buf(),
+            },
+        );
+        let provider = SourcesCredentialProvider(&sources.registry_credentials);
+
+        let first = provider.credentials("registry.corp").await.unwrap();
+        std::fs::write(token_file.path(), "token-2").unwrap();
+        let second = provider.credentials("registry.corp").await.unwrap();
+
+        assert_eq!(first, Some(Credential::Bearer("token-1".to_owned())));
+        assert_eq!(second, Some(Credential::Bearer("token-2".to_owned())));
+    }
+
+    #[rstest]
+    #[case::missing_file(PathBuf::from("/does/not/exist"))]
+    #[case::empty_file(NamedTempFile::new().unwrap().into_temp_path().keep().unwrap())]
+    #[tokio::test]
+    async fn invalid_bearer_token_file(#[case] bearer_token_file: PathBuf) {
+        let sources = sources_with_credentials(
+            "registry.corp",
+            RegistryCredentials::BearerTokenFile { bearer_token_file },
+        );
+        let provider = SourcesCredentialProvider(&sources.registry_credentials);
+
+        let result = provider.credentials("registry.corp").await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn sources_provider_ignores_other_registries() {
+        let sources = sources_with_credentials(
+            "registry.corp",
+            RegistryCredentials::IdentityToken {
+                identity_token: "refresh-token".to_owned(),
+            },
+        );
+        let provider = SourcesCredentialProvider(&sources.registry_credentials);
+
+        assert_eq!(provider.credentials("ghcr.io").await.unwrap(), None);
+        assert_eq!(
+            provider.credentials("registry.corp").await.unwrap(),
+            Some(Credential::IdentityToken("refresh-token".to_owned()))
+        );
+    }
+
+    #[rstest]
+    #[case::provider_without_credentials(None, Credential::Basic("robot".to_owned(), "s3cr3t".to_owned()))]
+    #[case::provider_takes_precedence(
+        Some(Credential::Bearer("token".to_owned())),
+        Credential::Bearer("token".to_owned())
+    )]
+    #[tokio::test]
+    async fn custom_providers_are_queried_first(
+        #[case] provided: Option<Credential>,
+        #[case] expected: Credential,
+    ) {
+        let mut sources = sources_with_credentials(
+            "registry.corp",
+            RegistryCredentials::Basic {
+                username: "robot".to_owned(),
+                password: "s3cr3t".to_owned(),
+            },
+        );
+        sources.credential_providers = vec![Arc::new(StaticProvider(provided))];
+
+        let credential = find_credentials("registry.corp", &sources).await.unwrap();
+
+        assert_eq!(credential, Some(expected));
+    }
+
+    #[rstest]
+    #[case::username_password(
+        r#"{"ServerURL":"registry.corp","Username":"robot","Secret":"s3cr3t"}"#,
+        Credential::Basic("robot".to_owned(), "s3cr3t".to_owned())
+    )]
+    #[case::identity_token(
+        r#"{"ServerURL":"registry.corp","Username":"<token>","Secret":"refresh-token"}"#,
+        Credential::IdentityToken("refresh-token".to_owned())
+    )]
+    fn credential_helper_output(#[case] output: &str, #[case] expected: Credential) {
+        let credential = parse_credential_helper_output(output.as_bytes()).unwrap();
+
+        assert_eq!(credential, expected);
+    }
+
+    #[rstest]
+    #[case::with_service(
+        r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io""#,
+        Some(BearerChallenge {
+            realm: "https://auth.docker.io/token".to_owned(),
+            service: Some("registry.docker.io".to_owned()),
+        })
+    )]
+    #[case::without_service(
+        r#"bearer realm="https://registry.corp/oauth2/token""#,
+        Some(BearerChallenge {
+            realm: "https://registry.corp/oauth2/token".to_owned(),
+            service: None,
+        })
+    )]
+    #[case::basic_scheme(r#"Basic realm="registry.corp""#, None)]
+    #[case::missing_realm(r#"Bearer service="registry.corp""#, None)]
+    fn bearer_challenge(#[case] header: &str, #[case] expected: Option<BearerChallenge>) {
+        assert_eq!(parse_bearer_challenge(header), expected);
+    }
+
+    #[rstest]
+    #[case::access_token(r#"{"access_token":"abc","expires_in":300}"#, Some(("abc", 300)))]
+    #[case::token(r#"{"token":"abc"}"#, Some(("abc", 60)))]
+    #[case::no_token(r#"{"expires_in":300}"#, None)]
+    fn token_response(#[case] response: &str, #[case] expected: Option<(&str, u64)>) {
+        let response: TokenResponse = serde_json::from_str(response).unwrap();
+
+        assert_eq!(
+            response.into_token(),
+            expected.map(|(token, ttl)| (token.to_owned(), Duration::from_secs(ttl)))
+        );
+    }
+}
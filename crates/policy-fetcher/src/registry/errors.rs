@@ -1,6 +1,8 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
-use crate::errors::InvalidURLError;
+use crate::{errors::InvalidURLError, sources::SourceError};
 
 pub type RegistryResult<T> = std::result::Result<T, RegistryError>;
 
@@ -20,4 +22,28 @@ pub enum RegistryError {
     InvalidURLError(#[from] InvalidURLError),
     #[error(transparent)]
     JSONParseError(#[from] serde_json::Error),
+    #[error(transparent)]
+    OciLayoutError(#[from] crate::oci_layout::errors::OciLayoutError),
+    #[error("invalid media type: {0}")]
+    InvalidMediaTypeError(String),
+}
+
+pub type CredentialsResult<T> = std::result::Result<T, CredentialsError>;
+
+#[derive(Error, Debug)]
+pub enum CredentialsError {
+    #[error("cannot read bearer token file {0:?}: {1}")]
+    CannotReadTokenFileError(PathBuf, #[source] std::io::Error),
+    #[error("bearer token file {0:?} is empty")]
+    EmptyTokenFileError(PathBuf),
+    #[error("credential helper {0} failed: {1}")]
+    CredentialHelperError(String, String),
+    #[error("cannot read the credentials of the docker configuration: {0}")]
+    DockerConfigError(String),
+    #[error("cannot exchange identity token for registry {0}: {1}")]
+    TokenExchangeError(String, String),
+    #[error(transparent)]
+    SourceError(#[from] SourceError),
+    #[error(transparent)]
+    HttpClientError(#[from] reqwest::Error),
 }
@@ -1,12 +1,11 @@
 use std::{collections::BTreeMap, convert::TryFrom, str::FromStr};
 
 use async_trait::async_trait;
-use docker_credential::DockerCredential;
 use errors::RegistryError;
 use futures::future::BoxFuture;
 use lazy_static::lazy_static;
 use oci_client::{
-    Reference,
+    Reference, RegistryOperation,
     client::{
         Certificate as OciCertificate, CertificateEncoding, Client, ClientConfig,
         ClientProtocol as OciClientProtocol, Config, ImageLayer,
@@ -15,15 +14,18 @@ use oci_client::{
     secrets::RegistryAuth,
 };
 use regex::Regex;
-use tracing::{debug, info, warn};
+use reqwest::header::HeaderValue;
+use tracing::{debug, info};
 use url::Url;
 
 use crate::{
     fetcher::{ClientProtocol, PolicyFetcher, TlsVerificationMode},
-    registry::errors::RegistryResult,
+    oci_layout::{self, Artifact},
+    registry::{credentials::RegistryAction, errors::RegistryResult},
     sources::{Certificate, SourceError, SourceResult, Sources},
 };
 
+pub mod credentials;
 pub mod errors;
 
 lazy_static! {
@@ -150,6 +152,32 @@ where
     operation(client_protocol).await
 }
 
+/// Perform the given operation against the locations of the referenced OCI artifact, as
+/// defined by the registry mirrors of the sources. The next location is tried when the
+/// operation fails, the error of the last attempt is returned when all of them fail.
+async fn try_with_mirrors<'a, F, T>(
+    reference: &Reference,
+    sources: &'a Sources,
+    operation: F,
+) -> RegistryResult<T>
+where
+    F: Fn(Reference) -> BoxFuture<'a, RegistryResult<T>>,
+{
+    let mut last_error = None;
+    for location in sources.mirrored_references(reference) {
+        let location_name = location.whole();
+        match operation(location).await {
+            Ok(result) => return Ok(result),
+            Err(err) => {
+                info!(%err, reference = %reference.whole(), location = %location_name, "operation failed");
+                last_error = Some(err);
+            }
+        }
+    }
+
+    Err(last_error.expect("at least one location is always tried"))
+}
+
 impl Registry {
     pub fn new() -> Registry {
         Registry {}
@@ -159,26 +187,14 @@ impl Registry {
         Client::new(client_config(client_protocol, sources))
     }
 
-    pub fn auth(registry: &str) -> RegistryAuth {
-        match docker_credential::get_credential(registry) {
-            Ok(credential) => match credential {
-                DockerCredential::IdentityToken(_) => {
-                    warn!(%registry, "IdentityToken credential not supported. Using anonymous instead");
-                    RegistryAuth::Anonymous
-                }
-                DockerCredential::UsernamePassword(user_name, password) => {
-                    RegistryAuth::Basic(user_name, password)
-                }
-            },
-            Err(error) => {
-                debug!(
-                    ?error,
-                    %registry,
-                    "Couldn't fetch credentials. Using anonymous instead"
-                );
-                RegistryAuth::Anonymous
-            }
-        }
+    /// The authentication to be used to perform the given action against the
+    /// referenced repository. See [`credentials`] for how the credentials are found.
+    pub async fn auth(
+        reference: &Reference,
+        action: RegistryAction,
+        sources: &Sources,
+    ) -> RegistryAuth {
+        credentials::registry_auth(reference, action, sources).await
     }
 
     /// Fetch the manifest of the OCI object referenced by the given url.
@@ -191,21 +207,26 @@ impl Registry {
         // ensure it contains also the registry. Example: `busybox` ->
         // `docker.io/library/busybox:latest`
         let reference = build_fully_resolved_reference(url)?;
-        let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
-        let registry_auth = Registry::auth(reference.registry());
         let sources: Sources = sources.cloned().unwrap_or_default();
-
-        let (oci_manifest, _) = try_with_protocols(&url, &sources, |client_protocol| {
-            let sources_clone = sources.clone();
-            Box::pin({
-                let reference = reference.clone();
-                let registry_auth = registry_auth.clone();
-                async move {
-                    let res = Registry::client(client_protocol, &sources_clone)
-                        .pull_manifest(&reference, &registry_auth)
-                        .await?;
-                    Ok(res)
-                }
+        let sources = &sources;
+
+        let (oci_manifest, _) = try_with_mirrors(&reference, sources, |reference| {
+            Box::pin(async move {
+                let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
+                let registry_auth = Registry::auth(&reference, RegistryAction::Pull, sources).await;
+                try_with_protocols(&url, sources, |client_protocol| {
+                    Box::pin({
+                        let reference = reference.clone();
+                        let registry_auth = registry_auth.clone();
+                        async move {
+                            let res = Registry::client(client_protocol, sources)
+                                .pull_manifest(&reference, &registry_auth)
+                                .await?;
+                            Ok(res)
+                        }
+                    })
+                })
+                .await
             })
         })
         .await?;
@@ -223,21 +244,26 @@ impl Registry {
         // ensure it contains also the registry. Example: `busybox` ->
         // `docker.io/library/busybox:latest`
         let reference = build_fully_resolved_reference(url)?;
-        let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
-        let registry_auth = Registry::auth(reference.registry());
         let sources: Sources = sources.cloned().unwrap_or_default();
-
-        let digest = try_with_protocols(&url, &sources, |client_protocol| {
-            let sources_clone = sources.clone();
-            Box::pin({
-                let reference = reference.clone();
-                let registry_auth = registry_auth.clone();
-                async move {
-                    let res = Registry::client(client_protocol, &sources_clone)
-                        .fetch_manifest_digest(&reference, &registry_auth)
-                        .await?;
-                    Ok(res)
-                }
+        let sources = &sources;
+
+        let digest = try_with_mirrors(&reference, sources, |reference| {
+            Box::pin(async move {
+                let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
+                let registry_auth = Registry::auth(&reference, RegistryAction::Pull, sources).await;
+                try_with_protocols(&url, sources, |client_protocol| {
+                    Box::pin({
+                        let reference = reference.clone();
+                        let registry_auth = registry_auth.clone();
+                        async move {
+                            let res = Registry::client(client_protocol, sources)
+                                .fetch_manifest_digest(&reference, &registry_auth)
+                                .await?;
+                            Ok(res)
+                        }
+                    })
+                })
+                .await
             })
         })
         .await?;
@@ -245,6 +271,36 @@ impl Registry {
         Ok(digest)
     }
 
+    /// List the tags of the repository of the OCI object referenced by the given url.
+    pub async fn tags(&self, url: &str, sources: Option<&Sources>) -> RegistryResult<Vec<String>> {
+        let reference = build_fully_resolved_reference(url)?;
+        let sources: Sources = sources.cloned().unwrap_or_default();
+        let sources = &sources;
+
+        let tags = try_with_mirrors(&reference, sources, |reference| {
+            Box::pin(async move {
+                let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
+                let registry_auth = Registry::auth(&reference, RegistryAction::Pull, sources).await;
+                try_with_protocols(&url, sources, |client_protocol| {
+                    Box::pin({
+                        let reference = reference.clone();
+                        let registry_auth = registry_auth.clone();
+                        async move {
+                            let res = Registry::client(client_protocol, sources)
+                                .list_tags(&reference, &registry_auth, None, None)
+                                .await?;
+                            Ok(res.tags)
+                        }
+                    })
+                })
+                .await
+            })
+        })
+        .await?;
+
+        Ok(tags)
+    }
+
     /// Push the policy to the OCI registry specified by `url`.
     ///
     /// Returns the immutable reference to the policy (i.e.
@@ -298,7 +354,7 @@ impl Registry {
         let reference =
             Reference::from_str(url.as_ref().strip_prefix("registry://").unwrap_or_default())?;
 
-        let registry_auth = Registry::auth(reference.registry());
+        let registry_auth = Registry::auth(&reference, RegistryAction::Push, sources).await;
 
         let layers = vec![ImageLayer::new(
             policy.to_vec(),
@@ -338,28 +394,132 @@ impl Registry {
         serde_json::Value,
     )> {
         let reference = build_fully_resolved_reference(url)?;
-        let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
-        let registry_auth = Registry::auth(reference.registry());
         let sources: Sources = sources.cloned().unwrap_or_default();
+        let sources = &sources;
+
+        let (manifest, digest, config) = try_with_mirrors(&reference, sources, |reference| {
+            Box::pin(async move {
+                let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
+                let registry_auth = Registry::auth(&reference, RegistryAction::Pull, sources).await;
+                try_with_protocols(&url, sources, |client_protocol| {
+                    Box::pin({
+                        let reference = reference.clone();
+                        let registry_auth = registry_auth.clone();
+                        async move {
+                            let res = Registry::client(client_protocol, sources)
+                                .pull_manifest_and_config(&reference, &registry_auth)
+                                .await?;
+                            Ok(res)
+                        }
+                    })
+                })
+                .await
+            })
+        })
+        .await?;
 
-        let (manifest, digest, config) = try_with_protocols(&url, &sources, |client_protocol| {
-            let sources_clone = sources.clone();
+        let config_json = serde_json::from_str(&config)?;
+
+        Ok((manifest, digest, config_json))
+    }
+
+    /// Fetch the OCI artifact referenced by the given url: its manifest, kept
+    /// as it is, and all the blobs referenced by it.
+    pub async fn pull_artifact(
+        &self,
+        url: &str,
+        sources: Option<&Sources>,
+    ) -> RegistryResult<Artifact> {
+        let reference = build_fully_resolved_reference(url)?;
+        let sources: Sources = sources.cloned().unwrap_or_default();
+        let sources = &sources;
+
+        try_with_mirrors(&reference, sources, |reference| {
+            Box::pin(async move {
+                let url: Url = Url::parse(format!("registry://{reference}").as_str())?;
+                let registry_auth = Registry::auth(&reference, RegistryAction::Pull, sources).await;
+                try_with_protocols(&url, sources, |client_protocol| {
+                    Box::pin({
+                        let reference = reference.clone();
+                        let registry_auth = registry_auth.clone();
+                        async move {
+                            let client = Registry::client(client_protocol, sources);
+                            let (manifest, _) = client
+                                .pull_manifest_raw(
+                                    &reference,
+                                    &registry_auth,
+                                    &oci_layout::SUPPORTED_MANIFEST_MEDIA_TYPES,
+                                )
+                                .await?;
+                            let manifest = manifest.to_vec();
+
+                            let mut blobs = BTreeMap::new();
+                            for descriptor in oci_layout::referenced_blobs(&manifest)? {
+                                let mut blob = Vec::new();
+                                client
+                                    .pull_blob(&reference, descriptor.digest.as_str(), &mut blob)
+                                    .await?;
+                                blobs.insert(descriptor.digest, blob);
+                            }
+
+                            Ok(Artifact {
+                                media_type: oci_layout::manifest_media_type(&manifest)?,
+                                manifest,
+                                blobs,
+                            })
+                        }
+                    })
+                })
+                .await
+            })
+        })
+        .await
+    }
+
+    /// Push the OCI artifact to the registry specified by `destination`. The
+    /// manifest is pushed as it is, hence the digest of the artifact doesn't change.
+    ///
+    /// Returns the immutable reference to the artifact
+    pub async fn push_artifact(
+        &self,
+        artifact: &Artifact,
+        destination: &str,
+        sources: Option<&Sources>,
+    ) -> RegistryResult<String> {
+        let url = Url::parse(destination)
+            .map_err(|_| crate::errors::InvalidURLError(destination.to_owned()))?;
+        let sources: Sources = sources.cloned().unwrap_or_default();
+        let sources = &sources;
+        let destination = destination
+            .strip_prefix("registry://")
+            .ok_or_else(|| RegistryError::InvalidDestinationError)?;
+        let reference = Reference::from_str(destination)?;
+        let registry_auth = Registry::auth(&reference, RegistryAction::Push, sources).await;
+        let content_type = HeaderValue::from_str(&artifact.media_type)
+            .map_err(|_| RegistryError::InvalidMediaTypeError(artifact.media_type.clone()))?;
+
+        let manifest_url = try_with_protocols(&url, sources, |client_protocol| {
             Box::pin({
                 let reference = reference.clone();
                 let registry_auth = registry_auth.clone();
+                let content_type = content_type.clone();
                 async move {
-                    let res = Registry::client(client_protocol, &sources_clone)
-                        .pull_manifest_and_config(&reference, &registry_auth)
+                    debug!(?client_protocol, reference = %reference, "pushing artifact");
+                    let client = Registry::client(client_protocol, sources);
+                    client
+                        .auth(&reference, &registry_auth, RegistryOperation::Push)
                         .await?;
-                    Ok(res)
+                    for (digest, blob) in &artifact.blobs {
+                        client.push_blob(&reference, blob.clone(), digest).await?;
+                    }
+                    Ok(client
+                        .push_manifest_raw(&reference, artifact.manifest.clone(), content_type)
+                        .await?)
                 }
             })
         })
         .await?;
-
-        let config_json = serde_json::from_str(&config)?;
-
-        Ok((manifest, digest, config_json))
+        build_immutable_ref(destination, &manifest_url)
     }
 }
 
@@ -383,7 +543,7 @@ impl PolicyFetcher for Registry {
         let image_content = Registry::client(client_protocol, sources)
             .pull(
                 &reference,
-                &Registry::auth(&crate::host_and_port(url)?),
+                &Registry::auth(&reference, RegistryAction::Pull, sources).await,
                 vec![manifest::WASM_LAYER_MEDIA_TYPE],
             )
             .await?
@@ -3,14 +3,19 @@ use thiserror::Error;
 
 use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::{fs, fs::File};
 
+use oci_client::Reference;
+use tracing::warn;
 use x509_parser::pem::parse_x509_pem;
 use x509_parser::prelude::*;
 
 use crate::errors::FailedToParseYamlDataError;
 use crate::proxy::ProxyConfig;
+use crate::registry::credentials::CredentialProvider;
 
 pub type SourceResult<T> = std::result::Result<T, SourceError>;
 
@@ -32,6 +37,10 @@ pub enum SourceError {
     FailedToParseYamlDataError(#[from] FailedToParseYamlDataError),
     #[error("failed to create the http client: {0}")]
     FailedToCreateHttpClientError(#[from] reqwest::Error),
+    #[error("cannot fetch {0}: {1}")]
+    HttpRequestError(String, #[source] reqwest::Error),
+    #[error("Invalid registry mirror for prefix '{0}': {1}")]
+    InvalidRegistryMirrorError(String, String),
 }
 
 #[derive(Clone, Default, Deserialize, Debug)]
@@ -76,6 +85,8 @@ struct RawSources {
     insecure_sources: HashSet<String>,
     source_authorities: RawSourceAuthorities,
     proxies: Option<ProxyConfig>,
+    registry_mirrors: Vec<RegistryMirror>,
+    registry_credentials: HashMap<String, RegistryCredentials>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -105,6 +116,195 @@ impl TryFrom<RawSourceAuthorities> for SourceAuthorities {
     }
 }
 
+/// Rewrite rule for the references of the OCI artifacts, used to pull them
+/// from a mirror of the original registry.
+///
+/// This is how the rule looks like inside of the sources file:
+/// ```yaml
+/// registry_mirrors:
+///   - prefix: ghcr.io/kubewarden
+///     mirrors:
+///       - harbor.corp/ghcr-proxy/kubewarden
+///       - harbor-dr.corp/ghcr-proxy/kubewarden
+///     mirrors_only: true
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct RegistryMirror {
+    /// The references starting with this prefix are rewritten. The prefix is
+    /// matched against the fully resolved name of the artifact (e.g.
+    /// `docker.io/library/busybox`) on a path segment boundary.
+    pub prefix: String,
+    /// The prefixes replacing the original one, tried in order
+    pub mirrors: Vec<String>,
+    /// Do not fall back to the original location when the artifact cannot be
+    /// pulled from any of the mirrors
+    #[serde(default)]
+    pub mirrors_only: bool,
+}
+
+impl RegistryMirror {
+    fn matches(&self, name: &str) -> bool {
+        name.strip_prefix(self.prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    fn validate(&self) -> SourceResult<()> {
+        let invalid =
+            |msg: String| SourceError::InvalidRegistryMirrorError(self.prefix.clone(), msg);
+
+        if self.prefix.is_empty() || self.prefix.ends_with('/') {
+            return Err(invalid(
+                "the prefix must be a registry host, optionally followed by a repository path"
+                    .to_owned(),
+            ));
+        }
+        if self.mirrors.is_empty() {
+            return Err(invalid("no mirror has been provided".to_owned()));
+        }
+        for mirror in &self.mirrors {
+            if mirror.ends_with('/')
+                || Reference::try_from(format!("{mirror}/policy:latest")).is_err()
+            {
+                return Err(invalid(format!("'{mirror}' is not a valid location")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rewrite the name of an image (registry and repository, e.g. `ghcr.io/kubewarden/policy`)
+/// using the mirror rule with the longest matching prefix. Returns the names to be tried,
+/// in order.
+fn mirrored_names(name: &str, registry_mirrors: &[RegistryMirror]) -> Vec<String> {
+    let Some(rule) = registry_mirrors
+        .iter()
+        .filter(|rule| rule.matches(name))
+        .max_by_key(|rule| rule.prefix.len())
+    else {
+        return vec![name.to_owned()];
+    };
+
+    let rest = &name[rule.prefix.len()..];
+    let mut names: Vec<String> = rule
+        .mirrors
+        .iter()
+        .map(|mirror| format!("{mirror}{rest}"))
+        .collect();
+    if !rule.mirrors_only {
+        names.push(name.to_owned());
+    }
+    names
+}
+
+/// The credentials used to authenticate against a registry, as declared inside
+/// of the sources file:
+/// ```yaml
+/// registry_credentials:
+///   registry.corp:
+///     username: robot
+///     password: s3cr3t
+///   harbor.corp:
+///     bearer_token_file: /var/run/secrets/harbor/token
+///   myregistry.azurecr.io:
+///     identity_token: eyJhbGciOi...
+///   123456789.dkr.ecr.eu-west-1.amazonaws.com:
+///     credential_helper: ecr-login
+/// ```
+#[derive(Clone, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "RawRegistryCredentials")]
+pub enum RegistryCredentials {
+    /// Username and password
+    Basic { username: String, password: String },
+    /// A bearer token read from a file. The file is read every time the
+    /// credentials are needed, hence the token can be rotated at runtime.
+    BearerTokenFile { bearer_token_file: PathBuf },
+    /// An OAuth2 refresh token, exchanged for an access token by using the
+    /// token server of the registry
+    IdentityToken { identity_token: String },
+    /// A docker credential helper, the `docker-credential-<name>` executable
+    /// must be available inside of the `PATH`
+    CredentialHelper { credential_helper: String },
+}
+
+/// The registry credentials, as written inside of the sources file. Exactly
+/// one kind of credentials must be given
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRegistryCredentials {
+    username: Option<String>,
+    password: Option<String>,
+    bearer_token_file: Option<PathBuf>,
+    identity_token: Option<String>,
+    credential_helper: Option<String>,
+}
+
+impl TryFrom<RawRegistryCredentials> for RegistryCredentials {
+    type Error = String;
+
+    fn try_from(raw: RawRegistryCredentials) -> Result<Self, Self::Error> {
+        match raw {
+            RawRegistryCredentials {
+                username: Some(username),
+                password: Some(password),
+                bearer_token_file: None,
+                identity_token: None,
+                credential_helper: None,
+            } => Ok(RegistryCredentials::Basic { username, password }),
+            RawRegistryCredentials {
+                username: None,
+                password: None,
+                bearer_token_file: Some(bearer_token_file),
+                identity_token: None,
+                credential_helper: None,
+            } => Ok(RegistryCredentials::BearerTokenFile { bearer_token_file }),
+            RawRegistryCredentials {
+                username: None,
+                password: None,
+                bearer_token_file: None,
+                identity_token: Some(identity_token),
+                credential_helper: None,
+            } => Ok(RegistryCredentials::IdentityToken { identity_token }),
+            RawRegistryCredentials {
+                username: None,
+                password: None,
+                bearer_token_file: None,
+                identity_token: None,
+                credential_helper: Some(credential_helper),
+            } => Ok(RegistryCredentials::CredentialHelper { credential_helper }),
+            _ => Err(
+                "registry credentials must be either username and password, \
+                bearer_token_file, identity_token or credential_helper"
+                    .to_owned(),
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for RegistryCredentials {
+    // Do not leak secrets into the logs
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryCredentials::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            RegistryCredentials::BearerTokenFile { bearer_token_file } => f
+                .debug_struct("BearerTokenFile")
+                .field("bearer_token_file", bearer_token_file)
+                .finish(),
+            RegistryCredentials::IdentityToken { .. } => f
+                .debug_struct("IdentityToken")
+                .field("identity_token", &"<redacted>")
+                .finish(),
+            RegistryCredentials::CredentialHelper { credential_helper } => f
+                .debug_struct("CredentialHelper")
+                .field("credential_helper", credential_helper)
+                .finish(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Sources {
     pub insecure_sources: HashSet<String>,
@@ -113,6 +313,14 @@ pub struct Sources {
     /// source configuration. When `None`, `ProxyConfig::from_env()` is used as
     /// a fallback so existing callers that never set this field continue to work.
     pub proxies: Option<ProxyConfig>,
+    /// Rules used to pull OCI artifacts from mirrors of their original registry
+    pub registry_mirrors: Vec<RegistryMirror>,
+    /// Credentials to be used against the registries, indexed by registry
+    /// host (and port, when not the default one)
+    pub registry_credentials: HashMap<String, RegistryCredentials>,
+    /// Additional providers of registry credentials. They are consulted, in
+    /// order, before the `registry_credentials` and the docker configuration.
+    pub credential_providers: Vec<Arc<dyn CredentialProvider>>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -125,10 +333,17 @@ impl TryFrom<RawSources> for Sources {
     type Error = SourceError;
 
     fn try_from(sources: RawSources) -> SourceResult<Sources> {
+        for registry_mirror in &sources.registry_mirrors {
+            registry_mirror.validate()?;
+        }
+
         Ok(Sources {
             insecure_sources: sources.insecure_sources.clone(),
             source_authorities: sources.source_authorities.try_into()?,
             proxies: sources.proxies,
+            registry_mirrors: sources.registry_mirrors,
+            registry_credentials: sources.registry_credentials,
+            credential_providers: Vec::new(),
         })
     }
 }
@@ -264,6 +479,35 @@ impl Sources {
     pub fn proxies(&self) -> ProxyConfig {
         self.proxies.clone().unwrap_or_else(ProxyConfig::from_env)
     }
+
+    /// Returns the references to be used to pull the given OCI artifact, in
+    /// the order in which they have to be tried.
+    ///
+    /// When a registry mirror rule matches the reference, the locations of the
+    /// mirrors come first, followed by the original reference unless the rule
+    /// is `mirrors_only`. Otherwise, only the original reference is returned.
+    pub fn mirrored_references(&self, reference: &Reference) -> Vec<Reference> {
+        let name = format!("{}/{}", reference.registry(), reference.repository());
+        let whole = reference.whole();
+        let suffix = whole.strip_prefix(name.as_str()).unwrap_or_default();
+
+        let references: Vec<Reference> = mirrored_names(&name, &self.registry_mirrors)
+            .into_iter()
+            .filter_map(|mirrored_name| {
+                let mirrored = format!("{mirrored_name}{suffix}");
+                Reference::try_from(mirrored.as_str())
+                    .inspect_err(|error| {
+                        warn!(%mirrored, ?error, "ignoring invalid mirrored reference");
+                    })
+                    .ok()
+            })
+            .collect();
+
+        if references.is_empty() {
+            return vec![reference.clone()];
+        }
+        references
+    }
 }
 
 pub fn read_sources_file(path: &Path) -> SourceResult<Sources> {
@@ -275,6 +519,7 @@ pub fn read_sources_file(path: &Path) -> SourceResult<Sources> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rstest::rstest;
     use serde_json::json;
     use std::io::Write;
     use tempfile::NamedTempFile;
@@ -365,6 +610,202 @@ proxies:
         );
     }
 
+    #[test]
+    fn test_deserialization_of_registry_mirrors() {
+        let yaml = r#"
+registry_mirrors:
+  - prefix: ghcr.io/kubewarden
+    mirrors:
+      - harbor.corp/ghcr-proxy/kubewarden
+    mirrors_only: true
+  - prefix: docker.io
+    mirrors:
+      - harbor.corp/dockerhub-proxy
+"#;
+        let raw: RawSources = serde_yaml::from_str(yaml).expect("failed to deserialize");
+        let sources: Sources = raw.try_into().expect("failed to convert");
+        assert_eq!(
+            sources.registry_mirrors,
+            vec![
+                RegistryMirror {
+                    prefix: "ghcr.io/kubewarden".to_string(),
+                    mirrors: vec!["harbor.corp/ghcr-proxy/kubewarden".to_string()],
+                    mirrors_only: true,
+                },
+                RegistryMirror {
+                    prefix: "docker.io".to_string(),
+                    mirrors: vec!["harbor.corp/dockerhub-proxy".to_string()],
+                    mirrors_only: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialization_of_registry_credentials() {
+        let yaml = r#"
+registry_credentials:
+  registry.corp:
+    username: robot
+    password: s3cr3t
+  harbor.corp:
+    bearer_token_file: /var/run/secrets/harbor/token
+  myregistry.azurecr.io:
+    identity_token: refresh-token
+  localhost:5000:
+    credential_helper: pass
+"#;
+        let raw: RawSources = serde_yaml::from_str(yaml).expect("failed to deserialize");
+        let sources: Sources = raw.try_into().expect("failed to convert");
+        assert_eq!(
+            sources.registry_credentials,
+            HashMap::from([
+                (
+                    "registry.corp".to_string(),
+                    RegistryCredentials::Basic {
+                        username: "robot".to_string(),
+                        password: "s3cr3t".to_string(),
+                    }
+                ),
+                (
+                    "harbor.corp".to_string(),
+                    RegistryCredentials::BearerTokenFile {
+                        bearer_token_file: PathBuf::from("/var/run/secrets/harbor/token"),
+                    }
+                ),
+                (
+                    "myregistry.azurecr.io".to_string(),
+                    RegistryCredentials::IdentityToken {
+                        identity_token: "refresh-token".to_string(),
+                    }
+                ),
+                (
+                    "localhost:5000".to_string(),
+                    RegistryCredentials::CredentialHelper {
+                        credential_helper: "pass".to_string(),
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case::only_username("username: robot")]
+    #[case::mixed_kinds("{username: robot, password: s3cr3t, identity_token: eyJ}")]
+    #[case::unknown_field("{credential_helper: ecr-login, region: eu-west-1}")]
+    #[case::empty("{}")]
+    fn test_invalid_registry_credentials(#[case] credentials: &str) {
+        assert!(serde_yaml::from_str::<RegistryCredentials>(credentials).is_err());
+    }
+
+    #[test]
+    fn test_registry_credentials_do_not_leak_secrets() {
+        let credentials = RegistryCredentials::Basic {
+            username: "robot".to_string(),
+            password: "s3cr3t".to_string(),
+        };
+
+        let debug = format!("{credentials:?}");
+
+        assert!(debug.contains("robot"));
+        assert!(!debug.contains("s3cr3t"));
+    }
+
+    #[rstest]
+    #[case::empty_prefix("", &["harbor.corp/ghcr-proxy"])]
+    #[case::prefix_with_trailing_slash("ghcr.io/", &["harbor.corp/ghcr-proxy"])]
+    #[case::no_mirrors("ghcr.io", &[])]
+    #[case::invalid_mirror("ghcr.io", &["harbor.corp/GHCR proxy"])]
+    #[case::mirror_with_trailing_slash("ghcr.io", &["harbor.corp/ghcr-proxy/"])]
+    fn test_invalid_registry_mirror(#[case] prefix: &str, #[case] mirrors: &[&str]) {
+        let raw = RawSources {
+            registry_mirrors: vec![RegistryMirror {
+                prefix: prefix.to_string(),
+                mirrors: mirrors.iter().map(|m| m.to_string()).collect(),
+                mirrors_only: false,
+            }],
+            ..Default::default()
+        };
+
+        let sources: SourceResult<Sources> = raw.try_into();
+
+        assert!(matches!(
+            sources,
+            Err(SourceError::InvalidRegistryMirrorError(_, _))
+        ));
+    }
+
+    #[rstest]
+    #[case::no_matching_rule(
+        "quay.io/kubewarden/policy:v1",
+        &["quay.io/kubewarden/policy:v1"]
+    )]
+    #[case::mirror_with_fallback(
+        "docker.io/library/busybox:latest",
+        &["harbor.corp/dockerhub-proxy/library/busybox:latest", "docker.io/library/busybox:latest"]
+    )]
+    #[case::longest_prefix_wins(
+        "ghcr.io/kubewarden/policies/psp-capabilities:v0.1.3",
+        &[
+            "harbor.corp/ghcr-proxy/kubewarden/policies/psp-capabilities:v0.1.3",
+            "harbor-dr.corp/ghcr-proxy/kubewarden/policies/psp-capabilities:v0.1.3",
+        ]
+    )]
+    #[case::digest_is_preserved(
+        "ghcr.io/kubewarden/verify@sha256:72b4569c3daee67abeaa64192fb53895d0edb2d44fa6e1d9d4c5d3f8ece09f6e",
+        &[
+            "harbor.corp/ghcr-proxy/kubewarden/verify@sha256:72b4569c3daee67abeaa64192fb53895d0edb2d44fa6e1d9d4c5d3f8ece09f6e",
+            "harbor-dr.corp/ghcr-proxy/kubewarden/verify@sha256:72b4569c3daee67abeaa64192fb53895d0edb2d44fa6e1d9d4c5d3f8ece09f6e",
+        ]
+    )]
+    #[case::prefix_matched_on_segment_boundary(
+        "ghcr.io/kubewarden-labs/policy:v1",
+        &["harbor.corp/ghcr-proxy-all/kubewarden-labs/policy:v1", "ghcr.io/kubewarden-labs/policy:v1"]
+    )]
+    #[case::prefix_matching_whole_name(
+        "ghcr.io/kubewarden/policy:v1",
+        &["harbor.corp/mirrored-policy:v1"]
+    )]
+    fn test_mirrored_references(#[case] reference: &str, #[case] expected: &[&str]) {
+        let sources = Sources {
+            registry_mirrors: vec![
+                RegistryMirror {
+                    prefix: "docker.io".to_string(),
+                    mirrors: vec!["harbor.corp/dockerhub-proxy".to_string()],
+                    mirrors_only: false,
+                },
+                RegistryMirror {
+                    prefix: "ghcr.io".to_string(),
+                    mirrors: vec!["harbor.corp/ghcr-proxy-all".to_string()],
+                    mirrors_only: false,
+                },
+                RegistryMirror {
+                    prefix: "ghcr.io/kubewarden".to_string(),
+                    mirrors: vec![
+                        "harbor.corp/ghcr-proxy/kubewarden".to_string(),
+                        "harbor-dr.corp/ghcr-proxy/kubewarden".to_string(),
+                    ],
+                    mirrors_only: true,
+                },
+                RegistryMirror {
+                    prefix: "ghcr.io/kubewarden/policy".to_string(),
+                    mirrors: vec!["harbor.corp/mirrored-policy".to_string()],
+                    mirrors_only: true,
+                },
+            ],
+            ..Default::default()
+        };
+        let reference = Reference::try_from(reference).unwrap();
+
+        let references: Vec<String> = sources
+            .mirrored_references(&reference)
+            .iter()
+            .map(|r| r.whole())
+            .collect();
+
+        assert_eq!(references, expected);
+    }
+
     #[test]
     fn test_raw_source_authority_cannot_be_converted_into_raw_certificate_when_file_is_missing() {
         let mut path = PathBuf::new();
@@ -20,4 +20,16 @@ pub enum StoreError {
     DigestError(#[from] crate::policy::DigestError),
     #[error(transparent)]
     DecoderError(#[from] base64::DecodeError),
+    #[error("cannot parse the store index: {0}")]
+    CorruptedIndexError(#[source] serde_json::Error),
+    #[error("unsupported store index version: {0}")]
+    UnsupportedIndexVersionError(u32),
+    #[error(transparent)]
+    OciLayoutError(#[from] crate::oci_layout::errors::OciLayoutError),
+    #[error("integrity check of policy {uri} failed: expected digest {expected}, got {actual}")]
+    IntegrityError {
+        uri: String,
+        expected: String,
+        actual: String,
+    },
 }
@@ -1,11 +1,23 @@
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
 use path_slash::PathExt;
-use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, info, warn};
 use url::Url;
 use walkdir::WalkDir;
 
-use crate::policy::Policy;
+use crate::{
+    oci_layout::{Artifact, OciLayout},
+    policy::Policy,
+};
 use errors::StoreError;
 
 use self::errors::StoreResult;
@@ -20,47 +32,108 @@ lazy_static! {
     pub static ref DEFAULT_STORE_ROOT: PathBuf = DEFAULT_ROOT.cache_dir().join("store");
 }
 
+/// Used to generate unique names for the temporary files of the store
+static TMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+const BLOBS_DIR: &str = "blobs";
+const INDEX_FILE: &str = "index.json";
+const INDEX_LOCK_FILE: &str = "index.lock";
+const TMP_DIR: &str = "tmp";
+const OCI_LAYOUT_DIR: &str = "oci-layout";
+const INDEX_VERSION: u32 = 1;
+const DIGEST_ALGORITHM: &str = "sha256";
+/// The temporary files older than this are considered leftovers of
+/// interrupted operations, the younger ones may still be in use
+const STALE_TMP_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
 pub enum PolicyPath {
     PrefixOnly,
     PrefixAndFilename,
 }
 
+/// An entry of the store index
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Digest of the policy, in the `sha256:<hex>` format
+    pub digest: String,
+    /// When the policy has been pulled, as seconds since the UNIX epoch
+    pub pulled_at: u64,
+    /// Digest of the OCI manifest the policy has been pulled from. Set only
+    /// for the policies coming from OCI registries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_digest: Option<String>,
+    /// ETag of the policy, as returned by the server. Set only for the
+    /// policies served over HTTP(S).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Digest of the detached signature, or of the Sigstore bundle, published
+    /// next to the policy. Set only for the policies served over HTTP(S).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_digest: Option<String>,
+}
+
+/// The files published next to a policy served over HTTP(S)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpsPolicyMetadata {
+    /// ETag of the policy, used to check whether it changed
+    pub etag: Option<String>,
+    /// Detached signature or Sigstore bundle of the policy
+    pub signature: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    /// The entries, indexed by policy URI
+    policies: BTreeMap<String, IndexEntry>,
+}
+
+/// The outcome of a garbage collection of the store
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct GarbageCollectionReport {
+    /// The digests of the blobs that have been removed
+    pub removed_blobs: Vec<String>,
+    /// The URIs of the index entries that have been removed, because their
+    /// blob was missing or corrupted
+    pub removed_entries: Vec<String>,
+    /// The disk space that has been freed, in bytes
+    pub reclaimed_bytes: u64,
+}
+
 /// Store represents a structure that is able to save and retrieve
 /// WebAssembly modules from a central and local location.
 ///
-/// In the local filesystem, the structure of the directory is the
-/// following:
+/// The modules are stored by content, using their sha256 digest, hence
+/// the same module pulled from different URIs is stored only once.
+/// An index maps the URIs of the policies to the digests of their modules:
 ///
-/// <root>/<scheme>/<host>/<image>:<tag>.wasm
+/// <root>
+///     - blobs
+///         - sha256
+///             - 93a...
+///             - f41...
+///     - index.json
+///     - index.lock
+///     - oci-layout
+///     - tmp
 ///
-/// for each image. No index or embedded database is implemented
-/// yet. Assuming we have pulled the following images:
+/// The updates of the index are serialized by locking the `index.lock`
+/// file, this allows multiple processes to share the same store.
+/// The integrity of a module is checked every time it's looked up by
+/// URI. The modules not referenced by the index anymore are removed
+/// by the garbage collection, see [`Store::gc`].
 ///
-/// - https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm
-/// - registry://ghcr.io/some-org/some/path/to/wasm-module.wasm:1.0.0
-/// - registry://internal.host.company:5000/some/path/to/wasm-module.wasm:1.0.0
+/// The other artifacts of the policies loaded from OCI image layouts, like
+/// their signatures and attestations, are kept inside of the `oci-layout`
+/// image layout. This allows to verify the policies without contacting
+/// their registry.
 ///
-/// The structure of the folder would look like the following:
+/// Stores created by previous releases laid out the modules by URI:
 ///
-/// <root>
-///     - https
-///         - internal.host.company
-///             - some
-///                 - path
-///                     - 1.0.0        
-///                         - to
-///                             - wasm-module.wasm
-///     - registry
-///         - ghcr.io
-///             - some
-///                 - path
-///                     - to
-///                         - wasm-module.wasm:1.0.0
-///         - internal.host.company:5000
-///             - some
-///                 - path
-///                     - to
-///                         - wasm-module.wasm:1.0.0
+/// <root>/<scheme>/<host>/<image>:<tag>
+///
+/// These modules are imported into the index the first time the store
+/// is accessed.
 #[derive(Debug, PartialEq, Eq)]
 pub struct Store {
     pub root: PathBuf,
@@ -73,26 +146,24 @@ impl Store {
         }
     }
 
-    /// Creates all directories provided in `path` starting from the
-    /// root of this store.
-    pub fn ensure(&self, path: &Path) -> std::io::Result<()> {
-        std::fs::create_dir_all(self.root.join(path))
+    /// Returns the path where the module with the given digest is stored.
+    /// The digest can be provided with or without the `sha256:` prefix.
+    pub fn blob_path(&self, digest: &str) -> PathBuf {
+        let hex = digest
+            .strip_prefix(&format!("{DIGEST_ALGORITHM}:"))
+            .unwrap_or(digest);
+        self.root.join(BLOBS_DIR).join(DIGEST_ALGORITHM).join(hex)
     }
 
-    /// Returns the full path of a policy coming from the URL `url` in
-    /// this store. If `policy_path` is set to `PrefixOnly`, the
-    /// filename of the policy will be omitted, otherwise it will be
-    /// included.
-    pub fn policy_full_path(&self, url: &str, policy_path: PolicyPath) -> StoreResult<PathBuf> {
-        let path = self.policy_path(url, policy_path)?;
-
-        Ok(self.root.join(path))
+    fn index_path(&self) -> PathBuf {
+        self.root.join(INDEX_FILE)
     }
 
-    /// Returns the path of a policy coming from the URL `url`
-    /// without the store. If `policy_path` is set to `PrefixOnly`, the
-    /// filename of the policy will be omitted, otherwise it will be
-    /// included.
+    /// Returns the path of a policy coming from the URL `url` inside of
+    /// a store using the layout of the previous releases, relative to
+    /// the root of the store. This is the path used by the policy archives.
+    /// If `policy_path` is set to `PrefixOnly`, the filename of the policy
+    /// will be omitted, otherwise it will be included.
     pub fn policy_path(&self, url: &str, policy_path: PolicyPath) -> StoreResult<PathBuf> {
         let url = Url::parse(url)?;
         let filename = policy_file_name(&url);
@@ -122,84 +193,478 @@ impl Store {
         path::encode_path(policy_prefix)
     }
 
-    /// Lists all policies in this store
+    /// Saves the module of a policy coming from `uri`, replacing the
+    /// one previously stored for the same URI.
+    pub fn add_policy(&self, uri: &str, module: &[u8]) -> StoreResult<Policy> {
+        self.insert_policy(uri, module, |_| Ok(()))
+    }
+
+    /// Saves the module of a policy pulled from an OCI registry, together
+    /// with the digest of the manifest it has been pulled from. This is used
+    /// to detect when the tag of the policy is moved to another manifest.
+    pub fn add_registry_policy(
+        &self,
+        uri: &str,
+        module: &[u8],
+        manifest_digest: &str,
+    ) -> StoreResult<Policy> {
+        self.insert_policy(uri, module, |entry| {
+            entry.manifest_digest = Some(manifest_digest.to_owned());
+            Ok(())
+        })
+    }
+
+    /// Saves the module of a policy served over HTTP(S), together with its
+    /// ETag and its signature. The signature is stored as a blob too, see
+    /// [`Store::signature_path`].
+    pub fn add_https_policy(
+        &self,
+        uri: &str,
+        module: &[u8],
+        metadata: &HttpsPolicyMetadata,
+    ) -> StoreResult<Policy> {
+        self.insert_policy(uri, module, |entry| {
+            entry.etag = metadata.etag.clone();
+            entry.signature_digest = metadata
+                .signature
+                .as_ref()
+                .map(|signature| self.write_blob(signature))
+                .transpose()?;
+            Ok(())
+        })
+    }
+
+    /// Saves the module and updates the index. The blobs written by
+    /// `with_metadata` are protected by the index lock too, otherwise
+    /// a concurrent garbage collection could remove them
+    fn insert_policy(
+        &self,
+        uri: &str,
+        module: &[u8],
+        with_metadata: impl FnOnce(&mut IndexEntry) -> StoreResult<()>,
+    ) -> StoreResult<Policy> {
+        let uri = index_key(uri)?;
+        let _lock = self.lock_index()?;
+
+        let mut index = self.read_index()?;
+        let digest = self.write_blob(module)?;
+        let mut entry = IndexEntry {
+            digest: digest.clone(),
+            pulled_at: now(),
+            manifest_digest: None,
+            etag: None,
+            signature_digest: None,
+        };
+        with_metadata(&mut entry)?;
+        index.policies.insert(uri.clone(), entry);
+        self.write_index(&index)?;
+
+        Ok(Policy {
+            uri,
+            local_path: self.blob_path(&digest),
+        })
+    }
+
+    /// Removes the policy coming from `uri` from the index. Its module is
+    /// deleted too, unless it's referenced by other policies.
+    /// Returns `false` when the policy is not inside of the store.
+    pub fn remove_policy_by_uri(&self, uri: &str) -> StoreResult<bool> {
+        let uri = index_key(uri)?;
+        let _lock = self.lock_index()?;
+
+        let mut index = self.read_index()?;
+        let Some(entry) = index.policies.remove(&uri) else {
+            return Ok(false);
+        };
+        self.write_index(&index)?;
+
+        if !index
+            .policies
+            .values()
+            .any(|other| other.digest == entry.digest)
+        {
+            remove_file_if_exists(&self.blob_path(&entry.digest))?;
+        }
+
+        Ok(true)
+    }
+
+    /// Saves an artifact related to the policies pulled from OCI registries,
+    /// like the signatures and the attestations published by cosign.
+    /// `ref_name` is the reference of the artifact, without the
+    /// `registry://` scheme. An artifact with the same reference is replaced.
+    pub fn add_registry_artifact(&self, ref_name: &str, artifact: &Artifact) -> StoreResult<()> {
+        let _lock = self.lock_index()?;
+        OciLayout::create(&self.root.join(OCI_LAYOUT_DIR))?.add_artifact(artifact, ref_name)?;
+        Ok(())
+    }
+
+    /// Returns the artifact saved by [`Store::add_registry_artifact`] using
+    /// the given reference, if it exists
+    pub fn registry_artifact(&self, ref_name: &str) -> StoreResult<Option<Artifact>> {
+        let layout_root = self.root.join(OCI_LAYOUT_DIR);
+        if !OciLayout::is_oci_layout(&layout_root) {
+            return Ok(None);
+        }
+        // the layout is updated under the index lock, its blobs are written
+        // before the index referencing them, but the index isn't replaced
+        // atomically
+        let _lock = self.lock_index()?;
+        Ok(OciLayout::open(&layout_root)?.artifact(ref_name)?)
+    }
+
+    /// Returns the index entry of the policy coming from `uri`, if it exists
+    pub fn index_entry(&self, uri: &str) -> StoreResult<Option<IndexEntry>> {
+        let uri = index_key(uri)?;
+        Ok(self.load_index()?.policies.remove(&uri))
+    }
+
+    /// Returns the path of the detached signature, or of the Sigstore bundle,
+    /// published next to the policy coming from `uri`, if it has been stored
+    pub fn signature_path(&self, uri: &str) -> StoreResult<Option<PathBuf>> {
+        Ok(self
+            .index_entry(uri)?
+            .and_then(|entry| entry.signature_digest)
+            .map(|digest| self.blob_path(&digest))
+            .filter(|path| path.exists()))
+    }
+
+    /// Lists all policies in this store, sorted by URI.
+    ///
+    /// The integrity of the modules is not checked, use
+    /// [`Store::get_policy_by_uri`] for that.
     pub fn list(&self) -> StoreResult<Vec<Policy>> {
-        let mut policies = Vec::new();
+        Ok(self
+            .load_index()?
+            .policies
+            .into_iter()
+            .map(|(uri, entry)| Policy {
+                uri,
+                local_path: self.blob_path(&entry.digest),
+            })
+            .collect())
+    }
 
-        if !self.root.exists() {
-            return Ok(policies);
+    /// Get a policy by its URI, if it exists. An error is returned when
+    /// the stored module doesn't match the digest recorded inside of the index.
+    pub fn get_policy_by_uri(&self, uri: &str) -> StoreResult<Option<Policy>> {
+        let uri = index_key(uri)?;
+
+        let Some(entry) = self.load_index()?.policies.remove(&uri) else {
+            return Ok(None);
+        };
+        let local_path = self.blob_path(&entry.digest);
+        if !local_path.exists() {
+            debug!(uri, digest = entry.digest, "policy module is missing");
+            return Ok(None);
         }
 
-        let store_root_path = std::fs::read_dir(self.root.as_path())?;
-        for scheme in store_root_path {
-            let scheme = scheme?;
-            match scheme.file_name().to_str() {
-                Some(scheme) => {
-                    if !scheme::is_known_remote_scheme(scheme) {
-                        continue;
-                    }
+        let actual = file_digest(&local_path)?;
+        if actual != entry.digest {
+            return Err(StoreError::IntegrityError {
+                uri,
+                expected: entry.digest,
+                actual,
+            });
+        }
+
+        Ok(Some(Policy { uri, local_path }))
+    }
+
+    /// Get a policy that matches the given SHA prefix, if it exists.
+    /// When the module is referenced by multiple URIs, the first one
+    /// in alphabetical order is returned.
+    pub fn get_policy_by_sha_prefix(&self, sha_prefix: &str) -> StoreResult<Option<Policy>> {
+        let index = self.load_index()?;
+        let matches: Vec<(&String, &IndexEntry)> = index
+            .policies
+            .iter()
+            .filter(|(_, entry)| {
+                entry
+                    .digest
+                    .strip_prefix(&format!("{DIGEST_ALGORITHM}:"))
+                    .unwrap_or(&entry.digest)
+                    .starts_with(sha_prefix)
+            })
+            .collect();
+
+        let digests: BTreeSet<&String> = matches.iter().map(|(_, entry)| &entry.digest).collect();
+        if digests.len() > 1 {
+            return Err(StoreError::MultiplePoliciesFoundError(
+                sha_prefix.to_owned(),
+            ));
+        }
+
+        Ok(matches.first().map(|(uri, entry)| Policy {
+            uri: uri.to_string(),
+            local_path: self.blob_path(&entry.digest),
+        }))
+    }
+
+    /// Removes the modules that are not referenced by the index anymore,
+    /// together with the leftovers of interrupted operations. Only the
+    /// temporary files older than a day are removed, the other ones could
+    /// belong to operations still in progress, maybe done by other processes.
+    ///
+    /// The integrity of the referenced modules is checked too: the corrupted
+    /// ones are removed, together with the index entries referencing them.
+    pub fn gc(&self) -> StoreResult<GarbageCollectionReport> {
+        let _lock = self.lock_index()?;
+        let mut report = GarbageCollectionReport::default();
+
+        let mut index = self.read_index()?;
+        let mut valid_digests = BTreeSet::new();
+        let mut invalid_digests = BTreeSet::new();
+        for entry in index.policies.values() {
+            if valid_digests.contains(&entry.digest) || invalid_digests.contains(&entry.digest) {
+                continue;
+            }
+            let blob_path = self.blob_path(&entry.digest);
+            if blob_path.exists() && file_digest(&blob_path)? == entry.digest {
+                valid_digests.insert(entry.digest.clone());
+            } else {
+                warn!(
+                    digest = entry.digest,
+                    "policy module is missing or corrupted"
+                );
+                invalid_digests.insert(entry.digest.clone());
+            }
+        }
+
+        index.policies.retain(|uri, entry| {
+            let valid = valid_digests.contains(&entry.digest);
+            if !valid {
+                report.removed_entries.push(uri.to_owned());
+            }
+            valid
+        });
+        if !report.removed_entries.is_empty() {
+            self.write_index(&index)?;
+        }
+        // the signatures of the remaining policies are kept too
+        valid_digests.extend(
+            index
+                .policies
+                .values()
+                .filter_map(|entry| entry.signature_digest.clone()),
+        );
+
+        let blobs_dir = self.root.join(BLOBS_DIR).join(DIGEST_ALGORITHM);
+        if blobs_dir.exists() {
+            for blob in std::fs::read_dir(&blobs_dir)? {
+                let blob = blob?;
+                let digest = format!("{DIGEST_ALGORITHM}:{}", blob.file_name().to_string_lossy());
+                if valid_digests.contains(&digest) {
+                    continue;
                 }
-                None => continue,
+                report.reclaimed_bytes += blob.metadata()?.len();
+                std::fs::remove_file(blob.path())?;
+                report.removed_blobs.push(digest);
             }
-            for host in std::fs::read_dir(scheme.path())? {
-                let host = host?;
-                for policy in WalkDir::new(host.path()) {
-                    let policy = policy?;
-
-                    let metadata = std::fs::metadata(policy.path())?;
-                    if metadata.is_file() {
-                        let policy_store_path =
-                            Path::new("/").join(policy.path().strip_prefix(host.path())?);
-                        policies.push(Policy {
-                            uri: format!(
-                                "{}://{}{}",
-                                scheme.file_name().to_str().unwrap(),
-                                path::decode_path(host.file_name())?.to_str().unwrap(),
-                                path::decode_path(policy_store_path)?.to_slash_lossy()
-                            ),
-                            local_path: policy.path().to_path_buf(),
-                        })
+        }
+
+        let tmp_dir = self.root.join(TMP_DIR);
+        if tmp_dir.exists() {
+            for tmp in std::fs::read_dir(&tmp_dir)? {
+                let tmp = tmp?;
+                if !is_stale(&tmp.metadata()?) {
+                    continue;
+                }
+                for entry in WalkDir::new(tmp.path()) {
+                    let entry = entry?;
+                    if entry.file_type().is_file() {
+                        report.reclaimed_bytes += entry.metadata()?.len();
                     }
                 }
+                if tmp.file_type()?.is_dir() {
+                    std::fs::remove_dir_all(tmp.path())?;
+                } else {
+                    std::fs::remove_file(tmp.path())?;
+                }
             }
         }
+
+        report.removed_blobs.sort();
+        info!(
+            removed_blobs = report.removed_blobs.len(),
+            removed_entries = report.removed_entries.len(),
+            reclaimed_bytes = report.reclaimed_bytes,
+            "store garbage collection completed"
+        );
+
+        Ok(report)
+    }
+
+    /// Creates a new directory inside of the store, that can be used to
+    /// stage files before importing them. The directory is removed by
+    /// the garbage collection once it's stale, see [`Store::gc`].
+    pub fn staging_dir(&self) -> StoreResult<PathBuf> {
+        let dir = self.tmp_path("staging");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Imports the policies found inside of `root`, which must use the
+    /// layout of the stores created by previous releases. This is also
+    /// the layout used by the policy archives.
+    pub fn import_legacy_layout(&self, root: &Path) -> StoreResult<Vec<Policy>> {
+        let _lock = self.lock_index()?;
+
+        let mut index = self.read_index()?;
+        let policies = self.import_legacy_policies(root, &mut index)?;
+        self.write_index(&index)?;
+
         Ok(policies)
     }
 
-    /// Get a policy by its URI, if it exists.
-    pub fn get_policy_by_uri(&self, uri: &str) -> StoreResult<Option<Policy>> {
-        let uri = Url::parse(uri)?;
+    fn import_legacy_policies(&self, root: &Path, index: &mut Index) -> StoreResult<Vec<Policy>> {
+        let mut imported = Vec::new();
+        for policy in legacy_policies(root)? {
+            let uri = index_key(&policy.uri)?;
+            let digest = self.write_blob(&std::fs::read(&policy.local_path)?)?;
+            let pulled_at = std::fs::metadata(&policy.local_path)?
+                .modified()
+                .map(unix_timestamp)
+                .unwrap_or_else(|_| now());
+            index.policies.insert(
+                uri.clone(),
+                IndexEntry {
+                    digest: digest.clone(),
+                    pulled_at,
+                    manifest_digest: None,
+                    etag: None,
+                    signature_digest: None,
+                },
+            );
+            imported.push(Policy {
+                uri,
+                local_path: self.blob_path(&digest),
+            });
+        }
+        Ok(imported)
+    }
+
+    /// Takes the lock that serializes the updates of the index, shared with
+    /// the other processes using the store. The lock is released when the
+    /// returned file is dropped.
+    fn lock_index(&self) -> StoreResult<File> {
+        std::fs::create_dir_all(&self.root)?;
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.root.join(INDEX_LOCK_FILE))?;
+        lock.lock()?;
+        Ok(lock)
+    }
+
+    /// Loads the index, for read-only operations. The index is replaced
+    /// atomically, hence reading it doesn't require the lock. The lock is
+    /// taken only to migrate a store created by a previous release.
+    fn load_index(&self) -> StoreResult<Index> {
+        if !self.index_path().exists() {
+            if !self.root.exists() {
+                return Ok(Index {
+                    version: INDEX_VERSION,
+                    ..Default::default()
+                });
+            }
+            let _lock = self.lock_index()?;
+            return self.read_index();
+        }
+
+        self.read_index()
+    }
 
-        if !scheme::is_known_remote_scheme(uri.scheme()) {
-            return Err(StoreError::UnknownSchemeError(uri.scheme().to_owned()));
+    /// Loads the index, migrating the store created by a previous release
+    /// when the index doesn't exist yet. Must be called with the index lock
+    /// held.
+    fn read_index(&self) -> StoreResult<Index> {
+        let index_path = self.index_path();
+        if !index_path.exists() {
+            return self.migrate_legacy_layout();
         }
 
-        let policy_path = self.policy_full_path(uri.as_str(), PolicyPath::PrefixAndFilename)?;
-        if policy_path.exists() {
-            Ok(Some(Policy {
-                uri: uri.to_string(),
-                local_path: policy_path,
-            }))
-        } else {
-            Ok(None)
+        let index: Index = serde_json::from_slice(&std::fs::read(&index_path)?)
+            .map_err(StoreError::CorruptedIndexError)?;
+        if index.version != INDEX_VERSION {
+            return Err(StoreError::UnsupportedIndexVersionError(index.version));
         }
+        Ok(index)
     }
 
-    /// Get a policy that matches the given SHA prefix, if it exists.
-    pub fn get_policy_by_sha_prefix(&self, sha_prefix: &str) -> StoreResult<Option<Policy>> {
-        self.list()?.into_iter().try_fold(None, |acc, policy| {
-            if !policy.digest()?.starts_with(sha_prefix) {
-                return Ok(acc);
-            }
+    /// Import the policies stored using the layout of the previous releases,
+    /// then remove them from their old location. Must be called with the
+    /// index lock held.
+    fn migrate_legacy_layout(&self) -> StoreResult<Index> {
+        let mut index = Index {
+            version: INDEX_VERSION,
+            ..Default::default()
+        };
+        if !self.root.exists() {
+            return Ok(index);
+        }
 
-            if acc.is_some() {
-                Err(StoreError::MultiplePoliciesFoundError(
-                    sha_prefix.to_owned(),
-                ))
-            } else {
-                Ok(Some(policy))
+        let imported = self.import_legacy_policies(&self.root, &mut index)?;
+        if imported.is_empty() {
+            return Ok(index);
+        }
+        self.write_index(&index)?;
+        info!(
+            policies = imported.len(),
+            "policies migrated to the content addressable store"
+        );
+
+        for scheme in std::fs::read_dir(&self.root)? {
+            let scheme = scheme?;
+            if scheme
+                .file_name()
+                .to_str()
+                .is_some_and(scheme::is_known_remote_scheme)
+            {
+                std::fs::remove_dir_all(scheme.path())?;
             }
-        })
+        }
+
+        Ok(index)
+    }
+
+    fn write_index(&self, index: &Index) -> StoreResult<()> {
+        let index = Index {
+            version: INDEX_VERSION,
+            policies: index.policies.clone(),
+        };
+        let contents =
+            serde_json::to_vec_pretty(&index).map_err(StoreError::CorruptedIndexError)?;
+        self.write_atomically(&self.index_path(), &contents)
+    }
+
+    /// Writes the module inside of the blobs directory, returns its digest
+    fn write_blob(&self, module: &[u8]) -> StoreResult<String> {
+        let digest = format!("{DIGEST_ALGORITHM}:{}", hex::encode(Sha256::digest(module)));
+        self.write_atomically(&self.blob_path(&digest), module)?;
+        Ok(digest)
+    }
+
+    /// Writes the file by renaming a temporary one, this prevents readers
+    /// from seeing partially written files
+    fn write_atomically(&self, path: &Path, contents: &[u8]) -> StoreResult<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.tmp_path("write");
+        std::fs::create_dir_all(self.root.join(TMP_DIR))?;
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    fn tmp_path(&self, prefix: &str) -> PathBuf {
+        self.root.join(TMP_DIR).join(format!(
+            "{prefix}-{}-{}",
+            std::process::id(),
+            TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ))
     }
 }
 
@@ -231,51 +696,110 @@ fn host_and_port(url: &Url) -> String {
         .unwrap_or_default()
 }
 
+/// Returns the key used to index the policy coming from `uri`.
+/// Registry references without tag and digest are indexed using the
+/// `latest` tag, like it's done when pulling them.
+fn index_key(uri: &str) -> StoreResult<String> {
+    let url = Url::parse(uri)?;
+    if !scheme::is_known_remote_scheme(url.scheme()) {
+        return Err(StoreError::UnknownSchemeError(url.scheme().to_owned()));
+    }
+
+    let uri = url.to_string();
+    if url.scheme() == "registry" {
+        let name = url.path().rsplit('/').next().unwrap_or_default();
+        if !name.contains(':') && !name.contains('@') {
+            return Ok(format!("{uri}:latest"));
+        }
+    }
+    Ok(uri)
+}
+
+/// Lists the policies stored inside of `root` using the layout of the
+/// previous releases
+fn legacy_policies(root: &Path) -> StoreResult<Vec<Policy>> {
+    let mut policies = Vec::new();
+
+    if !root.exists() {
+        return Ok(policies);
+    }
+
+    for scheme in std::fs::read_dir(root)? {
+        let scheme = scheme?;
+        match scheme.file_name().to_str() {
+            Some(scheme) => {
+                if !scheme::is_known_remote_scheme(scheme) {
+                    continue;
+                }
+            }
+            None => continue,
+        }
+        for host in std::fs::read_dir(scheme.path())? {
+            let host = host?;
+            for policy in WalkDir::new(host.path()) {
+                let policy = policy?;
+
+                let metadata = std::fs::metadata(policy.path())?;
+                if metadata.is_file() {
+                    let policy_store_path =
+                        Path::new("/").join(policy.path().strip_prefix(host.path())?);
+                    policies.push(Policy {
+                        uri: format!(
+                            "{}://{}{}",
+                            scheme.file_name().to_str().unwrap(),
+                            path::decode_path(host.file_name())?.to_str().unwrap(),
+                            path::decode_path(policy_store_path)?.to_slash_lossy()
+                        ),
+                        local_path: policy.path().to_path_buf(),
+                    })
+                }
+            }
+        }
+    }
+    Ok(policies)
+}
+
+/// Returns the digest of the file, in the `sha256:<hex>` format
+fn file_digest(path: &Path) -> StoreResult<String> {
+    Ok(format!(
+        "{DIGEST_ALGORITHM}:{}",
+        hex::encode(Sha256::digest(std::fs::read(path)?))
+    ))
+}
+
+fn remove_file_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Returns `true` when the temporary file, or directory, has not been
+/// modified for a while
+fn is_stale(metadata: &std::fs::Metadata) -> bool {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > STALE_TMP_AGE)
+}
+
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn now() -> u64 {
+    unix_timestamp(SystemTime::now())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::Store;
     use rstest::rstest;
 
-    #[rstest(
-        input_url,
-        input_policy_path,
-        expected_relative_path,
-        case(
-            "registry://ghcr.io/kubewarden/policies/pod-privileged:v0.2.2",
-            PolicyPath::PrefixAndFilename,
-            "registry/ghcr.io/kubewarden/policies/pod-privileged:v0.2.2"
-        ),
-        case(
-            "https://github.com/kubewarden/pod-privileged-policy/releases/download/v0.1.6/policy.wasm ",
-            PolicyPath::PrefixAndFilename,
-            "https/github.com/kubewarden/pod-privileged-policy/releases/download/v0.1.6/policy.wasm"
-        ),
-        case(
-            "registry://ghcr.io/kubewarden/policies/pod-privileged:v0.2.2",
-            PolicyPath::PrefixOnly,
-            "registry/ghcr.io/kubewarden/policies"
-        ),
-        case(
-            "https://github.com/kubewarden/pod-privileged-policy/releases/download/v0.1.6/policy.wasm ",
-            PolicyPath::PrefixOnly,
-            "https/github.com/kubewarden/pod-privileged-policy/releases/download/v0.1.6"
-        )
-    )]
-    fn policy_full_path(
-        input_url: &str,
-        input_policy_path: PolicyPath,
-        expected_relative_path: &str,
-    ) {
-        let default = Store::default();
-        let path = default.policy_full_path(input_url, input_policy_path);
-        assert!(path.is_ok());
-        assert_eq!(
-            default.root.join(path::encode_path(expected_relative_path)),
-            path.unwrap_or_default()
-        );
-    }
-
     #[rstest(
         input_url,
         input_policy_path,
@@ -312,4 +836,37 @@ mod tests {
 
         Ok(())
     }
+
+    #[rstest]
+    #[case::registry_with_tag(
+        "registry://ghcr.io/kubewarden/policies/pod-privileged:v0.2.2",
+        "registry://ghcr.io/kubewarden/policies/pod-privileged:v0.2.2"
+    )]
+    #[case::registry_without_tag(
+        "registry://ghcr.io/kubewarden/policies/pod-privileged",
+        "registry://ghcr.io/kubewarden/policies/pod-privileged:latest"
+    )]
+    #[case::registry_with_port_without_tag(
+        "registry://localhost:5000/pod-privileged",
+        "registry://localhost:5000/pod-privileged:latest"
+    )]
+    #[case::registry_with_digest(
+        "registry://ghcr.io/kubewarden/policies/pod-privileged@sha256:72b4569c3daee67abeaa64192fb53895d0edb2d44fa6e1d9d4c5d3f8ece09f6e",
+        "registry://ghcr.io/kubewarden/policies/pod-privileged@sha256:72b4569c3daee67abeaa64192fb53895d0edb2d44fa6e1d9d4c5d3f8ece09f6e"
+    )]
+    #[case::https(
+        "https://github.com/kubewarden/pod-privileged-policy/releases/download/v0.1.6/policy.wasm",
+        "https://github.com/kubewarden/pod-privileged-policy/releases/download/v0.1.6/policy.wasm"
+    )]
+    fn compute_index_key(#[case] uri: &str, #[case] expected: &str) {
+        assert_eq!(index_key(uri).unwrap(), expected);
+    }
+
+    #[test]
+    fn index_key_of_unknown_scheme() {
+        assert!(matches!(
+            index_key("ftp://internal.host.company/policy.wasm"),
+            Err(StoreError::UnknownSchemeError(_))
+        ));
+    }
 }
@@ -0,0 +1,698 @@
+//! Verification of the in-toto attestations of policies, like their SLSA provenance.
+//!
+//! `cosign attest` stores the attestations of an image inside of the
+//! `sha256-<digest>.att` tag of its repository. Each layer is a DSSE envelope
+//! wrapping an in-toto statement about the image. The envelopes are signed
+//! like the image signatures: the certificate and the Rekor bundle of keyless
+//! signatures are stored inside of the annotations of the layer.
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sigstore::{
+    cosign::{self, signature_layers::SignatureLayer},
+    trust::TrustRoot,
+};
+use std::collections::BTreeMap;
+use tracing::{debug, info, warn};
+
+use crate::{
+    Registry,
+    errors::FailedToParseYamlDataError,
+    oci_layout::{self, Artifact, Descriptor},
+    registry::build_fully_resolved_reference,
+    sources::Sources,
+    store::Store,
+    verify::{
+        ATTESTATIONS_SUFFIX,
+        bundle::{self, LocalBundle, SignedContent},
+        config::AttestationRequirement,
+        cosign_ref_name,
+        errors::{VerifyError, VerifyResult},
+    },
+};
+
+pub const DSSE_ENVELOPE_MEDIA_TYPE: &str = "application/vnd.dsse.envelope.v1+json";
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+pub const SLSA_PROVENANCE_V0_2_PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v0.2";
+pub const SLSA_PROVENANCE_V1_PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+
+/// An attestation satisfying the requirements of the verification config,
+/// together with the facts about the build recorded by its SLSA provenance
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    pub predicate_type: String,
+    pub builder_id: Option<String>,
+    pub source_repository: Option<String>,
+    pub source_ref: Option<String>,
+}
+
+/// An attestation whose signature has been verified
+pub(crate) struct TrustedAttestation {
+    layer: SignatureLayer,
+    attestation: Attestation,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    payload_type: String,
+    /// base64 encoded payload
+    payload: String,
+    signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeSignature {
+    /// base64 encoded signature
+    sig: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Statement {
+    #[serde(default)]
+    subject: Vec<StatementSubject>,
+    predicate_type: String,
+    #[serde(default)]
+    predicate: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct StatementSubject {
+    #[serde(default)]
+    digest: BTreeMap<String, String>,
+}
+
+/// The message signed by DSSE envelopes: the pre-authentication encoding of
+/// the payload and of its type
+pub(crate) fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    message.extend_from_slice(payload);
+    message
+}
+
+/// The payload of a message produced by [`pae`]
+pub(crate) fn pae_payload(message: &[u8]) -> Option<&[u8]> {
+    let rest = message.strip_prefix(b"DSSEv1 ")?;
+    let (payload_type_length, rest) = split_length(rest)?;
+    let rest = rest.get(payload_type_length..)?.strip_prefix(b" ")?;
+    let (payload_length, payload) = split_length(rest)?;
+    (payload.len() == payload_length).then_some(payload)
+}
+
+fn split_length(data: &[u8]) -> Option<(usize, &[u8])> {
+    let end = data.iter().position(|&byte| byte == b' ')?;
+    let length = std::str::from_utf8(&data[..end]).ok()?.parse().ok()?;
+    Some((length, &data[end + 1..]))
+}
+
+/// Fetches the attestations of the image, discarding the ones that cannot be
+/// verified. `image_digest` is the verified manifest digest of the image.
+pub(crate) async fn fetch_attestations(
+    image_url: &str,
+    image_digest: &str,
+    sources: Option<&Sources>,
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<Vec<TrustedAttestation>> {
+    let reference = build_fully_resolved_reference(image_url)?;
+    let attestations_url = format!(
+        "registry://{}",
+        cosign_ref_name(&reference, image_digest, ATTESTATIONS_SUFFIX)
+    );
+    let artifact = Registry::new()
+        .pull_artifact(&attestations_url, sources)
+        .await
+        .map_err(|e| {
+            VerifyError::AttestationVerificationError(format!(
+                "cannot fetch the attestations of image {image_url}: {e}"
+            ))
+        })?;
+    verify_attestations(image_url, image_digest, &artifact, trust_root)
+}
+
+/// Reads the attestations of the image from the store, like
+/// [`fetch_attestations`] does from the registry
+pub(crate) fn stored_attestations(
+    store: &Store,
+    image_url: &str,
+    image_digest: &str,
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<Vec<TrustedAttestation>> {
+    let reference = build_fully_resolved_reference(image_url)?;
+    let artifact = store
+        .registry_artifact(&cosign_ref_name(
+            &reference,
+            image_digest,
+            ATTESTATIONS_SUFFIX,
+        ))
+        .map_err(|e| {
+            VerifyError::AttestationVerificationError(format!(
+                "cannot read the attestations of image {image_url}: {e}"
+            ))
+        })?
+        .ok_or_else(|| {
+            VerifyError::AttestationVerificationError(format!(
+                "no attestations of image {image_url} inside of the store"
+            ))
+        })?;
+    verify_attestations(image_url, image_digest, &artifact, trust_root)
+}
+
+/// Verifies the attestations held by the given cosign artifact, discarding
+/// the ones that cannot be verified
+fn verify_attestations(
+    image_url: &str,
+    image_digest: &str,
+    artifact: &Artifact,
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<Vec<TrustedAttestation>> {
+    let descriptors = oci_layout::referenced_blobs(&artifact.manifest).map_err(|e| {
+        VerifyError::AttestationVerificationError(format!(
+            "invalid attestations manifest of image {image_url}: {e}"
+        ))
+    })?;
+
+    let attestations = descriptors
+        .iter()
+        .filter(|descriptor| descriptor.media_type == DSSE_ENVELOPE_MEDIA_TYPE)
+        .filter_map(|descriptor| {
+            let blob = artifact.blobs.get(&descriptor.digest)?;
+            match verify_attestation(image_url, image_digest, descriptor, blob, trust_root) {
+                Ok(attestation) => Some(attestation),
+                Err(error) => {
+                    warn!(
+                        image = image_url,
+                        layer = descriptor.digest,
+                        %error,
+                        "discarding attestation"
+                    );
+                    None
+                }
+            }
+        })
+        .collect();
+    Ok(attestations)
+}
+
+/// Verifies the signature of the DSSE envelope stored inside of an attestation
+/// layer, and ensures its statement refers to the image
+fn verify_attestation(
+    image_url: &str,
+    image_digest: &str,
+    descriptor: &Descriptor,
+    blob: &[u8],
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<TrustedAttestation> {
+    if format!("sha256:{}", hex::encode(Sha256::digest(blob))) != descriptor.digest {
+        return Err(VerifyError::AttestationVerificationError(
+            "the digest of the layer doesn't match with its descriptor".to_string(),
+        ));
+    }
+    let envelope: Envelope = serde_json::from_slice(blob).map_err(|e| {
+        VerifyError::AttestationVerificationError(format!("invalid DSSE envelope: {e}"))
+    })?;
+    if envelope.payload_type != IN_TOTO_PAYLOAD_TYPE {
+        return Err(VerifyError::AttestationVerificationError(format!(
+            "unsupported payload type: {}",
+            envelope.payload_type
+        )));
+    }
+    let payload = STANDARD.decode(&envelope.payload).map_err(|e| {
+        VerifyError::AttestationVerificationError(format!("invalid DSSE payload: {e}"))
+    })?;
+    let signature = envelope.signatures.first().ok_or_else(|| {
+        VerifyError::AttestationVerificationError("the DSSE envelope is not signed".to_string())
+    })?;
+
+    let bundle = LocalBundle::from_cosign_layer(&signature.sig, descriptor)?;
+    let content = SignedContent::Envelope {
+        payload_type: &envelope.payload_type,
+        payload: &payload,
+    };
+    let layer =
+        bundle::verify_signed_content(image_url, image_digest, &content, &bundle, trust_root)?;
+
+    let statement = parse_statement(&payload, image_digest)?;
+    Ok(TrustedAttestation {
+        layer,
+        attestation: attestation_facts(&statement),
+    })
+}
+
+/// Parses the in-toto statement, which must refer to the image
+fn parse_statement(payload: &[u8], image_digest: &str) -> VerifyResult<Statement> {
+    let statement: Statement = serde_json::from_slice(payload).map_err(|e| {
+        VerifyError::AttestationVerificationError(format!("invalid in-toto statement: {e}"))
+    })?;
+    let image_digest = image_digest.strip_prefix("sha256:").unwrap_or(image_digest);
+    let refers_to_image = statement
+        .subject
+        .iter()
+        .any(|subject| subject.digest.get("sha256").map(String::as_str) == Some(image_digest));
+    if !refers_to_image {
+        return Err(VerifyError::AttestationVerificationError(
+            "the in-toto statement refers to a different image".to_string(),
+        ));
+    }
+    Ok(statement)
+}
+
+/// Reads the facts about the build from the SLSA provenance predicates. Only
+/// the predicate type is known about the other predicates.
+fn attestation_facts(statement: &Statement) -> Attestation {
+    let predicate = &statement.predicate;
+    let field = |pointer| {
+        predicate
+            .pointer(pointer)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+
+    let (builder_id, source_repository, source_ref) = match statement.predicate_type.as_str() {
+        SLSA_PROVENANCE_V0_2_PREDICATE_TYPE => {
+            let (repository, git_ref) = field("/invocation/configSource/uri")
+                .or_else(|| field("/materials/0/uri"))
+                .map(|uri| split_source_uri(&uri))
+                .unzip();
+            (field("/builder/id"), repository, git_ref.flatten())
+        }
+        SLSA_PROVENANCE_V1_PREDICATE_TYPE => {
+            let (repository, git_ref) = field("/buildDefinition/resolvedDependencies/0/uri")
+                .map(|uri| split_source_uri(&uri))
+                .unzip();
+            (
+                field("/runDetails/builder/id"),
+                field("/buildDefinition/externalParameters/workflow/repository")
+                    .map(|repository| normalize_repository(&repository))
+                    .or(repository),
+                field("/buildDefinition/externalParameters/workflow/ref").or(git_ref.flatten()),
+            )
+        }
+        _ => (None, None, None),
+    };
+
+    Attestation {
+        predicate_type: statement.predicate_type.clone(),
+        builder_id,
+        source_repository,
+        source_ref,
+    }
+}
+
+/// Splits a source URI, like `git+https://github.com/kubewarden/policy@refs/tags/v1.0.0`,
+/// into its repository and its git reference
+fn split_source_uri(uri: &str) -> (String, Option<String>) {
+    // the host can hold credentials, the reference follows the path
+    let path_start = uri
+        .find("://")
+        .and_then(|scheme_end| {
+            uri[scheme_end + 3..]
+                .find('/')
+                .map(|path_start| scheme_end + 3 + path_start)
+        })
+        .unwrap_or(0);
+    match uri[path_start..].rfind('@') {
+        Some(at) => (
+            normalize_repository(&uri[..path_start + at]),
+            Some(uri[path_start + at + 1..].to_string()),
+        ),
+        None => (normalize_repository(uri), None),
+    }
+}
+
+fn normalize_repository(repository: &str) -> String {
+    let repository = repository.strip_prefix("git+").unwrap_or(repository);
+    repository
+        .strip_suffix(".git")
+        .unwrap_or(repository)
+        .to_string()
+}
+
+impl AttestationRequirement {
+    fn is_satisfied_by(&self, trusted_attestation: &TrustedAttestation) -> bool {
+        let attestation = &trusted_attestation.attestation;
+        let matches = |expected: &Option<String>, actual: &Option<String>| {
+            expected.is_none() || expected == actual
+        };
+        if attestation.predicate_type != self.predicate_type
+            || !matches(&self.builder_id, &attestation.builder_id)
+            || !matches(
+                &self.source_repository.as_deref().map(normalize_repository),
+                &attestation.source_repository,
+            )
+            || !matches(&self.source_ref, &attestation.source_ref)
+        {
+            return false;
+        }
+
+        let layers = std::slice::from_ref(&trusted_attestation.layer);
+        self.signatures
+            .iter()
+            .any(|signature| match signature.verifier() {
+                Ok(verifier) => {
+                    let constraints = [verifier];
+                    cosign::verify_constraints(layers, constraints.iter()).is_ok()
+                }
+                Err(error) => {
+                    info!(?error, ?signature, "Cannot create verifier for signature");
+                    false
+                }
+            })
+    }
+}
+
+/// Ensures each requirement is satisfied by at least one of the attestations.
+/// Returns the attestations satisfying the requirements, in the same order.
+pub(crate) fn verify_attestations_against_requirements(
+    requirements: &[AttestationRequirement],
+    attestations: &[TrustedAttestation],
+) -> VerifyResult<Vec<Attestation>> {
+    let mut verified = Vec::new();
+    let mut unsatisfied = Vec::new();
+    for requirement in requirements {
+        match attestations
+            .iter()
+            .find(|attestation| requirement.is_satisfied_by(attestation))
+        {
+            Some(attestation) => {
+                debug!(
+                    predicate_type = requirement.predicate_type,
+                    "attestation requirement satisfied"
+                );
+                verified.push(attestation.attestation.clone());
+            }
+            None => unsatisfied.push(requirement),
+        }
+    }
+
+    if !unsatisfied.is_empty() {
+        let mut errormsg = "Image verification failed: missing attestations\n".to_string();
+        errormsg.push_str("The following attestations were not satisfied:\n");
+        for requirement in unsatisfied {
+            errormsg
+                .push_str(&serde_yaml::to_string(requirement).map_err(FailedToParseYamlDataError)?);
+        }
+        return Err(VerifyError::AttestationVerificationError(errormsg));
+    }
+    Ok(verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::config::{Signature, Subject};
+    use rstest::rstest;
+    use sigstore::cosign::{
+        payload::simple_signing::SimpleSigning,
+        signature_layers::{CertificateSignature, CertificateSubject},
+    };
+
+    const IMAGE_DIGEST: &str =
+        "sha256:5f481572d088dc4023afb35fced9530ced3d9b03bf7299c6f492163cb9f0452e";
+
+    fn statement(predicate_type: &str, predicate: serde_json::Value) -> Statement {
+        serde_json::from_value(serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{
+                "name": "ghcr.io/kubewarden/policies/pod-privileged",
+                "digest": { "sha256": IMAGE_DIGEST.strip_prefix("sha256:").unwrap() }
+            }],
+            "predicateType": predicate_type,
+            "predicate": predicate,
+        }))
+        .unwrap()
+    }
+
+    fn provenance_v1() -> Statement {
+        statement(
+            SLSA_PROVENANCE_V1_PREDICATE_TYPE,
+            serde_json::json!({
+                "buildDefinition": {
+                    "buildType": "https://slsa-framework.github.io/github-actions-buildtypes/workflow/v1",
+                    "externalParameters": {
+                        "workflow": {
+                            "ref": "refs/tags/v1.0.0",
+                            "repository": "https://github.com/kubewarden/pod-privileged-policy",
+                            "path": ".github/workflows/release.yml"
+                        }
+                    },
+                    "resolvedDependencies": [{
+                        "uri": "git+https://github.com/kubewarden/pod-privileged-policy@refs/tags/v1.0.0"
+                    }]
+                },
+                "runDetails": {
+                    "builder": {
+                        "id": "https://github.com/kubewarden/github-actions/.github/workflows/reusable-release-policy-rust.yml@refs/tags/v3.0.0"
+                    }
+                }
+            }),
+        )
+    }
+
+    fn trusted_attestation(subject: &str, statement: &Statement) -> TrustedAttestation {
+        let pub_key = r#"-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAELKhD7F5OKy77Z582Y6h0u1J3GNA+
+kvUsh4eKpd1lwkDAzfFDs7yXEExsEkPPuiQJBelDT68n7PDIWB/QEY7mrA==
+-----END PUBLIC KEY-----"#;
+        let simple_signing: SimpleSigning = serde_json::from_value(serde_json::json!({
+            "critical": {
+                "identity": { "docker-reference": "ghcr.io/kubewarden/policies/pod-privileged" },
+                "image": { "docker-manifest-digest": IMAGE_DIGEST },
+                "type": "cosign container image signature"
+            },
+            "optional": null
+        }))
+        .unwrap();
+
+        TrustedAttestation {
+            layer: SignatureLayer {
+                simple_signing,
+                oci_digest: "not relevant".to_string(),
+                certificate_signature: Some(CertificateSignature {
+                    verification_key: sigstore::crypto::CosignVerificationKey::try_from_pem(
+                        pub_key.as_bytes(),
+                    )
+                    .unwrap(),
+                    issuer: Some("https://token.actions.githubusercontent.com".to_string()),
+                    subject: CertificateSubject::Uri(subject.to_string()),
+                    github_workflow_trigger: None,
+                    github_workflow_sha: None,
+                    github_workflow_name: None,
+                    github_workflow_repository: None,
+                    github_workflow_ref: None,
+                }),
+                bundle: None,
+                signature: None,
+                raw_data: Vec::new(),
+            },
+            attestation: attestation_facts(statement),
+        }
+    }
+
+    fn requirement(
+        builder_id: Option<&str>,
+        source_repository: Option<&str>,
+        source_ref: Option<&str>,
+    ) -> AttestationRequirement {
+        AttestationRequirement {
+            predicate_type: SLSA_PROVENANCE_V1_PREDICATE_TYPE.to_string(),
+            signatures: vec![Signature::GenericIssuer {
+                issuer: "https://token.actions.githubusercontent.com".to_string(),
+                subject: Subject::UrlPrefix("https://github.com/kubewarden/".parse().unwrap()),
+                annotations: None,
+            }],
+            builder_id: builder_id.map(|value| value.to_string()),
+            source_repository: source_repository.map(|value| value.to_string()),
+            source_ref: source_ref.map(|value| value.to_string()),
+        }
+    }
+
+    #[test]
+    fn pre_authentication_encoding() {
+        let message = pae("http://example.com/HelloWorld", b"hello world");
+        assert_eq!(
+            message,
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world"
+        );
+        assert_eq!(pae_payload(&message), Some(b"hello world".as_slice()));
+    }
+
+    #[rstest]
+    #[case::not_dsse(b"hello world")]
+    #[case::wrong_payload_length(b"DSSEv1 4 type 3 hello")]
+    #[case::wrong_type_length(b"DSSEv1 10 type 5 hello")]
+    #[case::invalid_length(b"DSSEv1 four type 5 hello")]
+    fn invalid_pre_authentication_encoding(#[case] message: &[u8]) {
+        assert!(pae_payload(message).is_none());
+    }
+
+    #[rstest]
+    #[case::with_ref(
+        "git+https://github.com/kubewarden/policy@refs/tags/v1.0.0",
+        "https://github.com/kubewarden/policy",
+        Some("refs/tags/v1.0.0")
+    )]
+    #[case::without_ref(
+        "https://github.com/kubewarden/policy.git",
+        "https://github.com/kubewarden/policy",
+        None
+    )]
+    #[case::credentials(
+        "git+ssh://git@github.com/kubewarden/policy",
+        "ssh://git@github.com/kubewarden/policy",
+        None
+    )]
+    fn split_source_uris(
+        #[case] uri: &str,
+        #[case] repository: &str,
+        #[case] git_ref: Option<&str>,
+    ) {
+        assert_eq!(
+            split_source_uri(uri),
+            (
+                repository.to_string(),
+                git_ref.map(|value| value.to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn slsa_provenance_v1_facts() {
+        assert_eq!(
+            attestation_facts(&provenance_v1()),
+            Attestation {
+                predicate_type: SLSA_PROVENANCE_V1_PREDICATE_TYPE.to_string(),
+                builder_id: Some("https://github.com/kubewarden/github-actions/.github/workflows/reusable-release-policy-rust.yml@refs/tags/v3.0.0".to_string()),
+                source_repository: Some("https://github.com/kubewarden/pod-privileged-policy".to_string()),
+                source_ref: Some("refs/tags/v1.0.0".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn slsa_provenance_v0_2_facts() {
+        let statement = statement(
+            SLSA_PROVENANCE_V0_2_PREDICATE_TYPE,
+            serde_json::json!({
+                "builder": { "id": "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_container_slsa3.yml@refs/tags/v1.9.0" },
+                "invocation": {
+                    "configSource": {
+                        "uri": "git+https://github.com/kubewarden/pod-privileged-policy@refs/heads/main",
+                        "entryPoint": ".github/workflows/release.yml"
+                    }
+                }
+            }),
+        );
+        assert_eq!(
+            attestation_facts(&statement),
+            Attestation {
+                predicate_type: SLSA_PROVENANCE_V0_2_PREDICATE_TYPE.to_string(),
+                builder_id: Some("https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_container_slsa3.yml@refs/tags/v1.9.0".to_string()),
+                source_repository: Some("https://github.com/kubewarden/pod-privileged-policy".to_string()),
+                source_ref: Some("refs/heads/main".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn other_predicate_facts() {
+        let statement = statement(
+            "https://cyclonedx.org/bom",
+            serde_json::json!({ "bomFormat": "CycloneDX" }),
+        );
+        assert_eq!(
+            attestation_facts(&statement),
+            Attestation {
+                predicate_type: "https://cyclonedx.org/bom".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::same_image(IMAGE_DIGEST, true)]
+    #[case::other_image(
+        "sha256:0000000000000000000000000000000000000000000000000000000000000000",
+        false
+    )]
+    fn statement_subject(#[case] image_digest: &str, #[case] valid: bool) {
+        let payload = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{
+                "name": "ghcr.io/kubewarden/policies/pod-privileged",
+                "digest": { "sha256": IMAGE_DIGEST.strip_prefix("sha256:").unwrap() }
+            }],
+            "predicateType": SLSA_PROVENANCE_V1_PREDICATE_TYPE,
+            "predicate": {}
+        })
+        .to_string();
+        assert_eq!(
+            parse_statement(payload.as_bytes(), image_digest).is_ok(),
+            valid
+        );
+    }
+
+    #[rstest]
+    #[case::predicate_type_only(requirement(None, None, None), true)]
+    #[case::all_facts(
+        requirement(
+            Some(
+                "https://github.com/kubewarden/github-actions/.github/workflows/reusable-release-policy-rust.yml@refs/tags/v3.0.0"
+            ),
+            Some("https://github.com/kubewarden/pod-privileged-policy.git"),
+            Some("refs/tags/v1.0.0"),
+        ),
+        true
+    )]
+    #[case::other_builder(
+        requirement(Some("https://github.com/acme/builder"), None, None),
+        false
+    )]
+    #[case::other_repository(
+        requirement(None, Some("https://github.com/acme/pod-privileged-policy"), None),
+        false
+    )]
+    #[case::other_ref(requirement(None, None, Some("refs/heads/main")), false)]
+    #[case::other_predicate_type(
+        AttestationRequirement {
+            predicate_type: SLSA_PROVENANCE_V0_2_PREDICATE_TYPE.to_string(),
+            ..requirement(None, None, None)
+        },
+        false
+    )]
+    fn attestation_requirements(
+        #[case] requirement: AttestationRequirement,
+        #[case] satisfied: bool,
+    ) {
+        let attestations = [trusted_attestation(
+            "https://github.com/kubewarden/pod-privileged-policy/.github/workflows/release.yml@refs/tags/v1.0.0",
+            &provenance_v1(),
+        )];
+        let result = verify_attestations_against_requirements(&[requirement], &attestations);
+        assert_eq!(result.is_ok(), satisfied, "{result:?}");
+    }
+
+    #[test]
+    fn attestation_signed_by_other_identity() {
+        let attestations = [trusted_attestation(
+            "https://github.com/acme/pod-privileged-policy/.github/workflows/release.yml@refs/tags/v1.0.0",
+            &provenance_v1(),
+        )];
+        let result = verify_attestations_against_requirements(
+            &[requirement(None, None, None)],
+            &attestations,
+        );
+        assert!(matches!(
+            result,
+            Err(VerifyError::AttestationVerificationError(_))
+        ));
+    }
+}
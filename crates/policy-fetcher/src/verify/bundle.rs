@@ -0,0 +1,1418 @@
+//! Verification of policies using local Sigstore bundles.
+//!
+//! A bundle holds everything needed to verify the signature of a Wasm module
+//! without contacting a registry: the signature, the Fulcio certificate of
+//! keyless signatures and the Rekor entry proving when the signature was
+//! made. Both the bundles created by `cosign sign-blob --bundle` and the
+//! ones following the Sigstore protobuf specs (`*.sigstore.json`) are
+//! supported, as well as the detached signatures created by
+//! `cosign sign-blob --output-signature` (`*.sig`).
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use sigstore::{
+    cosign::{
+        payload::simple_signing::SimpleSigning,
+        signature_layers::{CertificateSignature, SignatureLayer},
+    },
+    crypto::{CosignVerificationKey, Signature},
+    trust::TrustRoot,
+};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use x509_parser::{pem::parse_x509_pem, prelude::*};
+
+use crate::{
+    oci_layout::Descriptor,
+    store::Store,
+    verify::{
+        attestation,
+        errors::{VerifyError, VerifyResult},
+        fulcio::{self, FulcioIdentity},
+    },
+};
+
+/// The extensions of the bundles looked up next to a Wasm module, in order
+/// of preference: `policy.wasm.sigstore.json`, `policy.wasm.bundle`, then
+/// the detached signature `policy.wasm.sig`
+pub const BUNDLE_EXTENSIONS: [&str; 3] = ["sigstore.json", "bundle", "sig"];
+
+const SIGSTORE_BUNDLE_MEDIA_TYPE_PREFIX: &str = "application/vnd.dev.sigstore.bundle";
+
+/// The annotations holding the Fulcio certificate and the Rekor bundle of the
+/// layers of the signatures and of the attestations published by cosign
+const CERTIFICATE_ANNOTATION: &str = "dev.sigstore.cosign/certificate";
+const BUNDLE_ANNOTATION: &str = "dev.sigstore.cosign/bundle";
+
+/// Looks for a bundle sitting next to the Wasm module referenced by `policy_url`.
+/// Only `file://` URLs can have a bundle next to them.
+pub fn find_bundle(policy_url: &str) -> Option<PathBuf> {
+    let module_path = url::Url::parse(policy_url)
+        .ok()
+        .filter(|url| url.scheme() == "file")?
+        .to_file_path()
+        .ok()?;
+    bundle_paths(&module_path)
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+/// Looks for the bundle, or the detached signature, published next to a
+/// policy served over HTTP(S). It's saved inside of the store when pulling
+/// the policy.
+pub fn find_stored_bundle(store: &Store, policy_url: &str) -> Option<PathBuf> {
+    url::Url::parse(policy_url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))?;
+    store.signature_path(policy_url).ok().flatten()
+}
+
+fn bundle_paths(module_path: &Path) -> Vec<PathBuf> {
+    BUNDLE_EXTENSIONS
+        .iter()
+        .map(|extension| {
+            let mut path = module_path.as_os_str().to_owned();
+            path.push(format!(".{extension}"));
+            PathBuf::from(path)
+        })
+        .collect()
+}
+
+/// A Sigstore bundle, holding the signature of a Wasm module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalBundle {
+    signature: Vec<u8>,
+    /// DER encoded Fulcio certificate, set only for keyless signatures
+    certificate: Option<Vec<u8>>,
+    /// The sha256 digest of the signed module, when recorded by the bundle
+    message_digest: Option<Vec<u8>>,
+    tlog_entry: Option<TlogEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TlogEntry {
+    /// The base64 encoded body of the entry, as returned by Rekor
+    body: String,
+    integrated_time: i64,
+    log_index: i64,
+    /// The hex encoded sha256 digest of the public key of the log
+    log_id: String,
+    signed_entry_timestamp: Option<Vec<u8>>,
+    inclusion_proof: Option<InclusionProof>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InclusionProof {
+    log_index: u64,
+    tree_size: u64,
+    root_hash: Vec<u8>,
+    hashes: Vec<Vec<u8>>,
+    checkpoint: Option<String>,
+}
+
+impl LocalBundle {
+    /// Reads the bundle, or the detached signature, from the given file
+    pub fn from_path(path: &Path) -> VerifyResult<Self> {
+        let data = std::fs::read(path)?;
+        Self::from_bytes(&data).map_err(|e| match e {
+            VerifyError::InvalidBundleError(msg) => {
+                VerifyError::InvalidBundleError(format!("{}: {}", path.display(), msg))
+            }
+            e => e,
+        })
+    }
+
+    /// Parses either a bundle, see [`LocalBundle::from_json`], or a detached signature
+    pub fn from_bytes(data: &[u8]) -> VerifyResult<Self> {
+        if data.trim_ascii_start().starts_with(b"{") {
+            Self::from_json(data)
+        } else {
+            Self::from_detached_signature(data)
+        }
+    }
+
+    /// Creates a bundle holding only a base64 encoded signature made with a
+    /// key. Only the constraints relying on public keys can be satisfied by it.
+    pub fn from_detached_signature(signature: &[u8]) -> VerifyResult<Self> {
+        let signature = std::str::from_utf8(signature)
+            .map_err(|e| VerifyError::InvalidBundleError(format!("invalid signature: {e}")))?;
+        Ok(LocalBundle {
+            signature: decode("signature", signature.trim())?,
+            certificate: None,
+            message_digest: None,
+            tlog_entry: None,
+        })
+    }
+
+    /// Parses either a cosign bundle or a bundle following the Sigstore
+    /// protobuf specs
+    pub fn from_json(data: &[u8]) -> VerifyResult<Self> {
+        let value: serde_json::Value = serde_json::from_slice(data)
+            .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?;
+        let is_sigstore_bundle = value
+            .get("mediaType")
+            .and_then(|media_type| media_type.as_str())
+            .is_some_and(|media_type| media_type.starts_with(SIGSTORE_BUNDLE_MEDIA_TYPE_PREFIX));
+
+        if is_sigstore_bundle {
+            serde_json::from_value::<SigstoreBundle>(value)
+                .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?
+                .try_into()
+        } else {
+            serde_json::from_value::<CosignBundle>(value)
+                .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?
+                .try_into()
+        }
+    }
+
+    /// Creates a bundle from a layer of the signatures, or of the
+    /// attestations, published by cosign. The layer holds the same data of a
+    /// cosign bundle: the certificate and the Rekor bundle are stored inside
+    /// of its annotations.
+    pub(crate) fn from_cosign_layer(
+        base64_signature: &str,
+        descriptor: &Descriptor,
+    ) -> VerifyResult<Self> {
+        let annotation = |name| {
+            descriptor
+                .annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(name))
+        };
+        let rekor_bundle = annotation(BUNDLE_ANNOTATION)
+            .map(|rekor_bundle| serde_json::from_str::<serde_json::Value>(rekor_bundle))
+            .transpose()
+            .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?;
+        Self::from_json(
+            serde_json::json!({
+                "base64Signature": base64_signature,
+                "cert": annotation(CERTIFICATE_ANNOTATION).map(|pem| STANDARD.encode(pem)),
+                "rekorBundle": rekor_bundle,
+            })
+            .to_string()
+            .as_bytes(),
+        )
+    }
+}
+
+/// Verifies the signature of `module` contained inside of the bundle.
+///
+/// Keyless signatures require the Fulcio certificates and the Rekor keys of
+/// the `trust_root`: the certificate must be issued by Fulcio, and the signed
+/// entry timestamp of the Rekor entry must prove the signature was made while
+/// the certificate was valid.
+/// Signatures made with a key are checked later by the verification
+/// constraints, their Rekor entry is verified only when a trust root is given.
+///
+/// Returns a trusted signature layer, which can be checked against the
+/// verification constraints. Bundles don't carry annotations, hence the
+/// constraints requiring annotations cannot be satisfied.
+pub fn verify_bundle(
+    policy_uri: &str,
+    module: &[u8],
+    bundle: &LocalBundle,
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<SignatureLayer> {
+    let module_digest = format!("sha256:{}", hex::encode(Sha256::digest(module)));
+    verify_signed_content(
+        policy_uri,
+        &module_digest,
+        &SignedContent::Blob(module),
+        bundle,
+        trust_root,
+    )
+}
+
+/// The content signed by a bundle
+pub(crate) enum SignedContent<'a> {
+    /// A blob signed as is, like a Wasm module
+    Blob(&'a [u8]),
+    /// The payload of a DSSE envelope, like an in-toto attestation
+    Envelope {
+        payload_type: &'a str,
+        payload: &'a [u8],
+    },
+}
+
+impl SignedContent<'_> {
+    /// The message covered by the signature
+    fn message(&self) -> Vec<u8> {
+        match self {
+            SignedContent::Blob(blob) => blob.to_vec(),
+            SignedContent::Envelope {
+                payload_type,
+                payload,
+            } => attestation::pae(payload_type, payload),
+        }
+    }
+}
+
+/// Verifies the signature of `content`, which refers to the artifact
+/// identified by `reference` and `digest`. See [`verify_bundle`].
+///
+/// The raw data of the returned signature layer is the signed message, hence
+/// the constraints relying on public keys can verify it.
+pub(crate) fn verify_signed_content(
+    reference: &str,
+    digest: &str,
+    content: &SignedContent,
+    bundle: &LocalBundle,
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<SignatureLayer> {
+    let message = content.message();
+    let message_digest = Sha256::digest(&message).to_vec();
+    if let Some(bundle_digest) = &bundle.message_digest
+        && *bundle_digest != message_digest
+    {
+        return Err(VerifyError::BundleVerificationError(
+            "the digest recorded inside of the bundle doesn't match with the one of the module"
+                .to_string(),
+        ));
+    }
+
+    let integrated_time_verified = match (&bundle.tlog_entry, trust_root) {
+        (Some(tlog_entry), Some(trust_root)) => {
+            let integrated_time_verified = verify_tlog_entry(tlog_entry, trust_root)?;
+            verify_tlog_entry_body(tlog_entry, content, bundle)?;
+            integrated_time_verified
+        }
+        (Some(_), None) if bundle.certificate.is_none() => {
+            warn!(
+                policy = reference,
+                "no Rekor data available: the transparency log entry of the bundle is not verified"
+            );
+            false
+        }
+        _ => false,
+    };
+
+    let certificate_signature = match &bundle.certificate {
+        Some(certificate) => {
+            let trust_root = trust_root.ok_or_else(|| {
+                VerifyError::BundleVerificationError(
+                    "keyless signatures cannot be verified without Fulcio and Rekor data"
+                        .to_string(),
+                )
+            })?;
+            let tlog_entry = bundle.tlog_entry.as_ref().ok_or_else(|| {
+                VerifyError::BundleVerificationError(
+                    "keyless signatures require a transparency log entry".to_string(),
+                )
+            })?;
+            // the inclusion proof doesn't cover the integrated time, only
+            // the signed entry timestamp proves when the signature was made
+            if !integrated_time_verified {
+                return Err(VerifyError::BundleVerificationError(
+                    "keyless signatures require a signed entry timestamp proving when the signature was added to the transparency log".to_string(),
+                ));
+            }
+            let certificate_signature =
+                verify_certificate(certificate, tlog_entry.integrated_time, trust_root)?;
+            certificate_signature
+                .verification_key
+                .verify_signature(Signature::Raw(&bundle.signature), &message)
+                .map_err(VerifyError::KeyVerificationError)?;
+            Some(certificate_signature)
+        }
+        None => None,
+    };
+
+    let simple_signing: SimpleSigning = serde_json::from_value(serde_json::json!({
+        "critical": {
+            "identity": { "docker-reference": reference },
+            "image": { "docker-manifest-digest": digest },
+            "type": "cosign container image signature"
+        },
+        "optional": null
+    }))
+    .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?;
+
+    debug!(policy = reference, "bundle signature verified");
+    Ok(SignatureLayer {
+        simple_signing,
+        oci_digest: format!("sha256:{}", hex::encode(&message_digest)),
+        certificate_signature,
+        bundle: transparency_log_bundle(bundle, integrated_time_verified),
+        signature: Some(STANDARD.encode(&bundle.signature)),
+        raw_data: message,
+    })
+}
+
+/// The Rekor bundle of the signature layer, set only when the signed entry
+/// timestamp of the transparency log entry has been verified
+fn transparency_log_bundle<T>(bundle: &LocalBundle, verified: bool) -> Option<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let tlog_entry = bundle.tlog_entry.as_ref().filter(|_| verified)?;
+    let signed_entry_timestamp = STANDARD.encode(tlog_entry.signed_entry_timestamp.as_ref()?);
+    serde_json::from_value(serde_json::json!({
+        "SignedEntryTimestamp": signed_entry_timestamp,
+        "Payload": {
+            "body": tlog_entry.body,
+            "integratedTime": tlog_entry.integrated_time,
+            "logIndex": tlog_entry.log_index,
+            "logID": tlog_entry.log_id,
+        }
+    }))
+    .ok()
+}
+
+/// Verifies that the entry has been added to a trusted Rekor instance, using
+/// its inclusion proof and its signed entry timestamp, whichever are present.
+///
+/// Returns whether the integrated time of the entry has been verified: only
+/// the signed entry timestamp covers it, the inclusion proof doesn't.
+fn verify_tlog_entry(
+    tlog_entry: &TlogEntry,
+    trust_root: &(dyn TrustRoot + Sync),
+) -> VerifyResult<bool> {
+    let rekor_keys = trust_root.rekor_keys().map_err(|e| {
+        VerifyError::BundleVerificationError(format!("cannot read Rekor keys: {e}"))
+    })?;
+    let rekor_key = rekor_keys
+        .values()
+        .find(|key| hex::encode(Sha256::digest(key)) == tlog_entry.log_id)
+        .ok_or_else(|| {
+            VerifyError::BundleVerificationError(format!(
+                "the transparency log {} is not trusted",
+                tlog_entry.log_id
+            ))
+        })?;
+    let rekor_key = CosignVerificationKey::try_from_der(rekor_key)
+        .map_err(VerifyError::KeyVerificationError)?;
+    verify_tlog_entry_with_key(tlog_entry, &rekor_key)
+}
+
+/// Verifies the inclusion proof and the signed entry timestamp of the entry
+/// using the key of the log. See [`verify_tlog_entry`].
+fn verify_tlog_entry_with_key(
+    tlog_entry: &TlogEntry,
+    rekor_key: &CosignVerificationKey,
+) -> VerifyResult<bool> {
+    let mut included = false;
+    if let Some(proof) = &tlog_entry.inclusion_proof
+        && let Some(checkpoint) = &proof.checkpoint
+    {
+        let body = STANDARD
+            .decode(&tlog_entry.body)
+            .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?;
+        let root_hash = inclusion_proof_root(proof, &body).ok_or_else(|| {
+            VerifyError::BundleVerificationError("invalid inclusion proof".to_string())
+        })?;
+        if root_hash != proof.root_hash {
+            return Err(VerifyError::BundleVerificationError(
+                "the inclusion proof doesn't match with the root hash of the log".to_string(),
+            ));
+        }
+        verify_checkpoint(checkpoint, proof, rekor_key)?;
+        included = true;
+    }
+
+    let Some(signed_entry_timestamp) = &tlog_entry.signed_entry_timestamp else {
+        return if included {
+            Ok(false)
+        } else {
+            Err(VerifyError::BundleVerificationError(
+                "the transparency log entry has neither an inclusion proof nor a signed entry timestamp"
+                    .to_string(),
+            ))
+        };
+    };
+    rekor_key
+        .verify_signature(
+            Signature::Raw(signed_entry_timestamp),
+            &signed_entry_timestamp_payload(tlog_entry)?,
+        )
+        .map_err(|_| {
+            VerifyError::BundleVerificationError("invalid signed entry timestamp".to_string())
+        })?;
+    Ok(true)
+}
+
+/// The canonical JSON document signed by Rekor: its keys are sorted
+#[derive(Serialize)]
+struct SignedEntryTimestampPayload<'a> {
+    body: &'a str,
+    #[serde(rename = "integratedTime")]
+    integrated_time: i64,
+    #[serde(rename = "logID")]
+    log_id: &'a str,
+    #[serde(rename = "logIndex")]
+    log_index: i64,
+}
+
+fn signed_entry_timestamp_payload(tlog_entry: &TlogEntry) -> VerifyResult<Vec<u8>> {
+    serde_json::to_vec(&SignedEntryTimestampPayload {
+        body: &tlog_entry.body,
+        integrated_time: tlog_entry.integrated_time,
+        log_id: &tlog_entry.log_id,
+        log_index: tlog_entry.log_index,
+    })
+    .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))
+}
+
+/// Computes the root hash of the log from the inclusion proof of the entry,
+/// as described by RFC 9162, section 2.1.3.2
+fn inclusion_proof_root(proof: &InclusionProof, body: &[u8]) -> Option<Vec<u8>> {
+    if proof.log_index >= proof.tree_size {
+        return None;
+    }
+    let mut f_n = proof.log_index;
+    let mut s_n = proof.tree_size - 1;
+    let mut root = Sha256::new()
+        .chain_update([0x00])
+        .chain_update(body)
+        .finalize()
+        .to_vec();
+
+    for hash in &proof.hashes {
+        if s_n == 0 {
+            return None;
+        }
+        let hasher = Sha256::new().chain_update([0x01]);
+        if f_n & 1 == 1 || f_n == s_n {
+            root = hasher
+                .chain_update(hash)
+                .chain_update(&root)
+                .finalize()
+                .to_vec();
+            if f_n & 1 == 0 {
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            }
+        } else {
+            root = hasher
+                .chain_update(&root)
+                .chain_update(hash)
+                .finalize()
+                .to_vec();
+        }
+        f_n >>= 1;
+        s_n >>= 1;
+    }
+
+    (s_n == 0).then_some(root)
+}
+
+/// A checkpoint of the log, signed by Rekor using the signed note format
+#[derive(Debug, PartialEq, Eq)]
+struct Checkpoint<'a> {
+    note: &'a str,
+    tree_size: u64,
+    root_hash: Vec<u8>,
+    signatures: Vec<Vec<u8>>,
+}
+
+fn parse_checkpoint(envelope: &str) -> Option<Checkpoint<'_>> {
+    let separator = envelope.find("\n\n")?;
+    let note = &envelope[..separator + 1];
+    let mut lines = note.lines();
+    let _origin = lines.next()?;
+    let tree_size = lines.next()?.parse().ok()?;
+    let root_hash = STANDARD.decode(lines.next()?).ok()?;
+
+    // each signature line is `— <name> <base64(key hint || signature)>`
+    let signatures = envelope[separator + 2..]
+        .lines()
+        .filter_map(|line| line.strip_prefix("\u{2014} "))
+        .filter_map(|line| line.rsplit_once(' '))
+        .filter_map(|(_name, signature)| STANDARD.decode(signature).ok())
+        .filter(|signature| signature.len() > 4)
+        .map(|signature| signature[4..].to_vec())
+        .collect();
+
+    Some(Checkpoint {
+        note,
+        tree_size,
+        root_hash,
+        signatures,
+    })
+}
+
+fn verify_checkpoint(
+    envelope: &str,
+    proof: &InclusionProof,
+    rekor_key: &CosignVerificationKey,
+) -> VerifyResult<()> {
+    let checkpoint = parse_checkpoint(envelope).ok_or_else(|| {
+        VerifyError::InvalidBundleError("cannot parse the checkpoint of the log".to_string())
+    })?;
+    if checkpoint.tree_size != proof.tree_size || checkpoint.root_hash != proof.root_hash {
+        return Err(VerifyError::BundleVerificationError(
+            "the checkpoint doesn't match with the inclusion proof".to_string(),
+        ));
+    }
+    let signed = checkpoint.signatures.iter().any(|signature| {
+        rekor_key
+            .verify_signature(Signature::Raw(signature), checkpoint.note.as_bytes())
+            .is_ok()
+    });
+    if signed {
+        Ok(())
+    } else {
+        Err(VerifyError::BundleVerificationError(
+            "the checkpoint is not signed by a trusted transparency log".to_string(),
+        ))
+    }
+}
+
+/// The body of a `hashedrekord` entry
+#[derive(Deserialize)]
+pub(crate) struct HashedRekord {
+    pub(crate) spec: HashedRekordSpec,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct HashedRekordSpec {
+    pub(crate) data: HashedRekordData,
+    pub(crate) signature: HashedRekordSignature,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct HashedRekordData {
+    pub(crate) hash: HashedRekordHash,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct HashedRekordHash {
+    pub(crate) algorithm: String,
+    pub(crate) value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HashedRekordSignature {
+    pub(crate) content: String,
+    pub(crate) public_key: HashedRekordPublicKey,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct HashedRekordPublicKey {
+    pub(crate) content: String,
+}
+
+/// The body of an `intoto` entry, recording a DSSE envelope
+#[derive(Deserialize)]
+pub(crate) struct Intoto {
+    pub(crate) spec: IntotoSpec,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IntotoSpec {
+    pub(crate) content: IntotoContent,
+    /// base64 encoded PEM certificate, recorded by the `0.0.1` entries
+    pub(crate) public_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IntotoContent {
+    pub(crate) payload_hash: HashedRekordHash,
+    /// Recorded by the `0.0.2` entries
+    pub(crate) envelope: Option<IntotoEnvelope>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct IntotoEnvelope {
+    #[serde(default)]
+    pub(crate) signatures: Vec<IntotoSignature>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IntotoSignature {
+    /// base64 encoded PEM certificate
+    pub(crate) public_key: String,
+}
+
+impl Intoto {
+    /// The base64 encoded PEM certificates, or public keys, of the signers
+    pub(crate) fn public_keys(&self) -> impl Iterator<Item = &str> {
+        self.spec
+            .public_key
+            .iter()
+            .chain(
+                self.spec
+                    .content
+                    .envelope
+                    .iter()
+                    .flat_map(|envelope| envelope.signatures.iter())
+                    .map(|signature| &signature.public_key),
+            )
+            .map(String::as_str)
+    }
+
+    /// Whether the entry records the given payload
+    pub(crate) fn records_payload(&self, payload: &[u8]) -> bool {
+        let hash = &self.spec.content.payload_hash;
+        hash.algorithm == "sha256" && hash.value == hex::encode(Sha256::digest(payload))
+    }
+}
+
+/// The kind of a Rekor entry, like `hashedrekord` or `intoto`
+pub(crate) fn entry_kind(body: &[u8]) -> Option<String> {
+    let entry: serde_json::Value = serde_json::from_slice(body).ok()?;
+    entry.get("kind")?.as_str().map(|kind| kind.to_string())
+}
+
+/// Ensures the Rekor entry refers to the signed content, the signature and
+/// the certificate of the bundle
+fn verify_tlog_entry_body(
+    tlog_entry: &TlogEntry,
+    content: &SignedContent,
+    bundle: &LocalBundle,
+) -> VerifyResult<()> {
+    let body = STANDARD
+        .decode(&tlog_entry.body)
+        .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?;
+    let kind = entry_kind(&body).unwrap_or_default();
+    match (kind.as_str(), content) {
+        ("hashedrekord", SignedContent::Blob(blob)) => {
+            verify_hashedrekord_entry(&body, &Sha256::digest(blob), bundle)
+        }
+        ("intoto", SignedContent::Envelope { payload, .. }) => {
+            verify_intoto_entry(&body, payload, bundle)
+        }
+        _ => Err(VerifyError::InvalidBundleError(format!(
+            "unsupported transparency log entry kind: {kind}"
+        ))),
+    }
+}
+
+fn verify_hashedrekord_entry(
+    body: &[u8],
+    module_digest: &[u8],
+    bundle: &LocalBundle,
+) -> VerifyResult<()> {
+    let entry: HashedRekord = serde_json::from_slice(body).map_err(|e| {
+        VerifyError::InvalidBundleError(format!("unsupported transparency log entry: {e}"))
+    })?;
+
+    let hash = &entry.spec.data.hash;
+    if hash.algorithm != "sha256" || hash.value != hex::encode(module_digest) {
+        return Err(VerifyError::BundleVerificationError(
+            "the transparency log entry refers to a different module".to_string(),
+        ));
+    }
+    let signature = STANDARD
+        .decode(&entry.spec.signature.content)
+        .map_err(|e| VerifyError::InvalidBundleError(e.to_string()))?;
+    if signature != bundle.signature {
+        return Err(VerifyError::BundleVerificationError(
+            "the transparency log entry refers to a different signature".to_string(),
+        ));
+    }
+    verify_entry_certificate(
+        std::iter::once(entry.spec.signature.public_key.content.as_str()),
+        bundle,
+    )
+}
+
+fn verify_intoto_entry(body: &[u8], payload: &[u8], bundle: &LocalBundle) -> VerifyResult<()> {
+    let entry: Intoto = serde_json::from_slice(body).map_err(|e| {
+        VerifyError::InvalidBundleError(format!("unsupported transparency log entry: {e}"))
+    })?;
+    if !entry.records_payload(payload) {
+        return Err(VerifyError::BundleVerificationError(
+            "the transparency log entry refers to a different attestation".to_string(),
+        ));
+    }
+    verify_entry_certificate(entry.public_keys(), bundle)
+}
+
+/// Ensures one of the certificates recorded by the Rekor entry is the one of
+/// the bundle
+fn verify_entry_certificate<'a>(
+    entry_certificates: impl IntoIterator<Item = &'a str>,
+    bundle: &LocalBundle,
+) -> VerifyResult<()> {
+    let Some(certificate) = &bundle.certificate else {
+        return Ok(());
+    };
+    let found = entry_certificates.into_iter().any(|entry_certificate| {
+        STANDARD
+            .decode(entry_certificate)
+            .ok()
+            .and_then(|pem| parse_x509_pem(&pem).ok().map(|(_, pem)| pem.contents))
+            .as_ref()
+            == Some(certificate)
+    });
+    if found {
+        Ok(())
+    } else {
+        Err(VerifyError::BundleVerificationError(
+            "the transparency log entry refers to a different certificate".to_string(),
+        ))
+    }
+}
+
+/// Ensures the certificate has been issued by Fulcio and was valid when the
+/// signature was added to the transparency log. Returns the identity of the signer.
+fn verify_certificate(
+    certificate: &[u8],
+    integrated_time: i64,
+    trust_root: &(dyn TrustRoot + Sync),
+) -> VerifyResult<CertificateSignature> {
+    let (_, leaf) = X509Certificate::from_der(certificate)
+        .map_err(|e| VerifyError::InvalidBundleError(format!("invalid certificate: {e}")))?;
+    if !is_valid_at(&leaf, integrated_time) {
+        return Err(VerifyError::BundleVerificationError(
+            "the certificate was not valid when the signature was added to the transparency log"
+                .to_string(),
+        ));
+    }
+
+    let fulcio_certs = trust_root.fulcio_certs().map_err(|e| {
+        VerifyError::BundleVerificationError(format!("cannot read Fulcio certificates: {e}"))
+    })?;
+    let issued_by_fulcio = fulcio_certs.iter().any(|ca| {
+        X509Certificate::from_der(ca.as_ref()).is_ok_and(|(_, ca)| {
+            ca.subject().as_raw() == leaf.issuer().as_raw()
+                && is_valid_at(&ca, integrated_time)
+                && CosignVerificationKey::try_from_der(ca.public_key().raw).is_ok_and(|key| {
+                    key.verify_signature(
+                        Signature::Raw(&leaf.signature_value.data),
+                        leaf.tbs_certificate.as_ref(),
+                    )
+                    .is_ok()
+                })
+        })
+    });
+    if !issued_by_fulcio {
+        return Err(VerifyError::BundleVerificationError(
+            "the certificate has not been issued by a trusted Fulcio instance".to_string(),
+        ));
+    }
+
+    let verification_key = CosignVerificationKey::try_from_der(leaf.public_key().raw)
+        .map_err(VerifyError::KeyVerificationError)?;
+    let identity = FulcioIdentity::from_certificate(&leaf);
+    let extension = |oid| identity.extension(oid).map(|value| value.to_string());
+
+    Ok(CertificateSignature {
+        verification_key,
+        issuer: identity.issuer().map(|issuer| issuer.to_string()),
+        subject: identity.subject.clone().ok_or_else(|| {
+            VerifyError::BundleVerificationError(
+                "the certificate doesn't have an email or URI subject alternative name".to_string(),
+            )
+        })?,
+        github_workflow_trigger: extension(fulcio::OID_GITHUB_WORKFLOW_TRIGGER),
+        github_workflow_sha: extension(fulcio::OID_GITHUB_WORKFLOW_SHA),
+        github_workflow_name: extension(fulcio::OID_GITHUB_WORKFLOW_NAME),
+        github_workflow_repository: extension(fulcio::OID_GITHUB_WORKFLOW_REPOSITORY),
+        github_workflow_ref: extension(fulcio::OID_GITHUB_WORKFLOW_REF),
+    })
+}
+
+fn is_valid_at(certificate: &X509Certificate, timestamp: i64) -> bool {
+    let validity = certificate.validity();
+    validity.not_before.timestamp() <= timestamp && timestamp <= validity.not_after.timestamp()
+}
+
+fn decode(field: &str, value: &str) -> VerifyResult<Vec<u8>> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| VerifyError::InvalidBundleError(format!("invalid {field}: {e}")))
+}
+
+fn int64<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // the protobuf JSON mapping encodes 64 bits integers as strings
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Int64 {
+        Number(i64),
+        String(String),
+    }
+
+    match Int64::deserialize(deserializer)? {
+        Int64::Number(value) => Ok(value),
+        Int64::String(value) => value.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// The bundle created by `cosign sign-blob --bundle`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CosignBundle {
+    base64_signature: String,
+    /// base64 encoded PEM certificate, or public key
+    cert: Option<String>,
+    rekor_bundle: Option<RekorBundle>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RekorBundle {
+    signed_entry_timestamp: String,
+    payload: RekorPayload,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RekorPayload {
+    body: String,
+    integrated_time: i64,
+    log_index: i64,
+    #[serde(rename = "logID")]
+    log_id: String,
+}
+
+impl TryFrom<CosignBundle> for LocalBundle {
+    type Error = VerifyError;
+
+    fn try_from(bundle: CosignBundle) -> VerifyResult<Self> {
+        let certificate = match bundle.cert {
+            Some(cert) => {
+                let pem = decode("certificate", &cert)?;
+                let (_, pem) = parse_x509_pem(&pem).map_err(|e| {
+                    VerifyError::InvalidBundleError(format!("invalid certificate: {e}"))
+                })?;
+                // the public key of signatures made with a key is not trusted
+                (pem.label == "CERTIFICATE").then_some(pem.contents)
+            }
+            None => None,
+        };
+        let tlog_entry = match bundle.rekor_bundle {
+            Some(rekor_bundle) => Some(TlogEntry {
+                body: rekor_bundle.payload.body,
+                integrated_time: rekor_bundle.payload.integrated_time,
+                log_index: rekor_bundle.payload.log_index,
+                log_id: rekor_bundle.payload.log_id,
+                signed_entry_timestamp: Some(decode(
+                    "signed entry timestamp",
+                    &rekor_bundle.signed_entry_timestamp,
+                )?),
+                inclusion_proof: None,
+            }),
+            None => None,
+        };
+
+        Ok(LocalBundle {
+            signature: decode("signature", &bundle.base64_signature)?,
+            certificate,
+            message_digest: None,
+            tlog_entry,
+        })
+    }
+}
+
+/// The bundle defined by the Sigstore protobuf specs, serialized as JSON
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SigstoreBundle {
+    verification_material: VerificationMaterial,
+    message_signature: Option<MessageSignature>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMaterial {
+    certificate: Option<RawBytes>,
+    x509_certificate_chain: Option<X509CertificateChain>,
+    #[serde(default)]
+    tlog_entries: Vec<TransparencyLogEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBytes {
+    raw_bytes: String,
+}
+
+#[derive(Deserialize)]
+struct X509CertificateChain {
+    certificates: Vec<RawBytes>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransparencyLogEntry {
+    #[serde(deserialize_with = "int64")]
+    log_index: i64,
+    log_id: LogId,
+    #[serde(deserialize_with = "int64")]
+    integrated_time: i64,
+    inclusion_promise: Option<InclusionPromise>,
+    inclusion_proof: Option<InclusionProofJson>,
+    canonicalized_body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogId {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionPromise {
+    signed_entry_timestamp: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionProofJson {
+    #[serde(deserialize_with = "int64")]
+    log_index: i64,
+    root_hash: String,
+    #[serde(deserialize_with = "int64")]
+    tree_size: i64,
+    #[serde(default)]
+    hashes: Vec<String>,
+    checkpoint: Option<CheckpointJson>,
+}
+
+#[derive(Deserialize)]
+struct CheckpointJson {
+    envelope: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageSignature {
+    message_digest: Option<MessageDigest>,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct MessageDigest {
+    algorithm: String,
+    digest: String,
+}
+
+impl TryFrom<SigstoreBundle> for LocalBundle {
+    type Error = VerifyError;
+
+    fn try_from(bundle: SigstoreBundle) -> VerifyResult<Self> {
+        let message_signature = bundle.message_signature.ok_or_else(|| {
+            VerifyError::InvalidBundleError(
+                "only bundles holding a message signature are supported".to_string(),
+            )
+        })?;
+        let message_digest = match message_signature.message_digest {
+            Some(digest) if digest.algorithm == "SHA2_256" => {
+                Some(decode("message digest", &digest.digest)?)
+            }
+            Some(digest) => {
+                return Err(VerifyError::InvalidBundleError(format!(
+                    "unsupported message digest algorithm: {}",
+                    digest.algorithm
+                )));
+            }
+            None => None,
+        };
+
+        let material = bundle.verification_material;
+        let certificate = match (material.certificate, material.x509_certificate_chain) {
+            (Some(certificate), _) => Some(decode("certificate", &certificate.raw_bytes)?),
+            // the first certificate of the chain is the one of the signer
+            (None, Some(chain)) => match chain.certificates.first() {
+                Some(certificate) => Some(decode("certificate", &certificate.raw_bytes)?),
+                None => None,
+            },
+            (None, None) => None,
+        };
+
+        let tlog_entry = match material.tlog_entries.into_iter().next() {
+            Some(entry) => {
+                let inclusion_proof = match entry.inclusion_proof {
+                    Some(proof) => Some(InclusionProof {
+                        log_index: u64::try_from(proof.log_index).map_err(|e| {
+                            VerifyError::InvalidBundleError(format!("invalid log index: {e}"))
+                        })?,
+                        tree_size: u64::try_from(proof.tree_size).map_err(|e| {
+                            VerifyError::InvalidBundleError(format!("invalid tree size: {e}"))
+                        })?,
+                        root_hash: decode("root hash", &proof.root_hash)?,
+                        hashes: proof
+                            .hashes
+                            .iter()
+                            .map(|hash| decode("inclusion proof hash", hash))
+                            .collect::<VerifyResult<_>>()?,
+                        checkpoint: proof.checkpoint.map(|checkpoint| checkpoint.envelope),
+                    }),
+                    None => None,
+                };
+                Some(TlogEntry {
+                    body: entry.canonicalized_body,
+                    integrated_time: entry.integrated_time,
+                    log_index: entry.log_index,
+                    log_id: hex::encode(decode("log ID", &entry.log_id.key_id)?),
+                    signed_entry_timestamp: entry
+                        .inclusion_promise
+                        .map(|promise| {
+                            decode("signed entry timestamp", &promise.signed_entry_timestamp)
+                        })
+                        .transpose()?,
+                    inclusion_proof,
+                })
+            }
+            None => None,
+        };
+
+        Ok(LocalBundle {
+            signature: decode("signature", &message_signature.signature)?,
+            certificate,
+            message_digest,
+            tlog_entry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use sigstore::crypto::SigningScheme;
+
+    const SIGNATURE: &str = "MEUCIGqWScz7s9aP2sGXNFKeqivw3B6kPRs56AITIHnvd5igAiEA1kzbaV2Y5yPE81EN92NUFOl31LLJSvwsjFQ07m2XqaA=";
+
+    fn leaf_hash(body: &[u8]) -> Vec<u8> {
+        Sha256::new()
+            .chain_update([0x00])
+            .chain_update(body)
+            .finalize()
+            .to_vec()
+    }
+
+    fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+        Sha256::new()
+            .chain_update([0x01])
+            .chain_update(left)
+            .chain_update(right)
+            .finalize()
+            .to_vec()
+    }
+
+    fn inclusion_proof(log_index: u64, tree_size: u64, hashes: Vec<Vec<u8>>) -> InclusionProof {
+        InclusionProof {
+            log_index,
+            tree_size,
+            root_hash: vec![],
+            hashes,
+            checkpoint: None,
+        }
+    }
+
+    /// A transparency log entry inside of a log made only of it, along with
+    /// the key of the log. The checkpoint of the log is always signed.
+    fn signed_tlog_entry(signed_entry_timestamp: bool) -> (TlogEntry, CosignVerificationKey) {
+        let signer = SigningScheme::ECDSA_P256_SHA256_ASN1
+            .create_signer()
+            .unwrap();
+        let rekor_key = CosignVerificationKey::try_from_der(
+            &signer
+                .to_sigstore_keypair()
+                .unwrap()
+                .public_key_to_der()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let root_hash = leaf_hash(b"body");
+        let note = format!(
+            "rekor.sigstore.dev - 1193050959916656506\n1\n{}\n",
+            STANDARD.encode(&root_hash)
+        );
+        let mut signature = b"hint".to_vec();
+        signature.extend(signer.sign(note.as_bytes()).unwrap());
+        let checkpoint = format!(
+            "{note}\n\u{2014} rekor.sigstore.dev {}\n",
+            STANDARD.encode(&signature)
+        );
+
+        let mut tlog_entry = TlogEntry {
+            body: STANDARD.encode(b"body"),
+            integrated_time: 1700000000,
+            log_index: 0,
+            log_id: "c0d2".to_string(),
+            signed_entry_timestamp: None,
+            inclusion_proof: Some(InclusionProof {
+                log_index: 0,
+                tree_size: 1,
+                root_hash,
+                hashes: vec![],
+                checkpoint: Some(checkpoint),
+            }),
+        };
+        if signed_entry_timestamp {
+            let payload = signed_entry_timestamp_payload(&tlog_entry).unwrap();
+            tlog_entry.signed_entry_timestamp = Some(signer.sign(&payload).unwrap());
+        }
+        (tlog_entry, rekor_key)
+    }
+
+    #[rstest]
+    #[case::sigstore_json("sigstore.json", "policy.wasm.sigstore.json")]
+    #[case::bundle("bundle", "policy.wasm.bundle")]
+    #[case::detached_signature("sig", "policy.wasm.sig")]
+    fn find_bundle_next_to_module(#[case] extension: &str, #[case] expected: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let module = dir.path().join("policy.wasm");
+        std::fs::write(&module, b"module").unwrap();
+        std::fs::write(dir.path().join(format!("policy.wasm.{extension}")), b"{}").unwrap();
+
+        let url = url::Url::from_file_path(&module).unwrap().to_string();
+        assert_eq!(find_bundle(&url), Some(dir.path().join(expected)));
+    }
+
+    #[test]
+    fn find_bundle_inside_of_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::new(dir.path());
+        let url = "https://example.com/policies/policy.wasm";
+        assert_eq!(find_stored_bundle(&store, url), None);
+
+        store
+            .add_https_policy(
+                url,
+                b"module",
+                &crate::store::HttpsPolicyMetadata {
+                    etag: None,
+                    signature: Some(SIGNATURE.as_bytes().to_vec()),
+                },
+            )
+            .unwrap();
+        let bundle = find_stored_bundle(&store, url).expect("bundle not found");
+        assert!(LocalBundle::from_path(&bundle).is_ok());
+    }
+
+    #[rstest]
+    #[case::registry("registry://ghcr.io/kubewarden/policies/psp:v1.0.0")]
+    #[case::missing_bundle("file:///policies/policy.wasm")]
+    fn find_bundle_not_found(#[case] url: &str) {
+        assert_eq!(find_bundle(url), None);
+    }
+
+    #[test]
+    fn parse_cosign_bundle() {
+        let bundle = serde_json::json!({
+            "base64Signature": SIGNATURE,
+            "rekorBundle": {
+                "SignedEntryTimestamp": "c2V0",
+                "Payload": {
+                    "body": "Ym9keQ==",
+                    "integratedTime": 1700000000,
+                    "logIndex": 42,
+                    "logID": "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d"
+                }
+            }
+        });
+
+        let bundle = LocalBundle::from_json(bundle.to_string().as_bytes()).unwrap();
+        assert_eq!(bundle.signature, STANDARD.decode(SIGNATURE).unwrap());
+        assert_eq!(bundle.certificate, None);
+        assert_eq!(
+            bundle.tlog_entry,
+            Some(TlogEntry {
+                body: "Ym9keQ==".to_string(),
+                integrated_time: 1700000000,
+                log_index: 42,
+                log_id: "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d"
+                    .to_string(),
+                signed_entry_timestamp: Some(b"set".to_vec()),
+                inclusion_proof: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_sigstore_bundle() {
+        let bundle = serde_json::json!({
+            "mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json",
+            "verificationMaterial": {
+                "certificate": { "rawBytes": "Y2VydA==" },
+                "tlogEntries": [{
+                    "logIndex": "42",
+                    "logId": { "keyId": "wNI9atQGlz+VWfO6LRygH4QUfY/8W4RFwiT5i5WRgB0=" },
+                    "kindVersion": { "kind": "hashedrekord", "version": "0.0.1" },
+                    "integratedTime": "1700000000",
+                    "inclusionPromise": { "signedEntryTimestamp": "c2V0" },
+                    "inclusionProof": {
+                        "logIndex": "41",
+                        "rootHash": "cm9vdA==",
+                        "treeSize": "100",
+                        "hashes": ["aGFzaA=="],
+                        "checkpoint": { "envelope": "checkpoint" }
+                    },
+                    "canonicalizedBody": "Ym9keQ=="
+                }]
+            },
+            "messageSignature": {
+                "messageDigest": { "algorithm": "SHA2_256", "digest": "ZGlnZXN0" },
+                "signature": SIGNATURE
+            }
+        });
+
+        let bundle = LocalBundle::from_json(bundle.to_string().as_bytes()).unwrap();
+        assert_eq!(bundle.certificate, Some(b"cert".to_vec()));
+        assert_eq!(bundle.message_digest, Some(b"digest".to_vec()));
+        assert_eq!(
+            bundle.tlog_entry,
+            Some(TlogEntry {
+                body: "Ym9keQ==".to_string(),
+                integrated_time: 1700000000,
+                log_index: 42,
+                log_id: "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d"
+                    .to_string(),
+                signed_entry_timestamp: Some(b"set".to_vec()),
+                inclusion_proof: Some(InclusionProof {
+                    log_index: 41,
+                    tree_size: 100,
+                    root_hash: b"root".to_vec(),
+                    hashes: vec![b"hash".to_vec()],
+                    checkpoint: Some("checkpoint".to_string()),
+                }),
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::not_json("not json")]
+    #[case::missing_signature(r#"{"cert": null}"#)]
+    #[case::dsse_envelope(
+        r#"{"mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json", "verificationMaterial": {}, "dsseEnvelope": {}}"#
+    )]
+    #[case::unsupported_digest(
+        r#"{"mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json", "verificationMaterial": {}, "messageSignature": {"messageDigest": {"algorithm": "SHA2_512", "digest": ""}, "signature": ""}}"#
+    )]
+    fn parse_invalid_bundle(#[case] bundle: &str) {
+        assert!(matches!(
+            LocalBundle::from_json(bundle.as_bytes()),
+            Err(VerifyError::InvalidBundleError(_))
+        ));
+    }
+
+    #[rstest]
+    #[case::detached_signature(SIGNATURE, false)]
+    #[case::detached_signature_with_newline(&format!("{SIGNATURE}\n"), false)]
+    #[case::bundle(&format!(r#"  {{"base64Signature": "{SIGNATURE}"}}"#), false)]
+    #[case::invalid_signature("not base64!", true)]
+    fn parse_bundle_or_detached_signature(#[case] data: &str, #[case] invalid: bool) {
+        match LocalBundle::from_bytes(data.as_bytes()) {
+            Ok(bundle) => {
+                assert!(!invalid);
+                assert_eq!(bundle.signature, STANDARD.decode(SIGNATURE).unwrap());
+                assert_eq!(bundle.certificate, None);
+            }
+            Err(e) => assert!(invalid, "unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn signed_entry_timestamp_payload_is_canonical() {
+        let tlog_entry = TlogEntry {
+            body: "Ym9keQ==".to_string(),
+            integrated_time: 1700000000,
+            log_index: 42,
+            log_id: "c0d2".to_string(),
+            signed_entry_timestamp: None,
+            inclusion_proof: None,
+        };
+
+        assert_eq!(
+            String::from_utf8(signed_entry_timestamp_payload(&tlog_entry).unwrap()).unwrap(),
+            r#"{"body":"Ym9keQ==","integratedTime":1700000000,"logID":"c0d2","logIndex":42}"#
+        );
+    }
+
+    #[rstest]
+    #[case::first_leaf(0, vec![b"b".to_vec(), b"c".to_vec()])]
+    #[case::second_leaf(1, vec![b"a".to_vec(), b"c".to_vec()])]
+    #[case::last_leaf(2, vec![b"ab".to_vec()])]
+    fn compute_inclusion_proof_root(#[case] log_index: u64, #[case] proof: Vec<Vec<u8>>) {
+        // a tree with three leaves: the hashes of the proof are referenced by name
+        let hash_of = |name: &[u8]| match name {
+            b"ab" => node_hash(&leaf_hash(b"a"), &leaf_hash(b"b")),
+            leaf => leaf_hash(leaf),
+        };
+        let root = node_hash(&hash_of(b"ab"), &hash_of(b"c"));
+        let body = [b"a", b"b", b"c"][log_index as usize];
+
+        let proof = inclusion_proof(log_index, 3, proof.iter().map(|h| hash_of(h)).collect());
+        assert_eq!(inclusion_proof_root(&proof, body), Some(root));
+    }
+
+    #[rstest]
+    #[case::index_out_of_tree(inclusion_proof(3, 3, vec![]))]
+    #[case::proof_too_short(inclusion_proof(0, 3, vec![leaf_hash(b"b")]))]
+    #[case::proof_too_long(inclusion_proof(0, 2, vec![leaf_hash(b"b"), leaf_hash(b"c")]))]
+    fn invalid_inclusion_proof(#[case] proof: InclusionProof) {
+        assert_eq!(inclusion_proof_root(&proof, b"a"), None);
+    }
+
+    #[rstest]
+    #[case::with_signed_entry_timestamp(true)]
+    #[case::inclusion_proof_only(false)]
+    fn verify_tlog_entry_with_inclusion_proof(#[case] signed_entry_timestamp: bool) {
+        let (tlog_entry, rekor_key) = signed_tlog_entry(signed_entry_timestamp);
+
+        // only the signed entry timestamp proves the integrated time
+        let integrated_time_verified = verify_tlog_entry_with_key(&tlog_entry, &rekor_key).unwrap();
+        assert_eq!(integrated_time_verified, signed_entry_timestamp);
+    }
+
+    #[test]
+    fn verify_tlog_entry_with_tampered_integrated_time() {
+        let (mut tlog_entry, rekor_key) = signed_tlog_entry(true);
+        // the inclusion proof and the checkpoint are still valid
+        tlog_entry.integrated_time -= 3600;
+
+        assert!(matches!(
+            verify_tlog_entry_with_key(&tlog_entry, &rekor_key),
+            Err(VerifyError::BundleVerificationError(msg)) if msg == "invalid signed entry timestamp"
+        ));
+    }
+
+    #[rstest]
+    #[case::verified(true, true, true)]
+    #[case::not_verified(true, false, false)]
+    #[case::without_signed_entry_timestamp(false, true, false)]
+    fn transparency_log_bundle_of_the_signature_layer(
+        #[case] signed_entry_timestamp: bool,
+        #[case] verified: bool,
+        #[case] expected: bool,
+    ) {
+        let (tlog_entry, _) = signed_tlog_entry(signed_entry_timestamp);
+        let bundle = LocalBundle {
+            signature: b"signature".to_vec(),
+            certificate: None,
+            message_digest: None,
+            tlog_entry: Some(tlog_entry),
+        };
+
+        let rekor_bundle = transparency_log_bundle::<serde_json::Value>(&bundle, verified);
+        assert_eq!(rekor_bundle.is_some(), expected);
+    }
+
+    #[test]
+    fn parse_signed_checkpoint() {
+        let mut signature = b"hint".to_vec();
+        signature.extend_from_slice(b"signature");
+        let envelope = format!(
+            "rekor.sigstore.dev - 1193050959916656506\n100\n{}\n\n\u{2014} rekor.sigstore.dev {}\n",
+            STANDARD.encode(b"root"),
+            STANDARD.encode(&signature)
+        );
+
+        let checkpoint = parse_checkpoint(&envelope).unwrap();
+        assert_eq!(
+            checkpoint,
+            Checkpoint {
+                note: "rekor.sigstore.dev - 1193050959916656506\n100\ncm9vdA==\n",
+                tree_size: 100,
+                root_hash: b"root".to_vec(),
+                signatures: vec![b"signature".to_vec()],
+            }
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use std::{boxed::Box, collections::BTreeMap, fs, path::Path};
 
+use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize};
 use sigstore::cosign::verification_constraint::VerificationConstraint;
 use url::Url;
@@ -18,13 +19,115 @@ use crate::{
 /// When a new version is created:
 /// * Update this stype to point to the new version
 /// * Implement `TryFrom` that goes from (v - 1) to (v)
-pub type LatestVerificationConfig = VerificationConfigV1;
+pub type LatestVerificationConfig = VerificationConfigV2;
 
+/// The signatures a policy must have
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
-pub struct VerificationConfigV1 {
+pub struct SignatureRequirements {
     pub all_of: Option<Vec<Signature>>,
     pub any_of: Option<AnyOf>,
+    /// The in-toto attestations the policy must have, like its SLSA provenance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestations: Option<Vec<AttestationRequirement>>,
+}
+
+/// The first version of the configuration applies the same signature
+/// requirements to all the policies
+pub type VerificationConfigV1 = SignatureRequirements;
+
+/// Verification settings with dedicated signature requirements for the
+/// policies matching a reference pattern.
+///
+/// The policies matching a pattern of `allowUnsigned` are allowed to be
+/// unsigned, regardless of the other rules. The rules of `policies` are
+/// evaluated in order and the first one matching the policy wins. The policies
+/// that don't match any rule must satisfy the `default` requirements.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct VerificationConfigV2 {
+    pub default: Option<SignatureRequirements>,
+    #[serde(default)]
+    pub policies: Vec<PolicyVerificationRule>,
+    #[serde(default)]
+    pub allow_unsigned: Vec<String>,
+}
+
+/// The signature requirements of the policies matching a reference pattern
+///
+/// Patterns are matched against the reference of the policy, without the
+/// `registry://` scheme for policies stored inside of OCI registries, and
+/// against the whole URL for all the other policies. Inside of a pattern `*`
+/// matches any sequence of characters but `/`, `**` matches any sequence of
+/// characters and `?` matches a single character but `/`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PolicyVerificationRule {
+    pub reference: String,
+    pub signatures: SignatureRequirements,
+}
+
+/// How a policy has to be verified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyVerification<'a> {
+    /// The policy must satisfy these signature requirements
+    Signatures(&'a SignatureRequirements),
+    /// The policy is allowed to be unsigned
+    Unsigned,
+}
+
+impl VerificationConfigV2 {
+    /// Finds out how the policy with the given URL has to be verified
+    pub fn policy_verification(&self, policy_url: &str) -> VerifyResult<PolicyVerification<'_>> {
+        let reference = policy_url.strip_prefix("registry://").unwrap_or(policy_url);
+
+        for pattern in &self.allow_unsigned {
+            if reference_pattern_regex(pattern)?.is_match(reference) {
+                return Ok(PolicyVerification::Unsigned);
+            }
+        }
+        for rule in &self.policies {
+            if reference_pattern_regex(&rule.reference)?.is_match(reference) {
+                return Ok(PolicyVerification::Signatures(&rule.signatures));
+            }
+        }
+
+        self.default
+            .as_ref()
+            .map(PolicyVerification::Signatures)
+            .ok_or_else(|| VerifyError::NoVerificationRuleError(policy_url.to_owned()))
+    }
+}
+
+impl TryFrom<VerificationConfigV1> for VerificationConfigV2 {
+    type Error = VerifyError;
+
+    fn try_from(config: VerificationConfigV1) -> VerifyResult<Self> {
+        Ok(VerificationConfigV2 {
+            default: Some(config),
+            policies: Vec::new(),
+            allow_unsigned: Vec::new(),
+        })
+    }
+}
+
+/// Translates a policy reference pattern into a regular expression
+fn reference_pattern_regex(pattern: &str) -> VerifyResult<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Ok(Regex::new(&regex)?)
 }
 
 /// Enum that holds all the known versions of the configuration file
@@ -36,6 +139,8 @@ pub struct VerificationConfigV1 {
 pub enum VersionedVerificationConfig {
     #[serde(rename = "v1")]
     V1(VerificationConfigV1),
+    #[serde(rename = "v2")]
+    V2(VerificationConfigV2),
     #[serde(other)]
     Unsupported,
 }
@@ -61,6 +166,26 @@ fn default_minimum_matches() -> u8 {
     1
 }
 
+/// An in-toto attestation of the policy image, created by `cosign attest`.
+///
+/// The attestation must be signed satisfying at least one of the
+/// `signatures`, and its predicate must match the given conditions. The
+/// conditions about the build apply to the SLSA provenance predicates.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AttestationRequirement {
+    /// The predicate type, like `https://slsa.dev/provenance/v1`
+    pub predicate_type: String,
+    pub signatures: Vec<Signature>,
+    /// The id of the builder, like
+    /// `https://github.com/slsa-framework/slsa-github-generator/.github/workflows/builder_go_slsa3.yml@refs/tags/v1.9.0`
+    pub builder_id: Option<String>,
+    /// The repository the policy has been built from, like `https://github.com/kubewarden/policy`
+    pub source_repository: Option<String>,
+    /// The git reference the policy has been built from, like `refs/tags/v1.0.0`
+    pub source_ref: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase", tag = "kind", deny_unknown_fields)]
 pub enum Signature {
@@ -79,6 +204,28 @@ pub enum Signature {
         repo: Option<String>,
         annotations: Option<BTreeMap<String, String>>,
     },
+    /// Keyless signature, matching the OIDC issuer, either exactly or using a
+    /// regular expression, the subject and the claims of the workflow recorded
+    /// inside of the Fulcio certificate
+    #[serde(rename_all = "camelCase")]
+    CertificateIdentity {
+        issuer: Option<String>,
+        issuer_regex: Option<String>,
+        subject: Subject,
+        extensions: Option<Box<CertificateExtensions>>,
+        #[serde(default)]
+        require_transparency_log: bool,
+        annotations: Option<BTreeMap<String, String>>,
+    },
+    /// Keyless signature produced by a GitLab CI pipeline. The owner is the
+    /// path of the group, including its subgroups
+    GitlabCi {
+        owner: String,
+        repo: Option<String>,
+        /// The URL of the GitLab instance, `https://gitlab.com` by default
+        issuer: Option<String>,
+        annotations: Option<BTreeMap<String, String>>,
+    },
 }
 
 impl Signature {
@@ -116,16 +263,81 @@ impl Signature {
                 repo.as_ref().map(|r| r.as_str()),
                 annotations.as_ref(),
             ))),
+            Signature::CertificateIdentity {
+                issuer,
+                issuer_regex,
+                subject,
+                extensions,
+                require_transparency_log,
+                annotations,
+            } => {
+                let vc = verification_constraints::CertificateIdentityVerifier::new(
+                    issuer.as_deref(),
+                    issuer_regex.as_deref(),
+                    subject,
+                    extensions.as_deref(),
+                    *require_transparency_log,
+                    annotations.as_ref(),
+                )?;
+                Ok(Box::new(vc))
+            }
+            Signature::GitlabCi {
+                owner,
+                repo,
+                issuer,
+                annotations,
+            } => Ok(Box::new(verification_constraints::GitLabVerifier::new(
+                owner,
+                repo.as_deref(),
+                issuer.as_deref(),
+                annotations.as_ref(),
+            ))),
         }
     }
 }
 
+/// The claims of the workflow that produced a keyless signature, recorded as
+/// extensions of the Fulcio certificate. See
+/// https://github.com/sigstore/fulcio/blob/main/docs/oid-info.md
+///
+/// All the claims that are set must match exactly.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CertificateExtensions {
+    pub build_signer_uri: Option<String>,
+    pub build_signer_digest: Option<String>,
+    pub runner_environment: Option<String>,
+    pub source_repository_uri: Option<String>,
+    pub source_repository_digest: Option<String>,
+    pub source_repository_ref: Option<String>,
+    pub source_repository_identifier: Option<String>,
+    pub source_repository_owner_uri: Option<String>,
+    pub source_repository_owner_identifier: Option<String>,
+    pub build_config_uri: Option<String>,
+    pub build_config_digest: Option<String>,
+    pub build_trigger: Option<String>,
+    pub run_invocation_uri: Option<String>,
+    pub source_repository_visibility_at_signing: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub enum Subject {
     Equal(String),
     #[serde(deserialize_with = "deserialize_subject_url_prefix")]
     UrlPrefix(Url),
+    /// A regular expression that must match the whole subject
+    #[serde(deserialize_with = "deserialize_subject_regex")]
+    Regex(String),
+}
+
+fn deserialize_subject_regex<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let regex = String::deserialize(deserializer)?;
+    verification_constraints::full_match_regex(&regex).map_err(serde::de::Error::custom)?;
+    Ok(regex)
 }
 
 fn deserialize_subject_url_prefix<'de, D>(deserializer: D) -> Result<Url, D::Error>
@@ -162,7 +374,11 @@ pub fn build_latest_verification_config(
         serde_yaml::from_str(config_str).map_err(FailedToParseYamlDataError)?;
     let config = match vc {
         VerificationConfig::Versioned(versioned_config) => match versioned_config {
-            VersionedVerificationConfig::V1(c) => c,
+            VersionedVerificationConfig::V1(c) => {
+                validate_signature_requirements(&c, "config")?;
+                c.try_into()?
+            }
+            VersionedVerificationConfig::V2(c) => c,
             VersionedVerificationConfig::Unsupported => {
                 return Err(VerifyError::InvalidVerifyFileError(format!(
                     "Not a supported configuration version: {versioned_config:?}"
@@ -171,15 +387,20 @@ pub fn build_latest_verification_config(
         },
         VerificationConfig::Invalid(mut value) => {
             // let's try to get a more specific error message
-            // for that we will perform a direct conversion into LatestVerificationConfig,
-            // this is going to provide a more detailed error message to the user, like
+            // for that we will perform a direct conversion into the config
+            // of the requested version, this is going to provide a more
+            // detailed error message to the user, like
             // "missing field `subject`"
+            let unwanted_key: serde_yaml::Value = "apiVersion".to_string().into();
+            let api_version = value
+                .get(&unwanted_key)
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_owned());
             let sanitized_value = if value.is_mapping() {
                 // The value includes the `apiVersion` key, which is unknown to the
-                // LatestVerificationConfig type.
+                // configuration types.
                 // We have to remove it to avoid a non-relevant error.
                 let mapping = value.as_mapping_mut().unwrap();
-                let unwanted_key: serde_yaml::Value = "apiVersion".to_string().into();
                 mapping.remove(&unwanted_key);
 
                 // need to convert back to a non-mutable Mapping, there's no From<mut Mapping>
@@ -189,28 +410,99 @@ pub fn build_latest_verification_config(
             } else {
                 value
             };
-            let err = serde_yaml::from_value::<LatestVerificationConfig>(sanitized_value).map_err(
-                |err| {
-                    VerifyError::InvalidVerifyFileError(format!(
+            let parsed = match api_version.as_deref() {
+                // configurations without a version are using the first format
+                None | Some("v1") => {
+                    serde_yaml::from_value::<VerificationConfigV1>(sanitized_value).map(|c| {
+                        validate_signature_requirements(&c, "config")
+                            .and_then(|_| LatestVerificationConfig::try_from(c))
+                    })
+                }
+                _ => serde_yaml::from_value::<LatestVerificationConfig>(sanitized_value).map(Ok),
+            };
+            match parsed {
+                Ok(config) => config?,
+                Err(err) => {
+                    return Err(VerifyError::InvalidVerifyFileError(format!(
                         "Not a valid configuration file: {err}"
-                    ))
-                },
-            );
-            return err;
+                    )));
+                }
+            }
         }
     };
 
-    if config.all_of.is_none() && config.any_of.is_none() {
-        return Err(VerifyError::InvalidVerifyFileError(
-            "config is missing signatures in both allOf and anyOff list".to_owned(),
-        ));
+    if let Some(default) = &config.default {
+        validate_signature_requirements(default, "default")?;
+    }
+    for rule in &config.policies {
+        validate_reference_pattern(&rule.reference)?;
+        validate_signature_requirements(&rule.signatures, &rule.reference)?;
+    }
+    for pattern in &config.allow_unsigned {
+        validate_reference_pattern(pattern)?;
     }
     Ok(config)
 }
 
+fn validate_reference_pattern(pattern: &str) -> VerifyResult<()> {
+    reference_pattern_regex(pattern).map(|_| ()).map_err(|e| {
+        VerifyError::InvalidVerifyFileError(format!(
+            "Not a valid policy reference pattern {pattern}: {e}"
+        ))
+    })
+}
+
+fn validate_signature_requirements(
+    requirements: &SignatureRequirements,
+    context: &str,
+) -> VerifyResult<()> {
+    if requirements.all_of.is_none() && requirements.any_of.is_none() {
+        return Err(VerifyError::InvalidVerifyFileError(format!(
+            "{context} is missing signatures in both allOf and anyOff list"
+        )));
+    }
+
+    for attestation in requirements.attestations.iter().flatten() {
+        if attestation.signatures.is_empty() {
+            return Err(VerifyError::InvalidVerifyFileError(format!(
+                "{context} has an attestation of type {} without signatures",
+                attestation.predicate_type
+            )));
+        }
+    }
+
+    // report the certificate identities that can never be satisfied, like
+    // the ones with invalid regular expressions
+    let signatures = requirements
+        .all_of
+        .iter()
+        .flatten()
+        .chain(
+            requirements
+                .any_of
+                .iter()
+                .flat_map(|any_of| &any_of.signatures),
+        )
+        .chain(
+            requirements
+                .attestations
+                .iter()
+                .flatten()
+                .flat_map(|attestation| &attestation.signatures),
+        )
+        .filter(|signature| matches!(signature, Signature::CertificateIdentity { .. }));
+    for signature in signatures {
+        signature.verifier().map_err(|e| {
+            VerifyError::InvalidVerifyFileError(format!("Not a valid signature: {e}"))
+        })?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rstest::rstest;
 
     #[test]
     fn test_deserialize_on_broken_yaml() {
@@ -278,6 +570,7 @@ mod tests {
                     let expected = VerificationConfigV1 {
                         all_of: Some(signatures),
                         any_of: None,
+                        attestations: None,
                     };
                     assert_eq!(v1, expected);
                 }
@@ -322,6 +615,7 @@ mod tests {
                     let expected = VerificationConfigV1 {
                         all_of: Some(signatures),
                         any_of: None,
+                        attestations: None,
                     };
                     assert_eq!(v1, expected);
                 }
@@ -330,4 +624,332 @@ mod tests {
             _ => panic!("got an invalid config"),
         }
     }
+
+    #[test]
+    fn test_deserialize_certificate_identities() {
+        let config = r#"---
+    apiVersion: v1
+
+    anyOf:
+      minimumMatches: 1
+      signatures:
+      - kind: certificateIdentity
+        issuerRegex: https://token\.actions\.githubusercontent\.com
+        subject:
+           regex: https://github\.com/kubewarden/.*
+        extensions:
+          sourceRepositoryRef: refs/heads/main
+          runnerEnvironment: github-hosted
+        requireTransparencyLog: true
+      - kind: gitlabCi
+        owner: kubewarden/policies
+        repo: pod-privileged
+    "#;
+
+        let vc = build_latest_verification_config(config).expect("valid config");
+        let signatures: Vec<Signature> = vec![
+            Signature::CertificateIdentity {
+                issuer: None,
+                issuer_regex: Some(r"https://token\.actions\.githubusercontent\.com".to_string()),
+                subject: Subject::Regex(r"https://github\.com/kubewarden/.*".to_string()),
+                extensions: Some(Box::new(CertificateExtensions {
+                    source_repository_ref: Some("refs/heads/main".to_string()),
+                    runner_environment: Some("github-hosted".to_string()),
+                    ..Default::default()
+                })),
+                require_transparency_log: true,
+                annotations: None,
+            },
+            Signature::GitlabCi {
+                owner: "kubewarden/policies".to_string(),
+                repo: Some("pod-privileged".to_string()),
+                issuer: None,
+                annotations: None,
+            },
+        ];
+        assert_eq!(
+            vc.default.and_then(|default| default.any_of),
+            Some(AnyOf {
+                minimum_matches: 1,
+                signatures,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::invalid_subject_regex(
+        r#"
+      - kind: genericIssuer
+        issuer: https://token.actions.githubusercontent.com
+        subject:
+           regex: "https://github.com/(kubewarden"
+    "#
+    )]
+    #[case::invalid_issuer_regex(
+        r#"
+      - kind: certificateIdentity
+        issuerRegex: "https://(token"
+        subject:
+           equal: user@provider.com
+    "#
+    )]
+    #[case::missing_issuer(
+        r#"
+      - kind: certificateIdentity
+        subject:
+           equal: user@provider.com
+    "#
+    )]
+    #[case::unknown_extension(
+        r#"
+      - kind: certificateIdentity
+        issuer: https://token.actions.githubusercontent.com
+        subject:
+           equal: user@provider.com
+        extensions:
+          branch: main
+    "#
+    )]
+    fn test_deserialize_invalid_certificate_identities(#[case] signature: &str) {
+        let config = format!(
+            r#"---
+    apiVersion: v1
+
+    allOf:{signature}"#
+        );
+        assert!(matches!(
+            build_latest_verification_config(&config),
+            Err(VerifyError::InvalidVerifyFileError(_))
+        ));
+    }
+
+    fn github_action(owner: &str) -> Signature {
+        Signature::GithubAction {
+            owner: owner.to_string(),
+            repo: None,
+            annotations: None,
+        }
+    }
+
+    #[rstest]
+    #[case::versioned("apiVersion: v1\n")]
+    #[case::unversioned("")]
+    fn test_migrate_v1(#[case] api_version: &str) {
+        let config = format!(
+            r#"---
+{api_version}allOf:
+  - kind: githubAction
+    owner: kubewarden
+"#
+        );
+
+        let vc = build_latest_verification_config(&config).expect("valid config");
+        assert_eq!(
+            vc,
+            VerificationConfigV2 {
+                default: Some(SignatureRequirements {
+                    all_of: Some(vec![github_action("kubewarden")]),
+                    any_of: None,
+                    attestations: None,
+                }),
+                policies: Vec::new(),
+                allow_unsigned: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_v2() {
+        let config = r#"---
+    apiVersion: v2
+
+    default:
+      allOf:
+        - kind: githubAction
+          owner: kubewarden
+    policies:
+      - reference: registry.example.com/acme/**
+        signatures:
+          anyOf:
+            signatures:
+              - kind: githubAction
+                owner: acme
+    allowUnsigned:
+      - registry.example.com/playground/*
+    "#;
+
+        let vc = build_latest_verification_config(config).expect("valid config");
+        assert_eq!(
+            vc,
+            VerificationConfigV2 {
+                default: Some(SignatureRequirements {
+                    all_of: Some(vec![github_action("kubewarden")]),
+                    any_of: None,
+                    attestations: None,
+                }),
+                policies: vec![PolicyVerificationRule {
+                    reference: "registry.example.com/acme/**".to_string(),
+                    signatures: SignatureRequirements {
+                        all_of: None,
+                        any_of: Some(AnyOf {
+                            minimum_matches: 1,
+                            signatures: vec![github_action("acme")],
+                        }),
+                        attestations: None,
+                    },
+                }],
+                allow_unsigned: vec!["registry.example.com/playground/*".to_string()],
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::missing_signatures(
+        r#"
+    policies:
+      - reference: registry.example.com/acme/*
+        signatures: {}
+    "#,
+        "registry.example.com/acme/* is missing signatures in both allOf and anyOff list"
+    )]
+    #[case::unknown_field(
+        r#"
+    policies:
+      - reference: registry.example.com/acme/*
+        allOf:
+          - kind: githubAction
+            owner: acme
+    "#,
+        "Not a valid configuration file: unknown field `allOf`, expected `reference` or `signatures`"
+    )]
+    #[case::attestation_without_signatures(
+        r#"
+    default:
+      allOf:
+        - kind: githubAction
+          owner: kubewarden
+      attestations:
+        - predicateType: https://slsa.dev/provenance/v1
+          signatures: []
+    "#,
+        "default has an attestation of type https://slsa.dev/provenance/v1 without signatures"
+    )]
+    fn test_deserialize_invalid_v2(#[case] body: &str, #[case] expected: &str) {
+        let config = format!("---\n    apiVersion: v2\n{body}");
+        match build_latest_verification_config(&config) {
+            Err(VerifyError::InvalidVerifyFileError(msg)) => assert_eq!(msg, expected),
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_attestations() {
+        let config = r#"---
+    apiVersion: v2
+    default:
+      allOf:
+        - kind: githubAction
+          owner: kubewarden
+      attestations:
+        - predicateType: https://slsa.dev/provenance/v1
+          signatures:
+            - kind: githubAction
+              owner: kubewarden
+          builderId: https://github.com/kubewarden/github-actions/.github/workflows/reusable-release-policy-rust.yml@refs/tags/v3.0.0
+          sourceRepository: https://github.com/kubewarden/pod-privileged-policy
+    "#;
+        let config = build_latest_verification_config(config).expect("valid config");
+        assert_eq!(
+            config.default.and_then(|default| default.attestations),
+            Some(vec![AttestationRequirement {
+                predicate_type: "https://slsa.dev/provenance/v1".to_string(),
+                signatures: vec![github_action("kubewarden")],
+                builder_id: Some("https://github.com/kubewarden/github-actions/.github/workflows/reusable-release-policy-rust.yml@refs/tags/v3.0.0".to_string()),
+                source_repository: Some("https://github.com/kubewarden/pod-privileged-policy".to_string()),
+                source_ref: None,
+            }])
+        );
+    }
+
+    #[rstest]
+    #[case::exact("ghcr.io/kubewarden/policies/pod-privileged:v0.1.0", true)]
+    #[case::single_segment("ghcr.io/kubewarden/policies/*", true)]
+    #[case::single_segment_no_nesting("ghcr.io/kubewarden/*", false)]
+    #[case::any_segments("ghcr.io/kubewarden/**", true)]
+    #[case::single_char("ghcr.io/kubewarden/policies/pod-privileged:v0.1.?", true)]
+    #[case::single_chars("ghcr.io/kubewarden/policies/pod-privileged:v0?1?0", true)]
+    #[case::single_char_no_slash("ghcr.io/kubewarden?policies/pod-privileged:v0.1.0", false)]
+    #[case::dot_is_literal("ghcr.io/kubewarden/policies/pod.privileged:v0.1.0", false)]
+    #[case::no_partial_match("ghcr.io/kubewarden/policies/pod", false)]
+    #[case::escaped("ghcr.io/kubewarden/policies/pod-privileged:v0.1.0+", false)]
+    fn test_reference_pattern(#[case] pattern: &str, #[case] expected: bool) {
+        let regex = reference_pattern_regex(pattern).expect("valid pattern");
+        assert_eq!(
+            regex.is_match("ghcr.io/kubewarden/policies/pod-privileged:v0.1.0"),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::first_matching_rule(
+        "registry://registry.example.com/acme/team/policy:v1",
+        Some("acme")
+    )]
+    #[case::second_matching_rule("registry://registry.example.com/team/acme:v1", Some("team"))]
+    #[case::catch_all_rule("registry://registry.example.com/team/policy:v1", Some("example"))]
+    #[case::unsigned_over_rules("registry://registry.example.com/playground/acme:v1", None)]
+    #[case::default("registry://ghcr.io/kubewarden/policies/policy:v1", Some("kubewarden"))]
+    #[case::https("https://example.com/policies/policy.wasm", None)]
+    fn test_policy_verification(#[case] policy_url: &str, #[case] owner: Option<&str>) {
+        let requirements = |owner: &str| SignatureRequirements {
+            all_of: Some(vec![github_action(owner)]),
+            any_of: None,
+            attestations: None,
+        };
+        let config = VerificationConfigV2 {
+            default: Some(requirements("kubewarden")),
+            policies: vec![
+                PolicyVerificationRule {
+                    reference: "registry.example.com/acme/**".to_string(),
+                    signatures: requirements("acme"),
+                },
+                PolicyVerificationRule {
+                    reference: "registry.example.com/**/acme:*".to_string(),
+                    signatures: requirements("team"),
+                },
+                PolicyVerificationRule {
+                    reference: "registry.example.com/**".to_string(),
+                    signatures: requirements("example"),
+                },
+            ],
+            allow_unsigned: vec![
+                "registry.example.com/playground/*".to_string(),
+                "https://example.com/policies/*.wasm".to_string(),
+            ],
+        };
+
+        let expected = owner.map(requirements);
+        match config
+            .policy_verification(policy_url)
+            .expect("policy verification")
+        {
+            PolicyVerification::Signatures(requirements) => {
+                assert_eq!(Some(requirements), expected.as_ref())
+            }
+            PolicyVerification::Unsigned => assert!(expected.is_none()),
+        }
+    }
+
+    #[test]
+    fn test_policy_verification_without_default() {
+        let config = VerificationConfigV2 {
+            allow_unsigned: vec!["registry.example.com/playground/*".to_string()],
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            config.policy_verification("registry://ghcr.io/kubewarden/policies/policy:v1"),
+            Err(VerifyError::NoVerificationRuleError(_))
+        ));
+    }
 }
@@ -29,8 +29,20 @@ pub enum VerifyError {
     DigestErrors(#[from] crate::policy::DigestError),
     #[error(transparent)]
     RegistryError(#[from] RegistryError),
+    #[error(transparent)]
+    StoreError(#[from] crate::store::errors::StoreError),
     #[error("{0}")]
     GithubUrlParserError(String),
     #[error(transparent)]
     FailedToParseYamlDataError(#[from] FailedToParseYamlDataError),
+    #[error("invalid Sigstore bundle: {0}")]
+    InvalidBundleError(String),
+    #[error("Sigstore bundle verification failed: {0}")]
+    BundleVerificationError(String),
+    #[error("invalid regular expression: {0}")]
+    InvalidRegexError(#[from] regex::Error),
+    #[error("no verification rule matches policy {0}")]
+    NoVerificationRuleError(String),
+    #[error("{0}")]
+    AttestationVerificationError(String),
 }
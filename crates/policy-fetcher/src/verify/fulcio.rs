@@ -0,0 +1,255 @@
+//! Identity of the signers, as recorded by the certificates issued by Fulcio.
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use sigstore::cosign::{SignatureLayer, signature_layers::CertificateSubject};
+use std::collections::BTreeMap;
+use tracing::debug;
+use x509_parser::{extensions::GeneralName, pem::parse_x509_pem, prelude::*};
+
+use crate::verify::{
+    attestation,
+    bundle::{self, HashedRekord, Intoto},
+};
+
+// Fulcio certificate extensions, see
+// https://github.com/sigstore/fulcio/blob/main/docs/oid-info.md
+//
+// The values of the deprecated extensions are raw strings, while the ones of
+// the newer extensions are DER encoded UTF8Strings.
+pub(crate) const OID_ISSUER: &str = "1.3.6.1.4.1.57264.1.1";
+pub(crate) const OID_GITHUB_WORKFLOW_TRIGGER: &str = "1.3.6.1.4.1.57264.1.2";
+pub(crate) const OID_GITHUB_WORKFLOW_SHA: &str = "1.3.6.1.4.1.57264.1.3";
+pub(crate) const OID_GITHUB_WORKFLOW_NAME: &str = "1.3.6.1.4.1.57264.1.4";
+pub(crate) const OID_GITHUB_WORKFLOW_REPOSITORY: &str = "1.3.6.1.4.1.57264.1.5";
+pub(crate) const OID_GITHUB_WORKFLOW_REF: &str = "1.3.6.1.4.1.57264.1.6";
+pub(crate) const OID_ISSUER_V2: &str = "1.3.6.1.4.1.57264.1.8";
+pub(crate) const OID_BUILD_SIGNER_URI: &str = "1.3.6.1.4.1.57264.1.9";
+pub(crate) const OID_BUILD_SIGNER_DIGEST: &str = "1.3.6.1.4.1.57264.1.10";
+pub(crate) const OID_RUNNER_ENVIRONMENT: &str = "1.3.6.1.4.1.57264.1.11";
+pub(crate) const OID_SOURCE_REPOSITORY_URI: &str = "1.3.6.1.4.1.57264.1.12";
+pub(crate) const OID_SOURCE_REPOSITORY_DIGEST: &str = "1.3.6.1.4.1.57264.1.13";
+pub(crate) const OID_SOURCE_REPOSITORY_REF: &str = "1.3.6.1.4.1.57264.1.14";
+pub(crate) const OID_SOURCE_REPOSITORY_IDENTIFIER: &str = "1.3.6.1.4.1.57264.1.15";
+pub(crate) const OID_SOURCE_REPOSITORY_OWNER_URI: &str = "1.3.6.1.4.1.57264.1.16";
+pub(crate) const OID_SOURCE_REPOSITORY_OWNER_IDENTIFIER: &str = "1.3.6.1.4.1.57264.1.17";
+pub(crate) const OID_BUILD_CONFIG_URI: &str = "1.3.6.1.4.1.57264.1.18";
+pub(crate) const OID_BUILD_CONFIG_DIGEST: &str = "1.3.6.1.4.1.57264.1.19";
+pub(crate) const OID_BUILD_TRIGGER: &str = "1.3.6.1.4.1.57264.1.20";
+pub(crate) const OID_RUN_INVOCATION_URI: &str = "1.3.6.1.4.1.57264.1.21";
+pub(crate) const OID_SOURCE_REPOSITORY_VISIBILITY: &str = "1.3.6.1.4.1.57264.1.22";
+
+const DEPRECATED_OIDS: [&str; 6] = [
+    OID_ISSUER,
+    OID_GITHUB_WORKFLOW_TRIGGER,
+    OID_GITHUB_WORKFLOW_SHA,
+    OID_GITHUB_WORKFLOW_NAME,
+    OID_GITHUB_WORKFLOW_REPOSITORY,
+    OID_GITHUB_WORKFLOW_REF,
+];
+
+/// The identity of the signer found inside of a Fulcio certificate
+pub(crate) struct FulcioIdentity {
+    pub(crate) subject: Option<CertificateSubject>,
+    /// The Fulcio extensions, indexed by their OID
+    extensions: BTreeMap<String, String>,
+}
+
+impl FulcioIdentity {
+    pub(crate) fn from_certificate(certificate: &X509Certificate) -> Self {
+        let subject = certificate
+            .subject_alternative_name()
+            .ok()
+            .flatten()
+            .and_then(|san| {
+                san.value.general_names.iter().find_map(|name| match name {
+                    GeneralName::RFC822Name(email) => {
+                        Some(CertificateSubject::Email(email.to_string()))
+                    }
+                    GeneralName::URI(uri) => Some(CertificateSubject::Uri(uri.to_string())),
+                    _ => None,
+                })
+            });
+        let extensions = certificate
+            .extensions()
+            .iter()
+            .filter_map(|extension| {
+                let oid = extension.oid.to_id_string();
+                let value = if DEPRECATED_OIDS.contains(&oid.as_str()) {
+                    String::from_utf8(extension.value.to_vec()).ok()
+                } else {
+                    der_utf8_string(extension.value)
+                };
+                value.map(|value| (oid, value))
+            })
+            .collect();
+
+        Self {
+            subject,
+            extensions,
+        }
+    }
+
+    /// The OIDC issuer, read from the newer extension when available
+    pub(crate) fn issuer(&self) -> Option<&str> {
+        self.extension(OID_ISSUER_V2)
+            .or_else(|| self.extension(OID_ISSUER))
+    }
+
+    pub(crate) fn extension(&self, oid: &str) -> Option<&str> {
+        self.extensions.get(oid).map(|value| value.as_str())
+    }
+}
+
+/// Returns the string held by the subject of a certificate, either an email or an URI
+pub(crate) fn subject_value(subject: &CertificateSubject) -> &str {
+    match subject {
+        CertificateSubject::Email(email) => email,
+        CertificateSubject::Uri(uri) => uri,
+    }
+}
+
+/// Reads the identity of the signer from the certificate recorded by the
+/// transparency log entry of the signature layer.
+///
+/// The certificate signature of the layer exposes only a subset of the Fulcio
+/// extensions, while the entry holds the whole certificate. The certificate of
+/// the entry must refer to the same signature and to the same identity of the
+/// certificate signature of the layer, which has already been verified.
+pub(crate) fn transparency_log_identity(sl: &SignatureLayer) -> Option<FulcioIdentity> {
+    let certificate_signature = sl.certificate_signature.as_ref()?;
+    // the bundle is serialized to be independent from its representation
+    let bundle = serde_json::to_value(sl.bundle.as_ref()?).ok()?;
+    let body = bundle.get("Payload")?.get("body")?.as_str()?;
+    let body = STANDARD.decode(body).ok()?;
+
+    let entry_certificates: Vec<String> = match bundle::entry_kind(&body)?.as_str() {
+        "hashedrekord" => {
+            let entry: HashedRekord = serde_json::from_slice(&body).ok()?;
+            let signature = STANDARD.decode(&entry.spec.signature.content).ok()?;
+            let layer_signature = STANDARD.decode(sl.signature.as_ref()?).ok()?;
+            if signature != layer_signature {
+                debug!("the transparency log entry refers to a different signature");
+                return None;
+            }
+            vec![entry.spec.signature.public_key.content]
+        }
+        "intoto" => {
+            // the raw data of attestation layers is the signed DSSE message
+            let entry: Intoto = serde_json::from_slice(&body).ok()?;
+            let payload = attestation::pae_payload(&sl.raw_data)?;
+            if !entry.records_payload(payload) {
+                debug!("the transparency log entry refers to a different attestation");
+                return None;
+            }
+            entry.public_keys().map(|key| key.to_string()).collect()
+        }
+        _ => return None,
+    };
+
+    let identity = entry_certificates.iter().find_map(|pem| {
+        let pem = STANDARD.decode(pem).ok()?;
+        let (_, pem) = parse_x509_pem(&pem).ok()?;
+        let (_, certificate) = X509Certificate::from_der(&pem.contents).ok()?;
+        let identity = FulcioIdentity::from_certificate(&certificate);
+        let same_identity = identity.issuer() == certificate_signature.issuer.as_deref()
+            && identity.subject.as_ref().map(subject_value)
+                == Some(subject_value(&certificate_signature.subject));
+        same_identity.then_some(identity)
+    });
+    if identity.is_none() {
+        debug!("the transparency log entry refers to a different identity");
+    }
+
+    identity
+}
+
+/// Decodes a DER encoded UTF8String, used by the newer Fulcio extensions
+pub(crate) fn der_utf8_string(value: &[u8]) -> Option<String> {
+    let (&tag, rest) = value.split_first()?;
+    if tag != 0x0c {
+        return None;
+    }
+    let (&length, rest) = rest.split_first()?;
+    let (length, rest) = match length {
+        length if length < 0x80 => (length as usize, rest),
+        0x81 => {
+            let (&length, rest) = rest.split_first()?;
+            (length as usize, rest)
+        }
+        0x82 => {
+            let (length, rest) = rest.split_first_chunk::<2>()?;
+            (u16::from_be_bytes(*length) as usize, rest)
+        }
+        _ => return None,
+    };
+    String::from_utf8(rest.get(..length)?.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn der_utf8(value: &str) -> Vec<u8> {
+        let mut der = vec![0x0c, value.len() as u8];
+        der.extend_from_slice(value.as_bytes());
+        der
+    }
+
+    #[test]
+    fn identity_from_certificate() {
+        let mut params = rcgen::CertificateParams::default();
+        params.subject_alt_names = vec![rcgen::SanType::URI(
+            "https://github.com/kubewarden/policy/.github/workflows/release.yml@refs/heads/main"
+                .try_into()
+                .unwrap(),
+        )];
+        params.custom_extensions = vec![
+            rcgen::CustomExtension::from_oid_content(
+                &[1, 3, 6, 1, 4, 1, 57264, 1, 8],
+                der_utf8("https://token.actions.githubusercontent.com"),
+            ),
+            rcgen::CustomExtension::from_oid_content(
+                &[1, 3, 6, 1, 4, 1, 57264, 1, 5],
+                b"kubewarden/policy".to_vec(),
+            ),
+            rcgen::CustomExtension::from_oid_content(
+                &[1, 3, 6, 1, 4, 1, 57264, 1, 14],
+                der_utf8("refs/heads/main"),
+            ),
+        ];
+        let key_pair = rcgen::KeyPair::generate().unwrap();
+        let certificate = params.self_signed(&key_pair).unwrap();
+        let (_, certificate) = X509Certificate::from_der(certificate.der()).unwrap();
+
+        let identity = FulcioIdentity::from_certificate(&certificate);
+        assert_eq!(
+            identity.issuer(),
+            Some("https://token.actions.githubusercontent.com")
+        );
+        assert_eq!(
+            identity.subject.as_ref().map(subject_value),
+            Some(
+                "https://github.com/kubewarden/policy/.github/workflows/release.yml@refs/heads/main"
+            )
+        );
+        assert_eq!(
+            identity.extension(OID_GITHUB_WORKFLOW_REPOSITORY),
+            Some("kubewarden/policy")
+        );
+        assert_eq!(
+            identity.extension(OID_SOURCE_REPOSITORY_REF),
+            Some("refs/heads/main")
+        );
+        assert_eq!(identity.extension(OID_RUNNER_ENVIRONMENT), None);
+    }
+
+    #[rstest]
+    #[case::short_form(&[0x0c, 0x03, b'f', b'o', b'o'], Some("foo"))]
+    #[case::long_form(&[0x0c, 0x81, 0x03, b'f', b'o', b'o'], Some("foo"))]
+    #[case::wrong_tag(&[0x04, 0x03, b'f', b'o', b'o'], None)]
+    #[case::truncated(&[0x0c, 0x05, b'f', b'o', b'o'], None)]
+    fn decode_der_utf8_string(#[case] value: &[u8], #[case] expected: Option<&str>) {
+        assert_eq!(der_utf8_string(value).as_deref(), expected);
+    }
+}
@@ -0,0 +1,204 @@
+//! Verification of the image signatures published by cosign, without
+//! contacting the registry.
+//!
+//! `cosign sign` stores the signatures of an image inside of the
+//! `sha256-<digest>.sig` tag of its repository. Each layer is a simple signing
+//! payload referring to the manifest digest of the image, its signature is
+//! stored inside of the annotations of the layer, together with the
+//! certificate and the Rekor bundle of keyless signatures. The artifact is
+//! read from the store, where it's saved when loading OCI image layouts.
+
+use sha2::{Digest, Sha256};
+use sigstore::{
+    cosign::{payload::simple_signing::SimpleSigning, signature_layers::SignatureLayer},
+    trust::TrustRoot,
+};
+use tracing::warn;
+
+use crate::{
+    oci_layout::{self, Artifact, Descriptor},
+    verify::{
+        bundle::{self, LocalBundle, SignedContent},
+        errors::{VerifyError, VerifyResult},
+    },
+};
+
+pub const SIMPLE_SIGNING_MEDIA_TYPE: &str = "application/vnd.dev.cosign.simplesigning.v1+json";
+const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+
+/// Verifies the signatures held by the given cosign artifact, discarding the
+/// ones that cannot be verified. `image_digest` is the manifest digest of the
+/// image, which must be the one signed by the payloads.
+pub(crate) fn verify_signatures(
+    image_url: &str,
+    image_digest: &str,
+    artifact: &Artifact,
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<Vec<SignatureLayer>> {
+    let descriptors = oci_layout::referenced_blobs(&artifact.manifest).map_err(|e| {
+        VerifyError::ImageVerificationError(format!(
+            "invalid signatures manifest of image {image_url}: {e}"
+        ))
+    })?;
+
+    let layers = descriptors
+        .iter()
+        .filter(|descriptor| descriptor.media_type == SIMPLE_SIGNING_MEDIA_TYPE)
+        .filter_map(|descriptor| {
+            let blob = artifact.blobs.get(&descriptor.digest)?;
+            match verify_signature(image_url, image_digest, descriptor, blob, trust_root) {
+                Ok(layer) => Some(layer),
+                Err(error) => {
+                    warn!(
+                        image = image_url,
+                        layer = descriptor.digest,
+                        %error,
+                        "discarding signature"
+                    );
+                    None
+                }
+            }
+        })
+        .collect();
+    Ok(layers)
+}
+
+/// Verifies the signature of a simple signing payload, and ensures the
+/// payload refers to the image
+fn verify_signature(
+    image_url: &str,
+    image_digest: &str,
+    descriptor: &Descriptor,
+    blob: &[u8],
+    trust_root: Option<&(dyn TrustRoot + Sync)>,
+) -> VerifyResult<SignatureLayer> {
+    if format!("sha256:{}", hex::encode(Sha256::digest(blob))) != descriptor.digest {
+        return Err(VerifyError::ImageVerificationError(
+            "the digest of the layer doesn't match with its descriptor".to_string(),
+        ));
+    }
+    let signature = descriptor
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(SIGNATURE_ANNOTATION))
+        .ok_or_else(|| {
+            VerifyError::ImageVerificationError("the layer is not signed".to_string())
+        })?;
+
+    let payload: serde_json::Value = serde_json::from_slice(blob).map_err(|e| {
+        VerifyError::ImageVerificationError(format!("invalid simple signing payload: {e}"))
+    })?;
+    let signed_digest = payload
+        .pointer("/critical/image/docker-manifest-digest")
+        .and_then(|digest| digest.as_str());
+    if signed_digest != Some(image_digest) {
+        return Err(VerifyError::ImageVerificationError(
+            "the simple signing payload refers to a different image".to_string(),
+        ));
+    }
+    let simple_signing: SimpleSigning = serde_json::from_value(payload).map_err(|e| {
+        VerifyError::ImageVerificationError(format!("invalid simple signing payload: {e}"))
+    })?;
+
+    let bundle = LocalBundle::from_cosign_layer(signature, descriptor)?;
+    let mut layer = bundle::verify_signed_content(
+        image_url,
+        image_digest,
+        &SignedContent::Blob(blob),
+        &bundle,
+        trust_root,
+    )?;
+    // the annotations of the payload are checked by the verification constraints
+    layer.simple_signing = simple_signing;
+    Ok(layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use std::collections::BTreeMap;
+
+    const IMAGE_DIGEST: &str =
+        "sha256:93a0d2f8f8c2b0d8bd5a7a7d8b5b7d9b1f8a0a4f1c6c3b2f5b1f7f1c1a8d4e2f";
+
+    fn payload(image_digest: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "critical": {
+                "identity": {"docker-reference": "ghcr.io/kubewarden/policy"},
+                "image": {"docker-manifest-digest": image_digest},
+                "type": "cosign container image signature"
+            },
+            "optional": {"owner": "kubewarden"}
+        }))
+        .unwrap()
+    }
+
+    fn signatures_artifact(payload: &[u8], signature: &[u8]) -> Artifact {
+        let sha256 = |data: &[u8]| format!("sha256:{}", hex::encode(Sha256::digest(data)));
+        let config = b"{}".to_vec();
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": sha256(&config),
+                "size": config.len(),
+            },
+            "layers": [{
+                "mediaType": SIMPLE_SIGNING_MEDIA_TYPE,
+                "digest": sha256(payload),
+                "size": payload.len(),
+                "annotations": {SIGNATURE_ANNOTATION: STANDARD.encode(signature)},
+            }],
+        });
+        Artifact {
+            media_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
+            manifest: serde_json::to_vec(&manifest).unwrap(),
+            blobs: BTreeMap::from([
+                (sha256(&config), config),
+                (sha256(payload), payload.to_vec()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn signature_made_with_a_key() {
+        // signatures made with a key are checked by the verification constraints
+        let payload = payload(IMAGE_DIGEST);
+        let artifact = signatures_artifact(&payload, b"signature");
+
+        let layers = verify_signatures(
+            "ghcr.io/kubewarden/policy:v1",
+            IMAGE_DIGEST,
+            &artifact,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].raw_data, payload);
+        assert_eq!(layers[0].signature.as_deref(), Some("c2lnbmF0dXJl"));
+        assert_eq!(
+            serde_json::to_value(&layers[0].simple_signing).unwrap()["optional"]["owner"],
+            "kubewarden"
+        );
+    }
+
+    #[test]
+    fn signature_of_another_image_is_discarded() {
+        let payload =
+            payload("sha256:0000000000000000000000000000000000000000000000000000000000000000");
+        let artifact = signatures_artifact(&payload, b"signature");
+
+        let layers = verify_signatures(
+            "ghcr.io/kubewarden/policy:v1",
+            IMAGE_DIGEST,
+            &artifact,
+            None,
+        )
+        .unwrap();
+
+        assert!(layers.is_empty());
+    }
+}
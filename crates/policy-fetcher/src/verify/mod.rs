@@ -3,34 +3,66 @@ use sigstore::{
     cosign::{self, ClientBuilder, CosignCapabilities, signature_layers::SignatureLayer},
     errors::SigstoreError,
     registry::oci_reference::OciReference,
-    trust::sigstore::SigstoreTrustRoot,
+    trust::{TrustRoot, sigstore::SigstoreTrustRoot},
 };
-use std::{convert::TryFrom, str::FromStr, sync::Arc};
+use std::{convert::TryFrom, path::Path, str::FromStr, sync::Arc};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
 use crate::{
     Registry,
     errors::FailedToParseYamlDataError,
+    oci_layout,
     policy::Policy,
-    registry::build_fully_resolved_reference,
+    registry::{build_fully_resolved_reference, credentials::RegistryAction},
     sources::Sources,
+    store::Store,
     verify::{
         config::Signature,
         errors::{VerifyError, VerifyResult},
     },
 };
 
+pub mod attestation;
+pub mod bundle;
 pub mod config;
 pub mod errors;
+mod fulcio;
+pub mod image_signature;
 pub mod verification_constraints;
 
+/// The suffixes of the tags holding the signatures and the attestations
+/// published by cosign next to an image
+pub const SIGNATURES_SUFFIX: &str = "sig";
+pub const ATTESTATIONS_SUFFIX: &str = "att";
+
+/// The reference of the artifact published by cosign next to the image with
+/// the given manifest digest, like `ghcr.io/kubewarden/policy:sha256-<hex>.sig`
+pub fn cosign_ref_name(reference: &Reference, image_digest: &str, suffix: &str) -> String {
+    format!(
+        "{}/{}:{}.{suffix}",
+        reference.registry(),
+        reference.repository(),
+        image_digest.replace(':', "-")
+    )
+}
+
+/// The outcome of a successful verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The manifest digest of the verified policy
+    pub manifest_digest: String,
+    /// The attestations satisfying the attestation requirements, in the same order
+    pub attestations: Vec<attestation::Attestation>,
+}
+
 /// This structure simplifies the process of policy verification
 /// using Sigstore
 #[derive(Clone)]
 pub struct Verifier {
     cosign_client: Arc<Mutex<sigstore::cosign::Client>>,
     sources: Option<Sources>,
+    trust_root: Option<Arc<SigstoreTrustRoot>>,
 }
 
 impl Verifier {
@@ -43,6 +75,7 @@ impl Verifier {
         Self {
             cosign_client,
             sources,
+            trust_root: None,
         }
     }
 
@@ -57,7 +90,7 @@ impl Verifier {
         let mut cosign_client_builder = ClientBuilder::default()
             .with_oci_client_config(client_config)
             .enable_registry_caching();
-        let cosign_client = match trust_root {
+        let cosign_client = match trust_root.as_ref() {
             Some(trust_root) => {
                 cosign_client_builder =
                     cosign_client_builder.with_trust_repository(trust_root.as_ref())?;
@@ -79,13 +112,16 @@ impl Verifier {
         Ok(Verifier {
             cosign_client: Arc::new(Mutex::new(cosign_client)),
             sources,
+            trust_root,
         })
     }
 
-    /// Verifies the given policy using the LatestVerificationConfig provided by
-    /// the user.
+    /// Verifies the given policy against its signature requirements, see
+    /// `config::VerificationConfigV2::policy_verification`.
     ///
     /// In case of success, returns the manifest digest of the verified policy.
+    /// The attestations required by the signature requirements are verified
+    /// too, see [`Verifier::verify_with_report`].
     ///
     /// Note well: this method doesn't compare the checksum of a possible local
     /// file with the one inside of the signed (and verified) manifest, as that
@@ -96,21 +132,155 @@ impl Verifier {
     pub async fn verify(
         &mut self,
         image_url: &str,
-        verification_config: &config::LatestVerificationConfig,
+        signature_requirements: &config::SignatureRequirements,
     ) -> VerifyResult<String> {
+        Ok(self
+            .verify_with_report(image_url, signature_requirements)
+            .await?
+            .manifest_digest)
+    }
+
+    /// Verifies the given policy like [`Verifier::verify`]. In case of
+    /// success, returns the manifest digest of the verified policy together
+    /// with the attestations satisfying the requirements.
+    pub async fn verify_with_report(
+        &mut self,
+        image_url: &str,
+        signature_requirements: &config::SignatureRequirements,
+    ) -> VerifyResult<VerificationReport> {
         let (source_image_digest, trusted_layers) =
-            fetch_sigstore_remote_data(&self.cosign_client, image_url).await?;
+            fetch_sigstore_remote_data(&self.cosign_client, image_url, self.sources.as_ref())
+                .await?;
 
         // verify signatures against our config:
         //
-        verify_signatures_against_config(verification_config, &trusted_layers)?;
+        verify_signatures_against_config(signature_requirements, &trusted_layers)?;
+
+        // verify attestations against our config:
+        //
+        let attestations = match signature_requirements.attestations.as_deref() {
+            Some(requirements) if !requirements.is_empty() => {
+                let trust_root = self
+                    .trust_root
+                    .as_deref()
+                    .map(|trust_root| trust_root as &(dyn TrustRoot + Sync));
+                let trusted_attestations = attestation::fetch_attestations(
+                    image_url,
+                    &source_image_digest,
+                    self.sources.as_ref(),
+                    trust_root,
+                )
+                .await?;
+                attestation::verify_attestations_against_requirements(
+                    requirements,
+                    &trusted_attestations,
+                )?
+            }
+            _ => Vec::new(),
+        };
 
         // everything is fine here:
         debug!(
             policy = image_url.to_string().as_str(),
             "Policy successfully verified"
         );
-        Ok(source_image_digest)
+        Ok(VerificationReport {
+            manifest_digest: source_image_digest,
+            attestations,
+        })
+    }
+
+    /// Verifies the given policy like [`Verifier::verify_with_report`], using
+    /// the signatures and the attestations saved inside of the store by
+    /// [`Store::add_registry_artifact`]. The registry is not contacted, this
+    /// allows to verify the policies loaded from OCI image layouts inside of
+    /// air-gapped environments.
+    ///
+    /// The manifest digest of the policy is the one recorded by the store.
+    /// Returns `None` when the store doesn't hold the policy or its signatures.
+    pub fn verify_stored(
+        &self,
+        store: &Store,
+        image_url: &str,
+        signature_requirements: &config::SignatureRequirements,
+    ) -> VerifyResult<Option<VerificationReport>> {
+        let Some((image_digest, signatures)) = stored_signatures(store, image_url)? else {
+            return Ok(None);
+        };
+        let trust_root = self
+            .trust_root
+            .as_deref()
+            .map(|trust_root| trust_root as &(dyn TrustRoot + Sync));
+
+        let trusted_layers =
+            image_signature::verify_signatures(image_url, &image_digest, &signatures, trust_root)?;
+        verify_signatures_against_config(signature_requirements, &trusted_layers)?;
+
+        let attestations = match signature_requirements.attestations.as_deref() {
+            Some(requirements) if !requirements.is_empty() => {
+                let trusted_attestations =
+                    attestation::stored_attestations(store, image_url, &image_digest, trust_root)?;
+                attestation::verify_attestations_against_requirements(
+                    requirements,
+                    &trusted_attestations,
+                )?
+            }
+            _ => Vec::new(),
+        };
+
+        debug!(
+            policy = image_url,
+            "Policy successfully verified using the signatures inside of the store"
+        );
+        Ok(Some(VerificationReport {
+            manifest_digest: image_digest,
+            attestations,
+        }))
+    }
+
+    /// Verifies the given policy using a local Sigstore bundle and its
+    /// signature requirements.
+    ///
+    /// The signature of the Wasm module is checked against the bundle and the
+    /// trust root of the verifier, without contacting any registry. This works
+    /// with policies stored anywhere, including fully offline environments.
+    pub fn verify_bundle(
+        &self,
+        policy: &Policy,
+        bundle_path: &Path,
+        signature_requirements: &config::SignatureRequirements,
+    ) -> VerifyResult<()> {
+        if signature_requirements
+            .attestations
+            .as_ref()
+            .is_some_and(|attestations| !attestations.is_empty())
+        {
+            return Err(VerifyError::AttestationVerificationError(format!(
+                "Policy {} requires attestations, which cannot be verified using a Sigstore bundle",
+                policy.uri
+            )));
+        }
+        if !policy.local_path.exists() {
+            return Err(VerifyError::MissingWasmFileError(
+                policy.local_path.display().to_string(),
+            ));
+        }
+        let module = std::fs::read(&policy.local_path)?;
+        let bundle = bundle::LocalBundle::from_path(bundle_path)?;
+        let trust_root = self
+            .trust_root
+            .as_deref()
+            .map(|trust_root| trust_root as &(dyn TrustRoot + Sync));
+
+        let trusted_layer = bundle::verify_bundle(&policy.uri, &module, &bundle, trust_root)?;
+        verify_signatures_against_config(signature_requirements, &[trusted_layer])?;
+
+        debug!(
+            policy = policy.uri.as_str(),
+            bundle = ?bundle_path,
+            "Policy successfully verified using Sigstore bundle"
+        );
+        Ok(())
     }
 
     /// Verifies the checksum of the local file by comparing it with the one
@@ -187,16 +357,79 @@ impl Verifier {
     }
 }
 
-/// Verifies the trusted layers against the VerificationConfig passed to it.
+/// Whether the store holds the signatures of the policy, hence it can be
+/// verified without contacting the registry, see [`Verifier::verify_stored`]
+pub fn has_stored_signatures(store: &Store, image_url: &str) -> bool {
+    matches!(stored_signatures(store, image_url), Ok(Some(_)))
+}
+
+/// The manifest digest of the policy recorded by the store, together with
+/// the artifact holding its signatures
+fn stored_signatures(
+    store: &Store,
+    image_url: &str,
+) -> VerifyResult<Option<(String, oci_layout::Artifact)>> {
+    if !image_url.starts_with("registry://") {
+        return Ok(None);
+    }
+    let Some(image_digest) = store
+        .index_entry(image_url)?
+        .and_then(|entry| entry.manifest_digest)
+    else {
+        return Ok(None);
+    };
+    let reference = build_fully_resolved_reference(image_url)?;
+    let signatures = store.registry_artifact(&cosign_ref_name(
+        &reference,
+        &image_digest,
+        SIGNATURES_SUFFIX,
+    ))?;
+    Ok(signatures.map(|signatures| (image_digest, signatures)))
+}
+
+/// Verifies the checksum of the policy stored inside of `store`, like
+/// [`Verifier::verify_local_file_checksum`] does, without contacting the
+/// registry: the store must have pulled the policy from the signed (and
+/// verified) manifest, and the module must not have been tampered with.
+pub fn verify_stored_file_checksum(
+    store: &Store,
+    policy: &Policy,
+    verified_manifest_digest: &str,
+) -> VerifyResult<()> {
+    let entry = store.index_entry(&policy.uri)?.ok_or_else(|| {
+        VerifyError::ChecksumVerificationError(format!(
+            "Cannot verify local file integrity, policy {} is not inside of the store",
+            policy.uri
+        ))
+    })?;
+    if entry.manifest_digest.as_deref() != Some(verified_manifest_digest) {
+        return Err(VerifyError::ChecksumVerificationError(format!(
+            "The policy inside of the store has not been pulled from the signed manifest {verified_manifest_digest}"
+        )));
+    }
+
+    let file_digest = format!("sha256:{}", policy.digest()?);
+    if file_digest != entry.digest {
+        Err(VerifyError::ChecksumVerificationError(format!(
+            "The digest of the local file doesn't match with the one recorded inside of the store. Got {file_digest} instead of {}",
+            entry.digest
+        )))
+    } else {
+        info!("Local file checksum verification passed");
+        Ok(())
+    }
+}
+
+/// Verifies the trusted layers against the signature requirements passed to it.
 /// It does that by creating the verification constraints from the config, and
 /// then filtering the trusted_layers with the corresponding constraints.
 fn verify_signatures_against_config(
-    verification_config: &config::LatestVerificationConfig,
+    signature_requirements: &config::SignatureRequirements,
     trusted_layers: &[SignatureLayer],
 ) -> VerifyResult<()> {
     // filter trusted_layers against our verification constraints:
     //
-    if verification_config.all_of.is_none() && verification_config.any_of.is_none() {
+    if signature_requirements.all_of.is_none() && signature_requirements.any_of.is_none() {
         // deserialized config is already sanitized, and should not reach here anyways
         return Err(VerifyError::ImageVerificationError(
             "Image verification failed: no signatures to verify".to_owned(),
@@ -205,7 +438,7 @@ fn verify_signatures_against_config(
 
     use rayon::prelude::*;
 
-    if let Some(ref signatures_all_of) = verification_config.all_of {
+    if let Some(ref signatures_all_of) = signature_requirements.all_of {
         let unsatisfied_signatures: Vec<&Signature> = signatures_all_of
             .par_iter()
             .filter(|signature| match signature.verifier() {
@@ -240,7 +473,7 @@ fn verify_signatures_against_config(
         }
     }
 
-    if let Some(ref signatures_any_of) = verification_config.any_of {
+    if let Some(ref signatures_any_of) = signature_requirements.any_of {
         let unsatisfied_signatures: Vec<&Signature> = signatures_any_of
             .signatures
             .par_iter()
@@ -281,15 +514,66 @@ fn verify_signatures_against_config(
 /// Returns:
 /// * String holding the source image digest
 /// * List of signature layers
+///
+/// The data is fetched from the mirrors of the image registry, when defined
+/// inside of the `sources`. Signatures are bound to the digest of the image,
+/// which is the same on the mirrors, hence they are still verified against
+/// the original image reference.
 pub async fn fetch_sigstore_remote_data(
     cosign_client_input: &Arc<Mutex<cosign::Client>>,
     image_url: &str,
+    sources: Option<&Sources>,
 ) -> VerifyResult<(String, Vec<SignatureLayer>)> {
     let mut cosign_client = cosign_client_input.lock().await;
 
-    // obtain registry auth:
     let reference = build_fully_resolved_reference(image_url)?;
-    let auth = Registry::auth(reference.registry());
+    let image_name = reference.whole();
+    let sources = sources.cloned().unwrap_or_default();
+
+    let mut last_error = None;
+    for location in sources.mirrored_references(&reference) {
+        let location_name = location.whole();
+        match fetch_sigstore_data_from_location(
+            &mut cosign_client,
+            &image_name,
+            &location,
+            &sources,
+        )
+        .await
+        {
+            Ok(data) => {
+                if location_name != image_name {
+                    info!(
+                        image = image_name,
+                        location = location_name,
+                        "signatures fetched from registry mirror"
+                    );
+                }
+                return Ok(data);
+            }
+            Err(error) => {
+                debug!(
+                    image = image_name,
+                    location = location_name,
+                    ?error,
+                    "cannot fetch signatures"
+                );
+                last_error = Some(error);
+            }
+        }
+    }
+
+    Err(last_error.expect("at least one location is always tried"))
+}
+
+async fn fetch_sigstore_data_from_location(
+    cosign_client: &mut cosign::Client,
+    image_name: &str,
+    location: &Reference,
+    sources: &Sources,
+) -> VerifyResult<(String, Vec<SignatureLayer>)> {
+    // obtain registry auth:
+    let auth = Registry::auth(location, RegistryAction::Pull, sources).await;
 
     let sigstore_auth = match auth {
         RegistryAuth::Anonymous => sigstore::registry::Auth::Anonymous,
@@ -303,8 +587,7 @@ pub async fn fetch_sigstore_remote_data(
     //
     // trusted_signature_layers() will error early if cosign_client using
     // Fulcio,Rekor certs and signatures are not verified
-    let image_name = reference.whole();
-    let image_oci_ref = OciReference::from_str(&image_name)
+    let image_oci_ref = OciReference::from_str(&location.whole())
         .map_err(VerifyError::FailedToFetchTrustedLayersError)?;
     let (cosign_signature_image, source_image_digest) = cosign_client
         .triangulate(&image_oci_ref, &sigstore_auth)
@@ -333,7 +616,7 @@ pub async fn fetch_sigstore_remote_data(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use config::{AnyOf, LatestVerificationConfig, Signature, Subject};
+    use config::{AnyOf, Signature, SignatureRequirements, Subject};
     use cosign::signature_layers::CertificateSubject;
     use sigstore::{
         cosign::payload::simple_signing::SimpleSigning,
@@ -405,12 +688,13 @@ kvUsh4eKpd1lwkDAzfFDs7yXEExsEkPPuiQJBelDT68n7PDIWB/QEY7mrA==
             "https://github.com/login/oauth",
             "user2@provider.com",
         )];
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: Some(signatures_all_of),
             any_of: Some(AnyOf {
                 minimum_matches: 1,
                 signatures: signatures_any_of,
             }),
+            attestations: None,
         };
 
         // build trusted layers:
@@ -426,9 +710,10 @@ kvUsh4eKpd1lwkDAzfFDs7yXEExsEkPPuiQJBelDT68n7PDIWB/QEY7mrA==
     #[test]
     fn test_verify_config_missing_both_any_of_all_of() {
         // build verification config:
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: None,
             any_of: None,
+            attestations: None,
         };
 
         // build trusted layers:
@@ -451,9 +736,10 @@ kvUsh4eKpd1lwkDAzfFDs7yXEExsEkPPuiQJBelDT68n7PDIWB/QEY7mrA==
             "https://github.com/login/oauth",
             "user1@provider.com",
         )];
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: Some(signatures_all_of),
             any_of: None,
+            attestations: None,
         };
 
         // build trusted layers:
@@ -484,9 +770,10 @@ annotations: null
             generic_issuer("https://github.com/login/oauth", "user2@provider.com"),
             generic_issuer("https://github.com/login/oauth", "user3@provider.com"),
         ];
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: Some(signatures_all_of),
             any_of: None,
+            attestations: None,
         };
 
         // build trusted layers:
@@ -517,12 +804,13 @@ annotations: null
             generic_issuer("https://github.com/login/oauth", "user2@provider.com"),
             generic_issuer("https://github.com/login/oauth", "user3@provider.com"),
         ];
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: None,
             any_of: Some(AnyOf {
                 minimum_matches: 2,
                 signatures: signatures_any_of,
             }),
+            attestations: None,
         };
 
         // build trusted layers:
@@ -556,12 +844,13 @@ annotations: null
             generic_issuer("https://github.com/login/oauth", "user2@provider.com"),
             generic_issuer("https://github.com/login/oauth", "user3@provider.com"),
         ];
-        let verification_config = LatestVerificationConfig {
+        let verification_config = SignatureRequirements {
             all_of: None,
             any_of: Some(AnyOf {
                 minimum_matches: 2,
                 signatures: signatures_any_of,
             }),
+            attestations: None,
         };
 
         // build trusted layers:
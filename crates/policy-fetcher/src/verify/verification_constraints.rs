@@ -1,5 +1,6 @@
 use std::{collections::BTreeMap, convert::TryFrom};
 
+use regex::Regex;
 use sigstore::cosign::signature_layers::CertificateSignature;
 use sigstore::cosign::verification_constraint::{
     AnnotationVerifier, PublicKeyVerifier, VerificationConstraint,
@@ -9,10 +10,17 @@ use sigstore::errors::{Result, SigstoreError};
 use tracing::debug;
 
 use crate::verify::{
-    config::Subject,
+    config::{CertificateExtensions, Subject},
     errors::{VerifyError, VerifyResult},
+    fulcio::{self, FulcioIdentity},
 };
 
+/// Compiles a regular expression that must match the whole value, not just
+/// a part of it
+pub(crate) fn full_match_regex(regex: &str) -> VerifyResult<Regex> {
+    Ok(Regex::new(&format!("^(?:{regex})$"))?)
+}
+
 /// Verification Constraint for public keys and annotations
 ///
 /// This constraint ensures that the SignatureLayer contains both a signature
@@ -65,6 +73,7 @@ impl VerificationConstraint for PublicKeyAndAnnotationsVerifier {
 pub struct GenericIssuerSubjectVerifier {
     issuer: String,
     subject: Subject,
+    subject_regex: Option<Regex>,
     annotation_verifier: Option<AnnotationVerifier>,
 }
 
@@ -90,11 +99,19 @@ impl GenericIssuerSubjectVerifier {
                     Subject::UrlPrefix(u)
                 }
             }
+            Subject::Regex(_) => subject.clone(),
+        };
+        // The regular expression is validated when the configuration is
+        // loaded, an invalid one never matches
+        let subject_regex = match &subject {
+            Subject::Regex(regex) => full_match_regex(regex).ok(),
+            _ => None,
         };
 
         Self {
             issuer: issuer.to_string(),
             subject: s,
+            subject_regex,
             annotation_verifier,
         }
     }
@@ -146,6 +163,26 @@ impl GenericIssuerSubjectVerifier {
         }
         satisfied
     }
+
+    fn verify_subject_regex(&self, certificate_signature: &CertificateSignature) -> bool {
+        let certificate_subject = fulcio::subject_value(&certificate_signature.subject);
+
+        let satisfied = Some(&self.issuer) == certificate_signature.issuer.as_ref()
+            && self
+                .subject_regex
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(certificate_subject));
+
+        if !satisfied {
+            debug!(
+                issuer = ?&self.issuer,
+                expected_regex = ?&self.subject,
+                current_value = %certificate_subject,
+                "SubjectRegex not satisfied"
+            );
+        }
+        satisfied
+    }
 }
 
 impl VerificationConstraint for GenericIssuerSubjectVerifier {
@@ -160,6 +197,7 @@ impl VerificationConstraint for GenericIssuerSubjectVerifier {
             Subject::UrlPrefix(prefix) => {
                 self.verify_subject_url_prefix(prefix, certificate_signature)
             }
+            Subject::Regex(_) => self.verify_subject_regex(certificate_signature),
         };
         let outcome = if let Some(av) = &self.annotation_verifier {
             basic_check && av.verify(sl)?
@@ -277,6 +315,312 @@ impl VerificationConstraint for GitHubVerifier {
     }
 }
 
+/// Matches a value either exactly, by prefix or using a regular expression
+#[derive(Debug)]
+enum ValueMatcher {
+    Equal(String),
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl ValueMatcher {
+    fn from_subject(subject: &Subject) -> VerifyResult<Self> {
+        Ok(match subject {
+            Subject::Equal(value) => ValueMatcher::Equal(value.to_owned()),
+            Subject::UrlPrefix(url) => {
+                // the trailing `/` prevents `kubewarden` from matching `kubewarden-hacker`
+                let mut prefix = url.to_string();
+                if !prefix.ends_with('/') {
+                    prefix.push('/');
+                }
+                ValueMatcher::Prefix(prefix)
+            }
+            Subject::Regex(regex) => ValueMatcher::Regex(full_match_regex(regex)?),
+        })
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            ValueMatcher::Equal(expected) => expected == value,
+            ValueMatcher::Prefix(prefix) => value.starts_with(prefix.as_str()),
+            ValueMatcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Returns the Fulcio extensions that must be found inside of the
+/// certificate, indexed by their OID
+fn expected_extensions(extensions: &CertificateExtensions) -> Vec<(&'static str, String)> {
+    [
+        (fulcio::OID_BUILD_SIGNER_URI, &extensions.build_signer_uri),
+        (
+            fulcio::OID_BUILD_SIGNER_DIGEST,
+            &extensions.build_signer_digest,
+        ),
+        (
+            fulcio::OID_RUNNER_ENVIRONMENT,
+            &extensions.runner_environment,
+        ),
+        (
+            fulcio::OID_SOURCE_REPOSITORY_URI,
+            &extensions.source_repository_uri,
+        ),
+        (
+            fulcio::OID_SOURCE_REPOSITORY_DIGEST,
+            &extensions.source_repository_digest,
+        ),
+        (
+            fulcio::OID_SOURCE_REPOSITORY_REF,
+            &extensions.source_repository_ref,
+        ),
+        (
+            fulcio::OID_SOURCE_REPOSITORY_IDENTIFIER,
+            &extensions.source_repository_identifier,
+        ),
+        (
+            fulcio::OID_SOURCE_REPOSITORY_OWNER_URI,
+            &extensions.source_repository_owner_uri,
+        ),
+        (
+            fulcio::OID_SOURCE_REPOSITORY_OWNER_IDENTIFIER,
+            &extensions.source_repository_owner_identifier,
+        ),
+        (fulcio::OID_BUILD_CONFIG_URI, &extensions.build_config_uri),
+        (
+            fulcio::OID_BUILD_CONFIG_DIGEST,
+            &extensions.build_config_digest,
+        ),
+        (fulcio::OID_BUILD_TRIGGER, &extensions.build_trigger),
+        (
+            fulcio::OID_RUN_INVOCATION_URI,
+            &extensions.run_invocation_uri,
+        ),
+        (
+            fulcio::OID_SOURCE_REPOSITORY_VISIBILITY,
+            &extensions.source_repository_visibility_at_signing,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(oid, value)| value.as_ref().map(|value| (oid, value.to_owned())))
+    .collect()
+}
+
+/// Verification Constraint for the identity recorded by Fulcio certificates
+///
+/// This constraint looks at the signature done in keyless mode and inspects
+/// its issuer, its subject and the claims of the workflow that produced it.
+/// The claims are read from the certificate recorded by the transparency log
+/// entry of the signature, which is required when any claim is checked.
+#[derive(Debug)]
+pub struct CertificateIdentityVerifier {
+    issuer: ValueMatcher,
+    subject: ValueMatcher,
+    extensions: Vec<(&'static str, String)>,
+    require_transparency_log: bool,
+    annotation_verifier: Option<AnnotationVerifier>,
+}
+
+impl CertificateIdentityVerifier {
+    pub fn new(
+        issuer: Option<&str>,
+        issuer_regex: Option<&str>,
+        subject: &Subject,
+        extensions: Option<&CertificateExtensions>,
+        require_transparency_log: bool,
+        annotations: Option<&BTreeMap<String, String>>,
+    ) -> VerifyResult<Self> {
+        let issuer = match (issuer, issuer_regex) {
+            (Some(issuer), None) => ValueMatcher::Equal(issuer.to_owned()),
+            (None, Some(regex)) => ValueMatcher::Regex(full_match_regex(regex)?),
+            _ => {
+                return Err(VerifyError::InvalidVerifyFileError(
+                    "exactly one of issuer and issuerRegex must be provided".to_owned(),
+                ));
+            }
+        };
+        let subject = ValueMatcher::from_subject(subject)?;
+        let annotation_verifier = annotations.map(|a| AnnotationVerifier {
+            annotations: a.to_owned(),
+        });
+
+        Ok(Self {
+            issuer,
+            subject,
+            extensions: extensions.map(expected_extensions).unwrap_or_default(),
+            require_transparency_log,
+            annotation_verifier,
+        })
+    }
+
+    fn verify_extensions(&self, identity: &FulcioIdentity) -> bool {
+        self.extensions.iter().all(|(oid, expected)| {
+            let current = identity.extension(oid);
+            let satisfied = current == Some(expected.as_str());
+            if !satisfied {
+                debug!(
+                    oid = %oid,
+                    expected_value = ?expected,
+                    current_value = ?current,
+                    "certificate extension not satisfied"
+                );
+            }
+            satisfied
+        })
+    }
+}
+
+impl VerificationConstraint for CertificateIdentityVerifier {
+    fn verify(&self, sl: &SignatureLayer) -> Result<bool> {
+        let Some(certificate_signature) = &sl.certificate_signature else {
+            return Ok(false);
+        };
+
+        let issuer_satisfied = certificate_signature
+            .issuer
+            .as_deref()
+            .is_some_and(|issuer| self.issuer.matches(issuer));
+        if !issuer_satisfied {
+            debug!(
+                expected_value = ?self.issuer,
+                current_value = ?certificate_signature.issuer,
+                "issuer not satisfied"
+            );
+            return Ok(false);
+        }
+
+        let certificate_subject = fulcio::subject_value(&certificate_signature.subject);
+        if !self.subject.matches(certificate_subject) {
+            debug!(
+                expected_value = ?self.subject,
+                current_value = %certificate_subject,
+                "subject not satisfied"
+            );
+            return Ok(false);
+        }
+
+        if self.require_transparency_log && sl.bundle.is_none() {
+            debug!("signature is not recorded by a trusted transparency log");
+            return Ok(false);
+        }
+
+        if !self.extensions.is_empty() {
+            let Some(identity) = fulcio::transparency_log_identity(sl) else {
+                debug!(
+                    "certificate extensions cannot be verified, the signature doesn't have a valid transparency log entry"
+                );
+                return Ok(false);
+            };
+            if !self.verify_extensions(&identity) {
+                return Ok(false);
+            }
+        }
+
+        let outcome = if let Some(av) = &self.annotation_verifier {
+            av.verify(sl)?
+        } else {
+            true
+        };
+        Ok(outcome)
+    }
+}
+
+/// Verification Constraint for Signatures produced by GitLab CI pipelines
+///
+/// This constraint looks at the signature done in keyless mode by a GitLab
+/// CI pipeline and inspects the source repository recorded by its certificate.
+/// The repository is read from the certificate recorded by the transparency
+/// log entry of the signature.
+#[derive(Debug)]
+pub struct GitLabVerifier {
+    issuer: String,
+    owner: String,
+    repo: Option<String>,
+    annotation_verifier: Option<AnnotationVerifier>,
+}
+
+const GITLAB_ISSUER: &str = "https://gitlab.com";
+
+impl GitLabVerifier {
+    pub fn new(
+        owner: &str,
+        repo: Option<&str>,
+        issuer: Option<&str>,
+        annotations: Option<&BTreeMap<String, String>>,
+    ) -> Self {
+        let annotation_verifier = annotations.map(|a| AnnotationVerifier {
+            annotations: a.to_owned(),
+        });
+
+        Self {
+            issuer: issuer
+                .unwrap_or(GITLAB_ISSUER)
+                .trim_end_matches('/')
+                .to_owned(),
+            owner: owner.trim_matches('/').to_owned(),
+            repo: repo.map(|r| r.to_owned()),
+            annotation_verifier,
+        }
+    }
+
+    fn verify_source_repository(&self, source_repository: &str) -> bool {
+        match &self.repo {
+            Some(repo) => source_repository == format!("{}/{}/{repo}", self.issuer, self.owner),
+            // any project of the group or of its subgroups
+            None => source_repository.starts_with(&format!("{}/{}/", self.issuer, self.owner)),
+        }
+    }
+}
+
+impl VerificationConstraint for GitLabVerifier {
+    fn verify(&self, sl: &SignatureLayer) -> Result<bool> {
+        let Some(certificate_signature) = &sl.certificate_signature else {
+            return Ok(false);
+        };
+
+        if certificate_signature.issuer.as_deref() != Some(self.issuer.as_str()) {
+            debug!(
+                expected_value = ?self.issuer,
+                current_value = ?certificate_signature.issuer,
+                "issuer not satisfied"
+            );
+            return Ok(false);
+        }
+
+        // the certificate subject must be the URI of the pipeline and not an email
+        if let CertificateSubject::Email(email) = &certificate_signature.subject {
+            debug!(
+                current_value = ?email,
+                "subject not satisfied, expected URI, got email instead"
+            );
+            return Ok(false);
+        }
+
+        let Some(identity) = fulcio::transparency_log_identity(sl) else {
+            debug!(
+                "source repository cannot be verified, the signature doesn't have a valid transparency log entry"
+            );
+            return Ok(false);
+        };
+        let source_repository = identity.extension(fulcio::OID_SOURCE_REPOSITORY_URI);
+        if !source_repository.is_some_and(|uri| self.verify_source_repository(uri)) {
+            debug!(
+                expected_owner = ?self.owner,
+                expected_repo = ?self.repo,
+                current_value = ?source_repository,
+                "source repository not satisfied"
+            );
+            return Ok(false);
+        }
+
+        let outcome = if let Some(av) = &self.annotation_verifier {
+            av.verify(sl)?
+        } else {
+            true
+        };
+        Ok(outcome)
+    }
+}
+
 struct GitHubRepo {
     pub owner: String,
     pub repo: String,
@@ -405,6 +749,79 @@ kvUsh4eKpd1lwkDAzfFDs7yXEExsEkPPuiQJBelDT68n7PDIWB/QEY7mrA==
         }
     }
 
+    fn der_utf8(value: &str) -> Vec<u8> {
+        let mut der = vec![0x0c, value.len() as u8];
+        der.extend_from_slice(value.as_bytes());
+        der
+    }
+
+    /// Adds a transparency log entry to the signature layer. The entry records
+    /// a certificate issued to the identity of the layer, holding the given
+    /// Fulcio extensions
+    fn with_transparency_log_entry(
+        mut sl: SignatureLayer,
+        extensions: &[(&[u64], &str)],
+    ) -> SignatureLayer {
+        use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+        let certificate_signature = sl.certificate_signature.as_ref().unwrap();
+        let mut params = rcgen::CertificateParams::default();
+        params.subject_alt_names = vec![match &certificate_signature.subject {
+            CertificateSubject::Email(email) => {
+                rcgen::SanType::Rfc822Name(email.as_str().try_into().unwrap())
+            }
+            CertificateSubject::Uri(uri) => rcgen::SanType::URI(uri.as_str().try_into().unwrap()),
+        }];
+        if let Some(issuer) = &certificate_signature.issuer {
+            params
+                .custom_extensions
+                .push(rcgen::CustomExtension::from_oid_content(
+                    &[1, 3, 6, 1, 4, 1, 57264, 1, 8],
+                    der_utf8(issuer),
+                ));
+        }
+        for (oid, value) in extensions {
+            params
+                .custom_extensions
+                .push(rcgen::CustomExtension::from_oid_content(
+                    oid,
+                    der_utf8(value),
+                ));
+        }
+        let key_pair = rcgen::KeyPair::generate().unwrap();
+        let certificate = params.self_signed(&key_pair).unwrap();
+
+        let body = serde_json::json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "data": {
+                    "hash": {
+                        "algorithm": "sha256",
+                        "value": "not relevant",
+                    }
+                },
+                "signature": {
+                    "content": sl.signature,
+                    "publicKey": {
+                        "content": STANDARD.encode(certificate.pem()),
+                    }
+                }
+            }
+        });
+        sl.bundle = serde_json::from_value(serde_json::json!({
+            "SignedEntryTimestamp": "",
+            "Payload": {
+                "body": STANDARD.encode(body.to_string()),
+                "integratedTime": 0,
+                "logIndex": 0,
+                "logID": "not relevant",
+            }
+        }))
+        .expect("Cannot build bundle");
+        sl
+    }
+
     #[test]
     fn test_public_key_and_annotation_verifier() {
         let (pub_key, sl) = build_signature_layers_pub_key();
@@ -720,4 +1137,234 @@ kvUsh4eKpd1lwkDAzfFDs7yXEExsEkPPuiQJBelDT68n7PDIWB/QEY7mrA==
         let is_verified = vc.verify(&sl).expect("Should have been successful");
         assert!(!is_verified);
     }
+
+    #[rstest]
+    #[case::full_match("https://github.com/kubewarden/.*", true)]
+    #[case::partial_match("kubewarden", false)]
+    #[case::no_match("https://github.com/kubewarden-hacker/.*", false)]
+    fn test_generic_issuer_subject_regex(#[case] regex: &str, #[case] expected: bool) {
+        let issuer = "https://token.actions.githubusercontent.com";
+        let subject_str = "https://github.com/kubewarden/policy-secure-pod-images/.github/workflows/release.yml@refs/heads/main";
+        let certificate_subject = CertificateSubject::Uri(subject_str.to_string());
+
+        let sl =
+            build_signature_layers_keyless(Some(issuer.to_string()), certificate_subject, None);
+
+        let subject = Subject::Regex(regex.to_string());
+        let vc = GenericIssuerSubjectVerifier::new(issuer, &subject, None);
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert_eq!(is_verified, expected);
+    }
+
+    #[rstest]
+    #[case::issuer_equal(Some("https://token.actions.githubusercontent.com"), None, true)]
+    #[case::issuer_regex(None, Some(r"https://token\.actions\.githubusercontent\.com"), true)]
+    #[case::issuer_regex_partial_match(None, Some("token"), false)]
+    #[case::issuer_different(Some("https://gitlab.com"), None, false)]
+    fn test_certificate_identity_verifier_issuer(
+        #[case] issuer: Option<&str>,
+        #[case] issuer_regex: Option<&str>,
+        #[case] expected: bool,
+    ) {
+        let subject_str = "https://github.com/kubewarden/policy-secure-pod-images/.github/workflows/release.yml@refs/heads/main";
+        let sl = build_signature_layers_keyless(
+            Some("https://token.actions.githubusercontent.com".to_string()),
+            CertificateSubject::Uri(subject_str.to_string()),
+            None,
+        );
+
+        let subject = Subject::Equal(subject_str.to_string());
+        let vc =
+            CertificateIdentityVerifier::new(issuer, issuer_regex, &subject, None, false, None)
+                .expect("Cannot create verification constraint");
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert_eq!(is_verified, expected);
+    }
+
+    #[rstest]
+    #[case::both(Some("https://gitlab.com"), Some("https://gitlab\\.com"))]
+    #[case::none(None, None)]
+    #[case::invalid_regex(None, Some("https://(gitlab"))]
+    fn test_certificate_identity_verifier_invalid_issuer(
+        #[case] issuer: Option<&str>,
+        #[case] issuer_regex: Option<&str>,
+    ) {
+        let subject = Subject::Equal("user@provider.com".to_string());
+        assert!(
+            CertificateIdentityVerifier::new(issuer, issuer_regex, &subject, None, false, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_certificate_identity_verifier_require_transparency_log() {
+        let issuer = "https://github.com/login/oauth";
+        let subject_str = "user@provider.com";
+        let sl = build_signature_layers_keyless(
+            Some(issuer.to_string()),
+            CertificateSubject::Email(subject_str.to_string()),
+            None,
+        );
+
+        let subject = Subject::Equal(subject_str.to_string());
+        let vc = CertificateIdentityVerifier::new(Some(issuer), None, &subject, None, true, None)
+            .expect("Cannot create verification constraint");
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert!(!is_verified);
+
+        let sl = with_transparency_log_entry(sl, &[]);
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert!(is_verified);
+    }
+
+    #[rstest]
+    #[case::matching(Some("refs/heads/main"), Some("github-hosted"), true)]
+    #[case::subset(Some("refs/heads/main"), None, true)]
+    #[case::different_ref(Some("refs/heads/feature"), None, false)]
+    #[case::missing_extension(None, Some("self-hosted"), false)]
+    fn test_certificate_identity_verifier_extensions(
+        #[case] source_repository_ref: Option<&str>,
+        #[case] runner_environment: Option<&str>,
+        #[case] expected: bool,
+    ) {
+        let issuer = "https://token.actions.githubusercontent.com";
+        let subject_str = "https://github.com/kubewarden/policy-secure-pod-images/.github/workflows/release.yml@refs/heads/main";
+        let sl = build_signature_layers_keyless(
+            Some(issuer.to_string()),
+            CertificateSubject::Uri(subject_str.to_string()),
+            None,
+        );
+        let sl = with_transparency_log_entry(
+            sl,
+            &[
+                (&[1, 3, 6, 1, 4, 1, 57264, 1, 14], "refs/heads/main"),
+                (&[1, 3, 6, 1, 4, 1, 57264, 1, 11], "github-hosted"),
+            ],
+        );
+
+        let subject = Subject::UrlPrefix(
+            url::Url::parse("https://github.com/kubewarden").expect("Cannot build url prefix"),
+        );
+        let extensions = CertificateExtensions {
+            source_repository_ref: source_repository_ref.map(|r| r.to_string()),
+            runner_environment: runner_environment.map(|r| r.to_string()),
+            ..Default::default()
+        };
+        let vc = CertificateIdentityVerifier::new(
+            Some(issuer),
+            None,
+            &subject,
+            Some(&extensions),
+            false,
+            None,
+        )
+        .expect("Cannot create verification constraint");
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert_eq!(is_verified, expected);
+    }
+
+    #[test]
+    fn test_certificate_identity_verifier_extensions_without_transparency_log() {
+        let issuer = "https://token.actions.githubusercontent.com";
+        let subject_str = "https://github.com/kubewarden/policy-secure-pod-images/.github/workflows/release.yml@refs/heads/main";
+        let sl = build_signature_layers_keyless(
+            Some(issuer.to_string()),
+            CertificateSubject::Uri(subject_str.to_string()),
+            None,
+        );
+
+        let subject = Subject::Equal(subject_str.to_string());
+        let extensions = CertificateExtensions {
+            source_repository_ref: Some("refs/heads/main".to_string()),
+            ..Default::default()
+        };
+        let vc = CertificateIdentityVerifier::new(
+            Some(issuer),
+            None,
+            &subject,
+            Some(&extensions),
+            false,
+            None,
+        )
+        .expect("Cannot create verification constraint");
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert!(!is_verified);
+    }
+
+    #[rstest]
+    #[case::owner_and_repo("kubewarden", Some("pod-privileged"), true)]
+    #[case::owner("kubewarden", None, true)]
+    #[case::parent_group("kubewarden/policies", None, true)]
+    #[case::different_repo("kubewarden", Some("psp-one"), false)]
+    #[case::different_owner("kubewarden-hacker", None, false)]
+    fn test_gitlab_verifier(
+        #[case] owner: &str,
+        #[case] repo: Option<&str>,
+        #[case] expected: bool,
+    ) {
+        let source_repository = if owner == "kubewarden/policies" {
+            "https://gitlab.com/kubewarden/policies/pod-privileged"
+        } else {
+            "https://gitlab.com/kubewarden/pod-privileged"
+        };
+        let subject_str = format!("{source_repository}//.gitlab-ci.yml@refs/heads/main");
+        let sl = build_signature_layers_keyless(
+            Some("https://gitlab.com".to_string()),
+            CertificateSubject::Uri(subject_str),
+            None,
+        );
+        let sl = with_transparency_log_entry(
+            sl,
+            &[(&[1, 3, 6, 1, 4, 1, 57264, 1, 12], source_repository)],
+        );
+
+        let vc = GitLabVerifier::new(owner, repo, None, None);
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert_eq!(is_verified, expected);
+    }
+
+    #[test]
+    fn test_gitlab_verifier_reject_because_no_transparency_log() {
+        let sl = build_signature_layers_keyless(
+            Some("https://gitlab.com".to_string()),
+            CertificateSubject::Uri(
+                "https://gitlab.com/kubewarden/pod-privileged//.gitlab-ci.yml@refs/heads/main"
+                    .to_string(),
+            ),
+            None,
+        );
+
+        let vc = GitLabVerifier::new("kubewarden", None, None, None);
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert!(!is_verified);
+    }
+
+    #[test]
+    fn test_gitlab_verifier_self_managed_instance() {
+        let source_repository = "https://gitlab.example.com/kubewarden/pod-privileged";
+        let sl = build_signature_layers_keyless(
+            Some("https://gitlab.example.com".to_string()),
+            CertificateSubject::Uri(format!(
+                "{source_repository}//.gitlab-ci.yml@refs/heads/main"
+            )),
+            None,
+        );
+        let sl = with_transparency_log_entry(
+            sl,
+            &[(&[1, 3, 6, 1, 4, 1, 57264, 1, 12], source_repository)],
+        );
+
+        let vc = GitLabVerifier::new("kubewarden", None, None, None);
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert!(!is_verified);
+
+        let vc = GitLabVerifier::new(
+            "kubewarden",
+            None,
+            Some("https://gitlab.example.com/"),
+            None,
+        );
+        let is_verified = vc.verify(&sl).expect("Should have been successful");
+        assert!(is_verified);
+    }
 }
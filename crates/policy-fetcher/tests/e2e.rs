@@ -282,10 +282,13 @@ mod e2e {
         create_docker_config_file(&auth_dir, port);
 
         let client_arc = Arc::new(Mutex::new(cosign_client));
-        let (_, trusted_layers) =
-            fetch_sigstore_remote_data(&client_arc, &format!("registry://{}", push_image.whole()))
-                .await
-                .expect("failed to fetch sigstore remote data");
+        let (_, trusted_layers) = fetch_sigstore_remote_data(
+            &client_arc,
+            &format!("registry://{}", push_image.whole()),
+            None,
+        )
+        .await
+        .expect("failed to fetch sigstore remote data");
 
         cosign::verify_constraints(&trusted_layers, [signature_verifier].iter())
             .expect("failed to verify constraints");
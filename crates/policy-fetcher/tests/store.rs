@@ -1,43 +1,47 @@
-use std::{path::Path, slice};
-
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use policy_fetcher::oci_layout::Artifact;
 use policy_fetcher::policy::Policy;
-use policy_fetcher::store::{Store, path};
+use policy_fetcher::store::{Store, errors::StoreError, path};
 use tempfile::tempdir;
 
+const UNREFERENCED_DIGEST: &str =
+    "sha256:0000000000000000000000000000000000000000000000000000000000000000";
+
+fn simple_wasm() -> Vec<u8> {
+    std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_data/simple.wasm"))
+        .unwrap()
+}
+
+fn other_wasm() -> Vec<u8> {
+    let mut module = simple_wasm();
+    module.extend_from_slice(b"\x00\x04name");
+    module
+}
+
 #[test]
 fn test_list() {
     let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
 
-    let mut expected_policies = vec![
-        Policy {
-            uri: "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm".to_owned(),
-            local_path: store_root.path().join(path::encode_path(
-                "https/internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
-            )),
-        },
-        Policy {
-            uri: "registry://ghcr.io/some/path/to/wasm-module.wasm:1.0.0".to_owned(),
-            local_path: store_root.path().join(path::encode_path(
-                "registry/ghcr.io/some/path/to/wasm-module.wasm:1.0.0",
-            )),
-        },
-        Policy {
-            uri: "registry://internal.host.company:5000/some/path/to/wasm-module.wasm:1.0.0"
-                .to_owned(),
-            local_path: store_root.path().join(path::encode_path(
-                "registry/internal.host.company:5000/some/path/to/wasm-module.wasm:1.0.0",
-            )),
-        },
+    let uris = [
+        "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
+        "registry://ghcr.io/some/path/to/wasm-module.wasm:1.0.0",
+        "registry://internal.host.company:5000/some/path/to/wasm-module.wasm:1.0.0",
     ];
+    let mut expected_policies: Vec<Policy> = uris
+        .iter()
+        .map(|uri| store.add_policy(uri, &simple_wasm()).unwrap())
+        .collect();
 
-    setup_store(&expected_policies).unwrap();
-
-    let store = Store::new(store_root.path());
-    let mut list = store.list().expect("failed to list policies");
+    let list = store.list().expect("failed to list policies");
 
     expected_policies.sort_by_key(|p| p.uri.clone());
-    list.sort_by_key(|p| p.uri.clone());
-
     assert_eq!(expected_policies, list);
 }
 
@@ -51,21 +55,65 @@ fn test_list_non_existing_store_root() {
 }
 
 #[test]
-fn test_get_policy_by_uri() {
+fn test_same_module_is_stored_once() {
     let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
 
-    let expected_policy = Policy {
-        uri: "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm".to_owned(),
-        local_path: store_root.path().join(path::encode_path(
-            "https/internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
-        )),
-    };
+    let v1 = store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v1", &simple_wasm())
+        .unwrap();
+    let latest = store
+        .add_policy("registry://ghcr.io/kubewarden/policy", &simple_wasm())
+        .unwrap();
+
+    assert_eq!(v1.local_path, latest.local_path);
+    assert_eq!(latest.uri, "registry://ghcr.io/kubewarden/policy:latest");
+    assert_eq!(
+        std::fs::read_dir(store_root.path().join("blobs/sha256"))
+            .unwrap()
+            .count(),
+        1
+    );
+}
 
-    setup_store(slice::from_ref(&expected_policy)).unwrap();
+#[test]
+fn test_add_registry_policy_records_manifest_digest() {
+    let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
+    let manifest_digest = "sha256:61ef63621fa5be8e422881d96d05edfef810992fbf9468e35d1fa5ae815bd97c";
+
+    store
+        .add_registry_policy(
+            "registry://ghcr.io/kubewarden/policy",
+            &simple_wasm(),
+            manifest_digest,
+        )
+        .unwrap();
+    store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v1", &simple_wasm())
+        .unwrap();
+
+    let entry = store
+        .index_entry("registry://ghcr.io/kubewarden/policy:latest")
+        .unwrap()
+        .expect("entry not found");
+    assert_eq!(entry.manifest_digest.as_deref(), Some(manifest_digest));
+    let entry = store
+        .index_entry("registry://ghcr.io/kubewarden/policy:v1")
+        .unwrap()
+        .expect("entry not found");
+    assert!(entry.manifest_digest.is_none());
+}
 
+#[test]
+fn test_get_policy_by_uri() {
+    let store_root = tempdir().unwrap();
     let store = Store::new(store_root.path());
+    let uri = "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm";
+    let expected_policy = store.add_policy(uri, &simple_wasm()).unwrap();
+
     let policy = store
-        .get_policy_by_uri(&expected_policy.uri)
+        .get_policy_by_uri(uri)
         .expect("failed to get policy by uri");
 
     assert_eq!(Some(expected_policy), policy);
@@ -84,17 +132,14 @@ fn test_get_policy_by_uri_not_found() {
 #[test]
 fn test_get_policy_by_uri_unknown_scheme() {
     let store_root = tempdir().unwrap();
-
-    let policies = vec![Policy {
-        uri: "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm".to_owned(),
-        local_path: store_root.path().join(path::encode_path(
-            "https/internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
-        )),
-    }];
-
-    setup_store(&policies).unwrap();
-
     let store = Store::new(store_root.path());
+    store
+        .add_policy(
+            "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
+            &simple_wasm(),
+        )
+        .unwrap();
+
     let result =
         store.get_policy_by_uri("ftp://internal.host.company/some/path/to/1.0.0/wasm-module.wasm");
 
@@ -102,19 +147,29 @@ fn test_get_policy_by_uri_unknown_scheme() {
 }
 
 #[test]
-fn test_get_policy_by_sha_prefix() {
+fn test_get_policy_by_uri_corrupted_module() {
     let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
+    let uri = "registry://ghcr.io/kubewarden/policy:v1";
+    let policy = store.add_policy(uri, &simple_wasm()).unwrap();
+    std::fs::write(&policy.local_path, b"tampered").unwrap();
 
-    let expected_policy = Policy {
-        uri: "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm".to_owned(),
-        local_path: store_root.path().join(path::encode_path(
-            "https/internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
-        )),
-    };
+    let result = store.get_policy_by_uri(uri);
 
-    setup_store(slice::from_ref(&expected_policy)).unwrap();
+    assert!(matches!(result, Err(StoreError::IntegrityError { .. })));
+}
 
+#[test]
+fn test_get_policy_by_sha_prefix() {
+    let store_root = tempdir().unwrap();
     let store = Store::new(store_root.path());
+    let expected_policy = store
+        .add_policy(
+            "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
+            &simple_wasm(),
+        )
+        .unwrap();
+
     let policy = store
         .get_policy_by_sha_prefix("93a")
         .expect("failed to get policy by sha prefix");
@@ -133,41 +188,209 @@ fn test_get_policy_by_sha_prefix_not_found() {
 }
 
 #[test]
-fn test_get_policy_by_sha_prefix_duplicate() {
+fn test_get_policy_by_sha_prefix_same_module() {
     let store_root = tempdir().unwrap();
     let store = Store::new(store_root.path());
+    store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v1", &simple_wasm())
+        .unwrap();
+    store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v2", &simple_wasm())
+        .unwrap();
 
-    let policies = vec![
-        Policy {
-            uri: "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm".to_owned(),
-            local_path: store_root.path().join(path::encode_path(
-                "https/internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
-            )),
-        },
-        Policy {
-            uri: "registry://ghcr.io/some/path/to/wasm-module.wasm:1.0.0".to_owned(),
-            local_path: store_root.path().join(path::encode_path(
-                "registry/internal.host.company/some/path/to/1.0.0/wasm-module.wasm:1.0.0",
-            )),
-        },
-    ];
+    let policy = store
+        .get_policy_by_sha_prefix("93a")
+        .expect("failed to get policy by sha prefix")
+        .expect("policy not found");
 
-    setup_store(&policies).unwrap();
+    assert_eq!(policy.uri, "registry://ghcr.io/kubewarden/policy:v1");
+}
 
-    let result = store.get_policy_by_sha_prefix("93a");
+#[test]
+fn test_get_policy_by_sha_prefix_duplicate() {
+    let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
+    store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v1", &simple_wasm())
+        .unwrap();
+    store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v2", &other_wasm())
+        .unwrap();
+
+    // the empty prefix matches all the modules
+    let result = store.get_policy_by_sha_prefix("");
 
     assert!(result.is_err());
 }
 
-fn setup_store(policies: &[Policy]) -> std::result::Result<(), std::io::Error> {
-    for policy in policies {
-        std::fs::create_dir_all(policy.local_path.parent().unwrap())?;
+#[test]
+fn test_remove_policy_by_uri() {
+    let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
+    let v1 = store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v1", &simple_wasm())
+        .unwrap();
+    store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v2", &simple_wasm())
+        .unwrap();
+
+    assert!(
+        store
+            .remove_policy_by_uri("registry://ghcr.io/kubewarden/policy:v1")
+            .unwrap()
+    );
+    // the module is still referenced by v2
+    assert!(v1.local_path.exists());
+
+    assert!(
+        store
+            .remove_policy_by_uri("registry://ghcr.io/kubewarden/policy:v2")
+            .unwrap()
+    );
+    assert!(!v1.local_path.exists());
+    assert!(
+        !store
+            .remove_policy_by_uri("registry://ghcr.io/kubewarden/policy:v2")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_gc() {
+    let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
+    let kept = store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v1", &simple_wasm())
+        .unwrap();
+    let corrupted = store
+        .add_policy("registry://ghcr.io/kubewarden/policy:v2", &other_wasm())
+        .unwrap();
+    std::fs::write(&corrupted.local_path, b"tampered").unwrap();
+    let unreferenced = store.blob_path(UNREFERENCED_DIGEST);
+    std::fs::write(&unreferenced, b"unreferenced").unwrap();
+    let staging_dir = store.staging_dir().unwrap();
+    std::fs::write(staging_dir.join("policy.wasm"), simple_wasm()).unwrap();
+    let stale_staging_dir = store.staging_dir().unwrap();
+    std::fs::write(stale_staging_dir.join("policy.wasm"), simple_wasm()).unwrap();
+    // leftover of an operation interrupted two days ago
+    std::fs::File::open(&stale_staging_dir)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60))
+        .unwrap();
+
+    let report = store.gc().expect("gc failed");
+
+    assert_eq!(
+        report.removed_entries,
+        vec!["registry://ghcr.io/kubewarden/policy:v2".to_string()]
+    );
+    assert_eq!(report.removed_blobs.len(), 2);
+    assert!(report.reclaimed_bytes > 0);
+    assert!(kept.local_path.exists());
+    assert!(!corrupted.local_path.exists());
+    assert!(!unreferenced.exists());
+    // the staging directory could be in use by another process
+    assert!(staging_dir.join("policy.wasm").exists());
+    assert!(!stale_staging_dir.exists());
+    assert_eq!(store.list().unwrap(), vec![kept]);
+}
+
+#[test]
+fn test_registry_artifacts() {
+    let store_root = tempdir().unwrap();
+    let store = Store::new(store_root.path());
+    let ref_name = "ghcr.io/kubewarden/policy:sha256-93a0.sig";
+    let sha256 = |data: &[u8]| format!("sha256:{}", hex::encode(Sha256::digest(data)));
+    let config = b"{}".to_vec();
+    let payload = br#"{"critical":{}}"#.to_vec();
+    let manifest = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "mediaType": "application/vnd.oci.image.config.v1+json",
+            "digest": sha256(&config),
+            "size": config.len(),
+        },
+        "layers": [{
+            "mediaType": "application/vnd.dev.cosign.simplesigning.v1+json",
+            "digest": sha256(&payload),
+            "size": payload.len(),
+            "annotations": {"dev.cosignproject.cosign/signature": "c2lnbmF0dXJl"},
+        }],
+    });
+    let artifact = Artifact {
+        media_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
+        manifest: serde_json::to_vec(&manifest).unwrap(),
+        blobs: BTreeMap::from([(sha256(&config), config), (sha256(&payload), payload)]),
+    };
+
+    assert_eq!(store.registry_artifact(ref_name).unwrap(), None);
+
+    store.add_registry_artifact(ref_name, &artifact).unwrap();
+
+    assert_eq!(store.registry_artifact(ref_name).unwrap(), Some(artifact));
+    assert_eq!(
+        store
+            .registry_artifact("ghcr.io/kubewarden/policy:sha256-93a0.att")
+            .unwrap(),
+        None
+    );
+    // the artifacts are not policies
+    assert!(store.list().unwrap().is_empty());
+    assert!(store.gc().unwrap().removed_blobs.is_empty());
+    assert!(store.registry_artifact(ref_name).unwrap().is_some());
+}
 
-        std::fs::copy(
-            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_data/simple.wasm"),
-            &policy.local_path,
-        )?;
+#[test]
+fn test_concurrent_updates_of_the_index() {
+    let store_root = tempdir().unwrap();
+
+    std::thread::scope(|scope| {
+        for i in 0..8 {
+            let root = store_root.path();
+            scope.spawn(move || {
+                // each thread uses its own store, like different processes do
+                Store::new(root)
+                    .add_policy(
+                        &format!("registry://ghcr.io/kubewarden/policy:v{i}"),
+                        &simple_wasm(),
+                    )
+                    .unwrap();
+            });
+        }
+    });
+
+    assert_eq!(Store::new(store_root.path()).list().unwrap().len(), 8);
+}
+
+#[test]
+fn test_migrate_legacy_layout() {
+    let store_root = tempdir().unwrap();
+    let legacy_paths = [
+        "https/internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
+        "registry/ghcr.io/some/path/to/wasm-module.wasm:1.0.0",
+    ];
+    for legacy_path in legacy_paths {
+        let legacy_path: PathBuf = store_root.path().join(path::encode_path(legacy_path));
+        std::fs::create_dir_all(legacy_path.parent().unwrap()).unwrap();
+        std::fs::write(&legacy_path, simple_wasm()).unwrap();
     }
+    let store = Store::new(store_root.path());
 
-    Ok(())
+    let uris: Vec<String> = store
+        .list()
+        .expect("failed to list policies")
+        .into_iter()
+        .map(|policy| policy.uri)
+        .collect();
+
+    assert_eq!(
+        uris,
+        vec![
+            "https://internal.host.company/some/path/to/1.0.0/wasm-module.wasm",
+            "registry://ghcr.io/some/path/to/wasm-module.wasm:1.0.0",
+        ]
+    );
+    assert!(!store_root.path().join("https").exists());
+    assert!(!store_root.path().join("registry").exists());
 }
@@ -117,6 +117,18 @@ pub(crate) fn build_cli() -> Command {
             .env("KUBEWARDEN_POLICIES_DOWNLOAD_DIR")
             .help("Download path for the policies"),
 
+        Arg::new("policies-fetch-mode")
+            .long("policies-fetch-mode")
+            .value_name("POLICIES_FETCH_MODE")
+            .default_value("IfDigestChanged")
+            .env("KUBEWARDEN_POLICIES_FETCH_MODE")
+            .value_parser([
+                PossibleValue::new("IfNotPresent"),
+                PossibleValue::new("Always"),
+                PossibleValue::new("IfDigestChanged"),
+            ])
+            .help("When the policies found inside of the download path are pulled again"),
+
         Arg::new("sigstore-cache-dir")
             .long("sigstore-cache-dir")
             .value_name("SIGSTORE_CACHE_DIR")
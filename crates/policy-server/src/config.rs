@@ -12,11 +12,13 @@ use lazy_static::lazy_static;
 use opentelemetry_otlp::tonic_types::transport::{Certificate, ClientTlsConfig, Identity};
 use policy_evaluator::{
     admission_response_handler::policy_mode::PolicyMode,
+    http_endpoint::HttpEndpoint,
     policy_evaluator::PolicySettings,
     policy_fetcher::{
+        FetchMode,
         proxy::ProxyConfig,
         sources::{Sources, read_sources_file},
-        verify::config::{LatestVerificationConfig, VerificationConfigV1, read_verification_file},
+        verify::config::{LatestVerificationConfig, read_verification_file},
     },
     policy_metadata::ContextAwareResource,
 };
@@ -36,6 +38,7 @@ pub struct Config {
     pub sources: Option<Sources>,
     pub policies: HashMap<String, PolicyOrPolicyGroup>,
     pub policies_download_dir: PathBuf,
+    pub policies_fetch_mode: FetchMode,
     pub ignore_kubernetes_connection_failure: bool,
     pub always_accept_admission_reviews_on_namespace: Option<String>,
     // This is the global timeout for each policy evaluation.
@@ -45,7 +48,7 @@ pub struct Config {
     pub metrics_enabled: bool,
     pub sigstore_cache_dir: PathBuf,
     pub sigstore_trust_config_path: Option<PathBuf>,
-    pub verification_config: Option<VerificationConfigV1>,
+    pub verification_config: Option<LatestVerificationConfig>,
     pub log_level: String,
     pub log_fmt: String,
     pub log_no_color: bool,
@@ -74,6 +77,10 @@ impl Config {
             .get_one::<String>("policies-download-dir")
             .map(PathBuf::from)
             .expect("This should not happen, there's a default value for policies-download-dir");
+        let policies_fetch_mode = matches
+            .get_one::<String>("policies-fetch-mode")
+            .expect("This should not happen, there's a default value for policies-fetch-mode")
+            .parse::<FetchMode>()?;
         let policy_evaluation_limit_seconds = if *matches
             .get_one::<bool>("disable-timeout-protection")
             .expect("clap should have set a default value")
@@ -156,6 +163,7 @@ impl Config {
             sources,
             policies,
             policies_download_dir,
+            policies_fetch_mode,
             ignore_kubernetes_connection_failure,
             tls_config,
             always_accept_admission_reviews_on_namespace,
@@ -316,7 +324,8 @@ pub enum PolicyOrPolicyGroupSettings {
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct PolicyGroupMember {
-    /// The URL where the policy is located
+    /// The URL where the policy is located. Policies applying Gatekeeper
+    /// mutators can use `builtin://gatekeeper-mutation` instead
     pub module: String,
     /// The settings for the policy
     pub settings: Option<PolicySettings>,
@@ -328,6 +337,10 @@ pub struct PolicyGroupMember {
     /// List of host capabilities granted to this policy
     #[serde(default)]
     pub host_capabilities: Vec<String>,
+    /// List of HTTP endpoints the policy can reach via the `net/v1/http_request`
+    /// host capability
+    #[serde(default)]
+    pub allowed_http_endpoints: BTreeSet<HttpEndpoint>,
 }
 
 impl PolicyGroupMember {
@@ -345,7 +358,8 @@ pub enum PolicyOrPolicyGroup {
     /// An individual policy
     #[serde(rename_all = "camelCase")]
     Policy {
-        /// The URL where the policy is located
+        /// The URL where the policy is located. Policies applying Gatekeeper
+        /// mutators can use `builtin://gatekeeper-mutation` instead
         module: String,
         #[serde(default)]
         /// The mode of the policy
@@ -364,6 +378,10 @@ pub enum PolicyOrPolicyGroup {
         /// The list of host capabilities granted to this policy
         #[serde(default)]
         host_capabilities: Vec<String>,
+        /// The list of HTTP endpoints the policy can reach via the
+        /// `net/v1/http_request` host capability
+        #[serde(default)]
+        allowed_http_endpoints: BTreeSet<HttpEndpoint>,
     },
     /// A group of policies that are evaluated together using a given expression
     #[serde(rename_all = "camelCase")]
@@ -479,6 +497,11 @@ example:
           kind: Pod
     hostCapabilities:
       - kubernetes/*
+      - net/v1/http_request
+    allowedHttpEndpoints:
+      - urlPrefix: https://exceptions.svc.cluster.local/api/
+      - urlPrefix: https://quota.svc.cluster.local
+        methods: ["GET", "POST"]
 group_policy:
     policyMode: monitor
     expression: "true"
@@ -518,7 +541,22 @@ group_policy:
                     ]),
                     message: Some("my custom error message".to_owned()),
                     timeout_eval_seconds: None,
-                    host_capabilities: vec!["kubernetes/*".to_owned()],
+                    host_capabilities: vec![
+                        "kubernetes/*".to_owned(),
+                        "net/v1/http_request".to_owned(),
+                    ],
+                    allowed_http_endpoints: BTreeSet::from([
+                        HttpEndpoint {
+                            url_prefix: "https://exceptions.svc.cluster.local/api/"
+                                .parse()
+                                .unwrap(),
+                            methods: BTreeSet::from(["GET".to_owned()]),
+                        },
+                        HttpEndpoint {
+                            url_prefix: "https://quota.svc.cluster.local".parse().unwrap(),
+                            methods: BTreeSet::from(["GET".to_owned(), "POST".to_owned()]),
+                        },
+                    ]),
                 },
             ),
             (
@@ -536,6 +574,7 @@ group_policy:
                                 context_aware_resources: BTreeSet::new(),
                                 timeout_eval_seconds: None,
                                 host_capabilities: vec![],
+                                allowed_http_endpoints: BTreeSet::new(),
                             },
                         ),
                         (
@@ -546,6 +585,7 @@ group_policy:
                                 context_aware_resources: BTreeSet::new(),
                                 timeout_eval_seconds: None,
                                 host_capabilities: vec![],
+                                allowed_http_endpoints: BTreeSet::new(),
                             },
                         ),
                     ]),
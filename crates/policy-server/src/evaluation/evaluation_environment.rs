@@ -13,6 +13,8 @@ use policy_evaluator::{
     callback_requests::CallbackRequest,
     evaluation_context::EvaluationContext,
     host_capabilities::HostCapabilities,
+    host_capability_provider::HostCapabilityProviders,
+    http_endpoint::HttpEndpoint,
     kubewarden_policy_sdk::settings::SettingsValidationResponse,
     policy_evaluator::{PolicyEvaluator, PolicyEvaluatorPre, PolicyExecutionMode, ValidateRequest},
     policy_evaluator_builder::PolicyEvaluatorBuilder,
@@ -78,6 +80,10 @@ pub(crate) struct EvaluationEnvironment {
     /// as value.
     policy_id_to_host_capabilities: HashMap<PolicyID, HostCapabilities>,
 
+    /// A map with the ID of the policy as key, and the list of HTTP endpoints the
+    /// policy is allowed to reach as value.
+    policy_id_to_http_endpoints: HashMap<PolicyID, BTreeSet<HttpEndpoint>>,
+
     /// Map a `policy_id` to the module's digest.
     /// This allows us to deduplicate the Wasm modules defined by the user.
     policy_id_to_module_digest: HashMap<PolicyID, ModuleDigest>,
@@ -112,6 +118,7 @@ pub(crate) struct EvaluationEnvironmentBuilder<'engine, 'precompiled_policies> {
     continue_on_errors: bool,
     global_policy_evaluation_limit_seconds: Option<u64>,
     always_accept_admission_reviews_on_namespace: Option<String>,
+    host_capability_providers: HostCapabilityProviders,
 }
 
 impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'precompiled_policies> {
@@ -128,6 +135,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
             continue_on_errors: false,
             global_policy_evaluation_limit_seconds: None,
             always_accept_admission_reviews_on_namespace: None,
+            host_capability_providers: HostCapabilityProviders::default(),
         }
     }
 
@@ -152,6 +160,16 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
         self
     }
 
+    /// Set the host capability providers registered on the `CallbackHandler`, their
+    /// capabilities can then be granted to the policies
+    pub fn with_host_capability_providers(
+        mut self,
+        host_capability_providers: HostCapabilityProviders,
+    ) -> Self {
+        self.host_capability_providers = host_capability_providers;
+        self
+    }
+
     // Because of automock, we have to provide a tailored build method between test and production
     // code
     #[cfg(test)]
@@ -214,6 +232,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                     context_aware_resources,
                     timeout_eval_seconds,
                     host_capabilities,
+                    allowed_http_endpoints,
                     ..
                 } => {
                     let policy_evaluation_settings = PolicyEvaluationSettings {
@@ -227,12 +246,15 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                     let epoch_deadline =
                         timeout_eval_seconds.or(self.global_policy_evaluation_limit_seconds);
 
-                    let host_capabilities =
-                        HostCapabilities::new(host_capabilities).map_err(|e| {
-                            EvaluationError::BootstrapFailure(format!(
-                                "invalid hostCapabilities pattern for policy {id}: {e}"
-                            ))
-                        })?;
+                    let host_capabilities = HostCapabilities::new_with_providers(
+                        host_capabilities,
+                        &self.host_capability_providers,
+                    )
+                    .map_err(|e| {
+                        EvaluationError::BootstrapFailure(format!(
+                            "invalid hostCapabilities pattern for policy {id}: {e}"
+                        ))
+                    })?;
 
                     let eval_ctx = EvaluationContext {
                         policy_id: id.to_string(),
@@ -240,6 +262,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                         ctx_aware_resources_allow_list: context_aware_resources.to_owned(),
                         epoch_deadline,
                         host_capabilities,
+                        http_endpoints_allow_list: allowed_http_endpoints.to_owned(),
                     };
 
                     if let Err(e) = self.bootstrap_policy(
@@ -304,8 +327,9 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                             .timeout_eval_seconds
                             .or(self.global_policy_evaluation_limit_seconds);
 
-                        let host_capabilities = HostCapabilities::new(
+                        let host_capabilities = HostCapabilities::new_with_providers(
                             policy.host_capabilities.clone(),
+                            &self.host_capability_providers,
                         )
                         .map_err(|e| {
                             EvaluationError::BootstrapFailure(format!(
@@ -321,6 +345,7 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                                 .to_owned(),
                             epoch_deadline,
                             host_capabilities,
+                            http_endpoints_allow_list: policy.allowed_http_endpoints.to_owned(),
                         };
 
                         if let Err(e) = self.bootstrap_policy(
@@ -419,13 +444,24 @@ impl EvaluationEnvironment {
             .module_digest_to_policy_evaluator_pre
             .contains_key(module_digest)
         {
-            debug!(?policy_id, "create wasmtime::Module");
-            let module = create_wasmtime_module(policy_id, engine, precompiled_policy)?;
+            // the Gatekeeper mutators are applied natively, the module is not used
+            let module = match precompiled_policy.execution_mode {
+                PolicyExecutionMode::GatekeeperMutation => None,
+                _ => {
+                    debug!(?policy_id, "create wasmtime::Module");
+                    Some(create_wasmtime_module(
+                        policy_id,
+                        engine,
+                        precompiled_policy,
+                    )?)
+                }
+            };
             debug!(?policy_id, "create PolicyEvaluatorPre");
             let pol_eval_pre = create_policy_evaluator_pre(
                 engine,
-                &module,
+                module.as_ref(),
                 precompiled_policy.execution_mode,
+                precompiled_policy.kubernetes_inventory,
                 eval_ctx.epoch_deadline,
             )?;
 
@@ -447,6 +483,9 @@ impl EvaluationEnvironment {
         self.policy_id_to_host_capabilities
             .insert(policy_id.to_owned(), eval_ctx.host_capabilities);
 
+        self.policy_id_to_http_endpoints
+            .insert(policy_id.to_owned(), eval_ctx.http_endpoints_allow_list);
+
         Ok(())
     }
 
@@ -574,12 +613,19 @@ impl EvaluationEnvironment {
             .cloned()
             .unwrap_or(HostCapabilities::DenyAll);
 
+        let http_endpoints_allow_list = self
+            .policy_id_to_http_endpoints
+            .get(policy_id)
+            .cloned()
+            .unwrap_or_default();
+
         let eval_ctx = EvaluationContext {
             policy_id: policy_id.to_string(),
             callback_channel: self.callback_handler_tx.clone(),
             ctx_aware_resources_allow_list: ctx_aware_resources_allow_list.clone(),
             epoch_deadline,
             host_capabilities,
+            http_endpoints_allow_list,
         };
 
         policy_evaluator_pre.rehydrate(&eval_ctx).map_err(|e| {
@@ -680,6 +726,12 @@ impl EvaluationEnvironment {
                 .cloned()
                 .unwrap_or(HostCapabilities::DenyAll);
 
+            let http_endpoints_allow_list = self
+                .policy_id_to_http_endpoints
+                .get(&policy_id)
+                .cloned()
+                .unwrap_or_default();
+
             let policy_settings = self.get_policy_settings(&policy_id)?;
             let settings = match policy_settings.settings {
                 PolicyOrPolicyGroupSettings::Policy(settings) => settings,
@@ -695,6 +747,7 @@ impl EvaluationEnvironment {
                 ctx_aware_resources_allow_list: ctx_aware_resources_allow_list.clone(),
                 epoch_deadline,
                 host_capabilities,
+                http_endpoints_allow_list,
             };
 
             evaluator.add_policy_member(
@@ -728,14 +781,20 @@ fn create_wasmtime_module(
 /// Internal function, takes care of creating the `PolicyEvaluator` instance for the given policy
 fn create_policy_evaluator_pre(
     engine: &wasmtime::Engine,
-    module: &wasmtime::Module,
+    module: Option<&wasmtime::Module>,
     mode: PolicyExecutionMode,
+    kubernetes_inventory: bool,
     policy_evaluation_limit_seconds: Option<u64>,
 ) -> Result<PolicyEvaluatorPre> {
     let mut policy_evaluator_builder = PolicyEvaluatorBuilder::new()
         .engine(engine.to_owned())
-        .policy_module(module.to_owned())
         .execution_mode(mode);
+    if let Some(module) = module {
+        policy_evaluator_builder = policy_evaluator_builder.policy_module(module.to_owned());
+    }
+    if !kubernetes_inventory {
+        policy_evaluator_builder = policy_evaluator_builder.skip_kubernetes_inventory();
+    }
 
     if let Some(limit) = policy_evaluation_limit_seconds {
         policy_evaluator_builder =
@@ -751,9 +810,14 @@ fn create_policy_evaluator_pre(
 mod tests {
     use std::collections::BTreeSet;
 
-    use policy_evaluator::{admission_response, policy_evaluator::ValidateRequest};
+    use futures::future::BoxFuture;
+    use policy_evaluator::{
+        admission_response, callback_handler::CallbackHandlerBuilder, gatekeeper_mutation,
+        host_capability_provider::HostCapabilityProvider, policy_evaluator::ValidateRequest,
+    };
     use rstest::*;
     use sha2::{Digest, Sha256};
+    use tokio::sync::oneshot;
 
     use super::*;
     use crate::config::{PolicyGroupMember, PolicyOrPolicyGroup};
@@ -775,6 +839,7 @@ mod tests {
         PrecompiledPolicy {
             precompiled_module: module.serialize().unwrap(),
             execution_mode: policy_evaluator::policy_evaluator::PolicyExecutionMode::OpaGatekeeper,
+            kubernetes_inventory: true,
             digest: hex::encode(digest),
         }
     }
@@ -825,6 +890,7 @@ mod tests {
                     message: None,
                     timeout_eval_seconds: None,
                     host_capabilities: vec![],
+                    allowed_http_endpoints: BTreeSet::new(),
                 },
             );
             precompiled_policies.insert(policy_url, Ok(precompiled_policy.clone()));
@@ -842,6 +908,7 @@ mod tests {
                 message: None,
                 timeout_eval_seconds: Some(5),
                 host_capabilities: vec![],
+                allowed_http_endpoints: BTreeSet::new(),
             },
         );
 
@@ -858,6 +925,7 @@ mod tests {
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
                         host_capabilities: vec![],
+                        allowed_http_endpoints: BTreeSet::new(),
                     },
                 )]
                 .into_iter()
@@ -887,6 +955,7 @@ mod tests {
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
                         host_capabilities: vec![],
+                        allowed_http_endpoints: BTreeSet::new(),
                     },
                 )]
                 .into_iter()
@@ -926,6 +995,7 @@ mod tests {
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
                         host_capabilities: vec![],
+                        allowed_http_endpoints: BTreeSet::new(),
                     },
                 )]
                 .into_iter()
@@ -947,6 +1017,7 @@ mod tests {
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
                             host_capabilities: vec![],
+                            allowed_http_endpoints: BTreeSet::new(),
                         },
                     ),
                     (
@@ -957,6 +1028,7 @@ mod tests {
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
                             host_capabilities: vec![],
+                            allowed_http_endpoints: BTreeSet::new(),
                         },
                     ),
                     (
@@ -967,6 +1039,7 @@ mod tests {
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
                             host_capabilities: vec![],
+                            allowed_http_endpoints: BTreeSet::new(),
                         },
                     ),
                 ]
@@ -991,6 +1064,7 @@ mod tests {
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
                             host_capabilities: vec![],
+                            allowed_http_endpoints: BTreeSet::new(),
                         },
                     ),
                     (
@@ -1001,6 +1075,7 @@ mod tests {
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
                             host_capabilities: vec![],
+                            allowed_http_endpoints: BTreeSet::new(),
                         },
                     ),
                     (
@@ -1011,6 +1086,7 @@ mod tests {
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
                             host_capabilities: vec![],
+                            allowed_http_endpoints: BTreeSet::new(),
                         },
                     ),
                 ]
@@ -1160,6 +1236,117 @@ mod tests {
         );
     }
 
+    #[test]
+    fn gatekeeper_mutation_policy_without_module() {
+        let engine = wasmtime::Engine::default();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+        let precompiled_policies: PrecompiledPolicies = HashMap::from([(
+            gatekeeper_mutation::BUILTIN_MODULE.to_owned(),
+            Ok(PrecompiledPolicy::gatekeeper_mutation()),
+        )]);
+        let policies: HashMap<String, PolicyOrPolicyGroup> = serde_yaml::from_str(&format!(
+            r#"
+owner-label:
+  module: {}
+  allowedToMutate: true
+  settings:
+    mutators:
+      - kind: AssignMetadata
+        metadata:
+          name: owner
+        spec:
+          location: metadata.labels.owner
+          parameters:
+            assign:
+              value: admin
+"#,
+            gatekeeper_mutation::BUILTIN_MODULE
+        ))
+        .unwrap();
+
+        let evaluation_environment =
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .build_evaluation_environment(&policies)
+                .unwrap();
+
+        let policy_id = PolicyID::Policy("owner-label".to_string());
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request)
+            .unwrap();
+        assert!(response.allowed);
+        assert!(response.patch.is_some());
+    }
+
+    struct CmdbProvider;
+
+    impl HostCapabilityProvider for CmdbProvider {
+        fn namespace(&self) -> String {
+            "cmdb".to_owned()
+        }
+
+        fn operations(&self) -> BTreeSet<String> {
+            BTreeSet::from(["v1/team_owner".to_owned()])
+        }
+
+        fn call(
+            &self,
+            _operation: &str,
+            _payload: serde_json::Value,
+        ) -> BoxFuture<'static, anyhow::Result<serde_json::Value>> {
+            Box::pin(async { Ok(serde_json::json!({"owner": "team-a"})) })
+        }
+    }
+
+    #[rstest]
+    #[case::provider_registered(true)]
+    #[case::provider_not_registered(false)]
+    #[tokio::test]
+    async fn host_capabilities_of_registered_providers(#[case] provider_registered: bool) {
+        let engine = wasmtime::Engine::default();
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut callback_handler_builder = CallbackHandlerBuilder::new(shutdown_rx);
+        if provider_registered {
+            callback_handler_builder =
+                callback_handler_builder.host_capability_provider(Arc::new(CmdbProvider));
+        }
+        let callback_handler = callback_handler_builder.build().await.unwrap();
+
+        let precompiled_policies: PrecompiledPolicies = HashMap::from([(
+            gatekeeper_mutation::BUILTIN_MODULE.to_owned(),
+            Ok(PrecompiledPolicy::gatekeeper_mutation()),
+        )]);
+        let policies: HashMap<String, PolicyOrPolicyGroup> = serde_yaml::from_str(&format!(
+            r#"
+owner-label:
+  module: {}
+  hostCapabilities:
+    - cmdb/v1/team_owner
+  settings:
+    mutators: []
+"#,
+            gatekeeper_mutation::BUILTIN_MODULE
+        ))
+        .unwrap();
+
+        let result = EvaluationEnvironmentBuilder::new(
+            &engine,
+            &precompiled_policies,
+            callback_handler.sender_channel(),
+        )
+        .with_host_capability_providers(callback_handler.host_capability_providers())
+        .build_evaluation_environment(&policies);
+
+        match result {
+            Ok(_) => assert!(provider_registered),
+            Err(e) => {
+                assert!(!provider_registered);
+                assert!(e.to_string().contains("invalid hostCapabilities pattern"));
+            }
+        }
+    }
+
     #[test]
     fn validate_policy_with_initialization_error() {
         let mut evaluation_environment = build_evaluation_environment();
@@ -1,7 +1,8 @@
 use anyhow::{Result, anyhow};
 use lazy_static::lazy_static;
 use policy_evaluator::{
-    ProtocolVersion, policy_evaluator::PolicyExecutionMode, policy_metadata::Metadata, wasmtime,
+    ProtocolVersion, gatekeeper_mutation, policy_evaluator::PolicyExecutionMode,
+    policy_metadata::Metadata, wasmtime,
 };
 use semver::{BuildMetadata, Prerelease, Version};
 use sha2::{Digest, Sha256};
@@ -37,6 +38,9 @@ pub(crate) struct PrecompiledPolicy {
     /// The execution mode of the policy
     pub execution_mode: PolicyExecutionMode,
 
+    /// Whether a Rego policy gets the inventory of its context aware resources
+    pub kubernetes_inventory: bool,
+
     /// sha256 digest of the precompiled module
     pub digest: String,
 }
@@ -48,6 +52,7 @@ impl PrecompiledPolicy {
         let policy_metadata = Metadata::from_contents(&policy_contents)?;
         let metadata = policy_metadata.unwrap_or_default();
         let execution_mode = metadata.execution_mode;
+        let kubernetes_inventory = metadata.kubernetes_inventory();
         has_minimum_kubewarden_version(&metadata)?;
 
         has_valid_protocol_version(&metadata)?;
@@ -61,9 +66,21 @@ impl PrecompiledPolicy {
         Ok(Self {
             precompiled_module,
             execution_mode,
+            kubernetes_inventory,
             digest: hex::encode(digest),
         })
     }
+
+    /// The policy of [`gatekeeper_mutation::BUILTIN_MODULE`]. The mutators are
+    /// applied natively: there's no module, the URI is used as digest
+    pub fn gatekeeper_mutation() -> Self {
+        Self {
+            precompiled_module: Vec::new(),
+            execution_mode: PolicyExecutionMode::GatekeeperMutation,
+            kubernetes_inventory: false,
+            digest: gatekeeper_mutation::BUILTIN_MODULE.to_owned(),
+        }
+    }
 }
 
 /// A dictionary with:
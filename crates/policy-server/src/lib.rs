@@ -25,7 +25,7 @@ use certs::create_tls_config_and_watch_certificate_changes;
 use evaluation::EvaluationEnvironmentBuilder;
 use policy_evaluator::{
     callback_handler::{CallbackHandler, CallbackHandlerBuilder},
-    kube,
+    gatekeeper_mutation, kube,
     policy_fetcher::sigstore::trust::sigstore::SigstoreTrustRoot,
     wasmtime,
 };
@@ -135,7 +135,9 @@ impl PolicyServer {
             None
         };
         let mut downloader =
-            Downloader::new(config.sources.clone(), downloader_sigstore_trust_root).await?;
+            Downloader::new(config.sources.clone(), downloader_sigstore_trust_root)
+                .await?
+                .with_fetch_mode(config.policies_fetch_mode);
 
         let fetched_policies = downloader
             .download_policies(
@@ -178,7 +180,8 @@ impl PolicyServer {
             &precompiled_policies,
             callback_sender_channel.clone(),
         )
-        .with_continue_on_errors(config.continue_on_errors);
+        .with_continue_on_errors(config.continue_on_errors)
+        .with_host_capability_providers(callback_handler.host_capability_providers());
         if let Some(namespace) = config.always_accept_admission_reviews_on_namespace {
             evaluation_environment_builder = evaluation_environment_builder
                 .with_always_accept_admission_reviews_on_namespace(namespace);
@@ -323,7 +326,7 @@ fn precompile_policies(
         "instantiating wasmtime::Module objects"
     );
 
-    fetched_policies
+    let mut precompiled_policies: PrecompiledPolicies = fetched_policies
         .par_iter()
         .map(|(policy_url, fetched_policy)| match fetched_policy {
             Ok(policy) => {
@@ -333,7 +336,15 @@ fn precompile_policies(
             }
             Err(error) => (policy_url.clone(), Err(anyhow!(error.to_string()))),
         })
-        .collect()
+        .collect();
+
+    // the Gatekeeper mutators are applied natively, there's no module to compile
+    precompiled_policies.insert(
+        gatekeeper_mutation::BUILTIN_MODULE.to_owned(),
+        Ok(PrecompiledPolicy::gatekeeper_mutation()),
+    );
+
+    precompiled_policies
 }
 
 async fn create_sigstore_trustroot(config: &Config) -> Result<Arc<SigstoreTrustRoot>> {
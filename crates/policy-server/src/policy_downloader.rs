@@ -6,11 +6,16 @@ use std::{
 
 use anyhow::{Result, anyhow};
 use policy_evaluator::{
-    policy_fetcher,
+    gatekeeper_mutation, policy_fetcher,
     policy_fetcher::{
-        sigstore,
+        FetchMode, sigstore,
         sources::Sources,
-        verify::{Verifier, config::LatestVerificationConfig},
+        store::Store,
+        verify::{
+            self, Verifier,
+            config::{LatestVerificationConfig, PolicyVerification},
+            errors::VerifyError,
+        },
     },
     policy_metadata::Metadata,
 };
@@ -28,6 +33,7 @@ pub(crate) type FetchedPolicies = HashMap<String, Result<PathBuf>>;
 pub(crate) struct Downloader {
     verifier: Option<Verifier>,
     sources: Option<Sources>,
+    fetch_mode: FetchMode,
 }
 
 impl Downloader {
@@ -47,7 +53,17 @@ impl Downloader {
             None
         };
 
-        Ok(Downloader { verifier, sources })
+        Ok(Downloader {
+            verifier,
+            sources,
+            fetch_mode: FetchMode::default(),
+        })
+    }
+
+    /// Define when the policies that have already been downloaded are pulled again
+    pub fn with_fetch_mode(mut self, fetch_mode: FetchMode) -> Self {
+        self.fetch_mode = fetch_mode;
+        self
     }
 
     /// Download all the policies to the given destination
@@ -69,10 +85,8 @@ impl Downloader {
             "policies download",
         );
 
-        let verification_config = verification_config.unwrap_or(&LatestVerificationConfig {
-            all_of: None,
-            any_of: None,
-        });
+        let default_verification_config = LatestVerificationConfig::default();
+        let verification_config = verification_config.unwrap_or(&default_verification_config);
 
         // The same WebAssembly module can be referenced by multiple policies,
         // there's no need to keep downloading and verifying it
@@ -99,12 +113,71 @@ impl Downloader {
 
             let mut verified_manifest_digest: Option<String> = None;
 
-            if let Some(ver) = self.verifier.as_mut() {
+            // the signatures required by the policy, if it has to be verified
+            let signature_requirements = match self
+                .verifier
+                .as_ref()
+                .map(|_| verification_config.policy_verification(policy_url))
+            {
+                None => None,
+                Some(Ok(PolicyVerification::Signatures(signature_requirements))) => {
+                    Some(signature_requirements)
+                }
+                Some(Ok(PolicyVerification::Unsigned)) => {
+                    info!(
+                        policy = name.as_str(),
+                        "policy allowed to be unsigned, skipping verification"
+                    );
+                    None
+                }
+                Some(Err(e)) => {
+                    error!(policy = name.as_str(), error =?e, "policy cannot be verified");
+                    fetched_policies.insert(
+                        policy_url.to_owned(),
+                        Err(anyhow!("Policy '{}' cannot be verified: {}", name, e)),
+                    );
+
+                    continue;
+                }
+            };
+
+            // local policies with a Sigstore bundle next to them are verified
+            // offline, once they have been copied. The same happens to the
+            // policies served over HTTP(S), using the signature published next
+            // to them
+            let bundle =
+                signature_requirements.and_then(|_| verify::bundle::find_bundle(policy_url));
+            let served_over_http =
+                policy_url.starts_with("http://") || policy_url.starts_with("https://");
+            // the policies loaded from OCI image layouts are verified using the
+            // signatures saved inside of the store, without contacting the registry
+            let store = Store::new(destination.as_ref());
+            let stored_signatures = signature_requirements.is_some()
+                && verify::has_stored_signatures(&store, policy_url);
+
+            if let Some(ver) = self.verifier.as_mut()
+                && let Some(signature_requirements) = signature_requirements
+                && bundle.is_none()
+                && !served_over_http
+            {
                 info!(
                     policy = name.as_str(),
                     "verifying policy authenticity and integrity using sigstore"
                 );
-                verified_manifest_digest = match ver.verify(policy_url, verification_config).await {
+                let verification = if stored_signatures {
+                    ver.verify_stored(&store, policy_url, signature_requirements)
+                        .and_then(|report| {
+                            report.map(|report| report.manifest_digest).ok_or_else(|| {
+                                VerifyError::ImageVerificationError(
+                                    "the signatures of the policy are not inside of the store"
+                                        .to_string(),
+                                )
+                            })
+                        })
+                } else {
+                    ver.verify(policy_url, signature_requirements).await
+                };
+                verified_manifest_digest = match verification {
                     Ok(d) => Some(d),
                     Err(e) => {
                         error!(policy = name.as_str(), error =?e, "policy cannot be verified");
@@ -127,10 +200,11 @@ impl Downloader {
                 );
             }
 
-            let fetched_policy = match policy_fetcher::fetch_policy(
+            let fetched_policy = match policy_fetcher::fetch_policy_with_mode(
                 policy_url,
                 policy_fetcher::PullDestination::Store(destination.as_ref().to_path_buf()),
                 self.sources.as_ref(),
+                self.fetch_mode,
             )
             .await
             {
@@ -155,14 +229,55 @@ impl Downloader {
                 }
             };
 
-            if let Some(ver) = self.verifier.as_mut() {
-                if let Err(e) = ver
-                    .verify_local_file_checksum(
-                        &fetched_policy,
-                        verified_manifest_digest.as_ref().unwrap(),
-                    )
-                    .await
-                {
+            if let Some(ver) = self.verifier.as_mut()
+                && let Some(signature_requirements) = signature_requirements
+            {
+                let bundle =
+                    bundle.or_else(|| verify::bundle::find_stored_bundle(&store, policy_url));
+                if served_over_http && bundle.is_none() {
+                    error!(
+                        policy = name.as_str(),
+                        "no signature published next to the policy"
+                    );
+                    fetched_policies.insert(
+                        policy_url.to_owned(),
+                        Err(anyhow!(
+                            "Policy '{}' cannot be verified: no signature published next to it",
+                            name
+                        )),
+                    );
+                    continue;
+                }
+                let (verification, status) = match bundle.as_ref() {
+                    Some(bundle) => {
+                        info!(
+                            policy = name.as_str(),
+                            bundle = bundle.to_str(),
+                            "verifying policy authenticity and integrity using a sigstore bundle"
+                        );
+                        (
+                            ver.verify_bundle(&fetched_policy, bundle, signature_requirements),
+                            "verified-bundle",
+                        )
+                    }
+                    None if stored_signatures => (
+                        verify::verify_stored_file_checksum(
+                            &store,
+                            &fetched_policy,
+                            verified_manifest_digest.as_ref().unwrap(),
+                        ),
+                        "verified-local-checksum",
+                    ),
+                    None => (
+                        ver.verify_local_file_checksum(
+                            &fetched_policy,
+                            verified_manifest_digest.as_ref().unwrap(),
+                        )
+                        .await,
+                        "verified-local-checksum",
+                    ),
+                };
+                if let Err(e) = verification {
                     error!(
                         policy = name.as_str(),
                         error =? e,
@@ -182,7 +297,7 @@ impl Downloader {
                         .as_ref()
                         .unwrap_or(&"unknown".to_string())
                         .as_str(),
-                    status = "verified-local-checksum",
+                    status,
                     "policy download",
                 );
             }
@@ -253,6 +368,9 @@ fn policies_to_download(
         }
     }
 
+    // the Gatekeeper mutators are applied natively, there's nothing to download
+    flattened_policies.retain(|_, url| url != gatekeeper_mutation::BUILTIN_MODULE);
+
     flattened_policies
 }
 
@@ -261,6 +379,25 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[test]
+    fn builtin_modules_are_not_downloaded() {
+        let policies: HashMap<String, PolicyOrPolicyGroup> = serde_yaml::from_str(&format!(
+            "mutators:\n  module: {}\nprivileged-pods:\n  module: registry://ghcr.io/kubewarden/policies/pod-privileged:v0.2.5\n",
+            gatekeeper_mutation::BUILTIN_MODULE
+        ))
+        .expect("Cannot parse policy cfg");
+
+        let policies = policies_to_download(&policies);
+
+        assert_eq!(
+            policies,
+            HashMap::from([(
+                "privileged-pods".to_string(),
+                "registry://ghcr.io/kubewarden/policies/pod-privileged:v0.2.5".to_string()
+            )])
+        );
+    }
+
     #[tokio::test]
     async fn verify_success() {
         let verification_cfg_yml = r#"---
@@ -286,7 +423,7 @@ mod tests {
           env: prod
         "#;
         let verification_config =
-            serde_yaml::from_str::<LatestVerificationConfig>(verification_cfg_yml)
+            verify::config::build_latest_verification_config(verification_cfg_yml)
                 .expect("Cannot convert verification config");
 
         let policies_cfg = r#"
@@ -332,7 +469,7 @@ mod tests {
         owner: kubewarden
        "#;
         let verification_config =
-            serde_yaml::from_str::<LatestVerificationConfig>(verification_cfg_yml)
+            verify::config::build_latest_verification_config(verification_cfg_yml)
                 .expect("Cannot convert verification config");
 
         let policies_cfg = r#"
@@ -372,4 +509,105 @@ mod tests {
             Err(error) if error.to_string().contains("Policy 'pod-privileged' cannot be verified: Image verification failed: missing signatures")
         ));
     }
+
+    #[tokio::test]
+    async fn verify_bundle_error() {
+        let verification_cfg_yml = r#"---
+    allOf:
+      - kind: pubKey
+        owner: pubkey1.pub
+        key: |
+              -----BEGIN PUBLIC KEY-----
+              MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEQiTy5S+2JFvVlhUwWPLziM7iTM2j
+              byLgh2IjpNQN0Uio/9pZOTP/CsJmXoUNshfpTUHd3OxgHgz/6adtf2nBwQ==
+              -----END PUBLIC KEY-----
+       "#;
+        let verification_config =
+            verify::config::build_latest_verification_config(verification_cfg_yml)
+                .expect("Cannot convert verification config");
+
+        // a local policy, with a bundle that doesn't hold a valid signature
+        let policy_dir = TempDir::new().expect("Cannot create temp dir");
+        let module_path = policy_dir.path().join("policy.wasm");
+        std::fs::copy(
+            "tests/data/gatekeeper_always_happy_policy.wasm",
+            &module_path,
+        )
+        .expect("Cannot copy policy");
+        std::fs::write(
+            policy_dir.path().join("policy.wasm.sigstore.json"),
+            r#"{"base64Signature": "MEUCIGqWScz7s9aP2sGXNFKeqivw3B6kPRs56AITIHnvd5igAiEA1kzbaV2Y5yPE81EN92NUFOl31LLJSvwsjFQ07m2XqaA="}"#,
+        )
+        .expect("Cannot write bundle");
+        let policy_url = format!("file://{}", module_path.display());
+
+        let policies: HashMap<String, PolicyOrPolicyGroup> =
+            serde_yaml::from_str(&format!("local-policy:\n  module: {policy_url}\n"))
+                .expect("Cannot parse policy cfg");
+
+        let policy_download_dir = TempDir::new().expect("Cannot create temp dir");
+        let trust_root = sigstore::trust::sigstore::SigstoreTrustRoot::new(None)
+            .await
+            .unwrap();
+
+        let mut downloader = Downloader::new(None, Some(Arc::new(trust_root)))
+            .await
+            .unwrap();
+
+        let fetched_policies = downloader
+            .download_policies(
+                &policies,
+                policy_download_dir.path().to_str().unwrap(),
+                Some(&verification_config),
+            )
+            .await;
+
+        assert!(matches!(
+            fetched_policies.get(&policy_url).unwrap(),
+            Err(error) if error.to_string().contains("Verification of policy local-policy failed: Image verification failed: missing signatures")
+        ));
+    }
+
+    #[tokio::test]
+    async fn verify_skipped_for_unsigned_policies() {
+        let module_path = std::fs::canonicalize("tests/data/gatekeeper_always_happy_policy.wasm")
+            .expect("Cannot find policy");
+        let policy_url = format!("file://{}", module_path.display());
+
+        let verification_cfg_yml = r#"---
+    apiVersion: v2
+    default:
+      allOf:
+        - kind: githubAction
+          owner: kubewarden
+    allowUnsigned:
+      - "file://**/gatekeeper_always_happy_policy.wasm"
+       "#;
+        let verification_config =
+            verify::config::build_latest_verification_config(verification_cfg_yml)
+                .expect("Cannot convert verification config");
+
+        let policies: HashMap<String, PolicyOrPolicyGroup> =
+            serde_yaml::from_str(&format!("local-policy:\n  module: {policy_url}\n"))
+                .expect("Cannot parse policy cfg");
+
+        let policy_download_dir = TempDir::new().expect("Cannot create temp dir");
+        let trust_root = sigstore::trust::sigstore::SigstoreTrustRoot::new(None)
+            .await
+            .unwrap();
+
+        let mut downloader = Downloader::new(None, Some(Arc::new(trust_root)))
+            .await
+            .unwrap();
+
+        let fetched_policies = downloader
+            .download_policies(
+                &policies,
+                policy_download_dir.path().to_str().unwrap(),
+                Some(&verification_config),
+            )
+            .await;
+
+        assert!(fetched_policies.get(&policy_url).unwrap().is_ok());
+    }
 }
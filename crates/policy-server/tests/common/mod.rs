@@ -7,6 +7,7 @@ use std::{
 use axum::Router;
 use policy_evaluator::admission_response_handler::policy_mode::PolicyMode;
 use policy_evaluator::policy_evaluator::PolicySettings;
+use policy_evaluator::policy_fetcher::FetchMode;
 use policy_evaluator::policy_metadata::ContextAwareResource;
 use policy_server::{
     PolicyServer,
@@ -37,6 +38,7 @@ pub(crate) fn default_test_config() -> Config {
                 message: None,
                 timeout_eval_seconds: None,
                 host_capabilities: vec![],
+                allowed_http_endpoints: BTreeSet::new(),
             },
         ),
         (
@@ -56,6 +58,7 @@ pub(crate) fn default_test_config() -> Config {
                 message: None,
                 timeout_eval_seconds: None,
                 host_capabilities: vec![],
+                allowed_http_endpoints: BTreeSet::new(),
             },
         ),
         (
@@ -66,6 +69,7 @@ pub(crate) fn default_test_config() -> Config {
                 allowed_to_mutate: None,
                 timeout_eval_seconds: None,
                 host_capabilities: vec![],
+                allowed_http_endpoints: BTreeSet::new(),
                 settings: Some(
                     PolicySettings::try_from(&json!({
                         "sleepMilliseconds": 2
@@ -90,6 +94,7 @@ pub(crate) fn default_test_config() -> Config {
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
                         host_capabilities: vec![],
+                        allowed_http_endpoints: BTreeSet::new(),
                     },
                 )]),
             },
@@ -114,6 +119,7 @@ pub(crate) fn default_test_config() -> Config {
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
                         host_capabilities: vec![],
+                        allowed_http_endpoints: BTreeSet::new(),
                     },
                 )]),
             },
@@ -126,6 +132,7 @@ pub(crate) fn default_test_config() -> Config {
                 allowed_to_mutate: None,
                 timeout_eval_seconds: Some(1),
                 host_capabilities: vec![],
+                allowed_http_endpoints: BTreeSet::new(),
                 settings: Some(
                     PolicySettings::try_from(&json!({
                         "sleepMilliseconds": 2
@@ -155,6 +162,7 @@ pub(crate) fn pod_privileged_test_config() -> Config {
             message: None,
             timeout_eval_seconds: None,
             host_capabilities: vec![],
+            allowed_http_endpoints: BTreeSet::new(),
         },
     )]);
 
@@ -182,6 +190,7 @@ pub(crate) fn context_aware_policy_test_config(
             message: None,
             timeout_eval_seconds: None,
             host_capabilities,
+            allowed_http_endpoints: BTreeSet::new(),
         },
     )]);
 
@@ -212,6 +221,7 @@ pub(crate) fn context_aware_policy_group_test_config(
                     context_aware_resources,
                     timeout_eval_seconds: None,
                     host_capabilities,
+                    allowed_http_endpoints: BTreeSet::new(),
                 },
             )]),
         },
@@ -229,6 +239,7 @@ fn default_config() -> Config {
         sources: None,
         policies: HashMap::new(),
         policies_download_dir: tempdir().unwrap().keep(),
+        policies_fetch_mode: FetchMode::default(),
         ignore_kubernetes_connection_failure: true,
         always_accept_admission_reviews_on_namespace: None,
         policy_evaluation_limit_seconds: Some(2),
@@ -16,7 +16,9 @@ use policy_evaluator::{
     admission_response::{self, AdmissionResponseStatus, StatusCause, StatusDetails},
     admission_response_handler::policy_mode::PolicyMode,
     policy_evaluator::PolicySettings,
-    policy_fetcher::{proxy::ProxyConfig, sources::Sources, verify::config::VerificationConfigV1},
+    policy_fetcher::{
+        proxy::ProxyConfig, sources::Sources, verify::config::build_latest_verification_config,
+    },
 };
 use policy_server::{api::admission_review::AdmissionReviewResponse, config::PolicyOrPolicyGroup};
 use regex::Regex;
@@ -84,6 +86,7 @@ async fn test_validate_custom_rejection_message() {
             message: Some("Custom error message".to_owned()),
             timeout_eval_seconds: None,
             host_capabilities: vec![],
+            allowed_http_endpoints: BTreeSet::new(),
         },
     );
     let app = app(config).await;
@@ -764,7 +767,7 @@ async fn test_verified_policy() {
         annotations:
           env: prod
         "#;
-    let verification_config = serde_yaml::from_str::<VerificationConfigV1>(verification_cfg_yml)
+    let verification_config = build_latest_verification_config(verification_cfg_yml)
         .expect("Cannot parse verification config");
 
     let mut config = default_test_config();
@@ -779,6 +782,7 @@ async fn test_verified_policy() {
             message: None,
             timeout_eval_seconds: None,
             host_capabilities: vec![],
+            allowed_http_endpoints: BTreeSet::new(),
         },
     )]);
     config.verification_config = Some(verification_config);
@@ -819,6 +823,7 @@ async fn test_policy_with_invalid_settings() {
             message: None,
             timeout_eval_seconds: None,
             host_capabilities: vec![],
+            allowed_http_endpoints: BTreeSet::new(),
         },
     );
     config.continue_on_errors = true;
@@ -867,6 +872,7 @@ async fn test_policy_with_wrong_url() {
             message: None,
             timeout_eval_seconds: None,
             host_capabilities: vec![],
+            allowed_http_endpoints: BTreeSet::new(),
         },
     );
     config.continue_on_errors = true;
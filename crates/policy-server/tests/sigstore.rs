@@ -16,7 +16,9 @@ mod sigstore_tests {
 
     use policy_evaluator::{
         admission_response_handler::policy_mode::PolicyMode,
-        policy_fetcher::{sources::read_sources_file, verify::config::VerificationConfigV1},
+        policy_fetcher::{
+            sources::read_sources_file, verify::config::build_latest_verification_config,
+        },
     };
     use policy_server::config::PolicyOrPolicyGroup;
 
@@ -75,9 +77,8 @@ mod sigstore_tests {
         // Load verification config
         let verification_config_content = fs::read_to_string(&verification_config_path)
             .expect("cannot read verification_config.yaml");
-        let verification_config: VerificationConfigV1 =
-            serde_yaml::from_str(&verification_config_content)
-                .expect("cannot parse verification_config.yaml");
+        let verification_config = build_latest_verification_config(&verification_config_content)
+            .expect("cannot parse verification_config.yaml");
 
         // Load sources config to allow the policy server to handle insecure registry. The test
         // policy is stored in a local registry that does not support https.
@@ -104,6 +105,7 @@ mod sigstore_tests {
                 message: None,
                 timeout_eval_seconds: None,
                 host_capabilities: vec![],
+                allowed_http_endpoints: BTreeSet::new(),
             },
         )]);
 